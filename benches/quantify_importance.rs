@@ -1,6 +1,5 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use fanova::Fanova;
-use rand;
 
 fn k1(c: &mut Criterion) {
     let mut feature1 = Vec::new();
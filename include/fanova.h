@@ -0,0 +1,68 @@
+/* C interface of the fanova crate (built with `--features ffi`). */
+#ifndef FANOVA_H
+#define FANOVA_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Status codes returned by the functions below. */
+#define FANOVA_OK 0
+#define FANOVA_ERROR_NULL_POINTER 1
+#define FANOVA_ERROR_INVALID_STATE 2
+#define FANOVA_ERROR_LENGTH_MISMATCH 3
+#define FANOVA_ERROR_EMPTY_ROWS 4
+#define FANOVA_ERROR_NON_FINITE_TARGET 5
+#define FANOVA_ERROR_INVALID_ARGUMENT 6
+
+/*
+ * fANOVA evaluator which owns the copies of the given columns.
+ *
+ * The model is fitted lazily and refitted after the columns or the seed are changed.
+ */
+typedef struct FanovaEvaluator FanovaEvaluator;
+
+/* Creates an evaluator (must be released by `fanova_evaluator_free`). */
+FanovaEvaluator *fanova_evaluator_new(void);
+
+/* Releases an evaluator (`NULL` is ignored). */
+void fanova_evaluator_free(FanovaEvaluator *evaluator);
+
+/* Sets the seed of the random forest (otherwise a random seed is used). */
+int32_t fanova_evaluator_set_seed(FanovaEvaluator *evaluator, uint64_t seed);
+
+/* Appends a feature column (`values[0..len]` is copied). */
+int32_t fanova_evaluator_push_feature(FanovaEvaluator *evaluator, const double *values, size_t len);
+
+/* Sets the target column (`values[0..len]` is copied). */
+int32_t fanova_evaluator_set_target(FanovaEvaluator *evaluator, const double *values, size_t len);
+
+/*
+ * Fits a model and writes the importance of each feature to `means[0..len]` and
+ * `stddevs[0..len]`, where `len` must be the number of the feature columns.
+ */
+int32_t fanova_evaluator_importances(FanovaEvaluator *evaluator, double *means, double *stddevs,
+                                     size_t len);
+
+/*
+ * Writes the importance of the feature set `features[0..len]` to `mean` and `stddev`
+ * (out of range features yield zero importance).
+ */
+int32_t fanova_evaluator_quantify_importance(FanovaEvaluator *evaluator, const size_t *features,
+                                             size_t len, double *mean, double *stddev);
+
+/*
+ * Writes the marginal predictions of `feature` at `n_points` (two or more) equally spaced
+ * points to `grid`, `means` and `stddevs` (across the trees).
+ */
+int32_t fanova_evaluator_marginal(FanovaEvaluator *evaluator, size_t feature, double *grid,
+                                  double *means, double *stddevs, size_t n_points);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* FANOVA_H */
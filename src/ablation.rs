@@ -0,0 +1,83 @@
+//! Ablation analysis between two configurations (e.g., the default and the incumbent).
+//!
+//! Starting from the source configuration, the values of the features are switched to the
+//! ones of the target configuration one at a time. At each step, the feature whose switch
+//! improves the function the most is chosen greedily, so the improvement from the source to the
+//! target is attributed to the switches in the order of their contributions.
+
+/// A switch of an ablation path (see `Fanova::ablation_path`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AblationStep {
+    /// Index of the switched feature.
+    pub feature: usize,
+
+    /// Prediction after the switch.
+    pub prediction: f64,
+
+    /// Improvement of the prediction by the switch (negative if the switch makes it worse).
+    pub improvement: f64,
+}
+
+/// Walks from `source` to `target` greedily minimizing `loss`.
+///
+/// The features having the same values in both configurations are not switched.
+pub fn ablation_path<F>(source: &[f64], target: &[f64], loss: F) -> Vec<AblationStep>
+where
+    F: Fn(&[f64]) -> f64,
+{
+    debug_assert_eq!(source.len(), target.len());
+    let mut point = source.to_vec();
+    let mut current = loss(&point);
+    let mut remaining = (0..source.len())
+        .filter(|&i| source[i] != target[i])
+        .collect::<Vec<_>>();
+
+    let mut steps = Vec::with_capacity(remaining.len());
+    while !remaining.is_empty() {
+        // Ties are broken by the order of the features.
+        let (k, best) = remaining
+            .iter()
+            .enumerate()
+            .map(|(k, &i)| {
+                let x = std::mem::replace(&mut point[i], target[i]);
+                let value = loss(&point);
+                point[i] = x;
+                (k, value)
+            })
+            .fold((0, f64::INFINITY), |best, (k, value)| {
+                if value < best.1 {
+                    (k, value)
+                } else {
+                    best
+                }
+            });
+        let feature = remaining.remove(k);
+        point[feature] = target[feature];
+        steps.push(AblationStep {
+            feature,
+            prediction: best,
+            improvement: current - best,
+        });
+        current = best;
+    }
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ablation_path_works() {
+        // `x1` matters the most, and `x0` only matters once `x1` is switched.
+        let loss = |x: &[f64]| 10.0 - 6.0 * x[1] - 3.0 * x[0] * x[1] - x[3];
+        let steps = ablation_path(&[0.0, 0.0, 5.0, 0.0], &[1.0, 1.0, 5.0, 1.0], loss);
+        let features = steps.iter().map(|s| s.feature).collect::<Vec<_>>();
+        assert_eq!(features, [1, 0, 3]);
+        let improvements = steps.iter().map(|s| s.improvement).collect::<Vec<_>>();
+        assert_eq!(improvements, [6.0, 3.0, 1.0]);
+        assert_eq!(steps[2].prediction, 0.0);
+
+        assert!(ablation_path(&[1.0], &[1.0], |_| 0.0).is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+//! A command line driver of fANOVA.
+//!
+//! This reads a JSON request like `{"features": [[...], ...], "target": [...], "options": {...}}`
+//! from stdin and writes `{"importances": [{"mean": ..., "stddev": ...}, ...]}` to stdout.
+//! As it does not depend on the JavaScript glue, it can run on WASI runtimes (e.g., wasmtime).
+use fanova::{FanovaError, FanovaOptions, RandomForestOptions, SplitSampling, Table};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::io::Read;
+use std::num::NonZeroUsize;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Request {
+    features: Vec<Vec<f64>>,
+    target: Vec<f64>,
+    #[serde(default)]
+    options: Options,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Options {
+    trees: Option<NonZeroUsize>,
+    max_features: Option<NonZeroUsize>,
+    max_depth: Option<NonZeroUsize>,
+    min_samples_leaf: Option<NonZeroUsize>,
+    max_bins: Option<NonZeroUsize>,
+    max_thresholds_per_feature: Option<NonZeroUsize>,
+    split_sample: Option<NonZeroUsize>,
+    seed: Option<u64>,
+    strict: bool,
+}
+
+impl Options {
+    fn random_forest(&self) -> RandomForestOptions {
+        let mut options = RandomForestOptions::new();
+        if let Some(n) = self.trees {
+            options = options.trees(n);
+        }
+        if let Some(n) = self.max_features {
+            options = options.max_features(n);
+        }
+        if let Some(n) = self.max_depth {
+            options = options.max_depth(n);
+        }
+        if let Some(n) = self.min_samples_leaf {
+            options = options.min_samples_leaf(n);
+        }
+        if let Some(n) = self.max_bins {
+            options = options.max_bins(n);
+        }
+        if let Some(n) = self.max_thresholds_per_feature {
+            options = options.max_thresholds_per_feature(n);
+        }
+        if let Some(n) = self.split_sample {
+            options = options.split_sampling(SplitSampling::Uniform(n));
+        }
+        if let Some(seed) = self.seed {
+            options = options.seed(seed);
+        }
+        options
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Response {
+    importances: Vec<Importance>,
+}
+
+#[derive(Debug, Serialize)]
+struct Importance {
+    mean: f64,
+    stddev: f64,
+}
+
+fn run(request: Request) -> Result<Response, FanovaError> {
+    let mut columns = request
+        .features
+        .iter()
+        .map(|f| f.as_slice())
+        .collect::<Vec<_>>();
+    columns.push(&request.target);
+    let table = Table::new(columns)?;
+
+    let mut options = FanovaOptions::new().random_forest(request.options.random_forest());
+    if request.options.strict {
+        options = options.strict();
+    }
+    let mut fanova = options.fit_table(table)?;
+    let importances = (0..request.features.len())
+        .map(|i| {
+            let importance = fanova.quantify_importance(&[i]);
+            Importance {
+                mean: importance.mean,
+                stddev: importance.stddev,
+            }
+        })
+        .collect();
+    Ok(Response { importances })
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input)?;
+    let request = serde_json::from_str(&input)
+        .map_err(|e| FanovaError::Parse(format!("invalid request: {e}")))?;
+    let response = run(request)?;
+    println!("{}", serde_json::to_string(&response)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_works() -> Result<(), Box<dyn Error>> {
+        let request = serde_json::from_str(
+            r#"{"features": [[0, 1, 2, 3], [1, 0, 1, 0]], "target": [0, 1, 4, 9],
+                "options": {"trees": 10, "seed": 0}}"#,
+        )?;
+        let response = run(request)?;
+        assert_eq!(response.importances.len(), 2);
+
+        let request = serde_json::from_str(r#"{"features": [[0, 1]], "target": [0]}"#)?;
+        assert!(run(request).is_err());
+
+        let request = serde_json::from_str(
+            r#"{"features": [[0, 0, 1]], "target": [0, 1, 2], "options": {"strict": true}}"#,
+        )?;
+        assert!(run(request).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+//! fANOVA of categorical targets (e.g., whether trials converged, diverged or ran out of memory).
+//!
+//! The probability of each class is estimated by a forest fitted to the indicator of the class,
+//! and the importance of features is the fraction of the total variance of the class
+//! probabilities (i.e., summed over the classes) explained by them. Classes which rarely occur
+//! or hardly depend on the features have small variances and contribute little.
+use crate::fanova::{Fanova, Importance};
+use crate::functions;
+
+/// fANOVA models of a categorical target (see `FanovaOptions::fit_categorical`).
+#[derive(Debug)]
+pub struct CategoricalFanova {
+    // The models of the probabilities of the classes in ascending order of the labels.
+    models: Vec<(usize, Fanova)>,
+}
+
+impl CategoricalFanova {
+    pub(crate) fn new(models: Vec<(usize, Fanova)>) -> Self {
+        Self { models }
+    }
+
+    /// Returns the labels of the classes in ascending order.
+    pub fn classes(&self) -> impl '_ + Iterator<Item = usize> {
+        self.models.iter().map(|(label, _)| *label)
+    }
+
+    /// Returns the model of the probability of `class` (or `None` if no rows have the class).
+    ///
+    /// Its importances tell which features drive the class (e.g., the failures) alone.
+    pub fn class_model(&mut self, class: usize) -> Option<&mut Fanova> {
+        self.models
+            .iter_mut()
+            .find(|(label, _)| *label == class)
+            .map(|(_, model)| model)
+    }
+
+    /// Calculates the importance of each feature (in the order of the feature columns).
+    pub fn importances(&mut self) -> Vec<Importance> {
+        let features_len = self.models[0].1.feature_space().ranges().len();
+        (0..features_len)
+            .map(|i| self.quantify_importance(&[i]))
+            .collect()
+    }
+
+    /// Calculates the importance of the given features.
+    ///
+    /// The fractions of the variances of the class probabilities explained by `features` are
+    /// averaged with the weights of the variances for each tree (the `i`-th trees of the
+    /// models are combined), and `Importance` is their mean and standard deviation across the
+    /// trees.
+    pub fn quantify_importance(&mut self, features: &[usize]) -> Importance {
+        let features_len = self.models[0].1.feature_space().ranges().len();
+        if features.iter().any(|&f| f >= features_len) {
+            return Importance {
+                mean: 0.0,
+                stddev: 0.0,
+            };
+        }
+
+        let per_class = self
+            .models
+            .iter_mut()
+            .map(|(_, model)| (model.tree_importances(features), model.tree_variances()))
+            .collect::<Vec<_>>();
+        let trees_len = per_class
+            .iter()
+            .map(|(importances, _)| importances.len())
+            .min()
+            .unwrap_or(0);
+        let importances = (0..trees_len).map(|t| {
+            let (explained, total) = per_class
+                .iter()
+                // Constant class probabilities have nothing to explain.
+                .filter(|(_, variances)| variances[t] > 0.0)
+                .fold(
+                    (0.0, 0.0),
+                    |(explained, total), (importances, variances)| {
+                        (
+                            explained + importances[t] * variances[t],
+                            total + variances[t],
+                        )
+                    },
+                );
+            if total > 0.0 {
+                explained / total
+            } else {
+                0.0
+            }
+        });
+        let (mean, stddev) = functions::mean_and_stddev(importances);
+        Importance { mean, stddev }
+    }
+
+    /// Returns the probability of each class (in the order of `classes`) at `point`.
+    ///
+    /// The predictions of the models are clamped into `[0, 1]` and normalized to sum up to
+    /// one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `point` differs from the number of the features.
+    pub fn predict_probabilities(&self, point: &[f64]) -> Vec<f64> {
+        let predictions = self
+            .models
+            .iter()
+            .map(|(_, model)| model.predict(point).clamp(0.0, 1.0))
+            .collect::<Vec<_>>();
+        let sum = predictions.iter().sum::<f64>();
+        if sum > 0.0 {
+            predictions.into_iter().map(|p| p / sum).collect()
+        } else {
+            vec![1.0 / predictions.len() as f64; predictions.len()]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FanovaOptions, FitError, RandomForestOptions};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::num::NonZeroUsize;
+
+    #[test]
+    fn fit_categorical_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+
+        // Diverges (`1`) if `feature1` is large and runs out of memory (`2`) if both are large.
+        let classes = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(&x, &y)| match (x > 0.6, y > 0.8) {
+                (false, _) => 0,
+                (true, false) => 1,
+                (true, true) => 2,
+            })
+            .collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(20).expect("non zero");
+        let options =
+            FanovaOptions::new().random_forest(RandomForestOptions::new().seed(0).trees(trees));
+        let mut fanova = options
+            .clone()
+            .fit_categorical(vec![&feature1, &feature2], &classes)?;
+        assert_eq!(fanova.classes().collect::<Vec<_>>(), vec![0, 1, 2]);
+
+        let importances = fanova.importances();
+        assert!(importances[0].mean > importances[1].mean, "{importances:?}");
+        assert!(importances.iter().all(|i| (0.0..=1.0).contains(&i.mean)));
+        let out_of_memory = fanova.class_model(2).expect("class").importances();
+        assert!(out_of_memory[1].mean > 0.1, "{out_of_memory:?}");
+        assert!(fanova.class_model(3).is_none());
+
+        let probabilities = fanova.predict_probabilities(&[0.9, 0.9]);
+        assert!((probabilities.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!(probabilities[2] > 0.5, "{probabilities:?}");
+        assert!(fanova.predict_probabilities(&[0.1, 0.5])[0] > 0.9);
+
+        assert!(matches!(
+            options
+                .clone()
+                .fit_categorical(vec![&feature1], &vec![1; 200]),
+            Err(FitError::SingleClass)
+        ));
+        assert!(matches!(
+            options.fit_categorical(vec![&feature1], &[0, 1]),
+            Err(FitError::RowSizeMismatch)
+        ));
+        Ok(())
+    }
+}
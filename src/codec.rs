@@ -0,0 +1,169 @@
+//! A minimal little-endian binary encoding used to save and restore fitted models.
+use miniz_oxide::deflate::compress_to_vec_zlib;
+use miniz_oxide::inflate::decompress_to_vec_zlib_with_limit;
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// The header of the bytes compressed by `compress`.
+const COMPRESSED_MAGIC: &[u8; 4] = b"FNVZ";
+
+// The compression level of `miniz_oxide` (from 0 to 10): higher levels are much slower while
+// the encoded models (mostly `f64`s) hardly get smaller.
+const COMPRESSION_LEVEL: u8 = 6;
+
+// Larger inputs are rejected by `decompress` (to avoid exhausting the wasm heap).
+const MAX_DECOMPRESSED_LEN: usize = 1 << 30;
+
+/// Compresses encoded bytes with zlib (deflate) and prepends `COMPRESSED_MAGIC`.
+pub fn compress(bytes: &[u8]) -> Vec<u8> {
+    let mut compressed = COMPRESSED_MAGIC.to_vec();
+    compressed.extend(compress_to_vec_zlib(bytes, COMPRESSION_LEVEL));
+    compressed
+}
+
+/// Decompresses the bytes returned by `compress` (the other bytes are returned as they are).
+///
+/// `None` is returned if the compressed data is corrupted.
+pub fn decompress(bytes: &[u8]) -> Option<Cow<'_, [u8]>> {
+    match bytes.strip_prefix(COMPRESSED_MAGIC) {
+        Some(compressed) => decompress_to_vec_zlib_with_limit(compressed, MAX_DECOMPRESSED_LEN)
+            .ok()
+            .map(Cow::Owned),
+        None => Some(Cow::Borrowed(bytes)),
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn u8(&mut self, x: u8) {
+        self.buf.push(x);
+    }
+
+    pub fn usize(&mut self, x: usize) {
+        self.u64(x as u64);
+    }
+
+    pub fn u64(&mut self, x: u64) {
+        self.buf.extend_from_slice(&x.to_le_bytes());
+    }
+
+    pub fn f64(&mut self, x: f64) {
+        self.buf.extend_from_slice(&x.to_le_bytes());
+    }
+
+    pub fn range(&mut self, x: &Range<f64>) {
+        self.f64(x.start);
+        self.f64(x.end);
+    }
+
+    pub fn bytes(&mut self, x: &[u8]) {
+        self.usize(x.len());
+        self.buf.extend_from_slice(x);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Decodes values encoded by `Encoder` (`None` is returned if the input is truncated).
+#[derive(Debug)]
+pub struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    pub fn usize(&mut self) -> Option<usize> {
+        usize::try_from(self.u64()?).ok()
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    pub fn f64(&mut self) -> Option<f64> {
+        Some(f64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    pub fn range(&mut self) -> Option<Range<f64>> {
+        Some(self.f64()?..self.f64()?)
+    }
+
+    pub fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = self.usize()?;
+        self.take(len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < n {
+            return None;
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Some(head)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codec_works() {
+        let mut encoder = Encoder::new();
+        encoder.u8(1);
+        encoder.usize(2);
+        encoder.u64(u64::MAX);
+        encoder.f64(3.5);
+        encoder.range(&(0.0..1.0));
+        encoder.bytes(b"foo");
+        let bytes = encoder.finish();
+
+        let mut decoder = Decoder::new(&bytes);
+        assert_eq!(decoder.u8(), Some(1));
+        assert_eq!(decoder.usize(), Some(2));
+        assert_eq!(decoder.u64(), Some(u64::MAX));
+        assert_eq!(decoder.f64(), Some(3.5));
+        assert_eq!(decoder.range(), Some(0.0..1.0));
+        assert_eq!(decoder.bytes(), Some(&b"foo"[..]));
+        assert!(decoder.is_empty());
+        assert_eq!(decoder.u8(), None);
+
+        let mut decoder = Decoder::new(&bytes[..5]);
+        decoder.u8();
+        assert_eq!(decoder.usize(), None);
+    }
+
+    #[test]
+    fn compression_works() {
+        let mut encoder = Encoder::new();
+        (0..1000).for_each(|i| encoder.f64(f64::from(i % 10)));
+        let bytes = encoder.finish();
+
+        let compressed = compress(&bytes);
+        assert!(compressed.len() < bytes.len() / 10);
+        assert_eq!(decompress(&compressed).as_deref(), Some(&bytes[..]));
+        assert!(matches!(decompress(&bytes), Some(Cow::Borrowed(_))));
+        assert_eq!(decompress(&compressed[..compressed.len() / 2]), None);
+    }
+}
@@ -0,0 +1,164 @@
+use std::borrow::Cow;
+
+#[derive(Debug, Clone)]
+pub enum Column<'a> {
+    Dense(Cow<'a, [f64]>),
+    DenseF32(Cow<'a, [f32]>),
+    Sparse(Cow<'a, SparseColumn>),
+
+    // A column of a row-major matrix (`data[offset + stride * row]`).
+    Strided {
+        data: &'a [f64],
+        offset: usize,
+        stride: usize,
+    },
+}
+
+impl<'a> Column<'a> {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Dense(c) => c.len(),
+            Self::DenseF32(c) => c.len(),
+            Self::Sparse(c) => c.len,
+            Self::Strided {
+                data,
+                offset,
+                stride,
+            } => (data.len() - offset).div_ceil(*stride),
+        }
+    }
+
+    pub fn get(&self, row: usize) -> f64 {
+        match self {
+            Self::Dense(c) => c[row],
+            Self::DenseF32(c) => f64::from(c[row]),
+            Self::Sparse(c) => c.get(row),
+            Self::Strided {
+                data,
+                offset,
+                stride,
+            } => data[offset + stride * row],
+        }
+    }
+
+    pub fn iter(&self) -> impl '_ + Iterator<Item = f64> {
+        (0..self.len()).map(move |i| self.get(i))
+    }
+
+    pub fn push(&mut self, value: f64) {
+        match self {
+            Self::Dense(c) => c.to_mut().push(value),
+            Self::DenseF32(c) => c.to_mut().push(value as f32),
+            Self::Sparse(c) => c.to_mut().push(value),
+            Self::Strided { .. } => {
+                self.materialize();
+                self.push(value);
+            }
+        }
+    }
+
+    pub fn map<F>(&mut self, f: F)
+    where
+        F: Fn(f64) -> f64,
+    {
+        match self {
+            Self::Dense(c) => c.to_mut().iter_mut().for_each(|x| *x = f(*x)),
+            Self::DenseF32(c) => c
+                .to_mut()
+                .iter_mut()
+                .for_each(|x| *x = f(f64::from(*x)) as f32),
+            Self::Sparse(c) => {
+                let c = c.to_mut();
+                c.default = f(c.default);
+                c.values.iter_mut().for_each(|x| *x = f(*x));
+            }
+            Self::Strided { .. } => {
+                self.materialize();
+                self.map(f);
+            }
+        }
+    }
+
+    // Copies a strided column into a contiguous one.
+    fn materialize(&mut self) {
+        *self = Self::Dense(Cow::Owned(self.iter().collect()));
+    }
+
+    /// Returns the (approximate) number of bytes used by the values of this column.
+    pub fn heap_size(&self) -> usize {
+        match self {
+            Self::Dense(c) => std::mem::size_of_val(&c[..]),
+            Self::DenseF32(c) => std::mem::size_of_val(&c[..]),
+            Self::Sparse(c) => {
+                std::mem::size_of_val(&c.rows[..]) + std::mem::size_of_val(&c.values[..])
+            }
+            Self::Strided { .. } => self.len() * std::mem::size_of::<f64>(),
+        }
+    }
+
+    pub fn to_sparse(&self, default: f64) -> Self {
+        let mut sparse = SparseColumn {
+            len: 0,
+            default,
+            rows: Vec::new(),
+            values: Vec::new(),
+        };
+        for x in self.iter() {
+            sparse.push(x);
+        }
+        Self::Sparse(Cow::Owned(sparse))
+    }
+
+    pub fn borrow(&self) -> Column<'_> {
+        match self {
+            Self::Dense(c) => Column::Dense(Cow::Borrowed(c)),
+            Self::DenseF32(c) => Column::DenseF32(Cow::Borrowed(c)),
+            Self::Sparse(c) => Column::Sparse(Cow::Borrowed(c)),
+            Self::Strided {
+                data,
+                offset,
+                stride,
+            } => Column::Strided {
+                data,
+                offset: *offset,
+                stride: *stride,
+            },
+        }
+    }
+
+    pub fn into_owned(self) -> Column<'static> {
+        match self {
+            Self::Dense(c) => Column::Dense(Cow::Owned(c.into_owned())),
+            Self::DenseF32(c) => Column::DenseF32(Cow::Owned(c.into_owned())),
+            Self::Sparse(c) => Column::Sparse(Cow::Owned(c.into_owned())),
+            Self::Strided { .. } => Column::Dense(Cow::Owned(self.iter().collect())),
+        }
+    }
+}
+
+/// A column which is encoded as a default value and the exceptional (row, value) pairs.
+#[derive(Debug, Clone)]
+pub struct SparseColumn {
+    len: usize,
+    default: f64,
+    rows: Vec<usize>,
+    values: Vec<f64>,
+}
+
+impl SparseColumn {
+    fn get(&self, row: usize) -> f64 {
+        debug_assert!(row < self.len);
+        match self.rows.binary_search(&row) {
+            Ok(i) => self.values[i],
+            Err(_) => self.default,
+        }
+    }
+
+    fn push(&mut self, value: f64) {
+        if value.to_bits() != self.default.to_bits() {
+            self.rows.push(self.len);
+            self.values.push(value);
+        }
+        self.len += 1;
+    }
+}
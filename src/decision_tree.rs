@@ -1,14 +1,61 @@
-use crate::functions;
-use crate::table::Table;
+use crate::encoding;
+use crate::functions::{self, Welford};
+use crate::random_forest::{SplitCriterion, SplitSampling};
+use crate::sketch::QuantileSketch;
+use crate::space::FeatureSpace;
+use crate::surrogate::{SurrogateLeaf, SurrogateTree};
+use crate::table::{ColumnType, Table};
+use itertools::Itertools as _;
 use rand::seq::SliceRandom as _;
 use rand::Rng;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::ops::Range;
 
 const MIN_SAMPLES_SPLIT: usize = 2;
 const MAX_DEPTH: usize = 64;
 
+// Smaller nodes are not worth searching in parallel.
+const PARALLEL_MIN_ROWS: usize = 1024;
+
+// Smaller nodes are searched with all rows by `SplitSampling::Goss`.
+const GOSS_MIN_ROWS: usize = 1024;
+
 #[derive(Debug, Clone, Default)]
 pub struct DecisionTreeOptions {
     pub max_features: Option<usize>,
+    pub max_depth: Option<usize>,
+    pub min_samples_leaf: Option<usize>,
+
+    // The minimum total weight of the rows of each leaf (finer than `min_samples_leaf` if the
+    // rows are weighted).
+    pub min_weight_leaf: Option<f64>,
+
+    // Searches the splits of the columns of a node in parallel.
+    pub parallel: bool,
+
+    // Searches only the boundaries of (at most) this number of quantile bins of each column.
+    pub max_bins: Option<usize>,
+
+    // Searches (at most) this number of thresholds of each column of a node, which are spaced by
+    // the quantiles of the rows of the node. Ignored if `max_bins` is set.
+    pub max_thresholds: Option<usize>,
+
+    // Searches the splits of large nodes with sampled rows.
+    pub split_sampling: Option<SplitSampling>,
+
+    // The columns never used for splits (e.g., constant or duplicated features).
+    pub excluded_columns: Vec<usize>,
+
+    // Evaluates only a uniformly random threshold of each column (extremely randomized trees).
+    // `max_bins` and `split_sampling` are ignored.
+    pub random_splits: bool,
+
+    // Compares the candidate splits.
+    pub criterion: SplitCriterion,
+
+    // Splits the categorical columns into two subsets of their categories (see
+    // `categorical_split`) instead of at thresholds of their indices.
+    pub categorical_splits: bool,
 }
 
 #[derive(Debug)]
@@ -22,11 +69,30 @@ impl DecisionTreeRegressor {
         Self { tree }
     }
 
-    #[cfg(test)]
     pub fn predict(&self, xs: &[f64]) -> f64 {
         self.tree.predict(xs)
     }
 
+    /// Returns the depth of the deepest leaf (zero if the root is a leaf).
+    pub fn depth(&self) -> usize {
+        self.fold(
+            0,
+            |depth, _| (depth + 1, depth + 1),
+            0,
+            |max, depth, _| max.max(depth),
+        )
+    }
+
+    /// Converts the tree bottom-up: leaves by `leaf_f` and internal nodes by `internal_f` (from
+    /// the converted left and right children).
+    pub fn map<T>(
+        &self,
+        leaf_f: &mut impl FnMut(f64) -> T,
+        internal_f: &mut impl FnMut(&SplitPoint, T, T) -> T,
+    ) -> T {
+        self.tree.map(self.tree.root, leaf_f, internal_f)
+    }
+
     pub fn fold<InternalT, InternalF, LeafT, LeafF>(
         &self,
         internal_init: InternalT,
@@ -36,19 +102,20 @@ impl DecisionTreeRegressor {
     ) -> LeafT
     where
         InternalF: FnMut(InternalT, &SplitPoint) -> (InternalT, InternalT),
-        LeafF: FnMut(LeafT, InternalT, f64) -> LeafT,
+        LeafF: FnMut(LeafT, InternalT, Leaf) -> LeafT,
     {
+        let tree = &self.tree;
         let mut leaf_acc = leaf_init;
-        let mut stack = vec![(&self.tree.root, internal_init)];
+        let mut stack = vec![(tree.root, internal_init)];
         while let Some((node, internal_acc)) = stack.pop() {
-            match node {
-                Node::Leaf { value } => {
-                    leaf_acc = leaf_f(leaf_acc, internal_acc, *value);
+            match tree.node(node) {
+                Node::Leaf(leaf) => {
+                    leaf_acc = leaf_f(leaf_acc, internal_acc, leaf);
                 }
-                Node::Internal { children } => {
-                    let (acc_l, acc_r) = internal_f(internal_acc, &children.split);
-                    stack.push((&children.left, acc_l));
-                    stack.push((&children.right, acc_r));
+                Node::Internal { split, left, right } => {
+                    let (acc_l, acc_r) = internal_f(internal_acc, &split);
+                    stack.push((left, acc_l));
+                    stack.push((right, acc_r));
                 }
             }
         }
@@ -56,9 +123,122 @@ impl DecisionTreeRegressor {
     }
 }
 
-#[derive(Debug)]
+impl SurrogateTree for DecisionTreeRegressor {
+    fn predict(&self, point: &[f64]) -> f64 {
+        self.tree.predict(point)
+    }
+
+    /// A leaf under categorical splits (see `DecisionTreeOptions::categorical_splits`) covers
+    /// the subsets of the categories of their features, which are given as the leaves of the
+    /// runs of the consecutive categories (one for each combination of the runs of the
+    /// features).
+    fn leaves(&self, space: &[Range<f64>]) -> Vec<SurrogateLeaf> {
+        self.fold(
+            (FeatureSpace::from_ranges(space.to_vec()), Vec::new()),
+            |(space, subsets): (FeatureSpace, Vec<Subset>), split| match split.categories {
+                Some(_) => {
+                    let column = split.column;
+                    let current = subsets
+                        .iter()
+                        .find(|s| s.0 == column)
+                        .map_or_else(|| indices(&space.ranges()[column]), |s| s.1.clone());
+                    let (lower, upper) = current
+                        .into_iter()
+                        .partition::<Vec<_>, _>(|&c| split.goes_left(c));
+                    let with = |categories| {
+                        let mut subsets = subsets.clone();
+                        subsets.retain(|s| s.0 != column);
+                        subsets.push((column, categories));
+                        (space.clone(), subsets)
+                    };
+                    (with(lower), with(upper))
+                }
+                None => {
+                    let (lower, upper) = space.split(split.column, split.threshold);
+                    ((lower, subsets.clone()), (upper, subsets))
+                }
+            },
+            Vec::new(),
+            |mut acc, (space, subsets), leaf| {
+                acc.extend(subset_ranges(space, &subsets).map(|ranges| SurrogateLeaf {
+                    ranges,
+                    value: leaf.value,
+                    variance: leaf.variance,
+                }));
+                acc
+            },
+        )
+    }
+
+    fn depth(&self) -> usize {
+        DecisionTreeRegressor::depth(self)
+    }
+}
+
+// The categories (in ascending order) allowed along a feature by the categorical splits.
+type Subset = (usize, Vec<f64>);
+
+// Returns the category indices in `range` (of a categorical feature, like `ColumnType::Integer`).
+fn indices(range: &Range<f64>) -> Vec<f64> {
+    let mut indices = Vec::new();
+    let mut c = range.start + 0.5;
+    while c < range.end {
+        indices.push(c);
+        c += 1.0;
+    }
+    indices
+}
+
+// Returns the ranges of the boxes covering `space` restricted to `subsets`.
+fn subset_ranges(space: FeatureSpace, subsets: &[Subset]) -> impl Iterator<Item = Vec<Range<f64>>> {
+    if subsets.is_empty() {
+        return vec![space.into_ranges()].into_iter();
+    }
+    let ranges = space
+        .ranges()
+        .iter()
+        .enumerate()
+        .map(|(j, r)| match subsets.iter().find(|s| s.0 == j) {
+            Some((_, categories)) => encoding::runs(r, |c| contains(categories, c)),
+            None => vec![r.clone()],
+        })
+        .collect::<Vec<_>>();
+    ranges
+        .into_iter()
+        .multi_cartesian_product()
+        .collect::<Vec<_>>()
+        .into_iter()
+}
+
+// Index of a node in the arrays of `Tree`.
+type NodeId = u32;
+
+// The split column of leaves.
+const LEAF: u32 = u32::MAX;
+
+/// A decision tree whose nodes are stored as a struct of arrays (children refer to each other
+/// by their indices), so that a tree is built and dropped without per-node allocations (except
+/// for the categories of the categorical splits) and takes 32 bytes per node.
+#[derive(Debug, Default)]
 pub struct Tree {
-    root: Node,
+    // The split column of each node (`LEAF` for leaves).
+    columns: Vec<u32>,
+
+    // The threshold of each internal node or the value of each leaf.
+    values: Vec<f64>,
+
+    // The variance of the targets of each leaf (and zero for internal nodes).
+    variances: Vec<f64>,
+
+    // The left and right children of each internal node (unused for leaves).
+    children: Vec<[NodeId; 2]>,
+
+    // The index in `category_sets` of the left categories of each categorical split (`LEAF` for
+    // the other nodes).
+    categories: Vec<u32>,
+    category_sets: Vec<Box<[f64]>>,
+
+    root: NodeId,
 }
 
 impl Tree {
@@ -67,119 +247,1054 @@ impl Tree {
         mut table: Table,
         options: DecisionTreeOptions,
     ) -> Self {
+        // The sorted orders are maintained while splitting nodes, so no node needs to sort.
+        // Binned search doesn't need the orders.
+        let edges = options.max_bins.map(|bins| {
+            (0..table.features_len())
+                .map(|column| bin_edges(&table, column, bins))
+                .collect()
+        });
+        if edges.is_none() {
+            table.presort();
+        }
         let max_features = options.max_features.unwrap_or_else(|| table.features_len());
-        let mut builder = NodeBuilder { rng, max_features };
+        let mut excluded = vec![false; table.features_len()];
+        for &column in &options.excluded_columns {
+            excluded[column] = true;
+        }
+        let mut builder = NodeBuilder {
+            rng,
+            max_features,
+            max_depth: options.max_depth.unwrap_or(MAX_DEPTH),
+            min_samples_leaf: options.min_samples_leaf.unwrap_or(1).max(1),
+            min_weight_leaf: options.min_weight_leaf.unwrap_or(0.0),
+            max_thresholds: options.max_thresholds.map(|n| n.max(1)),
+            parallel: options.parallel,
+            excluded,
+            edges,
+            split_sampling: options.split_sampling,
+            random_splits: options.random_splits,
+            criterion: options.criterion,
+            categorical_splits: options.categorical_splits,
+            tree: Tree::default(),
+            columns: Vec::new(),
+            candidates: Vec::new(),
+            scratch: Scratch::default(),
+        };
         let root = builder.build(&mut table, 1);
-        Self { root }
+        let mut tree = builder.tree;
+        tree.root = root;
+        tree.columns.shrink_to_fit();
+        tree.values.shrink_to_fit();
+        tree.variances.shrink_to_fit();
+        tree.children.shrink_to_fit();
+        tree.categories.shrink_to_fit();
+        tree.category_sets.shrink_to_fit();
+        tree
     }
 
-    #[cfg(test)]
-    fn predict(&self, xs: &[f64]) -> f64 {
-        self.root.predict(xs)
+    fn node(&self, node: NodeId) -> Node {
+        let i = node as usize;
+        if self.columns[i] == LEAF {
+            Node::Leaf(Leaf {
+                value: self.values[i],
+                variance: self.variances[i],
+            })
+        } else {
+            let [left, right] = self.children[i];
+            let categories = self.categories[i];
+            let split = SplitPoint {
+                column: self.columns[i] as usize,
+                threshold: self.values[i],
+                categories: (categories != LEAF)
+                    .then(|| self.category_sets[categories as usize].clone()),
+            };
+            Node::Internal { split, left, right }
+        }
     }
-}
 
-#[derive(Debug)]
-pub enum Node {
-    Leaf { value: f64 },
-    Internal { children: Children },
-}
-
-impl Node {
-    #[cfg(test)]
-    fn predict(&self, xs: &[f64]) -> f64 {
-        match self {
-            Self::Leaf { value } => *value,
-            Self::Internal { children } => {
-                if xs[children.split.column] <= children.split.threshold {
-                    children.left.predict(xs)
-                } else {
-                    children.right.predict(xs)
+    fn push(&mut self, node: Node) -> NodeId {
+        let id = NodeId::try_from(self.columns.len()).expect("too many nodes");
+        match node {
+            Node::Leaf(leaf) => {
+                self.columns.push(LEAF);
+                self.values.push(leaf.value);
+                self.variances.push(leaf.variance);
+                self.children.push([LEAF, LEAF]);
+                self.categories.push(LEAF);
+            }
+            Node::Internal { split, left, right } => {
+                let column = u32::try_from(split.column).expect("too many columns");
+                self.columns.push(column);
+                self.values.push(split.threshold);
+                self.variances.push(0.0);
+                self.children.push([left, right]);
+                match split.categories {
+                    Some(categories) => {
+                        let set = u32::try_from(self.category_sets.len()).expect("too many sets");
+                        self.categories.push(set);
+                        self.category_sets.push(categories);
+                    }
+                    None => self.categories.push(LEAF),
                 }
             }
         }
+        id
+    }
+
+    fn map<T>(
+        &self,
+        node: NodeId,
+        leaf_f: &mut impl FnMut(f64) -> T,
+        internal_f: &mut impl FnMut(&SplitPoint, T, T) -> T,
+    ) -> T {
+        match self.node(node) {
+            Node::Leaf(leaf) => leaf_f(leaf.value),
+            Node::Internal { split, left, right } => {
+                let left = self.map(left, leaf_f, internal_f);
+                let right = self.map(right, leaf_f, internal_f);
+                internal_f(&split, left, right)
+            }
+        }
+    }
+
+    // This reads the arrays directly, so the categories of the categorical splits aren't copied.
+    fn predict(&self, xs: &[f64]) -> f64 {
+        let mut node = self.root as usize;
+        while self.columns[node] != LEAF {
+            let x = xs[self.columns[node] as usize];
+            let left = match self.categories[node] {
+                LEAF => x <= self.values[node],
+                set => contains(&self.category_sets[set as usize], x),
+            };
+            node = self.children[node][usize::from(!left)] as usize;
+        }
+        self.values[node]
     }
 }
 
 #[derive(Debug)]
-pub struct Children {
-    split: SplitPoint,
-    left: Box<Node>,
-    right: Box<Node>,
+pub enum Node {
+    Leaf(Leaf),
+    Internal {
+        split: SplitPoint,
+        left: NodeId,
+        right: NodeId,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Leaf {
+    pub value: f64,
+
+    // The (weighted) variance of the targets of the rows of the leaf.
+    pub variance: f64,
 }
 
 #[derive(Debug)]
 pub struct SplitPoint {
     pub column: usize,
+
+    // NaN for the categorical splits.
     pub threshold: f64,
+
+    // The categories (in ascending order) going to the left child of a categorical split (see
+    // `DecisionTreeOptions::categorical_splits`). The other categories go to the right child.
+    pub categories: Option<Box<[f64]>>,
+}
+
+impl SplitPoint {
+    fn new(column: usize, threshold: f64) -> Self {
+        Self {
+            column,
+            threshold,
+            categories: None,
+        }
+    }
+
+    /// Returns `true` if the rows of the value `x` go to the left child.
+    pub fn goes_left(&self, x: f64) -> bool {
+        match &self.categories {
+            Some(categories) => contains(categories, x),
+            None => x <= self.threshold,
+        }
+    }
+}
+
+fn contains(categories: &[f64], x: f64) -> bool {
+    categories.binary_search_by(|c| c.total_cmp(&x)).is_ok()
 }
 
 #[derive(Debug)]
 struct NodeBuilder<R> {
     rng: R,
     max_features: usize,
+    max_depth: usize,
+    min_samples_leaf: usize,
+    min_weight_leaf: f64,
+    max_thresholds: Option<usize>,
+    parallel: bool,
+    excluded: Vec<bool>,
+
+    // The bin edges of each column (for binned search).
+    edges: Option<Vec<Vec<f64>>>,
+
+    split_sampling: Option<SplitSampling>,
+    random_splits: bool,
+    criterion: SplitCriterion,
+    categorical_splits: bool,
+
+    tree: Tree,
+
+    // Buffers reused across the nodes: the splittable columns, the columns to be searched (with
+    // their random thresholds if `random_splits`) and the buffers of `best_split`.
+    columns: Vec<usize>,
+    candidates: Vec<(usize, f64)>,
+    scratch: Scratch,
+}
+
+#[derive(Debug, Default)]
+struct Scratch {
+    targets: Vec<(f64, f64)>,
+    suffixes: Vec<Welford>,
+    bins: Vec<Bin>,
+}
+
+// The rows of a node falling into a bin.
+#[derive(Debug, Clone, Copy)]
+struct Bin {
+    acc: Welford,
+    rows: usize,
+    min: f64,
+    max: f64,
+}
+
+impl Default for Bin {
+    fn default() -> Self {
+        Self {
+            acc: Welford::default(),
+            rows: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+}
+
+// The minimum size of each side of a split.
+#[derive(Debug, Clone, Copy)]
+struct LeafLimits {
+    rows: usize,
+    weight: f64,
+}
+
+#[derive(Debug)]
+struct Best {
+    information_gain: f64,
+    split: Option<SplitPoint>,
+}
+
+impl Default for Best {
+    fn default() -> Self {
+        Self {
+            information_gain: f64::MIN,
+            split: None,
+        }
+    }
+}
+
+impl Best {
+    // Keeps the first split if the information gains are the same, and returns whether `split`
+    // is taken.
+    fn update(&mut self, split: Option<(f64, SplitPoint)>) -> bool {
+        if let Some((information_gain, split)) = split {
+            if self.information_gain < information_gain {
+                self.information_gain = information_gain;
+                self.split = Some(split);
+                return true;
+            }
+        }
+        false
+    }
 }
 
 impl<R: Rng> NodeBuilder<R> {
-    fn build(&mut self, table: &mut Table, depth: usize) -> Node {
-        if table.rows_len() < MIN_SAMPLES_SPLIT || depth > MAX_DEPTH {
-            let value = functions::mean(table.target());
-            return Node::Leaf { value };
+    fn build(&mut self, table: &mut Table, depth: usize) -> NodeId {
+        let min_samples_split = std::cmp::max(MIN_SAMPLES_SPLIT, self.min_samples_leaf * 2);
+        if table.rows_len() < min_samples_split || depth > self.max_depth {
+            return self.push(Node::Leaf(leaf(self.criterion, table)));
         }
 
-        let impurity = functions::mse(table.target());
-        let valid_columns = (0..table.features_len())
-            .filter(|&i| !table.column(i).any(|f| f.is_nan()))
-            .collect::<Vec<_>>();
+        self.columns.clear();
+        self.columns.extend(
+            (0..table.features_len())
+                .filter(|&i| !self.excluded[i] && !table.column(i).any(|f| f.is_nan())),
+        );
+        let max_features = std::cmp::min(self.columns.len(), self.max_features);
+        self.candidates.clear();
+        self.candidates.extend(
+            self.columns
+                .choose_multiple(&mut self.rng, max_features)
+                .map(|&column| (column, f64::NAN)),
+        );
+        if self.random_splits {
+            // The thresholds are drawn beforehand, so they don't depend on the parallelism.
+            for (column, threshold) in &mut self.candidates {
+                *threshold = random_threshold(&mut self.rng, table, *column);
+            }
+        }
+
+        let sample = self
+            .split_sampling
+            .filter(|_| !self.random_splits)
+            .and_then(|sampling| sample_rows(&mut self.rng, table, sampling));
+        let (table_ref, limits) = match &sample {
+            Some(sample) => {
+                let ratio = sample.rows_len() as f64 / table.rows_len() as f64;
+                let min_samples_leaf = (self.min_samples_leaf as f64 * ratio).ceil() as usize;
+                let weight_ratio = total_weight(sample) / total_weight(table);
+                let limits = LeafLimits {
+                    rows: min_samples_leaf.max(1),
+                    weight: self.min_weight_leaf * weight_ratio,
+                };
+                (sample, limits)
+            }
+            None => (
+                &*table,
+                LeafLimits {
+                    rows: self.min_samples_leaf,
+                    weight: self.min_weight_leaf,
+                },
+            ),
+        };
+
+        // The first column wins ties, so the result doesn't depend on the parallelism.
+        let edges = self.edges.as_deref();
+        let random_splits = self.random_splits;
+        let max_thresholds = self.max_thresholds;
+        let criterion = self.criterion;
+        let categorical_splits = self.categorical_splits;
+        let column_split = |(column, threshold): (usize, f64), scratch: &mut Scratch| match edges {
+            _ if categorical_splits && table_ref.column_type(column) == ColumnType::Categorical => {
+                let random_threshold = random_splits.then_some(threshold);
+                categorical_split(
+                    table_ref,
+                    column,
+                    random_threshold,
+                    limits,
+                    criterion,
+                    scratch,
+                )
+            }
+            _ if random_splits => threshold_split(table_ref, column, threshold, limits, criterion),
+            Some(edges) => binned_best_split(
+                table_ref,
+                column,
+                &edges[column],
+                limits,
+                criterion,
+                scratch,
+            ),
+            None => best_split(
+                table_ref,
+                column,
+                limits,
+                max_thresholds,
+                criterion,
+                scratch,
+            ),
+        };
+        let mut best = Best::default();
+        if self.parallel && table_ref.rows_len() >= PARALLEL_MIN_ROWS {
+            let splits = self
+                .candidates
+                .par_iter()
+                .map_init(Scratch::default, |scratch, &candidate| {
+                    column_split(candidate, scratch)
+                })
+                .collect::<Vec<_>>();
+            for split in splits {
+                best.update(split);
+            }
+        } else {
+            for &candidate in &self.candidates {
+                best.update(column_split(candidate, &mut self.scratch));
+            }
+        }
 
-        let mut best_split: Option<SplitPoint> = None;
-        let mut best_informatin_gain = std::f64::MIN;
-        let max_features = std::cmp::min(valid_columns.len(), self.max_features);
-        for &column in valid_columns.choose_multiple(&mut self.rng, max_features) {
-            table.sort_rows_by_column(column);
-            for (row, threshold) in table.thresholds(column) {
-                let impurity_l = functions::mse(table.target().take(row));
-                let impurity_r = functions::mse(table.target().skip(row));
-                let ratio_l = row as f64 / table.rows_len() as f64;
-                let ratio_r = 1.0 - ratio_l;
-
-                let information_gain = impurity - (ratio_l * impurity_l + ratio_r * impurity_r);
-                if best_informatin_gain < information_gain {
-                    best_informatin_gain = information_gain;
-                    best_split = Some(SplitPoint { column, threshold });
+        if let Some(split) = best.split {
+            let split_row = table.split_rows(split.column, |x| split.goes_left(x));
+
+            // The split found with sampled rows may leave too few rows on either side.
+            let upper_rows = table.rows_len() - split_row;
+            let enough_weight = self.min_weight_leaf <= 0.0 || {
+                let (mut lower, mut upper) = (0.0, 0.0);
+                for (i, (_, w)) in table.weighted_target().enumerate() {
+                    if i < split_row {
+                        lower += w;
+                    } else {
+                        upper += w;
+                    }
                 }
+                lower.min(upper) >= self.min_weight_leaf
+            };
+            if split_row.min(upper_rows) >= self.min_samples_leaf && enough_weight {
+                let (left, right) =
+                    table.with_split(split_row, |table| self.build(table, depth + 1));
+                return self.push(Node::Internal { split, left, right });
+            }
+        }
+        self.push(Node::Leaf(leaf(self.criterion, table)))
+    }
+
+    fn push(&mut self, node: Node) -> NodeId {
+        self.tree.push(node)
+    }
+}
+
+fn total_weight(table: &Table) -> f64 {
+    table.weighted_target().map(|(_, w)| w).sum()
+}
+
+// Returns a copy of the rows of `table` used to search the splits (or `None` if all the rows
+// should be used).
+fn sample_rows<R: Rng + ?Sized>(
+    rng: &mut R,
+    table: &Table,
+    sampling: SplitSampling,
+) -> Option<Table<'static>> {
+    let rows_len = table.rows_len();
+    match sampling {
+        SplitSampling::Uniform(n) => {
+            if rows_len <= n.get() {
+                return None;
+            }
+            let rows = table.rows().collect::<Vec<_>>();
+            let mut sample = rows
+                .choose_multiple(rng, n.get())
+                .copied()
+                .collect::<Vec<_>>();
+            sample.sort_unstable();
+            Some(table.copy_rows(&sample, &vec![1.0; sample.len()]))
+        }
+        SplitSampling::Goss { top, other } => {
+            if rows_len < GOSS_MIN_ROWS {
+                return None;
+            }
+            let mean = functions::weighted_mean(table.weighted_target());
+            let mut rows = table
+                .rows()
+                .zip(table.target())
+                .map(|(row, y)| (row, (y - mean).abs()))
+                .collect::<Vec<_>>();
+            rows.sort_by(|a, b| functions::total_cmp(b.1, a.1).then(a.0.cmp(&b.0)));
+            let top_len = (rows_len as f64 * top).ceil() as usize;
+            let other_len = ((rows_len as f64 * other).ceil() as usize).min(rows_len - top_len);
+            let (top_rows, rest) = rows.split_at(top_len);
+
+            let mut sample = top_rows.iter().map(|&(row, _)| row).collect::<Vec<_>>();
+            let mut scales = vec![1.0; sample.len()];
+            sample.extend(rest.choose_multiple(rng, other_len).map(|&(row, _)| row));
+            let scale = rest.len() as f64 / other_len.max(1) as f64;
+            scales.resize(sample.len(), scale);
+            Some(table.copy_rows(&sample, &scales))
+        }
+    }
+}
+
+// Returns the best split of `column` and its score (see `split_score`).
+//
+// The threshold is chosen by the information gain (see `gain`) even for the gain ratio (like
+// C4.5, since the gain ratio alone favors the thresholds isolating a few rows).
+//
+// If `max_thresholds` is given and the column has more distinct thresholds in the node, only
+// the first threshold at or after each of the `max_thresholds` quantiles of the rows is searched.
+fn best_split(
+    table: &Table,
+    column: usize,
+    limits: LeafLimits,
+    max_thresholds: Option<usize>,
+    criterion: SplitCriterion,
+    scratch: &mut Scratch,
+) -> Option<(f64, SplitPoint)> {
+    let rows = table.sorted_rows(column);
+    let targets = &mut scratch.targets;
+    targets.clear();
+    targets.extend(table.weighted_target_of(&rows));
+
+    // `suffixes[i]` accumulates the rows after the `i`-th one.
+    let suffixes = &mut scratch.suffixes;
+    suffixes.clear();
+    suffixes.resize(targets.len() + 1, Welford::default());
+    for (i, &(y, w)) in targets.iter().enumerate().rev() {
+        suffixes[i] = suffixes[i + 1];
+        suffixes[i].push(y, w);
+    }
+    let total = suffixes[0];
+
+    let rows_len = targets.len();
+    let max_thresholds =
+        max_thresholds.filter(|&n| table.thresholds(column, &rows).nth(n).is_some());
+    // The `j`-th quantile row of `max_thresholds` ones.
+    let quantile_row = |j: usize, n: usize| (j * rows_len).div_ceil(n + 1);
+    let mut next_quantile = 1;
+
+    let mut best = Best::default();
+    let mut sides = (Welford::default(), Welford::default());
+    let mut candidates = 0;
+    let mut prefix = Welford::default();
+    let mut next = 0;
+    for (row, threshold) in table.thresholds(column, &rows) {
+        if row < limits.rows || rows_len - row < limits.rows {
+            continue;
+        }
+        if let Some(n) = max_thresholds {
+            if next_quantile > n {
+                break;
+            }
+            if row < quantile_row(next_quantile, n) {
+                continue;
             }
+            while next_quantile <= n && quantile_row(next_quantile, n) <= row {
+                next_quantile += 1;
+            }
+        }
+        for &(y, w) in &targets[next..row] {
+            prefix.push(y, w);
+        }
+        next = row;
+        if prefix.total_weight() < limits.weight || suffixes[row].total_weight() < limits.weight {
+            continue;
+        }
+
+        let information_gain = gain(criterion, &total, (&prefix, &suffixes[row]));
+        candidates += 1;
+        if best.update(Some((information_gain, SplitPoint::new(column, threshold)))) {
+            sides = (prefix, suffixes[row]);
+        }
+    }
+    let split = best.split?;
+    let score = split_score(
+        criterion,
+        best.information_gain,
+        sides,
+        candidates,
+        rows_len,
+    );
+    Some((score, split))
+}
+
+// Returns a threshold drawn uniformly at random between the smallest and largest values of
+// `column` (or NaN if the values are the same). The column must not contain NaN.
+//
+// Thresholds of discrete columns are placed between consecutive integers like
+// `binned_best_split`.
+fn random_threshold<R: Rng + ?Sized>(rng: &mut R, table: &Table, column: usize) -> f64 {
+    let (min, max) = table
+        .column(column)
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), x| {
+            (min.min(x), max.max(x))
+        });
+    if min >= max {
+        return f64::NAN;
+    }
+    let threshold = rng.gen_range(min..max);
+    if table.column_type(column).is_discrete() {
+        threshold.floor() + 0.5
+    } else {
+        threshold
+    }
+}
+
+// Returns the split of `column` at `threshold` and its score (or `None` if either side is
+// smaller than `limits`).
+fn threshold_split(
+    table: &Table,
+    column: usize,
+    threshold: f64,
+    limits: LeafLimits,
+    criterion: SplitCriterion,
+) -> Option<(f64, SplitPoint)> {
+    if threshold.is_nan() {
+        return None;
+    }
+    let (mut lower, mut upper) = (Welford::default(), Welford::default());
+    let mut lower_rows = 0;
+    for (x, (y, w)) in table.column(column).zip(table.weighted_target()) {
+        if x <= threshold {
+            lower.push(y, w);
+            lower_rows += 1;
+        } else {
+            upper.push(y, w);
         }
+    }
+    let rows_len = table.rows_len();
+    if lower_rows < limits.rows || rows_len - lower_rows < limits.rows {
+        return None;
+    }
+    if lower.total_weight() < limits.weight || upper.total_weight() < limits.weight {
+        return None;
+    }
+    let mut total = lower;
+    total.merge(&upper);
+    let information_gain = gain(criterion, &total, (&lower, &upper));
+    let score = split_score(criterion, information_gain, (lower, upper), 1, rows_len);
+    Some((score, SplitPoint::new(column, threshold)))
+}
+
+// Returns the edges dividing the values of `column` into (at most) `bins` bins of nearly the
+// same frequency (the `i`-th bin is `(edges[i - 1], edges[i]]`).
+fn bin_edges(table: &Table, column: usize, bins: usize) -> Vec<f64> {
+    let mut sketch = QuantileSketch::new(1.0 / (2 * bins) as f64);
+    table
+        .column(column)
+        .filter(|x| !x.is_nan())
+        .for_each(|x| sketch.insert(x));
+    let mut edges = (1..bins)
+        .filter_map(|i| sketch.quantile(i as f64 / bins as f64))
+        .collect::<Vec<_>>();
+    edges.dedup();
+    edges
+}
 
-        if let Some(split) = best_split {
-            let children = self.build_children(table, split, depth);
-            Node::Internal { children }
+// Same as `best_split` but only the boundaries between the bins defined by `edges` are searched.
+//
+// Each threshold is placed between the largest value of a bin and the smallest value of the next
+// non-empty bin in the node, like `Table::thresholds`.
+fn binned_best_split(
+    table: &Table,
+    column: usize,
+    edges: &[f64],
+    limits: LeafLimits,
+    criterion: SplitCriterion,
+    scratch: &mut Scratch,
+) -> Option<(f64, SplitPoint)> {
+    let bins = &mut scratch.bins;
+    bins.clear();
+    bins.resize(edges.len() + 1, Bin::default());
+    for (x, (y, w)) in table.column(column).zip(table.weighted_target()) {
+        let bin = &mut bins[edges.partition_point(|&e| e < x)];
+        bin.acc.push(y, w);
+        bin.rows += 1;
+        bin.min = bin.min.min(x);
+        bin.max = bin.max.max(x);
+    }
+    bins.retain(|b| b.rows > 0);
+
+    // `suffixes[i]` accumulates the bins after the `i`-th one.
+    let suffixes = &mut scratch.suffixes;
+    suffixes.clear();
+    suffixes.resize(bins.len() + 1, Welford::default());
+    for (i, bin) in bins.iter().enumerate().rev() {
+        suffixes[i] = suffixes[i + 1];
+        suffixes[i].merge(&bin.acc);
+    }
+    let total = suffixes[0];
+
+    let integer = table.column_type(column).is_discrete();
+    let rows_len = table.rows_len();
+    let mut best = Best::default();
+    let mut sides = (Welford::default(), Welford::default());
+    let mut candidates = 0;
+    let mut prefix = Welford::default();
+    let mut lower_rows = 0;
+    for (i, pair) in bins.windows(2).enumerate() {
+        prefix.merge(&pair[0].acc);
+        lower_rows += pair[0].rows;
+        if lower_rows < limits.rows || rows_len - lower_rows < limits.rows {
+            continue;
+        }
+        if prefix.total_weight() < limits.weight || suffixes[i + 1].total_weight() < limits.weight {
+            continue;
+        }
+        let threshold = if integer {
+            pair[0].max + 0.5
         } else {
-            let value = functions::mean(table.target());
-            Node::Leaf { value }
+            (pair[0].max + pair[1].min) / 2.0
+        };
+        let information_gain = gain(criterion, &total, (&prefix, &suffixes[i + 1]));
+        candidates += 1;
+        if best.update(Some((information_gain, SplitPoint::new(column, threshold)))) {
+            sides = (prefix, suffixes[i + 1]);
+        }
+    }
+    let split = best.split?;
+    let score = split_score(
+        criterion,
+        best.information_gain,
+        sides,
+        candidates,
+        rows_len,
+    );
+    Some((score, split))
+}
+
+// Returns the best split of the categorical `column` into two subsets of its categories and its
+// score (see `split_score`).
+//
+// The categories of the node are ordered by the means of their targets, and the prefixes of the
+// order are searched like the bins of `binned_best_split`. For the squared error, the best of
+// these is the best of all the subsets (Breiman et al., 1984). If `random_threshold` is given
+// (see `random_threshold`), only the prefix of as many categories as those below it is evaluated.
+fn categorical_split(
+    table: &Table,
+    column: usize,
+    random_threshold: Option<f64>,
+    limits: LeafLimits,
+    criterion: SplitCriterion,
+    scratch: &mut Scratch,
+) -> Option<(f64, SplitPoint)> {
+    // The bins of the categories in ascending order (`min` and `max` are the category).
+    let bins = &mut scratch.bins;
+    bins.clear();
+    for (x, (y, w)) in table.column(column).zip(table.weighted_target()) {
+        let i = match bins.binary_search_by(|b| b.min.total_cmp(&x)) {
+            Ok(i) => i,
+            Err(i) => {
+                let bin = Bin {
+                    min: x,
+                    max: x,
+                    ..Bin::default()
+                };
+                bins.insert(i, bin);
+                i
+            }
+        };
+        bins[i].acc.push(y, w);
+        bins[i].rows += 1;
+    }
+    let cuts = match random_threshold {
+        Some(threshold) if threshold.is_nan() => return None,
+        Some(threshold) => {
+            let cut = bins.iter().filter(|b| b.min < threshold).count();
+            cut..cut + 1
         }
+        None => 1..bins.len(),
+    };
+    // The sort is stable, so the categories of the same means stay in ascending order.
+    bins.sort_by(|a, b| functions::total_cmp(a.acc.mean(), b.acc.mean()));
+
+    // `suffixes[i]` accumulates the bins after the `i`-th one.
+    let suffixes = &mut scratch.suffixes;
+    suffixes.clear();
+    suffixes.resize(bins.len() + 1, Welford::default());
+    for (i, bin) in bins.iter().enumerate().rev() {
+        suffixes[i] = suffixes[i + 1];
+        suffixes[i].merge(&bin.acc);
     }
+    let total = suffixes[0];
 
-    fn build_children(&mut self, table: &mut Table, split: SplitPoint, depth: usize) -> Children {
-        table.sort_rows_by_column(split.column);
-        let split_row = table
-            .column(split.column)
-            .take_while(|&f| f <= split.threshold)
-            .count();
-        let (left, right) =
-            table.with_split(split_row, |table| Box::new(self.build(table, depth + 1)));
-        Children { split, left, right }
+    let rows_len = table.rows_len();
+    let mut best = Best::default();
+    let mut sides = (Welford::default(), Welford::default());
+    let mut best_cut = 0;
+    let mut candidates = 0;
+    let mut prefix = Welford::default();
+    let mut lower_rows = 0;
+    for cut in 1..bins.len() {
+        prefix.merge(&bins[cut - 1].acc);
+        lower_rows += bins[cut - 1].rows;
+        if !cuts.contains(&cut) {
+            continue;
+        }
+        if lower_rows < limits.rows || rows_len - lower_rows < limits.rows {
+            continue;
+        }
+        if prefix.total_weight() < limits.weight || suffixes[cut].total_weight() < limits.weight {
+            continue;
+        }
+        let information_gain = gain(criterion, &total, (&prefix, &suffixes[cut]));
+        candidates += 1;
+        if best.update(Some((information_gain, SplitPoint::new(column, f64::NAN)))) {
+            sides = (prefix, suffixes[cut]);
+            best_cut = cut;
+        }
+    }
+    let mut split = best.split?;
+    let mut categories = bins[..best_cut].iter().map(|b| b.min).collect::<Vec<_>>();
+    categories.sort_by(f64::total_cmp);
+    split.categories = Some(categories.into_boxed_slice());
+    let score = split_score(
+        criterion,
+        best.information_gain,
+        sides,
+        candidates,
+        rows_len,
+    );
+    Some((score, split))
+}
+
+// Returns the information gain of the split of a node, whose rows are accumulated in `total`,
+// into `sides`: the decrease of the variance of the targets or, for the deviance criteria (see
+// `SplitCriterion::tweedie_power`), that of the mean half deviance.
+//
+// The half Tweedie deviance of the rows of a node predicting their mean `m` is `-W * f(m)` up to
+// the terms independent of the split, where `f(m)` is `m * ln(m)` for the power `1` (Poisson),
+// `-ln(m)` for `2` (gamma) and `m^(2 - p) / ((1 - p) * (2 - p))` for the other powers `p`. The
+// splits leaving a side without positive targets are rejected (the logarithm of its mean would
+// be infinite).
+fn gain(criterion: SplitCriterion, total: &Welford, (lower, upper): (&Welford, &Welford)) -> f64 {
+    let Some(power) = criterion.tweedie_power() else {
+        return total.variance() - (lower.m2() + upper.m2()) / total.total_weight();
+    };
+    if lower.mean() <= 0.0 || upper.mean() <= 0.0 {
+        return f64::MIN;
+    }
+    let f = |m: f64| {
+        if power == 1.0 {
+            m * m.ln()
+        } else if power == 2.0 {
+            -m.ln()
+        } else {
+            m.powf(2.0 - power) / ((1.0 - power) * (2.0 - power))
+        }
+    };
+    let term = |side: &Welford| side.total_weight() * f(side.mean());
+    (term(lower) + term(upper) - term(total)) / total.total_weight()
+}
+
+// Returns the value of a leaf having the rows of `table`: the mean of the targets or, for the
+// deviance criteria, its logarithm.
+fn leaf(criterion: SplitCriterion, table: &Table) -> Leaf {
+    let mean = functions::weighted_mean(table.weighted_target());
+    let variance = functions::weighted_variance(table.weighted_target());
+    let value = if criterion.tweedie_power().is_some() {
+        // Only the nodes without positive targets (e.g., a bootstrap sample of zeros) have zero
+        // means, which are floored to keep the values finite.
+        mean.max(f64::MIN_POSITIVE).ln()
+    } else {
+        mean
+    };
+    Leaf { value, variance }
+}
+
+// Returns the score of the split of a node of `rows` rows into `sides`, whose information gain is
+// `gain` and which is the best of `candidates` thresholds of its column.
+//
+// The gain ratio regards the targets as the indicators of a class: the decrease of the entropy
+// of the class minus the cost of choosing one of the thresholds (`ln(candidates) / rows` as in
+// C4.5 Release 8) is divided by the entropy of the split.
+fn split_score(
+    criterion: SplitCriterion,
+    gain: f64,
+    (lower, upper): (Welford, Welford),
+    candidates: usize,
+    rows: usize,
+) -> f64 {
+    match criterion {
+        SplitCriterion::SquaredError | SplitCriterion::Poisson | SplitCriterion::Tweedie { .. } => {
+            gain
+        }
+        SplitCriterion::GainRatio => {
+            let mut total = lower;
+            total.merge(&upper);
+            let fraction = lower.total_weight() / total.total_weight();
+            let information_gain = entropy(total.mean())
+                - fraction * entropy(lower.mean())
+                - (1.0 - fraction) * entropy(upper.mean());
+            let penalty = (candidates.max(1) as f64).ln() / rows as f64;
+            let split_entropy = entropy(fraction);
+            if split_entropy > 0.0 {
+                (information_gain - penalty) / split_entropy
+            } else {
+                f64::MIN
+            }
+        }
     }
 }
 
+// The entropy (in nats) of a binary variable taking `1` with the probability `p`.
+fn entropy(p: f64) -> f64 {
+    let p = p.clamp(0.0, 1.0);
+    [p, 1.0 - p]
+        .into_iter()
+        .filter(|&q| q > 0.0)
+        .map(|q| -q * q.ln())
+        .sum()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use rand;
+    use crate::table::ColumnType;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::num::NonZeroUsize;
+
+    #[test]
+    fn gain_ratio_works() -> anyhow::Result<()> {
+        // The parity is the only signal, but the best of the many thresholds of the index fits
+        // the noise better.
+        let parity = (0..16).map(|i| f64::from(i % 2)).collect::<Vec<_>>();
+        let index = (0..16).map(f64::from).collect::<Vec<_>>();
+        let target = [
+            0.0, 1.0, 0.0, 1.0, 1.0, 1.0, 0.0, 1.0, 0.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0,
+        ];
+        let root_column = |criterion| -> anyhow::Result<usize> {
+            let table = Table::new(vec![&parity, &index, &target])?;
+            let options = DecisionTreeOptions {
+                max_depth: Some(1),
+                criterion,
+                ..Default::default()
+            };
+            let regressor =
+                DecisionTreeRegressor::fit(&mut StdRng::seed_from_u64(0), table, options);
+            Ok(regressor.map(&mut |_| usize::MAX, &mut |split, _, _| split.column))
+        };
+        assert_eq!(root_column(SplitCriterion::SquaredError)?, 1);
+        assert_eq!(root_column(SplitCriterion::GainRatio)?, 0);
+
+        assert_eq!(entropy(0.0), 0.0);
+        assert!((entropy(0.5) - std::f64::consts::LN_2).abs() < 1e-12);
+        Ok(())
+    }
+
+    #[test]
+    fn poisson_works() -> anyhow::Result<()> {
+        // The squared error separates the zeros, whose log mean would be infinite.
+        let index = (0..8).map(f64::from).collect::<Vec<_>>();
+        let target = [0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0];
+        let leaves = |criterion| -> anyhow::Result<Vec<f64>> {
+            let table = Table::new(vec![&index, &target])?;
+            let options = DecisionTreeOptions {
+                max_depth: Some(1),
+                criterion,
+                ..Default::default()
+            };
+            let regressor =
+                DecisionTreeRegressor::fit(&mut StdRng::seed_from_u64(0), table, options);
+            let mut leaves = Vec::new();
+            regressor.fold(
+                (),
+                |(), _| ((), ()),
+                (),
+                |(), (), leaf| leaves.push(leaf.value),
+            );
+            leaves.sort_by(f64::total_cmp);
+            Ok(leaves)
+        };
+        assert_eq!(leaves(SplitCriterion::SquaredError)?, vec![0.0, 1.0]);
+        assert_eq!(leaves(SplitCriterion::Poisson)?, vec![0.2f64.ln(), 0.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn tweedie_works() {
+        // The half unit deviance of the Tweedie distribution of power `p`.
+        fn deviance(p: f64, y: f64, mu: f64) -> f64 {
+            if p == 2.0 {
+                (mu / y).ln() + y / mu - 1.0
+            } else {
+                y.powf(2.0 - p) / ((1.0 - p) * (2.0 - p)) - y * mu.powf(1.0 - p) / (1.0 - p)
+                    + mu.powf(2.0 - p) / (2.0 - p)
+            }
+        }
+
+        let lower = [0.5, 1.0, 4.0];
+        let upper = [2.0, 8.0, 3.0, 9.0];
+        let accumulate = |ys: &[f64]| {
+            let mut acc = Welford::default();
+            ys.iter().for_each(|&y| acc.push(y, 1.0));
+            acc
+        };
+        let sides = (accumulate(&lower), accumulate(&upper));
+        let mut total = sides.0;
+        total.merge(&sides.1);
+        for power in [1.5, 2.0, 3.0] {
+            let node_deviance =
+                |ys: &[f64], mu: f64| -> f64 { ys.iter().map(|&y| deviance(power, y, mu)).sum() };
+            let all = [&lower[..], &upper[..]].concat();
+            let expected = (node_deviance(&all, total.mean())
+                - node_deviance(&lower, sides.0.mean())
+                - node_deviance(&upper, sides.1.mean()))
+                / 7.0;
+            let actual = gain(
+                SplitCriterion::Tweedie { power },
+                &total,
+                (&sides.0, &sides.1),
+            );
+            assert!(
+                (actual - expected).abs() < 1e-12,
+                "{power}: {actual} {expected}"
+            );
+        }
+
+        // The power `1` is the Poisson deviance.
+        assert_eq!(
+            gain(
+                SplitCriterion::Tweedie { power: 1.0 },
+                &total,
+                (&sides.0, &sides.1)
+            ),
+            gain(SplitCriterion::Poisson, &total, (&sides.0, &sides.1))
+        );
+        let zeros = accumulate(&[0.0, 0.0]);
+        let criterion = SplitCriterion::Tweedie { power: 1.5 };
+        assert_eq!(gain(criterion, &total, (&zeros, &sides.1)), f64::MIN);
+    }
+
+    #[test]
+    fn exact_ties_keep_the_first_threshold() -> anyhow::Result<()> {
+        // The targets are symmetric, so the splits at 0.5 and 6.5 have exactly the same gain.
+        let feature = (0..8).map(f64::from).collect::<Vec<_>>();
+        let target = [0.6, 0.8, 0.7, 0.9, 0.9, 0.7, 0.8, 0.6];
+
+        // The two-pass mean squared errors (used before the single-pass scan) round the gain of
+        // the latter up and pick it, which is why the scan changed some of the pinned results.
+        let mse = |ys: &[f64]| {
+            let mean = functions::mean(ys.iter().copied());
+            ys.iter().map(|y| (y - mean).powi(2)).sum::<f64>() / ys.len() as f64
+        };
+        let two_pass_gain = |row: usize| {
+            let ratio = row as f64 / target.len() as f64;
+            mse(&target) - (ratio * mse(&target[..row]) + (1.0 - ratio) * mse(&target[row..]))
+        };
+        assert!(two_pass_gain(1) < two_pass_gain(7));
+
+        let table = Table::new(vec![&feature, &target])?;
+        let options = DecisionTreeOptions {
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        let regressor = DecisionTreeRegressor::fit(&mut StdRng::seed_from_u64(0), table, options);
+        let threshold = regressor.map(&mut |_| f64::NAN, &mut |split, _, _| split.threshold);
+        assert_eq!(threshold, 0.5);
+        Ok(())
+    }
+
+    #[test]
+    fn fit_uses_presorted_rows() -> anyhow::Result<()> {
+        let feature = [3.0, 1.0, 7.0, 0.0, 5.0, 2.0, 6.0, 4.0];
+        let target = [3.0, 1.0, 7.0, 0.0, 5.0, 2.0, 6.0, 4.0];
+        let table = Table::new(vec![&feature, &target])?;
+        let sorts = || crate::table::UNSORTED_SORTS.with(|n| n.get());
+
+        let before = sorts();
+        let _ = table.sorted_rows(0);
+        assert_eq!(sorts(), before + 1);
+
+        // No node sorts its rows, since `Tree::fit` presorts the (unsorted) table.
+        let before = sorts();
+        let regressor = DecisionTreeRegressor::fit(
+            &mut StdRng::seed_from_u64(0),
+            table.clone(),
+            DecisionTreeOptions::default(),
+        );
+        assert_eq!(sorts(), before);
+        assert_eq!(regressor.predict(&[6.0]), 6.0);
+
+        // Binned search doesn't need the orders.
+        let options = DecisionTreeOptions {
+            max_bins: Some(4),
+            ..Default::default()
+        };
+        let _ = DecisionTreeRegressor::fit(&mut StdRng::seed_from_u64(0), table, options);
+        assert_eq!(sorts(), before);
+        Ok(())
+    }
 
     #[test]
     fn regression_works() -> Result<(), anyhow::Error> {
-        let columns = vec![
+        let columns = [
             // Features
             &[
                 0.0, 0.0, 1.0, 2.0, 2.0, 2.0, 1.0, 0.0, 0.0, 2.0, 0.0, 1.0, 1.0, 2.0,
@@ -201,7 +1316,7 @@ mod tests {
         let train_len = columns[0].len() - 2;
         let table = Table::new(columns.iter().map(|c| &c[..train_len]).collect())?;
         let regressor =
-            DecisionTreeRegressor::fit(&mut rand::thread_rng(), table, Default::default());
+            DecisionTreeRegressor::fit(&mut StdRng::seed_from_u64(0), table, Default::default());
         assert_eq!(
             regressor.predict(&columns.iter().map(|f| f[train_len]).collect::<Vec<_>>()),
             46.0
@@ -213,4 +1328,366 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn min_samples_leaf_and_max_depth_work() -> Result<(), anyhow::Error> {
+        let feature = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+        let target = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+        let count_leaves = |options: DecisionTreeOptions| -> anyhow::Result<Vec<usize>> {
+            let table = Table::new(vec![&feature, &target])?;
+            let tree = DecisionTreeRegressor::fit(&mut StdRng::seed_from_u64(0), table, options);
+            let mut leaves = Vec::new();
+            tree.fold(
+                0,
+                |depth, _| (depth + 1, depth + 1),
+                (),
+                |(), depth, _| leaves.push(depth),
+            );
+            Ok(leaves)
+        };
+
+        assert_eq!(count_leaves(Default::default())?.len(), 8);
+
+        let options = DecisionTreeOptions {
+            min_samples_leaf: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(count_leaves(options)?.len(), 2);
+
+        let options = DecisionTreeOptions {
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        let leaves = count_leaves(options)?;
+        assert_eq!(leaves.len(), 4);
+        assert!(leaves.iter().all(|&depth| depth == 2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn min_weight_leaf_works() -> Result<(), anyhow::Error> {
+        let feature = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+        let target = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+        let weights = [0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 3.0, 3.0];
+        let predictions = |options: DecisionTreeOptions| -> anyhow::Result<Vec<f64>> {
+            let mut table = Table::new(vec![&feature, &target])?;
+            table.set_weights(&weights)?;
+            let tree = DecisionTreeRegressor::fit(&mut StdRng::seed_from_u64(0), table, options);
+            Ok(feature.iter().map(|&x| tree.predict(&[x])).collect())
+        };
+
+        assert_eq!(predictions(Default::default())?, target);
+
+        // The light rows can only form a single leaf, while the heavy ones make their own.
+        let options = DecisionTreeOptions {
+            min_weight_leaf: Some(3.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            predictions(options.clone())?,
+            [2.5, 2.5, 2.5, 2.5, 2.5, 2.5, 6.0, 7.0]
+        );
+        let binned = DecisionTreeOptions {
+            max_bins: Some(16),
+            ..options.clone()
+        };
+        assert_eq!(
+            predictions(binned)?,
+            [2.5, 2.5, 2.5, 2.5, 2.5, 2.5, 6.0, 7.0]
+        );
+
+        // No split leaves enough weight on both sides.
+        let options = DecisionTreeOptions {
+            min_weight_leaf: Some(3.5),
+            ..Default::default()
+        };
+        assert!(predictions(options)?.iter().all(|&y| y == 46.5 / 9.0));
+        Ok(())
+    }
+
+    #[test]
+    fn max_thresholds_works() -> Result<(), anyhow::Error> {
+        let feature = (0..100).map(f64::from).collect::<Vec<_>>();
+        let target = feature
+            .iter()
+            .map(|&x| if x < 37.0 { 0.0 } else { 1.0 })
+            .collect::<Vec<_>>();
+        let root_threshold = |max_thresholds| -> anyhow::Result<f64> {
+            let table = Table::new(vec![&feature, &target])?;
+            let options = DecisionTreeOptions {
+                max_depth: Some(1),
+                max_thresholds,
+                ..Default::default()
+            };
+            let tree = DecisionTreeRegressor::fit(&mut StdRng::seed_from_u64(0), table, options);
+            Ok(tree.map(&mut |_| f64::NAN, &mut |split, _, _| split.threshold))
+        };
+
+        assert_eq!(root_threshold(None)?, 36.5);
+        assert_eq!(root_threshold(Some(100))?, 36.5);
+
+        // Only the quartiles are searched.
+        assert_eq!(root_threshold(Some(3))?, 49.5);
+        Ok(())
+    }
+
+    #[test]
+    fn no_valid_split_makes_leaf() -> Result<(), anyhow::Error> {
+        // All the features are constant or NaN while the targets differ.
+        let constant = [1.0; 6];
+        let nan = [f64::NAN, 0.0, 1.0, 2.0, 3.0, 4.0];
+        let target = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+        for options in [
+            DecisionTreeOptions::default(),
+            DecisionTreeOptions {
+                max_bins: Some(4),
+                ..Default::default()
+            },
+            DecisionTreeOptions {
+                parallel: true,
+                ..Default::default()
+            },
+        ] {
+            let table = Table::new(vec![&constant, &nan, &target])?;
+            let tree = DecisionTreeRegressor::fit(&mut StdRng::seed_from_u64(0), table, options);
+            assert_eq!(tree.predict(&[1.0, 0.0]), 2.5);
+            assert_eq!(tree.predict(&[0.0, 3.0]), 2.5);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parallel_split_search_works() -> Result<(), anyhow::Error> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let columns = (0..4)
+            .map(|_| {
+                (0..2000)
+                    .map(|_| rng.gen_range(0..10) as f64)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let target = (0..2000)
+            .map(|i| columns[0][i] + columns[1][i] * columns[2][i])
+            .collect::<Vec<_>>();
+        let leaves = |parallel| -> anyhow::Result<Vec<f64>> {
+            let mut table_columns = columns.iter().map(|c| c.as_slice()).collect::<Vec<_>>();
+            table_columns.push(&target);
+            let table = Table::new(table_columns)?;
+            let options = DecisionTreeOptions {
+                max_features: Some(2),
+                parallel,
+                ..Default::default()
+            };
+            let tree = DecisionTreeRegressor::fit(&mut StdRng::seed_from_u64(1), table, options);
+            let mut leaves = Vec::new();
+            tree.fold(
+                (),
+                |(), _| ((), ()),
+                (),
+                |(), (), leaf| leaves.push(leaf.value),
+            );
+            Ok(leaves)
+        };
+        assert_eq!(leaves(true)?, leaves(false)?);
+        Ok(())
+    }
+
+    #[test]
+    fn binned_split_search_works() -> Result<(), anyhow::Error> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature = (0..2000)
+            .map(|_| rng.gen_range(0..10) as f64)
+            .collect::<Vec<_>>();
+        let target = feature.iter().map(|x| x * x).collect::<Vec<_>>();
+        let leaves = |max_bins| -> anyhow::Result<Vec<f64>> {
+            let table = Table::new(vec![&feature, &target])?;
+            let options = DecisionTreeOptions {
+                max_bins,
+                ..Default::default()
+            };
+            let tree = DecisionTreeRegressor::fit(&mut StdRng::seed_from_u64(1), table, options);
+            let mut leaves = Vec::new();
+            tree.fold(
+                (),
+                |(), _| ((), ()),
+                (),
+                |(), (), leaf| leaves.push(leaf.value),
+            );
+            Ok(leaves)
+        };
+
+        // Each distinct value has its own bin.
+        assert_eq!(leaves(Some(64))?, leaves(None)?);
+
+        // A single boundary can be used.
+        assert_eq!(leaves(Some(2))?.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn random_splits_work() -> Result<(), anyhow::Error> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature = (0..200).map(|_| rng.gen::<f64>()).collect::<Vec<_>>();
+        let integer = (0..200)
+            .map(|_| rng.gen_range(0..10) as f64)
+            .collect::<Vec<_>>();
+        let target = feature
+            .iter()
+            .map(|&x| if x < 0.5 { 0.0 } else { 10.0 })
+            .collect::<Vec<_>>();
+        let fit = |parallel| -> anyhow::Result<DecisionTreeRegressor> {
+            let mut table = Table::new(vec![&feature, &integer, &target])?;
+            table.set_column_type(1, ColumnType::Integer)?;
+            let options = DecisionTreeOptions {
+                random_splits: true,
+                parallel,
+                ..Default::default()
+            };
+            Ok(DecisionTreeRegressor::fit(
+                &mut StdRng::seed_from_u64(1),
+                table,
+                options,
+            ))
+        };
+        let tree = fit(false)?;
+        assert_eq!(tree.predict(&[0.1, 3.0]), 0.0);
+        assert_eq!(tree.predict(&[0.9, 3.0]), 10.0);
+
+        let mut thresholds = Vec::new();
+        tree.fold(
+            (),
+            |(), split| {
+                if split.column == 1 {
+                    thresholds.push(split.threshold);
+                }
+                ((), ())
+            },
+            (),
+            |(), (), _| {},
+        );
+        assert!(
+            thresholds.iter().all(|t| t.fract() == 0.5),
+            "{thresholds:?}"
+        );
+
+        let leaves = |tree: &DecisionTreeRegressor| {
+            let mut leaves = Vec::new();
+            tree.fold(
+                (),
+                |(), _| ((), ()),
+                (),
+                |(), (), leaf| leaves.push(leaf.value),
+            );
+            leaves
+        };
+        assert_eq!(leaves(&fit(true)?), leaves(&tree));
+        Ok(())
+    }
+
+    #[test]
+    fn categorical_splits_work() -> Result<(), anyhow::Error> {
+        // The categories `0`, `3` and `4` (of `0..6`) raise the target, which no threshold of
+        // the indices separates.
+        let feature = (0..60).map(|i| f64::from(i % 6)).collect::<Vec<_>>();
+        let target = feature
+            .iter()
+            .map(|&c| {
+                if [0.0, 3.0, 4.0].contains(&c) {
+                    10.0
+                } else {
+                    0.0
+                }
+            })
+            .collect::<Vec<_>>();
+        let fit = |options: DecisionTreeOptions| -> anyhow::Result<DecisionTreeRegressor> {
+            let mut table = Table::new(vec![&feature, &target])?;
+            table.set_column_type(0, ColumnType::Categorical)?;
+            let options = DecisionTreeOptions {
+                categorical_splits: true,
+                ..options
+            };
+            Ok(DecisionTreeRegressor::fit(
+                &mut StdRng::seed_from_u64(0),
+                table,
+                options,
+            ))
+        };
+        let root_categories = |tree: &DecisionTreeRegressor| {
+            tree.map(&mut |_| None, &mut |split, _, _| split.categories.clone())
+        };
+
+        let depth_one = DecisionTreeOptions {
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        let binned = DecisionTreeOptions {
+            max_bins: Some(2),
+            ..depth_one.clone()
+        };
+        for options in [depth_one.clone(), binned] {
+            let tree = fit(options)?;
+            // The categories of the lower mean go to the left.
+            let categories = root_categories(&tree).expect("categorical split");
+            assert_eq!(&categories[..], [1.0, 2.0, 5.0]);
+            for c in 0..6 {
+                let expected = if [0, 3, 4].contains(&c) { 10.0 } else { 0.0 };
+                assert_eq!(tree.predict(&[f64::from(c)]), expected);
+            }
+            // An index which doesn't appear goes to the right.
+            assert_eq!(tree.predict(&[7.0]), 10.0);
+        }
+
+        let tree = fit(DecisionTreeOptions {
+            random_splits: true,
+            ..Default::default()
+        })?;
+        assert!(root_categories(&tree).is_some());
+        for c in 0..6 {
+            let expected = if [0, 3, 4].contains(&c) { 10.0 } else { 0.0 };
+            assert_eq!(tree.predict(&[f64::from(c)]), expected);
+        }
+
+        let ordinal = DecisionTreeOptions {
+            categorical_splits: false,
+            ..depth_one
+        };
+        let mut table = Table::new(vec![&feature, &target])?;
+        table.set_column_type(0, ColumnType::Categorical)?;
+        let tree = DecisionTreeRegressor::fit(&mut StdRng::seed_from_u64(0), table, ordinal);
+        assert!(root_categories(&tree).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn split_sampling_works() -> Result<(), anyhow::Error> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature = (0..5000)
+            .map(|_| rng.gen_range(0..10) as f64)
+            .collect::<Vec<_>>();
+        let target = feature
+            .iter()
+            .map(|&x| if x < 5.0 { 0.0 } else { 10.0 })
+            .collect::<Vec<_>>();
+        for sampling in [
+            SplitSampling::Uniform(NonZeroUsize::new(100).expect("non zero")),
+            SplitSampling::Goss {
+                top: 0.1,
+                other: 0.05,
+            },
+        ] {
+            let table = Table::new(vec![&feature, &target])?;
+            let options = DecisionTreeOptions {
+                split_sampling: Some(sampling),
+                min_samples_leaf: Some(10),
+                ..Default::default()
+            };
+            let tree = DecisionTreeRegressor::fit(&mut StdRng::seed_from_u64(1), table, options);
+
+            // The leaves are computed from all the rows, so they are pure.
+            assert_eq!(tree.predict(&[2.0]), 0.0);
+            assert_eq!(tree.predict(&[7.0]), 10.0);
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,361 @@
+//! Distillation of a function (e.g., the forest) into a single shallow tree.
+//!
+//! The function is evaluated at points sampled uniformly from the feature space, and a
+//! regression tree of a limited depth is fitted to the values. The tree is an interpretable
+//! summary of the function: the splits near the root show the features having the largest
+//! effects and the nested splits show how the features interact.
+use crate::decision_tree::{DecisionTreeOptions, DecisionTreeRegressor};
+use crate::functions;
+use crate::space::FeatureSpace;
+use crate::table::Table;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt::Write as _;
+use std::num::NonZeroUsize;
+
+/// Distillation options.
+#[derive(Debug, Clone)]
+pub struct DistillOptions {
+    samples: NonZeroUsize,
+    max_depth: NonZeroUsize,
+    min_samples_leaf: NonZeroUsize,
+    seed: Option<u64>,
+}
+
+impl DistillOptions {
+    /// Makes a `DistillOptions` instance with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of the sampled points.
+    ///
+    /// The default value is `2048`.
+    pub fn samples(mut self, samples: NonZeroUsize) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Sets the maximum depth of the tree.
+    ///
+    /// The default value is `3` (i.e., at most eight leaves).
+    pub fn max_depth(mut self, max_depth: NonZeroUsize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets the minimum number of the sampled points at each leaf.
+    ///
+    /// The default value is `16`.
+    pub fn min_samples_leaf(mut self, min_samples_leaf: NonZeroUsize) -> Self {
+        self.min_samples_leaf = min_samples_leaf;
+        self
+    }
+
+    /// Sets the random generator seed.
+    ///
+    /// The default value is random.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+impl Default for DistillOptions {
+    fn default() -> Self {
+        Self {
+            samples: NonZeroUsize::new(2048).expect("never fails"),
+            max_depth: NonZeroUsize::new(3).expect("never fails"),
+            min_samples_leaf: NonZeroUsize::new(16).expect("never fails"),
+            seed: None,
+        }
+    }
+}
+
+/// A shallow tree approximating a function (see `Fanova::distill`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExplanatoryTree {
+    /// The root node.
+    pub root: ExplanatoryNode,
+
+    /// The coefficient of determination of the tree with respect to the function at the
+    /// sampled points (`1.0` if the tree reproduces the function exactly).
+    pub fidelity: f64,
+}
+
+/// A node of `ExplanatoryTree`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExplanatoryNode {
+    /// A leaf.
+    Leaf {
+        /// The mean value of the function at the sampled points in the leaf.
+        value: f64,
+
+        /// The number of the sampled points in the leaf.
+        samples: usize,
+    },
+
+    /// An internal node.
+    Split {
+        /// The index of the split feature.
+        feature: usize,
+
+        /// The points whose feature values are less than or equal to this go to `left`.
+        threshold: f64,
+
+        /// The mean value of the function at the sampled points in the node.
+        value: f64,
+
+        /// The number of the sampled points in the node.
+        samples: usize,
+
+        /// The child of the smaller feature values.
+        left: Box<ExplanatoryNode>,
+
+        /// The child of the larger feature values.
+        right: Box<ExplanatoryNode>,
+    },
+}
+
+impl ExplanatoryNode {
+    /// Returns the value of the leaf where `point` falls.
+    pub fn predict(&self, point: &[f64]) -> f64 {
+        match self {
+            Self::Leaf { value, .. } => *value,
+            Self::Split {
+                feature,
+                threshold,
+                left,
+                right,
+                ..
+            } => {
+                if point[*feature] <= *threshold {
+                    left.predict(point)
+                } else {
+                    right.predict(point)
+                }
+            }
+        }
+    }
+
+    /// Replaces the threshold of each split with `f(feature, threshold)`.
+    ///
+    /// `f` must be increasing (e.g., to convert the thresholds into the original space of the
+    /// features).
+    pub fn map_thresholds(&mut self, f: &mut impl FnMut(usize, f64) -> f64) {
+        if let Self::Split {
+            feature,
+            threshold,
+            left,
+            right,
+            ..
+        } = self
+        {
+            *threshold = f(*feature, *threshold);
+            left.map_thresholds(f);
+            right.map_thresholds(f);
+        }
+    }
+
+    fn fill(&mut self, points: &[Vec<f64>], ys: &[f64], rows: Vec<usize>) {
+        match self {
+            Self::Leaf { samples, .. } => *samples = rows.len(),
+            Self::Split {
+                feature,
+                threshold,
+                value,
+                samples,
+                left,
+                right,
+            } => {
+                *value = functions::mean(rows.iter().map(|&i| ys[i]));
+                *samples = rows.len();
+                let (l, r) = rows
+                    .into_iter()
+                    .partition(|&i| points[i][*feature] <= *threshold);
+                left.fill(points, ys, l);
+                right.fill(points, ys, r);
+            }
+        }
+    }
+}
+
+impl ExplanatoryTree {
+    /// Renders the tree in the Graphviz DOT language.
+    ///
+    /// `names` are the labels of the features (the indices are used for the missing ones).
+    pub fn to_dot(&self, names: &[&str]) -> String {
+        let mut dot = "digraph tree {\n  node [shape=box];\n".to_owned();
+        let mut stack = vec![(&self.root, 0)];
+        let mut next_id = 1;
+        while let Some((node, id)) = stack.pop() {
+            match node {
+                ExplanatoryNode::Leaf { value, samples } => {
+                    let _ = writeln!(
+                        dot,
+                        "  {id} [label=\"value = {}\\nsamples = {samples}\"];",
+                        format_number(*value)
+                    );
+                }
+                ExplanatoryNode::Split {
+                    feature,
+                    threshold,
+                    value,
+                    samples,
+                    left,
+                    right,
+                } => {
+                    let name = names
+                        .get(*feature)
+                        .map_or_else(|| feature.to_string(), |name| name.replace('"', "\\\""));
+                    let _ = writeln!(
+                        dot,
+                        "  {id} [label=\"{name} <= {}\\nvalue = {}\\nsamples = {samples}\"];",
+                        format_number(*threshold),
+                        format_number(*value)
+                    );
+                    let (l, r) = (next_id, next_id + 1);
+                    next_id += 2;
+                    let _ = writeln!(dot, "  {id} -> {l} [label=\"yes\"];");
+                    let _ = writeln!(dot, "  {id} -> {r} [label=\"no\"];");
+                    stack.push((right, r));
+                    stack.push((left, l));
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+// Formats a value with four decimals (or in the scientific notation if it is tiny or huge).
+fn format_number(x: f64) -> String {
+    if x == 0.0 || (1e-3..1e6).contains(&x.abs()) {
+        format!("{x:.4}")
+    } else {
+        format!("{x:.3e}")
+    }
+}
+
+/// Fits a shallow tree to `f` over `space`.
+pub fn distill<F>(space: &FeatureSpace, options: &DistillOptions, f: F) -> ExplanatoryTree
+where
+    F: Fn(&[f64]) -> f64,
+{
+    let seed = options.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let mut rng = StdRng::seed_from_u64(seed);
+    let points = (0..options.samples.get())
+        .map(|_| {
+            space
+                .ranges()
+                .iter()
+                .map(|r| {
+                    if r.start < r.end {
+                        rng.gen_range(r.start..r.end)
+                    } else {
+                        r.start
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let ys = points.iter().map(|p| f(p)).collect::<Vec<_>>();
+
+    let features_len = space.ranges().len();
+    let mut columns = (0..features_len)
+        .map(|i| points.iter().map(|p| p[i]).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    columns.push(ys.clone());
+    let table = Table::new(columns.iter().map(|c| &c[..]).collect()).expect("never fails");
+    let tree_options = DecisionTreeOptions {
+        max_depth: Some(options.max_depth.get()),
+        min_samples_leaf: Some(options.min_samples_leaf.get()),
+        ..DecisionTreeOptions::default()
+    };
+    let tree = DecisionTreeRegressor::fit(&mut rng, table, tree_options);
+    let mut root = tree.map(
+        &mut |value| ExplanatoryNode::Leaf { value, samples: 0 },
+        &mut |split, left, right| match (&left, &right) {
+            // Splits of constant regions explain nothing.
+            (ExplanatoryNode::Leaf { value: l, .. }, ExplanatoryNode::Leaf { value: r, .. })
+                if l == r =>
+            {
+                left
+            }
+            _ => ExplanatoryNode::Split {
+                feature: split.column,
+                threshold: split.threshold,
+                value: 0.0,
+                samples: 0,
+                left: Box::new(left),
+                right: Box::new(right),
+            },
+        },
+    );
+    root.fill(&points, &ys, (0..points.len()).collect());
+
+    let mean = functions::mean(ys.iter().copied());
+    let total = ys.iter().map(|y| (y - mean).powi(2)).sum::<f64>();
+    let residual = points
+        .iter()
+        .zip(&ys)
+        .map(|(p, y)| (y - root.predict(p)).powi(2))
+        .sum::<f64>();
+    let fidelity = if total > 0.0 {
+        1.0 - residual / total
+    } else {
+        1.0
+    };
+    ExplanatoryTree { root, fidelity }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distill_works() {
+        let space = FeatureSpace::from_ranges(vec![0.0..1.0, 0.0..1.0, 0.0..0.0]);
+        let options = DistillOptions::new().seed(0);
+
+        // `x0` splits first and `x1` matters only if `x0` is large.
+        let f = |x: &[f64]| {
+            if x[0] <= 0.5 {
+                0.0
+            } else if x[1] <= 0.25 {
+                1.0
+            } else {
+                2.0
+            }
+        };
+        let tree = distill(&space, &options, f);
+        assert!(tree.fidelity > 0.99, "{}", tree.fidelity);
+        let ExplanatoryNode::Split {
+            feature,
+            threshold,
+            samples,
+            left,
+            right,
+            ..
+        } = &tree.root
+        else {
+            panic!("{tree:?}");
+        };
+        assert_eq!((*feature, *samples), (0, 2048));
+        assert!((threshold - 0.5).abs() < 0.01);
+        assert!(matches!(**left, ExplanatoryNode::Leaf { value, .. } if value == 0.0));
+        assert!(matches!(**right, ExplanatoryNode::Split { feature: 1, .. }));
+        assert_eq!(tree.root.predict(&[0.9, 0.9, 0.0]), 2.0);
+        assert_eq!(distill(&space, &options, f), tree);
+
+        let dot = tree.to_dot(&["a \"b\""]);
+        assert!(dot.starts_with("digraph tree {\n"));
+        assert!(dot.contains("0 [label=\"a \\\"b\\\" <= 0.5"));
+        assert!(dot.contains("label=\"1 <= 0.2"));
+        assert!(dot.contains("0 -> 1 [label=\"yes\"];"));
+
+        let mut shifted = tree.clone();
+        shifted.root.map_thresholds(&mut |_, t| t + 10.0);
+        assert_eq!(shifted.root.predict(&[10.9, 10.9, 0.0]), 2.0);
+    }
+}
@@ -0,0 +1,378 @@
+//! Encodings of the categorical features (see `CategoricalEncoding`).
+use crate::fanova::CategoricalEncoding;
+use crate::partition::{Partition, TreePartitions};
+use crate::space::FeatureSpace;
+use crate::surrogate::SurrogateTree;
+use crate::table::{ColumnType, Table};
+use itertools::Itertools as _;
+use std::ops::Range;
+
+// The number of the folds of `CategoricalEncoding::Target`.
+const TARGET_FOLDS: usize = 5;
+
+// The weight of the prior (the mean of all the targets) of the smoothed target means of
+// `CategoricalEncoding::Target`, so rare categories are pulled towards the prior.
+const TARGET_PRIOR_WEIGHT: f64 = 1.0;
+
+/// Mapping from the features of a table to the columns of its encoded table, in which each
+/// categorical feature is replaced by the boolean indicators of its categories
+/// (`CategoricalEncoding::OneHot`) or by the target means of its categories
+/// (`CategoricalEncoding::Target`).
+///
+/// The trees fitted to the encoded table are converted back into partitions of the original
+/// feature space (a partition of the encoded space covers the runs of the consecutive categories
+/// it allows), so the importances and the other outputs of the model are those of the original
+/// features.
+#[derive(Debug, Clone)]
+pub struct FeatureEncoder {
+    features: Vec<Encoded>,
+
+    // The feature space of the encoded table.
+    space: FeatureSpace,
+}
+
+#[derive(Debug, Clone)]
+enum Encoded {
+    // The index of the column of the feature.
+    Column(usize),
+
+    // The index of the first indicator and the categories (in ascending order) indicated by the
+    // consecutive indicators.
+    Indicators { start: usize, categories: Vec<f64> },
+
+    // The index of the column of the target means.
+    TargetMeans { column: usize, means: TargetMeans },
+}
+
+// The target means of the categories of a feature.
+#[derive(Debug, Clone)]
+struct TargetMeans {
+    // The categories in ascending order.
+    categories: Vec<f64>,
+
+    // The mean of each category over all the rows, by which the trees are converted back.
+    means: Vec<f64>,
+
+    // The mean of the indices that don't appear in the table (i.e., the prior).
+    absent: f64,
+
+    // The mean of each category over the rows out of each fold, which are the encoded values of
+    // the rows in the fold.
+    out_of_fold: Vec<Vec<f64>>,
+}
+
+impl FeatureEncoder {
+    /// Encodes the categorical features of `table` (or returns `None` if it has none or
+    /// `encoding` is `CategoricalEncoding::Ordinal` or `CategoricalEncoding::Native`).
+    pub fn encode(table: &Table, encoding: CategoricalEncoding) -> Option<(Self, Table<'static>)> {
+        let categorical = |i| table.column_type(i) == ColumnType::Categorical;
+        let encoded = !matches!(
+            encoding,
+            CategoricalEncoding::Ordinal | CategoricalEncoding::Native
+        );
+        if !encoded || !(0..table.features_len()).any(categorical) {
+            return None;
+        }
+
+        let mut features = Vec::with_capacity(table.features_len());
+        let mut columns = Vec::new();
+        for i in 0..table.features_len() {
+            if !categorical(i) {
+                features.push(Encoded::Column(columns.len()));
+                columns.push((table.column(i).collect(), table.column_type(i)));
+                continue;
+            }
+            let mut categories = table.column(i).filter(|x| !x.is_nan()).collect::<Vec<_>>();
+            categories.sort_by(f64::total_cmp);
+            categories.dedup();
+            if encoding == CategoricalEncoding::Target {
+                let means = TargetMeans::new(table, i, categories);
+                let encoded = table.column(i).enumerate().map(|(row, x)| {
+                    means
+                        .index(x)
+                        .map_or(f64::NAN, |j| means.out_of_fold[row % TARGET_FOLDS][j])
+                });
+                features.push(Encoded::TargetMeans {
+                    column: columns.len(),
+                    means: means.clone(),
+                });
+                columns.push((encoded.collect(), ColumnType::Continuous));
+                continue;
+            }
+            features.push(Encoded::Indicators {
+                start: columns.len(),
+                categories: categories.clone(),
+            });
+            for category in categories {
+                let indicator = table.column(i).map(|x| match x {
+                    x if x.is_nan() => f64::NAN,
+                    x if x == category => 1.0,
+                    _ => 0.0,
+                });
+                columns.push((indicator.collect(), ColumnType::Boolean));
+            }
+        }
+        let encoded = table.with_features(columns);
+        let space = FeatureSpace::from_table(&encoded);
+        Some((Self { features, space }, encoded))
+    }
+
+    /// Converts a row of the encoded table into the original features.
+    pub fn decode_row(&self, row: &[f64]) -> Vec<f64> {
+        self.features
+            .iter()
+            .map(|encoded| match encoded {
+                Encoded::Column(j) => row[*j],
+                Encoded::Indicators { start, categories } => row[*start..][..categories.len()]
+                    .iter()
+                    .position(|&x| x == 1.0)
+                    .map_or(f64::NAN, |k| categories[k]),
+                Encoded::TargetMeans { column, means } => means.decode(row[*column]),
+            })
+            .collect()
+    }
+
+    /// Returns the partitions of `space` (the original feature space) of a tree fitted to the
+    /// encoded table.
+    pub fn partitions(&self, tree: &dyn SurrogateTree, space: FeatureSpace) -> TreePartitions {
+        let encoded = TreePartitions::new(tree, self.space.clone());
+        let partitions = encoded
+            .iter()
+            .flat_map(|p| {
+                let ranges = self
+                    .features
+                    .iter()
+                    .zip(space.ranges())
+                    .map(|(encoded, whole)| match encoded {
+                        Encoded::Column(j) => vec![p.space.ranges()[*j].clone()],
+                        Encoded::Indicators { start, categories } => {
+                            let indicators = &p.space.ranges()[*start..][..categories.len()];
+                            allowed_runs(indicators, categories, whole)
+                        }
+                        Encoded::TargetMeans { column, means } => {
+                            // The outermost partitions also cover the means out of the range of
+                            // the encoded values.
+                            let (r, bounds) =
+                                (&p.space.ranges()[*column], &self.space.ranges()[*column]);
+                            let start = if r.start == bounds.start {
+                                f64::NEG_INFINITY
+                            } else {
+                                r.start
+                            };
+                            let end = if r.end == bounds.end {
+                                f64::INFINITY
+                            } else {
+                                r.end
+                            };
+                            runs(whole, |c| (start..end).contains(&means.mean(c)))
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                ranges
+                    .into_iter()
+                    .multi_cartesian_product()
+                    .map(|ranges| Partition {
+                        value: p.value,
+                        variance: p.variance,
+                        space: FeatureSpace::from_ranges(ranges),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        TreePartitions::from_partitions(partitions, space)
+    }
+}
+
+impl TargetMeans {
+    // The rows are assigned to the folds in turn.
+    fn new(table: &Table, feature: usize, categories: Vec<f64>) -> Self {
+        // The weighted sums of the targets and the weights of each category in each fold.
+        let mut sums = vec![vec![(0.0, 0.0); categories.len()]; TARGET_FOLDS];
+        let mut priors = vec![(0.0, 0.0); TARGET_FOLDS];
+        for (row, (x, (y, w))) in table
+            .column(feature)
+            .zip(table.weighted_target())
+            .enumerate()
+        {
+            let k = row % TARGET_FOLDS;
+            priors[k] = (priors[k].0 + y * w, priors[k].1 + w);
+            if let Ok(j) = categories.binary_search_by(|c| c.total_cmp(&x)) {
+                sums[k][j] = (sums[k][j].0 + y * w, sums[k][j].1 + w);
+            }
+        }
+        let total = |sums: &[(f64, f64)]| {
+            sums.iter()
+                .fold((0.0, 0.0), |(s, w), &(y, v)| (s + y, w + v))
+        };
+        let smoothed = |(sum, weight): (f64, f64), (prior_sum, prior_weight): (f64, f64)| {
+            let prior = if prior_weight > 0.0 {
+                prior_sum / prior_weight
+            } else {
+                0.0
+            };
+            (sum + TARGET_PRIOR_WEIGHT * prior) / (weight + TARGET_PRIOR_WEIGHT)
+        };
+
+        let prior = total(&priors);
+        let category_totals = (0..categories.len())
+            .map(|j| total(&sums.iter().map(|s| s[j]).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        let out_of_fold = (0..TARGET_FOLDS)
+            .map(|k| {
+                let minus = |(a, b): (f64, f64), (c, d): (f64, f64)| (a - c, b - d);
+                let prior = minus(prior, priors[k]);
+                category_totals
+                    .iter()
+                    .zip(&sums[k])
+                    .map(|(&t, &s)| smoothed(minus(t, s), prior))
+                    .collect()
+            })
+            .collect();
+        Self {
+            means: category_totals
+                .iter()
+                .map(|&t| smoothed(t, prior))
+                .collect(),
+            absent: smoothed((0.0, 0.0), prior),
+            categories,
+            out_of_fold,
+        }
+    }
+
+    fn index(&self, category: f64) -> Option<usize> {
+        self.categories
+            .binary_search_by(|c| c.total_cmp(&category))
+            .ok()
+    }
+
+    fn mean(&self, category: f64) -> f64 {
+        self.index(category).map_or(self.absent, |j| self.means[j])
+    }
+
+    // Returns the category of an encoded value (the first one if the categories share the
+    // value in some folds).
+    fn decode(&self, value: f64) -> f64 {
+        self.out_of_fold
+            .iter()
+            .find_map(|means| means.iter().position(|&m| m == value))
+            .map_or(f64::NAN, |j| self.categories[j])
+    }
+}
+
+// Returns the runs of the consecutive categories in `whole` (the range of the category indices
+// like `ColumnType::Integer`) whose indicators are within the given ranges. The indices between
+// the categories are regarded as the categories whose indicators are all zero.
+fn allowed_runs(
+    indicators: &[Range<f64>],
+    categories: &[f64],
+    whole: &Range<f64>,
+) -> Vec<Range<f64>> {
+    // The indicators are split at `0.5`.
+    let zero = |r: &Range<f64>| r.start < 0.5;
+    let one = |r: &Range<f64>| r.end > 0.5;
+    let ones = indicators.iter().filter(|r| !zero(r)).count();
+    runs(whole, |c| {
+        match categories.binary_search_by(|x| x.total_cmp(&c)) {
+            Ok(k) => one(&indicators[k]) && (ones == 0 || (ones == 1 && !zero(&indicators[k]))),
+            Err(_) => ones == 0,
+        }
+    })
+}
+
+// Returns the runs of the consecutive category indices in `whole` that are allowed.
+pub(crate) fn runs(whole: &Range<f64>, allowed: impl Fn(f64) -> bool) -> Vec<Range<f64>> {
+    let mut runs: Vec<Range<f64>> = Vec::new();
+    let mut c = whole.start + 0.5;
+    while c < whole.end {
+        if allowed(c) {
+            match runs.last_mut() {
+                Some(run) if run.end == c - 0.5 => run.end = c + 0.5,
+                _ => runs.push(c - 0.5..c + 0.5),
+            }
+        }
+        c += 1.0;
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allowed_runs_works() {
+        let whole = -0.5..4.5;
+        let categories = [0.0, 1.0, 3.0, 4.0];
+        let both = -0.5..1.5;
+        let zero = -0.5..0.5;
+        let one = 0.5..1.5;
+
+        let all = [both.clone(), both.clone(), both.clone(), both.clone()];
+        assert_eq!(allowed_runs(&all, &categories, &whole), [-0.5..4.5]);
+
+        // Only the category `1`.
+        let only = [both.clone(), one.clone(), both.clone(), both.clone()];
+        assert_eq!(allowed_runs(&only, &categories, &whole), [0.5..1.5]);
+
+        // All but the category `1` (the absent index `2` is allowed as well).
+        let others = [both.clone(), zero.clone(), both.clone(), both.clone()];
+        assert_eq!(
+            allowed_runs(&others, &categories, &whole),
+            [-0.5..0.5, 1.5..4.5]
+        );
+
+        // Two categories can't be both set.
+        let none = [one.clone(), one, both.clone(), both];
+        assert!(allowed_runs(&none, &categories, &whole).is_empty());
+    }
+
+    #[test]
+    fn decode_row_works() -> anyhow::Result<()> {
+        let xs = [0.5, 1.5, 2.5, 3.5];
+        let cs = [2.0, 0.0, f64::NAN, 2.0];
+        let ys = [1.0, 2.0, 3.0, 4.0];
+        let mut table = Table::new(vec![&xs, &cs, &ys])?;
+        table.set_column_type(1, ColumnType::Categorical)?;
+
+        let (one_hot, encoded) =
+            FeatureEncoder::encode(&table, CategoricalEncoding::OneHot).expect("categorical");
+        assert_eq!(encoded.features_len(), 3);
+        assert_eq!(encoded.column_type(1), ColumnType::Boolean);
+        assert_eq!(encoded.column(2).collect::<Vec<_>>()[..2], [1.0, 0.0]);
+        assert!(encoded.column(2).nth(2).is_some_and(f64::is_nan));
+        assert_eq!(encoded.target().collect::<Vec<_>>(), ys);
+        for row in 0..4 {
+            let encoded_row = (0..3)
+                .map(|i| encoded.column(i).nth(row).expect("row"))
+                .collect::<Vec<_>>();
+            let decoded = one_hot.decode_row(&encoded_row);
+            assert_eq!(decoded[0], xs[row]);
+            assert_eq!(decoded[1].to_bits(), cs[row].to_bits());
+        }
+
+        table.set_column_type(1, ColumnType::Integer)?;
+        assert!(FeatureEncoder::encode(&table, CategoricalEncoding::OneHot).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn target_encoding_works() -> anyhow::Result<()> {
+        let cs = [0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+        let ys = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0];
+        let mut table = Table::new(vec![&cs, &ys])?;
+        table.set_column_type(0, ColumnType::Categorical)?;
+
+        let (encoder, encoded) =
+            FeatureEncoder::encode(&table, CategoricalEncoding::Target).expect("categorical");
+        assert_eq!(encoded.column_type(0), ColumnType::Continuous);
+
+        // The first row (of the first fold) is encoded by the targets `2..=5` of its category and
+        // the prior `6` (the mean of the other folds).
+        let column = encoded.column(0).collect::<Vec<_>>();
+        assert_eq!(column[0], (14.0 + 6.0) / 5.0);
+        assert_eq!(column[5], (34.0 + 6.0) / 5.0);
+        assert_eq!(encoder.decode_row(&[column[5]]), [1.0]);
+        assert!(encoder.decode_row(&[0.0])[0].is_nan());
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+//! The error type covering all the fallible operations of this crate.
+use crate::fanova::FitError;
+use crate::table::TableError;
+use thiserror::Error;
+
+/// Possible errors which could be returned by the operations of this crate.
+///
+/// The errors of the individual steps (`TableError` and `FitError`) are converted into this by
+/// `?`, so that building a table, fitting a model, querying it and restoring it can share a
+/// single error type.
+#[non_exhaustive]
+#[derive(Debug, Error, Clone)]
+pub enum FanovaError {
+    /// The table could not be built (see `Table` and `TableBuilder`).
+    #[error(transparent)]
+    Table(#[from] TableError),
+
+    /// The model could not be fitted (see `FanovaOptions`).
+    #[error(transparent)]
+    Fit(#[from] FitError),
+
+    /// A feature index given to a model is out of range (see `Fanova::try_quantify_importance`).
+    #[error("feature {feature} is out of range for a model of {features_len} features")]
+    FeatureOutOfRange {
+        /// Feature index.
+        feature: usize,
+
+        /// Number of the features of the model.
+        features_len: usize,
+    },
+
+    /// The bytes are not a serialized model (e.g., truncated or serialized by another version of
+    /// this crate, see `Fanova::from_bytes`).
+    #[error("the bytes are not a valid serialized model")]
+    InvalidModel,
+
+    /// The input (e.g., a JSON document) could not be parsed.
+    #[error("{0}")]
+    Parse(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::Table;
+
+    #[test]
+    fn conversion_works() {
+        let build = || -> Result<Table<'static>, FanovaError> { Ok(Table::new(Vec::new())?) };
+        assert!(matches!(
+            build(),
+            Err(FanovaError::Table(TableError::EmptyTable))
+        ));
+
+        let e = FanovaError::from(FitError::ModelMismatch);
+        assert_eq!(e.to_string(), FitError::ModelMismatch.to_string());
+    }
+}
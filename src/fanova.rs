@@ -1,46 +1,80 @@
-use crate::decision_tree::DecisionTreeRegressor;
-use crate::functions;
-use crate::partition::TreePartitions;
-use crate::random_forest::{RandomForestOptions, RandomForestRegressor};
-use crate::space::FeatureSpace;
-use crate::table::{Table, TableError};
+use crate::ablation::{self, AblationStep};
+use crate::categorical::CategoricalFanova;
+use crate::codec::{self, Decoder, Encoder};
+use crate::distill::{self, DistillOptions, ExplanatoryTree};
+use crate::encoding::FeatureEncoder;
+use crate::error::FanovaError;
+use crate::functions::{self, TotalOrder, Welford};
+use crate::morris::{self, ElementaryEffects, MorrisOptions};
+use crate::partition::{Partition, TreePartitions};
+use crate::random_forest::{
+    Ensemble, ExtraTreesOptions, FitQuality, RandomForestOptions, RandomForestRegressor, RngState,
+    SplitCriterion, TreeFitter,
+};
+use crate::shap;
+use crate::simd;
+use crate::sobol::{self, SobolIndices};
+use crate::space::{Domain, FeatureSpace};
+use crate::structure::{self, TreeNode};
+use crate::surrogate::{Surrogate, SurrogateTree};
+use crate::table::{Aggregation, Table, TableError};
+use crate::trace;
+use crate::validation::{self, ExcludedFeature, Issue, ValidationReport};
 use itertools::Itertools as _;
-use js_sys::Array;
-use ordered_float::OrderedFloat;
-use rayon::iter::{IntoParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
-use serde_wasm_bindgen::from_value;
-use std::collections::BTreeMap;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefMutIterator, ParallelIterator,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::num::NonZeroUsize;
 use std::ops::Range;
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
-use wasm_bindgen::prelude::*;
+
+/// The header of the bytes returned by `Fanova::to_bytes`.
+const MODEL_MAGIC: &[u8; 4] = b"FNM8";
 
 /// fANOVA options.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct FanovaOptions {
-    random_forest: RandomForestOptions,
+    surrogate: Arc<dyn Surrogate>,
     parallel: bool,
+    aggregation: Option<Aggregation>,
+    winsorization: Option<(f64, f64)>,
+    standardization: bool,
+    top_quantile: Option<f64>,
+    best_rows: Option<(BestRows, bool)>,
+    early_stopping: Option<EarlyStopping>,
+    time_budget: Option<Duration>,
+    categorical_encoding: CategoricalEncoding,
+    strict: bool,
 }
 
-#[wasm_bindgen]
-pub fn wasm_fanova_calculate(features: Array, targets: Array) -> Vec<f64> {
-    // TODO(c-bata): Fix error handling
-    let features_vec: Vec<Vec<f64>> = features
-        .iter()
-        .map(|x| from_value::<Vec<f64>>(x).unwrap())
-        .collect();
-    let targets_vec: Vec<f64> = targets.iter().map(|x| x.as_f64().unwrap()).collect();
-
-    let mut fanova = FanovaOptions::new()
-        .random_forest(RandomForestOptions::new().seed(0))
-        .fit(
-            features_vec.iter().map(|x| x.as_slice()).collect(),
-            &targets_vec,
-        )
-        .unwrap();
-    let importances = (0..features_vec.len())
-        .map(|i| fanova.quantify_importance(&[i]).mean)
-        .collect::<Vec<_>>();
-    return importances;
+// Stops fitting once the importances converge (see `FanovaOptions::early_stopping`).
+#[derive(Debug, Clone, Copy)]
+struct EarlyStopping {
+    interval: NonZeroUsize,
+    tolerance: f64,
+}
+
+impl Default for FanovaOptions {
+    fn default() -> Self {
+        Self {
+            surrogate: Arc::new(RandomForestOptions::default()),
+            parallel: false,
+            aggregation: None,
+            winsorization: None,
+            standardization: false,
+            top_quantile: None,
+            best_rows: None,
+            early_stopping: None,
+            time_budget: None,
+            categorical_encoding: CategoricalEncoding::default(),
+            strict: false,
+        }
+    }
 }
 
 impl FanovaOptions {
@@ -49,11 +83,27 @@ impl FanovaOptions {
         Self::default()
     }
 
-    /// Sets the `RandomForestOptions`.
+    /// Make `FanovaOptions` of the given preset (see `Preset`).
+    ///
+    /// The other settings are the defaults, and can be changed as usual (use
+    /// `Preset::random_forest` to change the options of the surrogate, e.g., the seed).
+    pub fn preset(preset: Preset) -> Self {
+        Self::new().random_forest(preset.random_forest())
+    }
+
+    /// Uses a random forest of the given options as the surrogate model.
     ///
-    /// The default value is `RandomForestOptions::default()`.
+    /// The default surrogate model is a random forest of `RandomForestOptions::default()`.
     pub fn random_forest(mut self, options: RandomForestOptions) -> Self {
-        self.random_forest = options;
+        self.surrogate = Arc::new(options);
+        self
+    }
+
+    /// Uses extremely randomized trees of the given options as the surrogate model.
+    ///
+    /// Note that `Fanova::fit_quality` returns `None` since the trees have no out-of-bag rows.
+    pub fn extra_trees(mut self, options: ExtraTreesOptions) -> Self {
+        self.surrogate = Arc::new(options);
         self
     }
 
@@ -67,341 +117,4587 @@ impl FanovaOptions {
         self
     }
 
-    /// Builds an fANOVA model for the given features and target.
-    pub fn fit(self, features: Vec<&[f64]>, target: &[f64]) -> Result<Fanova, FitError> {
-        let mut columns = features;
-        columns.push(target);
-        let table = Table::new(columns)?;
-
-        let feature_space = FeatureSpace::from_table(&table);
-
-        let trees = if self.parallel {
-            RandomForestRegressor::fit_parallel(table, self.random_forest)
-                .into_trees()
-                .into_par_iter()
-                .map(|tree| Tree::new(tree, feature_space.clone()))
-                .collect()
-        } else {
-            RandomForestRegressor::fit(table, self.random_forest)
-                .into_trees()
-                .into_iter()
-                .map(|tree| Tree::new(tree, feature_space.clone()))
-                .collect()
-        };
-
-        Ok(Fanova {
-            feature_space,
-            parallel: self.parallel,
-            trees,
-        })
+    /// Merges rows having the identical feature values before fitting.
+    ///
+    /// Otherwise repeated configurations (e.g., of grid searches) are counted multiple times.
+    /// The target value of a merged row is calculated by `aggregation`.
+    /// The number of the merged rows is reported by `Fanova::duplicated_rows`.
+    ///
+    /// The default is not to merge.
+    pub fn aggregate_duplicates(mut self, aggregation: Aggregation) -> Self {
+        self.aggregation = Some(aggregation);
+        self
     }
-}
 
-#[derive(Debug)]
-struct Tree {
-    partitions: TreePartitions,
-    mean: f64,
-    variance: f64,
-    importances: BTreeMap<Vec<usize>, f64>,
-}
+    /// Fits the trees to whether the target of each row is within the lowest `quantile` of the
+    /// targets (`1.0` if so, otherwise `0.0`) instead of the target itself.
+    ///
+    /// The marginal predictions are then the probabilities of landing in the top `quantile`
+    /// (negate the target to maximize it), so the importances measure which features decide
+    /// whether a run is good rather than the variance of the target.
+    ///
+    /// The default is to fit the target itself.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `quantile` is not in the range `(0.0, 1.0]`.
+    pub fn top_quantile(mut self, quantile: f64) -> Self {
+        assert!(
+            quantile > 0.0 && quantile <= 1.0,
+            "quantile must be in the range (0.0, 1.0]"
+        );
+        self.top_quantile = Some(quantile);
+        self
+    }
 
-impl Tree {
-    fn new(regressor: DecisionTreeRegressor, feature_space: FeatureSpace) -> Self {
-        let partitions = TreePartitions::new(&regressor, feature_space);
-        let (mean, variance) = partitions.mean_and_variance();
-        Self {
-            partitions,
-            mean,
-            variance,
-            importances: BTreeMap::new(),
+    /// Fits only the best rows by the target (e.g., the best 10% of the trials) and drops the
+    /// others.
+    ///
+    /// The importances then tell what differentiates the good runs from each other, rather than
+    /// the good runs from the bad ones, and the feature space (over which the marginals are
+    /// taken) is that of the best rows. The best rows have the lowest targets unless `maximize`
+    /// is `true`. Unlike `top_quantile` and `winsorize_target`, the targets of the best rows are
+    /// kept as they are. The rows are selected after `aggregate_duplicates` (ties are broken by
+    /// the order of the rows).
+    ///
+    /// The default is to fit all the rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fraction of `BestRows::Fraction` is not in the range `(0.0, 1.0]`.
+    pub fn best_rows(mut self, rows: BestRows, maximize: bool) -> Self {
+        if let BestRows::Fraction(fraction) = rows {
+            assert!(
+                fraction > 0.0 && fraction <= 1.0,
+                "fraction must be in the range (0.0, 1.0]"
+            );
         }
+        self.best_rows = Some((rows, maximize));
+        self
     }
 
-    fn clear(&mut self) {
-        self.importances.clear();
+    /// Clips the targets to their `lower` and `upper` quantiles (e.g., `0.01` and `0.99`) before
+    /// fitting.
+    ///
+    /// Otherwise, a few catastrophic trials (e.g., diverged ones with huge losses) dominate the
+    /// variance of the targets, and the importances only tell which features cause them. The
+    /// quantiles are taken after `aggregate_duplicates` and the bounds are reported by
+    /// `Fanova::target_bounds`. `top_quantile` is not affected by this.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lower` or `upper` is not in the range `[0.0, 1.0]` or if `lower` is larger
+    /// than `upper`.
+    pub fn winsorize_target(mut self, lower: f64, upper: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&lower) && (0.0..=1.0).contains(&upper) && lower <= upper,
+            "quantiles must be in the range [0.0, 1.0] and in ascending order"
+        );
+        self.winsorization = Some((lower, upper));
+        self
     }
-}
 
-/// fANOVA object.
-#[derive(Debug)]
-pub struct Fanova {
-    trees: Vec<Tree>,
-    feature_space: FeatureSpace,
-    parallel: bool,
-}
+    /// Standardizes the targets (to zero mean and unit variance) before fitting.
+    ///
+    /// The importances are fractions of the variance, which don't depend on the scale of the
+    /// targets anyway, but the other outputs (e.g., the predictions, marginals, SHAP values and
+    /// `FitQuality::rmse`) are then in the standard deviations of the targets, so that they are
+    /// comparable across studies of different objectives. The mean and the standard deviation
+    /// are reported by `Fanova::target_standardization`.
+    ///
+    /// This is applied after `winsorize_target` and `top_quantile`, and is ignored for the
+    /// deviance criteria (see `SplitCriterion::Tweedie`), whose predictions are the logarithms
+    /// of the targets (so scaling the targets only shifts them).
+    pub fn standardize_target(mut self) -> Self {
+        self.standardization = true;
+        self
+    }
 
-impl Fanova {
-    /// Builds an fANOVA model for the given features and target.
+    /// Stops adding trees once the importances converge.
     ///
-    /// This is equivalent to `FanovaOptions::new().fit(features, target)`.
-    pub fn fit(features: Vec<&[f64]>, target: &[f64]) -> Result<Self, FitError> {
-        FanovaOptions::default().fit(features, target)
+    /// The importance of each feature is recomputed after every `interval` trees, and the
+    /// fitting stops if no importance has changed by more than `tolerance` since the previous
+    /// check and no pair of features has swapped its ranking (unless their importances are
+    /// within `tolerance`). `Fanova::trees_len` returns the number of the fitted trees.
+    ///
+    /// Note that the trees are fitted sequentially even if `parallel` is set.
+    ///
+    /// The default is to fit all the trees of the surrogate model.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tolerance` is negative or NaN.
+    pub fn early_stopping(mut self, interval: NonZeroUsize, tolerance: f64) -> Self {
+        assert!(tolerance >= 0.0, "tolerance must be non-negative");
+        self.early_stopping = Some(EarlyStopping {
+            interval,
+            tolerance,
+        });
+        self
     }
 
-    /// Calculates the importance of the given features.
-    pub fn quantify_importance(&mut self, features: &[usize]) -> Importance {
-        if features
-            .iter()
-            .any(|&f| f >= self.feature_space.ranges().len())
-        {
-            return Importance {
-                mean: 0.0,
-                stddev: 0.0,
-            };
-        }
+    /// Stops adding trees once `budget` has elapsed since the fitting started (at least one tree
+    /// is fitted), so that the fitting returns within a bounded latency.
+    ///
+    /// `Fanova::is_partial` tells whether the budget has cut the fitting short and
+    /// `Fanova::trees_len` returns the number of the fitted trees. The importances of a partial
+    /// model are noisier (and not reproducible, as the number of the trees depends on the
+    /// speed of the machine).
+    ///
+    /// Note that the trees are fitted sequentially even if `parallel` is set.
+    ///
+    /// The default is to fit all the trees of the surrogate model.
+    pub fn time_budget(mut self, budget: Duration) -> Self {
+        self.time_budget = Some(budget);
+        self
+    }
 
-        let mut trees = std::mem::take(&mut self.trees);
-        let importances = if self.parallel {
-            trees
-                .par_iter_mut()
-                .map(|tree| self.quantify_importance_tree(tree, features))
-                .collect::<Vec<_>>()
-        } else {
-            trees
-                .iter_mut()
-                .map(|tree| self.quantify_importance_tree(tree, features))
-                .collect::<Vec<_>>()
-        };
-        self.trees = trees;
+    /// Sets how the `ColumnType::Categorical` features are encoded for the surrogate model.
+    ///
+    /// The default is `CategoricalEncoding::Ordinal`.
+    pub fn categorical_encoding(mut self, encoding: CategoricalEncoding) -> Self {
+        self.categorical_encoding = encoding;
+        self
+    }
 
-        let (mean, stddev) = functions::mean_and_stddev(importances.into_iter());
-        Importance { mean, stddev }
+    /// Refuses to fit if `validate` finds any issue in the input data.
+    ///
+    /// Duplicated rows are not regarded as an issue if `aggregate_duplicates` is set.
+    /// The default is to fit unless the data is invalid (e.g., non-finite targets).
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
     }
 
-    /// Clears the internal cache.
-    pub fn clear(&mut self) {
-        for t in &mut self.trees {
-            t.clear();
+    /// Builds an fANOVA model for the given features and target.
+    pub fn fit(self, features: Vec<&[f64]>, target: &[f64]) -> Result<Fanova, FitError> {
+        if self.strict {
+            self.check(validation::validate(&features, target))?;
         }
+        let mut columns = features;
+        columns.push(target);
+        let table = Table::new(columns)?;
+        self.fit_checked_table(table)
     }
 
-    #[cfg(test)]
-    fn feature_combinations(&self, k: usize) -> impl Iterator<Item = Vec<usize>> {
-        let features = self.feature_space.ranges().len();
-        (1..=k).flat_map(move |k| (0..features).combinations(k))
+    /// Fits the remaining trees of `model` in place, which has been fitted to `table` with these
+    /// options but cut short (e.g., by `time_budget`, or saved by `Fanova::to_bytes` before an
+    /// interruption).
+    ///
+    /// The fitting continues the random sequence of `model` (see `Fanova::rng_state`), so the
+    /// result (including `Fanova::fit_quality`) is identical to the model fitted without the
+    /// interruption, even if the surrogate model is given no seed. `time_budget` applies to the
+    /// resumed fitting, which can thus be repeated until `Fanova::is_partial` returns `false`.
+    ///
+    /// Fails with `FitError::ModelMismatch` if `model` has been fitted to a different table, with
+    /// different options or has been modified (e.g., by `Fanova::restrict`). `model` is left
+    /// unchanged then.
+    pub fn resume(self, table: Table, model: &mut Fanova) -> Result<(), FitError> {
+        self.check_target(&table)?;
+        *model = FanovaFitter::resume(self, table, model)?.finish();
+        Ok(())
     }
 
-    fn traverse_covered_subspaces<F>(
-        &self,
-        marginal_value_index: usize,
-        partition: &crate::partition::Partition,
-        feature_subspaces: &[(usize, Vec<Range<f64>>)],
-        f: &mut F,
-    ) where
-        F: FnMut(usize),
-    {
-        if feature_subspaces.is_empty() {
-            f(marginal_value_index);
-            return;
+    /// Builds an fANOVA model for the given table.
+    pub fn fit_table(self, table: Table) -> Result<Fanova, FitError> {
+        if self.strict {
+            let features = (0..table.features_len())
+                .map(|i| table.column(i).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            let features = features.iter().map(|c| &c[..]).collect::<Vec<_>>();
+            let target = table.target().collect::<Vec<_>>();
+            self.check(validation::validate(&features, &target))?;
         }
+        self.fit_checked_table(table)
+    }
 
-        let (feature, subspaces) = &feature_subspaces[0];
-        let range = partition.space.ranges()[*feature].clone();
-        let start = subspaces
-            .binary_search_by_key(&OrderedFloat(range.start), |x| OrderedFloat(x.start))
-            .unwrap_or_else(|index| index);
+    /// Tests the significance of the importance of each feature against its null importances.
+    ///
+    /// The null importances are the importances of the models fitted to `table` whose target is
+    /// randomly permuted (`permutations` times with `seed`), which break the relationship
+    /// between the features and the target while keeping their distributions. Features whose
+    /// importances are not larger than most of the null importances are likely to be noise,
+    /// which is common in small studies.
+    ///
+    /// Note that this fits `permutations + 1` models.
+    pub fn test_significance(
+        self,
+        table: Table,
+        permutations: NonZeroUsize,
+        seed: u64,
+    ) -> Result<Vec<Significance>, FitError> {
+        let observed = self.clone().fit_table(table.clone())?.importances();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut nulls = vec![Vec::with_capacity(permutations.get()); observed.len()];
+        for _ in 0..permutations.get() {
+            let mut shuffled = table.clone();
+            shuffled.shuffle_target(&mut rng);
+            let importances = self.clone().fit_table(shuffled)?.importances();
+            for (null, importance) in nulls.iter_mut().zip(importances) {
+                null.push(importance.mean);
+            }
+        }
+        Ok(observed
+            .into_iter()
+            .zip(nulls)
+            .map(|(importance, null)| Significance::new(importance.mean, &null))
+            .collect())
+    }
 
-        for i in (start..subspaces.len()).take_while(|&i| subspaces[i].end <= range.end) {
-            self.traverse_covered_subspaces(
-                marginal_value_index * subspaces.len() + i,
-                partition,
-                &feature_subspaces[1..],
-                f,
-            );
+    /// Estimates how well the surrogate model generalizes by k-fold cross-validation.
+    ///
+    /// The rows of `table` are randomly assigned to `folds` folds with `seed` (see
+    /// `Table::folds`), and a model fitted to the rows of the other folds predicts the targets of
+    /// each fold. Unlike `Fanova::fit_quality`, this also works for the surrogates without
+    /// out-of-bag rows (e.g., extra trees).
+    ///
+    /// Note that this fits `folds` models.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `folds` is less than `2`.
+    pub fn cross_validate(
+        self,
+        table: Table,
+        folds: usize,
+        seed: u64,
+    ) -> Result<CrossValidation, FitError> {
+        let criterion = self.criterion(&table);
+        let mut all = Vec::with_capacity(table.rows_len());
+        let mut qualities = Vec::with_capacity(folds);
+        for (train, test) in table.folds(folds, seed)? {
+            let fanova = self.clone().fit_table(train)?;
+            let predictions = fanova.holdout_predictions(criterion, &test);
+            qualities.push(FitQuality::from_predictions(&predictions).expect("non empty fold"));
+            all.extend(predictions);
         }
+        Ok(CrossValidation {
+            overall: FitQuality::from_predictions(&all).expect("non empty table"),
+            folds: qualities,
+        })
     }
 
-    fn quantify_importance_tree(&self, tree: &mut Tree, features: &[usize]) -> f64 {
-        if let Some(&importance) = tree.importances.get(features) {
-            return importance;
+    /// Estimates the importance of each feature by refitting the model without it (i.e., the
+    /// leave-one-covariate-out importance).
+    ///
+    /// The models are scored by the predictions of `holdout` (which has the same features as
+    /// `table`), or by their out-of-bag predictions if it is `None` (see `Fanova::fit_quality`).
+    /// Unlike the fANOVA importances, this measures what the model loses without a feature, so
+    /// a feature whose effect can be substituted by correlated features is not important. This
+    /// is a check when the fANOVA importances disagree with other importances.
+    ///
+    /// Note that this fits `features + 1` models. The table is presorted once and the refits
+    /// share its sorted orders.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FitError::NoOutOfBagRows` if `holdout` is `None` and the surrogate has no
+    /// out-of-bag rows (e.g., extra trees).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `table` has less than two features or `holdout` has less features than `table`.
+    pub fn drop_column_importances(
+        self,
+        mut table: Table,
+        holdout: Option<&Table>,
+    ) -> Result<DropColumnImportances, FitError> {
+        let features_len = table.features_len();
+        assert!(features_len >= 2, "two or more features are required");
+        if self.surrogate.ensemble(&table).presort() {
+            table.presort();
         }
+        let criterion = self.criterion(&table);
+        let score = |table: Table, features: &[usize]| {
+            let fanova = self.clone().fit_table(table)?;
+            match holdout {
+                Some(holdout) => {
+                    let predictions =
+                        fanova.holdout_predictions(criterion, &holdout.select_columns(features));
+                    FitQuality::from_predictions(&predictions).ok_or(FitError::EmptyRows)
+                }
+                None => fanova.fit_quality().ok_or(FitError::NoOutOfBagRows),
+            }
+        };
 
-        let feature_subspaces = features
-            .iter()
-            .copied()
+        let all = (0..features_len).collect::<Vec<_>>();
+        let baseline = score(table.clone(), &all)?;
+        let dropped = (0..features_len)
             .map(|i| {
-                let subspaces =
-                    subspaces(tree.partitions.iter().map(|p| p.space.ranges()[i].clone()));
-                (i, subspaces)
+                let others = all.iter().copied().filter(|&j| j != i).collect::<Vec<_>>();
+                score(table.select_columns(&others), &others)
             })
-            .collect::<Vec<_>>();
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(DropColumnImportances { baseline, dropped })
+    }
 
-        let overall_marginal_size = self.feature_space.marginal_size(features);
-        let mut marginal_values =
-            vec![0.0; feature_subspaces.iter().map(|(_, s)| s.len()).product()];
+    /// Fits a model to the rows of `table` within `domain` and restricts it to the domain (see
+    /// `Fanova::restrict`).
+    ///
+    /// Unlike restricting a model fitted to all the rows, the trees are then fitted only
+    /// around the region (e.g., with more splits in it). The rows whose restricted features are
+    /// NaN (i.e., inactive) are not within the domain, and `FitError::EmptyRows` is returned if
+    /// no rows are.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `domain` is invalid as described in `Fanova::restrict`.
+    pub fn fit_restricted(
+        self,
+        table: Table,
+        domain: &[Option<Vec<Range<f64>>>],
+    ) -> Result<Fanova, FitError> {
+        assert_eq!(
+            domain.len(),
+            table.features_len(),
+            "a domain must be given for each feature"
+        );
+        let rows = table.rows_within(domain);
+        if rows.is_empty() {
+            return Err(FitError::EmptyRows);
+        }
+        Ok(self.fit_table(table.select_rows(&rows))?.restrict(domain))
+    }
 
-        for p in tree.partitions.iter() {
-            let partition_marginal_size = p.space.marginal_size(features);
-            let weighted_value = p.value * (partition_marginal_size / overall_marginal_size);
+    /// Builds fANOVA models of a categorical target (e.g., the statuses of trials).
+    ///
+    /// `classes` are the class labels of the rows. A model is fitted to the indicator of each
+    /// class, so that it estimates the probability of the class, and the importances decompose
+    /// the variance of the class probabilities (see `CategoricalFanova`). `top_quantile`,
+    /// `best_rows`, `winsorize_target` and `standardize_target` are ignored.
+    ///
+    /// Note that this fits as many models as the distinct labels.
+    pub fn fit_categorical(
+        mut self,
+        features: Vec<&[f64]>,
+        classes: &[usize],
+    ) -> Result<CategoricalFanova, FitError> {
+        let labels = classes.iter().copied().collect::<BTreeSet<_>>();
+        if labels.is_empty() {
+            return Err(FitError::EmptyRows);
+        }
+        if labels.len() < 2 {
+            return Err(FitError::SingleClass);
+        }
+        self.top_quantile = None;
+        self.best_rows = None;
+        self.winsorization = None;
+        self.standardization = false;
+        let models = labels
+            .into_iter()
+            .map(|label| {
+                let target = classes
+                    .iter()
+                    .map(|&c| if c == label { 1.0 } else { 0.0 })
+                    .collect::<Vec<_>>();
+                Ok((label, self.clone().fit(features.clone(), &target)?))
+            })
+            .collect::<Result<Vec<_>, FitError>>()?;
+        Ok(CategoricalFanova::new(models))
+    }
 
-            self.traverse_covered_subspaces(0, p, &feature_subspaces, &mut |index| {
-                marginal_values[index] += weighted_value
-            });
+    fn check(&self, mut report: ValidationReport) -> Result<(), FitError> {
+        if self.aggregation.is_some() {
+            report.retain(|issue| !matches!(issue, Issue::DuplicatedRows { .. }));
+        }
+        if report.is_clean() {
+            Ok(())
+        } else {
+            Err(FitError::Validation(report))
         }
+    }
 
-        let mut variance = 0.0;
-        for (mut i, v) in marginal_values.into_iter().enumerate() {
-            let mut weight = 1.0;
-            for (_, subspaces) in feature_subspaces.iter().rev() {
-                let j = i % subspaces.len();
-                i /= subspaces.len();
-                weight *= subspaces[j].end - subspaces[j].start;
-            }
+    // Returns the split criterion of the surrogate for `table`.
+    fn criterion(&self, table: &Table) -> SplitCriterion {
+        self.surrogate.ensemble(table).tree_options.criterion
+    }
 
-            variance += (v - tree.mean).powi(2) * weight;
+    // Checks that the targets are in the domain of the deviance criteria (see
+    // `SplitCriterion::Tweedie`).
+    pub(crate) fn check_target(&self, table: &Table) -> Result<(), FitError> {
+        let Some(power) = self.criterion(table).tweedie_power() else {
+            return Ok(());
+        };
+        let valid = if self.top_quantile.is_some() {
+            // The indicators of the top quantile have zeros.
+            power < 2.0
+        } else if power < 2.0 {
+            table.target().all(|y| y >= 0.0) && table.target().any(|y| y > 0.0)
+        } else {
+            table.target().all(|y| y > 0.0)
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(FitError::TargetOutOfDomain)
         }
+    }
 
-        let size = self.feature_space.partial_size(features);
-        let mut importance = variance / size / tree.variance;
-        for k in 1..features.len() {
-            for sub_features in features.iter().copied().combinations(k) {
-                importance -= self.quantify_importance_tree(tree, &sub_features);
-            }
+    fn fit_checked_table(self, table: Table) -> Result<Fanova, FitError> {
+        self.check_target(&table)?;
+        if !self.parallel || self.early_stopping.is_some() || self.time_budget.is_some() {
+            return Ok(FanovaFitter::new(self, table).finish());
         }
 
-        tree.importances.insert(features.to_owned(), importance);
-        importance
+        let start = trace::now_millis();
+        let prepared = self.prepare(table);
+        let encoding = trace::now_millis() - start;
+        let (feature_space, target_scale) = (prepared.feature_space, prepared.target_scale);
+        let forest = RandomForestRegressor::fit_parallel(prepared.table, &prepared.surrogate);
+        let fit_quality = forest.fit_quality().map(|q| target_scale.invert_quality(q));
+        let rng_state = forest.rng_state();
+        let (sorting, tree_millis) = (forest.sorting_millis(), forest.tree_millis().to_vec());
+        let feature_encoder = prepared.feature_encoder.as_ref();
+        let (trees, conversion_millis): (Vec<_>, Vec<_>) = forest
+            .into_trees()
+            .into_par_iter()
+            .map(|tree| {
+                let start = trace::now_millis();
+                let tree = Tree::new(&tree, feature_space.clone(), feature_encoder);
+                (tree, trace::now_millis() - start)
+            })
+            .unzip();
+        let profile = Profile {
+            encoding,
+            sorting,
+            trees: tree_millis
+                .iter()
+                .zip(conversion_millis)
+                .map(|(fit, conversion)| fit + conversion)
+                .collect(),
+            marginals: 0.0,
+        };
+        Ok(Fanova {
+            profile,
+            feature_space,
+            parallel: self.parallel,
+            target_scale,
+            target_adjustments: prepared.target_adjustments,
+            fit_quality,
+            excluded_features: prepared.excluded_features,
+            partial: false,
+            rng_state,
+            trees,
+        })
     }
-}
 
-/// Importance of a feature set.
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct Importance {
-    /// Average of importances across random forest trees.
-    pub mean: f64,
+    // Preprocesses `table` and encodes its categorical features for the surrogate model.
+    fn prepare<'a>(&self, table: Table<'a>) -> Prepared<'a, '_> {
+        let (table, target_scale, target_adjustments) = self.preprocess(table);
+        let feature_space = FeatureSpace::from_table(&table);
+        let mut surrogate = Screened::new(&*self.surrogate, &table);
+        surrogate.categorical_splits = self.categorical_encoding == CategoricalEncoding::Native;
+        let excluded_features = surrogate.excluded.clone();
+        let (table, surrogate, feature_encoder) =
+            match FeatureEncoder::encode(&table, self.categorical_encoding) {
+                Some((feature_encoder, encoded)) => {
+                    let surrogate = Screened::new(&*self.surrogate, &encoded);
+                    (encoded, surrogate, Some(feature_encoder))
+                }
+                None => (table, surrogate, None),
+            };
+        Prepared {
+            table,
+            surrogate,
+            feature_encoder,
+            feature_space,
+            target_scale,
+            target_adjustments,
+            excluded_features,
+        }
+    }
 
-    /// Standard deviation of importances across random forest trees.
-    pub stddev: f64,
+    // Returns the table to be fitted, the scale of its targets and the adjustments applied to
+    // them.
+    fn preprocess<'a>(&self, table: Table<'a>) -> (Table<'a>, TargetScale, TargetAdjustments) {
+        let duplicated_rows = table.duplicated_rows_len();
+        let mut table = if let Some(aggregation) = self.aggregation {
+            table.aggregate_duplicates(aggregation)
+        } else {
+            table
+        };
+        if let Some((rows, maximize)) = self.best_rows {
+            let mut positions = (0..table.rows_len()).collect::<Vec<_>>();
+            let targets = table.target().collect::<Vec<_>>();
+            positions.sort_by(|&i, &j| {
+                let order = targets[i].total_cmp(&targets[j]);
+                if maximize {
+                    order.reverse()
+                } else {
+                    order
+                }
+            });
+            positions.truncate(rows.len(positions.len()));
+            positions.sort_unstable();
+            table.retain_rows(&positions);
+        }
+        let bounds = self.winsorization.map(|(lower, upper)| {
+            let mut targets = table.target().collect::<Vec<_>>();
+            targets.sort_by(f64::total_cmp);
+            let bounds = (
+                functions::quantile_sorted(&targets, lower),
+                functions::quantile_sorted(&targets, upper),
+            );
+            table.map_target(|y| y.clamp(bounds.0, bounds.1));
+            bounds
+        });
+        if let Some(quantile) = self.top_quantile {
+            let threshold = functions::quantile(table.target(), quantile);
+            table.map_target(|y| if y <= threshold { 1.0 } else { 0.0 });
+        }
+        let deviance = self.criterion(&table).tweedie_power().is_some();
+        let standardization =
+            (self.standardization && !deviance).then(|| functions::mean_and_stddev(table.target()));
+        let adjustments = TargetAdjustments {
+            bounds,
+            standardization,
+            duplicated_rows,
+        };
+        if standardization.is_some() {
+            table.map_target(|y| adjustments.standardize(y));
+        }
+        // Shifted targets may be out of the domain of the deviance criteria (and their leaf
+        // values are logarithms, which are not affine in the targets).
+        let target_scale = if deviance {
+            TargetScale::IDENTITY
+        } else {
+            TargetScale::detect(&table)
+        };
+        if target_scale != TargetScale::IDENTITY {
+            table.map_target(|y| target_scale.apply(y));
+        }
+        (table, target_scale, adjustments)
+    }
 }
 
-/// Possible errors which could be returned by `Fanove::fit` method.
+/// Named configurations of fANOVA (see `FanovaOptions::preset`).
 #[non_exhaustive]
-#[derive(Debug, Error, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// The defaults of the reference implementation of fANOVA (the `fanova` Python package by
+    /// the authors of the paper), so that the importances are comparable with those obtained
+    /// from it.
+    ///
+    /// The random forest has 16 trees fitted to bootstrapped rows, each of whose nodes searches
+    /// all the features (rather than the square root of their number) with at least one row at
+    /// each leaf and the maximum depth of 64. The targets are not clipped, like the default
+    /// cutoffs `(-inf, inf)` of the reference. Note that the importances agree up to the noise
+    /// of the forests, since the random sequences differ from the reference.
+    ReferenceFanova,
+}
+
+impl Preset {
+    /// Returns the options of the random forest of this preset.
+    pub fn random_forest(self) -> RandomForestOptions {
+        match self {
+            Self::ReferenceFanova => RandomForestOptions::new()
+                .trees(NonZeroUsize::new(16).expect("non zero"))
+                .max_features(NonZeroUsize::MAX)
+                .max_depth(NonZeroUsize::new(64).expect("non zero"))
+                .min_samples_leaf(NonZeroUsize::MIN),
+        }
+    }
+}
+
+/// Encoding of the categorical features for the surrogate model (see
+/// `ColumnType::Categorical`).
+///
+/// Either way, the model is defined over the indices of the categories, in which each category
+/// has the same weight when marginalizing, so the importances and the other outputs are those
+/// of the original features.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CategoricalEncoding {
+    /// Splits the indices of the categories like integers, i.e., each split separates the
+    /// categories whose indices are below a threshold from the others.
+    ///
+    /// This is suitable if the indices follow a meaningful order of the categories.
+    #[default]
+    Ordinal,
+
+    /// Replaces each categorical feature by the boolean indicators of its categories, i.e., each
+    /// split separates a category from the others regardless of their indices.
+    ///
+    /// The indicators share the candidates of the splits (see `RandomForestOptions::max_features`)
+    /// with the other features, and the trees are converted back into the original features.
+    OneHot,
+
+    /// Replaces each categorical feature by the (smoothed) target means of its categories, i.e.,
+    /// each split separates the categories whose means are below a threshold from the others.
+    ///
+    /// This is suitable for the features of many categories (e.g., the names of architectures),
+    /// which would take many ordinal splits or indicators. To avoid fitting the trees to the
+    /// targets leaking through the means, the rows are assigned to five folds in turn and each
+    /// row is encoded by the means over the rows of the other folds. The trees are converted
+    /// back into the original features by the means over all the rows, so the fit quality of a
+    /// resumed fitting (see `FanovaOptions::resume`) is an approximation.
+    Target,
+
+    /// Splits each categorical feature into two arbitrary subsets of its categories.
+    ///
+    /// The categories of a node are ordered by the means of their targets, and the best
+    /// threshold of that order is taken, which is the best of all the subsets for the squared
+    /// error. Unlike `Target`, the order is computed in each node from its own rows, and nothing
+    /// is encoded. A leaf covers a subset of the categories (rather than an interval of their
+    /// indices), whose size fraction is the number of the categories in the subset divided by
+    /// the number of all the indices, so the marginalization is exact. The indices which don't
+    /// appear in a node go to the right child.
+    ///
+    /// `RandomForestOptions::max_bins` and `RandomForestOptions::max_thresholds` don't apply to
+    /// the categorical features, and `ExtraTreesOptions` takes a random threshold of the order.
+    Native,
+}
+
+/// Selection of the best rows to be fitted (see `FanovaOptions::best_rows`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BestRows {
+    /// The given number of the best rows (or all the rows if there are fewer).
+    Count(NonZeroUsize),
+
+    /// The given fraction (in `(0.0, 1.0]`) of the rows, rounded up.
+    Fraction(f64),
+}
+
+impl BestRows {
+    // Returns the number of the selected rows out of `rows_len` rows.
+    fn len(self, rows_len: usize) -> usize {
+        match self {
+            Self::Count(n) => n.get().min(rows_len),
+            Self::Fraction(fraction) => {
+                ((rows_len as f64 * fraction).ceil() as usize).min(rows_len)
+            }
+        }
+    }
+}
+
+// The table to be fitted and its metadata (see `FanovaOptions::prepare`).
+struct Prepared<'a, 'b> {
+    // The preprocessed table (whose categorical features are encoded if `feature_encoder` is given).
+    table: Table<'a>,
+    surrogate: Screened<'b>,
+    feature_encoder: Option<FeatureEncoder>,
+
+    // The feature space and the excluded features of the preprocessed table before the encoding.
+    feature_space: FeatureSpace,
+    target_scale: TargetScale,
+    target_adjustments: TargetAdjustments,
+    excluded_features: Vec<ExcludedFeature>,
+}
+
+// The adjustments of the targets applied before fitting (see `FanovaOptions::preprocess`).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct TargetAdjustments {
+    // The lower and upper bounds of the winsorization.
+    bounds: Option<(f64, f64)>,
+
+    // The mean and the standard deviation of the standardization.
+    standardization: Option<(f64, f64)>,
+
+    // The number of the rows duplicating the features of earlier rows.
+    duplicated_rows: usize,
+}
+
+impl TargetAdjustments {
+    fn standardize(self, y: f64) -> f64 {
+        match self.standardization {
+            Some((mean, stddev)) if stddev > 0.0 => (y - mean) / stddev,
+            Some((mean, _)) => y - mean,
+            None => y,
+        }
+    }
+
+    fn unstandardize(self, y: f64) -> f64 {
+        match self.standardization {
+            Some((mean, stddev)) if stddev > 0.0 => y * stddev + mean,
+            Some((mean, _)) => y + mean,
+            None => y,
+        }
+    }
+
+    fn encode(&self, encoder: &mut Encoder) {
+        for pair in [self.bounds, self.standardization] {
+            encoder.u8(u8::from(pair.is_some()));
+            if let Some((a, b)) = pair {
+                encoder.f64(a);
+                encoder.f64(b);
+            }
+        }
+        encoder.usize(self.duplicated_rows);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        let mut decode_pair = || -> Option<Option<(f64, f64)>> {
+            if decoder.u8()? != 0 {
+                Some(Some((decoder.f64()?, decoder.f64()?)))
+            } else {
+                Some(None)
+            }
+        };
+        Some(Self {
+            bounds: decode_pair()?,
+            standardization: decode_pair()?,
+            duplicated_rows: decoder.usize()?,
+        })
+    }
+}
+
+/// The surrogate model whose trees don't split the constant and duplicated features.
+///
+/// Constant features have no thresholds anyway, but they would take the places of the candidate
+/// features at each split. A duplicated feature would share the importance of its original
+/// feature at random, so only the original one is kept.
+#[derive(Debug)]
+struct Screened<'a> {
+    surrogate: &'a dyn Surrogate,
+    excluded: Vec<ExcludedFeature>,
+
+    // Splits the categorical features into subsets (see `CategoricalEncoding::Native`).
+    categorical_splits: bool,
+}
+
+impl<'a> Screened<'a> {
+    fn new(surrogate: &'a dyn Surrogate, table: &Table) -> Self {
+        let columns = (0..table.features_len()).map(|i| table.column(i));
+        Self {
+            surrogate,
+            excluded: validation::screen_features(columns),
+            categorical_splits: false,
+        }
+    }
+}
+
+impl Surrogate for Screened<'_> {
+    fn ensemble(&self, table: &Table) -> Ensemble {
+        let mut ensemble = self.surrogate.ensemble(table);
+        ensemble.tree_options.excluded_columns =
+            self.excluded.iter().map(ExcludedFeature::feature).collect();
+        ensemble.tree_options.categorical_splits = self.categorical_splits;
+        ensemble
+    }
+}
+
+/// Builds an fANOVA model incrementally (one tree per step).
+///
+/// This is used to fit a model without blocking the JavaScript event loop for a long time.
+/// Note that `FanovaOptions::parallel` is ignored while fitting.
+#[derive(Debug)]
+pub(crate) struct FanovaFitter<'a> {
+    fitter: TreeFitter<'a>,
+    feature_space: FeatureSpace,
+    parallel: bool,
+    target_scale: TargetScale,
+    target_adjustments: TargetAdjustments,
+    excluded_features: Vec<ExcludedFeature>,
+    feature_encoder: Option<FeatureEncoder>,
+    trees: Vec<Tree>,
+    early_stopping: Option<EarlyStopping>,
+    profile: Profile,
+
+    // The time (see `trace::now_millis`) after which no tree is added, and whether it has cut
+    // the fitting short.
+    deadline: Option<f64>,
+    partial: bool,
+
+    // The sums of the importances of the features across the trees and their means at the last
+    // check (for early stopping).
+    importance_sums: Vec<f64>,
+    last_importances: Option<Vec<f64>>,
+}
+
+impl<'a> FanovaFitter<'a> {
+    pub fn new(options: FanovaOptions, table: Table<'a>) -> Self {
+        Self::start(options, table, None).expect("no model to be matched")
+    }
+
+    /// Resumes the fitting of `model` (see `FanovaOptions::resume`).
+    ///
+    /// The trees of `model` are moved into the fitter unless this fails.
+    pub fn resume(
+        options: FanovaOptions,
+        table: Table<'a>,
+        model: &mut Fanova,
+    ) -> Result<Self, FitError> {
+        Self::start(options, table, Some(model))
+    }
+
+    fn start(
+        options: FanovaOptions,
+        table: Table<'a>,
+        model: Option<&mut Fanova>,
+    ) -> Result<Self, FitError> {
+        let deadline = options
+            .time_budget
+            .map(|budget| trace::now_millis() + budget.as_secs_f64() * 1000.0);
+        let start = trace::now_millis();
+        let Prepared {
+            table,
+            surrogate,
+            feature_encoder,
+            feature_space,
+            target_scale,
+            target_adjustments,
+            excluded_features,
+        } = options.prepare(table);
+        let encoding = trace::now_millis() - start;
+        let fitter = match model.as_deref() {
+            None => TreeFitter::new(table, &surrogate),
+            Some(model) => {
+                let state = model.rng_state;
+                let matched = feature_space.ranges() == model.feature_space.ranges()
+                    && target_scale == model.target_scale
+                    && target_adjustments == model.target_adjustments
+                    && state.trees == model.trees.len()
+                    && state.trees <= surrogate.ensemble(&table).trees.get();
+                if !matched {
+                    return Err(FitError::ModelMismatch);
+                }
+                TreeFitter::resume(table, &surrogate, state, |tree, xs| {
+                    let partitions = &model.trees[tree].partitions;
+                    match &feature_encoder {
+                        Some(encoder) => partitions.predict_as_tree(&encoder.decode_row(xs)),
+                        None => partitions.predict_as_tree(xs),
+                    }
+                })
+            }
+        };
+        let profile = Profile {
+            encoding,
+            sorting: fitter.sorting_millis(),
+            trees: Vec::new(),
+            marginals: 0.0,
+        };
+        let mut this = Self {
+            profile,
+            excluded_features,
+            feature_encoder,
+            trees: Vec::with_capacity(fitter.len()),
+            importance_sums: vec![0.0; feature_space.ranges().len()],
+            fitter,
+            feature_space,
+            parallel: options.parallel,
+            target_scale,
+            target_adjustments,
+            early_stopping: options.early_stopping,
+            deadline,
+            partial: false,
+            last_importances: None,
+        };
+        // The convergence checks of early stopping are replayed as well.
+        if let Some(model) = model {
+            this.profile.trees = std::mem::take(&mut model.profile.trees);
+            for tree in std::mem::take(&mut model.trees) {
+                this.push(tree);
+            }
+        }
+        Ok(this)
+    }
+
+    /// Fits the next tree and returns `false` if all trees have already been fitted (or the
+    /// fitting has stopped early).
+    pub fn step(&mut self) -> bool {
+        let start = trace::now_millis();
+        if let Some(tree) = self.fitter.next() {
+            let tree = Tree::new(
+                &tree,
+                self.feature_space.clone(),
+                self.feature_encoder.as_ref(),
+            );
+            self.push(tree);
+            self.profile.trees.push(trace::now_millis() - start);
+            if self.fitter.len() > 0 && self.deadline.is_some_and(|d| trace::now_millis() >= d) {
+                self.fitter.stop();
+                self.partial = true;
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    // Adds a fitted tree and stops fitting if the importances have converged.
+    fn push(&mut self, mut tree: Tree) {
+        let Some(early_stopping) = self.early_stopping else {
+            self.trees.push(tree);
+            return;
+        };
+        for (i, sum) in self.importance_sums.iter_mut().enumerate() {
+            *sum += Fanova::quantify_importance_tree(&self.feature_space, &mut tree, &[i]);
+        }
+        self.trees.push(tree);
+        if self
+            .trees
+            .len()
+            .is_multiple_of(early_stopping.interval.get())
+            && self.converged()
+        {
+            self.fitter.stop();
+        }
+    }
+
+    // Checks whether the mean importances have converged since the last check.
+    fn converged(&mut self) -> bool {
+        let tolerance = self.early_stopping.map_or(0.0, |e| e.tolerance);
+        let n = self.trees.len() as f64;
+        let current = self
+            .importance_sums
+            .iter()
+            .map(|sum| sum / n)
+            .collect::<Vec<_>>();
+        let Some(last) = self.last_importances.replace(current.clone()) else {
+            return false;
+        };
+        let values_stable = current
+            .iter()
+            .zip(&last)
+            .all(|(x, y)| (x - y).abs() <= tolerance);
+        let ranking_stable = (0..current.len()).tuple_combinations().all(|(i, j)| {
+            let diff = current[i] - current[j];
+            diff * (last[i] - last[j]) >= 0.0 || diff.abs() <= tolerance
+        });
+        values_stable && ranking_stable
+    }
+
+    /// Returns the number of fitted trees and the total number of trees.
+    pub fn progress(&self) -> (usize, usize) {
+        let done = self.trees.len();
+        (done, done + self.fitter.len())
+    }
+
+    /// Finishes fitting (the remaining trees are fitted if exist and the time budget allows).
+    pub fn finish(mut self) -> Fanova {
+        while self.step() {}
+        let fit_quality = self.fitter.fit_quality();
+        Fanova {
+            profile: self.profile,
+            feature_space: self.feature_space,
+            parallel: self.parallel,
+            target_scale: self.target_scale,
+            target_adjustments: self.target_adjustments,
+            fit_quality: fit_quality.map(|q| self.target_scale.invert_quality(q)),
+            excluded_features: self.excluded_features,
+            partial: self.partial,
+            rng_state: self.fitter.rng_state(),
+            trees: self.trees,
+        }
+    }
+}
+
+// Targets whose range or offset (relative to the range) exceeds this magnitude are rescaled.
+const MAX_TARGET_MAGNITUDE: f64 = 1e8;
+
+/// Affine transform of the targets (`(y - offset) / scale`) applied before fitting.
+///
+/// Targets of extreme magnitudes (e.g., `1e200` or `1e12 + tiny differences`) overflow or lose
+/// precision in the sums of squares of the split search and the importance computation, so they
+/// are mapped into `[-1, 1]`. The leaf values are kept in the rescaled space (the importances are
+/// invariant under affine transforms) and converted back when they are reported.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TargetScale {
+    offset: f64,
+    scale: f64,
+}
+
+impl TargetScale {
+    const IDENTITY: Self = Self {
+        offset: 0.0,
+        scale: 1.0,
+    };
+
+    fn detect(table: &Table) -> Self {
+        let (min, max) = table
+            .target()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), y| {
+                (min.min(y), max.max(y))
+            });
+        let half_range = max / 2.0 - min / 2.0;
+        let magnitude = min.abs().max(max.abs());
+        let moderate = (1.0 / MAX_TARGET_MAGNITUDE..=MAX_TARGET_MAGNITUDE).contains(&half_range)
+            && magnitude <= half_range * MAX_TARGET_MAGNITUDE;
+        if moderate || !(half_range.is_finite() && half_range > 0.0) {
+            return Self::IDENTITY;
+        }
+        Self {
+            offset: min / 2.0 + max / 2.0,
+            scale: half_range,
+        }
+    }
+
+    fn apply(self, y: f64) -> f64 {
+        (y - self.offset) / self.scale
+    }
+
+    fn invert(self, y: f64) -> f64 {
+        y * self.scale + self.offset
+    }
+
+    fn invert_quality(self, quality: FitQuality) -> FitQuality {
+        FitQuality {
+            rmse: quality.rmse * self.scale,
+            ..quality
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Tree {
+    partitions: TreePartitions,
+    depth: usize,
+    mean: f64,
+    variance: f64,
+    importances: BTreeMap<Vec<usize>, f64>,
+}
+
+impl Tree {
+    // `feature_encoder` is the encoding of the table to which `tree` has been fitted (if any).
+    fn new(
+        tree: &dyn SurrogateTree,
+        feature_space: FeatureSpace,
+        feature_encoder: Option<&FeatureEncoder>,
+    ) -> Self {
+        let partitions = match feature_encoder {
+            Some(encoder) => encoder.partitions(tree, feature_space),
+            None => TreePartitions::new(tree, feature_space),
+        };
+        Self::from_partitions(partitions, tree.depth())
+    }
+
+    fn from_partitions(partitions: TreePartitions, depth: usize) -> Self {
+        let (mean, variance) = partitions.mean_and_variance();
+        Self {
+            partitions,
+            depth,
+            mean,
+            variance,
+            importances: BTreeMap::new(),
+        }
+    }
+
+    fn rebase(&mut self, feature_space: FeatureSpace) {
+        self.partitions.rebase(feature_space);
+        let (mean, variance) = self.partitions.mean_and_variance();
+        self.mean = mean;
+        self.variance = variance;
+        self.clear();
+    }
+
+    fn clear(&mut self) {
+        self.importances.clear();
+    }
+
+    // Returns whether the tree splits each feature of `feature_space`.
+    fn split_features(&self, feature_space: &FeatureSpace) -> Vec<bool> {
+        let whole = feature_space.ranges();
+        (0..whole.len())
+            .map(|i| {
+                self.partitions
+                    .iter()
+                    .any(|p| p.space.ranges()[i] != whole[i])
+            })
+            .collect()
+    }
+}
+
+/// fANOVA object.
+#[derive(Debug)]
+pub struct Fanova {
+    trees: Vec<Tree>,
+    feature_space: FeatureSpace,
+    parallel: bool,
+    target_scale: TargetScale,
+    target_adjustments: TargetAdjustments,
+    fit_quality: Option<FitQuality>,
+    excluded_features: Vec<ExcludedFeature>,
+    partial: bool,
+    rng_state: RngState,
+    profile: Profile,
+}
+
+impl Fanova {
+    /// Builds an fANOVA model for the given features and target.
+    ///
+    /// This is equivalent to `FanovaOptions::new().fit(features, target)`.
+    pub fn fit(features: Vec<&[f64]>, target: &[f64]) -> Result<Self, FitError> {
+        FanovaOptions::default().fit(features, target)
+    }
+
+    /// Builds an fANOVA model for the given table.
+    ///
+    /// This is equivalent to `FanovaOptions::new().fit_table(table)`.
+    pub fn new(table: Table) -> Result<Self, FitError> {
+        FanovaOptions::default().fit_table(table)
+    }
+
+    /// Builds an fANOVA model from the trees of a surrogate model fitted outside of this crate
+    /// (e.g., by another library), which are decomposed like the trees of `FanovaOptions`.
+    ///
+    /// The features and their ranges are those of `table`, to which the trees should have been
+    /// fitted (its target is not used). Each tree is decomposed separately and the results are
+    /// averaged over the trees as for a forest, so a model summing its trees (e.g., gradient
+    /// boosting) should be given as a single `SurrogateTree` whose leaves are the intersections
+    /// of the leaves of its trees. The model has no fit quality and can't be resumed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `trees` is empty or a leaf doesn't have a range for each feature of `table`.
+    pub fn from_trees(table: &Table, trees: &[&dyn SurrogateTree]) -> Self {
+        assert!(!trees.is_empty(), "no trees are given");
+
+        let feature_space = FeatureSpace::from_table(table);
+        let trees = trees
+            .iter()
+            .map(|&tree| Tree::new(tree, feature_space.clone(), None))
+            .collect();
+        Self {
+            trees,
+            feature_space,
+            parallel: false,
+            target_scale: TargetScale::IDENTITY,
+            target_adjustments: TargetAdjustments::default(),
+            fit_quality: None,
+            excluded_features: Vec::new(),
+            partial: false,
+            rng_state: RngState { seed: 0, trees: 0 },
+            profile: Profile::default(),
+        }
+    }
+
+    /// Calculates the importance of each feature (in the order of the feature columns).
+    pub fn importances(&mut self) -> Vec<Importance> {
+        let feature_sets = (0..self.feature_space.ranges().len())
+            .map(|i| vec![i])
+            .collect::<Vec<_>>();
+        self.quantify_importances(&feature_sets)
+    }
+
+    /// Calculates the importances of the given feature sets (e.g., all the pairs).
+    ///
+    /// This is equivalent to calling `quantify_importance` for each set, but the marginal
+    /// variances of the sets (and their subsets) are computed in parallel across both the trees
+    /// and the sets if `FanovaOptions::parallel` is set, which keeps all the threads busy even
+    /// if there are fewer trees than threads.
+    pub fn quantify_importances(&mut self, feature_sets: &[Vec<usize>]) -> Vec<Importance> {
+        if self.parallel {
+            self.precompute_importances(feature_sets);
+        }
+        feature_sets
+            .iter()
+            .map(|features| self.quantify_importance(features))
+            .collect()
+    }
+
+    // Caches the importances of the given feature sets and their subsets in all the trees.
+    fn precompute_importances(&mut self, feature_sets: &[Vec<usize>]) {
+        let features_len = self.feature_space.ranges().len();
+        let subsets = feature_sets
+            .iter()
+            .filter(|features| features.iter().all(|&f| f < features_len))
+            .flat_map(|features| {
+                (1..=features.len()).flat_map(|k| features.iter().copied().combinations(k))
+            })
+            .collect::<BTreeSet<_>>();
+        let tasks = self
+            .trees
+            .iter()
+            .enumerate()
+            .flat_map(|(t, tree)| {
+                subsets
+                    .iter()
+                    .filter(move |s| !tree.importances.contains_key(*s))
+                    .map(move |s| (t, s))
+            })
+            .collect::<Vec<_>>();
+        let _span = trace::span!("importance", "tasks={}", tasks.len());
+        let start = trace::now_millis();
+
+        // The marginal variances are the expensive part, and the rest only takes differences.
+        let fractions = tasks
+            .into_par_iter()
+            .map(|(t, s)| {
+                let fraction = Self::variance_fraction(&self.feature_space, &self.trees[t], s);
+                (t, s.clone(), fraction)
+            })
+            .collect::<Vec<_>>();
+        let mut precomputed = vec![BTreeMap::new(); self.trees.len()];
+        for (t, s, fraction) in fractions {
+            precomputed[t].insert(s, fraction);
+        }
+        let mut trees = std::mem::take(&mut self.trees);
+        trees
+            .par_iter_mut()
+            .zip(precomputed)
+            .for_each(|(tree, precomputed)| {
+                for s in precomputed.keys() {
+                    Self::importance_tree(&self.feature_space, tree, s, &precomputed);
+                }
+            });
+        self.trees = trees;
+        self.profile.marginals += trace::now_millis() - start;
+    }
+
+    /// Calculates the importance of the given features.
+    ///
+    /// The importance is zero if any of the features is out of range (see
+    /// `Fanova::try_quantify_importance`).
+    pub fn quantify_importance(&mut self, features: &[usize]) -> Importance {
+        if features
+            .iter()
+            .any(|&f| f >= self.feature_space.ranges().len())
+        {
+            return Importance {
+                mean: 0.0,
+                stddev: 0.0,
+            };
+        }
+
+        let importances = self.tree_importances(features);
+        let (mean, stddev) = functions::mean_and_stddev(importances.into_iter());
+        Importance { mean, stddev }
+    }
+
+    /// Same as `quantify_importance` but returns an error if any of the features is out of range.
+    pub fn try_quantify_importance(
+        &mut self,
+        features: &[usize],
+    ) -> Result<Importance, FanovaError> {
+        let features_len = self.feature_space.ranges().len();
+        if let Some(&feature) = features.iter().find(|&&f| f >= features_len) {
+            return Err(FanovaError::FeatureOutOfRange {
+                feature,
+                features_len,
+            });
+        }
+        Ok(self.quantify_importance(features))
+    }
+
+    /// Calculates the importances of the given features estimated by the first `1, 2, ..., n`
+    /// trees of the forest (where `n` is `trees_len`), i.e., the `i`-th element is the importance
+    /// after `i + 1` trees.
+    ///
+    /// The last element is the same as `quantify_importance`. If the estimates settle well
+    /// before the last tree, a smaller forest would give the same importances. The importances
+    /// are zero if any of the features is out of range.
+    pub fn importance_convergence(&mut self, features: &[usize]) -> Vec<Importance> {
+        let zero = Importance {
+            mean: 0.0,
+            stddev: 0.0,
+        };
+        if features
+            .iter()
+            .any(|&f| f >= self.feature_space.ranges().len())
+        {
+            return vec![zero; self.trees.len()];
+        }
+
+        let mut acc = Welford::default();
+        self.tree_importances(features)
+            .into_iter()
+            .map(|importance| {
+                acc.push(importance, 1.0);
+                let n = acc.total_weight();
+                // The same as `functions::mean_and_stddev` over the prefix.
+                let stddev = if n > 1.0 {
+                    (acc.m2() / (n - 1.0)).sqrt()
+                } else {
+                    0.0
+                };
+                Importance {
+                    mean: acc.mean(),
+                    stddev,
+                }
+            })
+            .collect()
+    }
+
+    /// Calculates the percentile interval of the importances of the given features
+    /// across random forest trees.
+    ///
+    /// For example, if `confidence` is `0.9`, the 5th and 95th percentiles are returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `confidence` is not in the range `[0.0, 1.0]`.
+    pub fn quantify_importance_interval(
+        &mut self,
+        features: &[usize],
+        confidence: f64,
+    ) -> (f64, f64) {
+        assert!(
+            (0.0..=1.0).contains(&confidence),
+            "confidence must be in the range [0.0, 1.0]"
+        );
+        if features
+            .iter()
+            .any(|&f| f >= self.feature_space.ranges().len())
+        {
+            return (0.0, 0.0);
+        }
+
+        let mut importances = self.tree_importances(features);
+        importances.sort_by(|a, b| a.total_cmp(b));
+        let alpha = (1.0 - confidence) / 2.0;
+        (
+            functions::quantile_sorted(&importances, alpha),
+            functions::quantile_sorted(&importances, 1.0 - alpha),
+        )
+    }
+
+    /// Calculates Friedman's H-statistic of the interaction between the given pair of features.
+    ///
+    /// That is, the square root of the fraction of the variance of the joint marginal
+    /// prediction of the pair which is not explained by the individual marginal predictions
+    /// (with respect to the uniform distribution over the feature space). Unlike the pairwise
+    /// importance, this is normalized by the effects of the pair itself, so it ranges from
+    /// `0.0` (no interaction) to `1.0` (a pure interaction) and can be compared across studies.
+    ///
+    /// Returns `0.0` if the features are out of range, the same or have no effects.
+    pub fn h_statistic(&mut self, feature_a: usize, feature_b: usize) -> f64 {
+        let features_len = self.feature_space.ranges().len();
+        if feature_a >= features_len || feature_b >= features_len || feature_a == feature_b {
+            return 0.0;
+        }
+        let (a, b) = (feature_a.min(feature_b), feature_a.max(feature_b));
+        let interaction = self.quantify_importance(&[a, b]).mean.max(0.0);
+        let individual = self.quantify_importance(&[a]).mean + self.quantify_importance(&[b]).mean;
+        let total = individual.max(0.0) + interaction;
+        if total <= 0.0 {
+            return 0.0;
+        }
+        (interaction / total).min(1.0).sqrt()
+    }
+
+    /// Estimates the total H-statistics of the features (in the order of the features).
+    ///
+    /// The total H-statistic of a feature is the square root of the fraction of the variance of
+    /// the forest explained by the interactions of the feature with any other features, which
+    /// is estimated as the difference between the total and first-order Sobol indices from
+    /// `samples` quasi-Monte Carlo samples (see `Fanova::sobol_indices`).
+    pub fn total_h_statistics(&self, samples: NonZeroUsize) -> Vec<f64> {
+        self.sobol_indices(samples)
+            .into_iter()
+            .map(|s| (s.total - s.first_order).clamp(0.0, 1.0).sqrt())
+            .collect()
+    }
+
+    /// Calculates the interaction importances of the pairs of the `top_k` features having the
+    /// largest importances, skipping the other pairs instead of sweeping all the `O(d²)` pairs.
+    ///
+    /// The pairs among the top features are also skipped if the upper bounds of their
+    /// importances are less than `min_importance`. The bound of a pair is the mean over the trees
+    /// of the variance fraction not explained by the individual features and the computed pairs,
+    /// counting only the trees splitting both features (the others have no interactions of the
+    /// pair). The pairs are computed in the descending order of the bounds, so the bounds of the
+    /// remaining pairs shrink as the large interactions are found.
+    pub fn top_interactions(&mut self, top_k: usize, min_importance: f64) -> InteractionSearch {
+        let features_len = self.feature_space.ranges().len();
+        let individuals = (0..features_len)
+            .map(|i| self.tree_importances(&[i]))
+            .collect::<Vec<_>>();
+        let splits = self
+            .trees
+            .iter()
+            .map(|t| t.split_features(&self.feature_space))
+            .collect::<Vec<_>>();
+        let mut residuals = (0..self.trees.len())
+            .map(|t| (1.0 - individuals.iter().map(|x| x[t]).sum::<f64>()).max(0.0))
+            .collect::<Vec<_>>();
+
+        let mut ranking = (0..features_len).collect::<Vec<_>>();
+        let means = individuals
+            .iter()
+            .map(|x| functions::mean(x.iter().copied()))
+            .collect::<Vec<_>>();
+        ranking.sort_by(|&a, &b| means[b].total_cmp(&means[a]));
+        let mut top = vec![false; features_len];
+        for &i in ranking.iter().take(top_k) {
+            top[i] = true;
+        }
+
+        let bound = |residuals: &[f64], (a, b): (usize, usize)| {
+            splits
+                .iter()
+                .zip(residuals)
+                .map(|(s, &r)| if s[a] && s[b] { r } else { 0.0 })
+                .sum::<f64>()
+                / residuals.len() as f64
+        };
+        let (mut candidates, others): (Vec<_>, Vec<_>) = (0..features_len)
+            .flat_map(|a| (a + 1..features_len).map(move |b| (a, b)))
+            .partition(|&(a, b)| top[a] && top[b]);
+        candidates.sort_by(|&x, &y| bound(&residuals, y).total_cmp(&bound(&residuals, x)));
+
+        // The computed interactions are taken from the residuals, which tightens the bounds of
+        // the remaining pairs.
+        let mut pairs = Vec::new();
+        let mut pruned = Vec::new();
+        for pair in candidates {
+            if bound(&residuals, pair) < min_importance {
+                pruned.push(pair);
+                continue;
+            }
+            let importances = self.tree_importances(&[pair.0, pair.1]);
+            for (r, x) in residuals.iter_mut().zip(&importances) {
+                *r = (*r - x.max(0.0)).max(0.0);
+            }
+            let (mean, stddev) = functions::mean_and_stddev(importances.into_iter());
+            pairs.push((pair, Importance { mean, stddev }));
+        }
+        pairs.sort_by(|x, y| y.1.mean.total_cmp(&x.1.mean));
+        let mut skipped = pruned
+            .into_iter()
+            .chain(others)
+            .map(|pair| (pair, bound(&residuals, pair)))
+            .collect::<Vec<_>>();
+        skipped.sort_by_key(|s| s.0);
+        InteractionSearch { pairs, skipped }
+    }
+
+    pub(crate) fn tree_importances(&mut self, features: &[usize]) -> Vec<f64> {
+        let _span = trace::span!("importance", "features={features:?}");
+        let start = trace::now_millis();
+        let mut trees = std::mem::take(&mut self.trees);
+        let importances = if self.parallel {
+            trees
+                .par_iter_mut()
+                .map(|tree| Self::quantify_importance_tree(&self.feature_space, tree, features))
+                .collect::<Vec<_>>()
+        } else {
+            trees
+                .iter_mut()
+                .map(|tree| Self::quantify_importance_tree(&self.feature_space, tree, features))
+                .collect::<Vec<_>>()
+        };
+        self.trees = trees;
+        self.profile.marginals += trace::now_millis() - start;
+        importances
+    }
+
+    /// Returns the variances of the predictions of the trees over the feature space (in the
+    /// original target scale).
+    pub(crate) fn tree_variances(&self) -> Vec<f64> {
+        let scale = self.target_scale.scale;
+        self.trees
+            .iter()
+            .map(|t| t.variance * scale * scale)
+            .collect()
+    }
+
+    /// Returns the feature space covered by the model.
+    pub(crate) fn feature_space(&self) -> &FeatureSpace {
+        &self.feature_space
+    }
+
+    /// Returns the means and standard deviations (across the trees) of the marginal predictions
+    /// of `feature` at `n_points` equally spaced points over its range, as `(point, mean, stddev)`.
+    #[cfg(any(feature = "ffi", feature = "python"))]
+    pub(crate) fn marginal_grid(&self, feature: usize, n_points: usize) -> Vec<(f64, f64, f64)> {
+        let range = &self.feature_space.ranges()[feature];
+        functions::equal_width_edges(range.start, range.end, n_points - 1)
+            .into_iter()
+            .map(|x| {
+                let predictions = self.marginal_predictions(&[feature], &[x]);
+                let (mean, stddev) = functions::mean_and_stddev(predictions.into_iter());
+                (x, mean, stddev)
+            })
+            .collect()
+    }
+
+    /// Returns the marginal predictions of the trees at `point` of the given features.
+    ///
+    /// That is, the predictions averaged over the features not in `features`.
+    pub(crate) fn marginal_predictions(&self, features: &[usize], point: &[f64]) -> Vec<f64> {
+        let _span = trace::span!("marginal", "features={features:?}");
+        self.marginalize(features, point, |p| p.value)
+            .map(|y| self.target_scale.invert(y))
+            .collect()
+    }
+
+    /// Returns the marginal prediction of `feature` at `x` (clamped into the feature space)
+    /// with its epistemic and aleatoric uncertainties.
+    ///
+    /// The epistemic uncertainty is the standard deviation of the marginal predictions of the
+    /// trees, which shows that the model is unsure (e.g., in regions with few rows). The
+    /// aleatoric uncertainty is the standard deviation of the targets within the leaves
+    /// (averaged like the predictions), which shows that the target is noisy (e.g., repeated
+    /// evaluations of similar configurations disagree). Note that the leaves of fully grown
+    /// trees have a few rows, so `min_samples_leaf` should be larger to estimate the noise (for
+    /// the deviance criteria, it is the noise of the targets, not of their logarithms).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `feature` is out of range.
+    pub fn marginal_uncertainty(&self, feature: usize, x: f64) -> MarginalUncertainty {
+        let whole = &self.feature_space.ranges()[feature];
+        let x = [x.clamp(whole.start, whole.end)];
+        let (mean, epistemic) =
+            functions::mean_and_stddev(self.marginal_predictions(&[feature], &x).into_iter());
+        let scale = self.target_scale.scale;
+        let variance =
+            functions::mean(self.marginalize(&[feature], &x, |p| p.variance)) * scale * scale;
+        MarginalUncertainty {
+            mean,
+            epistemic,
+            aleatoric: variance.sqrt(),
+        }
+    }
+
+    // Averages `f` of the partitions of each tree over the features not in `features` at
+    // `point` of `features` (in the scaled target space).
+    fn marginalize<'a>(
+        &'a self,
+        features: &'a [usize],
+        point: &'a [f64],
+        f: impl 'a + Fn(&Partition) -> f64,
+    ) -> impl 'a + Iterator<Item = f64> {
+        debug_assert_eq!(features.len(), point.len());
+        let ranges = self.feature_space.ranges();
+        let covers = |p: &Partition| {
+            features.iter().zip(point.iter()).all(|(&i, &x)| {
+                let r = &p.space.ranges()[i];
+                // The upper bound of the whole space is included in the last partition.
+                r.start <= x && (x < r.end || (x == r.end && r.end == ranges[i].end))
+            })
+        };
+        self.trees.iter().map(move |tree| {
+            let (values, weights): (Vec<_>, Vec<_>) = tree
+                .partitions
+                .iter()
+                .zip(tree.partitions.marginal_fractions(features))
+                .filter(|(p, _)| covers(p))
+                .map(|(p, fraction)| (f(p), fraction))
+                .unzip();
+            simd::dot(&values, &weights)
+        })
+    }
+
+    /// Calculates the partial dependence of the predictions on the pair of the given features.
+    ///
+    /// `result[i][j]` is the prediction at `(grid_a[i], grid_b[j])` averaged over the other
+    /// features (uniformly over the feature space) and the trees, i.e., the marginal prediction
+    /// whose variance is the pairwise importance of the features (with the importances of the
+    /// individual features). The grid values outside the feature space are clamped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the features are out of range or the same.
+    pub fn partial_dependence_2d(
+        &self,
+        feature_a: usize,
+        feature_b: usize,
+        grid_a: &[f64],
+        grid_b: &[f64],
+    ) -> Vec<Vec<f64>> {
+        let ranges = self.feature_space.ranges();
+        assert!(
+            feature_a < ranges.len() && feature_b < ranges.len(),
+            "feature out of range"
+        );
+        assert_ne!(feature_a, feature_b, "features must be different");
+        let _span = trace::span!("partial_dependence", "features={feature_a},{feature_b}");
+        let clamp = |grid: &[f64], whole: &Range<f64>| {
+            grid.iter()
+                .map(|x| x.clamp(whole.start, whole.end))
+                .collect::<Vec<_>>()
+        };
+        let grid_a = clamp(grid_a, &ranges[feature_a]);
+        let grid_b = clamp(grid_b, &ranges[feature_b]);
+        // The upper bound of the whole space is included in the last partition.
+        let covered = |grid: &[f64], r: &Range<f64>, whole: &Range<f64>| {
+            (0..grid.len())
+                .filter(|&i| {
+                    let x = grid[i];
+                    r.start <= x && (x < r.end || (x == r.end && r.end == whole.end))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut sums = vec![vec![0.0; grid_b.len()]; grid_a.len()];
+        for tree in &self.trees {
+            let features = [feature_a, feature_b];
+            let fractions = tree.partitions.marginal_fractions(&features);
+            for (p, fraction) in tree.partitions.iter().zip(fractions) {
+                let space = p.space.ranges();
+                let rows = covered(&grid_a, &space[feature_a], &ranges[feature_a]);
+                if rows.is_empty() {
+                    continue;
+                }
+                let columns = covered(&grid_b, &space[feature_b], &ranges[feature_b]);
+                for &i in &rows {
+                    for &j in &columns {
+                        sums[i][j] += p.value * fraction;
+                    }
+                }
+            }
+        }
+        let trees_len = self.trees.len() as f64;
+        sums.into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|sum| self.target_scale.invert(sum / trees_len))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns the interval of the given feature where the marginal prediction (averaged over
+    /// the trees) is the smallest, or the largest if `maximize` is `true`, and the prediction.
+    ///
+    /// The marginal prediction is constant between the split thresholds of the trees, so the
+    /// optimum is exact: the returned interval is the piece achieving it (ties are broken by
+    /// the lowest piece). Any value of the interval can be suggested as a good default of the
+    /// feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `feature` is out of range.
+    pub fn marginal_optimum(&self, feature: usize, maximize: bool) -> (Range<f64>, f64) {
+        let whole = self.feature_space.ranges()[feature].clone();
+        let mut bounds = self
+            .trees
+            .iter()
+            .flat_map(|t| t.partitions.iter())
+            .flat_map(|p| {
+                let r = &p.space.ranges()[feature];
+                [r.start, r.end]
+            })
+            .chain([whole.start, whole.end])
+            .collect::<Vec<_>>();
+        bounds.sort_by(|a, b| a.total_cmp(b));
+        bounds.dedup();
+        if bounds.len() == 1 {
+            bounds.push(whole.end);
+        }
+
+        let sign = if maximize { -1.0 } else { 1.0 };
+        bounds
+            .windows(2)
+            .map(|w| {
+                let x = (w[0] + w[1]) / 2.0;
+                let prediction =
+                    functions::mean(self.marginal_predictions(&[feature], &[x]).into_iter());
+                (w[0]..w[1], prediction)
+            })
+            .fold(None, |best: Option<(Range<f64>, f64)>, (r, y)| match best {
+                Some(best) if sign * best.1 <= sign * y => Some(best),
+                _ => Some((r, y)),
+            })
+            .expect("at least one piece")
+    }
+
+    /// Calculates the SHAP values of the features at `point` (in the order of the features).
+    ///
+    /// The values are averaged over the trees. The expectation over the missing features is
+    /// taken with respect to the uniform distribution over the feature space like the
+    /// importances, so the values sum up to the prediction at `point` minus `expected_value()`.
+    /// NaN values of `point` are regarded as missing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `point` differs from the number of the features.
+    pub fn shap_values(&self, point: &[f64]) -> Vec<f64> {
+        assert_eq!(point.len(), self.feature_space.ranges().len());
+        let mut phi = vec![0.0; point.len()];
+        for tree in &self.trees {
+            shap::add_tree_shap_values(&tree.partitions, point, &mut phi);
+        }
+        let scale = self.target_scale.scale / self.trees.len() as f64;
+        phi.iter_mut().for_each(|v| *v *= scale);
+        phi
+    }
+
+    /// Returns the mean prediction over the feature space (the base value of the SHAP values).
+    pub fn expected_value(&self) -> f64 {
+        let mean = functions::mean(self.trees.iter().map(|t| t.mean));
+        self.target_scale.invert(mean)
+    }
+
+    /// Estimates the Sobol indices of the features (in the order of the features) by
+    /// quasi-Monte Carlo sampling of the forest over the feature space.
+    ///
+    /// The forest is evaluated `samples * (features + 2)` times (see the `sobol` module for the
+    /// design). Unlike the importances, which are computed exactly on each tree, these are
+    /// sampling estimates of the variance decomposition of the averaged forest, so they are
+    /// useful to cross-check the importances.
+    pub fn sobol_indices(&self, samples: NonZeroUsize) -> Vec<SobolIndices> {
+        let _span = trace::span!("sobol", "samples={samples}");
+        sobol::sobol_indices(&self.feature_space, samples, self.parallel, |point| {
+            self.predict(point)
+        })
+    }
+
+    /// Computes the Morris elementary effects of the features (in the order of the features) on
+    /// the predictions of the forest.
+    ///
+    /// This only takes `trajectories * (features + 1)` predictions, so it is much cheaper than
+    /// the importances for many features and is useful to screen out the features having
+    /// negligible effects beforehand.
+    pub fn elementary_effects(&self, options: &MorrisOptions) -> Vec<ElementaryEffects> {
+        let _span = trace::span!("morris");
+        morris::elementary_effects(&self.feature_space, options, |point| self.predict(point))
+    }
+
+    /// Distills the forest into a single shallow tree fitted to its predictions at points
+    /// sampled uniformly from the feature space.
+    ///
+    /// The tree is a human-readable summary of how the forest depends on the features and
+    /// their interactions. `ExplanatoryTree::fidelity` tells how faithful the summary is.
+    pub fn distill(&self, options: &DistillOptions) -> ExplanatoryTree {
+        let _span = trace::span!("distill");
+        distill::distill(&self.feature_space, options, |point| self.predict(point))
+    }
+
+    /// Returns the nodes of the `index`-th tree of the random forest (e.g., to visualize it).
+    ///
+    /// The subtrees below `max_depth` (if any) are merged into leaves. Since the trees only keep
+    /// the partitions of their leaves, the statistics of the nodes are taken with respect to the
+    /// uniform distribution over the feature space rather than the rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than `trees_len()`.
+    pub fn tree_structure(&self, index: usize, max_depth: Option<usize>) -> TreeNode {
+        let tree = &self.trees[index];
+        let mut root = structure::reconstruct(&tree.partitions, tree.variance, max_depth);
+        root.map(&mut |_, t| t, &mut |y| self.target_scale.invert(y));
+        root
+    }
+
+    /// Returns the thresholds the trees of the random forest split each feature at in ascending
+    /// order, with the numbers of the splits at them.
+    ///
+    /// These are natural bin edges of the features (e.g., for histograms) and show which regions
+    /// of the features the forest tells apart.
+    pub fn split_thresholds(&self) -> Vec<Vec<(f64, usize)>> {
+        let mut counts = vec![BTreeMap::new(); self.feature_space.ranges().len()];
+        for tree in &self.trees {
+            structure::reconstruct(&tree.partitions, tree.variance, None).for_each_split(
+                &mut |feature, threshold, _| {
+                    *counts[feature].entry(TotalOrder(threshold)).or_insert(0) += 1;
+                },
+            );
+        }
+        counts
+            .into_iter()
+            .map(|c| c.into_iter().map(|(t, n)| (t.0, n)).collect())
+            .collect()
+    }
+
+    /// Calculates how often the trees split each feature (in the order of the feature columns).
+    ///
+    /// Each split counts `0.5^depth` (the root is at the depth `0`), so the splits near the roots,
+    /// which divide more of the feature space, count more. The counts of each tree are
+    /// normalized to sum up to one (or are all zero if it has no splits), and `Importance` is
+    /// their mean and standard deviation across the trees.
+    ///
+    /// This is much cheaper than the importances but ignores how much the splits change the
+    /// predictions, so the features which the trees split often to fit noise look important.
+    /// Comparing both tells whether a feature matters or is merely split often.
+    pub fn split_frequencies(&self) -> Vec<Importance> {
+        let features_len = self.feature_space.ranges().len();
+        let per_tree = self
+            .trees
+            .iter()
+            .map(|tree| {
+                let mut counts = vec![0.0; features_len];
+                structure::reconstruct(&tree.partitions, tree.variance, None).for_each_split(
+                    &mut |feature, _, depth| counts[feature] += 0.5f64.powi(depth as i32),
+                );
+                let total = counts.iter().sum::<f64>();
+                if total > 0.0 {
+                    counts.iter_mut().for_each(|c| *c /= total);
+                }
+                counts
+            })
+            .collect::<Vec<_>>();
+        (0..features_len)
+            .map(|i| {
+                let (mean, stddev) = functions::mean_and_stddev(per_tree.iter().map(|c| c[i]));
+                Importance { mean, stddev }
+            })
+            .collect()
+    }
+
+    /// Returns the model restricted to a region of the feature space, whose importances answer
+    /// what matters within the region (e.g., once a learning rate is in its good range).
+    ///
+    /// `domain` has the disjoint ranges of each feature in ascending order (or `None` for the
+    /// whole range), e.g., `[k - 0.5, k + 0.5]` for the `k`-th category, in the (transformed)
+    /// space of the table. Both the variance and the marginalization are then taken over the
+    /// region. The gaps between the ranges of a feature are removed (i.e., the values above a
+    /// gap are shifted down by its width), so the restricted model is defined over the ranges
+    /// of a single range feature as is, and over the re-indexed categories of a subset of
+    /// categories. See `FanovaOptions::fit_restricted` to also restrict the fitted rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `domain` differs from the number of the features, or if the
+    /// ranges of a feature are not sorted and disjoint or don't overlap the feature space.
+    pub fn restrict(&self, domain: &[Option<Vec<Range<f64>>>]) -> Self {
+        let domain = Domain::new(&self.feature_space, domain);
+        let feature_space = domain.space();
+        let trees = self
+            .trees
+            .iter()
+            .map(|tree| {
+                let partitions = tree
+                    .partitions
+                    .iter()
+                    .filter_map(|p| {
+                        let space = domain.project(&p.space)?;
+                        Some(Partition {
+                            value: p.value,
+                            variance: p.variance,
+                            space,
+                        })
+                    })
+                    .collect();
+                let partitions = TreePartitions::from_partitions(partitions, feature_space.clone());
+                Tree::from_partitions(partitions, tree.depth)
+            })
+            .collect();
+        Self {
+            trees,
+            feature_space,
+            parallel: self.parallel,
+            target_scale: self.target_scale,
+            target_adjustments: self.target_adjustments,
+            fit_quality: self.fit_quality,
+            excluded_features: self.excluded_features.clone(),
+            partial: self.partial,
+            rng_state: self.rng_state,
+            profile: Profile::default(),
+        }
+    }
+
+    /// Calculates the local parameter importances (LPI) of the features at `point` (e.g., the
+    /// best configuration).
+    ///
+    /// For each feature, the predictions of the forest are evaluated at `grid_points` evenly
+    /// spaced values of the feature with the other features fixed at `point`, and the variances
+    /// of the predictions are normalized to sum up to one. Unlike the importances, these
+    /// explain the neighborhood of `point` rather than the whole feature space.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `point` differs from the number of the features or if
+    /// `grid_points` is less than two.
+    pub fn local_importances(&self, point: &[f64], grid_points: usize) -> Vec<f64> {
+        let ranges = self.feature_space.ranges();
+        assert_eq!(point.len(), ranges.len());
+        assert!(grid_points >= 2, "grid_points must be two or more");
+        let _span = trace::span!("local_importance", "points={grid_points}");
+        let mut point = point.to_vec();
+        let variances = (0..ranges.len())
+            .map(|i| {
+                let x = point[i];
+                let grid =
+                    functions::equal_width_edges(ranges[i].start, ranges[i].end, grid_points - 1);
+                let predictions = grid
+                    .into_iter()
+                    .map(|v| {
+                        point[i] = v;
+                        self.predict(&point)
+                    })
+                    .collect::<Vec<_>>();
+                point[i] = x;
+                let (_, stddev) = functions::mean_and_stddev(predictions.into_iter());
+                stddev * stddev
+            })
+            .collect::<Vec<_>>();
+        let total = variances.iter().sum::<f64>();
+        if total == 0.0 {
+            return vec![0.0; variances.len()];
+        }
+        variances.into_iter().map(|v| v / total).collect()
+    }
+
+    /// Calculates the sensitivities (slopes) of the marginal predictions of the features at
+    /// `point` (e.g., the best configuration).
+    ///
+    /// For each feature, the marginal prediction (averaged over the other features like
+    /// `Fanova::partial_dependence_2d`) is differenced between `point[i] ± step * width` where
+    /// `width` is the width of the feature space along the feature (one-sided at the bounds).
+    /// The result is the slope per unit of the feature, so positive values mean that increasing
+    /// the feature increases the prediction. As the forest is piecewise constant, `step` should be
+    /// large enough to cover several splits. The values outside the feature space are clamped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `point` differs from the number of the features or `step` is not
+    /// in the range `(0.0, 1.0]`.
+    pub fn marginal_sensitivities(&self, point: &[f64], step: f64) -> Vec<f64> {
+        let ranges = self.feature_space.ranges();
+        assert_eq!(point.len(), ranges.len());
+        assert!(
+            step > 0.0 && step <= 1.0,
+            "step must be in the range (0.0, 1.0]"
+        );
+        let marginal =
+            |i: usize, x: f64| functions::mean(self.marginal_predictions(&[i], &[x]).into_iter());
+        point
+            .iter()
+            .zip(ranges)
+            .enumerate()
+            .map(|(i, (&x, r))| {
+                let x = x.clamp(r.start, r.end);
+                let h = step * (r.end - r.start);
+                let lower = (x - h).clamp(r.start, r.end);
+                let upper = (x + h).clamp(r.start, r.end);
+                if lower >= upper {
+                    return 0.0;
+                }
+                (marginal(i, upper) - marginal(i, lower)) / (upper - lower)
+            })
+            .collect()
+    }
+
+    /// Computes the ablation path from `source` to `target` (e.g., from the default
+    /// configuration to the incumbent) on the predictions of the forest.
+    ///
+    /// The features are switched from the values of `source` to the ones of `target` one by
+    /// one, choosing the switch which improves the prediction the most at each step. Smaller
+    /// predictions are regarded as better unless `maximize` is `true`, and the improvements
+    /// (positive if the switches make the predictions better) sum up to the total improvement
+    /// from `source` to `target`. The values outside the feature space are clamped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the lengths of `source` or `target` differ from the number of the features.
+    pub fn ablation_path(
+        &self,
+        source: &[f64],
+        target: &[f64],
+        maximize: bool,
+    ) -> Vec<AblationStep> {
+        let features_len = self.feature_space.ranges().len();
+        assert_eq!(source.len(), features_len);
+        assert_eq!(target.len(), features_len);
+        let sign = if maximize { -1.0 } else { 1.0 };
+        let mut steps = ablation::ablation_path(source, target, |point| sign * self.predict(point));
+        for step in &mut steps {
+            step.prediction *= sign;
+        }
+        steps
+    }
+
+    // Returns the triples of the target, the weight and the prediction (in the original scale of
+    // the targets) of each row of `table` (see `FitQuality::from_predictions`).
+    fn holdout_predictions(
+        &self,
+        criterion: SplitCriterion,
+        table: &Table,
+    ) -> Vec<(f64, f64, f64)> {
+        table
+            .weighted_target()
+            .enumerate()
+            .map(|(i, (y, w))| {
+                let prediction = self.predict(&table.row_features(i));
+                let prediction = self.target_adjustments.unstandardize(prediction);
+                (y, w, criterion.inverse_link(prediction))
+            })
+            .collect()
+    }
+
+    /// Returns the prediction of the forest at `point` (clamped into the feature space).
+    pub(crate) fn predict(&self, point: &[f64]) -> f64 {
+        self.predict_with_stddev(point).0
+    }
+
+    /// Returns the mean and the standard deviation (across the trees) of the predictions at
+    /// `point`, which is clamped into the feature space.
+    ///
+    /// The standard deviation measures the disagreement of the trees, so it can be used as the
+    /// uncertainty of the prediction (e.g., it is large far from the fitted rows).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `point` differs from the number of the features.
+    pub fn predict_with_stddev(&self, point: &[f64]) -> (f64, f64) {
+        let (mean, stddev) = functions::mean_and_stddev(self.scaled_predictions(point).into_iter());
+        (
+            self.target_scale.invert(mean),
+            stddev * self.target_scale.scale,
+        )
+    }
+
+    /// Returns the prediction of each tree at `point`, which is clamped into the feature space.
+    ///
+    /// The distribution of the predictions shows how much the trees agree (e.g., to draw it as a
+    /// histogram), and their means over the first `k` trees show how the prediction converges
+    /// as the trees are added.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `point` differs from the number of the features.
+    pub fn predict_per_tree(&self, point: &[f64]) -> Vec<f64> {
+        self.scaled_predictions(point)
+            .into_iter()
+            .map(|y| self.target_scale.invert(y))
+            .collect()
+    }
+
+    // Returns the predictions of the trees in the scaled target space.
+    fn scaled_predictions(&self, point: &[f64]) -> Vec<f64> {
+        assert_eq!(point.len(), self.feature_space.ranges().len());
+        let point = point
+            .iter()
+            .zip(self.feature_space.ranges())
+            .map(|(x, r)| x.clamp(r.start, r.end))
+            .collect::<Vec<_>>();
+        self.trees
+            .iter()
+            .map(|t| t.partitions.predict(&point))
+            .collect()
+    }
+
+    /// Returns the lower and upper bounds the targets are clipped to before fitting (see
+    /// `FanovaOptions::winsorize_target`), or `None` if they are not clipped.
+    pub fn target_bounds(&self) -> Option<(f64, f64)> {
+        self.target_adjustments.bounds
+    }
+
+    /// Returns the mean and the standard deviation the targets are standardized with before
+    /// fitting (see `FanovaOptions::standardize_target`), or `None` if they are not.
+    ///
+    /// The predictions `p` of the model are converted into the original scale by
+    /// `p * stddev + mean`.
+    pub fn target_standardization(&self) -> Option<(f64, f64)> {
+        self.target_adjustments.standardization
+    }
+
+    /// Returns the number of the fitted rows (e.g., trials) having the identical feature values
+    /// to earlier rows.
+    ///
+    /// They are merged into the earlier rows if `FanovaOptions::aggregate_duplicates` is set.
+    /// Otherwise they weigh the regions of repeated configurations more.
+    pub fn duplicated_rows(&self) -> usize {
+        self.target_adjustments.duplicated_rows
+    }
+
+    /// Returns the quality of the fit of the random forest estimated from the out-of-bag
+    /// predictions (or `None` if no rows have out-of-bag predictions).
+    ///
+    /// After `replace_trees`, this is the quality of the newer trees.
+    pub fn fit_quality(&self) -> Option<FitQuality> {
+        self.fit_quality
+    }
+
+    /// Returns the elapsed times of the phases of fitting and querying this model.
+    ///
+    /// The times are not serialized, so a model restored by `Fanova::from_bytes` (or by
+    /// `Fanova::restrict`) starts with an empty profile.
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// Returns the features that the trees never split (constant features and features
+    /// duplicating an earlier one), whose importances are zero.
+    pub fn excluded_features(&self) -> &[ExcludedFeature] {
+        &self.excluded_features
+    }
+
+    /// Returns the depths of the trees of the random forest.
+    pub(crate) fn tree_depths(&self) -> impl '_ + Iterator<Item = usize> + Clone {
+        self.trees.iter().map(|t| t.depth)
+    }
+
+    /// Returns the numbers of the leaves of the trees of the random forest.
+    pub(crate) fn tree_leaves(&self) -> impl '_ + Iterator<Item = usize> + Clone {
+        self.trees.iter().map(|t| t.partitions.len())
+    }
+
+    /// Returns the number of the trees of the random forest (which is smaller than the number
+    /// of the trees of the surrogate model if the fitting has stopped early).
+    pub fn trees_len(&self) -> usize {
+        self.trees.len()
+    }
+
+    /// Returns `true` if `FanovaOptions::time_budget` has cut the fitting short (i.e., some
+    /// trees of the surrogate model have not been fitted).
+    pub fn is_partial(&self) -> bool {
+        self.partial
+    }
+
+    /// Returns the position in the random sequence of the trees, which is saved by
+    /// `Fanova::to_bytes` so that `FanovaOptions::resume` can fit the remaining trees exactly as
+    /// the interrupted fitting would have.
+    ///
+    /// The seed is recorded even if the surrogate model is given no seed. After `replace_trees`,
+    /// this is the sequence of the newer trees.
+    pub fn rng_state(&self) -> RngState {
+        self.rng_state
+    }
+
+    /// Replaces the oldest trees with the trees of `newer` (warm start).
+    ///
+    /// `newer` is assumed to be fitted on a table which is made by appending rows to the table
+    /// of this model. The remaining trees are extended to cover the feature space of `newer` (and
+    /// their values are converted into the target scale and standardization of `newer`).
+    pub(crate) fn replace_trees(&mut self, newer: Fanova) {
+        let n = std::cmp::min(newer.trees.len(), self.trees.len());
+        self.trees.drain(..n);
+        let (from, to) = (self.target_scale, newer.target_scale);
+        let (adjusted, readjusted) = (self.target_adjustments, newer.target_adjustments);
+        let standardization_changed = adjusted.standardization != readjusted.standardization;
+        for tree in &mut self.trees {
+            if standardization_changed {
+                tree.partitions.map_values(|y| {
+                    let y = adjusted.unstandardize(from.invert(y));
+                    to.apply(readjusted.standardize(y))
+                });
+            } else if from != to {
+                tree.partitions.map_values(|y| to.apply(from.invert(y)));
+            }
+            tree.rebase(newer.feature_space.clone());
+        }
+        self.trees.extend(newer.trees);
+        self.feature_space = newer.feature_space;
+        self.target_scale = newer.target_scale;
+        self.target_adjustments = newer.target_adjustments;
+        self.fit_quality = newer.fit_quality;
+        self.excluded_features = newer.excluded_features;
+        self.partial = newer.partial;
+        self.rng_state = newer.rng_state;
+        let replaced = std::cmp::min(n, self.profile.trees.len());
+        self.profile.trees.drain(..replaced);
+        self.profile.trees.extend(newer.profile.trees);
+        self.profile.encoding = newer.profile.encoding;
+        self.profile.sorting = newer.profile.sorting;
+    }
+
+    /// Returns the number of the decision tree nodes of the model.
+    pub(crate) fn nodes_len(&self) -> usize {
+        // Each tree is a full binary tree whose leaves correspond to the partitions.
+        self.trees.iter().map(|t| t.partitions.len() * 2 - 1).sum()
+    }
+
+    /// Returns the (approximate) number of bytes used by the model (including the caches).
+    pub(crate) fn heap_size(&self) -> usize {
+        self.trees
+            .iter()
+            .map(|t| {
+                let cache_size = t
+                    .importances
+                    .keys()
+                    .map(|k| {
+                        std::mem::size_of_val(&k[..]) + std::mem::size_of::<(Vec<usize>, f64)>()
+                    })
+                    .sum::<usize>();
+                t.partitions.heap_size() + cache_size
+            })
+            .sum()
+    }
+
+    /// Serializes the fitted model into bytes, which are restored by `Fanova::from_bytes`.
+    ///
+    /// If `compress` is `true`, the bytes are compressed with zlib (deflate), which is slower
+    /// but makes the bytes of large forests typically several times smaller. The importance
+    /// caches are not included.
+    pub fn to_bytes(&self, compress: bool) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        encoder.bytes(MODEL_MAGIC);
+        self.encode(&mut encoder);
+        let bytes = encoder.finish();
+        if compress {
+            codec::compress(&bytes)
+        } else {
+            bytes
+        }
+    }
+
+    /// Restores a model from the bytes returned by `Fanova::to_bytes` (either compressed or not).
+    ///
+    /// Returns `FanovaError::InvalidModel` if the bytes are invalid (e.g., truncated or
+    /// serialized by another version of this crate).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FanovaError> {
+        let decode = || {
+            let bytes = codec::decompress(bytes)?;
+            let mut decoder = Decoder::new(&bytes);
+            if decoder.bytes()? != MODEL_MAGIC {
+                return None;
+            }
+            let fanova = Self::decode(&mut decoder)?;
+            decoder.is_empty().then_some(fanova)
+        };
+        decode().ok_or(FanovaError::InvalidModel)
+    }
+
+    /// Encodes the fitted model (the importance caches are not included).
+    pub(crate) fn encode(&self, encoder: &mut Encoder) {
+        let space = self.feature_space.ranges();
+        encoder.usize(space.len());
+        space.iter().for_each(|r| encoder.range(r));
+        encoder.u8(u8::from(self.parallel));
+        encoder.f64(self.target_scale.offset);
+        encoder.f64(self.target_scale.scale);
+        self.target_adjustments.encode(encoder);
+        encoder.u8(u8::from(self.fit_quality.is_some()));
+        if let Some(q) = self.fit_quality {
+            encoder.f64(q.r2);
+            encoder.f64(q.rmse);
+            encoder.usize(q.rows);
+        }
+        encoder.u8(u8::from(self.partial));
+        encoder.u64(self.rng_state.seed);
+        encoder.usize(self.rng_state.trees);
+        encoder.usize(self.excluded_features.len());
+        for excluded in &self.excluded_features {
+            match *excluded {
+                ExcludedFeature::Constant { feature } => {
+                    encoder.u8(0);
+                    encoder.usize(feature);
+                }
+                ExcludedFeature::Duplicate { feature, original } => {
+                    encoder.u8(1);
+                    encoder.usize(feature);
+                    encoder.usize(original);
+                }
+            }
+        }
+        encoder.usize(self.trees.len());
+        for tree in &self.trees {
+            encoder.usize(tree.depth);
+            encoder.usize(tree.partitions.len());
+            for p in tree.partitions.iter() {
+                encoder.f64(p.value);
+                encoder.f64(p.variance);
+                p.space.ranges().iter().for_each(|r| encoder.range(r));
+            }
+        }
+    }
+
+    /// Decodes a model encoded by `Fanova::encode`.
+    pub(crate) fn decode(decoder: &mut Decoder) -> Option<Self> {
+        let features_len = decoder.usize()?;
+        let decode_space = |decoder: &mut Decoder| {
+            (0..features_len)
+                .map(|_| decoder.range())
+                .collect::<Option<Vec<_>>>()
+                .map(FeatureSpace::from_ranges)
+        };
+        let feature_space = decode_space(decoder)?;
+        let parallel = decoder.u8()? != 0;
+        let target_scale = TargetScale {
+            offset: decoder.f64()?,
+            scale: decoder.f64()?,
+        };
+        let target_adjustments = TargetAdjustments::decode(decoder)?;
+        let fit_quality = if decoder.u8()? != 0 {
+            Some(FitQuality {
+                r2: decoder.f64()?,
+                rmse: decoder.f64()?,
+                rows: decoder.usize()?,
+            })
+        } else {
+            None
+        };
+        let partial = decoder.u8()? != 0;
+        let rng_state = RngState {
+            seed: decoder.u64()?,
+            trees: decoder.usize()?,
+        };
+        let excluded_features = (0..decoder.usize()?)
+            .map(|_| match decoder.u8()? {
+                0 => Some(ExcludedFeature::Constant {
+                    feature: decoder.usize()?,
+                }),
+                1 => Some(ExcludedFeature::Duplicate {
+                    feature: decoder.usize()?,
+                    original: decoder.usize()?,
+                }),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let trees = (0..decoder.usize()?)
+            .map(|_| {
+                let depth = decoder.usize()?;
+                let partitions = (0..decoder.usize()?)
+                    .map(|_| {
+                        let value = decoder.f64()?;
+                        let variance = decoder.f64()?;
+                        let space = decode_space(decoder)?;
+                        Some(Partition {
+                            value,
+                            variance,
+                            space,
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                if partitions.is_empty() {
+                    return None;
+                }
+                let partitions = TreePartitions::from_partitions(partitions, feature_space.clone());
+                Some(Tree::from_partitions(partitions, depth))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            trees,
+            feature_space,
+            parallel,
+            target_scale,
+            target_adjustments,
+            fit_quality,
+            excluded_features,
+            partial,
+            rng_state,
+            profile: Profile::default(),
+        })
+    }
+
+    /// Clears the internal cache.
+    pub fn clear(&mut self) {
+        for t in &mut self.trees {
+            t.clear();
+        }
+    }
+
+    #[cfg(test)]
+    fn feature_combinations(&self, k: usize) -> impl Iterator<Item = Vec<usize>> {
+        let features = self.feature_space.ranges().len();
+        (1..=k).flat_map(move |k| (0..features).combinations(k))
+    }
+
+    fn traverse_covered_subspaces<F>(
+        marginal_value_index: usize,
+        partition: &Partition,
+        feature_subspaces: &[(usize, Vec<Range<f64>>)],
+        f: &mut F,
+    ) where
+        F: FnMut(usize),
+    {
+        if feature_subspaces.is_empty() {
+            f(marginal_value_index);
+            return;
+        }
+
+        let (feature, subspaces) = &feature_subspaces[0];
+        let range = partition.space.ranges()[*feature].clone();
+        let start = subspaces
+            .binary_search_by(|x| functions::total_cmp(x.start, range.start))
+            .unwrap_or_else(|index| index);
+
+        for i in (start..subspaces.len()).take_while(|&i| subspaces[i].end <= range.end) {
+            Self::traverse_covered_subspaces(
+                marginal_value_index * subspaces.len() + i,
+                partition,
+                &feature_subspaces[1..],
+                f,
+            );
+        }
+    }
+
+    fn quantify_importance_tree(
+        feature_space: &FeatureSpace,
+        tree: &mut Tree,
+        features: &[usize],
+    ) -> f64 {
+        Self::importance_tree(feature_space, tree, features, &BTreeMap::new())
+    }
+
+    // Calculates the importance of `features` in `tree` using the variance fractions in
+    // `precomputed` (if any).
+    fn importance_tree(
+        feature_space: &FeatureSpace,
+        tree: &mut Tree,
+        features: &[usize],
+        precomputed: &BTreeMap<Vec<usize>, f64>,
+    ) -> f64 {
+        if let Some(&importance) = tree.importances.get(features) {
+            return importance;
+        }
+
+        if feature_space.partial_size(features) == 0.0 {
+            // A constant feature explains nothing.
+            tree.importances.insert(features.to_owned(), 0.0);
+            return 0.0;
+        }
+
+        let mut importance = match precomputed.get(features) {
+            Some(&fraction) => fraction,
+            None => Self::variance_fraction(feature_space, tree, features),
+        };
+        for k in 1..features.len() {
+            for sub_features in features.iter().copied().combinations(k) {
+                importance -=
+                    Self::importance_tree(feature_space, tree, &sub_features, precomputed);
+            }
+        }
+
+        tree.importances.insert(features.to_owned(), importance);
+        importance
+    }
+
+    // Returns the fraction of the variance of `tree` explained by the marginal predictions of
+    // `features` (including the effects of their subsets).
+    fn variance_fraction(feature_space: &FeatureSpace, tree: &Tree, features: &[usize]) -> f64 {
+        let size = feature_space.partial_size(features);
+        if size == 0.0 {
+            return 0.0;
+        }
+
+        let feature_subspaces = features
+            .iter()
+            .copied()
+            .map(|i| {
+                let subspaces =
+                    subspaces(tree.partitions.iter().map(|p| p.space.ranges()[i].clone()));
+                (i, subspaces)
+            })
+            .collect::<Vec<_>>();
+
+        let mut marginal_values =
+            vec![0.0; feature_subspaces.iter().map(|(_, s)| s.len()).product()];
+
+        let fractions = tree.partitions.marginal_fractions(features);
+        for (p, fraction) in tree.partitions.iter().zip(fractions) {
+            let weighted_value = p.value * fraction;
+
+            Self::traverse_covered_subspaces(0, p, &feature_subspaces, &mut |index| {
+                marginal_values[index] += weighted_value
+            });
+        }
+
+        let weights = (0..marginal_values.len())
+            .map(|mut i| {
+                let mut weight = 1.0;
+                for (_, subspaces) in feature_subspaces.iter().rev() {
+                    let j = i % subspaces.len();
+                    i /= subspaces.len();
+                    weight *= subspaces[j].end - subspaces[j].start;
+                }
+                weight
+            })
+            .collect::<Vec<_>>();
+        let variance = simd::weighted_squared_deviations(&marginal_values, &weights, tree.mean);
+        variance / size / tree.variance
+    }
+}
+
+/// Importance of a feature set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Importance {
+    /// Average of importances across random forest trees.
+    pub mean: f64,
+
+    /// Standard deviation of importances across random forest trees.
+    pub stddev: f64,
+}
+
+/// Significance of the importance of a feature (see `FanovaOptions::test_significance`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Significance {
+    /// Importance of the feature.
+    pub importance: f64,
+
+    /// Mean of the null importances.
+    pub null_mean: f64,
+
+    /// Standard deviation of the null importances.
+    pub null_stddev: f64,
+
+    /// Empirical p-value, i.e., `(1 + k) / (1 + n)` where `k` of the `n` null importances are
+    /// greater than or equal to the importance.
+    pub p_value: f64,
+
+    /// Standard score of the importance with respect to the null importances (infinite if the
+    /// null importances are constant and differ from the importance).
+    pub z_score: f64,
+}
+
+impl Significance {
+    fn new(importance: f64, null: &[f64]) -> Self {
+        let (null_mean, null_stddev) = functions::mean_and_stddev(null.iter().copied());
+        let exceeded = null.iter().filter(|&&x| x >= importance).count();
+        let p_value = (1 + exceeded) as f64 / (1 + null.len()) as f64;
+        let z_score = if null_stddev > 0.0 {
+            (importance - null_mean) / null_stddev
+        } else if importance == null_mean {
+            0.0
+        } else {
+            (importance - null_mean).signum() * f64::INFINITY
+        };
+        Self {
+            importance,
+            null_mean,
+            null_stddev,
+            p_value,
+            z_score,
+        }
+    }
+}
+
+/// Result of `Fanova::top_interactions`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InteractionSearch {
+    /// Importances of the computed pairs sorted in descending order.
+    pub pairs: Vec<((usize, usize), Importance)>,
+
+    /// Skipped pairs with the upper bounds of their importances.
+    pub skipped: Vec<((usize, usize), f64)>,
+}
+
+/// Marginal prediction with its uncertainties (see `Fanova::marginal_uncertainty`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MarginalUncertainty {
+    /// Mean of the marginal predictions of the trees.
+    pub mean: f64,
+
+    /// Standard deviation of the marginal predictions of the trees.
+    pub epistemic: f64,
+
+    /// Standard deviation of the targets within the leaves.
+    pub aleatoric: f64,
+}
+
+/// Result of `FanovaOptions::cross_validate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossValidation {
+    /// Quality of the predictions of all the rows (each predicted by the model not fitted to
+    /// it).
+    pub overall: FitQuality,
+
+    /// Quality of the predictions of the rows of each fold.
+    pub folds: Vec<FitQuality>,
+}
+
+/// Elapsed times (in milliseconds) of the phases of fitting and querying a model (see
+/// `Fanova::profile`), e.g., to find which phase makes a dataset slow.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    /// Preprocessing the table (see `FanovaOptions`) and encoding its categorical features
+    /// (see `CategoricalEncoding`).
+    pub encoding: f64,
+
+    /// Presorting the rows of the table by each feature (unless
+    /// `RandomForestOptions::max_bins` is set).
+    pub sorting: f64,
+
+    /// Fitting each tree and converting it into partitions (in the order of the trees).
+    ///
+    /// The trees are fitted concurrently if `FanovaOptions::parallel` is set, so the sum can
+    /// exceed the elapsed time of the whole fitting.
+    pub trees: Vec<f64>,
+
+    /// Computing the marginal variances of the trees for the importances (accumulated over
+    /// the queries, e.g., `Fanova::quantify_importance`).
+    pub marginals: f64,
+}
+
+/// Result of `FanovaOptions::drop_column_importances`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropColumnImportances {
+    /// Quality of the model fitted to all the features.
+    pub baseline: FitQuality,
+
+    /// Quality of the model fitted without each feature.
+    pub dropped: Vec<FitQuality>,
+}
+
+impl DropColumnImportances {
+    /// Returns the decrease of the R² by dropping each feature.
+    ///
+    /// The importances could be negative if the model generalizes better without a feature
+    /// (e.g., features fitted only to noise).
+    pub fn importances(&self) -> Vec<f64> {
+        self.dropped
+            .iter()
+            .map(|q| self.baseline.r2 - q.r2)
+            .collect()
+    }
+}
+
+/// Possible errors which could be returned by `Fanove::fit` method.
+#[non_exhaustive]
+#[derive(Debug, Error, Clone)]
 pub enum FitError {
     /// Features and target must have one or more rows.
     #[error("features and target must have one or more rows")]
     EmptyRows,
 
-    /// Some of features or target have a different row count from others.
-    #[error("some of features or target have a different row count from others")]
-    RowSizeMismatch,
+    /// Some of features or target have a different row count from others.
+    #[error("some of features or target have a different row count from others")]
+    RowSizeMismatch,
+
+    /// Target contains non finite numbers.
+    #[error("target contains non finite numbers")]
+    NonFiniteTarget,
+
+    /// The given table is invalid.
+    #[error(transparent)]
+    InvalidTable(TableError),
+
+    /// The categorical target must have two or more classes (see
+    /// `FanovaOptions::fit_categorical`).
+    #[error("the categorical target must have two or more classes")]
+    SingleClass,
+
+    /// The targets of `SplitCriterion::Poisson` and `SplitCriterion::Tweedie` must be
+    /// non-negative and not all zero (and positive if the power is `2.0` or more).
+    #[error("the targets are out of the domain of the deviance criterion")]
+    TargetOutOfDomain,
+
+    /// The surrogate model has no out-of-bag rows to be scored (see
+    /// `FanovaOptions::drop_column_importances`).
+    #[error("the surrogate model has no out-of-bag rows")]
+    NoOutOfBagRows,
+
+    /// The model to be resumed has not been fitted to the given table with the same options (see
+    /// `FanovaOptions::resume`).
+    #[error("the model has not been fitted to the given table with the same options")]
+    ModelMismatch,
+
+    /// Issues are found in the input data (see `FanovaOptions::strict`).
+    #[error("invalid input data: {0}")]
+    Validation(ValidationReport),
+}
+
+impl From<TableError> for FitError {
+    fn from(f: TableError) -> Self {
+        match f {
+            TableError::EmptyTable => Self::EmptyRows,
+            TableError::NonFiniteTarget => Self::NonFiniteTarget,
+            TableError::RowSizeMismatch => Self::RowSizeMismatch,
+            e => Self::InvalidTable(e),
+        }
+    }
+}
+
+fn subspaces(partitions: impl Iterator<Item = Range<f64>>) -> Vec<Range<f64>> {
+    let mut subspaces = BTreeMap::new();
+    for p in partitions {
+        insert_subspace(&mut subspaces, p);
+    }
+    subspaces.into_values().collect()
+}
+
+fn insert_subspace(subspaces: &mut BTreeMap<TotalOrder, Range<f64>>, mut p: Range<f64>) {
+    if (p.start - p.end).abs() < f64::EPSILON {
+        return;
+    }
+
+    if let Some(mut q) = subspaces
+        .range(..=TotalOrder(p.start))
+        .next_back()
+        .map(|(_, q)| q.clone())
+    {
+        if (q.start - p.start).abs() < f64::EPSILON {
+            if q.end > p.end {
+                subspaces.remove(&TotalOrder(q.start));
+
+                q.start = p.end;
+                subspaces.insert(TotalOrder(p.start), p);
+                subspaces.insert(TotalOrder(q.start), q);
+            } else {
+                assert!(q.end <= p.end);
+                p.start = q.end;
+                insert_subspace(subspaces, p);
+            }
+        } else {
+            assert!(q.start < p.start);
+            if q.end <= p.start {
+                insert_disjoint_subspace(subspaces, p);
+            } else if q.end > p.end {
+                subspaces.remove(&TotalOrder(q.start));
+
+                let r = Range {
+                    start: p.end,
+                    end: q.end,
+                };
+                q.end = p.start;
+                subspaces.insert(TotalOrder(q.start), q);
+                subspaces.insert(TotalOrder(p.start), p);
+                subspaces.insert(TotalOrder(r.start), r);
+            } else {
+                assert!(q.end <= p.end);
+                subspaces.remove(&TotalOrder(q.start));
+
+                let r = Range {
+                    start: q.end,
+                    end: p.end,
+                };
+                q.end = p.start;
+                p.end = r.start;
+                subspaces.insert(TotalOrder(q.start), q);
+                subspaces.insert(TotalOrder(p.start), p);
+                insert_subspace(subspaces, r);
+            }
+        }
+    } else {
+        insert_disjoint_subspace(subspaces, p);
+    }
+}
+
+// Inserts `p` which doesn't overlap the subspaces starting at or below `p.start` (but may
+// overlap the following ones unless the partitions are visited in the order of a tree).
+fn insert_disjoint_subspace(subspaces: &mut BTreeMap<TotalOrder, Range<f64>>, p: Range<f64>) {
+    let next = subspaces
+        .range(TotalOrder(p.start)..)
+        .next()
+        .map(|(_, q)| q.start)
+        .filter(|&start| start < p.end);
+    match next {
+        Some(start) => {
+            subspaces.insert(TotalOrder(p.start), p.start..start);
+            insert_subspace(subspaces, start..p.end);
+        }
+        None => {
+            subspaces.insert(TotalOrder(p.start), p);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distill::ExplanatoryNode;
+    use crate::random_forest::{SplitCriterion, SplitSampling};
+    use crate::surrogate::SurrogateLeaf;
+    use crate::table::ColumnType;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn quantify_importance_k1_works() -> anyhow::Result<()> {
+        let mut feature1 = Vec::new();
+        let mut feature2 = Vec::new();
+        let mut feature3 = Vec::new();
+        let mut target = Vec::new();
+
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let f1 = rng.gen();
+            let f2 = rng.gen();
+            let f3 = rng.gen();
+            let t = f1 + f2 * 2.0 + f3 * 3.0;
+
+            feature1.push(f1);
+            feature2.push(f2);
+            feature3.push(f3);
+            target.push(t);
+        }
+
+        let mut fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0))
+            .fit(vec![&feature1, &feature2, &feature3], &target)?;
+        let importances = (0..3)
+            .map(|i| fanova.quantify_importance(&[i]).mean)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            importances,
+            vec![
+                0.043620746377350854,
+                0.23006198949738835,
+                0.6002590904515438
+            ]
+        );
+
+        Ok(())
+    }
+
+    // The features and the target of `quantify_importance_k1_works`.
+    fn linear_dataset() -> (Vec<Vec<f64>>, Vec<f64>) {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut features = vec![Vec::new(); 3];
+        let mut target = Vec::new();
+        for _ in 0..100 {
+            let xs: [f64; 3] = rng.gen();
+            for (feature, x) in features.iter_mut().zip(xs) {
+                feature.push(x);
+            }
+            target.push(xs[0] + xs[1] * 2.0 + xs[2] * 3.0);
+        }
+        (features, target)
+    }
+
+    #[test]
+    fn quantify_importance_interval_works() -> anyhow::Result<()> {
+        let (features, target) = linear_dataset();
+        let mut fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0))
+            .fit(features.iter().map(|f| &f[..]).collect(), &target)?;
+        let importance = fanova.quantify_importance(&[2]).mean;
+        let (lower, upper) = fanova.quantify_importance_interval(&[2], 0.9);
+        assert!(lower < importance && importance < upper);
+        Ok(())
+    }
+
+    #[test]
+    fn importances_works() -> anyhow::Result<()> {
+        let (features, target) = linear_dataset();
+        let columns = features.iter().map(|f| &f[..]).collect::<Vec<_>>();
+        let mut fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0))
+            .fit(columns.clone(), &target)?;
+        let importances = (0..3)
+            .map(|i| fanova.quantify_importance(&[i]).mean)
+            .collect::<Vec<_>>();
+        let means = fanova
+            .importances()
+            .iter()
+            .map(|i| i.mean)
+            .collect::<Vec<_>>();
+        assert_eq!(means, importances);
+
+        let table = Table::new(columns.into_iter().chain([&target[..]]).collect())?;
+        assert_eq!(Fanova::new(table)?.importances().len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn try_quantify_importance_works() -> anyhow::Result<()> {
+        let (features, target) = linear_dataset();
+        let mut fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0))
+            .fit(features.iter().map(|f| &f[..]).collect(), &target)?;
+        let importance = fanova.quantify_importance(&[2]).mean;
+        assert_eq!(fanova.try_quantify_importance(&[2])?.mean, importance);
+        assert!(matches!(
+            fanova.try_quantify_importance(&[0, 3]),
+            Err(FanovaError::FeatureOutOfRange {
+                feature: 3,
+                features_len: 3
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn from_trees_works() -> anyhow::Result<()> {
+        let (features, target) = linear_dataset();
+        let table = || Table::new(features.iter().chain([&target]).map(|c| &c[..]).collect());
+
+        // The trees of the forest give the same model as fitting it.
+        let forest = RandomForestOptions::default().seed(0);
+        let mut fitted = FanovaOptions::default()
+            .random_forest(forest.clone())
+            .fit_table(table()?)?;
+        let regressors = RandomForestRegressor::fit(table()?, &forest).into_trees();
+        let trees = regressors
+            .iter()
+            .map(|t| t as &dyn SurrogateTree)
+            .collect::<Vec<_>>();
+        let mut decomposed = Fanova::from_trees(&table()?, &trees);
+        assert_eq!(decomposed.importances(), fitted.importances());
+        assert_eq!(decomposed.fit_quality(), None);
+
+        // A tree of another model depending only on the first feature.
+        #[derive(Debug)]
+        struct Step;
+
+        impl SurrogateTree for Step {
+            fn predict(&self, point: &[f64]) -> f64 {
+                f64::from(u8::from(point[0] > 0.5))
+            }
+
+            fn leaves(&self, space: &[Range<f64>]) -> Vec<SurrogateLeaf> {
+                let (mut lower, mut upper) = (space.to_vec(), space.to_vec());
+                lower[0].end = 0.5;
+                upper[0].start = 0.5;
+                vec![
+                    SurrogateLeaf {
+                        ranges: lower,
+                        value: 0.0,
+                        variance: 0.0,
+                    },
+                    SurrogateLeaf {
+                        ranges: upper,
+                        value: 1.0,
+                        variance: 0.0,
+                    },
+                ]
+            }
+
+            fn depth(&self) -> usize {
+                1
+            }
+        }
+        let mut step = Fanova::from_trees(&table()?, &[&Step]);
+        let importances = step.importances();
+        assert!((importances[0].mean - 1.0).abs() < 1e-12);
+        assert_eq!(importances[1].mean, 0.0);
+        assert_eq!(
+            step.predict(&[0.9, 0.0, 0.0]),
+            Step.predict(&[0.9, 0.0, 0.0])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fanova_fitter_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..50).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..50).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(feature2.iter())
+            .map(|(f1, f2)| f1 + f1 * f2)
+            .collect::<Vec<_>>();
+        let table = Table::new(vec![&feature1, &feature2, &target])?;
+        let forest = RandomForestOptions::default()
+            .seed(0)
+            .trees(NonZeroUsize::new(8).expect("non zero"));
+
+        for options in [
+            FanovaOptions::default().random_forest(forest.clone()),
+            FanovaOptions::default().random_forest(forest).parallel(),
+        ] {
+            let mut expected = options.clone().fit_table(table.clone())?;
+
+            let mut fitter = FanovaFitter::new(options, table.clone());
+            assert_eq!(fitter.progress(), (0, 8));
+            let mut steps = 0;
+            while fitter.step() {
+                steps += 1;
+                assert_eq!(fitter.progress(), (steps, 8));
+            }
+            assert_eq!(steps, 8);
+            let mut fanova = fitter.finish();
+
+            assert_eq!(fanova.to_bytes(false), expected.to_bytes(false));
+            for features in [&[0][..], &[1], &[0, 1]] {
+                assert_eq!(
+                    fanova.quantify_importance(features),
+                    expected.quantify_importance(features)
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn encode_decode_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..50).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..50).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(feature2.iter())
+            .map(|(f1, f2)| f1 * f2)
+            .collect::<Vec<_>>();
+
+        let mut fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0))
+            .fit(vec![&feature1, &feature2], &target)?;
+        let mut encoder = Encoder::new();
+        fanova.encode(&mut encoder);
+        let bytes = encoder.finish();
+
+        let mut decoder = Decoder::new(&bytes);
+        let mut restored = Fanova::decode(&mut decoder).expect("valid model");
+        assert!(decoder.is_empty());
+        for features in [&[0][..], &[1], &[0, 1]] {
+            assert_eq!(
+                restored.quantify_importance(features),
+                fanova.quantify_importance(features)
+            );
+        }
+
+        assert!(Fanova::decode(&mut Decoder::new(&bytes[..bytes.len() - 1])).is_none());
+
+        let bytes = fanova.to_bytes(false);
+        let compressed = fanova.to_bytes(true);
+        assert!(compressed.len() < bytes.len());
+        for bytes in [&bytes, &compressed] {
+            let mut restored = Fanova::from_bytes(bytes)?;
+            assert_eq!(
+                restored.quantify_importance(&[0, 1]),
+                fanova.quantify_importance(&[0, 1])
+            );
+        }
+        assert!(matches!(
+            Fanova::from_bytes(&bytes[1..]),
+            Err(FanovaError::InvalidModel)
+        ));
+        assert!(matches!(
+            Fanova::from_bytes(&compressed[..compressed.len() - 1]),
+            Err(FanovaError::InvalidModel)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn target_scaling_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..50).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..50).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(x1, x2)| x1 + x2 * 2.0)
+            .collect::<Vec<_>>();
+        let fit = |target: &[f64]| {
+            FanovaOptions::default()
+                .random_forest(RandomForestOptions::default().seed(0))
+                .fit(vec![&feature1, &feature2], target)
+        };
+        let mut fanova = fit(&target)?;
+        assert_eq!(fanova.target_scale, TargetScale::IDENTITY);
+        let expected = fanova.quantify_importance(&[1]).mean;
+
+        // The sums of squares of these targets overflow or lose precision without scaling.
+        for (scale, offset) in [(1e200, 0.0), (1e-3, 1e12)] {
+            let scaled = target
+                .iter()
+                .map(|y| y * scale + offset)
+                .collect::<Vec<_>>();
+            let mut fanova = fit(&scaled)?;
+            assert_ne!(fanova.target_scale, TargetScale::IDENTITY);
+            let importance = fanova.quantify_importance(&[1]).mean;
+            assert!((importance - expected).abs() < 0.01, "{importance}");
+
+            let marginal = fanova.marginal_predictions(&[0, 1], &[0.5, 0.5]);
+            let mean = functions::mean(marginal.into_iter());
+            assert!(
+                (mean - (1.5 * scale + offset)).abs() < 0.5 * scale,
+                "{mean}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn shap_values_work() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..50).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..50).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1.iter().map(|x| x * 10.0).collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(10).expect("non zero");
+        let fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees))
+            .fit(vec![&feature1, &feature2], &target)?;
+
+        for point in [[0.9, 0.5], [0.1, 0.3], [f64::NAN, 0.3], [2.0, -1.0]] {
+            let phi = fanova.shap_values(&point);
+            let ranges = fanova.feature_space().ranges();
+            let clamped = [
+                point[0].clamp(ranges[0].start, ranges[0].end),
+                point[1].clamp(ranges[1].start, ranges[1].end),
+            ];
+            let prediction = if point[0].is_nan() {
+                functions::mean(fanova.marginal_predictions(&[1], &clamped[1..]).into_iter())
+            } else {
+                functions::mean(fanova.marginal_predictions(&[0, 1], &clamped).into_iter())
+            };
+            let total = phi.iter().sum::<f64>() + fanova.expected_value();
+            assert!((total - prediction).abs() < 1e-9, "{point:?}");
+            assert!(phi[1].abs() < phi[0].abs() || point[0].is_nan(), "{phi:?}");
+        }
+        assert!(fanova.shap_values(&[0.9, 0.5])[0] > 0.0);
+        assert_eq!(fanova.shap_values(&[f64::NAN, 0.5])[0], 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn predict_with_stddev_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..50).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..50).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1.iter().map(|x| x * 10.0).collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(10).expect("non zero");
+        let fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees))
+            .fit(vec![&feature1, &feature2], &target)?;
+
+        let (low, _) = fanova.predict_with_stddev(&[0.1, 0.5]);
+        let (high, stddev) = fanova.predict_with_stddev(&[0.9, 0.5]);
+        assert!(low < 3.0 && high > 7.0, "{low}, {high}");
+        assert!((0.0..2.0).contains(&stddev), "{stddev}");
+        assert_eq!(fanova.predict(&[0.9, 0.5]), high);
+
+        // The points are clamped into the feature space.
+        let ranges = fanova.feature_space().ranges();
+        let corner = [ranges[0].end, ranges[1].start];
+        assert_eq!(
+            fanova.predict_with_stddev(&[5.0, -5.0]),
+            fanova.predict_with_stddev(&corner)
+        );
+
+        let per_tree = fanova.predict_per_tree(&[0.9, 0.5]);
+        assert_eq!(per_tree.len(), 10);
+        let (mean, std) = functions::mean_and_stddev(per_tree.into_iter());
+        assert!((mean - high).abs() < 1e-9 && (std - stddev).abs() < 1e-9);
+        assert_eq!(
+            fanova.predict_per_tree(&[5.0, -5.0]),
+            fanova.predict_per_tree(&corner)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn top_quantile_works() -> anyhow::Result<()> {
+        // Rare outliers of `x1` dominate the variance, but `x0` decides whether the target is in
+        // the lowest 5%.
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(&x0, &x1)| {
+                let good = if x0 < 0.1 { -1.0 } else { 0.0 };
+                let outlier = if x1 > 0.9 { 1000.0 } else { 0.0 };
+                good + outlier
+            })
+            .collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(10).expect("non zero");
+        let options = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees));
+
+        let mut fanova = options.clone().fit(vec![&feature1, &feature2], &target)?;
+        assert!(fanova.quantify_importance(&[1]).mean > fanova.quantify_importance(&[0]).mean);
+
+        let mut fanova = options
+            .top_quantile(0.05)
+            .fit(vec![&feature1, &feature2], &target)?;
+        let importances = fanova.importances();
+        assert!(importances[0].mean > importances[1].mean, "{importances:?}");
+        let (good, _) = fanova.predict_with_stddev(&[0.05, 0.5]);
+        let (bad, _) = fanova.predict_with_stddev(&[0.5, 0.5]);
+        assert!(good > 0.5 && bad < 0.1, "{good}, {bad}");
+        Ok(())
+    }
+
+    #[test]
+    fn importance_convergence_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(&x0, &x1)| 10.0 * x0 + x1)
+            .collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(20).expect("non zero");
+        let mut fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees))
+            .fit(vec![&feature1, &feature2], &target)?;
+
+        let convergence = fanova.importance_convergence(&[0]);
+        assert_eq!(convergence.len(), 20);
+        assert_eq!(convergence[0].stddev, 0.0);
+        let last = convergence[19];
+        let expected = fanova.quantify_importance(&[0]);
+        assert!((last.mean - expected.mean).abs() < 1e-12);
+        assert!((last.stddev - expected.stddev).abs() < 1e-12);
+        let tree_importances = fanova.tree_importances(&[0]);
+        let (mean, _) = functions::mean_and_stddev(tree_importances[..5].iter().copied());
+        assert!((convergence[4].mean - mean).abs() < 1e-12);
+
+        assert!(fanova
+            .importance_convergence(&[2])
+            .iter()
+            .all(|i| i.mean == 0.0));
+        Ok(())
+    }
+
+    #[test]
+    fn preset_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(&x0, &x1)| 10.0 * x0 + x1)
+            .collect::<Vec<_>>();
+
+        let mut fanova = FanovaOptions::preset(Preset::ReferenceFanova)
+            .fit(vec![&feature1, &feature2], &target)?;
+        assert_eq!(fanova.trees_len(), 16);
+        let importances = fanova.importances();
+        assert!(importances[0].mean > importances[1].mean, "{importances:?}");
+
+        // All the features are the candidates of each split.
+        let trees = NonZeroUsize::new(16).expect("non zero");
+        let seeded = Preset::ReferenceFanova.random_forest().seed(0);
+        let explicit = RandomForestOptions::default()
+            .seed(0)
+            .trees(trees)
+            .max_features(NonZeroUsize::new(2).expect("non zero"));
+        let mut preset = FanovaOptions::new()
+            .random_forest(seeded)
+            .fit(vec![&feature1, &feature2], &target)?;
+        let mut expected = FanovaOptions::new()
+            .random_forest(explicit)
+            .fit(vec![&feature1, &feature2], &target)?;
+        assert_eq!(preset.importances(), expected.importances());
+        Ok(())
+    }
+
+    #[test]
+    fn best_rows_works() -> anyhow::Result<()> {
+        // `x0` separates the good rows from the bad ones, but only `x1` matters among the good
+        // ones.
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(&x0, &x1)| if x0 < 0.2 { x1 } else { 100.0 })
+            .collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(10).expect("non zero");
+        let options = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees));
+
+        let mut fanova = options.clone().fit(vec![&feature1, &feature2], &target)?;
+        assert!(fanova.quantify_importance(&[0]).mean > fanova.quantify_importance(&[1]).mean);
+
+        let count = NonZeroUsize::new(20).expect("non zero");
+        for rows in [BestRows::Count(count), BestRows::Fraction(0.1)] {
+            let mut fanova = options
+                .clone()
+                .best_rows(rows, false)
+                .fit(vec![&feature1, &feature2], &target)?;
+            let importances = fanova.importances();
+            assert!(importances[1].mean > importances[0].mean, "{importances:?}");
+            assert!(fanova.feature_space().ranges()[0].end < 0.2);
+        }
+
+        // The largest targets are the best when maximizing.
+        let fanova = options
+            .best_rows(BestRows::Fraction(0.5), true)
+            .fit(vec![&feature1, &feature2], &target)?;
+        assert!(fanova.feature_space().ranges()[0].start >= 0.2);
+        Ok(())
+    }
+
+    #[test]
+    fn poisson_works() -> anyhow::Result<()> {
+        // The features multiply the counts, so they don't interact in the log counts.
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..300).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..300).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(&x0, &x1)| (3.0 * x0 + x1).exp().round())
+            .collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(10).expect("non zero");
+        let forest = RandomForestOptions::default().seed(0).trees(trees);
+        let interaction = |criterion| -> anyhow::Result<f64> {
+            let options =
+                FanovaOptions::default().random_forest(forest.clone().criterion(criterion));
+            let mut fanova = options.fit(vec![&feature1, &feature2], &target)?;
+            Ok(fanova.quantify_importance(&[0, 1]).mean)
+        };
+        let squared_error = interaction(SplitCriterion::SquaredError)?;
+        let poisson = interaction(SplitCriterion::Poisson)?;
+        assert!(poisson < squared_error / 2.0, "{poisson}, {squared_error}");
+
+        let options =
+            FanovaOptions::default().random_forest(forest.criterion(SplitCriterion::Poisson));
+        let mut fanova = options.clone().fit(vec![&feature1, &feature2], &target)?;
+        let importances = fanova.importances();
+        assert!(importances[0].mean > importances[1].mean, "{importances:?}");
+        let prediction = fanova.predict(&[0.5, 0.5]);
+        assert!((prediction - 2.0).abs() < 0.3, "{prediction}");
+        let quality = fanova.fit_quality().expect("out-of-bag rows");
+        assert!(quality.r2 > 0.8, "{quality:?}");
+
+        let mut negative = target.clone();
+        negative[0] = -1.0;
+        assert!(matches!(
+            options.clone().fit(vec![&feature1, &feature2], &negative),
+            Err(FitError::TargetOutOfDomain)
+        ));
+        assert!(matches!(
+            options.fit(vec![&feature1, &feature2], &vec![0.0; 300]),
+            Err(FitError::TargetOutOfDomain)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn tweedie_works() -> anyhow::Result<()> {
+        // GPU hours: the pruned trials (small `x1`) consume nothing and the others consume more
+        // with larger `x0`.
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..300).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..300).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(&x0, &x1)| if x1 < 0.3 { 0.0 } else { (4.0 * x0).exp() })
+            .collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(10).expect("non zero");
+        let forest = RandomForestOptions::default().seed(0).trees(trees);
+        let options = |power| {
+            let criterion = SplitCriterion::Tweedie { power };
+            FanovaOptions::default().random_forest(forest.clone().criterion(criterion))
+        };
+
+        let mut fanova = options(1.5).fit(vec![&feature1, &feature2], &target)?;
+        let importances = fanova.importances();
+        // The zeros have the smallest log means, so the pruning dominates.
+        assert!(importances[1].mean > importances[0].mean, "{importances:?}");
+        assert!(importances[0].mean > 0.05, "{importances:?}");
+        let prediction = fanova.predict(&[0.5, 0.8]);
+        assert!((prediction - 2.0).abs() < 0.5, "{prediction}");
+        let quality = fanova.fit_quality().expect("out-of-bag rows");
+        assert!(quality.r2 > 0.8, "{quality:?}");
+
+        // The power `1` is the Poisson deviance.
+        let poisson = FanovaOptions::default()
+            .random_forest(forest.clone().criterion(SplitCriterion::Poisson))
+            .fit(vec![&feature1, &feature2], &target)?
+            .importances();
+        let tweedie = options(1.0)
+            .fit(vec![&feature1, &feature2], &target)?
+            .importances();
+        assert_eq!(tweedie, poisson);
+
+        // The gamma deviance requires positive targets.
+        assert!(matches!(
+            options(2.0).fit(vec![&feature1, &feature2], &target),
+            Err(FitError::TargetOutOfDomain)
+        ));
+        let positive = target.iter().map(|y| y + 1.0).collect::<Vec<_>>();
+        assert!(options(2.0)
+            .fit(vec![&feature1, &feature2], &positive)
+            .is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn winsorize_target_works() -> anyhow::Result<()> {
+        // Two diverged trials of large `x1` dominate the variance, though `x0` decides the
+        // others.
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let mut feature2 = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        feature2[0] = 0.99;
+        feature2[1] = 0.98;
+        let mut target = feature1.clone();
+        target[0] = 1e6;
+        target[1] = 1e6;
+        let trees = NonZeroUsize::new(10).expect("non zero");
+        let options = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees));
+
+        let mut fanova = options.clone().fit(vec![&feature1, &feature2], &target)?;
+        assert_eq!(fanova.target_bounds(), None);
+        assert!(fanova.quantify_importance(&[1]).mean > fanova.quantify_importance(&[0]).mean);
+
+        let mut fanova = options
+            .winsorize_target(0.0, 0.98)
+            .fit(vec![&feature1, &feature2], &target)?;
+        let (lower, upper) = fanova.target_bounds().expect("winsorized");
+        assert_eq!(lower, functions::quantile(target.iter().copied(), 0.0));
+        assert!((0.9..1.0).contains(&upper), "{upper}");
+        let importances = fanova.importances();
+        assert!(importances[0].mean > 0.9, "{importances:?}");
+        assert!(fanova.predict(&[0.5, 0.99]) <= upper);
+
+        let restored = Fanova::from_bytes(&fanova.to_bytes(false))?;
+        assert_eq!(restored.target_bounds(), Some((lower, upper)));
+        Ok(())
+    }
+
+    #[test]
+    fn standardize_target_works() -> anyhow::Result<()> {
+        // The same study measured in seconds and in milliseconds.
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let seconds = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(&x0, &x1)| 10.0 + x0 * 2.0 + x1)
+            .collect::<Vec<_>>();
+        let milliseconds = seconds.iter().map(|y| y * 1000.0).collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(10).expect("non zero");
+        let options = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees))
+            .standardize_target();
+
+        let mut a = options.clone().fit(vec![&feature1, &feature2], &seconds)?;
+        let mut b = options
+            .clone()
+            .fit(vec![&feature1, &feature2], &milliseconds)?;
+        let (mean, stddev) = a.target_standardization().expect("standardized");
+        let (expected_mean, expected_stddev) = functions::mean_and_stddev(seconds.iter().copied());
+        assert_eq!((mean, stddev), (expected_mean, expected_stddev));
+        let (b_mean, b_stddev) = b.target_standardization().expect("standardized");
+        assert!((b_mean / mean - 1000.0).abs() < 1e-9 && (b_stddev / stddev - 1000.0).abs() < 1e-9);
+
+        // The rounding errors may break the ties of the splits differently.
+        for point in [[0.1, 0.2], [0.5, 0.5], [0.9, 0.3]] {
+            let (pa, pb) = (a.predict(&point), b.predict(&point));
+            assert!((pa - pb).abs() < 0.1, "{pa}, {pb}");
+            let original = pa * stddev + mean;
+            let expected = 10.0 + point[0] * 2.0 + point[1];
+            assert!((original - expected).abs() < 0.5, "{original}, {expected}");
+        }
+        let (ia, ib) = (a.importances(), b.importances());
+        assert!((ia[0].mean - ib[0].mean).abs() < 0.05, "{ia:?}, {ib:?}");
+        let rmse = |f: &Fanova| f.fit_quality().expect("out-of-bag rows").rmse;
+        assert!(
+            (rmse(&a) - rmse(&b)).abs() < 0.1,
+            "{} {}",
+            rmse(&a),
+            rmse(&b)
+        );
+
+        let restored = Fanova::from_bytes(&a.to_bytes(false))?;
+        assert_eq!(restored.target_standardization(), Some((mean, stddev)));
+
+        // The held-out targets are compared in the original scale.
+        let table = Table::new(vec![&feature1, &feature2, &milliseconds])?;
+        let cv = options.cross_validate(table, 5, 0)?;
+        assert!(cv.overall.r2 > 0.8, "{cv:?}");
+        Ok(())
+    }
+
+    #[test]
+    fn early_stopping_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(&x, &y)| 10.0 * x + y)
+            .collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(500).expect("non zero");
+        let interval = NonZeroUsize::new(10).expect("non zero");
+        let options = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees));
+
+        let mut fanova = options
+            .clone()
+            .early_stopping(interval, 0.01)
+            .fit(vec![&feature1, &feature2], &target)?;
+        let stopped = fanova.trees_len();
+        assert!((20..500).contains(&stopped), "{stopped}");
+        assert!(stopped.is_multiple_of(10));
+
+        let mut full = options.clone().fit(vec![&feature1, &feature2], &target)?;
+        assert_eq!(full.trees_len(), 500);
+        for (a, b) in fanova.importances().iter().zip(full.importances()) {
+            assert!((a.mean - b.mean).abs() < 0.05, "{a:?}, {b:?}");
+        }
+
+        // The parallel fitting stops at the same tree.
+        let parallel = options
+            .clone()
+            .parallel()
+            .early_stopping(interval, 0.01)
+            .fit(vec![&feature1, &feature2], &target)?;
+        assert_eq!(parallel.trees_len(), stopped);
+
+        // A zero tolerance wouldn't stop unless the trees are identical.
+        let fanova = options
+            .early_stopping(interval, 0.0)
+            .fit(vec![&feature1, &feature2], &target)?;
+        assert_eq!(fanova.trees_len(), 500);
+        Ok(())
+    }
+
+    #[test]
+    fn time_budget_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(&x, &y)| 10.0 * x + y)
+            .collect::<Vec<_>>();
+        let options =
+            FanovaOptions::default().random_forest(RandomForestOptions::default().seed(0));
+
+        // A zero budget fits only one tree (even in parallel).
+        let mut fanova = options
+            .clone()
+            .parallel()
+            .time_budget(Duration::ZERO)
+            .fit(vec![&feature1, &feature2], &target)?;
+        assert!(fanova.is_partial());
+        assert_eq!(fanova.trees_len(), 1);
+        assert!(fanova.importances()[0].mean > 0.5);
+        let restored = Fanova::from_bytes(&fanova.to_bytes(false))?;
+        assert!(restored.is_partial());
+
+        let mut full = options.clone().fit(vec![&feature1, &feature2], &target)?;
+        let mut budgeted = options
+            .time_budget(Duration::from_secs(60))
+            .fit(vec![&feature1, &feature2], &target)?;
+        assert!(!full.is_partial() && !budgeted.is_partial());
+        assert_eq!(budgeted.trees_len(), full.trees_len());
+        assert_eq!(budgeted.importances(), full.importances());
+        Ok(())
+    }
+
+    #[test]
+    fn resume_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(&x, &y)| 10.0 * x + y)
+            .collect::<Vec<_>>();
+        let table = || Table::new(vec![&feature1, &feature2, &target]);
+        let trees = NonZeroUsize::new(30).expect("non zero");
+
+        // An unseeded fitting interrupted after the first tree (and saved) resumes the sequence
+        // of its seed.
+        let options = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().trees(trees))
+            .time_budget(Duration::ZERO);
+        let partial = options.clone().fit_table(table()?)?;
+        let state = partial.rng_state();
+        assert_eq!(state.trees, 1);
+        let mut resumed = Fanova::from_bytes(&partial.to_bytes(false))?;
+        assert_eq!(resumed.rng_state(), state);
+        FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().trees(trees))
+            .resume(table()?, &mut resumed)?;
+        assert!(!resumed.is_partial());
+
+        let mut full = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().trees(trees).seed(state.seed))
+            .fit_table(table()?)?;
+        assert_eq!(resumed.trees_len(), 30);
+        assert_eq!(resumed.rng_state(), full.rng_state());
+        assert_eq!(resumed.fit_quality(), full.fit_quality());
+        assert_eq!(resumed.importances(), full.importances());
+
+        // The out-of-bag rows on the thresholds (between the values missing in the bootstrap
+        // samples) are predicted as by the fitted trees.
+        let xs = (0..50).map(f64::from).collect::<Vec<_>>();
+        let ys = xs.iter().map(|x| x * 2.0).collect::<Vec<_>>();
+        let options =
+            FanovaOptions::default().random_forest(RandomForestOptions::default().seed(0));
+        let full = options.clone().fit(vec![&xs, &feature1[..50]], &ys)?;
+        let mut resumed = options
+            .clone()
+            .time_budget(Duration::ZERO)
+            .fit(vec![&xs, &feature1[..50]], &ys)?;
+        let grid = Table::new(vec![&xs, &feature1[..50], &ys])?;
+        options.resume(grid, &mut resumed)?;
+        assert_eq!(resumed.fit_quality(), full.fit_quality());
+
+        // The convergence checks of early stopping are replayed (one tree is added per resume).
+        let interval = NonZeroUsize::new(5).expect("non zero");
+        let options = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0))
+            .early_stopping(interval, 0.05);
+        let stopped = options.clone().fit_table(table()?)?;
+        assert!(stopped.trees_len() < 100);
+        let options = options.time_budget(Duration::ZERO);
+        let mut resumed = options.clone().fit_table(table()?)?;
+        while resumed.is_partial() {
+            options.clone().resume(table()?, &mut resumed)?;
+        }
+        assert_eq!(resumed.trees_len(), stopped.trees_len());
+        assert_eq!(resumed.fit_quality(), stopped.fit_quality());
+
+        // The model must have been fitted to the same table with the same options.
+        let mut partial = options.clone().fit_table(table()?)?;
+        let mut restricted = partial.restrict(&[Some(vec![0.0..0.5]), None]);
+        let other = Table::new(vec![&feature2, &feature1, &target])?;
+        assert!(matches!(
+            options.clone().resume(other, &mut partial),
+            Err(FitError::ModelMismatch)
+        ));
+        assert_eq!(partial.trees_len(), 1);
+        assert!(matches!(
+            options.resume(table()?, &mut restricted),
+            Err(FitError::ModelMismatch)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn profile_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(a, b)| a + 2.0 * b)
+            .collect::<Vec<_>>();
+        let table = || Table::new(vec![&feature1, &feature2, &target]);
+        let trees = NonZeroUsize::new(8).expect("non zero");
+        let options = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees));
+
+        for options in [options.clone(), options.clone().parallel()] {
+            let mut fanova = options.fit_table(table()?)?;
+            let profile = fanova.profile().clone();
+            assert_eq!(profile.trees.len(), 8);
+            assert!(profile.trees.iter().all(|&t| t > 0.0));
+            assert!(profile.encoding >= 0.0 && profile.sorting >= 0.0);
+            assert_eq!(profile.marginals, 0.0);
+
+            fanova.importances();
+            assert!(fanova.profile().marginals > 0.0);
+            assert_eq!(fanova.profile().trees, profile.trees);
+        }
+
+        // The times of the trees fitted before an interruption are kept.
+        let options = options.time_budget(Duration::ZERO);
+        let mut resumed = options.clone().fit_table(table()?)?;
+        while resumed.is_partial() {
+            options.clone().resume(table()?, &mut resumed)?;
+        }
+        assert_eq!(resumed.profile().trees.len(), 8);
+
+        let restored = Fanova::from_bytes(&resumed.to_bytes(false))?;
+        assert_eq!(restored.profile(), &Profile::default());
+        Ok(())
+    }
+
+    #[test]
+    fn categorical_encoding_works() -> anyhow::Result<()> {
+        // Only the category `2` (of `0..5`) raises the target, which takes two ordinal splits.
+        let mut rng = StdRng::seed_from_u64(0);
+        let categories = (0..200)
+            .map(|_| f64::from(rng.gen_range(0..5)))
+            .collect::<Vec<_>>();
+        let noise = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = categories
+            .iter()
+            .zip(&noise)
+            .map(|(&c, &x)| f64::from(u8::from(c == 2.0)) + 0.1 * x)
+            .collect::<Vec<_>>();
+        let table = || -> Result<Table, TableError> {
+            let mut table = Table::new(vec![&categories, &noise, &target])?;
+            table.set_column_type(0, ColumnType::Categorical)?;
+            Ok(table)
+        };
+        let depth = NonZeroUsize::new(1).expect("non zero");
+        let forest = RandomForestOptions::default().seed(0).max_depth(depth);
+        let options = FanovaOptions::default().random_forest(forest);
+
+        // The gap between the marginal of the category `2` and those of its neighbours.
+        let gap = |fanova: &mut Fanova| {
+            let marginal = |c| functions::mean(fanova.marginal_predictions(&[0], &[c]).into_iter());
+            marginal(2.0) - marginal(1.0).max(marginal(3.0))
+        };
+        let mut ordinal = options.clone().fit_table(table()?)?;
+        let one_hot_options = options.categorical_encoding(CategoricalEncoding::OneHot);
+        for options in [one_hot_options.clone(), one_hot_options.clone().parallel()] {
+            let mut one_hot = options.fit_table(table()?)?;
+            assert_eq!(
+                one_hot.feature_space().ranges(),
+                ordinal.feature_space().ranges()
+            );
+            assert!(gap(&mut one_hot) > 0.5);
+            assert!(gap(&mut one_hot) > 2.0 * gap(&mut ordinal));
+            assert!(one_hot.quantify_importance(&[0]).mean > 0.5);
+        }
+
+        // The encoded fittings are also resumed.
+        let options = one_hot_options.clone().time_budget(Duration::ZERO);
+        let mut resumed = options.clone().fit_table(table()?)?;
+        while resumed.is_partial() {
+            options.clone().resume(table()?, &mut resumed)?;
+        }
+        let mut full = one_hot_options.fit_table(table()?)?;
+        assert_eq!(resumed.fit_quality(), full.fit_quality());
+        assert_eq!(resumed.importances(), full.importances());
+        Ok(())
+    }
+
+    #[test]
+    fn target_encoding_works() -> anyhow::Result<()> {
+        // The effects of 40 categories (in a random order of their indices).
+        let mut rng = StdRng::seed_from_u64(0);
+        let effects = (0..40).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let categories = (0..400)
+            .map(|_| f64::from(rng.gen_range(0..40)))
+            .collect::<Vec<_>>();
+        let noise = (0..400).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = categories
+            .iter()
+            .zip(&noise)
+            .map(|(&c, &x)| effects[c as usize] + 0.1 * x)
+            .collect::<Vec<_>>();
+        let mut table = Table::new(vec![&categories, &noise, &target])?;
+        table.set_column_type(0, ColumnType::Categorical)?;
+
+        let forest = RandomForestOptions::default().seed(0);
+        let mut fanova = FanovaOptions::default()
+            .random_forest(forest)
+            .categorical_encoding(CategoricalEncoding::Target)
+            .fit_table(table)?;
+        assert_eq!(fanova.feature_space().ranges()[0], -0.5..39.5);
+        assert!(fanova.quantify_importance(&[0]).mean > 0.8);
+
+        // The marginals follow the effects.
+        let marginals = (0..40)
+            .map(|c| {
+                functions::mean(
+                    fanova
+                        .marginal_predictions(&[0], &[f64::from(c)])
+                        .into_iter(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut ranked = (0..40).collect::<Vec<_>>();
+        ranked.sort_by(|&i, &j| effects[i].total_cmp(&effects[j]));
+        assert!(marginals[ranked[39]] - marginals[ranked[0]] > 0.5);
+        Ok(())
+    }
+
+    #[test]
+    fn native_encoding_works() -> anyhow::Result<()> {
+        // The categories `0`, `2` and `4` (of `0..5`) raise the target, which no single ordinal
+        // or one-hot split separates.
+        let mut rng = StdRng::seed_from_u64(0);
+        let categories = (0..200)
+            .map(|_| f64::from(rng.gen_range(0..5)))
+            .collect::<Vec<_>>();
+        let noise = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = categories
+            .iter()
+            .zip(&noise)
+            .map(|(&c, &x)| f64::from(u8::from(c % 2.0 == 0.0)) + 0.1 * x)
+            .collect::<Vec<_>>();
+        let table = || -> Result<Table, TableError> {
+            let mut table = Table::new(vec![&categories, &noise, &target])?;
+            table.set_column_type(0, ColumnType::Categorical)?;
+            Ok(table)
+        };
+        let depth = NonZeroUsize::new(1).expect("non zero");
+        let forest = RandomForestOptions::default().seed(0).max_depth(depth);
+        let options = FanovaOptions::default()
+            .random_forest(forest)
+            .categorical_encoding(CategoricalEncoding::Native);
+
+        let mut native = options.clone().fit_table(table()?)?;
+        let mut parallel = options.parallel().fit_table(table()?)?;
+        assert_eq!(native.importances(), parallel.importances());
+        assert_eq!(native.feature_space().ranges()[0], -0.5..4.5);
+        assert!(native.quantify_importance(&[0]).mean > 0.9);
+        let marginal = |c| functions::mean(native.marginal_predictions(&[0], &[c]).into_iter());
+        let (raised, others) = ([0.0, 2.0, 4.0].map(marginal), [1.0, 3.0].map(marginal));
+        assert!(raised.iter().all(|&m| others.iter().all(|&n| m - n > 0.8)));
+
+        let trees = ExtraTreesOptions::default().seed(0);
+        let mut extra_trees = FanovaOptions::default()
+            .extra_trees(trees)
+            .categorical_encoding(CategoricalEncoding::Native)
+            .fit_table(table()?)?;
+        assert!(extra_trees.quantify_importance(&[0]).mean > 0.9);
+        Ok(())
+    }
+
+    #[test]
+    fn subspaces_works() {
+        // In the order of a tree (the upper partitions first).
+        let ranges = [0.5..1.0, 0.0..0.5, 0.0..1.0];
+        assert_eq!(subspaces(ranges.into_iter()), [0.0..0.5, 0.5..1.0]);
+
+        // In any order.
+        let ranges = [0.0..0.3, 0.6..1.0, 0.3..1.0, 0.0..1.0];
+        assert_eq!(
+            subspaces(ranges.into_iter()),
+            [0.0..0.3, 0.3..0.6, 0.6..1.0]
+        );
+    }
+
+    #[test]
+    fn excluded_features_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let constant = vec![1.0; 100];
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(&x, &y)| 10.0 * x + y)
+            .collect::<Vec<_>>();
+        let columns = vec![&feature1[..], &constant, &feature2, &feature1];
+        let options =
+            FanovaOptions::default().random_forest(RandomForestOptions::default().seed(0));
+
+        for options in [options.clone(), options.parallel()] {
+            let mut fanova = options.fit(columns.clone(), &target)?;
+            assert_eq!(
+                fanova.excluded_features(),
+                [
+                    ExcludedFeature::Constant { feature: 1 },
+                    ExcludedFeature::Duplicate {
+                        feature: 3,
+                        original: 0
+                    }
+                ]
+            );
+            let importances = fanova.importances();
+            assert_eq!(importances[1].mean, 0.0);
+            assert!(importances[3].mean.abs() < 1e-12, "{:?}", importances[3]);
+            assert!(importances[0].mean > importances[2].mean);
+            assert!(importances[2].mean > 0.0);
+            assert_eq!(fanova.quantify_importance(&[0, 1]).mean, 0.0);
+
+            let restored = Fanova::from_bytes(&fanova.to_bytes(false))?;
+            assert_eq!(restored.excluded_features(), fanova.excluded_features());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn cross_validate_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let noise = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1.iter().map(|&x| 10.0 * x).collect::<Vec<_>>();
+        let table = Table::new(vec![&feature1, &noise, &target])?;
+        let options =
+            FanovaOptions::default().random_forest(RandomForestOptions::default().seed(0));
+
+        let cv = options.clone().cross_validate(table.clone(), 5, 0)?;
+        assert_eq!(cv.folds.len(), 5);
+        assert!(
+            cv.folds.iter().all(|q| q.rows == 20 && q.r2 > 0.8),
+            "{cv:?}"
+        );
+        assert_eq!(cv.overall.rows, 100);
+        assert!(cv.overall.r2 > 0.9 && cv.overall.rmse < 1.0, "{cv:?}");
+        assert_eq!(options.clone().cross_validate(table.clone(), 5, 0)?, cv);
+
+        // The target can't be predicted from noise.
+        let table = Table::new(vec![&noise, &target])?;
+        let cv = options.cross_validate(table, 5, 0)?;
+        assert!(cv.overall.r2 < 0.3, "{cv:?}");
+        Ok(())
+    }
+
+    #[test]
+    fn drop_column_importances_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let correlated = feature1
+            .iter()
+            .map(|x| x + rng.gen_range(-0.01..0.01))
+            .collect::<Vec<_>>();
+        let feature3 = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let noise = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature3)
+            .map(|(x1, x3)| x1 + 2.0 * x3)
+            .collect::<Vec<_>>();
+        let table = Table::new(vec![&feature1, &correlated, &feature3, &noise, &target])?;
+        let options =
+            FanovaOptions::default().random_forest(RandomForestOptions::default().seed(0));
+
+        // The correlated features substitute each other.
+        let result = options
+            .clone()
+            .drop_column_importances(table.clone(), None)?;
+        let importances = result.importances();
+        assert_eq!(result.dropped.len(), 4);
+        assert!(importances[2] > 0.3, "{importances:?}");
+        assert!(importances[0].abs() < 0.05 && importances[1].abs() < 0.05);
+        assert!(importances[3].abs() < 0.05, "{importances:?}");
+        let mut fanova = options.clone().fit_table(table.clone())?;
+        assert_eq!(Some(result.baseline), fanova.fit_quality());
+        assert!(fanova.importances()[0].mean > importances[0]);
+
+        let (train, test) = table.split(0.7, 0)?;
+        let result = options.drop_column_importances(train.clone(), Some(&test))?;
+        assert_eq!(result.baseline.rows, test.rows_len());
+        assert!(result.importances()[2] > 0.3, "{result:?}");
+
+        let options = FanovaOptions::default().extra_trees(ExtraTreesOptions::default().seed(0));
+        let e = options
+            .clone()
+            .drop_column_importances(train.clone(), None)
+            .expect_err("no out-of-bag rows");
+        assert!(matches!(e, FitError::NoOutOfBagRows));
+        assert!(options.drop_column_importances(train, Some(&test)).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn restrict_works() -> anyhow::Result<()> {
+        // The second feature matters only once the first one is in its good range.
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(&x1, &x2)| if x1 < 0.5 { 10.0 } else { x2 })
+            .collect::<Vec<_>>();
+        let table = Table::new(vec![&feature1, &feature2, &target])?;
+        let options =
+            FanovaOptions::default().random_forest(RandomForestOptions::default().seed(0));
+        let mut fanova = options.clone().fit_table(table.clone())?;
+        let importances = fanova.importances();
+        assert!(importances[0].mean > 0.8, "{importances:?}");
 
-    /// Target contains non finite numbers.
-    #[error("target contains non finite numbers")]
-    NonFiniteTarget,
-}
+        let domain = [Some(vec![0.5..1.0]), None];
+        // The trees splitting the first feature slightly above `0.5` leak the high values.
+        let mut restricted = fanova.restrict(&domain);
+        let importances = restricted.importances();
+        assert!(importances[1].mean > 0.6, "{importances:?}");
+        let ranges = restricted.feature_space().ranges();
+        assert_eq!(ranges[0], 0.5..fanova.feature_space().ranges()[0].end);
+        assert_eq!(ranges[1], fanova.feature_space().ranges()[1]);
+        assert!((restricted.predict(&[0.8, 0.9]) - 0.9).abs() < 0.2);
 
-impl From<TableError> for FitError {
-    fn from(f: TableError) -> Self {
-        match f {
-            TableError::EmptyTable => Self::EmptyRows,
-            TableError::NonFiniteTarget => Self::NonFiniteTarget,
-            TableError::RowSizeMismatch => Self::RowSizeMismatch,
+        let mut refitted = options.clone().fit_restricted(table.clone(), &domain)?;
+        let importances = refitted.importances();
+        assert!(importances[1].mean > 0.9, "{importances:?}");
+
+        // The gap between the ranges is removed.
+        let domain = [Some(vec![0.0..0.25, 0.75..1.0]), None];
+        let mut restricted = fanova.restrict(&domain);
+        let (start, end) = (fanova.feature_space().ranges()[0].start, 0.75);
+        let range = restricted.feature_space().ranges()[0].clone();
+        assert!((range.end - range.start - (0.25 - start) - (end - 0.75)).abs() < 0.3);
+        assert!(restricted.predict(&[0.1, 0.5]) > 9.0);
+        assert!(restricted.predict(&[range.end, 0.9]) < 2.0);
+        let importances = restricted.importances();
+        assert!(importances[0].mean > 0.5, "{importances:?}");
+
+        let domain = [Some(vec![2.0..3.0]), None];
+        let e = options.fit_restricted(table, &domain).expect_err("no rows");
+        assert!(matches!(e, FitError::EmptyRows));
+        Ok(())
+    }
+
+    #[test]
+    fn quantify_importances_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let features = (0..4)
+            .map(|_| (0..100).map(|_| rng.gen()).collect::<Vec<f64>>())
+            .collect::<Vec<_>>();
+        let target = (0..100)
+            .map(|i| features[0][i] * features[1][i] + features[2][i])
+            .collect::<Vec<_>>();
+        let options =
+            FanovaOptions::default().random_forest(RandomForestOptions::default().seed(0));
+        let columns = features.iter().map(|c| &c[..]).collect::<Vec<_>>();
+        let mut sequential = options.clone().fit(columns.clone(), &target)?;
+        let mut parallel = options.parallel().fit(columns, &target)?;
+
+        let mut feature_sets = (0..4)
+            .flat_map(|a| (a + 1..4).map(move |b| vec![a, b]))
+            .collect::<Vec<_>>();
+        feature_sets.extend([vec![3, 1], vec![0, 1, 2], vec![4]]);
+        let expected = feature_sets
+            .iter()
+            .map(|features| sequential.quantify_importance(features))
+            .collect::<Vec<_>>();
+        assert_eq!(parallel.quantify_importances(&feature_sets), expected);
+        assert_eq!(sequential.quantify_importances(&feature_sets), expected);
+        assert_eq!(parallel.importances(), sequential.importances());
+        Ok(())
+    }
+
+    #[test]
+    fn top_interactions_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let features = (0..4)
+            .map(|_| (0..200).map(|_| rng.gen()).collect::<Vec<f64>>())
+            .collect::<Vec<_>>();
+        let target = (0..200)
+            .map(|i| 10.0 * features[0][i] * features[1][i] + 3.0 * features[2][i])
+            .collect::<Vec<_>>();
+        let mut fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0))
+            .fit(features.iter().map(|c| &c[..]).collect(), &target)?;
+
+        let search = fanova.top_interactions(3, 0.0);
+        assert_eq!(search.pairs.len(), 3);
+        assert_eq!(search.pairs[0].0, (0, 1));
+        assert_eq!(search.pairs[0].1, fanova.quantify_importance(&[0, 1]));
+        let skipped = search.skipped.iter().map(|s| s.0).collect::<Vec<_>>();
+        assert_eq!(skipped, [(0, 3), (1, 3), (2, 3)]);
+
+        // The bounds are never less than the importances.
+        let full = fanova.top_interactions(4, f64::NEG_INFINITY);
+        assert_eq!((full.pairs.len(), full.skipped.len()), (6, 0));
+        for &((a, b), bound) in &search.skipped {
+            let importance = fanova.quantify_importance(&[a, b]).mean;
+            assert!(
+                importance <= bound + 1e-9,
+                "{a}, {b}: {importance} > {bound}"
+            );
         }
+
+        let pruned = fanova.top_interactions(4, 0.05);
+        assert!(pruned.pairs.iter().any(|p| p.0 == (0, 1)));
+        assert!(pruned.skipped.iter().all(|s| s.1 < 0.05));
+        assert_eq!(pruned.pairs.len() + pruned.skipped.len(), 6);
+
+        // No pairs are worth computing if the target is additive.
+        let target = (0..200)
+            .map(|i| features[0][i] + 2.0 * features[1][i] + 3.0 * features[2][i])
+            .collect::<Vec<_>>();
+        let mut fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0))
+            .fit(features.iter().map(|c| &c[..]).collect(), &target)?;
+        let pruned = fanova.top_interactions(4, 0.05);
+        assert!(!pruned.skipped.is_empty());
+        Ok(())
     }
-}
 
-fn subspaces(partitions: impl Iterator<Item = Range<f64>>) -> Vec<Range<f64>> {
-    let mut subspaces = BTreeMap::new();
-    for p in partitions {
-        insert_subspace(&mut subspaces, p);
+    #[test]
+    fn distill_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(&x, &y)| if x < 0.5 { 0.0 } else { 10.0 } + y)
+            .collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(10).expect("non zero");
+        let fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees))
+            .fit(vec![&feature1, &feature2], &target)?;
+
+        let options = DistillOptions::new().seed(0);
+        let tree = fanova.distill(&options);
+        assert!(tree.fidelity > 0.9, "{}", tree.fidelity);
+        assert!(matches!(
+            tree.root,
+            ExplanatoryNode::Split { feature: 0, .. }
+        ));
+        Ok(())
     }
-    subspaces.into_iter().map(|(_, v)| v).collect()
-}
 
-fn insert_subspace(subspaces: &mut BTreeMap<OrderedFloat<f64>, Range<f64>>, mut p: Range<f64>) {
-    if (p.start - p.end).abs() < std::f64::EPSILON {
-        return;
+    #[test]
+    fn split_thresholds_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature3 = vec![1.0; 100];
+        let target = feature1
+            .iter()
+            .map(|&x| if x < 0.5 { 0.0 } else { 10.0 })
+            .collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(5).expect("non zero");
+        let fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees))
+            .fit(vec![&feature1, &feature2, &feature3], &target)?;
+
+        let thresholds = fanova.split_thresholds();
+        assert_eq!(thresholds.len(), 3);
+        assert!(thresholds[2].is_empty());
+        assert!(thresholds[0].windows(2).all(|w| w[0].0 < w[1].0));
+        let splits = thresholds.iter().flatten().map(|(_, n)| n).sum::<usize>();
+        assert_eq!(splits, fanova.tree_leaves().sum::<usize>() - 5);
+
+        // The thresholds around the step are drawn from several bootstrap samples.
+        let step_splits = thresholds[0]
+            .iter()
+            .filter(|(t, _)| (t - 0.5).abs() < 0.05)
+            .map(|(_, n)| n)
+            .sum::<usize>();
+        assert!(step_splits >= 5, "{step_splits}");
+        Ok(())
     }
 
-    if let Some(mut q) = subspaces
-        .range(..=OrderedFloat(p.start))
-        .rev()
-        .next()
-        .map(|(_, q)| q.clone())
-    {
-        if (q.start - p.start).abs() < std::f64::EPSILON {
-            if q.end > p.end {
-                subspaces.remove(&OrderedFloat(q.start));
+    #[test]
+    fn split_frequencies_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature3 = vec![1.0; 100];
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(&x, &y)| if x < 0.5 { 0.0 } else { 10.0 } + y)
+            .collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(5).expect("non zero");
+        let mut fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees))
+            .fit(vec![&feature1, &feature2, &feature3], &target)?;
 
-                q.start = p.end;
-                subspaces.insert(OrderedFloat(p.start), p);
-                subspaces.insert(OrderedFloat(q.start), q);
-            } else {
-                assert!(q.end <= p.end);
-                p.start = q.end;
-                insert_subspace(subspaces, p);
-            }
-        } else {
-            assert!(q.start < p.start);
-            if q.end > p.end {
-                subspaces.remove(&OrderedFloat(q.start));
+        let frequencies = fanova.split_frequencies();
+        let total = frequencies.iter().map(|f| f.mean).sum::<f64>();
+        assert!((total - 1.0).abs() < 1e-12, "{frequencies:?}");
+        assert_eq!(frequencies[2].mean, 0.0);
 
-                let r = Range {
-                    start: p.end,
-                    end: q.end,
-                };
-                q.end = p.start;
-                subspaces.insert(OrderedFloat(q.start), q);
-                subspaces.insert(OrderedFloat(p.start), p);
-                subspaces.insert(OrderedFloat(r.start), r);
-            } else {
-                assert!(q.end <= p.end);
-                subspaces.remove(&OrderedFloat(q.start));
+        // The trees split `feature2` more often to fit its small slope, though `feature1`
+        // explains most of the variance.
+        assert!(frequencies[1].mean > frequencies[0].mean, "{frequencies:?}");
+        assert!(frequencies[0].mean > 0.1, "{frequencies:?}");
+        let importances = fanova.importances();
+        assert!(importances[0].mean > 0.9, "{importances:?}");
+        Ok(())
+    }
 
-                let r = Range {
-                    start: q.end,
-                    end: p.end,
-                };
-                q.end = p.start;
-                p.end = r.start;
-                subspaces.insert(OrderedFloat(q.start), q);
-                subspaces.insert(OrderedFloat(p.start), p);
-                insert_subspace(subspaces, r);
+    #[test]
+    fn tree_structure_works() -> anyhow::Result<()> {
+        // Returns the number of the leaves and the sum of the gains.
+        fn summarize(node: &TreeNode) -> (usize, f64) {
+            match node {
+                TreeNode::Leaf { leaves, .. } => (*leaves, 0.0),
+                TreeNode::Split {
+                    gain, left, right, ..
+                } => {
+                    let (l, lg) = summarize(left);
+                    let (r, rg) = summarize(right);
+                    (l + r, gain + lg + rg)
+                }
             }
         }
-    } else {
-        subspaces.insert(OrderedFloat(p.start), p);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(&x, &y)| if x < 0.5 { 0.0 } else { 10.0 } + y)
+            .collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(3).expect("non zero");
+        let fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees))
+            .fit(vec![&feature1, &feature2], &target)?;
+
+        let root = fanova.tree_structure(1, None);
+        let (leaves, gains) = summarize(&root);
+        assert_eq!(leaves, fanova.tree_leaves().nth(1).expect("three trees"));
+        assert!((gains - 1.0).abs() < 1e-9, "{gains}");
+        let TreeNode::Split {
+            feature,
+            fraction,
+            gain,
+            ..
+        } = &root
+        else {
+            panic!("{root:?}");
+        };
+        assert_eq!(*feature, 0);
+        assert!((fraction - 1.0).abs() < 1e-9);
+        assert!(*gain > 0.8, "{gain}");
+        for _ in 0..100 {
+            let point = [rng.gen(), rng.gen()];
+            let expected = fanova.predict_per_tree(&point)[1];
+            assert!((root.predict(&point) - expected).abs() < 1e-9);
+        }
+
+        let pruned = fanova.tree_structure(1, Some(1));
+        let TreeNode::Split { left, right, .. } = &pruned else {
+            panic!("{pruned:?}");
+        };
+        assert!(matches!(**left, TreeNode::Leaf { .. }));
+        assert!(matches!(**right, TreeNode::Leaf { .. }));
+        assert_eq!(summarize(&pruned).0, leaves);
+        assert!((pruned.value() - root.value()).abs() < 1e-9);
+        assert!(matches!(
+            fanova.tree_structure(1, Some(0)),
+            TreeNode::Leaf { leaves, .. } if leaves == summarize(&root).0
+        ));
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rand::rngs::StdRng;
-    use rand::{Rng, SeedableRng};
+    #[test]
+    fn marginal_sensitivities_work() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(&x, &y)| x * 10.0 - (y - 0.5).powi(2) * 4.0)
+            .collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(10).expect("non zero");
+        let fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees))
+            .fit(vec![&feature1, &feature2], &target)?;
+
+        let low = fanova.marginal_sensitivities(&[0.5, 0.2], 0.1);
+        assert!((low[0] - 10.0).abs() < 3.0, "{low:?}");
+        assert!(low[1] > 0.0, "{low:?}");
+        let high = fanova.marginal_sensitivities(&[0.5, 0.8], 0.1);
+        assert!(high[1] < 0.0, "{high:?}");
+
+        // The bounds are differenced one-sidedly.
+        let bound = fanova.marginal_sensitivities(&[5.0, 0.2], 0.1);
+        assert!(bound[0] > 0.0 && bound[0].is_finite(), "{bound:?}");
+        Ok(())
+    }
 
     #[test]
-    fn quantify_importance_k1_works() -> anyhow::Result<()> {
-        let mut feature1 = Vec::new();
-        let mut feature2 = Vec::new();
-        let mut feature3 = Vec::new();
-        let mut target = Vec::new();
+    fn test_significance_works() -> anyhow::Result<()> {
+        // `feature2` is noise.
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..30).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..30).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1.iter().map(|x| x * 10.0).collect::<Vec<_>>();
+        let table = Table::new(vec![&feature1, &feature2, &target])?;
+        let trees = NonZeroUsize::new(10).expect("non zero");
+        let permutations = NonZeroUsize::new(19).expect("non zero");
+        let significances = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees))
+            .test_significance(table, permutations, 0)?;
+
+        assert_eq!(significances[0].p_value, 0.05);
+        assert!(significances[0].z_score > 3.0, "{significances:?}");
+        assert!(significances[1].p_value > 0.05, "{significances:?}");
+        assert!(significances[1].importance < significances[0].importance);
+
+        let significance = Significance::new(0.5, &[0.1, 0.1, 0.5]);
+        assert_eq!(significance.p_value, 0.5);
+        assert_eq!(Significance::new(0.5, &[0.1, 0.1]).z_score, f64::INFINITY);
+        Ok(())
+    }
 
+    #[test]
+    fn sobol_indices_work() -> anyhow::Result<()> {
         let mut rng = StdRng::seed_from_u64(0);
-        for _ in 0..100 {
-            let f1 = rng.gen();
-            let f2 = rng.gen();
-            let f3 = rng.gen();
-            let t = f1 + f2 * 2.0 + f3 * 3.0;
+        let feature1 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(x, y)| x * 10.0 + y)
+            .collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(10).expect("non zero");
+        let mut fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees))
+            .fit(vec![&feature1, &feature2], &target)?;
 
-            feature1.push(f1);
-            feature2.push(f2);
-            feature3.push(f3);
-            target.push(t);
+        let samples = NonZeroUsize::new(1024).expect("non zero");
+        let indices = fanova.sobol_indices(samples);
+        let importances = fanova.importances();
+        for (s, importance) in indices.iter().zip(&importances) {
+            assert!((s.first_order - importance.mean).abs() < 0.05, "{s:?}");
+            assert!(s.first_order <= s.total + 0.01, "{s:?}");
         }
+        assert!(indices[0].first_order > 0.9);
+        Ok(())
+    }
+
+    #[test]
+    fn local_importances_work() -> anyhow::Result<()> {
+        // `feature2` only matters when `feature1` is large.
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(&x, &y)| if x > 0.5 { y * 10.0 } else { x })
+            .collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(10).expect("non zero");
+        let fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees))
+            .fit(vec![&feature1, &feature2], &target)?;
 
+        let low = fanova.local_importances(&[0.2, 0.5], 20);
+        assert!((low.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        let high = fanova.local_importances(&[0.8, 0.5], 20);
+        assert!(low[1] < high[1], "{low:?}, {high:?}");
+        assert!(high[1] > 0.5, "{high:?}");
+        Ok(())
+    }
+
+    #[test]
+    fn h_statistics_work() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature3 = (0..200).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .zip(&feature3)
+            .map(|((x, y), z)| (x - 0.5) * (y - 0.5) + z)
+            .collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(10).expect("non zero");
         let mut fanova = FanovaOptions::default()
-            .random_forest(RandomForestOptions::default().seed(0))
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees))
             .fit(vec![&feature1, &feature2, &feature3], &target)?;
-        let importances = (0..3)
-            .map(|i| fanova.quantify_importance(&[i]).mean)
+
+        let h01 = fanova.h_statistic(0, 1);
+        let h02 = fanova.h_statistic(0, 2);
+        assert!(h01 > 0.5 && h01 <= 1.0, "{h01}");
+        assert!(h02 < h01, "{h02}");
+        assert_eq!(fanova.h_statistic(1, 0), h01);
+        assert_eq!(fanova.h_statistic(0, 0), 0.0);
+        assert_eq!(fanova.h_statistic(0, 3), 0.0);
+
+        let samples = NonZeroUsize::new(1024).expect("non zero");
+        let total = fanova.total_h_statistics(samples);
+        assert_eq!(total.len(), 3);
+        assert!(total.iter().all(|h| (0.0..=1.0).contains(h)));
+        assert!(total[0] > total[2] && total[1] > total[2], "{total:?}");
+        Ok(())
+    }
+
+    #[test]
+    fn partial_dependence_2d_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..50).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..50).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature3 = (0..50).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .zip(&feature3)
+            .map(|((x, y), z)| x * y + z)
+            .collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(10).expect("non zero");
+        let fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees))
+            .fit(vec![&feature1, &feature2, &feature3], &target)?;
+
+        let grid_a = [-1.0, 0.2, 0.5, 0.9];
+        let grid_b = [0.1, 0.7, 2.0];
+        let dependence = fanova.partial_dependence_2d(0, 1, &grid_a, &grid_b);
+        assert_eq!(dependence.len(), 4);
+        let ranges = fanova.feature_space().ranges();
+        for (i, &x) in grid_a.iter().enumerate() {
+            for (j, &y) in grid_b.iter().enumerate() {
+                let point = [
+                    x.clamp(ranges[0].start, ranges[0].end),
+                    y.clamp(ranges[1].start, ranges[1].end),
+                ];
+                let expected =
+                    functions::mean(fanova.marginal_predictions(&[0, 1], &point).into_iter());
+                assert!((dependence[i][j] - expected).abs() < 1e-9, "i={i}, j={j}");
+            }
+        }
+        assert!(dependence[3][2] > dependence[0][0]);
+        Ok(())
+    }
+
+    #[test]
+    fn marginal_optimum_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..100).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(x, y)| (x - 0.3).powi(2) + y)
             .collect::<Vec<_>>();
+        let trees = NonZeroUsize::new(10).expect("non zero");
+        let fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0).trees(trees))
+            .fit(vec![&feature1, &feature2], &target)?;
+
+        let (range, prediction) = fanova.marginal_optimum(0, false);
+        assert!(range.start > 0.1 && range.end < 0.5, "{range:?}");
+        for x in [0.1, 0.5, 0.9] {
+            let other = functions::mean(fanova.marginal_predictions(&[0], &[x]).into_iter());
+            assert!(prediction <= other);
+        }
+        let (range, prediction) = fanova.marginal_optimum(1, true);
+        assert!(range.end > 0.9, "{range:?}");
+        assert!(prediction > fanova.expected_value());
+        Ok(())
+    }
+
+    #[test]
+    fn strict_mode_works() -> anyhow::Result<()> {
+        let feature = [0.0, 1.0, 0.0, 2.0];
+        let constant = [1.0; 4];
+        let target = [1.0, 2.0, 3.0, 4.0];
+        let options = || {
+            let trees = NonZeroUsize::new(2).expect("non zero");
+            FanovaOptions::default()
+                .random_forest(RandomForestOptions::default().seed(0).trees(trees))
+        };
+
+        assert!(options().fit(vec![&feature, &constant], &target).is_ok());
+        let Err(FitError::Validation(report)) =
+            options().strict().fit(vec![&feature, &constant], &target)
+        else {
+            panic!("strict fitting must fail");
+        };
         assert_eq!(
-            importances,
-            vec![0.04285945139294453, 0.23639697156594727, 0.5975522202656363]
+            report.issues(),
+            [
+                Issue::ConstantFeature { feature: 1 },
+                Issue::DuplicatedRows { rows: vec![2] }
+            ]
+        );
+
+        let table = Table::new(vec![&feature, &target])?;
+        assert!(options().strict().fit_table(table.clone()).is_err());
+        assert_eq!(options().fit_table(table.clone())?.duplicated_rows(), 1);
+        let options = options().strict().aggregate_duplicates(Aggregation::Mean);
+        let fanova = options.fit_table(table)?;
+        assert_eq!(fanova.duplicated_rows(), 1);
+        let mut encoder = Encoder::new();
+        fanova.encode(&mut encoder);
+        let restored = Fanova::decode(&mut Decoder::new(&encoder.finish())).expect("valid model");
+        assert_eq!(restored.duplicated_rows(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn replace_trees_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature = (0..50).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature.iter().map(|x| x * x).collect::<Vec<_>>();
+        let options = |trees| {
+            let trees = NonZeroUsize::new(trees).expect("non zero");
+            FanovaOptions::default()
+                .random_forest(RandomForestOptions::default().seed(0).trees(trees))
+        };
+
+        let mut fanova = options(10).fit(vec![&feature[..40]], &target[..40])?;
+        let newer = options(4).fit(vec![&feature], &target)?;
+        let space = newer.feature_space.clone();
+        fanova.replace_trees(newer);
+
+        assert_eq!(fanova.trees_len(), 10);
+        assert_eq!(fanova.feature_space.ranges(), space.ranges());
+        for tree in &fanova.trees {
+            let size = tree.partitions.iter().map(|p| p.space.size()).sum::<f64>();
+            assert!((size - space.size()).abs() < 1e-12);
+        }
+        assert!(fanova.quantify_importance(&[0]).mean > 0.99);
+
+        Ok(())
+    }
+
+    #[test]
+    fn marginal_predictions_works() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..50).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..50).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1.iter().map(|x| x * 10.0).collect::<Vec<_>>();
+
+        let fanova = FanovaOptions::default()
+            .random_forest(RandomForestOptions::default().seed(0))
+            .fit(vec![&feature1, &feature2], &target)?;
+        let low = functions::mean(fanova.marginal_predictions(&[0], &[0.1]).into_iter());
+        let high = functions::mean(fanova.marginal_predictions(&[0], &[0.9]).into_iter());
+        assert!((low - 1.0).abs() < 1.0);
+        assert!((high - 9.0).abs() < 1.0);
+
+        let ranges = fanova.feature_space().ranges();
+        let upper = [ranges[0].end, ranges[1].end];
+        assert_eq!(fanova.marginal_predictions(&[0, 1], &upper).len(), 100);
+
+        Ok(())
+    }
+
+    #[test]
+    fn marginal_uncertainty_works() -> anyhow::Result<()> {
+        // The target is noisy only in the upper half of the first feature.
+        let mut rng = StdRng::seed_from_u64(0);
+        let feature1 = (0..400).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let feature2 = (0..400).map(|_| rng.gen()).collect::<Vec<f64>>();
+        let target = feature1
+            .iter()
+            .map(|&x| {
+                if x < 0.5 {
+                    0.0
+                } else {
+                    10.0 + rng.gen_range(-3.0..3.0)
+                }
+            })
+            .collect::<Vec<_>>();
+        let min_samples_leaf = NonZeroUsize::new(20).expect("non zero");
+        let fanova = FanovaOptions::default()
+            .random_forest(
+                RandomForestOptions::default()
+                    .seed(0)
+                    .min_samples_leaf(min_samples_leaf),
+            )
+            .fit(vec![&feature1, &feature2], &target)?;
+
+        let quiet = fanova.marginal_uncertainty(0, 0.2);
+        let noisy = fanova.marginal_uncertainty(0, 0.8);
+        assert!(quiet.mean.abs() < 0.1 && (noisy.mean - 10.0).abs() < 0.5);
+        assert!(quiet.aleatoric < 0.1, "{quiet:?}");
+        // The standard deviation of the uniform noise is `3 / sqrt(3)`.
+        assert!((noisy.aleatoric - 3.0f64.sqrt()).abs() < 0.3, "{noisy:?}");
+        assert!(noisy.epistemic > 0.0 && noisy.epistemic < noisy.aleatoric);
+        let mean = functions::mean(fanova.marginal_predictions(&[0], &[0.8]).into_iter());
+        assert_eq!(noisy.mean, mean);
+        assert_eq!(
+            fanova.marginal_uncertainty(0, 2.0),
+            fanova.marginal_uncertainty(0, 1.0)
         );
 
+        let restored = Fanova::from_bytes(&fanova.to_bytes(false))?;
+        assert_eq!(restored.marginal_uncertainty(0, 0.8), noisy);
+        Ok(())
+    }
+
+    // The importances must be identical on all targets (including wasm) and regardless of
+    // the parallelism, so they are pinned here.
+    #[test]
+    fn deterministic_results_work() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let features = (0..3)
+            .map(|_| (0..2000).map(|_| rng.gen()).collect::<Vec<f64>>())
+            .collect::<Vec<_>>();
+        let target = (0..2000)
+            .map(|i| features[0][i] * features[1][i] + features[2][i] * 2.0)
+            .collect::<Vec<_>>();
+
+        let n = |n| NonZeroUsize::new(n).expect("non zero");
+        let options = RandomForestOptions::default().seed(0).trees(n(8));
+        let goss = SplitSampling::Goss {
+            top: 0.2,
+            other: 0.1,
+        };
+        let cases = [
+            (options.clone(), 0.04752012394321727),
+            (options.clone().max_bins(n(16)), 0.05079373284014841),
+            (
+                options
+                    .clone()
+                    .split_sampling(SplitSampling::Uniform(n(256))),
+                0.04769729954873189,
+            ),
+            (options.split_sampling(goss), 0.0480775799341732),
+        ];
+        for (options, expected) in cases {
+            for fanova_options in [
+                FanovaOptions::default().random_forest(options.clone()),
+                FanovaOptions::default().random_forest(options).parallel(),
+            ] {
+                let mut fanova =
+                    fanova_options.fit(features.iter().map(|f| &f[..]).collect(), &target)?;
+                assert_eq!(fanova.quantify_importance(&[0]).mean, expected);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn extra_trees_work() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let features = (0..3)
+            .map(|_| (0..200).map(|_| rng.gen()).collect::<Vec<f64>>())
+            .collect::<Vec<_>>();
+        let target = (0..200)
+            .map(|i| features[0][i] + features[1][i] * 2.0 + features[2][i] * 3.0)
+            .collect::<Vec<_>>();
+
+        let trees = NonZeroUsize::new(10).expect("non zero");
+        let options = ExtraTreesOptions::default().seed(0).trees(trees);
+        let mut importances = Vec::new();
+        for fanova_options in [
+            FanovaOptions::default().extra_trees(options.clone()),
+            FanovaOptions::default().extra_trees(options).parallel(),
+        ] {
+            let mut fanova =
+                fanova_options.fit(features.iter().map(|f| &f[..]).collect(), &target)?;
+            assert_eq!(fanova.trees_len(), 10);
+            assert_eq!(fanova.fit_quality(), None);
+            importances.push(
+                fanova
+                    .importances()
+                    .into_iter()
+                    .map(|i| i.mean)
+                    .collect::<Vec<_>>(),
+            );
+        }
+        assert_eq!(importances[0], importances[1]);
+        let importances = &importances[0];
+        assert!(importances[0] < importances[1] && importances[1] < importances[2]);
         Ok(())
     }
 
@@ -436,12 +4732,12 @@ mod tests {
         assert_eq!(
             importances,
             vec![
-                0.07680860824384894,
-                0.22398368444798653,
-                0.19379724508736923,
-                0.07470334097342736,
-                0.060765985323840255,
-                0.3294309902816728
+                0.07599451375519135,
+                0.2036996040805318,
+                0.1955528236979387,
+                0.08049837549767513,
+                0.07766825045886538,
+                0.31656875408264884
             ]
         );
 
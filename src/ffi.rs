@@ -0,0 +1,427 @@
+//! C interface (see `include/fanova.h`).
+//!
+//! An evaluator is created by `fanova_evaluator_new`, fed with the feature columns and the
+//! target, and released by `fanova_evaluator_free`.
+use crate::{Fanova, FanovaError, FanovaOptions, FitError, RandomForestOptions, TableError};
+use std::slice;
+
+/// The operation succeeded.
+pub const FANOVA_OK: i32 = 0;
+
+/// A pointer argument is null.
+pub const FANOVA_ERROR_NULL_POINTER: i32 = 1;
+
+/// The evaluator has no feature columns or no target.
+pub const FANOVA_ERROR_INVALID_STATE: i32 = 2;
+
+/// The length of a column or an output buffer is wrong.
+pub const FANOVA_ERROR_LENGTH_MISMATCH: i32 = 3;
+
+/// The columns have no rows.
+pub const FANOVA_ERROR_EMPTY_ROWS: i32 = 4;
+
+/// The target contains non finite numbers.
+pub const FANOVA_ERROR_NON_FINITE_TARGET: i32 = 5;
+
+/// A feature index is out of range or the number of points is less than two.
+pub const FANOVA_ERROR_INVALID_ARGUMENT: i32 = 6;
+
+/// fANOVA evaluator which owns the copies of the given columns.
+///
+/// The model is fitted lazily and refitted after the columns or the seed are changed.
+#[derive(Debug, Default)]
+pub struct FanovaEvaluator {
+    features: Vec<Vec<f64>>,
+    target: Option<Vec<f64>>,
+    seed: Option<u64>,
+    fanova: Option<Fanova>,
+}
+
+impl FanovaEvaluator {
+    fn fanova(&mut self) -> Result<&mut Fanova, i32> {
+        if self.fanova.is_none() {
+            let Some(target) = &self.target else {
+                return Err(FANOVA_ERROR_INVALID_STATE);
+            };
+            if self.features.is_empty() {
+                return Err(FANOVA_ERROR_INVALID_STATE);
+            }
+
+            let mut random_forest = RandomForestOptions::new();
+            if let Some(seed) = self.seed {
+                random_forest = random_forest.seed(seed);
+            }
+            let features = self.features.iter().map(|c| &c[..]).collect();
+            let fanova = FanovaOptions::new()
+                .random_forest(random_forest)
+                .fit(features, target)
+                .map_err(|e| status(&e.into()))?;
+            self.fanova = Some(fanova);
+        }
+        Ok(self.fanova.as_mut().expect("fitted"))
+    }
+
+    fn importances(&mut self, means: &mut [f64], stddevs: &mut [f64]) -> Result<(), i32> {
+        let importances = self.fanova()?.importances();
+        for (i, importance) in importances.into_iter().enumerate() {
+            means[i] = importance.mean;
+            stddevs[i] = importance.stddev;
+        }
+        Ok(())
+    }
+
+    fn marginal(
+        &mut self,
+        feature: usize,
+        grid: &mut [f64],
+        means: &mut [f64],
+        stddevs: &mut [f64],
+    ) -> Result<(), i32> {
+        if feature >= self.features.len() || grid.len() < 2 {
+            return Err(FANOVA_ERROR_INVALID_ARGUMENT);
+        }
+        let points = self.fanova()?.marginal_grid(feature, grid.len());
+        for (i, (x, mean, stddev)) in points.into_iter().enumerate() {
+            grid[i] = x;
+            means[i] = mean;
+            stddevs[i] = stddev;
+        }
+        Ok(())
+    }
+}
+
+fn status(e: &FanovaError) -> i32 {
+    match e {
+        FanovaError::Table(TableError::EmptyTable) | FanovaError::Fit(FitError::EmptyRows) => {
+            FANOVA_ERROR_EMPTY_ROWS
+        }
+        FanovaError::Table(TableError::NonFiniteTarget)
+        | FanovaError::Fit(FitError::NonFiniteTarget) => FANOVA_ERROR_NON_FINITE_TARGET,
+        FanovaError::Table(_)
+        | FanovaError::Fit(FitError::RowSizeMismatch | FitError::InvalidTable(_)) => {
+            FANOVA_ERROR_LENGTH_MISMATCH
+        }
+        _ => FANOVA_ERROR_INVALID_ARGUMENT,
+    }
+}
+
+// Copies `len` values from `values` (`null` is allowed only if `len` is zero).
+unsafe fn copy_values(values: *const f64, len: usize) -> Option<Vec<f64>> {
+    if len == 0 {
+        Some(Vec::new())
+    } else if values.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts(values, len).to_vec())
+    }
+}
+
+fn into_status(result: Result<(), i32>) -> i32 {
+    result.err().unwrap_or(FANOVA_OK)
+}
+
+/// Creates an evaluator.
+///
+/// The returned evaluator must be released by `fanova_evaluator_free`.
+#[no_mangle]
+pub extern "C" fn fanova_evaluator_new() -> *mut FanovaEvaluator {
+    Box::into_raw(Box::default())
+}
+
+/// Releases an evaluator (`null` is ignored).
+///
+/// # Safety
+///
+/// `evaluator` must be a pointer returned by `fanova_evaluator_new` which has not been released.
+#[no_mangle]
+pub unsafe extern "C" fn fanova_evaluator_free(evaluator: *mut FanovaEvaluator) {
+    if !evaluator.is_null() {
+        drop(Box::from_raw(evaluator));
+    }
+}
+
+/// Sets the seed of the random forest (otherwise a random seed is used).
+///
+/// # Safety
+///
+/// `evaluator` must be a live pointer returned by `fanova_evaluator_new`.
+#[no_mangle]
+pub unsafe extern "C" fn fanova_evaluator_set_seed(
+    evaluator: *mut FanovaEvaluator,
+    seed: u64,
+) -> i32 {
+    let Some(evaluator) = evaluator.as_mut() else {
+        return FANOVA_ERROR_NULL_POINTER;
+    };
+    evaluator.seed = Some(seed);
+    evaluator.fanova = None;
+    FANOVA_OK
+}
+
+/// Appends a feature column (`values[0..len]` is copied).
+///
+/// # Safety
+///
+/// `evaluator` must be a live pointer returned by `fanova_evaluator_new` and
+/// `values` must point to `len` readable values.
+#[no_mangle]
+pub unsafe extern "C" fn fanova_evaluator_push_feature(
+    evaluator: *mut FanovaEvaluator,
+    values: *const f64,
+    len: usize,
+) -> i32 {
+    let Some(evaluator) = evaluator.as_mut() else {
+        return FANOVA_ERROR_NULL_POINTER;
+    };
+    let Some(values) = copy_values(values, len) else {
+        return FANOVA_ERROR_NULL_POINTER;
+    };
+    evaluator.features.push(values);
+    evaluator.fanova = None;
+    FANOVA_OK
+}
+
+/// Sets the target column (`values[0..len]` is copied).
+///
+/// # Safety
+///
+/// `evaluator` must be a live pointer returned by `fanova_evaluator_new` and
+/// `values` must point to `len` readable values.
+#[no_mangle]
+pub unsafe extern "C" fn fanova_evaluator_set_target(
+    evaluator: *mut FanovaEvaluator,
+    values: *const f64,
+    len: usize,
+) -> i32 {
+    let Some(evaluator) = evaluator.as_mut() else {
+        return FANOVA_ERROR_NULL_POINTER;
+    };
+    let Some(values) = copy_values(values, len) else {
+        return FANOVA_ERROR_NULL_POINTER;
+    };
+    evaluator.target = Some(values);
+    evaluator.fanova = None;
+    FANOVA_OK
+}
+
+/// Fits a model and writes the importance of each feature to `means[0..len]` and
+/// `stddevs[0..len]`, where `len` must be the number of the feature columns.
+///
+/// # Safety
+///
+/// `evaluator` must be a live pointer returned by `fanova_evaluator_new` and
+/// both `means` and `stddevs` must point to `len` writable values.
+#[no_mangle]
+pub unsafe extern "C" fn fanova_evaluator_importances(
+    evaluator: *mut FanovaEvaluator,
+    means: *mut f64,
+    stddevs: *mut f64,
+    len: usize,
+) -> i32 {
+    let Some(evaluator) = evaluator.as_mut() else {
+        return FANOVA_ERROR_NULL_POINTER;
+    };
+    if means.is_null() || stddevs.is_null() {
+        return FANOVA_ERROR_NULL_POINTER;
+    }
+    if len != evaluator.features.len() {
+        return FANOVA_ERROR_LENGTH_MISMATCH;
+    }
+    let means = slice::from_raw_parts_mut(means, len);
+    let stddevs = slice::from_raw_parts_mut(stddevs, len);
+    into_status(evaluator.importances(means, stddevs))
+}
+
+/// Fits a model (if needed) and writes the importance of the feature set
+/// `features[0..len]` to `mean` and `stddev`.
+///
+/// Out of range features yield zero importance.
+///
+/// # Safety
+///
+/// `evaluator` must be a live pointer returned by `fanova_evaluator_new`,
+/// `features` must point to `len` readable values and
+/// both `mean` and `stddev` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn fanova_evaluator_quantify_importance(
+    evaluator: *mut FanovaEvaluator,
+    features: *const usize,
+    len: usize,
+    mean: *mut f64,
+    stddev: *mut f64,
+) -> i32 {
+    let Some(evaluator) = evaluator.as_mut() else {
+        return FANOVA_ERROR_NULL_POINTER;
+    };
+    if (features.is_null() && len != 0) || mean.is_null() || stddev.is_null() {
+        return FANOVA_ERROR_NULL_POINTER;
+    }
+    let features = if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(features, len)
+    };
+    match evaluator.fanova() {
+        Ok(fanova) => {
+            let importance = fanova.quantify_importance(features);
+            *mean = importance.mean;
+            *stddev = importance.stddev;
+            FANOVA_OK
+        }
+        Err(status) => status,
+    }
+}
+
+/// Fits a model (if needed) and writes the marginal predictions of `feature` at `n_points`
+/// equally spaced points to `grid`, `means` and `stddevs` (across the trees).
+///
+/// # Safety
+///
+/// `evaluator` must be a live pointer returned by `fanova_evaluator_new` and
+/// `grid`, `means` and `stddevs` must point to `n_points` writable values.
+#[no_mangle]
+pub unsafe extern "C" fn fanova_evaluator_marginal(
+    evaluator: *mut FanovaEvaluator,
+    feature: usize,
+    grid: *mut f64,
+    means: *mut f64,
+    stddevs: *mut f64,
+    n_points: usize,
+) -> i32 {
+    let Some(evaluator) = evaluator.as_mut() else {
+        return FANOVA_ERROR_NULL_POINTER;
+    };
+    if grid.is_null() || means.is_null() || stddevs.is_null() {
+        return FANOVA_ERROR_NULL_POINTER;
+    }
+    let grid = slice::from_raw_parts_mut(grid, n_points);
+    let means = slice::from_raw_parts_mut(means, n_points);
+    let stddevs = slice::from_raw_parts_mut(stddevs, n_points);
+    into_status(evaluator.marginal(feature, grid, means, stddevs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    #[test]
+    fn ffi_works() {
+        let feature1 = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+        let feature2 = [1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+        let target = [0.0, 1.0, 4.0, 9.0, 16.0, 25.0, 36.0, 49.0];
+        let mut means = [0.0; 2];
+        let mut stddevs = [0.0; 2];
+
+        unsafe {
+            let evaluator = fanova_evaluator_new();
+            assert_eq!(
+                fanova_evaluator_importances(
+                    evaluator,
+                    means.as_mut_ptr(),
+                    stddevs.as_mut_ptr(),
+                    0
+                ),
+                FANOVA_ERROR_INVALID_STATE
+            );
+
+            assert_eq!(fanova_evaluator_set_seed(evaluator, 0), FANOVA_OK);
+            for feature in [&feature1, &feature2] {
+                let status = fanova_evaluator_push_feature(evaluator, feature.as_ptr(), 8);
+                assert_eq!(status, FANOVA_OK);
+            }
+            assert_eq!(
+                fanova_evaluator_set_target(evaluator, target.as_ptr(), 7),
+                FANOVA_OK
+            );
+            assert_eq!(
+                fanova_evaluator_importances(
+                    evaluator,
+                    means.as_mut_ptr(),
+                    stddevs.as_mut_ptr(),
+                    2
+                ),
+                FANOVA_ERROR_LENGTH_MISMATCH
+            );
+
+            assert_eq!(
+                fanova_evaluator_set_target(evaluator, target.as_ptr(), 8),
+                FANOVA_OK
+            );
+            assert_eq!(
+                fanova_evaluator_importances(
+                    evaluator,
+                    means.as_mut_ptr(),
+                    stddevs.as_mut_ptr(),
+                    1
+                ),
+                FANOVA_ERROR_LENGTH_MISMATCH
+            );
+            assert_eq!(
+                fanova_evaluator_importances(
+                    evaluator,
+                    means.as_mut_ptr(),
+                    stddevs.as_mut_ptr(),
+                    2
+                ),
+                FANOVA_OK
+            );
+            assert!(means[0] > means[1]);
+
+            let (mut mean, mut stddev) = (0.0, 0.0);
+            let status = fanova_evaluator_quantify_importance(
+                evaluator,
+                [0].as_ptr(),
+                1,
+                &mut mean,
+                &mut stddev,
+            );
+            assert_eq!(status, FANOVA_OK);
+            assert_eq!((mean, stddev), (means[0], stddevs[0]));
+
+            let mut grid = [0.0; 3];
+            let mut marginal_means = [0.0; 3];
+            let mut marginal_stddevs = [0.0; 3];
+            let mut marginal = |feature, n_points| {
+                fanova_evaluator_marginal(
+                    evaluator,
+                    feature,
+                    grid.as_mut_ptr(),
+                    marginal_means.as_mut_ptr(),
+                    marginal_stddevs.as_mut_ptr(),
+                    n_points,
+                )
+            };
+            assert_eq!(marginal(2, 3), FANOVA_ERROR_INVALID_ARGUMENT);
+            assert_eq!(marginal(0, 1), FANOVA_ERROR_INVALID_ARGUMENT);
+            assert_eq!(marginal(0, 3), FANOVA_OK);
+            assert_eq!(grid, [0.0, 3.5, 7.0]);
+            assert!(marginal_means[0] < marginal_means[2]);
+
+            assert_eq!(
+                fanova_evaluator_push_feature(evaluator, ptr::null(), 8),
+                FANOVA_ERROR_NULL_POINTER
+            );
+            fanova_evaluator_free(evaluator);
+            fanova_evaluator_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn header_works() {
+        let header = include_str!("../include/fanova.h");
+        for name in [
+            "fanova_evaluator_new",
+            "fanova_evaluator_free",
+            "fanova_evaluator_set_seed",
+            "fanova_evaluator_push_feature",
+            "fanova_evaluator_set_target",
+            "fanova_evaluator_importances",
+            "fanova_evaluator_quantify_importance",
+            "fanova_evaluator_marginal",
+            "FANOVA_ERROR_INVALID_ARGUMENT",
+        ] {
+            assert!(header.contains(name), "{name}");
+        }
+    }
+}
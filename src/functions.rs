@@ -1,25 +1,435 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
 pub fn mean(xs: impl Iterator<Item = f64>) -> f64 {
     mean_and_stddev(xs).0
 }
 
 pub fn mean_and_stddev(xs: impl Iterator<Item = f64>) -> (f64, f64) {
-    let (mut mean, mut s, mut n) = (0.0, 0.0, 0.0);
+    let mut acc = Welford::default();
     for x in xs {
-        n += 1.0;
-        let delta = x - mean;
-        mean += delta / n;
-        s += delta * (x - mean);
-    }
-    assert!(n >= 0.0, "Need at least one value");
-    if n == 1.0 {
-        (mean, 0.0)
+        acc.push(x, 1.0);
+    }
+    assert!(acc.total_weight() >= 0.0, "Need at least one value");
+    if acc.total_weight() == 1.0 {
+        (acc.mean(), 0.0)
+    } else {
+        (acc.mean(), (acc.m2() / (acc.total_weight() - 1.0)).sqrt())
+    }
+}
+
+/// Streaming accumulator of the weighted mean and variance (Welford's algorithm).
+///
+/// The running sums are compensated (see `KahanSum`) so that values spanning many orders of
+/// magnitude don't lose precision.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Welford {
+    total_weight: KahanSum,
+    mean: KahanSum,
+    m2: KahanSum,
+}
+
+impl Welford {
+    pub fn push(&mut self, x: f64, w: f64) {
+        if w == 0.0 {
+            return;
+        }
+        self.total_weight.add(w);
+        let delta = x - self.mean();
+        self.mean.add(delta * w / self.total_weight());
+        self.m2.add(w * delta * (x - self.mean()));
+    }
+
+    /// Adds the values accumulated by `other`.
+    pub fn merge(&mut self, other: &Self) {
+        if other.total_weight() == 0.0 {
+            return;
+        }
+        let (self_weight, other_weight) = (self.total_weight(), other.total_weight());
+        self.total_weight.add(other_weight);
+        let total_weight = self.total_weight();
+        let delta = other.mean() - self.mean();
+        self.mean.add(delta * other_weight / total_weight);
+        self.m2.add(other.m2());
+        self.m2
+            .add(delta * delta * self_weight * other_weight / total_weight);
+    }
+
+    pub fn total_weight(&self) -> f64 {
+        self.total_weight.value()
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean.value()
+    }
+
+    /// Weighted sum of squared deviations from the mean.
+    pub fn m2(&self) -> f64 {
+        self.m2.value()
+    }
+
+    /// Weighted (population) variance.
+    pub fn variance(&self) -> f64 {
+        self.m2() / self.total_weight()
+    }
+}
+
+/// Compensated summation (Neumaier's variant of the Kahan summation).
+///
+/// The rounding error of each addition is accumulated separately and added back to the result.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct KahanSum {
+    sum: f64,
+    compensation: f64,
+}
+
+impl KahanSum {
+    pub fn add(&mut self, x: f64) {
+        let sum = self.sum + x;
+        if self.sum.abs() >= x.abs() {
+            self.compensation += (self.sum - sum) + x;
+        } else {
+            self.compensation += (x - sum) + self.sum;
+        }
+        self.sum = sum;
+    }
+
+    pub fn value(&self) -> f64 {
+        self.sum + self.compensation
+    }
+}
+
+pub fn median(xs: Vec<f64>) -> f64 {
+    quantile(xs.into_iter(), 0.5)
+}
+
+/// Calculates the Pearson correlation coefficient of `(x, y)` pairs.
+///
+/// Returns NaN if either of the variables is constant.
+pub fn pearson(xys: impl Iterator<Item = (f64, f64)> + Clone) -> f64 {
+    let n = xys.clone().count() as f64;
+    let mean_x = xys.clone().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = xys.clone().map(|(_, y)| y).sum::<f64>() / n;
+    let (mut sxy, mut sxx, mut syy) = (0.0, 0.0, 0.0);
+    for (x, y) in xys {
+        sxy += (x - mean_x) * (y - mean_y);
+        sxx += (x - mean_x).powi(2);
+        syy += (y - mean_y).powi(2);
+    }
+    if sxx == 0.0 || syy == 0.0 {
+        f64::NAN
+    } else {
+        sxy / (sxx * syy).sqrt()
+    }
+}
+
+/// Calculates the Spearman rank correlation coefficient of `(x, y)` pairs.
+///
+/// Tied values get the average of their ranks.
+pub fn spearman(xys: impl Iterator<Item = (f64, f64)> + Clone) -> f64 {
+    let rank_x = ranks(xys.clone().map(|(x, _)| x));
+    let rank_y = ranks(xys.map(|(_, y)| y));
+    pearson(rank_x.into_iter().zip(rank_y))
+}
+
+/// Compares feature values in the order used to sort the rows of tables.
+///
+/// This is `f64::total_cmp` except that the zeros of both signs are equal and every NaN
+/// (whatever its sign and payload) is equal to the others and greater than any number. So the
+/// rows having NaNs are always sorted last, which agrees with the splits: `x <= threshold` is
+/// false for NaN, so those rows always go to the upper side.
+pub fn total_cmp(a: f64, b: f64) -> Ordering {
+    canonical(a).total_cmp(&canonical(b))
+}
+
+fn canonical(x: f64) -> f64 {
+    if x.is_nan() {
+        f64::NAN
     } else {
-        (mean, (s / (n - 1.0)).sqrt())
+        x + 0.0
     }
 }
 
-pub fn mse(xs: impl Iterator<Item = f64> + Clone) -> f64 {
-    let n = xs.clone().count() as f64;
-    let m = mean(xs.clone());
-    xs.map(|x| (x - m).powi(2)).sum::<f64>() / n
+/// A value ordered by `total_cmp` (e.g., for the keys of ordered maps).
+#[derive(Debug, Clone, Copy)]
+pub struct TotalOrder(pub f64);
+
+impl PartialEq for TotalOrder {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TotalOrder {}
+
+impl PartialOrd for TotalOrder {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TotalOrder {
+    fn cmp(&self, other: &Self) -> Ordering {
+        total_cmp(self.0, other.0)
+    }
+}
+
+/// Returns the (1-origin) ranks of `xs`, averaging the ranks of tied values.
+pub fn ranks(xs: impl Iterator<Item = f64>) -> Vec<f64> {
+    let xs = xs.collect::<Vec<_>>();
+    let mut order = (0..xs.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| xs[a].total_cmp(&xs[b]));
+
+    let mut ranks = vec![0.0; xs.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let end = (start..order.len())
+            .find(|&i| xs[order[i]] != xs[order[start]])
+            .unwrap_or(order.len());
+        let rank = (start + end + 1) as f64 / 2.0;
+        for &i in &order[start..end] {
+            ranks[i] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+/// Returns `bins + 1` edges dividing `[min, max]` into bins of the same width.
+pub fn equal_width_edges(min: f64, max: f64, bins: usize) -> Vec<f64> {
+    assert!(bins > 0, "Need at least one bin");
+    let width = (max - min) / bins as f64;
+    (0..=bins)
+        .map(|i| {
+            if i == bins {
+                max
+            } else {
+                min + width * i as f64
+            }
+        })
+        .collect()
+}
+
+/// Returns (at most) `bins + 1` edges dividing sorted `xs` into bins of (nearly) the same
+/// frequency.
+///
+/// Duplicated edges are removed, so the number of bins may be less than `bins`.
+pub fn quantile_edges(xs: &[f64], bins: usize) -> Vec<f64> {
+    assert!(bins > 0, "Need at least one bin");
+    let mut edges = (0..=bins)
+        .map(|i| quantile_sorted(xs, i as f64 / bins as f64))
+        .collect::<Vec<_>>();
+    edges.dedup();
+    edges
+}
+
+/// Counts the values of `xs` falling into each bin defined by `edges`.
+///
+/// The `i`-th bin is `[edges[i], edges[i + 1])` except that the last one includes its upper
+/// edge. Values out of the edges are ignored.
+pub fn bin_counts(xs: impl Iterator<Item = f64>, edges: &[f64]) -> Vec<usize> {
+    let mut counts = vec![0; edges.len().saturating_sub(1)];
+    if counts.is_empty() {
+        return counts;
+    }
+    for x in xs {
+        if !(edges[0] <= x && x <= edges[edges.len() - 1]) {
+            continue;
+        }
+        let i = edges.partition_point(|&e| e <= x).min(counts.len());
+        counts[i - 1] += 1;
+    }
+    counts
+}
+
+/// Counts the occurrences of each distinct value of `xs` (in ascending order).
+///
+/// Values are bucketed by their exact bit patterns (all NaNs are regarded as the same value).
+pub fn category_counts(xs: impl Iterator<Item = f64>) -> Vec<(f64, usize)> {
+    let mut counts = HashMap::<u64, usize>::new();
+    for x in xs {
+        let x = if x.is_nan() { f64::NAN } else { x };
+        *counts.entry(x.to_bits()).or_default() += 1;
+    }
+    let mut counts = counts
+        .into_iter()
+        .map(|(bits, count)| (f64::from_bits(bits), count))
+        .collect::<Vec<_>>();
+    counts.sort_by(|a, b| a.0.total_cmp(&b.0));
+    counts
+}
+
+/// Returns the most frequent value in `xs`.
+///
+/// Values are bucketed by their exact bit patterns (all NaNs are regarded as the same value).
+/// Ties are broken deterministically: the lowest value (in the `f64::total_cmp` order) wins.
+pub fn most_frequent(xs: impl Iterator<Item = f64>) -> f64 {
+    let counts = category_counts(xs);
+    let mut best = *counts.first().expect("Need at least one value");
+    for &(x, count) in &counts[1..] {
+        if count > best.1 {
+            best = (x, count);
+        }
+    }
+    best.0
+}
+
+/// Calculates the `q`-quantile of `xs` by linearly interpolating the closest ranks.
+///
+/// This is the same as the default method of `numpy.quantile`.
+pub fn quantile(xs: impl Iterator<Item = f64>, q: f64) -> f64 {
+    let mut xs = xs.collect::<Vec<_>>();
+    xs.sort_by(|a, b| a.total_cmp(b));
+    quantile_sorted(&xs, q)
+}
+
+/// Same as `quantile` but `xs` must have been sorted in ascending order.
+pub fn quantile_sorted(xs: &[f64], q: f64) -> f64 {
+    assert!(!xs.is_empty(), "Need at least one value");
+    assert!(
+        (0.0..=1.0).contains(&q),
+        "q must be in the range [0.0, 1.0]"
+    );
+    let h = (xs.len() - 1) as f64 * q;
+    let i = h.floor() as usize;
+    if i + 1 == xs.len() {
+        xs[i]
+    } else {
+        xs[i] + (h - i as f64) * (xs[i + 1] - xs[i])
+    }
+}
+
+/// Calculates the weighted mean of `(value, weight)` pairs.
+///
+/// If the total weight is zero, the unweighted mean is returned instead.
+pub fn weighted_mean(xs: impl Iterator<Item = (f64, f64)> + Clone) -> f64 {
+    let mut acc = Welford::default();
+    for (x, w) in xs.clone() {
+        acc.push(x, w);
+    }
+    if acc.total_weight() == 0.0 {
+        mean(xs.map(|(x, _)| x))
+    } else {
+        acc.mean()
+    }
+}
+
+/// Calculates the weighted (population) variance of `(value, weight)` pairs.
+///
+/// If the total weight is zero, the unweighted variance is returned instead.
+pub fn weighted_variance(xs: impl Iterator<Item = (f64, f64)> + Clone) -> f64 {
+    let mut acc = Welford::default();
+    for (x, w) in xs.clone() {
+        acc.push(x, w);
+    }
+    if acc.total_weight() == 0.0 {
+        xs.for_each(|(x, _)| acc.push(x, 1.0));
+    }
+    acc.variance()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weighted_mean_variance_and_welford_work() {
+        let xs = [(1.0, 1.0), (2.0, 3.0), (10.0, 0.0)];
+        assert_eq!(weighted_mean(xs.iter().copied()), 1.75);
+        assert_eq!(weighted_variance(xs.iter().copied()), 0.1875);
+
+        let mut acc = Welford::default();
+        xs.iter().for_each(|&(x, w)| acc.push(x, w));
+        assert_eq!(acc.mean(), 1.75);
+        assert_eq!(acc.variance(), 0.1875);
+
+        let xs = [(1.0, 0.0), (3.0, 0.0)];
+        assert_eq!(weighted_mean(xs.iter().copied()), 2.0);
+        assert_eq!(weighted_variance(xs.iter().copied()), 1.0);
+
+        let (mut a, mut b) = (Welford::default(), Welford::default());
+        a.push(1.0, 1.0);
+        b.push(2.0, 3.0);
+        a.merge(&b);
+        assert_eq!(a.mean(), 1.75);
+        assert_eq!(a.variance(), 0.1875);
+    }
+
+    #[test]
+    fn total_cmp_works() {
+        let mut xs = [
+            1.0,
+            f64::NAN,
+            -f64::NAN,
+            f64::INFINITY,
+            0.0,
+            -0.0,
+            f64::NEG_INFINITY,
+        ];
+        xs.sort_by(|&a, &b| total_cmp(a, b));
+        assert_eq!(xs[..5], [f64::NEG_INFINITY, 0.0, -0.0, 1.0, f64::INFINITY]);
+        assert!(xs[5..].iter().all(|x| x.is_nan()));
+        assert_eq!(total_cmp(-f64::NAN, f64::NAN), Ordering::Equal);
+        assert_eq!(TotalOrder(0.0), TotalOrder(-0.0));
+        assert!(TotalOrder(f64::NAN) > TotalOrder(f64::INFINITY));
+    }
+
+    #[test]
+    fn compensated_summation_works() {
+        let mut sum = KahanSum::default();
+        [1.0, 1e100, 1.0, -1e100].iter().for_each(|&x| sum.add(x));
+        assert_eq!(sum.value(), 2.0);
+
+        // The naive running mean drifts by a few units in the last place here.
+        let xs = (0..1000).map(|i| 1e9 + (i % 10) as f64 * 1e-3);
+        let (mean, stddev) = mean_and_stddev(xs);
+        assert_eq!(mean, 1e9 + 4.5e-3);
+        assert!((stddev - 0.002873719).abs() < 1e-6);
+    }
+
+    #[test]
+    fn correlation_works() {
+        let xys = [(1.0, 2.0), (2.0, 4.0), (3.0, 9.0), (4.0, 8.0)];
+        assert!((pearson(xys.iter().copied()) - 0.8986843649392481).abs() < 1e-12);
+        assert_eq!(spearman(xys.iter().copied()), 0.8);
+        assert!(pearson([(1.0, 2.0), (1.0, 3.0)].into_iter()).is_nan());
+        assert_eq!(
+            ranks([3.0, 1.0, 3.0, 2.0].into_iter()),
+            [3.5, 1.0, 3.5, 2.0]
+        );
+    }
+
+    #[test]
+    fn binning_works() {
+        assert_eq!(equal_width_edges(0.0, 1.0, 4), [0.0, 0.25, 0.5, 0.75, 1.0]);
+        assert_eq!(
+            quantile_edges(&[0.0, 1.0, 1.0, 1.0, 5.0], 4),
+            [0.0, 1.0, 5.0]
+        );
+        assert_eq!(
+            bin_counts([0.0, 0.3, 0.5, 1.0, 1.5].into_iter(), &[0.0, 0.5, 1.0]),
+            [2, 2]
+        );
+        assert_eq!(
+            category_counts([2.0, 1.0, 2.0].into_iter()),
+            [(1.0, 1), (2.0, 2)]
+        );
+    }
+
+    #[test]
+    fn most_frequent_works() {
+        assert_eq!(most_frequent([2.0, 1.0, 2.0, 3.0].into_iter()), 2.0);
+        assert_eq!(most_frequent([3.0, 1.0, 3.0, 1.0].into_iter()), 1.0);
+        assert!(most_frequent([f64::NAN, 1.0, -f64::NAN].into_iter()).is_nan());
+    }
+
+    #[test]
+    fn quantile_works() {
+        let xs = [3.0, 1.0, 4.0, 2.0];
+        assert_eq!(quantile(xs.iter().copied(), 0.0), 1.0);
+        assert_eq!(quantile(xs.iter().copied(), 0.5), 2.5);
+        assert_eq!(quantile(xs.iter().copied(), 0.9), 3.7);
+        assert_eq!(quantile(xs.iter().copied(), 1.0), 4.0);
+        assert_eq!(quantile_sorted(&[5.0], 0.3), 5.0);
+    }
 }
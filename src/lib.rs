@@ -35,7 +35,7 @@
 //!
 //! assert_eq!(
 //!     importances,
-//!     vec![0.04285945139294453, 0.23639697156594727, 0.5975522202656363]
+//!     vec![0.043620746377350854, 0.23006198949738835, 0.6002590904515438]
 //! );
 //! ```
 //!
@@ -45,14 +45,82 @@
 //!
 //! [fANOVA]: http://proceedings.mlr.press/v32/hutter14.html
 //!
+//! # Features
+//!
+//! - `wasm` (default): the JavaScript bindings. Disable the default features to use this crate
+//!   as a pure Rust library.
+//! - `threads`: parallel execution on Web Workers spawned by `wasm-bindgen-rayon` (see
+//!   `init_thread_pool` of the bindings and README.md for the build flags).
+//! - `ffi`: the C interface declared in `include/fanova.h`.
+//! - `python`: the Python extension module `fanova_rs` (built with PyO3).
+//! - `tracing`: records table construction, tree fitting and importance computation as `tracing`
+//!   spans with their elapsed times (reported to the browser console after `init_tracing` of the
+//!   bindings, or to the subscriber installed by the application otherwise).
+//! - `simd`: vectorized sums with the wasm `simd128` instructions (effective only if the crate is
+//!   compiled with `-C target-feature=+simd128`).
+//! - `cli`: the `fanova-cli` binary.
+//!
+//! # Determinism
+//!
+//! Given the same seed and inputs, the importances are bitwise identical on all targets
+//! (including wasm) and regardless of the parallelism and the `simd` feature.
+//! The only exceptions are `Transform::Log` and the split criteria other than
+//! `SplitCriterion::SquaredError`, which rely on the `ln`, `exp` and `powf` implementations of the
+//! platform.
 #![warn(missing_docs)]
-pub use self::fanova::{Fanova, FanovaOptions, FitError, Importance};
-pub use self::random_forest::RandomForestOptions;
+// Some internal helpers (e.g., model serialization) only back the JavaScript bindings.
+#![cfg_attr(not(all(feature = "wasm", not(target_os = "wasi"))), allow(dead_code))]
+pub use self::ablation::AblationStep;
+pub use self::categorical::CategoricalFanova;
+pub use self::distill::{DistillOptions, ExplanatoryNode, ExplanatoryTree};
+pub use self::error::FanovaError;
+pub use self::fanova::{
+    BestRows, CategoricalEncoding, CrossValidation, DropColumnImportances, Fanova, FanovaOptions,
+    FitError, Importance, InteractionSearch, MarginalUncertainty, Preset, Profile, Significance,
+};
+pub use self::morris::{ElementaryEffects, MorrisOptions};
+pub use self::random_forest::{
+    ExtraTreesOptions, FitQuality, RandomForestOptions, RngState, SplitCriterion, SplitSampling,
+};
+pub use self::sobol::SobolIndices;
+pub use self::stream::TableStream;
+pub use self::structure::TreeNode;
+pub use self::surrogate::{SurrogateLeaf, SurrogateTree};
+pub use self::table::{
+    Aggregation, Binning, ColumnType, Correlation, Histogram, Infinities, NonFiniteTargets, Strata,
+    Table, TableBuilder, TableError,
+};
+pub use self::transform::Transform;
+pub use self::validation::{validate, ExcludedFeature, Issue, ValidationReport};
 
+mod ablation;
+mod categorical;
+mod codec;
+mod column;
 mod decision_tree;
+mod distill;
+mod encoding;
+mod error;
 mod fanova;
+#[cfg(feature = "ffi")]
+mod ffi;
 mod functions;
+mod morris;
 mod partition;
+#[cfg(feature = "python")]
+mod python;
 mod random_forest;
+mod shap;
+mod simd;
+mod sketch;
+mod sobol;
 mod space;
+mod stream;
+mod structure;
+mod surrogate;
 mod table;
+mod trace;
+mod transform;
+mod validation;
+#[cfg(all(feature = "wasm", not(target_os = "wasi")))]
+mod wasm;
@@ -0,0 +1,164 @@
+//! Morris elementary effects screening (a cheap pre-analysis for many features).
+//!
+//! Each trajectory starts at a random point of a grid of `levels` values per feature and moves
+//! along the features one by one (in a random order) by `delta = levels / (2 * (levels - 1))`
+//! of the range. The elementary effect of a feature is the change of the function divided by
+//! the step, so a trajectory takes only `features + 1` evaluations.
+use crate::functions;
+use crate::space::FeatureSpace;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom as _;
+use rand::{Rng, SeedableRng};
+use std::num::NonZeroUsize;
+
+/// Morris screening options.
+#[derive(Debug, Clone)]
+pub struct MorrisOptions {
+    trajectories: NonZeroUsize,
+    levels: NonZeroUsize,
+    seed: Option<u64>,
+}
+
+impl MorrisOptions {
+    /// Makes a `MorrisOptions` instance with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of trajectories.
+    ///
+    /// The default value is `20`.
+    pub fn trajectories(mut self, trajectories: NonZeroUsize) -> Self {
+        self.trajectories = trajectories;
+        self
+    }
+
+    /// Sets the number of grid levels of each feature.
+    ///
+    /// The default value is `4`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `levels` is not even.
+    pub fn levels(mut self, levels: NonZeroUsize) -> Self {
+        assert!(levels.get().is_multiple_of(2), "levels must be even");
+        self.levels = levels;
+        self
+    }
+
+    /// Sets the random generator seed.
+    ///
+    /// The default value is random.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+impl Default for MorrisOptions {
+    fn default() -> Self {
+        Self {
+            trajectories: NonZeroUsize::new(20).expect("never fails"),
+            levels: NonZeroUsize::new(4).expect("never fails"),
+            seed: None,
+        }
+    }
+}
+
+/// Statistics of the elementary effects of a feature.
+///
+/// The effects are the changes of the prediction when the feature moves over its whole range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ElementaryEffects {
+    /// Mean of the effects.
+    pub mu: f64,
+
+    /// Mean of the absolute effects (the measure used to rank the features).
+    pub mu_star: f64,
+
+    /// Standard deviation of the effects (large if the feature is nonlinear or interacts).
+    pub sigma: f64,
+}
+
+/// Computes the elementary effects of `f` over `space`.
+pub fn elementary_effects<F>(
+    space: &FeatureSpace,
+    options: &MorrisOptions,
+    f: F,
+) -> Vec<ElementaryEffects>
+where
+    F: Fn(&[f64]) -> f64,
+{
+    let ranges = space.ranges();
+    let features_len = ranges.len();
+    let seed = options.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let mut rng = StdRng::seed_from_u64(seed);
+    let levels = options.levels.get();
+    let delta = levels as f64 / (2.0 * (levels - 1) as f64);
+
+    let scale = |u: &[f64]| {
+        u.iter()
+            .zip(ranges)
+            .map(|(u, r)| r.start + u * (r.end - r.start))
+            .collect::<Vec<_>>()
+    };
+    let mut effects = vec![Vec::with_capacity(options.trajectories.get()); features_len];
+    let mut order = (0..features_len).collect::<Vec<_>>();
+    for _ in 0..options.trajectories.get() {
+        // The base levels are chosen so that the steps stay in the space.
+        let steps = (0..features_len)
+            .map(|_| if rng.gen() { delta } else { -delta })
+            .collect::<Vec<_>>();
+        let mut u = steps
+            .iter()
+            .map(|&step| {
+                let level = rng.gen_range(0..levels / 2) as f64 / (levels - 1) as f64;
+                if step > 0.0 {
+                    level
+                } else {
+                    1.0 - level
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut y = f(&scale(&u));
+        order.shuffle(&mut rng);
+        for &i in &order {
+            u[i] += steps[i];
+            let next = f(&scale(&u));
+            effects[i].push((next - y) / steps[i]);
+            y = next;
+        }
+    }
+
+    effects
+        .into_iter()
+        .map(|effects| {
+            let (mu, sigma) = functions::mean_and_stddev(effects.iter().copied());
+            let mu_star = functions::mean(effects.iter().map(|e| e.abs()));
+            ElementaryEffects { mu, mu_star, sigma }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elementary_effects_works() {
+        let space = FeatureSpace::from_ranges(vec![0.0..1.0, 0.0..2.0, 0.0..1.0, 0.0..0.0]);
+        let trajectories = NonZeroUsize::new(50).expect("not zero");
+        let options = MorrisOptions::new().trajectories(trajectories).seed(0);
+
+        // `x0` is linear, `x1` doesn't affect, `x2` is nonlinear, and `x3` is constant.
+        let f = |x: &[f64]| 3.0 * x[0] + x[2] * x[2];
+        let effects = elementary_effects(&space, &options, f);
+        assert!((effects[0].mu - 3.0).abs() < 1e-9);
+        assert!((effects[0].mu_star - 3.0).abs() < 1e-9);
+        assert!(effects[0].sigma < 1e-9);
+        assert_eq!(effects[1].mu_star, 0.0);
+        assert!(effects[2].mu_star > 0.0 && effects[2].sigma > 0.0);
+        assert_eq!(effects[3].mu_star, 0.0);
+        assert_eq!(elementary_effects(&space, &options, f), effects);
+    }
+}
@@ -1,9 +1,14 @@
-use crate::decision_tree::DecisionTreeRegressor;
+use crate::simd;
 use crate::space::FeatureSpace;
+use crate::surrogate::SurrogateTree;
 
 #[derive(Debug)]
 pub struct Partition {
     pub value: f64,
+
+    // The variance of the targets of the leaf (i.e., the noise which the tree doesn't explain).
+    pub variance: f64,
+
     pub space: FeatureSpace,
 }
 
@@ -11,42 +16,270 @@ pub struct Partition {
 pub struct TreePartitions {
     partitions: Vec<Partition>,
     space: FeatureSpace,
+
+    // Precomputed for marginalization: the fraction of `space` covered by each partition, and
+    // the widths of the partitions relative to `space` (`widths[i * features + j]` is the one
+    // of the `i`-th partition along the `j`-th feature).
+    fractions: Vec<f64>,
+    widths: Vec<f64>,
 }
 
 impl TreePartitions {
-    pub fn new(regressor: &DecisionTreeRegressor, space: FeatureSpace) -> Self {
-        let partitions = regressor.fold(
-            space.clone(),
-            |space, split| space.split(split.column, split.threshold),
-            Vec::new(),
-            |mut acc, space, value| {
-                acc.push(Partition { value, space });
-                acc
-            },
-        );
-        Self { partitions, space }
+    /// Converts the leaves of `tree` into partitions of `space`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a leaf doesn't have a range for each feature of `space`.
+    pub fn new(tree: &dyn SurrogateTree, space: FeatureSpace) -> Self {
+        let features_len = space.ranges().len();
+        let partitions = tree
+            .leaves(space.ranges())
+            .into_iter()
+            .map(|leaf| {
+                assert_eq!(
+                    leaf.ranges.len(),
+                    features_len,
+                    "a leaf must have a range for each feature"
+                );
+                Partition {
+                    value: leaf.value,
+                    variance: leaf.variance,
+                    space: FeatureSpace::from_ranges(leaf.ranges),
+                }
+            })
+            .collect();
+        Self::from_partitions(partitions, space)
     }
 
-    pub fn mean_and_variance(&self) -> (f64, f64) {
-        let overall_size = self.space.size();
-        let weights = self
-            .iter()
-            .map(|p| p.space.size() / overall_size)
-            .collect::<Vec<_>>();
-        let mean = self
-            .iter()
-            .zip(weights.iter())
-            .map(|(p, w)| w * p.value)
-            .sum::<f64>();
-        let variance = self
+    pub fn from_partitions(partitions: Vec<Partition>, space: FeatureSpace) -> Self {
+        let mut this = Self {
+            partitions,
+            space,
+            fractions: Vec::new(),
+            widths: Vec::new(),
+        };
+        this.precompute();
+        this
+    }
+
+    fn precompute(&mut self) {
+        let whole = self.space.ranges();
+        self.widths = self
+            .partitions
             .iter()
-            .zip(weights.iter())
-            .map(|(p, w)| w * (p.value - mean).powi(2))
-            .sum::<f64>();
+            .flat_map(|p| {
+                p.space.ranges().iter().zip(whole).map(|(r, w)| {
+                    // Every partition covers the whole range of a constant feature.
+                    if w.end > w.start {
+                        (r.end - r.start) / (w.end - w.start)
+                    } else {
+                        1.0
+                    }
+                })
+            })
+            .collect();
+        let features = whole.len();
+        self.fractions = (0..self.partitions.len())
+            .map(|i| self.widths[i * features..][..features].iter().product())
+            .collect();
+    }
+
+    pub fn mean_and_variance(&self) -> (f64, f64) {
+        let values = self.iter().map(|p| p.value).collect::<Vec<_>>();
+        let mean = simd::dot(&values, &self.fractions);
+        let variance = simd::weighted_squared_deviations(&values, &self.fractions, mean);
         (mean, variance)
     }
 
+    /// Returns the fraction of the marginal space (the space of the features other than
+    /// `features`) covered by each partition.
+    ///
+    /// This takes `O(features)` time per partition. The product of the widths along the other
+    /// features is taken directly (instead of dividing `fractions` by the widths along
+    /// `features`), so a partition of zero width along `features` has no NaN fraction.
+    pub fn marginal_fractions<'a>(
+        &'a self,
+        features: &'a [usize],
+    ) -> impl 'a + Iterator<Item = f64> {
+        let features_len = self.space.ranges().len();
+        let mut marginal = vec![true; features_len];
+        for &j in features {
+            marginal[j] = false;
+        }
+        (0..self.partitions.len()).map(move |i| {
+            let widths = &self.widths[i * features_len..][..features_len];
+            widths
+                .iter()
+                .zip(&marginal)
+                .filter(|(_, &m)| m)
+                .map(|(w, _)| w)
+                .product()
+        })
+    }
+
+    /// Returns the (approximate) number of bytes used by the partitions.
+    pub fn heap_size(&self) -> usize {
+        let space_size = std::mem::size_of_val(self.space.ranges());
+        let precomputed_size =
+            std::mem::size_of_val(&self.fractions[..]) + std::mem::size_of_val(&self.widths[..]);
+        self.partitions.len() * (std::mem::size_of::<Partition>() + space_size) + precomputed_size
+    }
+
+    /// Extends the partitions to cover `space` (see `FeatureSpace::rebase`).
+    pub fn rebase(&mut self, space: FeatureSpace) {
+        for p in &mut self.partitions {
+            p.space.rebase(&self.space, &space);
+        }
+        self.space = space;
+        self.precompute();
+    }
+
+    /// Converts the values by an affine function `f` (and the variances by its squared slope).
+    pub fn map_values(&mut self, f: impl Fn(f64) -> f64) {
+        let slope = f(1.0) - f(0.0);
+        for p in &mut self.partitions {
+            p.value = f(p.value);
+            p.variance *= slope * slope;
+        }
+    }
+
+    /// Returns the value of the partition containing `point` (or NaN if no partitions do).
+    pub fn predict(&self, point: &[f64]) -> f64 {
+        let whole = self.space.ranges();
+        self.iter()
+            .find(|p| {
+                p.space
+                    .ranges()
+                    .iter()
+                    .zip(whole)
+                    .zip(point)
+                    .all(|((r, w), &x)| {
+                        // The upper bound of the whole space is included in the last partition.
+                        r.start <= x && (x < r.end || (x == r.end && r.end == w.end))
+                    })
+            })
+            .map_or(f64::NAN, |p| p.value)
+    }
+
+    /// Same as `predict` but a point on a threshold belongs to the lower partition as in
+    /// `DecisionTreeRegressor::predict` (e.g., to reproduce the predictions of the fitted trees).
+    pub fn predict_as_tree(&self, point: &[f64]) -> f64 {
+        let whole = self.space.ranges();
+        self.iter()
+            .find(|p| {
+                p.space
+                    .ranges()
+                    .iter()
+                    .zip(whole)
+                    .zip(point)
+                    .all(|((r, w), &x)| {
+                        (r.start < x || (x == r.start && r.start == w.start)) && x <= r.end
+                    })
+            })
+            .map_or(f64::NAN, |p| p.value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.partitions.len()
+    }
+
+    pub fn space(&self) -> &FeatureSpace {
+        &self.space
+    }
+
+    /// Returns the widths of the `i`-th partition relative to the whole space.
+    pub fn widths(&self, i: usize) -> &[f64] {
+        let features_len = self.space.ranges().len();
+        &self.widths[i * features_len..][..features_len]
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &Partition> {
         self.partitions.iter()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decision_tree::{DecisionTreeOptions, DecisionTreeRegressor};
+    use crate::table::{ColumnType, Table};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn categorical_splits_work() -> anyhow::Result<()> {
+        let feature = (0..60).map(|i| f64::from(i % 6)).collect::<Vec<_>>();
+        let target = feature
+            .iter()
+            .map(|&c| {
+                if [0.0, 3.0, 4.0].contains(&c) {
+                    10.0
+                } else {
+                    0.0
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut table = Table::new(vec![&feature, &target])?;
+        table.set_column_type(0, ColumnType::Categorical)?;
+        let options = DecisionTreeOptions {
+            max_depth: Some(1),
+            categorical_splits: true,
+            ..Default::default()
+        };
+        let regressor = DecisionTreeRegressor::fit(&mut StdRng::seed_from_u64(0), table, options);
+
+        // The leaves of the subsets `{1, 2, 5}` and `{0, 3, 4, 6}` (the absent index `6` goes to
+        // the right) make the runs of their consecutive categories.
+        let space = FeatureSpace::from_ranges(vec![-0.5..6.5]);
+        let partitions = TreePartitions::new(&regressor, space);
+        let ranges = partitions
+            .iter()
+            .map(|p| (p.space.ranges()[0].clone(), p.value))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ranges,
+            [
+                (-0.5..0.5, 10.0),
+                (2.5..4.5, 10.0),
+                (5.5..6.5, 10.0),
+                (0.5..2.5, 0.0),
+                (4.5..5.5, 0.0),
+            ]
+        );
+        let fractions = partitions.marginal_fractions(&[]).collect::<Vec<_>>();
+        assert_eq!(fractions.iter().sum::<f64>(), 1.0);
+        // Four of the seven categories have the value `10`.
+        let (mean, _) = partitions.mean_and_variance();
+        assert!((mean - 40.0 / 7.0).abs() < 1e-12);
+        for c in 0..7 {
+            let c = f64::from(c);
+            assert_eq!(partitions.predict_as_tree(&[c]), regressor.predict(&[c]));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn marginal_fractions_works() {
+        let space = FeatureSpace::from_ranges(vec![0.0..1.0, 0.0..4.0]);
+        let partition = |ranges: Vec<std::ops::Range<f64>>| Partition {
+            value: 0.0,
+            variance: 0.0,
+            space: FeatureSpace::from_ranges(ranges),
+        };
+        // The second partition has zero width along the first feature (e.g., a random threshold
+        // at the minimum).
+        let partitions = TreePartitions::from_partitions(
+            vec![
+                partition(vec![0.0..0.5, 0.0..1.0]),
+                partition(vec![0.0..0.0, 1.0..4.0]),
+            ],
+            space,
+        );
+        let fractions =
+            |features: &[usize]| partitions.marginal_fractions(features).collect::<Vec<_>>();
+        assert_eq!(fractions(&[]), [0.125, 0.0]);
+        assert_eq!(fractions(&[0]), [0.25, 0.75]);
+        assert_eq!(fractions(&[1]), [0.5, 0.0]);
+        assert_eq!(fractions(&[0, 1]), [1.0, 1.0]);
+    }
+}
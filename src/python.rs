@@ -0,0 +1,129 @@
+//! Python extension module `fanova_rs` (see README.md for building it).
+//!
+//! A model is fitted by `fanova_rs.fit(features, target, seed=None)`, where `features` is a list
+//! of the feature columns.
+use crate::{Fanova, FanovaError, FanovaOptions, RandomForestOptions};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+pyo3::create_exception!(
+    fanova_rs,
+    Error,
+    PyValueError,
+    "Error raised when a model can't be fitted or evaluated."
+);
+
+fn into_py_err(e: impl Into<FanovaError>) -> PyErr {
+    Error::new_err(e.into().to_string())
+}
+
+/// fANOVA model returned by `fit`.
+#[pyclass(name = "Fanova", module = "fanova_rs")]
+struct PyFanova {
+    fanova: Fanova,
+}
+
+#[pymethods]
+impl PyFanova {
+    /// Returns the mean and standard deviation of the importance of the feature set.
+    fn quantify_importance(&mut self, features: Vec<usize>) -> PyResult<(f64, f64)> {
+        let importance = self
+            .fanova
+            .try_quantify_importance(&features)
+            .map_err(into_py_err)?;
+        Ok((importance.mean, importance.stddev))
+    }
+
+    /// Returns the grid of `n_points` equally spaced points over the range of `feature` and the
+    /// means and standard deviations (across the trees) of the marginal predictions at them.
+    #[pyo3(signature = (feature, n_points = 100))]
+    fn get_marginal(
+        &self,
+        feature: usize,
+        n_points: usize,
+    ) -> PyResult<(Vec<f64>, Vec<f64>, Vec<f64>)> {
+        let features_len = self.fanova.feature_space().ranges().len();
+        if feature >= features_len {
+            return Err(into_py_err(FanovaError::FeatureOutOfRange {
+                feature,
+                features_len,
+            }));
+        }
+        if n_points < 2 {
+            return Err(Error::new_err("n_points must be two or more"));
+        }
+        let mut grid = Vec::with_capacity(n_points);
+        let mut means = Vec::with_capacity(n_points);
+        let mut stddevs = Vec::with_capacity(n_points);
+        for (x, mean, stddev) in self.fanova.marginal_grid(feature, n_points) {
+            grid.push(x);
+            means.push(mean);
+            stddevs.push(stddev);
+        }
+        Ok((grid, means, stddevs))
+    }
+}
+
+/// Builds an fANOVA model for the given feature columns and target.
+#[pyfunction]
+#[pyo3(signature = (features, target, seed = None))]
+fn fit(features: Vec<Vec<f64>>, target: Vec<f64>, seed: Option<u64>) -> PyResult<PyFanova> {
+    let mut random_forest = RandomForestOptions::new();
+    if let Some(seed) = seed {
+        random_forest = random_forest.seed(seed);
+    }
+    let features = features.iter().map(|c| &c[..]).collect();
+    let fanova = FanovaOptions::new()
+        .random_forest(random_forest)
+        .fit(features, &target)
+        .map_err(into_py_err)?;
+    Ok(PyFanova { fanova })
+}
+
+#[pymodule]
+fn fanova_rs(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("FanovaError", m.py().get_type::<Error>())?;
+    m.add_class::<PyFanova>()?;
+    m.add_function(wrap_pyfunction!(fit, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::IntoPyDict;
+
+    #[test]
+    fn python_module_works() -> PyResult<()> {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = pyo3::wrap_pymodule!(fanova_rs)(py);
+            let globals = [("fanova_rs", module)].into_py_dict(py)?;
+            py.run(
+                cr#"
+features = [[0, 1, 2, 3, 4, 5, 6, 7], [1, 0, 1, 0, 1, 0, 1, 0]]
+target = [0, 1, 4, 9, 16, 25, 36, 49]
+fanova = fanova_rs.fit(features, target, seed=0)
+
+mean, std = fanova.quantify_importance([0])
+assert mean > 0.9 and std >= 0.0, (mean, std)
+
+grid, means, stds = fanova.get_marginal(0, n_points=3)
+assert grid == [0.0, 3.5, 7.0], grid
+assert means[0] < means[1] < means[2], means
+assert len(stds) == 3
+
+for f in [lambda: fanova.quantify_importance([2]), lambda: fanova.get_marginal(2),
+          lambda: fanova_rs.fit([[0, 1]], [0])]:
+    try:
+        f()
+        raise AssertionError("no error")
+    except fanova_rs.FanovaError:
+        pass
+"#,
+                Some(&globals),
+                None,
+            )
+        })
+    }
+}
@@ -1,7 +1,9 @@
 use crate::decision_tree::{DecisionTreeOptions, DecisionTreeRegressor};
 #[cfg(test)]
 use crate::functions;
+use crate::surrogate::Surrogate;
 use crate::table::Table;
+use crate::trace;
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
@@ -12,9 +14,110 @@ use std::num::NonZeroUsize;
 pub struct RandomForestOptions {
     trees: NonZeroUsize,
     max_features: Option<NonZeroUsize>,
+    max_depth: Option<NonZeroUsize>,
+    min_samples_leaf: NonZeroUsize,
+    min_weight_leaf: Option<f64>,
+    max_bins: Option<NonZeroUsize>,
+    max_thresholds: Option<NonZeroUsize>,
+    split_sampling: Option<SplitSampling>,
+    criterion: SplitCriterion,
     seed: Option<u64>,
 }
 
+/// Row subsampling used to search the splits of large nodes.
+///
+/// Only the split search uses the sampled rows, and the leaf values are computed from all the
+/// rows of the leaves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitSampling {
+    /// Searches the splits of the nodes having more than the given number of rows with that
+    /// number of rows chosen uniformly at random.
+    Uniform(NonZeroUsize),
+
+    /// Gradient-based one-side sampling (GOSS).
+    ///
+    /// The `top` fraction of the rows whose targets are the farthest from the mean of the node
+    /// are always used, and the `other` fraction of the rows are randomly chosen from the rest
+    /// (their weights are scaled up to keep the total).
+    Goss {
+        /// Fraction of the rows having the largest residuals.
+        top: f64,
+
+        /// Fraction of the rows sampled from the others.
+        other: f64,
+    },
+}
+
+/// Criterion comparing the candidate splits of the nodes.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SplitCriterion {
+    /// The reduction of the (weighted) squared error of the target.
+    #[default]
+    SquaredError,
+
+    /// The information gain ratio of C4.5 (the decrease of the entropy divided by the entropy of
+    /// the split itself), which regards the target as the indicator of a class (so the target
+    /// values should be `0.0` or `1.0`).
+    ///
+    /// This is meant for classification (e.g., `FanovaOptions::fit_categorical`), where the plain
+    /// gains favor the features having many distinct values since the best of their many
+    /// thresholds is likely to fit noise. As in C4.5 Release 8, the gain of each feature is
+    /// reduced by the logarithm of the number of its candidate thresholds (per row) before the
+    /// features are compared, and the threshold of each feature is still chosen by the
+    /// reduction of the squared error (the Gini impurity of class indicators).
+    GainRatio,
+
+    /// The reduction of the Poisson deviance, which regards the target as a count (so the target
+    /// values must be non-negative and not all zero).
+    ///
+    /// This is meant for count-valued targets (e.g., the number of epochs until early stopping
+    /// or of errors), whose variances grow with their means so that the squared error is
+    /// dominated by the large counts. The leaf values are the logarithms of the mean counts (the
+    /// log link of Poisson regression), so the importances decompose the variance of the log
+    /// counts (i.e., the relative changes of the counts), and `Fanova::predict` returns the log
+    /// counts too. The splits leaving no positive counts on either side are not taken.
+    Poisson,
+
+    /// The reduction of the Tweedie deviance of the given power, which generalizes `Poisson`
+    /// (`power` of `1.0`) to skewed positive targets.
+    ///
+    /// The powers between `1.0` and `2.0` (e.g., `1.5`) model the compound Poisson-gamma
+    /// distributions, which have a point mass at zero, so they fit zero-inflated targets (e.g.,
+    /// the GPU hours consumed by trials, which are zero for the pruned ones). The power `2.0`
+    /// is the gamma deviance and larger ones are heavier tailed, which require positive targets.
+    /// The leaf values are the logarithms of the means like `Poisson`.
+    Tweedie {
+        /// The power of the variance function (`1.0` or more).
+        power: f64,
+    },
+}
+
+impl SplitCriterion {
+    /// Returns the power of the Tweedie deviance compared by this criterion (if any).
+    pub(crate) fn tweedie_power(self) -> Option<f64> {
+        match self {
+            Self::SquaredError | Self::GainRatio => None,
+            Self::Poisson => Some(1.0),
+            Self::Tweedie { power } => Some(power),
+        }
+    }
+
+    // Converts a leaf value (or a mean of them) into the scale of the target.
+    pub(crate) fn inverse_link(self, value: f64) -> f64 {
+        if self.tweedie_power().is_some() {
+            value.exp()
+        } else {
+            value
+        }
+    }
+
+    fn check(self) {
+        if let Self::Tweedie { power } = self {
+            assert!(power.is_finite() && power >= 1.0, "invalid tweedie power");
+        }
+    }
+}
+
 impl RandomForestOptions {
     /// Makes a `RandomForestOptions` instance with the default settings.
     pub fn new() -> Self {
@@ -44,6 +147,95 @@ impl RandomForestOptions {
         self.max_features = Some(max);
         self
     }
+
+    /// Sets the maximum depth of each decision tree.
+    ///
+    /// The default value is `64`.
+    pub fn max_depth(mut self, max: NonZeroUsize) -> Self {
+        self.max_depth = Some(max);
+        self
+    }
+
+    /// Sets the minimum number of rows required to be at each leaf node.
+    ///
+    /// The default value is `1`.
+    pub fn min_samples_leaf(mut self, min: NonZeroUsize) -> Self {
+        self.min_samples_leaf = min;
+        self
+    }
+
+    /// Sets the minimum total weight of the rows required to be at each leaf node.
+    ///
+    /// This is finer than `min_samples_leaf` if the rows are weighted (see `Table::set_weights`),
+    /// e.g., a leaf of many stale rows having tiny weights is rejected.
+    ///
+    /// By default, no constraint.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is negative or not finite.
+    pub fn min_weight_leaf(mut self, min: f64) -> Self {
+        assert!(min.is_finite() && min >= 0.0, "invalid minimum weight");
+        self.min_weight_leaf = Some(min);
+        self
+    }
+
+    /// Enables approximate split search.
+    ///
+    /// The values of each feature are divided into (at most) `max` bins of nearly the same
+    /// frequency by a streaming quantile sketch, and only the boundaries of the bins are
+    /// searched. This is much faster for large tables since the rows don't need to be sorted.
+    ///
+    /// By default, all the distinct values are searched.
+    pub fn max_bins(mut self, max: NonZeroUsize) -> Self {
+        self.max_bins = Some(max);
+        self
+    }
+
+    /// Caps the number of the thresholds searched in each feature of each node.
+    ///
+    /// If a feature has more than `max` distinct values in a node (e.g., wall-clock durations),
+    /// only `max` thresholds spaced by the quantiles of the rows of the node are searched.
+    /// Unlike `max_bins`, the rows are still sorted and the thresholds adapt to each node.
+    /// This is ignored if `max_bins` is set.
+    ///
+    /// By default, all the distinct values are searched.
+    pub fn max_thresholds_per_feature(mut self, max: NonZeroUsize) -> Self {
+        self.max_thresholds = Some(max);
+        self
+    }
+
+    /// Enables row subsampling in split search (see `SplitSampling`).
+    ///
+    /// By default, all the rows of each node are used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fractions of `SplitSampling::Goss` are not in the range `(0.0, 1.0]` or if
+    /// their sum exceeds `1.0`.
+    pub fn split_sampling(mut self, sampling: SplitSampling) -> Self {
+        if let SplitSampling::Goss { top, other } = sampling {
+            assert!(
+                top > 0.0 && other > 0.0 && top + other <= 1.0,
+                "invalid sampling fractions"
+            );
+        }
+        self.split_sampling = Some(sampling);
+        self
+    }
+
+    /// Sets the criterion comparing the candidate splits (see `SplitCriterion`).
+    ///
+    /// The default value is `SplitCriterion::SquaredError`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the power of `SplitCriterion::Tweedie` is less than `1.0` or not finite.
+    pub fn criterion(mut self, criterion: SplitCriterion) -> Self {
+        criterion.check();
+        self.criterion = criterion;
+        self
+    }
 }
 
 impl Default for RandomForestOptions {
@@ -51,63 +243,412 @@ impl Default for RandomForestOptions {
         Self {
             trees: NonZeroUsize::new(100).expect("never fails"),
             max_features: None,
+            max_depth: None,
+            min_samples_leaf: NonZeroUsize::new(1).expect("never fails"),
+            min_weight_leaf: None,
+            max_bins: None,
+            max_thresholds: None,
+            split_sampling: None,
+            criterion: SplitCriterion::default(),
             seed: None,
         }
     }
 }
 
-impl RandomForestOptions {
-    fn tree_rngs(&self) -> impl Iterator<Item = StdRng> {
-        let seed = self.seed.unwrap_or_else(|| rand::thread_rng().gen());
+impl Surrogate for RandomForestOptions {
+    fn ensemble(&self, table: &Table) -> Ensemble {
+        let max_features = if let Some(n) = self.max_features {
+            n.get()
+        } else {
+            (table.features_len() as f64).sqrt().ceil() as usize
+        };
+        Ensemble {
+            trees: self.trees,
+            seed: self.seed,
+            bootstrap: true,
+            tree_options: DecisionTreeOptions {
+                max_features: Some(max_features),
+                max_depth: self.max_depth.map(|n| n.get()),
+                min_samples_leaf: Some(self.min_samples_leaf.get()),
+                min_weight_leaf: self.min_weight_leaf,
+                parallel: false,
+                max_bins: self.max_bins.map(|n| n.get()),
+                max_thresholds: self.max_thresholds.map(|n| n.get()),
+                split_sampling: self.split_sampling,
+                excluded_columns: Vec::new(),
+                random_splits: false,
+                criterion: self.criterion,
+                categorical_splits: false,
+            },
+        }
+    }
+}
+
+/// Extremely randomized trees (ExtraTrees) options.
+///
+/// Unlike a random forest, each tree is fitted to all the rows and each node is split at the
+/// best of uniformly random thresholds of the candidate features. The trees are smoother and
+/// cheaper to fit, but no out-of-bag rows are left to estimate the quality of the fit.
+#[derive(Debug, Clone)]
+pub struct ExtraTreesOptions {
+    trees: NonZeroUsize,
+    max_features: Option<NonZeroUsize>,
+    max_depth: Option<NonZeroUsize>,
+    min_samples_leaf: NonZeroUsize,
+    min_weight_leaf: Option<f64>,
+    criterion: SplitCriterion,
+    seed: Option<u64>,
+}
+
+impl ExtraTreesOptions {
+    /// Makes an `ExtraTreesOptions` instance with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the random generator seed.
+    ///
+    /// The default value is random.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Sets the number of decision trees.
+    ///
+    /// The default value is `100`.
+    pub fn trees(mut self, trees: NonZeroUsize) -> Self {
+        self.trees = trees;
+        self
+    }
+
+    /// Sets the number of maximum candidate features used to determine each decision tree node.
+    ///
+    /// The default value is the number of features.
+    pub fn max_features(mut self, max: NonZeroUsize) -> Self {
+        self.max_features = Some(max);
+        self
+    }
+
+    /// Sets the maximum depth of each decision tree.
+    ///
+    /// The default value is `64`.
+    pub fn max_depth(mut self, max: NonZeroUsize) -> Self {
+        self.max_depth = Some(max);
+        self
+    }
+
+    /// Sets the minimum number of rows required to be at each leaf node.
+    ///
+    /// The default value is `1`.
+    pub fn min_samples_leaf(mut self, min: NonZeroUsize) -> Self {
+        self.min_samples_leaf = min;
+        self
+    }
+
+    /// Sets the minimum total weight of the rows required to be at each leaf node.
+    ///
+    /// This is finer than `min_samples_leaf` if the rows are weighted (see `Table::set_weights`),
+    /// e.g., a leaf of many stale rows having tiny weights is rejected.
+    ///
+    /// By default, no constraint.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is negative or not finite.
+    pub fn min_weight_leaf(mut self, min: f64) -> Self {
+        assert!(min.is_finite() && min >= 0.0, "invalid minimum weight");
+        self.min_weight_leaf = Some(min);
+        self
+    }
+
+    /// Sets the criterion comparing the candidate splits (see `SplitCriterion`).
+    ///
+    /// The default value is `SplitCriterion::SquaredError`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the power of `SplitCriterion::Tweedie` is less than `1.0` or not finite.
+    pub fn criterion(mut self, criterion: SplitCriterion) -> Self {
+        criterion.check();
+        self.criterion = criterion;
+        self
+    }
+}
+
+impl Default for ExtraTreesOptions {
+    fn default() -> Self {
+        Self {
+            trees: NonZeroUsize::new(100).expect("never fails"),
+            max_features: None,
+            max_depth: None,
+            min_samples_leaf: NonZeroUsize::new(1).expect("never fails"),
+            min_weight_leaf: None,
+            criterion: SplitCriterion::default(),
+            seed: None,
+        }
+    }
+}
+
+impl Surrogate for ExtraTreesOptions {
+    fn ensemble(&self, _table: &Table) -> Ensemble {
+        Ensemble {
+            trees: self.trees,
+            seed: self.seed,
+            bootstrap: false,
+            tree_options: DecisionTreeOptions {
+                max_features: self.max_features.map(|n| n.get()),
+                max_depth: self.max_depth.map(|n| n.get()),
+                min_samples_leaf: Some(self.min_samples_leaf.get()),
+                min_weight_leaf: self.min_weight_leaf,
+                random_splits: true,
+                criterion: self.criterion,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Settings of fitting the trees of an ensemble, which are given by a `Surrogate`.
+#[derive(Debug, Clone)]
+pub struct Ensemble {
+    pub trees: NonZeroUsize,
+    pub seed: Option<u64>,
+
+    // Fits each tree to a bootstrap sample (otherwise to all the rows).
+    pub bootstrap: bool,
+
+    pub tree_options: DecisionTreeOptions,
+}
+
+impl Ensemble {
+    // The seed is drawn from the thread RNG (and recorded) if no seed is given.
+    fn tree_rngs(&mut self) -> impl Iterator<Item = StdRng> {
+        let seed = *self.seed.get_or_insert_with(|| rand::thread_rng().gen());
         let mut rng = StdRng::seed_from_u64(seed);
         (0..self.trees.get()).map(move |_| StdRng::seed_from_u64(rng.gen()))
     }
+
+    // Binned search doesn't need the sorted orders.
+    pub(crate) fn presort(&self) -> bool {
+        self.tree_options.max_bins.is_none()
+    }
+}
+
+/// Position in the random sequence of the trees of a random forest (see `Fanova::rng_state`).
+///
+/// The random numbers of each tree (e.g., its bootstrap sample) are drawn from its own RNG, which
+/// is seeded in turn from `seed`, so the trees after the first `trees` ones can be fitted later
+/// exactly as they would have been without the interruption.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RngState {
+    /// Seed of the sequence (drawn randomly if the surrogate model is given no seed).
+    pub seed: u64,
+
+    /// Number of the trees already fitted.
+    pub trees: usize,
+}
+
+/// Quality of the fit of a random forest estimated from the out-of-bag predictions.
+///
+/// The out-of-bag prediction of a row is the mean prediction of the trees whose bootstrap
+/// samples don't contain the row, so these are estimates of the generalization error without a
+/// holdout set. Poor values (e.g., a negative `r2`) mean that the importances computed from the
+/// forest shouldn't be trusted.
+///
+/// This is also used for the predictions of the held-out rows by `FanovaOptions::cross_validate`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FitQuality {
+    /// Coefficient of determination (R²) of the out-of-bag predictions.
+    pub r2: f64,
+
+    /// Root mean squared error of the out-of-bag predictions.
+    pub rmse: f64,
+
+    /// Number of the rows having out-of-bag predictions.
+    pub rows: usize,
+}
+
+// Out-of-bag predictions of the rows of a table accumulated over the trees.
+#[derive(Debug, Clone)]
+struct OutOfBag {
+    sums: Vec<f64>,
+    counts: Vec<u32>,
+
+    // The mean predictions are compared with the targets after this.
+    criterion: SplitCriterion,
+}
+
+impl OutOfBag {
+    fn new(rows_len: usize, criterion: SplitCriterion) -> Self {
+        Self {
+            sums: vec![0.0; rows_len],
+            counts: vec![0; rows_len],
+            criterion,
+        }
+    }
+
+    // `predictions` are pairs of the position of a row and the prediction of a tree.
+    fn add(&mut self, predictions: &[(usize, f64)]) {
+        for &(i, y) in predictions {
+            self.sums[i] += y;
+            self.counts[i] += 1;
+        }
+    }
+
+    // Returns the quality of the predictions for the targets of `table` (or `None` if no rows
+    // have out-of-bag predictions).
+    fn quality(&self, table: &Table) -> Option<FitQuality> {
+        let rows = table
+            .weighted_target()
+            .zip(self.sums.iter().zip(&self.counts))
+            .filter(|(_, (_, &count))| count > 0)
+            .map(|((y, w), (&sum, &count))| {
+                (y, w, self.criterion.inverse_link(sum / f64::from(count)))
+            })
+            .collect::<Vec<_>>();
+        FitQuality::from_predictions(&rows)
+    }
+}
+
+impl FitQuality {
+    // Returns the quality of the predictions given as triples of the target, the weight and the
+    // prediction of each row (or `None` if there are no rows).
+    pub(crate) fn from_predictions(rows: &[(f64, f64, f64)]) -> Option<Self> {
+        if rows.is_empty() {
+            return None;
+        }
+        let total = rows.iter().map(|&(_, w, _)| w).sum::<f64>();
+        let mean = rows.iter().map(|&(y, w, _)| y * w).sum::<f64>() / total;
+        let residual = rows
+            .iter()
+            .map(|&(y, w, p)| w * (y - p).powi(2))
+            .sum::<f64>();
+        let variance = rows
+            .iter()
+            .map(|&(y, w, _)| w * (y - mean).powi(2))
+            .sum::<f64>();
+        let r2 = if variance > 0.0 {
+            1.0 - residual / variance
+        } else if residual == 0.0 {
+            1.0
+        } else {
+            0.0
+        };
+        Some(FitQuality {
+            r2,
+            rmse: (residual / total).sqrt(),
+            rows: rows.len(),
+        })
+    }
 }
 
 #[derive(Debug)]
 pub struct RandomForestRegressor {
     forest: Vec<DecisionTreeRegressor>,
+    fit_quality: Option<FitQuality>,
+    rng_state: RngState,
+
+    // The elapsed times (in milliseconds) of presorting the table and fitting each tree.
+    sorting_millis: f64,
+    tree_millis: Vec<f64>,
 }
 
 impl RandomForestRegressor {
-    pub fn fit(table: Table, options: RandomForestOptions) -> Self {
-        let max_features = Self::decide_max_features(&table, &options);
-        let forest = options
-            .tree_rngs()
-            .map(|mut rng| Self::tree_fit(&mut rng, &table, max_features))
-            .collect::<Vec<_>>();
-        Self { forest }
+    #[cfg(test)]
+    pub fn fit(table: Table, surrogate: &dyn Surrogate) -> Self {
+        let mut fitter = TreeFitter::new(table, surrogate);
+        let forest = fitter.by_ref().collect();
+        Self {
+            forest,
+            fit_quality: fitter.fit_quality(),
+            rng_state: fitter.rng_state(),
+            sorting_millis: fitter.sorting_millis(),
+            tree_millis: Vec::new(),
+        }
     }
 
-    pub fn fit_parallel(table: Table, options: RandomForestOptions) -> Self {
-        let max_features = Self::decide_max_features(&table, &options);
-        let forest = options
-            .tree_rngs()
-            .collect::<Vec<_>>()
+    pub fn fit_parallel(mut table: Table, surrogate: &dyn Surrogate) -> Self {
+        let mut ensemble = surrogate.ensemble(&table);
+        let start = trace::now_millis();
+        if ensemble.presort() {
+            table.presort();
+        }
+        let sorting_millis = trace::now_millis() - start;
+        ensemble.tree_options.parallel = true;
+        let rngs = ensemble.tree_rngs().collect::<Vec<_>>();
+        let rng_state = RngState {
+            seed: ensemble.seed.expect("recorded by `tree_rngs`"),
+            trees: rngs.len(),
+        };
+        let fitted = rngs
             .into_par_iter()
-            .map(|mut rng| Self::tree_fit(&mut rng, &table, max_features))
+            .map(|mut rng| {
+                let start = trace::now_millis();
+                let fitted = Self::tree_fit(&mut rng, &table, &ensemble);
+                (fitted, trace::now_millis() - start)
+            })
             .collect::<Vec<_>>();
-        Self { forest }
-    }
 
-    fn decide_max_features(table: &Table, options: &RandomForestOptions) -> usize {
-        if let Some(n) = options.max_features {
-            n.get()
-        } else {
-            (table.features_len() as f64).sqrt().ceil() as usize
+        // The predictions are accumulated in the order of the trees to keep the results
+        // deterministic.
+        let mut oob = OutOfBag::new(table.rows_len(), ensemble.tree_options.criterion);
+        let (forest, tree_millis) = fitted
+            .into_iter()
+            .map(|((tree, predictions), millis)| {
+                oob.add(&predictions);
+                (tree, millis)
+            })
+            .unzip();
+        Self {
+            forest,
+            fit_quality: oob.quality(&table),
+            rng_state,
+            sorting_millis,
+            tree_millis,
         }
     }
 
+    /// Returns the quality of the fit (or `None` if no rows have out-of-bag predictions).
+    pub fn fit_quality(&self) -> Option<FitQuality> {
+        self.fit_quality
+    }
+
+    pub fn rng_state(&self) -> RngState {
+        self.rng_state
+    }
+
+    /// Returns the elapsed time (in milliseconds) of presorting the table.
+    pub fn sorting_millis(&self) -> f64 {
+        self.sorting_millis
+    }
+
+    /// Returns the elapsed time (in milliseconds) of fitting each tree (in the order of the
+    /// trees).
+    pub fn tree_millis(&self) -> &[f64] {
+        &self.tree_millis
+    }
+
+    // Returns the fitted tree and its out-of-bag predictions (see `OutOfBag::add`).
     fn tree_fit<R: Rng + ?Sized>(
         rng: &mut R,
         table: &Table,
-        max_features: usize,
-    ) -> DecisionTreeRegressor {
-        let table = table.bootstrap_sample(rng);
-        let tree_options = DecisionTreeOptions {
-            max_features: Some(max_features),
-        };
-        DecisionTreeRegressor::fit(rng, table, tree_options)
+        ensemble: &Ensemble,
+    ) -> (DecisionTreeRegressor, Vec<(usize, f64)>) {
+        let _span = trace::span!("fit_tree", "rows={}", table.rows_len());
+        if !ensemble.bootstrap {
+            let tree =
+                DecisionTreeRegressor::fit(rng, table.clone(), ensemble.tree_options.clone());
+            return (tree, Vec::new());
+        }
+        let sample = table.bootstrap_sample(rng);
+        let out_of_bag = table.out_of_bag_rows(&sample);
+        let tree = DecisionTreeRegressor::fit(rng, sample, ensemble.tree_options.clone());
+        let predictions = out_of_bag
+            .into_iter()
+            .map(|i| (i, tree.predict(&table.row_features(i))))
+            .collect();
+        (tree, predictions)
     }
 
     pub fn into_trees(self) -> Vec<DecisionTreeRegressor> {
@@ -120,13 +661,127 @@ impl RandomForestRegressor {
     }
 }
 
+/// Fits the decision trees of a random forest one by one.
+#[derive(Debug)]
+pub struct TreeFitter<'a> {
+    table: Table<'a>,
+    rngs: std::vec::IntoIter<StdRng>,
+    ensemble: Ensemble,
+    oob: OutOfBag,
+    seed: u64,
+    fitted: usize,
+    sorting_millis: f64,
+}
+
+impl<'a> TreeFitter<'a> {
+    pub fn new(table: Table<'a>, surrogate: &dyn Surrogate) -> Self {
+        let ensemble = surrogate.ensemble(&table);
+        Self::with_ensemble(table, ensemble)
+    }
+
+    /// Resumes the fitting interrupted at `state` (see `RngState`).
+    ///
+    /// The out-of-bag predictions of the trees fitted before are replayed by `predict(tree, xs)`,
+    /// which returns the prediction of the `tree`-th tree at `xs`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `state.trees` exceeds the number of the trees of `surrogate`.
+    pub fn resume(
+        table: Table<'a>,
+        surrogate: &dyn Surrogate,
+        state: RngState,
+        predict: impl Fn(usize, &[f64]) -> f64,
+    ) -> Self {
+        let mut ensemble = surrogate.ensemble(&table);
+        assert!(state.trees <= ensemble.trees.get(), "too many fitted trees");
+        ensemble.seed = Some(state.seed);
+        let mut fitter = Self::with_ensemble(table, ensemble);
+        for tree in 0..state.trees {
+            let mut rng = fitter.rngs.next().expect("checked above");
+            if fitter.ensemble.bootstrap {
+                // The same random numbers as `RandomForestRegressor::tree_fit`.
+                let sample = fitter.table.bootstrap_sample(&mut rng);
+                let predictions = fitter
+                    .table
+                    .out_of_bag_rows(&sample)
+                    .into_iter()
+                    .map(|i| (i, predict(tree, &fitter.table.row_features(i))))
+                    .collect::<Vec<_>>();
+                fitter.oob.add(&predictions);
+            }
+        }
+        fitter.fitted = state.trees;
+        fitter
+    }
+
+    fn with_ensemble(mut table: Table<'a>, mut ensemble: Ensemble) -> Self {
+        let start = trace::now_millis();
+        if ensemble.presort() {
+            table.presort();
+        }
+        let rngs = ensemble.tree_rngs().collect::<Vec<_>>().into_iter();
+        Self {
+            oob: OutOfBag::new(table.rows_len(), ensemble.tree_options.criterion),
+            seed: ensemble.seed.expect("recorded by `tree_rngs`"),
+            fitted: 0,
+            sorting_millis: trace::now_millis() - start,
+            table,
+            rngs,
+            ensemble,
+        }
+    }
+
+    /// Returns the position in the random sequence of the trees.
+    pub fn rng_state(&self) -> RngState {
+        RngState {
+            seed: self.seed,
+            trees: self.fitted,
+        }
+    }
+
+    /// Returns the quality of the fit of the trees fitted so far.
+    pub fn fit_quality(&self) -> Option<FitQuality> {
+        self.oob.quality(&self.table)
+    }
+
+    /// Returns the elapsed time (in milliseconds) of presorting the table.
+    pub fn sorting_millis(&self) -> f64 {
+        self.sorting_millis
+    }
+
+    /// Skips the remaining trees.
+    pub fn stop(&mut self) {
+        self.rngs = Vec::new().into_iter();
+    }
+}
+
+impl<'a> Iterator for TreeFitter<'a> {
+    type Item = DecisionTreeRegressor;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut rng = self.rngs.next()?;
+        let (tree, predictions) =
+            RandomForestRegressor::tree_fit(&mut rng, &self.table, &self.ensemble);
+        self.oob.add(&predictions);
+        self.fitted += 1;
+        Some(tree)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.rngs.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for TreeFitter<'a> {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn regression_works() -> Result<(), anyhow::Error> {
-        let columns = vec![
+        let columns = [
             // Features.
             &[
                 0.0, 0.0, 1.0, 2.0, 2.0, 2.0, 1.0, 0.0, 0.0, 2.0, 0.0, 1.0, 1.0, 2.0,
@@ -149,18 +804,31 @@ mod tests {
 
         let table = Table::new(columns.iter().map(|f| &f[..train_len]).collect())?;
 
-        let mut options = RandomForestOptions::default();
-        options.seed = Some(0);
-        let regressor = RandomForestRegressor::fit(table, options);
+        let options = RandomForestOptions::default().seed(0);
+        let regressor = RandomForestRegressor::fit(table, &options);
         assert_eq!(
             regressor.predict(&columns.iter().map(|f| f[train_len]).collect::<Vec<_>>()),
-            41.063500000000005
+            41.5705
         );
         assert_eq!(
             regressor.predict(&columns.iter().map(|f| f[train_len + 1]).collect::<Vec<_>>()),
-            44.425000000000004
+            45.070166666666665
         );
 
         Ok(())
     }
+
+    #[test]
+    fn fit_quality_works() -> Result<(), anyhow::Error> {
+        let feature = (0..14).map(f64::from).collect::<Vec<_>>();
+        let target = [
+            25.0, 30.0, 46.0, 45.0, 52.0, 23.0, 43.0, 35.0, 38.0, 46.0, 48.0, 52.0, 44.0, 30.0,
+        ];
+        let table = Table::new(vec![&feature, &target])?;
+        let regressor = RandomForestRegressor::fit(table, &RandomForestOptions::default().seed(0));
+        let quality = regressor.fit_quality().expect("out-of-bag rows");
+        assert_eq!(quality.rows, target.len());
+        assert!(quality.rmse > 0.0 && quality.r2 < 1.0);
+        Ok(())
+    }
 }
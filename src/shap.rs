@@ -0,0 +1,108 @@
+//! SHAP values of the trees (a complementary attribution method to fANOVA).
+//!
+//! Like TreeSHAP, the values are computed exactly in polynomial time from the leaves of each
+//! tree. The expectation over the missing features is taken with respect to the uniform
+//! distribution over the feature space (the same as the marginal predictions of fANOVA), so the
+//! SHAP values of a point sum up to its prediction minus the mean prediction of the tree.
+//!
+//! Each leaf contributes `value * Π_{j ∈ S} [x_j ∈ leaf_j] * Π_{j ∉ S} width_j` to the value of a
+//! coalition `S` (where `width_j` is the fraction of the feature space covered by the leaf along
+//! the `j`-th feature). The Shapley values of such a product game only depend on the features
+//! which the leaf restricts, so each leaf takes `O(d^3)` time where `d` is the number of them.
+use crate::partition::TreePartitions;
+
+/// Adds the SHAP values of `point` for the tree to `phi`.
+///
+/// NaN features of `point` are regarded as missing (i.e., their SHAP values are zero), and the
+/// other values are clamped into the feature space.
+pub fn add_tree_shap_values(partitions: &TreePartitions, point: &[f64], phi: &mut [f64]) {
+    let whole = partitions.space().ranges();
+    let point = point
+        .iter()
+        .zip(whole)
+        .map(|(&x, r)| {
+            if x.is_nan() {
+                x
+            } else {
+                x.clamp(r.start, r.end)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut players = Vec::new();
+    for (i, p) in partitions.iter().enumerate() {
+        players.clear();
+        let mut value = p.value;
+        for (j, (&width, r)) in partitions
+            .widths(i)
+            .iter()
+            .zip(p.space.ranges())
+            .enumerate()
+        {
+            if width.is_nan() || width >= 1.0 {
+                continue;
+            }
+            let x = point[j];
+            if x.is_nan() {
+                value *= width;
+                continue;
+            }
+            // The upper bound of the whole space is included in the last partition.
+            let covered = r.start <= x && (x < r.end || (x == r.end && r.end == whole[j].end));
+            players.push((j, width, if covered { 1.0 } else { 0.0 }));
+        }
+        if value == 0.0 {
+            continue;
+        }
+        for (k, &(j, width, covered)) in players.iter().enumerate() {
+            if covered != width {
+                phi[j] += value * (covered - width) * shapley_sum(&players, k);
+            }
+        }
+    }
+}
+
+// Returns `Σ_{S ⊆ players \ {k}} |S|! (m - |S| - 1)! / m! * Π_{j ∈ S} covered_j * Π_{j ∉ S} width_j`
+// where `m` is the number of the players.
+fn shapley_sum(players: &[(usize, f64, f64)], k: usize) -> f64 {
+    // `coefficients[s]` is the sum of the products over the coalitions of size `s`.
+    let mut coefficients = vec![0.0; players.len()];
+    coefficients[0] = 1.0;
+    let others = players.iter().enumerate().filter(|&(i, _)| i != k);
+    for (n, (_, &(_, width, covered))) in others.enumerate() {
+        for s in (0..=n + 1).rev() {
+            let with = if s > 0 {
+                coefficients[s - 1] * covered
+            } else {
+                0.0
+            };
+            coefficients[s] = coefficients[s] * width + with;
+        }
+    }
+
+    // `weight` is `s! (m - s - 1)! / m!`.
+    let m = players.len();
+    let mut weight = 1.0 / m as f64;
+    let mut sum = 0.0;
+    for (s, c) in coefficients.iter().enumerate() {
+        sum += weight * c;
+        weight *= (s + 1) as f64 / (m - s - 1).max(1) as f64;
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shapley_sum_works() {
+        // A single player: the only coalition is the empty one.
+        assert_eq!(shapley_sum(&[(0, 0.5, 1.0)], 0), 1.0);
+
+        // Two players: `(width + covered) / 2` of the other.
+        let players = [(0, 0.5, 1.0), (1, 0.25, 0.0)];
+        assert_eq!(shapley_sum(&players, 0), 0.125);
+        assert_eq!(shapley_sum(&players, 1), 0.75);
+    }
+}
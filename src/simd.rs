@@ -0,0 +1,103 @@
+//! Vectorized sums of `f64` slices.
+//!
+//! The `simd` feature enables the `simd128` implementation for wasm builds compiled with
+//! `-C target-feature=+simd128` (WebAssembly has no runtime feature detection, so the check
+//! is done at compile time). Otherwise, the sums are computed with two scalar accumulators.
+//!
+//! Both implementations add the terms in the same order (even and odd indices separately, then
+//! the two partial sums and the last odd term), so the results are bitwise identical.
+
+/// Returns `Σ xs[i] * ys[i]`.
+pub fn dot(xs: &[f64], ys: &[f64]) -> f64 {
+    debug_assert_eq!(xs.len(), ys.len());
+    imp::dot(xs, ys)
+}
+
+/// Returns `Σ ws[i] * (xs[i] - mean)^2`.
+pub fn weighted_squared_deviations(xs: &[f64], ws: &[f64], mean: f64) -> f64 {
+    debug_assert_eq!(xs.len(), ws.len());
+    imp::weighted_squared_deviations(xs, ws, mean)
+}
+
+#[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
+mod imp {
+    use core::arch::wasm32::{f64x2, f64x2_add, f64x2_extract_lane, f64x2_mul, f64x2_splat};
+    use core::arch::wasm32::{f64x2_sub, v128};
+
+    fn load(xs: &[f64]) -> v128 {
+        f64x2(xs[0], xs[1])
+    }
+
+    fn horizontal_sum(acc: v128) -> f64 {
+        f64x2_extract_lane::<0>(acc) + f64x2_extract_lane::<1>(acc)
+    }
+
+    pub fn dot(xs: &[f64], ys: &[f64]) -> f64 {
+        let (xs, ys) = (xs.chunks_exact(2), ys.chunks_exact(2));
+        let rest = xs
+            .remainder()
+            .iter()
+            .zip(ys.remainder())
+            .map(|(x, y)| x * y);
+        let acc = xs.zip(ys).fold(f64x2_splat(0.0), |acc, (x, y)| {
+            f64x2_add(acc, f64x2_mul(load(x), load(y)))
+        });
+        horizontal_sum(acc) + rest.sum::<f64>()
+    }
+
+    pub fn weighted_squared_deviations(xs: &[f64], ws: &[f64], mean: f64) -> f64 {
+        let (xs, ws) = (xs.chunks_exact(2), ws.chunks_exact(2));
+        let rest = xs.remainder().iter().zip(ws.remainder());
+        let rest = rest.map(|(x, w)| w * ((x - mean) * (x - mean)));
+        let means = f64x2_splat(mean);
+        let acc = xs.zip(ws).fold(f64x2_splat(0.0), |acc, (x, w)| {
+            let d = f64x2_sub(load(x), means);
+            f64x2_add(acc, f64x2_mul(load(w), f64x2_mul(d, d)))
+        });
+        horizontal_sum(acc) + rest.sum::<f64>()
+    }
+}
+
+#[cfg(not(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128")))]
+mod imp {
+    // Mirrors the lanes of the `simd128` implementation.
+    fn pairwise_sum(terms: impl Iterator<Item = f64>) -> f64 {
+        let mut acc = [0.0, 0.0];
+        let mut rest = None;
+        for (i, term) in terms.enumerate() {
+            if i.is_multiple_of(2) {
+                rest = Some(term);
+            } else {
+                acc[0] += rest.take().expect("never fails");
+                acc[1] += term;
+            }
+        }
+        (acc[0] + acc[1]) + rest.into_iter().sum::<f64>()
+    }
+
+    pub fn dot(xs: &[f64], ys: &[f64]) -> f64 {
+        pairwise_sum(xs.iter().zip(ys).map(|(x, y)| x * y))
+    }
+
+    pub fn weighted_squared_deviations(xs: &[f64], ws: &[f64], mean: f64) -> f64 {
+        pairwise_sum(
+            xs.iter()
+                .zip(ws)
+                .map(|(x, w)| w * ((x - mean) * (x - mean))),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sums_work() {
+        let xs = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let ws = [0.5, 0.5, 1.0, 2.0, 0.0];
+        assert_eq!(dot(&xs, &ws), 12.5);
+        assert_eq!(weighted_squared_deviations(&xs, &ws, 3.0), 4.5);
+        assert_eq!(dot(&[], &[]), 0.0);
+    }
+}
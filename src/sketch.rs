@@ -0,0 +1,118 @@
+//! Streaming quantile sketch (Greenwald-Khanna) used to find approximate split candidates.
+
+/// Quantile summary whose answers are within `epsilon * n` of the exact ranks.
+#[derive(Debug, Clone)]
+pub struct QuantileSketch {
+    epsilon: f64,
+    n: usize,
+    tuples: Vec<Tuple>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Tuple {
+    value: f64,
+
+    // The difference between the minimum ranks of this and the previous tuples.
+    g: usize,
+
+    // The difference between the maximum and minimum ranks of this tuple.
+    delta: usize,
+}
+
+impl QuantileSketch {
+    pub fn new(epsilon: f64) -> Self {
+        assert!(epsilon > 0.0, "epsilon must be positive");
+        Self {
+            epsilon,
+            n: 0,
+            tuples: Vec::new(),
+        }
+    }
+
+    /// Adds a (non-NaN) value.
+    pub fn insert(&mut self, value: f64) {
+        let i = self.tuples.partition_point(|t| t.value <= value);
+        let delta = if i == 0 || i == self.tuples.len() {
+            0
+        } else {
+            self.band()
+        };
+        self.tuples.insert(i, Tuple { value, g: 1, delta });
+        self.n += 1;
+
+        let period = ((1.0 / (2.0 * self.epsilon)) as usize).max(1);
+        if self.n.is_multiple_of(period) {
+            self.compress();
+        }
+    }
+
+    /// Returns a value whose rank is approximately `q * n`.
+    ///
+    /// Returns `None` if no values have been added.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        let last = self.tuples.last()?;
+        let rank = q.clamp(0.0, 1.0) * self.n as f64;
+        let bound = self.epsilon * self.n as f64;
+        let mut min_rank = 0;
+        for (i, t) in self.tuples.iter().enumerate() {
+            min_rank += t.g;
+            let next = self.tuples.get(i + 1);
+            let next_max_rank = next.map_or(usize::MAX, |n| min_rank + n.g + n.delta);
+            if next_max_rank as f64 > rank + bound {
+                return Some(t.value);
+            }
+        }
+        Some(last.value)
+    }
+
+    fn band(&self) -> usize {
+        (2.0 * self.epsilon * self.n as f64).floor() as usize
+    }
+
+    // Merges the tuples which can be merged without exceeding the error bound.
+    fn compress(&mut self) {
+        let band = self.band();
+        let mut i = self.tuples.len().saturating_sub(2);
+        while i >= 1 {
+            let (t, next) = (self.tuples[i], self.tuples[i + 1]);
+            if t.g + next.g + next.delta <= band {
+                self.tuples[i + 1].g += t.g;
+                self.tuples.remove(i);
+            }
+            i -= 1;
+        }
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.tuples.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom as _;
+    use rand::SeedableRng;
+
+    #[test]
+    fn quantile_sketch_works() {
+        let n = 10000;
+        let mut values = (0..n).map(|i| i as f64).collect::<Vec<_>>();
+        values.shuffle(&mut StdRng::seed_from_u64(0));
+
+        let epsilon = 0.01;
+        let mut sketch = QuantileSketch::new(epsilon);
+        assert_eq!(sketch.quantile(0.5), None);
+        for &x in &values {
+            sketch.insert(x);
+        }
+        assert!(sketch.len() < n / 10);
+        for q in [0.0, 0.1, 0.25, 0.5, 0.9, 1.0] {
+            let x = sketch.quantile(q).expect("not empty");
+            let error = (x - q * n as f64).abs();
+            assert!(error <= epsilon * n as f64 + 1.0, "q={q}, x={x}");
+        }
+    }
+}
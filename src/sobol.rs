@@ -0,0 +1,279 @@
+//! Sobol indices estimated by quasi-Monte Carlo sampling (a complementary method to fANOVA).
+//!
+//! The indices are estimated from the Saltelli design: two sample matrices `A` and `B` are
+//! drawn from a low-discrepancy Sobol sequence over the feature space, and the function is
+//! evaluated at the rows of `A`, `B` and `A` whose `i`-th column is taken from `B` (for each
+//! feature `i`). The first-order indices use the estimator of Saltelli et al. (2010) and the
+//! total indices use the one of Jansen (1999).
+use crate::functions;
+use crate::space::FeatureSpace;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::num::NonZeroUsize;
+
+/// Sobol indices of a feature.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SobolIndices {
+    /// Fraction of the variance explained by the feature alone.
+    pub first_order: f64,
+
+    /// Fraction of the variance explained by the feature including all its interactions.
+    pub total: f64,
+}
+
+// The primitive polynomials and the initial direction numbers of the dimensions after the first
+// one, taken from "new-joe-kuo-6.21201" by S. Joe and F. Y. Kuo: `(s, a, m)` where `s` is the
+// degree of the polynomial and `a` encodes its inner coefficients.
+const DIRECTIONS: [(u32, u32, &[u32]); 20] = [
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+    (5, 4, &[1, 1, 5, 5, 5]),
+    (5, 7, &[1, 1, 7, 11, 19]),
+    (5, 11, &[1, 1, 5, 1, 1]),
+    (5, 13, &[1, 1, 1, 3, 11]),
+    (5, 14, &[1, 3, 5, 5, 31]),
+    (6, 1, &[1, 3, 3, 9, 7, 49]),
+    (6, 13, &[1, 1, 1, 15, 21, 21]),
+    (6, 16, &[1, 3, 1, 13, 27, 49]),
+    (6, 19, &[1, 1, 1, 15, 7, 5]),
+    (6, 22, &[1, 3, 1, 15, 13, 25]),
+    (6, 25, &[1, 1, 5, 5, 19, 61]),
+    (7, 1, &[1, 3, 7, 11, 23, 15, 103]),
+    (7, 4, &[1, 3, 7, 13, 13, 15, 69]),
+];
+
+const BITS: usize = 32;
+
+/// The maximum number of dimensions generated by `SobolSequence`.
+pub const SOBOL_DIMENSIONS: usize = DIRECTIONS.len() + 1;
+
+/// Sobol low-discrepancy sequence in the unit hypercube (generated in the Gray code order).
+#[derive(Debug, Clone)]
+pub struct SobolSequence {
+    // `directions[j][k]` is the `k`-th direction number of the `j`-th dimension.
+    directions: Vec<[u32; BITS]>,
+    state: Vec<u32>,
+    index: u32,
+}
+
+impl SobolSequence {
+    /// Makes a sequence of the given number of dimensions.
+    ///
+    /// The first point (the origin) is skipped since it is degenerate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `dimensions` exceeds `SOBOL_DIMENSIONS`.
+    pub fn new(dimensions: usize) -> Self {
+        assert!(
+            dimensions <= SOBOL_DIMENSIONS,
+            "too many dimensions for the Sobol sequence"
+        );
+        let directions = (0..dimensions)
+            .map(|j| {
+                let mut v = [0; BITS];
+                match j.checked_sub(1).map(|j| DIRECTIONS[j]) {
+                    None => {
+                        for (k, v) in v.iter_mut().enumerate() {
+                            *v = 1 << (BITS - 1 - k);
+                        }
+                    }
+                    Some((s, a, m)) => {
+                        let s = s as usize;
+                        let mut m = m.to_vec();
+                        for k in s..BITS {
+                            let mut x = m[k - s] ^ (m[k - s] << s);
+                            for i in 1..s {
+                                x ^= ((a >> (s - 1 - i)) & 1) * (m[k - i] << i);
+                            }
+                            m.push(x);
+                        }
+                        for (k, v) in v.iter_mut().enumerate() {
+                            *v = m[k] << (BITS - 1 - k);
+                        }
+                    }
+                }
+                v
+            })
+            .collect();
+        let mut this = Self {
+            directions,
+            state: vec![0; dimensions],
+            index: 0,
+        };
+        this.advance();
+        this
+    }
+
+    /// Writes the next point to `point`.
+    pub fn next_point(&mut self, point: &mut [f64]) {
+        debug_assert_eq!(point.len(), self.state.len());
+        for (x, &s) in point.iter_mut().zip(&self.state) {
+            *x = f64::from(s) / 2f64.powi(BITS as i32);
+        }
+        self.advance();
+    }
+
+    fn advance(&mut self) {
+        // The state changes by the direction number of the lowest zero bit of the index.
+        let k = self.index.trailing_ones() as usize;
+        for (s, v) in self.state.iter_mut().zip(&self.directions) {
+            *s ^= v[k];
+        }
+        self.index += 1;
+    }
+}
+
+/// Estimates the Sobol indices of `f` over `space` from `samples` rows of the Saltelli design.
+///
+/// `f` is evaluated `samples * (features + 2)` times. The sample matrices are drawn from the
+/// Sobol sequence for up to `SOBOL_DIMENSIONS / 2` features, and the remaining columns are
+/// filled with (seeded) pseudo-random numbers.
+///
+/// Note that the estimates may slightly fall outside the range `[0.0, 1.0]` when `samples` is
+/// small.
+pub fn sobol_indices<F>(
+    space: &FeatureSpace,
+    samples: NonZeroUsize,
+    parallel: bool,
+    f: F,
+) -> Vec<SobolIndices>
+where
+    F: Fn(&[f64]) -> f64 + Sync,
+{
+    let features_len = space.ranges().len();
+    let (a, b) = saltelli_matrices(space, samples.get());
+
+    // `y[0]` and `y[1]` are the values at `A` and `B`, and `y[i + 2]` is the ones at `A` whose
+    // `i`-th column is taken from `B`.
+    let evaluate = |k: usize| -> Vec<f64> {
+        let mut point = vec![0.0; features_len];
+        (0..samples.get())
+            .map(|n| {
+                let a = &a[n * features_len..][..features_len];
+                let b = &b[n * features_len..][..features_len];
+                match k {
+                    0 => f(a),
+                    1 => f(b),
+                    _ => {
+                        point.copy_from_slice(a);
+                        point[k - 2] = b[k - 2];
+                        f(&point)
+                    }
+                }
+            })
+            .collect()
+    };
+    let y = if parallel {
+        (0..features_len + 2)
+            .into_par_iter()
+            .map(evaluate)
+            .collect::<Vec<_>>()
+    } else {
+        (0..features_len + 2).map(evaluate).collect::<Vec<_>>()
+    };
+
+    let (_, stddev) = functions::mean_and_stddev(y[0].iter().chain(&y[1]).copied());
+    let variance = stddev * stddev;
+    y[2..]
+        .iter()
+        .map(|y_i| {
+            if variance == 0.0 {
+                return SobolIndices {
+                    first_order: 0.0,
+                    total: 0.0,
+                };
+            }
+            let first_order = functions::mean(
+                y[1].iter()
+                    .zip(&y[0])
+                    .zip(y_i)
+                    .map(|((b, a), ab)| b * (ab - a)),
+            );
+            let total = functions::mean(y[0].iter().zip(y_i).map(|(a, ab)| (a - ab).powi(2)));
+            SobolIndices {
+                first_order: first_order / variance,
+                total: total / 2.0 / variance,
+            }
+        })
+        .collect()
+}
+
+// Returns the (row-major) sample matrices `A` and `B` scaled into `space`.
+fn saltelli_matrices(space: &FeatureSpace, samples: usize) -> (Vec<f64>, Vec<f64>) {
+    let ranges = space.ranges();
+    let features_len = ranges.len();
+    let sobol_len = (2 * features_len).min(SOBOL_DIMENSIONS);
+    let mut sequence = SobolSequence::new(sobol_len);
+    let mut rng = StdRng::seed_from_u64(0);
+
+    let mut point = vec![0.0; 2 * features_len];
+    let (mut a, mut b) = (Vec::new(), Vec::new());
+    for _ in 0..samples {
+        sequence.next_point(&mut point[..sobol_len]);
+        for x in &mut point[sobol_len..] {
+            *x = rng.gen();
+        }
+        let (u, v) = point.split_at(features_len);
+        for ((&u, &v), r) in u.iter().zip(v).zip(ranges) {
+            a.push(r.start + u * (r.end - r.start));
+            b.push(r.start + v * (r.end - r.start));
+        }
+    }
+    (a, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sobol_sequence_works() {
+        let mut sequence = SobolSequence::new(SOBOL_DIMENSIONS);
+        let mut point = vec![0.0; SOBOL_DIMENSIONS];
+        let points = (0..255)
+            .map(|_| {
+                sequence.next_point(&mut point);
+                point.clone()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(points[0][..3], [0.5, 0.5, 0.5]);
+        assert_eq!(points[1][..3], [0.75, 0.25, 0.25]);
+        assert_eq!(points[2][..3], [0.25, 0.75, 0.75]);
+
+        // Including the skipped origin, each dimension of the first 256 points places exactly
+        // one point in each interval of width 1/256.
+        for j in 0..SOBOL_DIMENSIONS {
+            let mut cells = points
+                .iter()
+                .map(|p| (p[j] * 256.0) as usize)
+                .collect::<Vec<_>>();
+            cells.sort_unstable();
+            assert_eq!(cells, (1..256).collect::<Vec<_>>(), "dimension={j}");
+        }
+    }
+
+    #[test]
+    fn sobol_indices_works() {
+        // `x1` is additive, `x2` only interacts with `x0`, and `x3` is constant.
+        let space = FeatureSpace::from_ranges(vec![-1.0..1.0, -1.0..1.0, -1.0..1.0, 0.0..0.0]);
+        let samples = NonZeroUsize::new(4096).expect("not zero");
+        let f = |x: &[f64]| x[0] + 2.0 * x[1] + x[0] * x[2];
+        let indices = sobol_indices(&space, samples, false, f);
+
+        // The variance is `1/3 + 4/3 + 1/9 = 16/9`.
+        let expected = [(3.0 / 16.0, 4.0 / 16.0), (12.0 / 16.0, 12.0 / 16.0)];
+        let expected = [expected[0], expected[1], (0.0, 1.0 / 16.0), (0.0, 0.0)];
+        for (i, (s, (first_order, total))) in indices.iter().zip(expected).enumerate() {
+            assert!((s.first_order - first_order).abs() < 0.02, "i={i}, {s:?}");
+            assert!((s.total - total).abs() < 0.02, "i={i}, {s:?}");
+        }
+        assert_eq!(sobol_indices(&space, samples, true, f), indices);
+    }
+}
@@ -1,5 +1,5 @@
-use crate::table::Table;
-use ordered_float::OrderedFloat;
+use crate::functions;
+use crate::table::{ColumnType, Table};
 use std::ops::Range;
 
 #[derive(Debug, Clone)]
@@ -9,24 +9,57 @@ impl FeatureSpace {
     pub fn from_table(table: &Table) -> Self {
         let ranges = (0..table.features_len())
             .map(|i| {
-                let start = table
-                    .column(i)
-                    .min_by_key(|&v| OrderedFloat(v))
-                    .expect("never fails");
-                let end = table
-                    .column(i)
-                    .max_by_key(|&v| OrderedFloat(v))
-                    .expect("never fails");
-                Range { start, end }
+                // NaNs (i.e., inactive parameters) are ignored.
+                let values = table.column(i).filter(|v| !v.is_nan());
+                let start = values
+                    .clone()
+                    .min_by(|&a, &b| functions::total_cmp(a, b))
+                    .unwrap_or(0.0);
+                let end = values
+                    .max_by(|&a, &b| functions::total_cmp(a, b))
+                    .unwrap_or(0.0);
+                match table.column_type(i) {
+                    ColumnType::Continuous => Range { start, end },
+                    ColumnType::Integer | ColumnType::Categorical => Range {
+                        start: start - 0.5,
+                        end: end + 0.5,
+                    },
+                    ColumnType::Boolean => Range {
+                        start: -0.5,
+                        end: 1.5,
+                    },
+                }
             })
             .collect();
         Self(ranges)
     }
 
+    pub fn from_ranges(ranges: Vec<Range<f64>>) -> Self {
+        Self(ranges)
+    }
+
+    /// Moves the boundaries lying on the bounds of `old` to the bounds of `new`.
+    ///
+    /// This is used to extend the partitions of `old` to cover `new` (a superset of `old`).
+    pub fn rebase(&mut self, old: &Self, new: &Self) {
+        for ((r, old), new) in self.0.iter_mut().zip(old.0.iter()).zip(new.0.iter()) {
+            if r.start == old.start {
+                r.start = new.start;
+            }
+            if r.end == old.end {
+                r.end = new.end;
+            }
+        }
+    }
+
     pub fn ranges(&self) -> &[Range<f64>] {
         &self.0
     }
 
+    pub fn into_ranges(self) -> Vec<Range<f64>> {
+        self.0
+    }
+
     pub fn split(&self, feature_index: usize, split_point: f64) -> (Self, Self) {
         debug_assert!(feature_index < self.0.len());
         debug_assert!(self.0[feature_index].start <= split_point);
@@ -39,15 +72,6 @@ impl FeatureSpace {
         (lower, upper)
     }
 
-    pub fn marginal_size(&self, fixed: &[usize]) -> f64 {
-        self.0
-            .iter()
-            .enumerate()
-            .filter(|(i, _)| !fixed.contains(i))
-            .map(|(_, r)| r.end - r.start)
-            .product()
-    }
-
     pub fn partial_size(&self, features: &[usize]) -> f64 {
         features
             .iter()
@@ -55,7 +79,93 @@ impl FeatureSpace {
             .product()
     }
 
+    #[cfg(test)]
     pub fn size(&self) -> f64 {
         self.0.iter().map(|r| r.end - r.start).product()
     }
 }
+
+/// A subset of a feature space given by the union of disjoint ranges of each feature.
+///
+/// The subset is mapped into a smaller feature space by removing the gaps between the ranges
+/// (i.e., the values above a gap are shifted down by its width).
+#[derive(Debug, Clone)]
+pub struct Domain(Vec<Vec<Range<f64>>>);
+
+impl Domain {
+    /// Makes the domain of `space` restricted to the given ranges of each feature (`None` for
+    /// the whole range).
+    ///
+    /// The ranges are clipped into `space`, and the ranges of constant features are ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ranges of a feature are not sorted and disjoint or don't overlap `space`.
+    pub fn new(space: &FeatureSpace, domain: &[Option<Vec<Range<f64>>>]) -> Self {
+        assert_eq!(
+            domain.len(),
+            space.0.len(),
+            "a domain must be given for each feature"
+        );
+        let ranges = space
+            .0
+            .iter()
+            .zip(domain)
+            .map(|(whole, ranges)| {
+                let Some(ranges) = ranges.as_ref().filter(|_| whole.end > whole.start) else {
+                    return vec![whole.clone()];
+                };
+                assert!(
+                    ranges.iter().all(|r| r.start < r.end)
+                        && ranges.windows(2).all(|w| w[0].end <= w[1].start),
+                    "the ranges of a feature must be sorted and disjoint"
+                );
+                let clipped = ranges
+                    .iter()
+                    .map(|r| r.start.max(whole.start)..r.end.min(whole.end))
+                    .filter(|r| r.start < r.end)
+                    .collect::<Vec<_>>();
+                assert!(
+                    !clipped.is_empty(),
+                    "the ranges of a feature must overlap the feature space"
+                );
+                clipped
+            })
+            .collect();
+        Self(ranges)
+    }
+
+    /// Returns the feature space which this domain is mapped into.
+    pub fn space(&self) -> FeatureSpace {
+        let ranges = self.0.iter().map(|ranges| {
+            let last = ranges.last().expect("non empty");
+            ranges[0].start..Self::map(ranges, last.end)
+        });
+        FeatureSpace(ranges.collect())
+    }
+
+    /// Maps the intersection of `space` and this domain, or returns `None` if they don't
+    /// overlap.
+    pub fn project(&self, space: &FeatureSpace) -> Option<FeatureSpace> {
+        space
+            .0
+            .iter()
+            .zip(&self.0)
+            .map(|(r, ranges)| {
+                let projected = Self::map(ranges, r.start)..Self::map(ranges, r.end);
+                let constant = ranges[0].start == ranges[0].end;
+                (projected.start < projected.end || constant).then_some(projected)
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(FeatureSpace)
+    }
+
+    // Maps a value into the space (values in gaps are mapped to the end of the lower range).
+    fn map(ranges: &[Range<f64>], x: f64) -> f64 {
+        let covered = ranges
+            .iter()
+            .map(|r| (x - r.start).clamp(0.0, r.end - r.start))
+            .sum::<f64>();
+        ranges[0].start + covered
+    }
+}
@@ -0,0 +1,160 @@
+//! Tables built from rows fed in chunks (e.g., trials loaded page by page).
+use crate::table::{NonFiniteTargets, Table, TableError};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::num::NonZeroUsize;
+
+/// Builder of a table whose rows are fed in chunks.
+///
+/// At most `capacity` rows are kept, so the memory usage is bounded regardless of the number of
+/// the fed rows. Once more rows are fed, the kept rows are a uniform random sample of all the
+/// fed rows (reservoir sampling), which is enough to fit the trees and estimate the importances
+/// of studies having hundreds of thousands of trials.
+#[derive(Debug, Clone)]
+pub struct TableStream {
+    // The feature columns followed by the target column.
+    columns: Vec<Vec<f64>>,
+    capacity: NonZeroUsize,
+    rows_seen: usize,
+    rng: StdRng,
+}
+
+impl TableStream {
+    /// Makes an empty stream of a table having `features_len` features.
+    ///
+    /// `seed` is used to choose the kept rows once more than `capacity` rows are fed.
+    pub fn new(features_len: usize, capacity: NonZeroUsize, seed: u64) -> Self {
+        Self {
+            columns: vec![Vec::new(); features_len + 1],
+            capacity,
+            rows_seen: 0,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Feeds a chunk of rows given as the feature columns and the target column.
+    ///
+    /// The values are kept as they are (see `TableStream::to_table_with` to handle non-finite
+    /// targets when the table is built, or `Table::with_infinities` for the infinite values of
+    /// `TableStream::columns`).
+    pub fn push_chunk(&mut self, features: &[&[f64]], target: &[f64]) -> Result<(), TableError> {
+        if features.len() + 1 != self.columns.len() {
+            return Err(TableError::RowSizeMismatch);
+        }
+        for (feature, column) in features.iter().enumerate() {
+            if column.len() != target.len() {
+                return Err(TableError::ColumnLengthMismatch {
+                    feature,
+                    expected: target.len(),
+                    actual: column.len(),
+                });
+            }
+        }
+
+        let capacity = self.capacity.get();
+        for row in 0..target.len() {
+            let values = features.iter().map(|c| c[row]).chain([target[row]]);
+            if self.rows_seen < capacity {
+                for (column, x) in self.columns.iter_mut().zip(values) {
+                    column.push(x);
+                }
+            } else {
+                // Algorithm R: the row replaces a random kept row with the probability
+                // `capacity / (rows_seen + 1)`.
+                let i = self.rng.gen_range(0..=self.rows_seen as u64) as usize;
+                if i < capacity {
+                    for (column, x) in self.columns.iter_mut().zip(values) {
+                        column[i] = x;
+                    }
+                }
+            }
+            self.rows_seen += 1;
+        }
+        Ok(())
+    }
+
+    /// Returns the number of the fed rows.
+    pub fn rows_seen(&self) -> usize {
+        self.rows_seen
+    }
+
+    /// Returns the number of the kept rows.
+    pub fn rows_len(&self) -> usize {
+        self.columns.last().map_or(0, Vec::len)
+    }
+
+    /// Returns the kept feature columns followed by the target column.
+    pub fn columns(&self) -> Vec<&[f64]> {
+        self.columns.iter().map(|c| c.as_slice()).collect()
+    }
+
+    /// Builds a table of the kept rows.
+    pub fn to_table(&self) -> Result<Table<'_>, TableError> {
+        Table::new(self.columns())
+    }
+
+    /// Builds a table of the kept rows like `TableStream::to_table` but handles the rows whose
+    /// targets are not finite (e.g., diverged trials) as specified by `policy` (see
+    /// `Table::with_non_finite_targets`).
+    ///
+    /// Returns the table and the number of such rows.
+    pub fn to_table_with(
+        &self,
+        policy: NonFiniteTargets,
+    ) -> Result<(Table<'_>, usize), TableError> {
+        Table::with_non_finite_targets(self.columns(), policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_stream_works() -> anyhow::Result<()> {
+        let capacity = NonZeroUsize::new(100).expect("non zero");
+        let mut stream = TableStream::new(1, capacity, 0);
+        assert!(stream.push_chunk(&[], &[0.0]).is_err());
+        assert!(stream.push_chunk(&[&[0.0, 1.0]], &[0.0]).is_err());
+
+        let xs = (0..1000).map(f64::from).collect::<Vec<_>>();
+        for chunk in xs.chunks(64) {
+            stream.push_chunk(&[chunk], chunk)?;
+            if stream.rows_seen() == 64 {
+                assert_eq!(stream.columns(), [chunk, chunk]);
+            }
+        }
+        assert_eq!(stream.rows_seen(), 1000);
+        assert_eq!(stream.rows_len(), 100);
+
+        // The kept rows are distinct and spread over all the chunks.
+        let table = stream.to_table()?;
+        let mut kept = table.column(0).collect::<Vec<_>>();
+        assert_eq!(kept, table.target().collect::<Vec<_>>());
+        kept.sort_by(f64::total_cmp);
+        kept.dedup();
+        assert_eq!(kept.len(), 100);
+        assert!(kept.iter().filter(|&&x| x >= 500.0).count() > 30);
+
+        // The same seed keeps the same rows.
+        let mut same = TableStream::new(1, capacity, 0);
+        same.push_chunk(&[&xs], &xs)?;
+        assert_eq!(same.columns(), stream.columns());
+        Ok(())
+    }
+
+    #[test]
+    fn to_table_with_works() -> anyhow::Result<()> {
+        let capacity = NonZeroUsize::new(100).expect("non zero");
+        let mut stream = TableStream::new(1, capacity, 0);
+        stream.push_chunk(&[&[0.0, 1.0, 2.0]], &[1.0, f64::NAN, 3.0])?;
+        assert!(stream.to_table().is_err());
+        assert!(stream.to_table_with(NonFiniteTargets::Error).is_err());
+
+        let (table, non_finite) = stream.to_table_with(NonFiniteTargets::Drop)?;
+        assert_eq!(non_finite, 1);
+        assert_eq!(table.column(0).collect::<Vec<_>>(), [0.0, 2.0]);
+        assert_eq!(table.target().collect::<Vec<_>>(), [1.0, 3.0]);
+        Ok(())
+    }
+}
@@ -0,0 +1,358 @@
+//! Reconstruction of the nodes of the fitted trees (e.g., to visualize them).
+//!
+//! The fitted trees are kept as the partitions of the feature space covered by their leaves,
+//! which is enough to compute the importances but loses the splits. Since the leaves of a
+//! binary tree tile the space of each node (and the partitions are kept in a depth-first order),
+//! the splits are recovered by looking for a threshold that no partition straddles. The
+//! statistics of the nodes are taken with respect to the uniform distribution over the feature
+//! space (like the importances), not the rows.
+use crate::partition::{Partition, TreePartitions};
+use std::ops::Range;
+
+/// A node of a fitted tree (see `Fanova::tree_structure`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum TreeNode {
+    /// A leaf (or a subtree pruned by the maximum depth).
+    Leaf {
+        /// The mean prediction over the space of the leaf.
+        value: f64,
+
+        /// The fraction of the feature space covered by the leaf.
+        fraction: f64,
+
+        /// The number of the leaves of the fitted tree merged into this node (`1` unless the
+        /// subtree is pruned).
+        leaves: usize,
+    },
+
+    /// An internal node.
+    Split {
+        /// The index of the split feature.
+        feature: usize,
+
+        /// The points whose feature values are less than or equal to this go to `left`.
+        threshold: f64,
+
+        /// The mean prediction over the space of the node.
+        value: f64,
+
+        /// The fraction of the feature space covered by the node.
+        fraction: f64,
+
+        /// The fraction of the variance of the tree explained by this split (the gains of all
+        /// the splits of a tree sum up to `1.0`).
+        gain: f64,
+
+        /// The child of the smaller feature values.
+        left: Box<TreeNode>,
+
+        /// The child of the larger feature values.
+        right: Box<TreeNode>,
+    },
+}
+
+impl TreeNode {
+    /// Returns the value of the leaf where `point` falls.
+    pub fn predict(&self, point: &[f64]) -> f64 {
+        match self {
+            Self::Leaf { value, .. } => *value,
+            Self::Split {
+                feature,
+                threshold,
+                left,
+                right,
+                ..
+            } => {
+                if point[*feature] <= *threshold {
+                    left.predict(point)
+                } else {
+                    right.predict(point)
+                }
+            }
+        }
+    }
+
+    /// Returns the value of this node.
+    pub fn value(&self) -> f64 {
+        match self {
+            Self::Leaf { value, .. } | Self::Split { value, .. } => *value,
+        }
+    }
+
+    /// Calls `f(feature, threshold, depth)` for each split (the root is at the depth `0`).
+    pub(crate) fn for_each_split(&self, f: &mut impl FnMut(usize, f64, usize)) {
+        self.for_each_split_below(0, f);
+    }
+
+    fn for_each_split_below(&self, depth: usize, f: &mut impl FnMut(usize, f64, usize)) {
+        if let Self::Split {
+            feature,
+            threshold,
+            left,
+            right,
+            ..
+        } = self
+        {
+            f(*feature, *threshold, depth);
+            left.for_each_split_below(depth + 1, f);
+            right.for_each_split_below(depth + 1, f);
+        }
+    }
+
+    /// Replaces the threshold of each split with `f(feature, threshold)` and the value of each
+    /// node with `g(value)`.
+    ///
+    /// `f` must be increasing (e.g., to convert the thresholds into the original space of the
+    /// features).
+    pub(crate) fn map(
+        &mut self,
+        f: &mut impl FnMut(usize, f64) -> f64,
+        g: &mut impl FnMut(f64) -> f64,
+    ) {
+        match self {
+            Self::Leaf { value, .. } => *value = g(*value),
+            Self::Split {
+                feature,
+                threshold,
+                value,
+                left,
+                right,
+                ..
+            } => {
+                *threshold = f(*feature, *threshold);
+                *value = g(*value);
+                left.map(f, g);
+                right.map(f, g);
+            }
+        }
+    }
+}
+
+/// Rebuilds the tree whose leaves are `partitions`.
+///
+/// `variance` is the variance of the predictions of the tree over the space of `partitions`,
+/// and the nodes deeper than `max_depth` (if any) are merged into leaves.
+pub(crate) fn reconstruct(
+    partitions: &TreePartitions,
+    variance: f64,
+    max_depth: Option<usize>,
+) -> TreeNode {
+    let fractions = (0..partitions.len())
+        .map(|i| partitions.widths(i).iter().product::<f64>())
+        .collect::<Vec<_>>();
+    let builder = Builder {
+        partitions: partitions.iter().collect(),
+        fractions,
+        variance,
+        max_depth,
+    };
+    builder.build(0..partitions.len(), partitions.space().ranges(), 0)
+}
+
+// The partitions are in a depth-first order of the leaves (see `TreePartitions::new`), so the
+// leaves of each node are a contiguous range of them.
+struct Builder<'a> {
+    partitions: Vec<&'a Partition>,
+    fractions: Vec<f64>,
+    variance: f64,
+    max_depth: Option<usize>,
+}
+
+impl Builder<'_> {
+    // Returns the fraction and the mean value of the given partitions.
+    fn stats(&self, items: Range<usize>) -> (f64, f64) {
+        let fraction = self.fractions[items.clone()].iter().sum::<f64>();
+        let sum = items
+            .clone()
+            .map(|i| self.fractions[i] * self.partitions[i].value)
+            .sum::<f64>();
+        let value = if items.len() == 1 {
+            self.partitions[items.start].value
+        } else if fraction > 0.0 {
+            sum / fraction
+        } else {
+            // Every partition has zero width (e.g., only a single point is covered).
+            let len = items.len() as f64;
+            items.map(|i| self.partitions[i].value).sum::<f64>() / len
+        };
+        (fraction, value)
+    }
+
+    fn build(&self, items: Range<usize>, bounds: &[Range<f64>], depth: usize) -> TreeNode {
+        let (fraction, value) = self.stats(items.clone());
+        let split = if items.len() > 1 && self.max_depth.is_none_or(|max| depth < max) {
+            self.find_split(items.clone(), bounds)
+        } else {
+            None
+        };
+        let Some(Split {
+            feature,
+            threshold,
+            left,
+            right,
+        }) = split
+        else {
+            return TreeNode::Leaf {
+                value,
+                fraction,
+                leaves: items.len(),
+            };
+        };
+
+        let (left_fraction, left_value) = self.stats(left.clone());
+        let (right_fraction, right_value) = self.stats(right.clone());
+        let gain = if self.variance > 0.0 {
+            (left_fraction * (left_value - value).powi(2)
+                + right_fraction * (right_value - value).powi(2))
+                / self.variance
+        } else {
+            0.0
+        };
+        let mut left_bounds = bounds.to_vec();
+        left_bounds[feature].end = threshold;
+        let mut right_bounds = bounds.to_vec();
+        right_bounds[feature].start = threshold;
+        TreeNode::Split {
+            feature,
+            threshold,
+            value,
+            fraction,
+            gain,
+            left: Box::new(self.build(left, &left_bounds, depth + 1)),
+            right: Box::new(self.build(right, &right_bounds, depth + 1)),
+        }
+    }
+
+    // Finds the index where the first child ends: the partitions on one side of it are below a
+    // threshold of a feature and the others are above it.
+    //
+    // Several indices may qualify (e.g., if a child splits the same feature as its parent, the
+    // leaves of its outer child are separated by the threshold of the child too), and the one
+    // explaining the most variance is taken. Since all the candidates split the same feature,
+    // this does not change the thresholds of the splits.
+    fn find_split(&self, items: Range<usize>, bounds: &[Range<f64>]) -> Option<Split> {
+        let (total_fraction, total_value) = self.stats(items.clone());
+        let total_sum = total_fraction * total_value;
+        let mut best: Option<(f64, Split)> = None;
+        for (feature, bound) in bounds.iter().enumerate() {
+            let range = |i: usize| &self.partitions[i].space.ranges()[feature];
+
+            // The bounds of the partitions after each index.
+            let mut suffix_bounds = vec![(f64::INFINITY, f64::NEG_INFINITY); items.len() + 1];
+            for (k, i) in items.clone().enumerate().rev() {
+                let (start, end) = suffix_bounds[k + 1];
+                suffix_bounds[k] = (start.min(range(i).start), end.max(range(i).end));
+            }
+            let (mut start, mut end) = (f64::INFINITY, f64::NEG_INFINITY);
+            let (mut fraction, mut sum) = (0.0, 0.0);
+            for (k, i) in items.clone().enumerate().take(items.len() - 1) {
+                start = start.min(range(i).start);
+                end = end.max(range(i).end);
+                fraction += self.fractions[i];
+                sum += self.fractions[i] * self.partitions[i].value;
+                let (suffix_start, suffix_end) = suffix_bounds[k + 1];
+                let (threshold, first_is_left) = if end <= suffix_start {
+                    (suffix_start, true)
+                } else if suffix_end <= start {
+                    (start, false)
+                } else {
+                    continue;
+                };
+                if threshold <= bound.start || bound.end <= threshold {
+                    continue;
+                }
+
+                // The variance between the children (up to the constant factor).
+                let rest_fraction = total_fraction - fraction;
+                let score = if fraction > 0.0 && rest_fraction > 0.0 {
+                    let difference = sum / fraction - (total_sum - sum) / rest_fraction;
+                    fraction * rest_fraction * difference * difference
+                } else {
+                    0.0
+                };
+                if best.as_ref().is_none_or(|b| score > b.0) {
+                    let middle = items.start + k + 1;
+                    let (left, right) = if first_is_left {
+                        (items.start..middle, middle..items.end)
+                    } else {
+                        (middle..items.end, items.start..middle)
+                    };
+                    let split = Split {
+                        feature,
+                        threshold,
+                        left,
+                        right,
+                    };
+                    best = Some((score, split));
+                }
+            }
+        }
+        best.map(|(_, split)| split)
+    }
+}
+
+struct Split {
+    feature: usize,
+    threshold: f64,
+    left: Range<usize>,
+    right: Range<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decision_tree::{DecisionTreeOptions, DecisionTreeRegressor};
+    use crate::space::FeatureSpace;
+    use crate::table::Table;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn reconstruct_works() -> anyhow::Result<()> {
+        // Returns the splits in the depth-first order.
+        fn splits(node: &TreeNode, acc: &mut Vec<(usize, f64)>) {
+            if let TreeNode::Split {
+                feature,
+                threshold,
+                left,
+                right,
+                ..
+            } = node
+            {
+                acc.push((*feature, *threshold));
+                splits(left, acc);
+                splits(right, acc);
+            }
+        }
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut columns = (0..3)
+            .map(|_| (0..200).map(|_| rng.gen()).collect::<Vec<f64>>())
+            .collect::<Vec<_>>();
+        let target = (0..200)
+            .map(|i| columns[0][i] * columns[1][i] + columns[2][i])
+            .collect::<Vec<_>>();
+        columns.push(target);
+        let table = Table::new(columns.iter().map(|c| &c[..]).collect())?;
+        let regressor = DecisionTreeRegressor::fit(&mut rng, table, DecisionTreeOptions::default());
+        let space = FeatureSpace::from_ranges(vec![0.0..1.0; 3]);
+        let partitions = TreePartitions::new(&regressor, space);
+        let (_, variance) = partitions.mean_and_variance();
+
+        let mut expected = Vec::new();
+        regressor.map(&mut |_| (), &mut |split, (), ()| {
+            expected.push((split.column, split.threshold))
+        });
+        let mut actual = Vec::new();
+        let root = reconstruct(&partitions, variance, None);
+        splits(&root, &mut actual);
+        expected.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        actual.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        assert_eq!(actual, expected);
+        for _ in 0..100 {
+            let point = [rng.gen(), rng.gen(), rng.gen()];
+            assert_eq!(root.predict(&point), regressor.predict(&point));
+        }
+        Ok(())
+    }
+}
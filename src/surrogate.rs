@@ -0,0 +1,54 @@
+//! Surrogate models fitted to the table, whose trees are decomposed by fANOVA.
+//!
+//! The importances, the marginal predictions and the other analyses only use the leaves of the
+//! trees (as partitions of the feature space) and average them over the trees, so any ensemble
+//! of trees implementing `SurrogateTree` is decomposed without changing them.
+use crate::random_forest::Ensemble;
+use crate::table::Table;
+use std::fmt;
+use std::ops::Range;
+
+/// A tree ensemble used as the surrogate model (e.g., `RandomForestOptions`).
+///
+/// This chooses how the regression trees of this crate are fitted (their number, bootstrap
+/// sampling and the options of each tree, including the random thresholds of
+/// `ExtraTreesOptions`), which are chosen by `FanovaOptions::random_forest` or
+/// `FanovaOptions::extra_trees`. The trees of other models are given to `Fanova::from_trees`.
+pub(crate) trait Surrogate: fmt::Debug + Send + Sync {
+    /// Returns the settings of fitting the trees to `table`.
+    fn ensemble(&self, table: &Table) -> Ensemble;
+}
+
+/// A fitted tree of a surrogate model, which is decomposed by fANOVA.
+///
+/// The trees of `FanovaOptions` are converted into partitions through this, and the trees of
+/// other models (e.g., the trees of another library) are decomposed the same way by
+/// `Fanova::from_trees`.
+pub trait SurrogateTree: fmt::Debug + Send + Sync {
+    /// Returns the prediction at `point` (the values of the features in the order of the
+    /// columns).
+    fn predict(&self, point: &[f64]) -> f64;
+
+    /// Returns the leaves of this tree within `space` (the range of each feature).
+    ///
+    /// The leaves must be disjoint and cover `space` (a leaf covering a non-interval region,
+    /// e.g., a subset of categories, is given as several leaves of the same value).
+    fn leaves(&self, space: &[Range<f64>]) -> Vec<SurrogateLeaf>;
+
+    /// Returns the depth of the deepest leaf (zero if the root is a leaf).
+    fn depth(&self) -> usize;
+}
+
+/// A region of the feature space in which a `SurrogateTree` predicts a constant value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurrogateLeaf {
+    /// The range of each feature covered by the leaf.
+    pub ranges: Vec<Range<f64>>,
+
+    /// The prediction of the leaf.
+    pub value: f64,
+
+    /// The (weighted) variance of the targets of the training rows of the leaf, i.e., the noise
+    /// which the tree doesn't explain (zero if unknown).
+    pub variance: f64,
+}
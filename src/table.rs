@@ -1,18 +1,381 @@
-use ordered_float::OrderedFloat;
-use rand::Rng;
+use crate::column::Column;
+use crate::functions::{self, TotalOrder};
+use crate::trace;
+use crate::transform::{FittedTransform, Transform};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom as _;
+use rand::{Rng, SeedableRng};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::num::NonZeroUsize;
 use std::ops::Range;
 use thiserror::Error;
 
+/// Type of a feature column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColumnType {
+    /// Real values.
+    #[default]
+    Continuous,
+
+    /// Integer values.
+    ///
+    /// Splits are placed at half-integers and each integer in the range of the column
+    /// is regarded as having the same weight when marginalizing.
+    Integer,
+
+    /// Boolean values (`0.0` for false and `1.0` for true).
+    ///
+    /// Splits are only placed at `0.5` and both values are regarded as having the same weight
+    /// when marginalizing (even if only one of them appears in the column).
+    Boolean,
+
+    /// Indices of categories (integers).
+    ///
+    /// These are handled like `Integer` (i.e., ordinal codes) unless
+    /// `FanovaOptions::categorical_encoding` specifies another encoding.
+    Categorical,
+}
+
+impl ColumnType {
+    /// Returns `true` if splits are placed at half-integers.
+    pub(crate) fn is_discrete(self) -> bool {
+        matches!(self, Self::Integer | Self::Boolean | Self::Categorical)
+    }
+
+    // Returns the error if `x` is not a valid value of this type (NaNs are always valid).
+    fn check(self, feature: usize, x: f64) -> Result<(), TableError> {
+        match self {
+            Self::Integer | Self::Categorical if !is_integer_or_nan(x) => {
+                Err(TableError::NonIntegerValue { feature })
+            }
+            Self::Boolean if !(x.is_nan() || x == 0.0 || x == 1.0) => {
+                Err(TableError::NonBooleanValue { feature })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Aggregation method of the target values of duplicated rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregation {
+    /// Arithmetic mean.
+    Mean,
+
+    /// Median.
+    Median,
+
+    /// Most frequent value (the lowest one if tied).
+    Mode,
+}
+
+impl Aggregation {
+    // Aggregates pairs of a target value and its weight.
+    pub(crate) fn aggregate(self, ys: Vec<(f64, f64)>) -> f64 {
+        match self {
+            Self::Mean => functions::weighted_mean(ys.into_iter()),
+            Self::Median => functions::median(ys.into_iter().map(|(y, _)| y).collect()),
+            Self::Mode => functions::most_frequent(ys.into_iter().map(|(y, _)| y)),
+        }
+    }
+}
+
+/// Handling of the rows whose target values are not finite (e.g., failed trials).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonFiniteTargets {
+    /// Fails with `TableError::NonFiniteTarget`.
+    #[default]
+    Error,
+
+    /// Drops the rows.
+    Drop,
+
+    /// Replaces the target values with the aggregation of the finite ones.
+    Impute(Aggregation),
+}
+
+/// Handling of infinite values (e.g., diverged trials reporting `inf` losses).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Infinities {
+    /// Fails with `TableError::InfiniteFeature` (or `TableError::NonFiniteTarget`).
+    Error,
+
+    /// Drops the rows.
+    Drop,
+
+    /// Replaces `+inf` (`-inf`) with the `1 - q` (`q`) quantile of the finite values of the
+    /// column (e.g., `Clamp(0.0)` clamps to the maximum and minimum finite values).
+    Clamp(f64),
+}
+
+/// Strata used by `Table::stratified_sample`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strata {
+    /// Rows having the same value of the given feature belong to the same stratum.
+    Feature(usize),
+
+    /// Rows are divided into the given number of equal-frequency bins of the target values.
+    TargetQuantiles(NonZeroUsize),
+}
+
+/// Correlation coefficients between a feature and the target.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Correlation {
+    /// Pearson correlation coefficient.
+    pub pearson: f64,
+
+    /// Spearman rank correlation coefficient.
+    pub spearman: f64,
+}
+
+/// Binning method used by `Table::histogram`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binning {
+    /// Bins of the same width.
+    EqualWidth(NonZeroUsize),
+
+    /// Bins of (nearly) the same frequency.
+    Quantile(NonZeroUsize),
+}
+
+/// Histogram of a feature column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    /// Edges of the bins (the `i`-th bin is `[edges[i], edges[i + 1])`).
+    pub edges: Vec<f64>,
+
+    /// The number of rows falling into each bin.
+    pub counts: Vec<usize>,
+}
+
+// Index of a row in the columns.
+//
+// 32 bits are far more than enough for any study, and halve the memory of the row orders
+// (which is the largest part of a presorted table) on 64-bit targets.
+pub(crate) type RowId = u32;
+
+#[cfg(test)]
+thread_local! {
+    // The number of `sorted_rows` calls which had to sort (i.e., on tables not presorted).
+    pub(crate) static UNSORTED_SORTS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// A column-oriented table of features and a target.
+///
+/// The last column is the target and the others are features.
 #[derive(Debug, Clone)]
 pub struct Table<'a> {
-    row_index: Vec<usize>,
+    row_index: Vec<RowId>,
     row_range: Range<usize>,
-    columns: Vec<&'a [f64]>,
+    columns: Vec<Column<'a>>,
+    transforms: Vec<Vec<FittedTransform>>,
+    column_types: Vec<ColumnType>,
+
+    // `presorted[i][row_range]` holds the rows sorted by the `i`-th feature (if computed).
+    presorted: Option<Vec<Vec<RowId>>>,
+
+    weights: Option<Column<'a>>,
 }
 
 impl<'a> Table<'a> {
+    /// Makes a new `Table` instance.
+    ///
+    /// The last element of `columns` is regarded as the target.
     pub fn new(columns: Vec<&'a [f64]>) -> Result<Self, TableError> {
-        if columns.is_empty() || columns[0].is_empty() {
+        Self::from_columns(
+            columns
+                .into_iter()
+                .map(|c| Column::Dense(Cow::Borrowed(c)))
+                .collect(),
+        )
+    }
+
+    /// Makes a new `Table` instance like `Table::new` but handles the rows whose target values
+    /// are not finite as specified by `policy`.
+    ///
+    /// Returns the table and the number of such rows.
+    /// If all target values are not finite, `TableError::EmptyTable` (for `Drop`) or
+    /// `TableError::NonFiniteTarget` (for `Impute`) is returned.
+    pub fn with_non_finite_targets(
+        columns: Vec<&'a [f64]>,
+        policy: NonFiniteTargets,
+    ) -> Result<(Self, usize), TableError> {
+        Self::with_invalid_values(columns, policy, None)
+    }
+
+    /// Same as `Table::with_non_finite_targets` but the infinite values of all columns
+    /// (features and the target) are handled by `infinities` beforehand.
+    ///
+    /// The number of the rows having infinite values or non-finite targets is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the quantile of `Infinities::Clamp` is not in the range `[0.0, 0.5]`.
+    pub fn with_infinities(
+        columns: Vec<&'a [f64]>,
+        infinities: Infinities,
+        non_finite_targets: NonFiniteTargets,
+    ) -> Result<(Self, usize), TableError> {
+        Self::with_invalid_values(columns, non_finite_targets, Some(infinities))
+    }
+
+    // Infinite values are handled as the other values if `infinities` is `None`.
+    pub(crate) fn with_invalid_values(
+        columns: Vec<&'a [f64]>,
+        non_finite_targets: NonFiniteTargets,
+        infinities: Option<Infinities>,
+    ) -> Result<(Self, usize), TableError> {
+        let columns = columns
+            .into_iter()
+            .map(|c| Column::Dense(Cow::Borrowed(c)))
+            .collect();
+        Self::from_columns_with(columns, non_finite_targets, infinities)
+    }
+
+    /// Same as `Table::with_invalid_values` but stores the values as `f32`.
+    pub(crate) fn with_invalid_values_f32(
+        columns: Vec<&'a [f32]>,
+        non_finite_targets: NonFiniteTargets,
+        infinities: Option<Infinities>,
+    ) -> Result<(Self, usize), TableError> {
+        let columns = columns
+            .into_iter()
+            .map(|c| Column::DenseF32(Cow::Borrowed(c)))
+            .collect();
+        Self::from_columns_with(columns, non_finite_targets, infinities)
+    }
+
+    /// Makes a new `Table` instance which stores the values as `f32`.
+    ///
+    /// The values are widened to `f64` when they are read.
+    /// This halves the memory usage compared to `Table::new` if the table owns the columns
+    /// (e.g., after rows have been appended).
+    ///
+    /// The last element of `columns` is regarded as the target.
+    pub fn new_f32(columns: Vec<&'a [f32]>) -> Result<Self, TableError> {
+        Self::from_columns(
+            columns
+                .into_iter()
+                .map(|c| Column::DenseF32(Cow::Borrowed(c)))
+                .collect(),
+        )
+    }
+
+    fn from_columns_with(
+        mut columns: Vec<Column<'a>>,
+        non_finite_targets: NonFiniteTargets,
+        infinities: Option<Infinities>,
+    ) -> Result<(Self, usize), TableError> {
+        if let Some(Infinities::Clamp(q)) = infinities {
+            assert!((0.0..=0.5).contains(&q), "quantile out of range");
+        }
+        let rows_len = columns.first().map_or(0, |c| c.len());
+        if columns.iter().any(|c| c.len() != rows_len) {
+            return Err(TableError::RowSizeMismatch);
+        }
+        let target_index = columns.len().saturating_sub(1);
+        let infinite_error = |i| {
+            if i == target_index {
+                TableError::NonFiniteTarget
+            } else {
+                TableError::InfiniteFeature { feature: i }
+            }
+        };
+
+        // The dropped rows are kept in the columns (with a placeholder target) but not in
+        // `row_index`.
+        let mut affected = vec![false; rows_len];
+        let mut dropped = vec![false; rows_len];
+        for (i, column) in columns.iter_mut().enumerate() {
+            let Some(infinities) = infinities else {
+                break;
+            };
+            if !column.iter().any(f64::is_infinite) {
+                continue;
+            }
+            for (row, x) in column.iter().enumerate() {
+                affected[row] |= x.is_infinite();
+            }
+            match infinities {
+                Infinities::Error => return Err(infinite_error(i)),
+                Infinities::Drop => {
+                    for (row, x) in column.iter().enumerate() {
+                        dropped[row] |= x.is_infinite();
+                    }
+                }
+                Infinities::Clamp(q) => {
+                    let mut finite = column.iter().filter(|x| x.is_finite()).collect::<Vec<_>>();
+                    if finite.is_empty() {
+                        return Err(infinite_error(i));
+                    }
+                    finite.sort_by(|a, b| a.total_cmp(b));
+                    let lower = functions::quantile_sorted(&finite, q);
+                    let upper = functions::quantile_sorted(&finite, 1.0 - q);
+                    column.map(|x| match x {
+                        f64::INFINITY => upper,
+                        f64::NEG_INFINITY => lower,
+                        _ => x,
+                    });
+                }
+            }
+        }
+
+        let Some(target) = columns.pop() else {
+            return Err(TableError::EmptyTable);
+        };
+        let non_finite = target
+            .iter()
+            .zip(&dropped)
+            .filter(|&(y, &dropped)| !y.is_finite() && !dropped)
+            .count();
+        let fill = match non_finite_targets {
+            _ if non_finite == 0 => 0.0,
+            NonFiniteTargets::Error => return Err(TableError::NonFiniteTarget),
+            NonFiniteTargets::Drop => 0.0,
+            NonFiniteTargets::Impute(aggregation) => {
+                let finite = target
+                    .iter()
+                    .zip(&dropped)
+                    .filter(|(y, &d)| y.is_finite() && !d);
+                let finite = finite.map(|(y, _)| (y, 1.0)).collect::<Vec<_>>();
+                if finite.is_empty() {
+                    return Err(TableError::NonFiniteTarget);
+                }
+                aggregation.aggregate(finite)
+            }
+        };
+        for (row, y) in target.iter().enumerate() {
+            if !y.is_finite() {
+                affected[row] = true;
+                dropped[row] |= non_finite_targets == NonFiniteTargets::Drop;
+            }
+        }
+        if !affected.contains(&true) {
+            columns.push(target);
+        } else {
+            let target = target.iter().map(|y| if y.is_finite() { y } else { fill });
+            columns.push(Column::Dense(Cow::Owned(target.collect())));
+        }
+        if rows_len > 0 && !dropped.contains(&false) {
+            return Err(TableError::EmptyTable);
+        }
+
+        let mut table = Self::from_columns(columns)?;
+        table.row_index.retain(|&row| !dropped[row as usize]);
+        table.row_range.end = table.row_index.len();
+        Ok((table, affected.iter().filter(|&&a| a).count()))
+    }
+
+    fn from_columns(columns: Vec<Column<'a>>) -> Result<Self, TableError> {
+        let _span = trace::span!(
+            "table",
+            "columns={} rows={}",
+            columns.len(),
+            columns.first().map_or(0, |c| c.len())
+        );
+        if columns.is_empty() || columns[0].len() == 0 {
             return Err(TableError::EmptyTable);
         }
 
@@ -20,78 +383,944 @@ impl<'a> Table<'a> {
         if columns.iter().skip(1).any(|c| c.len() != rows_len) {
             return Err(TableError::RowSizeMismatch);
         }
+        if rows_len > RowId::MAX as usize {
+            return Err(TableError::TooManyRows);
+        }
 
         if columns[columns.len() - 1].iter().any(|t| !t.is_finite()) {
             return Err(TableError::NonFiniteTarget);
         }
 
         Ok(Self {
-            row_index: (0..rows_len).collect(),
+            row_index: (0..rows_len as RowId).collect(),
             row_range: Range {
                 start: 0,
                 end: rows_len,
             },
+            transforms: vec![Vec::new(); columns.len() - 1],
+            column_types: vec![ColumnType::default(); columns.len() - 1],
+            presorted: None,
+            weights: None,
             columns,
         })
     }
 
-    pub fn target(&self) -> impl '_ + Iterator<Item = f64> + Clone {
+    /// Makes a new `Table` instance from a row-major matrix which has `columns_len` columns.
+    ///
+    /// The `target_column`-th column is regarded as the target and the others are features
+    /// (in the original order).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `target_column` is out of range.
+    pub fn from_matrix(
+        matrix: &[f64],
+        columns_len: usize,
+        target_column: usize,
+    ) -> Result<Table<'static>, TableError> {
+        assert!(target_column < columns_len, "target column out of range");
+        if matrix.is_empty() {
+            return Err(TableError::EmptyTable);
+        }
+        if !matrix.len().is_multiple_of(columns_len) {
+            return Err(TableError::RowSizeMismatch);
+        }
+
+        let columns = (0..columns_len)
+            .filter(|&i| i != target_column)
+            .chain(std::iter::once(target_column))
+            .map(|i| {
+                matrix
+                    .iter()
+                    .skip(i)
+                    .step_by(columns_len)
+                    .copied()
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let table = Table::new(columns.iter().map(|c| &c[..]).collect())?;
+        Ok(table.into_owned())
+    }
+
+    /// Makes a new `Table` instance from a row-major feature matrix and a target column.
+    ///
+    /// `rows` holds `features_len` values per row.
+    /// Unlike `Table::from_matrix`, the matrix isn't transposed (i.e., the values are read from
+    /// `rows` directly) unless the columns are modified by `push_row` or `transform`.
+    pub fn from_rows(
+        rows: &'a [f64],
+        features_len: usize,
+        target: &'a [f64],
+    ) -> Result<Self, TableError> {
+        if rows.len() != features_len * target.len() {
+            return Err(TableError::RowSizeMismatch);
+        }
+
+        let columns = (0..features_len)
+            .map(|offset| Column::Strided {
+                data: rows,
+                offset,
+                stride: features_len,
+            })
+            .chain(std::iter::once(Column::Dense(Cow::Borrowed(target))))
+            .collect();
+        Self::from_columns(columns)
+    }
+
+    /// Converts this table into the one which owns all of the columns.
+    pub fn into_owned(self) -> Table<'static> {
+        Table {
+            row_index: self.row_index,
+            row_range: self.row_range,
+            columns: self.columns.into_iter().map(Column::into_owned).collect(),
+            transforms: self.transforms,
+            column_types: self.column_types,
+            presorted: self.presorted,
+            weights: self.weights.map(Column::into_owned),
+        }
+    }
+
+    pub(crate) fn target(&self) -> impl '_ + Iterator<Item = f64> + Clone {
         self.column(self.columns.len() - 1)
     }
 
-    pub fn column(&self, column_index: usize) -> impl '_ + Iterator<Item = f64> + Clone {
-        self.rows().map(move |i| self.columns[column_index][i])
+    pub(crate) fn map_target(&mut self, f: impl Fn(f64) -> f64) {
+        self.columns.last_mut().expect("never fails").map(f);
+    }
+
+    /// Returns pairs of the target value and the weight of each row.
+    ///
+    /// If the table doesn't have weights, the weight of every row is `1.0`.
+    pub(crate) fn weighted_target(&self) -> impl '_ + Iterator<Item = (f64, f64)> + Clone {
+        self.weighted_target_of(&self.row_index[self.row_range.clone()])
+    }
+
+    /// Same as `weighted_target` but for the given rows (e.g., `sorted_rows`).
+    pub(crate) fn weighted_target_of<'b>(
+        &'b self,
+        rows: &'b [RowId],
+    ) -> impl 'b + Iterator<Item = (f64, f64)> + Clone {
+        let target = &self.columns[self.columns.len() - 1];
+        rows.iter().map(move |&i| {
+            let i = i as usize;
+            let weight = self.weights.as_ref().map_or(1.0, |w| w.get(i));
+            (target.get(i), weight)
+        })
+    }
+
+    /// Sets the weights of the rows.
+    ///
+    /// Rows appended by `push_row` after this call have the weight `1.0`.
+    pub fn set_weights(&mut self, weights: &'a [f64]) -> Result<(), TableError> {
+        if weights.len() != self.columns[0].len() {
+            return Err(TableError::RowSizeMismatch);
+        }
+        if weights.iter().any(|w| !(w.is_finite() && *w >= 0.0)) {
+            return Err(TableError::InvalidWeight);
+        }
+        self.weights = Some(Column::Dense(Cow::Borrowed(weights)));
+        Ok(())
+    }
+
+    /// Replaces the target column.
+    ///
+    /// The feature columns (and their sorted orders, if computed) are kept, so this is cheaper
+    /// than making a new table to evaluate another target on the same features.
+    pub fn set_target(&mut self, target: &'a [f64]) -> Result<(), TableError> {
+        if target.len() != self.columns[0].len() {
+            return Err(TableError::RowSizeMismatch);
+        }
+        if target.iter().any(|t| !t.is_finite()) {
+            return Err(TableError::NonFiniteTarget);
+        }
+        let i = self.columns.len() - 1;
+        self.columns[i] = Column::Dense(Cow::Borrowed(target));
+        Ok(())
+    }
+
+    /// Randomly permutes the target values among the rows (e.g., to break the relationship
+    /// between the features and the target).
+    pub(crate) fn shuffle_target<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        let rows = &self.row_index[self.row_range.clone()];
+        let target = self.columns.last().expect("never fails");
+        let mut values = rows
+            .iter()
+            .map(|&i| target.get(i as usize))
+            .collect::<Vec<_>>();
+        values.shuffle(rng);
+        let mut shuffled = (0..target.len()).map(|i| target.get(i)).collect::<Vec<_>>();
+        for (&i, x) in rows.iter().zip(values) {
+            shuffled[i as usize] = x;
+        }
+        *self.columns.last_mut().expect("never fails") = Column::Dense(Cow::Owned(shuffled));
+    }
+
+    /// Returns `true` if this table has the weights of the rows.
+    pub fn has_weights(&self) -> bool {
+        self.weights.is_some()
+    }
+
+    pub(crate) fn column(&self, column_index: usize) -> impl '_ + Iterator<Item = f64> + Clone {
+        self.rows().map(move |i| self.columns[column_index].get(i))
+    }
+
+    /// Returns the (approximate) number of bytes used by this table.
+    pub(crate) fn heap_size(&self) -> usize {
+        let index_size = std::mem::size_of::<RowId>();
+        let columns = self.columns.iter().chain(self.weights.iter());
+        columns.map(|c| c.heap_size()).sum::<usize>()
+            + self.row_index.len() * index_size
+            + self
+                .presorted
+                .iter()
+                .flatten()
+                .map(|rows| rows.len() * index_size)
+                .sum::<usize>()
+    }
+
+    /// Returns `true` if the given column has at most one distinct value (NaNs are ignored).
+    pub(crate) fn is_constant_column(&self, column_index: usize) -> bool {
+        let mut values = self.column(column_index).filter(|x| !x.is_nan());
+        values.next().is_none_or(|x| values.all(|y| y == x))
     }
 
+    /// Returns the number of feature columns.
     pub fn features_len(&self) -> usize {
         self.columns.len() - 1
     }
 
+    /// Returns the number of rows.
     pub fn rows_len(&self) -> usize {
         self.row_range.end - self.row_range.start
     }
 
-    fn rows(&self) -> impl '_ + Iterator<Item = usize> + Clone {
-        self.row_index[self.row_range.start..self.row_range.end]
+    /// Returns a view of this table which only contains the given features (and the target).
+    ///
+    /// The `i`-th feature of the resulting table is the `features[i]`-th feature of this table.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of `features` is out of range.
+    pub fn select_columns(&self, features: &[usize]) -> Table<'_> {
+        assert!(
+            features.iter().all(|&i| i < self.features_len()),
+            "feature index out of range"
+        );
+        let columns = features
             .iter()
             .copied()
+            .chain(std::iter::once(self.columns.len() - 1))
+            .map(|i| self.columns[i].borrow())
+            .collect();
+        let transforms = features
+            .iter()
+            .map(|&i| self.transforms[i].clone())
+            .collect();
+        let column_types = features.iter().map(|&i| self.column_types[i]).collect();
+        let presorted = self
+            .presorted
+            .as_ref()
+            .map(|p| features.iter().map(|&i| p[i].clone()).collect());
+        Table {
+            row_index: self.row_index.clone(),
+            row_range: self.row_range.clone(),
+            columns,
+            transforms,
+            column_types,
+            presorted,
+            weights: self.weights.as_ref().map(Column::borrow),
+        }
+    }
+
+    /// Returns the error which `push_row` would return for the row without appending it (except
+    /// `TableError::TooManyRows`).
+    ///
+    /// This allows a batch of rows to be validated before any of them is appended.
+    pub fn check_row(&self, features: &[f64], target: f64) -> Result<(), TableError> {
+        self.transform_row(features, target).map(|_| ())
+    }
+
+    // Returns the features of a row to be appended in the transformed space.
+    fn transform_row(&self, features: &[f64], target: f64) -> Result<Vec<f64>, TableError> {
+        if features.len() != self.features_len() {
+            return Err(TableError::RowSizeMismatch);
+        }
+        if !target.is_finite() {
+            return Err(TableError::NonFiniteTarget);
+        }
+
+        let features = features
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| {
+                self.transforms[i].iter().try_fold(value, |x, t| {
+                    if t.contains(x) {
+                        Ok(t.apply(x))
+                    } else {
+                        Err(TableError::TransformDomain { feature: i })
+                    }
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for (i, &x) in features.iter().enumerate() {
+            self.column_types[i].check(i, x)?;
+        }
+        Ok(features)
+    }
+
+    /// Appends a row to the end of this table.
+    ///
+    /// Columns borrowed from the caller are copied on the first append.
+    /// The table is left unchanged if the row is rejected (e.g., a value is out of the domain of
+    /// a transform applied to its column).
+    pub fn push_row(&mut self, features: &[f64], target: f64) -> Result<(), TableError> {
+        let features = self.transform_row(features, target)?;
+        let row = self.columns[0].len();
+        if row >= RowId::MAX as usize {
+            return Err(TableError::TooManyRows);
+        }
+        for (i, &value) in features.iter().enumerate() {
+            self.columns[i].push(value);
+        }
+        self.columns[features.len()].push(target);
+        if let Some(weights) = &mut self.weights {
+            weights.push(1.0);
+        }
+
+        debug_assert_eq!(self.row_range.end, self.row_index.len());
+        self.row_index.push(row as RowId);
+        self.row_range.end += 1;
+
+        if let Some(presorted) = &mut self.presorted {
+            for (i, rows) in presorted.iter_mut().enumerate() {
+                let column = &self.columns[i];
+                let value = column.get(row);
+                let position = rows.partition_point(|&r| {
+                    functions::total_cmp(column.get(r as usize), value) != Ordering::Greater
+                });
+                rows.insert(position, row as RowId);
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes a new table in which rows having the identical feature values are merged into one.
+    ///
+    /// The target value of a merged row is calculated by `aggregation`, and its weight is the
+    /// sum of the weights of the merged rows (i.e., their number if this table has no weights), so
+    /// that it counts as many rows in the trees as before. If this table has weights,
+    /// `Aggregation::Mean` calculates the weighted mean.
+    /// The order of rows (the first occurrences) is preserved.
+    pub fn aggregate_duplicates(&self, aggregation: Aggregation) -> Table<'static> {
+        let mut groups = HashMap::<Vec<u64>, usize>::new();
+        let mut rows = Vec::<(usize, Vec<(f64, f64)>)>::new();
+        for (row, target) in self.rows().zip(self.weighted_target()) {
+            let group = *groups.entry(self.row_key(row)).or_insert_with(|| {
+                rows.push((row, Vec::new()));
+                rows.len() - 1
+            });
+            rows[group].1.push(target);
+        }
+
+        let mut columns = (0..self.features_len())
+            .map(|i| {
+                let values = rows.iter().map(|(row, _)| self.columns[i].get(*row));
+                Column::Dense(Cow::Owned(values.collect()))
+            })
+            .collect::<Vec<_>>();
+        let weights = rows
+            .iter()
+            .map(|(_, ys)| ys.iter().map(|(_, w)| w).sum::<f64>())
+            .collect::<Vec<_>>();
+        let target = rows.into_iter().map(|(_, ys)| aggregation.aggregate(ys));
+        columns.push(Column::Dense(Cow::Owned(target.collect())));
+
+        let mut table = Table::from_columns(columns).expect("never fails");
+        table.transforms = self.transforms.clone();
+        table.column_types = self.column_types.clone();
+        table.weights = Some(Column::Dense(Cow::Owned(weights)));
+        table
+    }
+
+    // Returns the number of rows having the identical feature values to earlier rows (i.e., the
+    // number of rows removed by `aggregate_duplicates`).
+    pub(crate) fn duplicated_rows_len(&self) -> usize {
+        let mut keys = HashSet::new();
+        self.rows()
+            .filter(|&row| !keys.insert(self.row_key(row)))
+            .count()
     }
 
-    pub fn sort_rows_by_column(&mut self, column: usize) {
-        let columns = &self.columns;
-        (&mut self.row_index[self.row_range.start..self.row_range.end])
-            .sort_by_key(|&x| OrderedFloat(columns[column][x]))
+    // The feature values of a row, where all NaNs are regarded as identical.
+    fn row_key(&self, row: usize) -> Vec<u64> {
+        (0..self.features_len())
+            .map(|i| {
+                let x = self.columns[i].get(row);
+                if x.is_nan() {
+                    f64::NAN.to_bits()
+                } else {
+                    x.to_bits()
+                }
+            })
+            .collect()
     }
 
-    pub fn bootstrap_sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Self {
+    /// Calculates the correlation coefficients between each feature and the target.
+    ///
+    /// This is useful for sanity-checking fANOVA results against simple (rank) correlations.
+    /// Rows whose feature values are NaN are ignored.
+    /// The coefficients of constant features are NaN.
+    pub fn correlations(&self) -> Vec<Correlation> {
+        (0..self.features_len())
+            .map(|i| {
+                let xys = self
+                    .column(i)
+                    .zip(self.target())
+                    .filter(|(x, _)| !x.is_nan());
+                Correlation {
+                    pearson: functions::pearson(xys.clone()),
+                    spearman: functions::spearman(xys),
+                }
+            })
+            .collect()
+    }
+
+    /// Makes the histogram of the given feature column (NaNs are ignored).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `feature` is out of range.
+    pub fn histogram(&self, feature: usize, binning: Binning) -> Histogram {
+        assert!(feature < self.features_len(), "feature index out of range");
+        let mut values = self
+            .column(feature)
+            .filter(|x| !x.is_nan())
+            .collect::<Vec<_>>();
+        if values.is_empty() {
+            return Histogram {
+                edges: Vec::new(),
+                counts: Vec::new(),
+            };
+        }
+        values.sort_by(|a, b| a.total_cmp(b));
+
+        let edges = match binning {
+            Binning::EqualWidth(bins) => {
+                functions::equal_width_edges(values[0], values[values.len() - 1], bins.get())
+            }
+            Binning::Quantile(bins) => functions::quantile_edges(&values, bins.get()),
+        };
+        let counts = functions::bin_counts(values.into_iter(), &edges);
+        Histogram { edges, counts }
+    }
+
+    /// Counts the occurrences of each distinct value of the given feature (in ascending order).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `feature` is out of range.
+    pub fn category_counts(&self, feature: usize) -> Vec<(f64, usize)> {
+        assert!(feature < self.features_len(), "feature index out of range");
+        functions::category_counts(self.column(feature))
+    }
+
+    /// Applies `transform` to the given feature column.
+    ///
+    /// The parameters of the transform (e.g., the minimum and maximum values of `MinMax`)
+    /// are estimated from the rows of this table and kept in the table, so that rows appended
+    /// later are transformed in the same way.
+    /// If this table is a view, the rows hidden by the view are not used for the estimation.
+    /// They are rewritten too (as they share the column storage), but never observed through this
+    /// table.
+    /// Transforms applied to the same column are composed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `feature` is out of range.
+    pub fn transform(&mut self, feature: usize, transform: Transform) -> Result<(), TableError> {
+        assert!(feature < self.features_len(), "feature index out of range");
+        match self.column_types[feature] {
+            ColumnType::Integer | ColumnType::Categorical => {
+                return Err(TableError::NonIntegerValue { feature })
+            }
+            ColumnType::Boolean => return Err(TableError::NonBooleanValue { feature }),
+            ColumnType::Continuous => {}
+        }
+
+        let fitted = FittedTransform::fit(transform, self.column(feature))
+            .ok_or(TableError::TransformDomain { feature })?;
+        self.columns[feature].map(|x| fitted.apply(x));
+        self.transforms[feature].push(fitted);
+        Ok(())
+    }
+
+    /// Converts the given feature column into a sparse encoding.
+    ///
+    /// In the encoding, only the values different from `default` occupy memory.
+    /// This is useful for parameters which are inactive or constant in most rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `feature` is out of range.
+    pub fn sparsify(&mut self, feature: usize, default: f64) {
+        assert!(feature < self.features_len(), "feature index out of range");
+        self.columns[feature] = self.columns[feature].to_sparse(default);
+    }
+
+    /// Sets the type of the given feature column.
+    ///
+    /// The default type is `ColumnType::Continuous`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `feature` is out of range.
+    pub fn set_column_type(
+        &mut self,
+        feature: usize,
+        column_type: ColumnType,
+    ) -> Result<(), TableError> {
+        assert!(feature < self.features_len(), "feature index out of range");
+        for x in self.column(feature) {
+            column_type.check(feature, x)?;
+        }
+        self.column_types[feature] = column_type;
+        Ok(())
+    }
+
+    /// Returns the type of the given feature column.
+    pub fn column_type(&self, feature: usize) -> ColumnType {
+        self.column_types[feature]
+    }
+
+    /// Converts a value in the transformed space of the given feature into the original space.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `feature` is out of range.
+    pub fn inverse_transform(&self, feature: usize, value: f64) -> f64 {
+        self.transforms[feature]
+            .iter()
+            .rev()
+            .fold(value, |y, t| t.invert(y))
+    }
+
+    // Converts a value in the original space of the given feature into the transformed space.
+    pub(crate) fn apply_transforms(&self, feature: usize, value: f64) -> f64 {
+        self.transforms[feature]
+            .iter()
+            .fold(value, |x, t| t.apply(x))
+    }
+
+    pub(crate) fn rows(&self) -> impl '_ + Iterator<Item = usize> + Clone {
+        self.row_index[self.row_range.start..self.row_range.end]
+            .iter()
+            .map(|&i| i as usize)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn sort_rows_by_column(&mut self, column: usize) {
+        let range = self.row_range.clone();
+        if let Some(presorted) = &self.presorted {
+            self.row_index[range.clone()].copy_from_slice(&presorted[column][range]);
+        } else {
+            let columns = &self.columns;
+            let column = &columns[column];
+            self.row_index[range].sort_by(|&a, &b| {
+                functions::total_cmp(column.get(a as usize), column.get(b as usize))
+            })
+        }
+    }
+
+    /// Returns the rows sorted by `column` without modifying this table.
+    ///
+    /// This doesn't allocate if the table has been presorted.
+    pub(crate) fn sorted_rows(&self, column: usize) -> Cow<'_, [RowId]> {
+        let range = self.row_range.clone();
+        if let Some(presorted) = &self.presorted {
+            Cow::Borrowed(&presorted[column][range])
+        } else {
+            #[cfg(test)]
+            UNSORTED_SORTS.with(|n| n.set(n.get() + 1));
+            let mut rows = self.row_index[range].to_vec();
+            let column = &self.columns[column];
+            rows.sort_by(|&a, &b| {
+                functions::total_cmp(column.get(a as usize), column.get(b as usize))
+            });
+            Cow::Owned(rows)
+        }
+    }
+
+    /// Sorts the rows of every feature once, so that `sorted_rows` doesn't need to sort.
+    ///
+    /// The sorted orders are inherited by `bootstrap_sample` and maintained by `split_rows`.
+    pub(crate) fn presort(&mut self) {
+        if self.presorted.is_some() {
+            return;
+        }
+        let _span = trace::span!("presort", "rows={}", self.rows_len());
+        let presorted = (0..self.features_len())
+            .map(|i| {
+                let column = &self.columns[i];
+                let mut rows = self.row_index[self.row_range.clone()].to_vec();
+                rows.sort_by(|&a, &b| {
+                    functions::total_cmp(column.get(a as usize), column.get(b as usize))
+                });
+                rows
+            })
+            .collect();
+        self.row_index = self.row_index[self.row_range.clone()].to_vec();
+        self.row_range = Range {
+            start: 0,
+            end: self.row_index.len(),
+        };
+        self.presorted = Some(presorted);
+    }
+
+    /// Moves the rows whose values of `column` satisfy `lower` (e.g., those less than or equal to
+    /// a threshold) before the others and returns the number of such rows.
+    ///
+    /// The rows of each side are sorted by `column` if this table has been presorted, and are
+    /// stably partitioned otherwise.
+    pub(crate) fn split_rows(&mut self, column: usize, lower: impl Fn(f64) -> bool) -> usize {
+        let split_column = &self.columns[column];
+        let is_lower = |row: RowId| lower(split_column.get(row as usize));
+        let range = self.row_range.clone();
+        let Some(presorted) = &mut self.presorted else {
+            let rows = &mut self.row_index[range];
+            let (lower, upper): (Vec<_>, Vec<_>) = rows.iter().partition(|&&row| is_lower(row));
+            rows[..lower.len()].copy_from_slice(&lower);
+            rows[lower.len()..].copy_from_slice(&upper);
+            return lower.len();
+        };
+
+        let mut split_row = 0;
+        let mut upper = Vec::new();
+        for (j, rows) in presorted.iter_mut().enumerate() {
+            let rows = &mut rows[range.clone()];
+            let mut lower = 0;
+            for i in 0..rows.len() {
+                let row = rows[i];
+                if is_lower(row) {
+                    rows[lower] = row;
+                    lower += 1;
+                } else {
+                    upper.push(row);
+                }
+            }
+            rows[lower..].copy_from_slice(&upper);
+            upper.clear();
+            if j == column {
+                split_row = lower;
+            }
+        }
+        self.row_index[range.clone()].copy_from_slice(&presorted[column][range]);
+        split_row
+    }
+
+    pub(crate) fn bootstrap_sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Table<'_> {
         let row_index = (0..self.rows_len())
-            .map(|_| self.row_index[rng.gen_range(self.row_range.start..self.row_range.end)])
+            .map(|_| self.row_index[self.row_range.start + random_index(rng, self.rows_len())])
             .collect::<Vec<_>>();
+
+        let presorted = self.presorted.as_ref().map(|presorted| {
+            let mut counts = vec![0; self.columns[0].len()];
+            for &row in &row_index {
+                counts[row as usize] += 1;
+            }
+            presorted
+                .iter()
+                .map(|rows| {
+                    rows[self.row_range.clone()]
+                        .iter()
+                        .flat_map(|&row| std::iter::repeat_n(row, counts[row as usize]))
+                        .collect()
+                })
+                .collect()
+        });
+
+        let mut table = self.view(row_index);
+        table.presorted = presorted;
+        table
+    }
+
+    /// Returns the positions of the rows of this table which are not in `sample` (a bootstrap
+    /// sample of this table).
+    pub(crate) fn out_of_bag_rows(&self, sample: &Table) -> Vec<usize> {
+        let mut in_bag = vec![false; self.columns[0].len()];
+        for row in sample.rows() {
+            in_bag[row] = true;
+        }
+        self.rows()
+            .enumerate()
+            .filter(|&(_, row)| !in_bag[row])
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Returns the feature values of the row at the given position.
+    pub(crate) fn row_features(&self, position: usize) -> Vec<f64> {
+        let row = self.row_index[self.row_range.start + position] as usize;
+        let features = &self.columns[..self.columns.len() - 1];
+        features.iter().map(|c| c.get(row)).collect()
+    }
+
+    /// Returns a view of this table which only contains the given rows.
+    ///
+    /// The `i`-th row of the resulting table is the `rows[i]`-th row of this table.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of `rows` is out of range.
+    pub fn select_rows(&self, rows: &[usize]) -> Table<'_> {
+        let row_index = self.row_index[self.row_range.clone()].to_vec();
+        self.view(rows.iter().map(|&i| row_index[i]).collect())
+    }
+
+    // Keeps only the rows at the given positions (in the order of `positions`).
+    pub(crate) fn retain_rows(&mut self, positions: &[usize]) {
+        let row_index = &self.row_index[self.row_range.clone()];
+        self.row_index = positions.iter().map(|&i| row_index[i]).collect();
+        self.row_range = 0..self.row_index.len();
+        self.presorted = None;
+    }
+
+    // Returns the positions of the rows whose features are within the given ranges (`None` for
+    // any values), where both ends of the ranges are included.
+    pub(crate) fn rows_within(&self, domain: &[Option<Vec<Range<f64>>>]) -> Vec<usize> {
+        (0..self.rows_len())
+            .filter(|&i| {
+                let row = self.row_index[self.row_range.start + i] as usize;
+                domain.iter().enumerate().all(|(j, ranges)| {
+                    let x = self.columns[j].get(row);
+                    ranges
+                        .as_ref()
+                        .is_none_or(|ranges| ranges.iter().any(|r| r.start <= x && x <= r.end))
+                })
+            })
+            .collect()
+    }
+
+    /// Samples `size` rows so that each stratum is represented in proportion to its size.
+    ///
+    /// The returned indices are sorted and can be passed to `Table::select_rows`.
+    /// If `replacement` is `false`, the sample size of each stratum is capped by its row count.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `strata` refers to a feature that is out of range.
+    pub fn stratified_sample(
+        &self,
+        strata: Strata,
+        size: usize,
+        replacement: bool,
+        seed: u64,
+    ) -> Vec<usize> {
+        let groups = match strata {
+            Strata::Feature(feature) => {
+                assert!(feature < self.features_len(), "feature index out of range");
+                let mut groups = BTreeMap::<_, Vec<_>>::new();
+                for (i, x) in self.column(feature).enumerate() {
+                    groups.entry(TotalOrder(x)).or_default().push(i);
+                }
+                groups.into_values().collect::<Vec<_>>()
+            }
+            Strata::TargetQuantiles(bins) => {
+                let mut rows = (0..self.rows_len()).collect::<Vec<_>>();
+                let target = self.target().collect::<Vec<_>>();
+                rows.sort_by(|&a, &b| functions::total_cmp(target[a], target[b]));
+                let mut groups = vec![Vec::new(); bins.get()];
+                for (rank, i) in rows.into_iter().enumerate() {
+                    groups[rank * bins.get() / self.rows_len()].push(i);
+                }
+                groups.retain(|g| !g.is_empty());
+                groups
+            }
+        };
+
+        // Largest remainder method.
+        let quotas = groups
+            .iter()
+            .map(|g| (size * g.len()) as f64 / self.rows_len() as f64)
+            .collect::<Vec<_>>();
+        let mut sizes = quotas
+            .iter()
+            .map(|q| q.floor() as usize)
+            .collect::<Vec<_>>();
+        let mut order = (0..groups.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| functions::total_cmp(quotas[b].fract(), quotas[a].fract()));
+        let remainder = size - sizes.iter().sum::<usize>();
+        for &i in order.iter().take(remainder) {
+            sizes[i] += 1;
+        }
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut sample = Vec::with_capacity(size);
+        for (group, size) in groups.iter().zip(sizes) {
+            if replacement {
+                sample.extend((0..size).map(|_| group[random_index(&mut rng, group.len())]));
+            } else {
+                sample.extend(group.choose_multiple(&mut rng, size).copied());
+            }
+        }
+        sample.sort_unstable();
+        sample
+    }
+
+    /// Randomly splits the rows of this table into two disjoint views.
+    ///
+    /// The first table has `round(rows_len * fraction)` rows and the second one has the rest.
+    /// An error is returned if either of them would be empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fraction` is not in the range `[0.0, 1.0]`.
+    pub fn split(&self, fraction: f64, seed: u64) -> Result<(Table<'_>, Table<'_>), TableError> {
+        assert!((0.0..=1.0).contains(&fraction), "fraction out of range");
+
+        let mut rows = self.row_index[self.row_range.clone()].to_vec();
+        let first_len = (rows.len() as f64 * fraction).round() as usize;
+        if first_len == 0 || first_len == rows.len() {
+            return Err(TableError::EmptyTable);
+        }
+
+        rows.shuffle(&mut StdRng::seed_from_u64(seed));
+        let second = rows.split_off(first_len);
+        Ok((self.view(rows), self.view(second)))
+    }
+
+    /// Randomly assigns the rows of this table to `folds` disjoint folds of (almost) equal sizes
+    /// and returns the pairs of the training view (the rows of the other folds) and the test
+    /// view (the rows of the fold), e.g., for k-fold cross-validation.
+    ///
+    /// An error is returned if there are fewer rows than folds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `folds` is less than `2`.
+    pub fn folds(
+        &self,
+        folds: usize,
+        seed: u64,
+    ) -> Result<Vec<(Table<'_>, Table<'_>)>, TableError> {
+        assert!(folds >= 2, "too few folds");
+
+        let mut rows = self.row_index[self.row_range.clone()].to_vec();
+        if rows.len() < folds {
+            return Err(TableError::EmptyTable);
+        }
+
+        rows.shuffle(&mut StdRng::seed_from_u64(seed));
+        Ok((0..folds)
+            .map(|k| {
+                let mut train = Vec::with_capacity(rows.len());
+                let mut test = Vec::with_capacity(rows.len() / folds + 1);
+                for (i, &row) in rows.iter().enumerate() {
+                    if i % folds == k {
+                        test.push(row);
+                    } else {
+                        train.push(row);
+                    }
+                }
+                (self.view(train), self.view(test))
+            })
+            .collect())
+    }
+
+    /// Copies the given rows (indices of the underlying columns, e.g., `rows()`) into a new
+    /// table whose row weights are multiplied by `scales`.
+    pub(crate) fn copy_rows(&self, rows: &[usize], scales: &[f64]) -> Table<'static> {
+        debug_assert_eq!(rows.len(), scales.len());
+        let copy = |column: &Column| {
+            Column::Dense(Cow::Owned(rows.iter().map(|&i| column.get(i)).collect()))
+        };
+        let weights = rows
+            .iter()
+            .zip(scales)
+            .map(|(&i, scale)| self.weights.as_ref().map_or(1.0, |w| w.get(i)) * scale)
+            .collect();
+        Table {
+            row_index: (0..rows.len() as RowId).collect(),
+            row_range: Range {
+                start: 0,
+                end: rows.len(),
+            },
+            columns: self.columns.iter().map(copy).collect(),
+            transforms: self.transforms.clone(),
+            column_types: self.column_types.clone(),
+            presorted: None,
+            weights: Some(Column::Dense(Cow::Owned(weights))),
+        }
+    }
+
+    // Makes a new table of the rows of this table whose feature columns are replaced by the given
+    // ones (e.g., for `CategoricalEncoding::OneHot`). The transforms are not kept.
+    pub(crate) fn with_features(&self, features: Vec<(Vec<f64>, ColumnType)>) -> Table<'static> {
+        debug_assert!(features.iter().all(|(c, _)| c.len() == self.rows_len()));
+        let rows = self.rows().collect::<Vec<_>>();
+        let copy = |column: &Column| {
+            Column::Dense(Cow::Owned(rows.iter().map(|&i| column.get(i)).collect()))
+        };
+        let (mut columns, column_types): (Vec<_>, Vec<_>) = features
+            .into_iter()
+            .map(|(c, t)| (Column::Dense(Cow::Owned(c)), t))
+            .unzip();
+        columns.push(copy(&self.columns[self.columns.len() - 1]));
+        Table {
+            row_index: (0..rows.len() as RowId).collect(),
+            row_range: Range {
+                start: 0,
+                end: rows.len(),
+            },
+            transforms: vec![Vec::new(); column_types.len()],
+            columns,
+            column_types,
+            presorted: None,
+            weights: self.weights.as_ref().map(copy),
+        }
+    }
+
+    fn view(&self, row_index: Vec<RowId>) -> Table<'_> {
         let row_range = Range {
             start: 0,
-            end: self.rows_len(),
+            end: row_index.len(),
         };
-        Self {
+        Table {
             row_index,
             row_range,
-            columns: self.columns.clone(),
+            columns: self.columns.iter().map(Column::borrow).collect(),
+            transforms: self.transforms.clone(),
+            column_types: self.column_types.clone(),
+            presorted: None,
+            weights: self.weights.as_ref().map(Column::borrow),
         }
     }
 
-    pub fn thresholds(&self, column: usize) -> impl '_ + Iterator<Item = (usize, f64)> {
-        // Assumption: `self.columns[column]` has been sorted.
-        let column = self.columns[column];
-        self.rows()
-            .map(move |i| column[i])
+    /// Returns the split candidates of `column` as pairs of the number of rows on the lower
+    /// side and the threshold.
+    ///
+    /// `rows` must be sorted by `column` (e.g., `sorted_rows`).
+    pub(crate) fn thresholds<'b>(
+        &'b self,
+        column: usize,
+        rows: &'b [RowId],
+    ) -> impl 'b + Iterator<Item = (usize, f64)> {
+        let integer = self.column_types[column].is_discrete();
+        let column = &self.columns[column];
+        rows.iter()
+            .map(move |&i| column.get(i as usize))
             .enumerate()
-            .scan(None, |prev, (i, x)| {
+            .scan(None, move |prev, (i, x)| {
                 if prev.is_none() {
                     *prev = Some(x);
                     Some(None)
                 } else if *prev != Some(x) {
                     let y = prev.expect("never fails");
                     *prev = Some(x);
-                    Some(Some((i, (x + y) / 2.0)))
+                    if integer {
+                        Some(Some((i, y + 0.5)))
+                    } else {
+                        Some(Some((i, (x + y) / 2.0)))
+                    }
                 } else {
                     Some(None)
                 }
@@ -99,7 +1328,7 @@ impl<'a> Table<'a> {
             .flatten()
     }
 
-    pub fn with_split<F, T>(&mut self, row: usize, mut f: F) -> (T, T)
+    pub(crate) fn with_split<F, T>(&mut self, row: usize, mut f: F) -> (T, T)
     where
         F: FnMut(&mut Self) -> T,
     {
@@ -118,14 +1347,792 @@ impl<'a> Table<'a> {
     }
 }
 
+/// Builder of `Table` which validates the given columns.
+///
+/// In addition to the checks done by `Table::new`, this rejects features which can't be used
+/// to calculate importance (i.e., all-NaN or constant columns).
+#[derive(Debug, Default, Clone)]
+pub struct TableBuilder<'a> {
+    features: Vec<(&'a [f64], ColumnType)>,
+    target: Option<&'a [f64]>,
+}
+
+impl<'a> TableBuilder<'a> {
+    /// Makes a new `TableBuilder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a continuous feature column.
+    pub fn feature(self, column: &'a [f64]) -> Self {
+        self.typed_feature(column, ColumnType::Continuous)
+    }
+
+    /// Adds a feature column of the given type.
+    pub fn typed_feature(mut self, column: &'a [f64], column_type: ColumnType) -> Self {
+        self.features.push((column, column_type));
+        self
+    }
+
+    /// Sets the target column.
+    pub fn target(mut self, column: &'a [f64]) -> Self {
+        self.target = Some(column);
+        self
+    }
+
+    /// Builds a `Table` instance.
+    pub fn build(self) -> Result<Table<'a>, TableError> {
+        let target = self.target.ok_or(TableError::MissingTarget)?;
+        if target.is_empty() {
+            return Err(TableError::EmptyTable);
+        }
+
+        for (i, (column, _)) in self.features.iter().enumerate() {
+            if column.len() != target.len() {
+                return Err(TableError::ColumnLengthMismatch {
+                    feature: i,
+                    expected: target.len(),
+                    actual: column.len(),
+                });
+            }
+            let mut values = column.iter().filter(|x| !x.is_nan());
+            match values.next() {
+                None => return Err(TableError::AllNanColumn { feature: i }),
+                Some(x) if values.all(|y| y == x) => {
+                    return Err(TableError::ConstantColumn { feature: i });
+                }
+                Some(_) => {}
+            }
+        }
+
+        let mut table = Table::new(
+            self.features
+                .iter()
+                .map(|(c, _)| *c)
+                .chain(std::iter::once(target))
+                .collect(),
+        )?;
+        for (i, (_, column_type)) in self.features.into_iter().enumerate() {
+            table.set_column_type(i, column_type)?;
+        }
+        Ok(table)
+    }
+}
+
+/// Possible errors which could be returned by `Table` constructors and methods.
+#[non_exhaustive]
 #[derive(Debug, Error, Clone)]
 pub enum TableError {
+    /// Table must have at least one column and one row.
     #[error("table must have at least one column and one row")]
     EmptyTable,
 
+    /// Some of columns have a different row count from others.
     #[error("some of columns have a different row count from others")]
     RowSizeMismatch,
 
+    /// Target column contains non finite numbers.
     #[error("target column contains non finite numbers")]
     NonFiniteTarget,
+
+    /// Weights must be non-negative finite numbers.
+    #[error("weights must be non-negative finite numbers")]
+    InvalidWeight,
+
+    /// Target column is not specified.
+    #[error("target column is not specified")]
+    MissingTarget,
+
+    /// Feature column has a different row count from the target column.
+    #[error("feature {feature} has {actual} rows but the target has {expected} rows")]
+    ColumnLengthMismatch {
+        /// Feature index.
+        feature: usize,
+
+        /// Row count of the target column.
+        expected: usize,
+
+        /// Row count of the feature column.
+        actual: usize,
+    },
+
+    /// Feature column only contains NaNs.
+    #[error("feature {feature} only contains NaNs")]
+    AllNanColumn {
+        /// Feature index.
+        feature: usize,
+    },
+
+    /// Feature column only contains a single value.
+    #[error("feature {feature} is constant")]
+    ConstantColumn {
+        /// Feature index.
+        feature: usize,
+    },
+
+    /// Integer feature column contains non integer values.
+    #[error("feature {feature} is an integer column but contains non integer values")]
+    NonIntegerValue {
+        /// Feature index.
+        feature: usize,
+    },
+
+    /// Boolean feature column contains values other than `0.0` and `1.0`.
+    #[error("feature {feature} is a boolean column but contains values other than 0 and 1")]
+    NonBooleanValue {
+        /// Feature index.
+        feature: usize,
+    },
+
+    /// Feature column contains values out of the domain of the requested transform.
+    #[error("feature {feature} contains values out of the domain of the transform")]
+    TransformDomain {
+        /// Feature index.
+        feature: usize,
+    },
+
+    /// Feature column contains infinite values.
+    #[error("feature {feature} contains infinite values")]
+    InfiniteFeature {
+        /// Feature index.
+        feature: usize,
+    },
+
+    /// Table has more than `u32::MAX` rows.
+    #[error("table must have at most {} rows", u32::MAX)]
+    TooManyRows,
+}
+
+fn is_integer_or_nan(x: f64) -> bool {
+    x.is_nan() || x.fract() == 0.0
+}
+
+// Returns a random index in `0..len`.
+//
+// The index is sampled as `u32` since `rng.gen_range` over `usize` consumes different random
+// values on 32-bit (wasm) and 64-bit targets.
+fn random_index<R: Rng + ?Sized>(rng: &mut R, len: usize) -> usize {
+    let len = u32::try_from(len).expect("too many rows");
+    rng.gen_range(0..len) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::space::FeatureSpace;
+
+    #[test]
+    fn select_columns_works() -> anyhow::Result<()> {
+        let columns = [
+            &[0.0, 1.0, 2.0][..],
+            &[3.0, 4.0, 5.0][..],
+            &[6.0, 7.0, 8.0][..],
+            &[9.0, 10.0, 11.0][..],
+        ];
+        let table = Table::new(columns.to_vec())?;
+
+        let selected = table.select_columns(&[2, 0]);
+        assert_eq!(selected.features_len(), 2);
+        assert_eq!(selected.rows_len(), 3);
+        assert_eq!(selected.column(0).collect::<Vec<_>>(), [6.0, 7.0, 8.0]);
+        assert_eq!(selected.column(1).collect::<Vec<_>>(), [0.0, 1.0, 2.0]);
+        assert_eq!(selected.target().collect::<Vec<_>>(), [9.0, 10.0, 11.0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_matrix_works() -> anyhow::Result<()> {
+        let matrix = [
+            0.0, 10.0, 100.0, //
+            1.0, 11.0, 101.0, //
+        ];
+        let table = Table::from_matrix(&matrix, 3, 1)?;
+        assert_eq!(table.features_len(), 2);
+        assert_eq!(table.column(0).collect::<Vec<_>>(), [0.0, 1.0]);
+        assert_eq!(table.column(1).collect::<Vec<_>>(), [100.0, 101.0]);
+        assert_eq!(table.target().collect::<Vec<_>>(), [10.0, 11.0]);
+
+        assert!(Table::from_matrix(&matrix, 4, 0).is_err());
+        assert!(Table::from_matrix(&[], 3, 0).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn split_works() -> anyhow::Result<()> {
+        let columns = [
+            &[0.0, 1.0, 2.0, 3.0, 4.0][..],
+            &[5.0, 6.0, 7.0, 8.0, 9.0][..],
+        ];
+        let table = Table::new(columns.to_vec())?;
+
+        let (train, valid) = table.split(0.6, 0)?;
+        assert_eq!(train.rows_len(), 3);
+        assert_eq!(valid.rows_len(), 2);
+
+        let mut rows = train.column(0).chain(valid.column(0)).collect::<Vec<_>>();
+        rows.sort_by(f64::total_cmp);
+        assert_eq!(rows, [0.0, 1.0, 2.0, 3.0, 4.0]);
+
+        let (again, _) = table.split(0.6, 0)?;
+        assert_eq!(
+            train.column(0).collect::<Vec<_>>(),
+            again.column(0).collect::<Vec<_>>()
+        );
+
+        assert!(table.split(0.0, 0).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn folds_works() -> anyhow::Result<()> {
+        let xs = (0..10).map(f64::from).collect::<Vec<_>>();
+        let table = Table::new(vec![&xs, &xs])?;
+
+        let folds = table.folds(3, 0)?;
+        assert_eq!(folds.len(), 3);
+        let mut tests = Vec::new();
+        for (train, test) in &folds {
+            assert!((3..=4).contains(&test.rows_len()));
+            assert_eq!(train.rows_len() + test.rows_len(), 10);
+            let train_rows = train.column(0).collect::<Vec<_>>();
+            assert!(test.column(0).all(|x| !train_rows.contains(&x)));
+            tests.extend(test.column(0));
+        }
+        tests.sort_by(f64::total_cmp);
+        assert_eq!(tests, xs);
+
+        let again = table.folds(3, 0)?;
+        assert_eq!(
+            again[0].1.column(0).collect::<Vec<_>>(),
+            folds[0].1.column(0).collect::<Vec<_>>()
+        );
+        assert!(table.folds(11, 0).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn transform_works() -> anyhow::Result<()> {
+        let columns = [&[1.0, 10.0, 100.0][..], &[0.0, 1.0, 2.0][..]];
+        let mut table = Table::new(columns.to_vec())?;
+
+        table.transform(0, Transform::Log)?;
+        table.transform(0, Transform::MinMax)?;
+        let values = table.column(0).collect::<Vec<_>>();
+        assert_eq!(values[0], 0.0);
+        assert!((values[1] - 0.5).abs() < 1e-12);
+        assert_eq!(values[2], 1.0);
+        assert!((table.inverse_transform(0, 0.5) - 10.0).abs() < 1e-9);
+
+        table.push_row(&[1000.0], 3.0)?;
+        assert!((table.column(0).last().unwrap() - 1.5).abs() < 1e-12);
+
+        let columns = [&[-1.0, 1.0][..], &[0.0, 1.0][..]];
+        let mut table = Table::new(columns.to_vec())?;
+        assert!(table.transform(0, Transform::Log).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn integer_column_works() -> anyhow::Result<()> {
+        let columns = [&[3.0, 1.0, 2.0, 5.0][..], &[0.0, 1.0, 2.0, 3.0][..]];
+        let mut table = Table::new(columns.to_vec())?;
+        table.set_column_type(0, ColumnType::Integer)?;
+
+        let rows = table.sorted_rows(0);
+        assert_eq!(*rows, [1, 2, 0, 3]);
+        assert_eq!(
+            table.thresholds(0, &rows).collect::<Vec<_>>(),
+            [(1, 1.5), (2, 2.5), (3, 3.5)]
+        );
+        drop(rows);
+
+        assert!(table.push_row(&[1.5], 4.0).is_err());
+        assert!(table.transform(0, Transform::MinMax).is_err());
+
+        let columns = [&[0.5, 1.0][..], &[0.0, 1.0][..]];
+        let mut table = Table::new(columns.to_vec())?;
+        assert!(table.set_column_type(0, ColumnType::Integer).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn boolean_column_works() -> anyhow::Result<()> {
+        let columns = [&[1.0, 0.0, f64::NAN, 1.0][..], &[0.0, 1.0, 2.0, 3.0][..]];
+        let mut table = Table::new(columns.to_vec())?;
+        table.set_column_type(0, ColumnType::Boolean)?;
+
+        let rows = table.sorted_rows(0);
+        assert_eq!(
+            table.thresholds(0, &rows[..3]).collect::<Vec<_>>(),
+            [(1, 0.5)]
+        );
+        drop(rows);
+
+        let space = FeatureSpace::from_table(&table);
+        assert_eq!(space.ranges()[0], -0.5..1.5);
+
+        assert!(matches!(
+            table.push_row(&[2.0], 4.0),
+            Err(TableError::NonBooleanValue { feature: 0 })
+        ));
+        assert!(table.transform(0, Transform::MinMax).is_err());
+
+        let columns = [&[2.0, 1.0][..], &[0.0, 1.0][..]];
+        let mut table = Table::new(columns.to_vec())?;
+        assert!(table.set_column_type(0, ColumnType::Boolean).is_err());
+
+        // Both values have the same weight even if only one of them appears.
+        let columns = [&[1.0, 1.0][..], &[0.0, 1.0][..]];
+        let mut table = Table::new(columns.to_vec())?;
+        table.set_column_type(0, ColumnType::Boolean)?;
+        assert_eq!(FeatureSpace::from_table(&table).ranges()[0], -0.5..1.5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sparsify_works() -> anyhow::Result<()> {
+        let columns = [&[0.0, 0.0, 2.0, 0.0][..], &[0.0, 1.0, 2.0, 3.0][..]];
+        let mut table = Table::new(columns.to_vec())?;
+        table.sparsify(0, 0.0);
+        assert_eq!(table.column(0).collect::<Vec<_>>(), [0.0, 0.0, 2.0, 0.0]);
+
+        table.push_row(&[4.0], 4.0)?;
+        table.push_row(&[0.0], 5.0)?;
+        table.transform(0, Transform::MinMax)?;
+        assert_eq!(
+            table.column(0).collect::<Vec<_>>(),
+            [0.0, 0.0, 0.5, 0.0, 1.0, 0.0]
+        );
+
+        table.sort_rows_by_column(0);
+        assert_eq!(
+            table.target().collect::<Vec<_>>(),
+            [0.0, 1.0, 3.0, 5.0, 2.0, 4.0]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn table_builder_works() -> anyhow::Result<()> {
+        let table = TableBuilder::new()
+            .feature(&[0.0, 1.0, 2.0])
+            .typed_feature(&[1.0, 2.0, 1.0], ColumnType::Integer)
+            .target(&[3.0, 4.0, 5.0])
+            .build()?;
+        assert_eq!(table.features_len(), 2);
+        assert_eq!(table.column_type(1), ColumnType::Integer);
+
+        assert!(matches!(
+            TableBuilder::new().feature(&[0.0, 1.0]).build(),
+            Err(TableError::MissingTarget)
+        ));
+        assert!(matches!(
+            TableBuilder::new()
+                .feature(&[0.0, 1.0])
+                .feature(&[0.0])
+                .target(&[0.0, 1.0])
+                .build(),
+            Err(TableError::ColumnLengthMismatch {
+                feature: 1,
+                expected: 2,
+                actual: 1
+            })
+        ));
+        assert!(matches!(
+            TableBuilder::new()
+                .feature(&[f64::NAN, f64::NAN])
+                .target(&[0.0, 1.0])
+                .build(),
+            Err(TableError::AllNanColumn { feature: 0 })
+        ));
+        assert!(matches!(
+            TableBuilder::new()
+                .feature(&[0.0, 1.0])
+                .feature(&[3.0, f64::NAN])
+                .target(&[0.0, 1.0])
+                .build(),
+            Err(TableError::ConstantColumn { feature: 1 })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn presort_works() -> anyhow::Result<()> {
+        let columns = [
+            &[3.0, 1.0, 2.0, 0.0][..],
+            &[0.0, 2.0, 1.0, 3.0][..],
+            &[0.0, 1.0, 2.0, 3.0][..],
+        ];
+        let mut table = Table::new(columns.to_vec())?;
+        table.presort();
+        table.push_row(&[1.5, 1.5], 4.0)?;
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut sample = table.bootstrap_sample(&mut rng);
+        let mut expected = sample.clone();
+        expected.presorted = None;
+
+        for column in 0..2 {
+            sample.sort_rows_by_column(column);
+            expected.sort_rows_by_column(column);
+            assert_eq!(
+                sample.column(column).collect::<Vec<_>>(),
+                expected.column(column).collect::<Vec<_>>()
+            );
+        }
+
+        let split_row = sample.split_rows(0, |x| x <= 1.7);
+        assert_eq!(split_row, expected.split_rows(0, |x| x <= 1.7));
+        sample.with_split(split_row, |sample| {
+            sample.sort_rows_by_column(1);
+            assert!(sample
+                .column(1)
+                .zip(sample.column(1).skip(1))
+                .all(|(a, b)| a <= b));
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn new_f32_works() -> anyhow::Result<()> {
+        let columns = [&[0.5f32, 1.5][..], &[2.0f32, 3.0][..]];
+        let mut table = Table::new_f32(columns.to_vec())?;
+        table.push_row(&[2.5], 4.0)?;
+        assert_eq!(table.column(0).collect::<Vec<_>>(), [0.5, 1.5, 2.5]);
+        assert_eq!(table.target().collect::<Vec<_>>(), [2.0, 3.0, 4.0]);
+
+        assert!(Table::new_f32(vec![&[0.0], &[f32::INFINITY]]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn non_finite_targets_work() -> anyhow::Result<()> {
+        let feature = [0.0, 1.0, 2.0, 3.0];
+        let target = [1.0, f64::NAN, 3.0, f64::NEG_INFINITY];
+        let columns = vec![&feature[..], &target[..]];
+
+        assert!(matches!(
+            Table::with_non_finite_targets(columns.clone(), NonFiniteTargets::Error),
+            Err(TableError::NonFiniteTarget)
+        ));
+
+        let (mut table, dropped) =
+            Table::with_non_finite_targets(columns.clone(), NonFiniteTargets::Drop)?;
+        assert_eq!(dropped, 2);
+        assert_eq!(table.column(0).collect::<Vec<_>>(), [0.0, 2.0]);
+        assert_eq!(table.target().collect::<Vec<_>>(), [1.0, 3.0]);
+        table.presort();
+        assert_eq!(table.sorted_rows(0).len(), 2);
+
+        let policy = NonFiniteTargets::Impute(Aggregation::Mean);
+        let (table, imputed) = Table::with_non_finite_targets(columns, policy)?;
+        assert_eq!(imputed, 2);
+        assert_eq!(table.target().collect::<Vec<_>>(), [1.0, 2.0, 3.0, 2.0]);
+
+        let nans = [f64::NAN; 4];
+        assert!(matches!(
+            Table::with_non_finite_targets(vec![&feature, &nans], NonFiniteTargets::Drop),
+            Err(TableError::EmptyTable)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn infinities_work() -> anyhow::Result<()> {
+        let feature = [0.0, f64::INFINITY, 2.0, 3.0, f64::NEG_INFINITY];
+        let target = [1.0, 2.0, f64::INFINITY, 4.0, 5.0];
+        let columns = vec![&feature[..], &target[..]];
+        let nft = NonFiniteTargets::Error;
+
+        assert!(matches!(
+            Table::with_infinities(columns.clone(), Infinities::Error, nft),
+            Err(TableError::InfiniteFeature { feature: 0 })
+        ));
+
+        let (table, dropped) = Table::with_infinities(columns.clone(), Infinities::Drop, nft)?;
+        assert_eq!(dropped, 3);
+        assert_eq!(table.column(0).collect::<Vec<_>>(), [0.0, 3.0]);
+        assert_eq!(table.target().collect::<Vec<_>>(), [1.0, 4.0]);
+
+        let (table, clamped) = Table::with_infinities(columns, Infinities::Clamp(0.0), nft)?;
+        assert_eq!(clamped, 3);
+        assert_eq!(
+            table.column(0).collect::<Vec<_>>(),
+            [0.0, 3.0, 2.0, 3.0, 0.0]
+        );
+        assert_eq!(
+            table.target().collect::<Vec<_>>(),
+            [1.0, 2.0, 5.0, 4.0, 5.0]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn aggregate_duplicates_works() -> anyhow::Result<()> {
+        let columns = [
+            &[0.0, 1.0, 0.0, 0.0, f64::NAN, f64::NAN][..],
+            &[2.0, 3.0, 2.0, 2.0, 1.0, 1.0][..],
+            &[1.0, 2.0, 3.0, 8.0, 4.0, 5.0][..],
+        ];
+        let table = Table::new(columns.to_vec())?;
+        assert_eq!(table.duplicated_rows_len(), 3);
+
+        let mean = table.aggregate_duplicates(Aggregation::Mean);
+        assert_eq!(mean.rows_len(), 3);
+        assert_eq!(mean.column(1).collect::<Vec<_>>(), [2.0, 3.0, 1.0]);
+        assert_eq!(
+            mean.weighted_target().collect::<Vec<_>>(),
+            [(4.0, 3.0), (2.0, 1.0), (4.5, 2.0)]
+        );
+
+        let median = table.aggregate_duplicates(Aggregation::Median);
+        assert_eq!(median.target().collect::<Vec<_>>(), [3.0, 2.0, 4.5]);
+
+        let mode = table.aggregate_duplicates(Aggregation::Mode);
+        assert_eq!(mode.target().collect::<Vec<_>>(), [1.0, 2.0, 4.0]);
+
+        let mut table = table;
+        table.set_weights(&[1.0, 1.0, 1.0, 2.0, 1.0, 0.0])?;
+        let weighted = table.aggregate_duplicates(Aggregation::Mean);
+        assert_eq!(
+            weighted.weighted_target().collect::<Vec<_>>(),
+            [(5.0, 4.0), (2.0, 1.0), (4.0, 1.0)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_rows_works() -> anyhow::Result<()> {
+        let rows = [
+            0.0, 10.0, //
+            1.0, 11.0, //
+            2.0, 12.0, //
+        ];
+        let mut table = Table::from_rows(&rows, 2, &[5.0, 6.0, 7.0])?;
+        assert_eq!(table.features_len(), 2);
+        assert_eq!(table.column(1).collect::<Vec<_>>(), [10.0, 11.0, 12.0]);
+
+        table.push_row(&[3.0, 13.0], 8.0)?;
+        assert_eq!(table.column(0).collect::<Vec<_>>(), [0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(
+            table.column(1).collect::<Vec<_>>(),
+            [10.0, 11.0, 12.0, 13.0]
+        );
+
+        assert!(Table::from_rows(&rows, 2, &[5.0, 6.0]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn stratified_sample_works() -> anyhow::Result<()> {
+        let columns = [
+            &[0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0][..],
+            &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0][..],
+        ];
+        let table = Table::new(columns.to_vec())?;
+
+        let sample = table.stratified_sample(Strata::Feature(0), 4, false, 0);
+        assert_eq!(sample.len(), 4);
+        assert_eq!(sample.iter().filter(|&&i| i >= 6).count(), 1);
+        assert_eq!(
+            sample,
+            table.stratified_sample(Strata::Feature(0), 4, false, 0)
+        );
+
+        let bins = NonZeroUsize::new(2).expect("never fails");
+        let sample = table.stratified_sample(Strata::TargetQuantiles(bins), 4, false, 0);
+        assert_eq!(sample.iter().filter(|&&i| i < 4).count(), 2);
+
+        let sample = table.stratified_sample(Strata::Feature(0), 16, true, 0);
+        assert_eq!(sample.len(), 16);
+        assert_eq!(sample.iter().filter(|&&i| i >= 6).count(), 4);
+
+        let rows = table.select_rows(&sample[..3]);
+        assert_eq!(rows.rows_len(), 3);
+        assert_eq!(
+            rows.target().collect::<Vec<_>>(),
+            sample[..3].iter().map(|&i| i as f64).collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn correlations_works() -> anyhow::Result<()> {
+        let columns = [
+            &[1.0, 2.0, 3.0, f64::NAN][..],
+            &[3.0, 2.0, 1.0, 0.0][..],
+            &[1.0, 8.0, 27.0, 64.0][..],
+        ];
+        let table = Table::new(columns.to_vec())?;
+        let correlations = table.correlations();
+        assert_eq!(correlations[0].spearman, 1.0);
+        assert!(correlations[0].pearson < 1.0);
+        assert_eq!(correlations[1].spearman, -1.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn set_target_works() -> anyhow::Result<()> {
+        let columns = [&[2.0, 0.0, 1.0][..], &[3.0, 4.0, 5.0][..]];
+        let mut table = Table::new(columns.to_vec())?;
+        table.presort();
+
+        table.set_target(&[6.0, 7.0, 8.0])?;
+        assert_eq!(table.target().collect::<Vec<_>>(), [6.0, 7.0, 8.0]);
+        table.sort_rows_by_column(0);
+        assert_eq!(table.target().collect::<Vec<_>>(), [7.0, 8.0, 6.0]);
+
+        assert!(matches!(
+            table.set_target(&[1.0]),
+            Err(TableError::RowSizeMismatch)
+        ));
+        assert!(matches!(
+            table.set_target(&[1.0, f64::NAN, 2.0]),
+            Err(TableError::NonFiniteTarget)
+        ));
+
+        // Only the target is shuffled.
+        let features = table.column(0).collect::<Vec<_>>();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut shuffled = table.clone();
+        shuffled.shuffle_target(&mut rng);
+        assert_eq!(shuffled.column(0).collect::<Vec<_>>(), features);
+        let mut target = shuffled.target().collect::<Vec<_>>();
+        target.sort_by(f64::total_cmp);
+        assert_eq!(target, [6.0, 7.0, 8.0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn heap_size_works() -> anyhow::Result<()> {
+        let columns = [&[0.0, 1.0, 2.0][..], &[3.0, 4.0, 5.0][..]];
+        let mut table = Table::new(columns.to_vec())?;
+        assert_eq!(table.heap_size(), 6 * 8 + 3 * 4);
+
+        table.presort();
+        assert_eq!(table.heap_size(), 6 * 8 + 3 * 4 + 3 * 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn histogram_works() -> anyhow::Result<()> {
+        let columns = [&[0.0, 1.0, 1.0, 4.0][..], &[0.0, 1.0, 2.0, 3.0][..]];
+        let table = Table::new(columns.to_vec())?;
+
+        let bins = NonZeroUsize::new(2).expect("never fails");
+        let histogram = table.histogram(0, Binning::EqualWidth(bins));
+        assert_eq!(histogram.edges, [0.0, 2.0, 4.0]);
+        assert_eq!(histogram.counts, [3, 1]);
+
+        let histogram = table.histogram(0, Binning::Quantile(bins));
+        assert_eq!(histogram.edges, [0.0, 1.0, 4.0]);
+        assert_eq!(histogram.counts, [1, 3]);
+
+        assert_eq!(table.category_counts(0), [(0.0, 1), (1.0, 2), (4.0, 1)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn nan_rows_are_sorted_last() -> anyhow::Result<()> {
+        let feature = [2.0, f64::NAN, -0.0, -f64::NAN, 1.0, 0.0];
+        let target = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+        let mut table = Table::new(vec![&feature, &target])?;
+        let mut presorted = table.clone();
+        presorted.presort();
+        presorted.push_row(&[-f64::NAN], 6.0)?;
+        presorted.push_row(&[0.5], 7.0)?;
+
+        let sorted = |t: &Table| {
+            t.sorted_rows(0)
+                .iter()
+                .map(|&r| r as usize)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sorted(&table), [2, 5, 4, 0, 1, 3]);
+        assert_eq!(sorted(&presorted), [2, 5, 7, 4, 0, 1, 3, 6]);
+
+        // The NaN rows go to the upper side whether the table is presorted or not.
+        assert_eq!(table.split_rows(0, |x| x <= 1.5), 3);
+        assert_eq!(table.target().take(3).collect::<Vec<_>>(), [2.0, 4.0, 5.0]);
+        assert_eq!(presorted.split_rows(0, |x| x <= 1.5), 4);
+        assert_eq!(
+            presorted.target().take(4).collect::<Vec<_>>(),
+            [2.0, 5.0, 7.0, 4.0]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn push_row_works() -> anyhow::Result<()> {
+        let columns = [&[0.0, 1.0][..], &[2.0, 3.0][..]];
+        let mut table = Table::new(columns.to_vec())?;
+
+        table.push_row(&[4.0], 5.0)?;
+        assert_eq!(table.rows_len(), 3);
+        assert_eq!(table.column(0).collect::<Vec<_>>(), [0.0, 1.0, 4.0]);
+        assert_eq!(table.target().collect::<Vec<_>>(), [2.0, 3.0, 5.0]);
+
+        assert!(table.push_row(&[4.0, 5.0], 6.0).is_err());
+        assert!(table.push_row(&[4.0], f64::NAN).is_err());
+        assert_eq!(table.rows_len(), 3);
+
+        let columns = [&[1.0, 2.0][..], &[2.0, 3.0][..]];
+        let mut table = Table::new(columns.to_vec())?;
+        table.transform(0, Transform::Log)?;
+        assert!(matches!(
+            table.push_row(&[0.0], 4.0),
+            Err(TableError::TransformDomain { feature: 0 })
+        ));
+        assert_eq!(table.rows_len(), 2);
+        assert_eq!(table.columns[0].len(), 2);
+        assert_eq!(table.target().collect::<Vec<_>>(), [2.0, 3.0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_row_works() -> anyhow::Result<()> {
+        let columns = [&[1.0, 2.0][..], &[0.0, 1.0][..], &[2.0, 3.0][..]];
+        let mut table = Table::new(columns.to_vec())?;
+        table.transform(0, Transform::Log)?;
+        table.set_column_type(1, ColumnType::Integer)?;
+
+        table.check_row(&[3.0, 2.0], 4.0)?;
+        assert!(matches!(
+            table.check_row(&[0.0, 2.0], 4.0),
+            Err(TableError::TransformDomain { feature: 0 })
+        ));
+        assert!(matches!(
+            table.check_row(&[3.0, 0.5], 4.0),
+            Err(TableError::NonIntegerValue { feature: 1 })
+        ));
+        assert!(matches!(
+            table.check_row(&[3.0], 4.0),
+            Err(TableError::RowSizeMismatch)
+        ));
+        assert!(matches!(
+            table.check_row(&[3.0, 2.0], f64::NAN),
+            Err(TableError::NonFiniteTarget)
+        ));
+        assert_eq!(table.rows_len(), 2);
+        Ok(())
+    }
 }
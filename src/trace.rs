@@ -0,0 +1,148 @@
+//! Timing spans of the major computation steps (enabled by the `tracing` feature).
+//!
+//! The spans are `tracing` spans at the `INFO` level, and each of them emits an event with its
+//! elapsed time (`elapsed_ms`) when closed. They are reported by the subscriber installed by the
+//! application (e.g., `tracing-subscriber`), or by `tracing-wasm` to the browser console and the
+//! performance timeline after `init_tracing` of the bindings is called.
+
+/// Guard of a span entered by `span!`, which is exited when dropped.
+#[derive(Debug)]
+#[must_use]
+pub struct Span {
+    #[cfg(feature = "tracing")]
+    inner: Option<(tracing::span::EnteredSpan, f64)>,
+}
+
+impl Span {
+    #[cfg(feature = "tracing")]
+    pub fn enter(span: tracing::Span) -> Self {
+        let start = now_millis();
+        Self {
+            inner: Some((span.entered(), start)),
+        }
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    pub const fn disabled() -> Self {
+        Self {}
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for Span {
+    fn drop(&mut self) {
+        let Some((span, start)) = self.inner.take() else {
+            return;
+        };
+        if let Some(metadata) = span.metadata() {
+            let elapsed_ms = now_millis() - start;
+            tracing::info!(elapsed_ms, "{} finished", metadata.name());
+        }
+    }
+}
+
+/// Enters a span named `$name` (a string literal) until the returned `Span` is dropped.
+///
+/// The rest of the arguments (as `format!`) make the `detail` field of the span (e.g., the size
+/// of the input), which is evaluated only if the `tracing` feature is enabled and the span is
+/// recorded by the subscriber.
+macro_rules! span {
+    ($name:literal) => {{
+        #[cfg(feature = "tracing")]
+        let span = $crate::trace::Span::enter(tracing::info_span!($name));
+        #[cfg(not(feature = "tracing"))]
+        let span = $crate::trace::Span::disabled();
+        span
+    }};
+    ($name:literal, $($detail:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        let span = $crate::trace::Span::enter(tracing::info_span!(
+            $name,
+            detail = %format_args!($($detail)+)
+        ));
+        #[cfg(not(feature = "tracing"))]
+        let span = {
+            let _ = || format!($($detail)+);
+            $crate::trace::Span::disabled()
+        };
+        span
+    }};
+}
+pub(crate) use span;
+
+/// Returns the current time in milliseconds (`Date.now()` in wasm builds).
+pub(crate) fn now_millis() -> f64 {
+    #[cfg(all(feature = "wasm", target_arch = "wasm32", not(target_os = "wasi")))]
+    {
+        js_sys::Date::now()
+    }
+    #[cfg(not(all(feature = "wasm", target_arch = "wasm32", not(target_os = "wasi"))))]
+    {
+        use std::sync::OnceLock;
+        use std::time::Instant;
+
+        static EPOCH: OnceLock<Instant> = OnceLock::new();
+        EPOCH.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn span_works() {
+        use std::io::Write;
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Clone, Default)]
+        struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for Buffer {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().expect("not poisoned").write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .without_time()
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            let _outer = span!("outer", "rows={}", 1);
+            let _inner = span!("inner");
+        });
+
+        let output =
+            String::from_utf8(buffer.0.lock().expect("not poisoned").clone()).expect("valid UTF-8");
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2, "{output}");
+        assert!(
+            lines[0].contains("outer{detail=rows=1}:inner: "),
+            "{output}"
+        );
+        assert!(lines[0].contains("inner finished elapsed_ms="), "{output}");
+        assert!(lines[1].contains("outer{detail=rows=1}: "), "{output}");
+        assert!(lines[1].contains("outer finished elapsed_ms="), "{output}");
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    #[test]
+    fn span_works() {
+        let mut evaluated = false;
+        {
+            let _outer = span!("outer", "rows={}", {
+                evaluated = true;
+                1
+            });
+            let _inner = span!("inner");
+        }
+        assert!(!evaluated);
+    }
+}
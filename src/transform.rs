@@ -0,0 +1,83 @@
+use crate::functions;
+
+/// Transform applied to a feature column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    /// Scales values into the range `[0.0, 1.0]`.
+    MinMax,
+
+    /// Standardizes values so that they have zero mean and unit variance.
+    ZScore,
+
+    /// Takes the natural logarithm of values.
+    Log,
+}
+
+/// A transform together with the parameters estimated from a column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FittedTransform {
+    MinMax { min: f64, max: f64 },
+    ZScore { mean: f64, stddev: f64 },
+    Log,
+}
+
+impl FittedTransform {
+    /// Estimates the parameters of `transform` from `xs` (NaNs are ignored).
+    ///
+    /// Returns `None` if `xs` is out of the domain of the transform.
+    pub fn fit(transform: Transform, xs: impl Iterator<Item = f64> + Clone) -> Option<Self> {
+        let xs = xs.filter(|x| !x.is_nan());
+        match transform {
+            Transform::MinMax => {
+                let min = xs.clone().fold(f64::INFINITY, f64::min);
+                let max = xs.fold(f64::NEG_INFINITY, f64::max);
+                Some(Self::MinMax { min, max })
+            }
+            Transform::ZScore => {
+                if xs.clone().next().is_none() {
+                    return Some(Self::ZScore {
+                        mean: 0.0,
+                        stddev: 0.0,
+                    });
+                }
+                let (mean, stddev) = functions::mean_and_stddev(xs);
+                Some(Self::ZScore { mean, stddev })
+            }
+            Transform::Log => {
+                if xs.clone().any(|x| x <= 0.0) {
+                    None
+                } else {
+                    Some(Self::Log)
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if `x` is in the domain of this transform (NaNs are always accepted).
+    pub fn contains(self, x: f64) -> bool {
+        match self {
+            Self::MinMax { .. } | Self::ZScore { .. } => true,
+            Self::Log => x.is_nan() || x > 0.0,
+        }
+    }
+
+    pub fn apply(self, x: f64) -> f64 {
+        match self {
+            Self::MinMax { min, max } if max > min => (x - min) / (max - min),
+            Self::MinMax { min, .. } => x - min,
+            Self::ZScore { mean, stddev } if stddev > 0.0 => (x - mean) / stddev,
+            Self::ZScore { mean, .. } => x - mean,
+            Self::Log => x.ln(),
+        }
+    }
+
+    pub fn invert(self, y: f64) -> f64 {
+        match self {
+            Self::MinMax { min, max } if max > min => y * (max - min) + min,
+            Self::MinMax { min, .. } => y + min,
+            Self::ZScore { mean, stddev } if stddev > 0.0 => y * stddev + mean,
+            Self::ZScore { mean, .. } => y + mean,
+            Self::Log => y.exp(),
+        }
+    }
+}
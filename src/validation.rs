@@ -0,0 +1,345 @@
+//! Checks of the input data which are reported before fitting.
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A problem found in the input data by `validate`.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Issue {
+    /// A feature has a different number of rows from the target.
+    LengthMismatch {
+        /// Feature index.
+        feature: usize,
+
+        /// Number of the rows of the feature.
+        len: usize,
+
+        /// Number of the rows of the target.
+        expected: usize,
+    },
+
+    /// All the values of a feature are NaN, so the feature is never used for splits.
+    AllNan {
+        /// Feature index.
+        feature: usize,
+    },
+
+    /// A feature has only one distinct (non-NaN) value, so its importance is always zero.
+    ConstantFeature {
+        /// Feature index.
+        feature: usize,
+    },
+
+    /// A feature has the same values as an earlier feature in all rows, so only the earlier one
+    /// is used for splits and the importance of this feature is always zero.
+    DuplicatedFeature {
+        /// Feature index.
+        feature: usize,
+
+        /// Index of the earlier feature.
+        original: usize,
+    },
+
+    /// The targets of some rows are NaN or infinite.
+    NonFiniteTargets {
+        /// Indices of the rows.
+        rows: Vec<usize>,
+    },
+
+    /// Some rows have the same feature values as an earlier row (e.g., repeated trials).
+    DuplicatedRows {
+        /// Indices of the rows (excluding the first occurrences).
+        rows: Vec<usize>,
+    },
+}
+
+impl Issue {
+    /// Returns `true` if fitting fails because of this issue.
+    ///
+    /// The other issues are warnings: the model can be fitted, but the importances may be
+    /// misleading.
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            Self::LengthMismatch { .. } | Self::NonFiniteTargets { .. }
+        )
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::LengthMismatch {
+                feature,
+                len,
+                expected,
+            } => write!(
+                f,
+                "feature {feature} has {len} rows while the target has {expected} rows"
+            ),
+            Self::AllNan { feature } => write!(f, "all values of feature {feature} are NaN"),
+            Self::ConstantFeature { feature } => write!(f, "feature {feature} is constant"),
+            Self::DuplicatedFeature { feature, original } => {
+                write!(f, "feature {feature} duplicates feature {original}")
+            }
+            Self::NonFiniteTargets { rows } => {
+                write!(f, "{} rows have non-finite targets", rows.len())
+            }
+            Self::DuplicatedRows { rows } => {
+                write!(
+                    f,
+                    "{} rows duplicate the features of earlier rows",
+                    rows.len()
+                )
+            }
+        }
+    }
+}
+
+/// Problems found in the input data by `validate`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationReport {
+    issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    /// Returns the issues in the order of the checks.
+    pub fn issues(&self) -> &[Issue] {
+        &self.issues
+    }
+
+    /// Returns `true` if no issues are found.
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Returns `true` if any of the issues prevents fitting (see `Issue::is_error`).
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(Issue::is_error)
+    }
+
+    pub(crate) fn retain(&mut self, f: impl FnMut(&Issue) -> bool) {
+        self.issues.retain(f);
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, issue) in self.issues.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{issue}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the given features and target before fitting.
+///
+/// The rows are not checked for duplicates if the lengths of the columns don't match.
+pub fn validate(features: &[&[f64]], target: &[f64]) -> ValidationReport {
+    let mut issues = Vec::new();
+    for (feature, column) in features.iter().enumerate() {
+        if column.len() != target.len() {
+            issues.push(Issue::LengthMismatch {
+                feature,
+                len: column.len(),
+                expected: target.len(),
+            });
+        }
+    }
+    let aligned = issues.is_empty();
+
+    for (feature, column) in features.iter().enumerate() {
+        let mut values = column.iter().filter(|x| !x.is_nan());
+        match values.next() {
+            None if !column.is_empty() => issues.push(Issue::AllNan { feature }),
+            Some(x) if values.all(|y| y == x) => issues.push(Issue::ConstantFeature { feature }),
+            _ => {}
+        }
+    }
+
+    if aligned {
+        for excluded in screen_features(features.iter().map(|c| c.iter().copied())) {
+            if let ExcludedFeature::Duplicate { feature, original } = excluded {
+                issues.push(Issue::DuplicatedFeature { feature, original });
+            }
+        }
+    }
+
+    let rows = (0..target.len())
+        .filter(|&row| !target[row].is_finite())
+        .collect::<Vec<_>>();
+    if !rows.is_empty() {
+        issues.push(Issue::NonFiniteTargets { rows });
+    }
+
+    if aligned {
+        let rows = duplicated_rows(features, target.len());
+        if !rows.is_empty() {
+            issues.push(Issue::DuplicatedRows { rows });
+        }
+    }
+    ValidationReport { issues }
+}
+
+/// A feature excluded from the split search (see `Fanova::excluded_features`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExcludedFeature {
+    /// The feature has at most one distinct (non-NaN) value.
+    Constant {
+        /// Feature index.
+        feature: usize,
+    },
+
+    /// The feature has the same values as the earlier feature `original` in all rows.
+    Duplicate {
+        /// Feature index.
+        feature: usize,
+
+        /// Index of the earlier feature (which is used for splits).
+        original: usize,
+    },
+}
+
+impl ExcludedFeature {
+    /// Returns the index of the excluded feature.
+    pub fn feature(&self) -> usize {
+        match *self {
+            Self::Constant { feature } | Self::Duplicate { feature, .. } => feature,
+        }
+    }
+}
+
+/// Detects the constant features and the features duplicating earlier ones (in the order of the
+/// features). Constant features are not regarded as duplicates.
+pub(crate) fn screen_features<I>(columns: impl IntoIterator<Item = I>) -> Vec<ExcludedFeature>
+where
+    I: Iterator<Item = f64>,
+{
+    let nan = f64::NAN.to_bits();
+    let mut seen = HashMap::<Vec<u64>, usize>::new();
+    columns
+        .into_iter()
+        .enumerate()
+        .filter_map(|(feature, column)| {
+            // `+ 0.0` maps `-0.0` to `0.0` so that the values are compared like `==`.
+            let key = column
+                .map(|x| if x.is_nan() { nan } else { (x + 0.0).to_bits() })
+                .collect::<Vec<_>>();
+            let mut values = key.iter().filter(|&&x| x != nan);
+            if values.next().is_none_or(|x| values.all(|y| y == x)) {
+                return Some(ExcludedFeature::Constant { feature });
+            }
+            match seen.entry(key) {
+                Entry::Occupied(e) => Some(ExcludedFeature::Duplicate {
+                    feature,
+                    original: *e.get(),
+                }),
+                Entry::Vacant(e) => {
+                    e.insert(feature);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+// Values are compared by their bit patterns (all NaNs are regarded as the same value) like
+// `Table::aggregate_duplicates`.
+fn duplicated_rows(features: &[&[f64]], rows_len: usize) -> Vec<usize> {
+    let mut seen = HashMap::<Vec<u64>, usize>::new();
+    (0..rows_len)
+        .filter(|&row| {
+            let key = features
+                .iter()
+                .map(|c| if c[row].is_nan() { f64::NAN } else { c[row] }.to_bits())
+                .collect::<Vec<_>>();
+            seen.insert(key, row).is_some()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_works() {
+        let x = [0.0, 1.0, 0.0, 2.0];
+        let constant = [3.0, f64::NAN, 3.0, 3.0];
+        let nan = [f64::NAN; 4];
+        let y = [1.0, f64::INFINITY, 1.0, f64::NAN];
+        let report = validate(&[&x, &constant, &nan], &y);
+        assert_eq!(
+            report.issues(),
+            [
+                Issue::ConstantFeature { feature: 1 },
+                Issue::AllNan { feature: 2 },
+                Issue::NonFiniteTargets { rows: vec![1, 3] },
+                Issue::DuplicatedRows { rows: vec![2] },
+            ]
+        );
+        assert!(report.has_errors());
+        assert_eq!(
+            report.to_string(),
+            "feature 1 is constant; all values of feature 2 are NaN; \
+             2 rows have non-finite targets; 1 rows duplicate the features of earlier rows"
+        );
+
+        let report = validate(&[&x, &x[..3]], &[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(
+            report.issues(),
+            [Issue::LengthMismatch {
+                feature: 1,
+                len: 3,
+                expected: 4
+            }]
+        );
+
+        let report = validate(&[&x], &[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(report.issues(), [Issue::DuplicatedRows { rows: vec![2] }]);
+        assert!(!report.has_errors());
+        assert!(validate(&[&[0.0, 1.0]], &[0.0, 1.0]).is_clean());
+    }
+
+    #[test]
+    fn screen_features_works() {
+        let x = [0.0, 1.0, f64::NAN, 2.0];
+        let same = [0.0, 1.0, f64::NAN, 2.0];
+        let constant = [-0.0, 0.0, f64::NAN, 0.0];
+        let nan = [f64::NAN; 4];
+        let other = [0.0, 1.0, 2.0, 2.0];
+        let columns: [&[f64]; 6] = [&x, &constant, &same, &nan, &other, &x];
+        let excluded = screen_features(columns.iter().map(|c| c.iter().copied()));
+        assert_eq!(
+            excluded,
+            [
+                ExcludedFeature::Constant { feature: 1 },
+                ExcludedFeature::Duplicate {
+                    feature: 2,
+                    original: 0
+                },
+                ExcludedFeature::Constant { feature: 3 },
+                ExcludedFeature::Duplicate {
+                    feature: 5,
+                    original: 0
+                },
+            ]
+        );
+        assert_eq!(excluded[1].feature(), 2);
+
+        let y = [0.0; 4];
+        let report = validate(&columns, &y);
+        assert!(report.issues().contains(&Issue::DuplicatedFeature {
+            feature: 5,
+            original: 0
+        }));
+        assert!(!report.has_errors());
+        assert!(report
+            .to_string()
+            .contains("feature 2 duplicates feature 0"));
+    }
+}
@@ -0,0 +1,7298 @@
+//! JavaScript bindings.
+use crate::codec::{self, Decoder, Encoder};
+use crate::distill::{DistillOptions, ExplanatoryNode};
+use crate::fanova::{BestRows, CategoricalEncoding, FanovaFitter, FanovaOptions};
+use crate::functions;
+use crate::morris::MorrisOptions;
+use crate::random_forest::{ExtraTreesOptions, RandomForestOptions, SplitCriterion, SplitSampling};
+use crate::table::{Aggregation, ColumnType, Infinities, NonFiniteTargets, Table, TableError};
+use crate::trace;
+use crate::validation::{self, Issue, ValidationReport};
+use crate::{
+    ExcludedFeature, Fanova, FanovaError, FitError, FitQuality, Importance, MarginalUncertainty,
+    Preset, TableStream, TreeNode,
+};
+use js_sys::{
+    Array, BigInt64Array, Float32Array, Float64Array, Function, Int32Array, Promise, Reflect,
+};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+use serde_wasm_bindgen::{from_value, to_value, Serializer};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::rc::Rc;
+#[cfg(feature = "threads")]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use wasm_bindgen::prelude::*;
+
+mod cache;
+mod csv;
+mod curve;
+mod journal;
+mod queue;
+mod scalarization;
+mod study;
+
+use self::csv::{Csv, Field};
+use self::scalarization::Scalarization;
+use self::study::{Distribution, Study, Target};
+
+/// The maximum duration (in milliseconds) of a fitting step before yielding to the event loop.
+const TIME_SLICE_MILLIS: f64 = 50.0;
+
+/// The size of a Wasm memory page.
+const WASM_PAGE_SIZE: usize = 65536;
+
+/// The header of the bytes returned by `Evaluator::export_state`.
+const STATE_MAGIC: &[u8; 4] = b"FNVL";
+
+/// The number of the trees between the convergence checks of the `early_stopping` fit option.
+const EARLY_STOPPING_INTERVAL: NonZeroUsize = NonZeroUsize::new(10).expect("never fails");
+
+/// The version of the documents returned by `Evaluator::get_report_json`.
+///
+/// This is incremented whenever a field is removed or its meaning changes (adding fields keeps
+/// the version).
+const REPORT_SCHEMA_VERSION: u32 = 1;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(callback: &JsValue, millis: i32);
+
+    #[wasm_bindgen(js_namespace = console, js_name = error)]
+    fn console_error(message: &str);
+}
+
+thread_local! {
+    static PROGRESS_CALLBACK: RefCell<Option<Function>> = const { RefCell::new(None) };
+    static FOOTPRINT: Cell<MemoryUsage> = const { Cell::new(MemoryUsage::zero()) };
+    static DIAGNOSTICS: Cell<Diagnostics> = const { Cell::new(Diagnostics::zero()) };
+    static LAST_ERROR: RefCell<Option<PanicReport>> = const { RefCell::new(None) };
+}
+
+/// Returns the memory footprint of the most recently computed model.
+///
+/// The result is an object like `{nodes, table_bytes, model_bytes, heap_pages, heap_bytes}`
+/// where `heap_pages` and `heap_bytes` are the current size of the Wasm memory.
+#[wasm_bindgen]
+pub fn memory_usage() -> Result<JsValue, JsValue> {
+    let mut usage = FOOTPRINT.with(Cell::get);
+    usage.heap_pages = heap_pages();
+    usage.heap_bytes = usage.heap_pages * WASM_PAGE_SIZE;
+    Ok(to_value(&usage).map_err(Error::from)?)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+struct MemoryUsage {
+    nodes: usize,
+    table_bytes: usize,
+    model_bytes: usize,
+    heap_pages: usize,
+    heap_bytes: usize,
+}
+
+impl MemoryUsage {
+    const fn zero() -> Self {
+        Self {
+            nodes: 0,
+            table_bytes: 0,
+            model_bytes: 0,
+            heap_pages: 0,
+            heap_bytes: 0,
+        }
+    }
+
+    fn new(table_bytes: usize, fanova: &Fanova) -> Self {
+        let heap_pages = heap_pages();
+        Self {
+            nodes: fanova.nodes_len(),
+            table_bytes,
+            model_bytes: fanova.heap_size(),
+            heap_pages,
+            heap_bytes: heap_pages * WASM_PAGE_SIZE,
+        }
+    }
+
+    fn record_table(table: &Table) {
+        DIAGNOSTICS.with(|d| {
+            d.set(Diagnostics {
+                rows: table.rows_len(),
+                features: table.features_len(),
+                ..d.get()
+            })
+        });
+        FOOTPRINT.with(|f| {
+            f.set(Self {
+                table_bytes: table.heap_size(),
+                ..f.get()
+            })
+        });
+    }
+
+    fn record_model(fanova: &Fanova) {
+        FOOTPRINT.with(|f| {
+            f.set(Self {
+                nodes: fanova.nodes_len(),
+                model_bytes: fanova.heap_size(),
+                ..f.get()
+            })
+        });
+    }
+}
+
+// Calls `step` until it returns `false` or `budget_millis` elapses (it is called at least once).
+//
+// Returns `true` if `step` has returned `false` (i.e., the computation has finished).
+fn run_slice(budget_millis: f64, mut step: impl FnMut() -> bool) -> Result<bool, Error> {
+    if budget_millis.is_nan() || budget_millis < 0.0 {
+        return Err(Error::new(
+            ErrorCode::InvalidInput,
+            "the budget must be non-negative",
+        ));
+    }
+    let deadline = trace::now_millis() + budget_millis;
+    loop {
+        if !step() {
+            return Ok(true);
+        }
+        if trace::now_millis() >= deadline {
+            return Ok(false);
+        }
+    }
+}
+
+fn heap_pages() -> usize {
+    #[cfg(target_arch = "wasm32")]
+    {
+        core::arch::wasm32::memory_size(0)
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        0
+    }
+}
+
+/// Registers a callback receiving the progress of fitting and importance computation.
+///
+/// The callback is called with an object like `{phase: "fit", done: 10, total: 100, percent: 10}`
+/// (`phase` is either `"fit"` or `"importance"`).
+/// Passing `null` or `undefined` unregisters the callback.
+#[wasm_bindgen]
+pub fn on_progress(callback: Option<Function>) {
+    PROGRESS_CALLBACK.with(|c| *c.borrow_mut() = callback);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Phase {
+    Fit,
+    Importance,
+}
+
+impl Phase {
+    // Records the current phase which is reported if a panic occurs.
+    fn enter(self) {
+        DIAGNOSTICS.with(|d| {
+            d.set(Diagnostics {
+                phase: Some(self),
+                ..d.get()
+            })
+        });
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct Progress {
+    phase: Phase,
+    done: usize,
+    total: usize,
+    percent: f64,
+}
+
+impl Progress {
+    fn new(phase: Phase, done: usize, total: usize) -> Self {
+        let percent = if total == 0 {
+            100.0
+        } else {
+            done as f64 / total as f64 * 100.0
+        };
+        Self {
+            phase,
+            done,
+            total,
+            percent,
+        }
+    }
+
+    fn report(self) {
+        PROGRESS_CALLBACK.with(|c| {
+            if let Some(callback) = &*c.borrow() {
+                if let Ok(progress) = to_value(&self) {
+                    let _ = callback.call1(&JsValue::NULL, &progress);
+                }
+            }
+        });
+    }
+}
+
+/// Installs the panic hook (called automatically when the Wasm module is instantiated).
+///
+/// The hook logs the panic with the diagnostic context to `console.error` and keeps it for
+/// `last_error()`, since a panic in Wasm is otherwise reported as "unreachable executed".
+#[wasm_bindgen(start)]
+pub fn install_panic_hook() {
+    std::panic::set_hook(Box::new(|info| {
+        let report = PanicReport::new(info);
+        console_error(&report.to_string());
+        LAST_ERROR.with(|e| *e.borrow_mut() = Some(report));
+    }));
+}
+
+/// Removes the cached models and importances.
+///
+/// The last few models fitted by `Evaluator` and `FitJob` are cached by the fingerprints of
+/// their datasets (the parameter values and the targets of the trials) and fit options, so
+/// re-creating an evaluator for an unchanged study (e.g., when a dashboard view is reopened)
+/// returns the cached model and importances at once. Call this to release their memory.
+#[wasm_bindgen]
+pub fn clear_cache() {
+    cache::clear();
+}
+
+/// Returns the most recent panic as an object like
+/// `{message, location, phase, rows, features}` (or `null` if no panic has occurred).
+///
+/// `phase` is either `"fit"`, `"importance"` or `null`, and `rows` and `features` are the size
+/// of the most recently given dataset.
+/// Note that the Wasm instance should be recreated after a panic.
+#[wasm_bindgen]
+pub fn last_error() -> Result<JsValue, JsValue> {
+    LAST_ERROR.with(|e| Ok(to_value(&*e.borrow()).map_err(Error::from)?))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Diagnostics {
+    phase: Option<Phase>,
+    rows: usize,
+    features: usize,
+}
+
+impl Diagnostics {
+    const fn zero() -> Self {
+        Self {
+            phase: None,
+            rows: 0,
+            features: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct PanicReport {
+    message: String,
+    location: Option<String>,
+    phase: Option<Phase>,
+    rows: usize,
+    features: usize,
+}
+
+impl PanicReport {
+    fn new(info: &std::panic::PanicHookInfo) -> Self {
+        let payload = info.payload();
+        let message = if let Some(s) = payload.downcast_ref::<&str>() {
+            (*s).to_owned()
+        } else if let Some(s) = payload.downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "unknown panic".to_owned()
+        };
+        let location = info.location().map(|l| l.to_string());
+        Self::with_context(message, location)
+    }
+
+    fn with_context(message: String, location: Option<String>) -> Self {
+        let diagnostics = DIAGNOSTICS.with(Cell::get);
+        Self {
+            message,
+            location,
+            phase: diagnostics.phase,
+            rows: diagnostics.rows,
+            features: diagnostics.features,
+        }
+    }
+}
+
+impl std::fmt::Display for PanicReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "fanova panicked: {}", self.message)?;
+        if let Some(location) = &self.location {
+            write!(f, " at {location}")?;
+        }
+        let phase = match self.phase {
+            Some(Phase::Fit) => "fit",
+            Some(Phase::Importance) => "importance",
+            None => "none",
+        };
+        write!(
+            f,
+            " (phase: {phase}, rows: {}, features: {})",
+            self.rows, self.features
+        )
+    }
+}
+
+/// Initializes the global thread pool used when the `threads` feature is enabled.
+///
+/// The pool runs on `threads` Web Workers spawned by `wasm-bindgen-rayon` (e.g.,
+/// `navigator.hardwareConcurrency`), and the returned promise is resolved once they are ready.
+/// This must be awaited before any computation. If the pool has already been initialized, the
+/// promise is rejected with an `INVALID_INPUT` error.
+#[cfg(all(target_arch = "wasm32", feature = "threads"))]
+#[wasm_bindgen]
+pub fn init_thread_pool(threads: usize) -> Promise {
+    if let Err(e) = claim_thread_pool() {
+        return Promise::reject(&e.into());
+    }
+    let workers =
+        wasm_bindgen_futures::JsFuture::from(wasm_bindgen_rayon::init_thread_pool(threads));
+    wasm_bindgen_futures::future_to_promise(async move {
+        // The pool can be initialized again if no worker has been started (e.g., `threads == 0`).
+        workers
+            .await
+            .inspect_err(|_| THREAD_POOL_CLAIMED.store(false, Ordering::SeqCst))
+    })
+}
+
+#[cfg(feature = "threads")]
+static THREAD_POOL_CLAIMED: AtomicBool = AtomicBool::new(false);
+
+// Fails if `init_thread_pool` has already been called (the same as `build_global` of rayon,
+// which can't be called before the workers are spawned).
+#[cfg(feature = "threads")]
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+fn claim_thread_pool() -> Result<(), Error> {
+    if THREAD_POOL_CLAIMED.swap(true, Ordering::SeqCst) {
+        return Err(Error::new(
+            ErrorCode::InvalidInput,
+            "the global thread pool has already been initialized",
+        ));
+    }
+    Ok(())
+}
+
+/// Reports the spans of the `tracing` feature to the browser console and the performance timeline
+/// (via `tracing-wasm`).
+///
+/// This should be called once before any computation. An `INVALID_INPUT` error is thrown if a
+/// global subscriber has already been installed.
+#[cfg(all(target_arch = "wasm32", feature = "tracing"))]
+#[wasm_bindgen]
+pub fn init_tracing() -> Result<(), JsValue> {
+    tracing_wasm::try_set_as_global_default().map_err(|e| {
+        Error::new(
+            ErrorCode::InvalidInput,
+            format!("failed to install the tracing subscriber: {e}"),
+        )
+        .into()
+    })
+}
+
+/// Calculates the importances of the features.
+///
+/// `features` is an array of the feature columns. Each column (and `targets`) can be given as
+/// a `Float32Array` or a `Float64Array`; the values are kept as `f32` if all of them are
+/// `Float32Array`s. The feature columns given as `Int32Array`s or `BigInt64Array`s (e.g.,
+/// integer parameters) are split only at half-integers, and so are the indices of categorical
+/// choices given as `{categorical: Int32Array}` (or a `BigInt64Array`), which are encoded by the
+/// `categorical_encoding` option. The values of `BigInt64Array`s must be within ±2^53.
+/// `options` is an optional object to configure the random forest (see `FitOptions`).
+#[wasm_bindgen]
+pub fn wasm_fanova_calculate(
+    features: Array,
+    targets: Array,
+    options: JsValue,
+) -> Result<Vec<f64>, JsValue> {
+    let options = FitOptions::parse(options)?;
+    let (table, _) = parse_table(features, targets, &options)?;
+    let features_len = table.features_len();
+    let importances = importances(fit(table, &options)?, features_len)
+        .into_iter()
+        .map(|importance| importance.mean)
+        .collect();
+    Ok(importances)
+}
+
+/// Checks the features and targets before fitting.
+///
+/// The arguments are the same as `wasm_fanova_calculate`.
+/// The result is an object like
+/// `{valid: false, issues: [{kind: "constant_feature", error: false, message: "...", feature: 1}]}`
+/// where `valid` is `false` if any issue is found and `error` is `true` for the issues which
+/// make fitting fail (see `ValidationReport`).
+#[wasm_bindgen]
+pub fn wasm_fanova_validate(features: Array, targets: Array) -> Result<JsValue, JsValue> {
+    let features = features
+        .iter()
+        .map(|c| Values::parse(c).map(|c| c.widen()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let targets = Values::parse(targets.into())?.widen();
+    let features = features.iter().map(|x| x.as_slice()).collect::<Vec<_>>();
+    let report = ValidationSummary::new(&validation::validate(&features, &targets));
+    Ok(to_value(&report).map_err(Error::from)?)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct ValidationSummary {
+    valid: bool,
+    issues: Vec<IssueEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct IssueEntry {
+    kind: &'static str,
+    error: bool,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    feature: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    original: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rows: Option<Vec<usize>>,
+}
+
+impl ValidationSummary {
+    fn new(report: &ValidationReport) -> Self {
+        let issues = report
+            .issues()
+            .iter()
+            .map(|issue| {
+                let (kind, feature, rows) = match issue {
+                    Issue::LengthMismatch { feature, .. } => {
+                        ("length_mismatch", Some(*feature), None)
+                    }
+                    Issue::AllNan { feature } => ("all_nan", Some(*feature), None),
+                    Issue::ConstantFeature { feature } => {
+                        ("constant_feature", Some(*feature), None)
+                    }
+                    Issue::DuplicatedFeature { feature, .. } => {
+                        ("duplicated_feature", Some(*feature), None)
+                    }
+                    Issue::NonFiniteTargets { rows } => {
+                        ("non_finite_targets", None, Some(rows.clone()))
+                    }
+                    Issue::DuplicatedRows { rows } => ("duplicated_rows", None, Some(rows.clone())),
+                };
+                let original = match issue {
+                    Issue::DuplicatedFeature { original, .. } => Some(*original),
+                    _ => None,
+                };
+                IssueEntry {
+                    kind,
+                    error: issue.is_error(),
+                    message: issue.to_string(),
+                    feature,
+                    original,
+                    rows,
+                }
+            })
+            .collect();
+        Self {
+            valid: report.is_clean(),
+            issues,
+        }
+    }
+}
+
+/// Calculates the importances of the features for each of the given targets.
+///
+/// `targets` is an array of target arrays (e.g., objective values, durations).
+/// The table encoding and the sorted orders of the features are shared across the targets.
+/// The result is an array of the importance arrays (in the order of `targets`).
+#[wasm_bindgen]
+pub fn wasm_fanova_calculate_batch(
+    features: Array,
+    targets: Array,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    let options = FitOptions::parse(options)?;
+    let parse = |columns: Array| {
+        columns
+            .iter()
+            .map(|c| Values::parse(c).map(|c| c.widen()))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let features = parse(features)?;
+    let targets = parse(targets)?;
+    let features = features.iter().map(|x| x.as_slice()).collect();
+    let importances = batch_importances(features, &targets, &options)?;
+    Ok(to_value(&importances).map_err(Error::from)?)
+}
+
+fn batch_importances(
+    features: Vec<&[f64]>,
+    targets: &[Vec<f64>],
+    options: &FitOptions,
+) -> Result<Vec<Vec<f64>>, Error> {
+    let features_len = features.len();
+    batch_tables(features, targets, options)?
+        .into_iter()
+        .map(|table| {
+            let importances = importances(fit(table, options)?, features_len);
+            Ok(importances.into_iter().map(|i| i.mean).collect())
+        })
+        .collect()
+}
+
+// Makes a table for each target sharing the sorted orders of the features.
+fn batch_tables<'a>(
+    features: Vec<&'a [f64]>,
+    targets: &'a [Vec<f64>],
+    options: &FitOptions,
+) -> Result<Vec<Table<'a>>, Error> {
+    let Some(first) = targets.first() else {
+        return Ok(Vec::new());
+    };
+    let mut columns = features.clone();
+    columns.push(first);
+    let mut table = Table::new(columns)?;
+    table.presort();
+
+    targets
+        .iter()
+        .map(|target| {
+            options.validate(&features, target)?;
+            let mut table = table.clone();
+            table.set_target(target)?;
+            Ok(table)
+        })
+        .collect()
+}
+
+/// Calculates the importance matrix of the features for the given metrics in a single call.
+///
+/// `targets` is an array of the target arrays of the metrics (e.g., accuracy, latency and memory
+/// usage). The sorted orders of the features are shared across the metrics and the forests of the
+/// metrics are fitted in parallel (if the thread pool is initialized by `init_thread_pool`).
+/// The result is an object like `{importances: [[...], ...], std: [[...], ...]}` where
+/// `importances[i][k]` is the importance of the `i`-th feature for the `k`-th metric and
+/// `std[i][k]` is its standard deviation across the trees.
+#[wasm_bindgen]
+pub fn wasm_fanova_importance_matrix(
+    features: Array,
+    targets: Array,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    let options = FitOptions::parse(options)?;
+    let parse = |columns: Array| {
+        columns
+            .iter()
+            .map(|c| Values::parse(c).map(|c| c.widen()))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let features = parse(features)?;
+    let targets = parse(targets)?;
+    let features = features.iter().map(|x| x.as_slice()).collect();
+    let matrix = importance_matrix(features, &targets, &options)?;
+    Ok(to_value(&matrix).map_err(Error::from)?)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct ImportanceMatrix {
+    importances: Vec<Vec<f64>>,
+    std: Vec<Vec<f64>>,
+}
+
+fn importance_matrix(
+    features: Vec<&[f64]>,
+    targets: &[Vec<f64>],
+    options: &FitOptions,
+) -> Result<ImportanceMatrix, Error> {
+    let features_len = features.len();
+    let tables = batch_tables(features, targets, options)?;
+    Phase::Fit.enter();
+    // The progress is not reported since the metrics are fitted in parallel.
+    let feature_sets = (0..features_len).map(|i| vec![i]).collect::<Vec<_>>();
+    let columns = tables
+        .into_par_iter()
+        .map(|table| {
+            let mut fanova = options.fanova().fit_table(check_table(table)?)?;
+            Ok(fanova.quantify_importances(&feature_sets))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let cells = |f: fn(&Importance) -> f64| {
+        (0..features_len)
+            .map(|i| columns.iter().map(|c| f(&c[i])).collect())
+            .collect()
+    };
+    Ok(ImportanceMatrix {
+        importances: cells(|i| i.mean),
+        std: cells(|i| i.stddev),
+    })
+}
+
+/// Calculates the importances of the features for each objective of a multi-objective study and
+/// for a scalarization of the objectives.
+///
+/// `objectives` is an array of the objective value arrays and `scalarization` is an object like
+/// `{method: "weighted_sum", directions: ["minimize", "maximize"], weights: [0.5, 0.5]}`
+/// (see `Scalarization` for the methods).
+/// The result is an object like `{objectives: [[...], ...], scalarized: [...]}`.
+#[wasm_bindgen]
+pub fn wasm_fanova_calculate_multi_objective(
+    features: Array,
+    objectives: Array,
+    scalarization: JsValue,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    let options = FitOptions::parse(options)?;
+    let scalarization: Scalarization = from_value(scalarization).map_err(Error::from)?;
+    let parse = |columns: Array| {
+        columns
+            .iter()
+            .map(|c| Values::parse(c).map(|c| c.widen()))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let features = parse(features)?;
+    let objectives = parse(objectives)?;
+    let features = features.iter().map(|x| x.as_slice()).collect();
+    let importances = multi_objective_importances(features, objectives, &scalarization, &options)?;
+    Ok(to_value(&importances).map_err(Error::from)?)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct MultiObjectiveImportances {
+    objectives: Vec<Vec<f64>>,
+    scalarized: Vec<f64>,
+}
+
+fn multi_objective_importances(
+    features: Vec<&[f64]>,
+    mut objectives: Vec<Vec<f64>>,
+    scalarization: &Scalarization,
+    options: &FitOptions,
+) -> Result<MultiObjectiveImportances, Error> {
+    let rows = objectives.first().map_or(0, |o| o.len());
+    if objectives.iter().any(|o| o.len() != rows) {
+        return Err(Error::new(
+            ErrorCode::InvalidInput,
+            "all objectives must have the same length",
+        ));
+    }
+    let values = (0..rows)
+        .map(|i| objectives.iter().map(|o| o[i]).collect())
+        .collect::<Vec<Vec<f64>>>();
+    objectives.push(scalarization.apply(&values)?);
+
+    let mut importances = batch_importances(features, &objectives, options)?;
+    let scalarized = importances.pop().expect("the scalarized target exists");
+    Ok(MultiObjectiveImportances {
+        objectives: importances,
+        scalarized,
+    })
+}
+
+/// Calculates the importances of the features for a categorical outcome (e.g., the statuses of
+/// trials such as `"converged"`, `"diverged"` and `"oom"`).
+///
+/// `outcomes` is an array of the labels of the trials, and a forest is fitted to estimate the
+/// probability of each label (see `FanovaOptions::fit_categorical`). The result is an object
+/// like `{importances: [...], classes: {<label>: [...]}}` where `importances` decompose the
+/// variance of the probabilities of all the labels and `classes` are the importances of the
+/// probability of each label alone (e.g., to see what drives the failures).
+#[wasm_bindgen]
+pub fn wasm_fanova_calculate_categorical(
+    features: Array,
+    outcomes: JsValue,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    let options = FitOptions::parse(options)?;
+    let features = features
+        .iter()
+        .map(|c| Values::parse(c).map(|c| c.widen()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let outcomes: Vec<String> = from_value(outcomes).map_err(Error::from)?;
+    let features = features.iter().map(|x| x.as_slice()).collect();
+    let importances = categorical_importances(features, &outcomes, &options)?;
+    Ok(to_js(&importances)?)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct CategoricalImportances {
+    importances: Vec<f64>,
+    classes: BTreeMap<String, Vec<f64>>,
+}
+
+fn categorical_importances(
+    features: Vec<&[f64]>,
+    outcomes: &[String],
+    options: &FitOptions,
+) -> Result<CategoricalImportances, Error> {
+    let labels = outcomes.iter().collect::<BTreeSet<_>>();
+    let classes = outcomes
+        .iter()
+        .map(|o| labels.iter().position(|l| *l == o).expect("never fails"))
+        .collect::<Vec<_>>();
+    let features_len = features.len();
+    Phase::Fit.enter();
+    let mut fanova = options.fanova().fit_categorical(features, &classes)?;
+    Phase::Importance.enter();
+    let importances = fanova.importances().into_iter().map(|i| i.mean).collect();
+    let classes = labels
+        .into_iter()
+        .enumerate()
+        .map(|(i, label)| {
+            let model = fanova.class_model(i).expect("every label has a class");
+            let importances = (0..features_len)
+                .map(|j| model.quantify_importance(&[j]).mean)
+                .collect();
+            (label.clone(), importances)
+        })
+        .collect();
+    Ok(CategoricalImportances {
+        importances,
+        classes,
+    })
+}
+
+/// Calculates the importances of the named parameters.
+///
+/// `params` is an object mapping parameter names to their values
+/// (e.g., `{x: [0.1, 0.5], y: [1, 2]}`) and `targets` is an array of the objective values.
+/// The result is an object like `{x: {importance: 0.8, std: 0.1, rank: 1}, ...}`
+/// (`rank` starts from `1` for the most important parameter).
+#[wasm_bindgen]
+pub fn wasm_fanova_importances(
+    params: JsValue,
+    targets: Array,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    let options = FitOptions::parse(options)?;
+    let params: BTreeMap<String, Vec<f64>> = from_value(params).map_err(Error::from)?;
+    let targets: Vec<f64> = from_value(targets.into()).map_err(Error::from)?;
+
+    let mut columns = params.values().map(|x| x.as_slice()).collect::<Vec<_>>();
+    columns.push(&targets);
+    let (table, _) = options.table(columns)?;
+    let table = table.into_owned();
+    let features_len = table.features_len();
+    let importances = importances(fit(table, &options)?, features_len);
+
+    let result = ImportanceEntry::ranked(params.into_keys().zip(importances));
+    Ok(to_js(&result)?)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct ImportanceEntry {
+    importance: f64,
+    std: f64,
+    rank: usize,
+}
+
+impl ImportanceEntry {
+    /// Ranks the importances in descending order (ties are ranked in the given order).
+    fn ranked(
+        importances: impl Iterator<Item = (String, Importance)>,
+    ) -> BTreeMap<String, ImportanceEntry> {
+        let mut importances = importances.collect::<Vec<_>>();
+        importances.sort_by(|a, b| b.1.mean.total_cmp(&a.1.mean));
+        importances
+            .into_iter()
+            .enumerate()
+            .map(|(i, (name, importance))| {
+                let entry = Self {
+                    importance: importance.mean,
+                    std: importance.stddev,
+                    rank: i + 1,
+                };
+                (name, entry)
+            })
+            .collect()
+    }
+}
+
+/// An fANOVA model fitted for a study (or an objective).
+///
+/// Each evaluator owns its table, forest and caches, so multiple evaluators can be used
+/// independently. Call `free()` to release the memory of an evaluator.
+///
+/// The last few fitted models and their importances are cached by the fingerprints of the
+/// datasets and fit options, so an evaluator created again for an unchanged study answers
+/// without fitting the forest (see `clear_cache()`).
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct Evaluator {
+    name: String,
+    options: FitOptions,
+    param_names: Vec<String>,
+
+    // The search space definitions of the parameters (if known).
+    distributions: Vec<Option<Distribution>>,
+
+    table: Table<'static>,
+    fanova: Fanova,
+
+    // The number of trials added since `fanova` was fitted.
+    pending_trials: usize,
+
+    // The number of partial refits (used to seed the new trees).
+    refits: u64,
+
+    // The number of trials whose targets were not finite (see `FitOptions`).
+    non_finite_trials: usize,
+
+    // The computation started by `start_marginals` (which is not exported).
+    marginal_job: Option<MarginalJob>,
+
+    // The computation started by `start_pairwise_importance` (which is not exported).
+    pairwise_job: Option<PairwiseJob>,
+}
+
+#[wasm_bindgen]
+impl Evaluator {
+    /// Fits a model for the given features and targets.
+    ///
+    /// `options` is an optional object to configure the random forest (see `FitOptions`).
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        name: String,
+        features: Array,
+        targets: Array,
+        options: JsValue,
+    ) -> Result<Evaluator, JsValue> {
+        let options = FitOptions::parse(options)?;
+        let (table, non_finite_trials) = parse_table(features, targets, &options)?;
+        let mut evaluator = Self::from_table(name, table, options)?;
+        evaluator.non_finite_trials = non_finite_trials;
+        Ok(evaluator)
+    }
+
+    /// Fits a model for the trials in an Optuna journal storage file.
+    ///
+    /// `study_name` can be omitted if the journal has only one study.
+    /// `target` is the value to be analyzed: the index of an objective (`0` for single-objective
+    /// studies), a user attribute (e.g., `{user_attr: "accuracy"}`) or the intermediate value
+    /// at a step (e.g., `{intermediate_value: 10}`). Only the trials having the target are used
+    /// (pruned trials are used for user attributes and intermediate values).
+    /// The parameters are encoded according to their distributions and parameters missing in
+    /// some trials are handled as specified by the `inactive_params` option.
+    pub fn from_journal(
+        journal: &str,
+        study_name: Option<String>,
+        target: JsValue,
+        options: JsValue,
+    ) -> Result<Evaluator, JsValue> {
+        let target = parse_target(target)?;
+        let options = FitOptions::parse(options)?;
+        Ok(Self::from_journal_with_options(
+            journal, study_name, target, options,
+        )?)
+    }
+
+    fn from_journal_with_options(
+        journal: &str,
+        study_name: Option<String>,
+        target: Target,
+        options: FitOptions,
+    ) -> Result<Self, Error> {
+        let study = journal::find_study(journal, study_name)?;
+        Self::from_study(study, &target, options)
+    }
+
+    /// Fits a model for the trials exported from an Optuna study.
+    ///
+    /// `trials` is a JSON array of trials like
+    /// `[{"params": {...}, "distributions": {...}, "values": [...], "state": "COMPLETE"}]`
+    /// (or an object like `{"study_name": ..., "trials": [...]}`) where `params` are the
+    /// parameter values as suggested and `distributions` are the Optuna distributions
+    /// (e.g., `{"name": "FloatDistribution", "attributes": {"low": 0, "high": 1, ...}}`).
+    /// Trials can also have `user_attrs` and `intermediate_values` (e.g., `{"10": 0.9}`).
+    /// See `from_journal` for `target`.
+    pub fn from_trials(
+        trials: &str,
+        target: JsValue,
+        options: JsValue,
+    ) -> Result<Evaluator, JsValue> {
+        let target = parse_target(target)?;
+        let options = FitOptions::parse(options)?;
+        let study = study::parse_trials(trials)?;
+        Ok(Self::from_study(study, &target, options)?)
+    }
+
+    fn from_study(mut study: Study, target: &Target, options: FitOptions) -> Result<Self, Error> {
+        if let Target::Objective(objective) = *target {
+            let completed = study.trials.iter().filter(|t| !t.values.is_empty());
+            if completed.clone().any(|t| t.values.len() <= objective) {
+                return Err(Error::new(
+                    ErrorCode::InvalidInput,
+                    "objective out of range",
+                ));
+            }
+        }
+        study.trials.retain(|t| t.target(target).is_some());
+        if study.trials.is_empty() {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "no trials have the target",
+            ));
+        }
+
+        let names = match options.inactive_params {
+            InactiveParams::Conditional => study.param_names(),
+            InactiveParams::Intersection => study.common_param_names(),
+        };
+        if names.is_empty() {
+            return Err(Error::new(ErrorCode::InvalidInput, "no parameters"));
+        }
+        let (features, target) = study.columns(&names, target);
+        let mut columns = features.iter().map(|c| c.as_slice()).collect::<Vec<_>>();
+        columns.push(&target);
+        let (table, non_finite_trials) = options.table(columns)?;
+        let mut table = table.into_owned();
+        let distributions = names
+            .iter()
+            .map(|name| study.distributions.get(name).cloned())
+            .collect::<Vec<_>>();
+        encode_table(&mut table, &distributions)?;
+        let mut evaluator = Self::from_table(study.name, table, options)?;
+        evaluator.distributions = distributions;
+        evaluator.param_names = names;
+        evaluator.non_finite_trials = non_finite_trials;
+        Ok(evaluator)
+    }
+
+    fn from_table(name: String, table: Table<'static>, options: FitOptions) -> Result<Self, Error> {
+        let fanova = fit_cached(table.clone(), &options)?;
+        Ok(Self::from_fitted(name, table, options, fanova))
+    }
+
+    fn from_fitted(
+        name: String,
+        table: Table<'static>,
+        options: FitOptions,
+        fanova: Fanova,
+    ) -> Self {
+        Self {
+            name,
+            options,
+            param_names: (0..table.features_len()).map(|i| i.to_string()).collect(),
+            distributions: vec![None; table.features_len()],
+            table,
+            fanova,
+            pending_trials: 0,
+            refits: 0,
+            non_finite_trials: 0,
+            marginal_job: None,
+            pairwise_job: None,
+        }
+    }
+
+    /// Returns the name of this evaluator.
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Returns the random seed used to fit the model.
+    ///
+    /// Evaluators created with the same trials and seed always report the same importances.
+    #[wasm_bindgen(getter)]
+    pub fn seed(&self) -> u64 {
+        self.options.seed
+    }
+
+    /// Returns the names of the parameters (features).
+    ///
+    /// The default names are the indices of the parameters (i.e., `"0"`, `"1"`, ...).
+    #[wasm_bindgen(getter)]
+    pub fn param_names(&self) -> Vec<String> {
+        self.param_names.clone()
+    }
+
+    /// Returns the search space definitions of the parameters as an object like
+    /// `{name: {type: "float", low, high, log, step}}` (`type` is either `"float"`, `"int"` or
+    /// `"categorical"`, which has `choices` instead).
+    ///
+    /// Parameters without known definitions (e.g., given by the constructor) are omitted.
+    pub fn get_distributions(&self) -> Result<JsValue, JsValue> {
+        let distributions = self
+            .param_names
+            .iter()
+            .zip(self.distributions.iter())
+            .filter_map(|(name, d)| Some((name, d.as_ref()?)))
+            .collect::<BTreeMap<_, _>>();
+        Ok(to_js(&distributions)?)
+    }
+
+    /// Sets the names of the parameters (features).
+    pub fn set_param_names(&mut self, names: Vec<String>) -> Result<(), JsValue> {
+        if names.len() != self.table.features_len() {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "the number of names must be equal to the number of parameters",
+            )
+            .into());
+        }
+        self.param_names = names;
+        Ok(())
+    }
+
+    /// Returns the number of trials.
+    #[wasm_bindgen(getter)]
+    pub fn trials_len(&self) -> usize {
+        self.table.rows_len()
+    }
+
+    /// Returns the number of trials whose targets were NaN, infinite or missing, which have
+    /// been dropped or imputed according to the `non_finite_targets` option.
+    #[wasm_bindgen(getter)]
+    pub fn non_finite_trials(&self) -> usize {
+        self.non_finite_trials
+    }
+
+    /// Appends trials to the table of this evaluator.
+    ///
+    /// `params` is an array of the parameter arrays of the trials (in the same feature order
+    /// as the constructor) and `values` is an array of their objective values.
+    /// The model is updated when the importances are requested next time.
+    /// If only a few trials are added, a part of the trees (in proportion to the number of
+    /// the added trials) are refitted instead of the whole forest.
+    pub fn add_trials(&mut self, params: JsValue, values: JsValue) -> Result<(), JsValue> {
+        let params: Vec<Vec<f64>> = from_value(params).map_err(Error::from)?;
+        let values: Vec<f64> = from_value(values).map_err(Error::from)?;
+        Ok(self.push_trials(&params, &values)?)
+    }
+
+    fn push_trials(&mut self, params: &[Vec<f64>], values: &[f64]) -> Result<(), Error> {
+        // Validates all trials beforehand so that no trial is added on failure.
+        if params.len() != values.len()
+            || params.iter().any(|p| p.len() != self.table.features_len())
+        {
+            return Err(TableError::RowSizeMismatch.into());
+        }
+        let infinities = self.options.infinities();
+        let affected = params
+            .iter()
+            .zip(values)
+            .filter(|(p, v)| !v.is_finite() || (infinities.is_some() && has_infinity(p)))
+            .count();
+        let (params, values) = self.handle_infinities(params, values)?;
+
+        let non_finite = values.iter().filter(|v| !v.is_finite()).count();
+        let fill = match self.options.non_finite_targets() {
+            _ if non_finite == 0 => None,
+            NonFiniteTargets::Error => return Err(TableError::NonFiniteTarget.into()),
+            NonFiniteTargets::Drop => None,
+            // The value is aggregated over the trials which have been added so far.
+            NonFiniteTargets::Impute(_) => Some(self.imputed_target(&values)),
+        };
+
+        let rows = params
+            .iter()
+            .zip(values.iter())
+            .filter_map(|(p, &v)| match (v.is_finite(), fill) {
+                (true, _) => Some((p, v)),
+                (false, Some(fill)) => Some((p, fill)),
+                (false, None) => None,
+            })
+            .collect::<Vec<_>>();
+        for &(p, v) in &rows {
+            self.table.check_row(p, v)?;
+        }
+        for &(p, v) in &rows {
+            self.table.push_row(p, v)?;
+        }
+        self.pending_trials += rows.len();
+        self.non_finite_trials += affected;
+        Ok(())
+    }
+
+    // Applies the `infinities` option to new trials.
+    //
+    // Clamped values are computed from the trials which have been added so far.
+    fn handle_infinities(
+        &self,
+        params: &[Vec<f64>],
+        values: &[f64],
+    ) -> Result<(Vec<Vec<f64>>, Vec<f64>), Error> {
+        let mut params = params.to_vec();
+        let mut values = values.to_vec();
+        let infinite = params
+            .iter()
+            .zip(&values)
+            .any(|(p, v)| v.is_infinite() || has_infinity(p));
+        match self.options.infinities() {
+            None => {}
+            Some(_) if !infinite => {}
+            Some(Infinities::Error) => {
+                return Err(Error::new(
+                    ErrorCode::InvalidInput,
+                    "trials contain infinite values",
+                ));
+            }
+            Some(Infinities::Drop) => {
+                (params, values) = params
+                    .into_iter()
+                    .zip(values)
+                    .filter(|(p, v)| !(v.is_infinite() || has_infinity(p)))
+                    .unzip();
+            }
+            Some(Infinities::Clamp(q)) => {
+                let features_len = self.table.features_len();
+                for i in 0..=features_len {
+                    let column = if i == features_len {
+                        self.table.target().collect::<Vec<_>>()
+                    } else {
+                        let column = self.table.column(i);
+                        column.map(|x| self.table.inverse_transform(i, x)).collect()
+                    };
+                    let (lower, upper) = clamping_bounds(column, q);
+                    let clamp = |x: &mut f64| match *x {
+                        f64::INFINITY => *x = upper,
+                        f64::NEG_INFINITY => *x = lower,
+                        _ => {}
+                    };
+                    if i == features_len {
+                        values.iter_mut().for_each(clamp);
+                    } else {
+                        params.iter_mut().for_each(|p| clamp(&mut p[i]));
+                    }
+                }
+            }
+        }
+        Ok((params, values))
+    }
+
+    fn imputed_target(&self, values: &[f64]) -> f64 {
+        let NonFiniteTargets::Impute(aggregation) = self.options.non_finite_targets() else {
+            unreachable!("only called for imputation");
+        };
+        let targets = self.table.target().chain(values.iter().copied());
+        aggregation.aggregate(
+            targets
+                .filter(|v| v.is_finite())
+                .map(|v| (v, 1.0))
+                .collect(),
+        )
+    }
+
+    fn fanova(&mut self) -> Result<&mut Fanova, Error> {
+        if self.pending_trials == 0 {
+            return Ok(&mut self.fanova);
+        }
+
+        let trees_len = self.fanova.trees_len();
+        let refit_trees = (trees_len * self.pending_trials).div_ceil(self.table.rows_len());
+        match NonZeroUsize::new(refit_trees).filter(|n| n.get() < trees_len) {
+            Some(trees) => {
+                self.refits += 1;
+                let seed = self.options.seed.wrapping_add(self.refits);
+                let options = self.options.fanova_with(seed, Some(trees));
+                let newer = fit_with_options(self.table.clone(), options)?;
+                self.fanova.replace_trees(newer);
+            }
+            None => {
+                self.fanova = fit_cached(self.table.clone(), &self.options)?;
+            }
+        }
+        self.pending_trials = 0;
+        Ok(&mut self.fanova)
+    }
+
+    /// Fits the remaining trees of a model cut short by the `time_budget_millis` fit option
+    /// (e.g., after `import_state`) and returns `true` if the model is complete.
+    ///
+    /// The fitting continues the random sequence of the model, so the completed model is the
+    /// same as the one fitted without the budget. It is limited by `time_budget_millis` again,
+    /// so this can be called repeatedly (e.g., while the page is idle) until it returns `true`.
+    /// A model whose trees have been partially refitted after `add_trial` is refitted from
+    /// scratch instead.
+    pub fn resume_fit(&mut self) -> Result<bool, JsValue> {
+        Ok(self.resume()?)
+    }
+
+    fn resume(&mut self) -> Result<bool, Error> {
+        let fanova = self.fanova()?;
+        if !fanova.is_partial() {
+            return Ok(true);
+        }
+        if fanova.rng_state().trees == fanova.trees_len() {
+            let table = check_table(self.table.clone())?;
+            Phase::Fit.enter();
+            let options = self.options.fanova();
+            let mut fitter = FanovaFitter::resume(options, table, &mut self.fanova)?;
+            while fitter.step() {
+                report_fit_progress(&fitter);
+            }
+            self.fanova = fitter.finish();
+        } else {
+            // The older trees belong to another random sequence.
+            self.fanova = fit(self.table.clone(), &self.options)?;
+        }
+        Ok(!self.fanova.is_partial())
+    }
+
+    /// Returns the importances of the features.
+    pub fn importances(&mut self) -> Result<Vec<f64>, JsValue> {
+        Ok(self.compute_importances()?)
+    }
+
+    /// Returns the importances of the parameters as a CSV string (e.g., to import them into
+    /// spreadsheets).
+    ///
+    /// The columns are `param,importance,std,rank` (in the order of the parameters, where
+    /// `rank` is the same as `wasm_fanova_importances`). Non-finite values are left empty.
+    pub fn get_importances_csv(&mut self) -> Result<String, JsValue> {
+        Ok(self.importances_csv()?)
+    }
+
+    fn importances_csv(&mut self) -> Result<String, Error> {
+        let features_len = self.table.features_len();
+        let fanova = self.fanova()?;
+        let importances = (0..features_len)
+            .map(|i| quantify_importance(fanova, i, features_len))
+            .collect::<Vec<_>>();
+        // Ranked like `ImportanceEntry::ranked` (by the indices since the names may not be
+        // unique).
+        let mut order = (0..features_len).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| importances[b].mean.total_cmp(&importances[a].mean));
+        let mut ranks = vec![0; features_len];
+        for (rank, &i) in order.iter().enumerate() {
+            ranks[i] = rank + 1;
+        }
+        let mut csv = Csv::new(&["param", "importance", "std", "rank"]);
+        for ((name, importance), rank) in self.param_names.iter().zip(&importances).zip(ranks) {
+            csv.row([
+                Field::Text(name),
+                Field::Number(importance.mean),
+                Field::Number(importance.stddev),
+                Field::Integer(rank),
+            ]);
+        }
+        Ok(csv.finish())
+    }
+
+    fn compute_importances(&mut self) -> Result<Vec<f64>, Error> {
+        let features_len = self.table.features_len();
+        self.fanova()?;
+        let fingerprint = self.fingerprint();
+        if let Some(importances) = fingerprint.and_then(cache::importances) {
+            return Ok(importances);
+        }
+        let importances = (0..features_len)
+            .map(|i| quantify_importance(&mut self.fanova, i, features_len).mean)
+            .collect::<Vec<_>>();
+        if let Some(fingerprint) = fingerprint {
+            cache::store_importances(fingerprint, &importances);
+        }
+        Ok(importances)
+    }
+
+    // Returns the fingerprint of the dataset and the options if the model is the one fitted to
+    // them at once (i.e., neither partially refitted nor cut short by the time budget).
+    fn fingerprint(&self) -> Option<u64> {
+        (self.pending_trials == 0 && self.refits == 0 && !self.fanova.is_partial())
+            .then(|| cache::fingerprint(&self.table, &self.options))
+    }
+
+    /// Returns the importance of the given feature set as an object like `{importance, std}`.
+    pub fn quantify_importance(&mut self, features: Vec<usize>) -> Result<JsValue, JsValue> {
+        let value = self.quantify(&features)?;
+        Ok(to_value(&value).map_err(Error::from)?)
+    }
+
+    fn quantify(&mut self, features: &[usize]) -> Result<ImportanceValue, Error> {
+        if features.iter().any(|&i| i >= self.table.features_len()) {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "parameter out of range",
+            ));
+        }
+        let fanova = self.fanova()?;
+        Phase::Importance.enter();
+        let importance = fanova.quantify_importance(features);
+        Ok(ImportanceValue {
+            importance: importance.mean,
+            std: importance.stddev,
+        })
+    }
+
+    /// Returns the importances of the parameters for landing in the top `quantile` of the
+    /// trials as an object like `{<name>: {importance, std, rank}}`.
+    ///
+    /// A separate forest is fitted to whether each trial is within the best `quantile` (in
+    /// `(0, 1]`) of the targets (see `FanovaOptions::top_quantile`), so the importances tell
+    /// which parameters to tune to get a good trial rather than which ones explain the variance
+    /// of the targets. `maximize` tells whether the best trials have the largest targets.
+    pub fn get_top_quantile_importances(
+        &mut self,
+        quantile: f64,
+        maximize: bool,
+    ) -> Result<JsValue, JsValue> {
+        let importances = self.top_quantile_importances(quantile, maximize)?;
+        Ok(to_js(&importances)?)
+    }
+
+    fn top_quantile_importances(
+        &mut self,
+        quantile: f64,
+        maximize: bool,
+    ) -> Result<BTreeMap<String, ImportanceEntry>, Error> {
+        if !(quantile > 0.0 && quantile <= 1.0) {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "the quantile must be in the range (0, 1]",
+            ));
+        }
+        let mut table = self.table.clone();
+        if maximize {
+            table.map_target(|y| -y);
+        }
+        let options = self.options.fanova().top_quantile(quantile);
+        let features_len = table.features_len();
+        let importances = importances(fit_with_options(table, options)?, features_len);
+        Ok(ImportanceEntry::ranked(
+            self.param_names.iter().cloned().zip(importances),
+        ))
+    }
+
+    /// Returns the importances within a region of the search space as an object like
+    /// `{<name>: {importance, std, rank}}`.
+    ///
+    /// `options` is an object like `{domain: {lr: [0.001, 0.01], optimizer: {choices: ["adam"]}},
+    /// refit: false}` (see `RestrictionOptions`). The variance and the marginalization are taken
+    /// over the region (see `Fanova::restrict`), so this tells what matters once some
+    /// parameters are in their good ranges. If `refit` is `true`, a separate forest is fitted
+    /// only to the trials within the region (see `FanovaOptions::fit_restricted`).
+    pub fn get_restricted_importances(&mut self, options: JsValue) -> Result<JsValue, JsValue> {
+        let options: RestrictionOptions = from_value(options).map_err(Error::from)?;
+        let importances = self.restricted_importances(&options)?;
+        Ok(to_js(&importances)?)
+    }
+
+    fn restricted_importances(
+        &mut self,
+        options: &RestrictionOptions,
+    ) -> Result<BTreeMap<String, ImportanceEntry>, Error> {
+        let domain = self.domain(options)?;
+        let features_len = self.table.features_len();
+        let restricted = if options.refit {
+            let rows = self.table.rows_within(&domain);
+            if rows.is_empty() {
+                return Err(Error::new(
+                    ErrorCode::InvalidInput,
+                    "no trials are within the domain",
+                ));
+            }
+            let table = self.table.select_rows(&rows);
+            let fanova = fit(table, &self.options)?;
+            check_domain(&fanova, &domain)?;
+            fanova.restrict(&domain)
+        } else {
+            let fanova = self.fanova()?;
+            check_domain(fanova, &domain)?;
+            fanova.restrict(&domain)
+        };
+        let importances = importances(restricted, features_len);
+        Ok(ImportanceEntry::ranked(
+            self.param_names.iter().cloned().zip(importances),
+        ))
+    }
+
+    // Converts the domain of `options` into the ranges of the features in the table.
+    fn domain(&self, options: &RestrictionOptions) -> Result<Vec<Option<Vec<Range<f64>>>>, Error> {
+        let invalid = |message: String| Err(Error::new(ErrorCode::InvalidInput, message));
+        let mut domain = vec![None; self.table.features_len()];
+        for (name, param) in &options.domain {
+            let Some(i) = self.param_names.iter().position(|n| n == name) else {
+                return invalid(format!("unknown parameter: {name}"));
+            };
+            let ranges = match param {
+                ParamDomain::Range([low, high]) => {
+                    if !(low.is_finite() && high.is_finite() && low < high) {
+                        return invalid(format!("the range of {name} must be finite [low, high]"));
+                    }
+                    let (low, high) = (
+                        self.table.apply_transforms(i, *low),
+                        self.table.apply_transforms(i, *high),
+                    );
+                    let margin = if self.table.column_type(i).is_discrete() {
+                        0.5
+                    } else {
+                        0.0
+                    };
+                    vec![(low - margin)..(high + margin)]
+                }
+                ParamDomain::Choices { choices: subset } => {
+                    let Some(Distribution::Categorical { choices }) = &self.distributions[i] else {
+                        return invalid(format!("{name} is not a categorical parameter"));
+                    };
+                    let mut indices = BTreeSet::new();
+                    for choice in subset {
+                        match choices.iter().position(|c| c == choice) {
+                            Some(k) => indices.insert(k),
+                            None => return invalid(format!("unknown choice of {name}: {choice}")),
+                        };
+                    }
+                    indices
+                        .into_iter()
+                        .map(|k| {
+                            let x = self.table.apply_transforms(i, k as f64);
+                            (x - 0.5)..(x + 0.5)
+                        })
+                        .collect()
+                }
+            };
+            domain[i] = Some(ranges);
+        }
+        Ok(domain)
+    }
+
+    /// Returns the importances of the parameters estimated by the first `1, 2, ..., n` trees of
+    /// the forest as an object like `{<name>: {importance: [...], std: [...]}}`, where the `i`-th
+    /// elements are the importance after `i + 1` trees and its standard deviation across them.
+    ///
+    /// The last elements are the same as `get_importances` (see
+    /// `Fanova::importance_convergence`), so this tells whether the forest is large enough (or
+    /// whether a smaller `n_trees` fit option would give the same importances).
+    pub fn get_importance_convergence(&mut self) -> Result<JsValue, JsValue> {
+        let convergence = self.importance_convergence()?;
+        Ok(to_js(&convergence)?)
+    }
+
+    fn importance_convergence(&mut self) -> Result<BTreeMap<String, ConvergenceEntry>, Error> {
+        self.fanova()?;
+        Phase::Importance.enter();
+        Ok(self
+            .param_names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let (importance, std) = self
+                    .fanova
+                    .importance_convergence(&[i])
+                    .into_iter()
+                    .map(|importance| (importance.mean, importance.stddev))
+                    .unzip();
+                (name.clone(), ConvergenceEntry { importance, std })
+            })
+            .collect())
+    }
+
+    /// Tests the significance of the importances against `n_permutations` null importances as
+    /// an object like `{<name>: {importance, null_mean, null_std, p_value, z_score}}`.
+    ///
+    /// The null importances are computed by refitting the forest to randomly permuted targets
+    /// (see `FanovaOptions::test_significance`), so this takes `n_permutations + 1` fits. Small
+    /// `p_value`s (e.g., less than `0.05`, which needs `19` or more permutations) suggest that the
+    /// parameters are genuinely important rather than fitted to noise.
+    pub fn get_null_importances(&mut self, n_permutations: usize) -> Result<JsValue, JsValue> {
+        let significances = self.null_importances(n_permutations)?;
+        Ok(to_js(&significances)?)
+    }
+
+    fn null_importances(
+        &mut self,
+        n_permutations: usize,
+    ) -> Result<BTreeMap<String, SignificanceEntry>, Error> {
+        let permutations = NonZeroUsize::new(n_permutations).ok_or_else(|| {
+            Error::new(
+                ErrorCode::InvalidInput,
+                "the number of permutations must be positive",
+            )
+        })?;
+        Phase::Fit.enter();
+        let significances = self.options.fanova().test_significance(
+            self.table.clone(),
+            permutations,
+            self.options.seed,
+        )?;
+        Ok(self
+            .param_names
+            .iter()
+            .cloned()
+            .zip(significances)
+            .map(|(name, s)| {
+                let entry = SignificanceEntry {
+                    importance: s.importance,
+                    null_mean: s.null_mean,
+                    null_std: s.null_stddev,
+                    p_value: s.p_value,
+                    z_score: s.z_score,
+                };
+                (name, entry)
+            })
+            .collect())
+    }
+
+    /// Estimates how well the forest generalizes to unseen trials by `n_folds`-fold
+    /// cross-validation as an object like `{r2, rmse, rows, folds: [{r2, rmse, rows}]}`.
+    ///
+    /// The trials are assigned to the folds with the `seed` fit option, and the forest is refitted
+    /// to the trials of the other folds to predict those of each fold (see
+    /// `FanovaOptions::cross_validate`), so this takes `n_folds` fits. The top-level `r2` and
+    /// `rmse` are computed from the predictions of all the trials, and `folds` are those of each
+    /// fold.
+    pub fn get_cross_validation(&mut self, n_folds: usize) -> Result<JsValue, JsValue> {
+        let summary = self.cross_validation(n_folds)?;
+        Ok(to_value(&summary).map_err(Error::from)?)
+    }
+
+    fn cross_validation(&mut self, n_folds: usize) -> Result<CrossValidationSummary, Error> {
+        if n_folds < 2 {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "the number of folds must be 2 or more",
+            ));
+        }
+        Phase::Fit.enter();
+        let cv =
+            self.options
+                .fanova()
+                .cross_validate(self.table.clone(), n_folds, self.options.seed)?;
+        let entry = |q: FitQuality| FoldEntry {
+            r2: q.r2,
+            rmse: q.rmse,
+            rows: q.rows,
+        };
+        Ok(CrossValidationSummary {
+            r2: cv.overall.r2,
+            rmse: cv.overall.rmse,
+            rows: cv.overall.rows,
+            folds: cv.folds.into_iter().map(entry).collect(),
+        })
+    }
+
+    /// Returns the decrease of the out-of-bag R² by refitting the forest without each parameter
+    /// as an object like `{<name>: {importance, r2, rank}}`, where `r2` is that of the refitted
+    /// forest (see `FanovaOptions::drop_column_importances`).
+    ///
+    /// This takes one fit per parameter (and `r2` of all the parameters is `r2` of
+    /// `get_fit_quality`). Parameters important in `get_importances` but not here have effects
+    /// which the other parameters can substitute (e.g., correlated parameters). This fails for
+    /// the `"extra_trees"` surrogate, which has no out-of-bag trials.
+    pub fn get_drop_column_importances(&mut self) -> Result<JsValue, JsValue> {
+        let importances = self.drop_column_importances()?;
+        Ok(to_js(&importances)?)
+    }
+
+    fn drop_column_importances(&mut self) -> Result<BTreeMap<String, DropColumnEntry>, Error> {
+        if self.table.features_len() < 2 {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "two or more parameters are required",
+            ));
+        }
+        Phase::Fit.enter();
+        let result = self
+            .options
+            .fanova()
+            .drop_column_importances(self.table.clone(), None)?;
+        let mut entries = self
+            .param_names
+            .iter()
+            .cloned()
+            .zip(result.importances().into_iter().zip(&result.dropped))
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| b.1 .0.total_cmp(&a.1 .0));
+        Ok(entries
+            .into_iter()
+            .enumerate()
+            .map(|(i, (name, (importance, dropped)))| {
+                let entry = DropColumnEntry {
+                    importance,
+                    r2: dropped.r2,
+                    rank: i + 1,
+                };
+                (name, entry)
+            })
+            .collect())
+    }
+
+    /// Returns the pairwise importances as an object like `{names, matrix}`.
+    ///
+    /// `matrix[i][j]` is the importance of the interaction between the `i`-th and `j`-th
+    /// parameters and `matrix[i][i]` is the importance of the `i`-th parameter itself.
+    pub fn get_pairwise_importance(&mut self) -> Result<JsValue, JsValue> {
+        let pairwise = self.pairwise_importance()?;
+        Ok(to_value(&pairwise).map_err(Error::from)?)
+    }
+
+    fn pairwise_importance(&mut self) -> Result<PairwiseImportance, Error> {
+        let features_len = self.table.features_len();
+        let fanova = self.fanova()?;
+        Phase::Importance.enter();
+        let matrix = (0..features_len)
+            .map(|i| {
+                // The importances are cached, so each pair is computed only once.
+                let feature_sets = (0..features_len).map(|j| pair(i, j)).collect::<Vec<_>>();
+                let row = fanova
+                    .quantify_importances(&feature_sets)
+                    .into_iter()
+                    .map(|importance| importance.mean)
+                    .collect();
+                Progress::new(Phase::Importance, i + 1, features_len).report();
+                row
+            })
+            .collect();
+        Ok(PairwiseImportance {
+            names: self.param_names.clone(),
+            matrix,
+        })
+    }
+
+    /// Starts computing the pairwise importances in time slices (see
+    /// `run_pairwise_importance`), which keeps the main thread responsive without threads.
+    ///
+    /// The result is the same as `get_pairwise_importance`. A computation previously started is
+    /// discarded.
+    pub fn start_pairwise_importance(&mut self) -> Result<(), JsValue> {
+        Ok(self.start_pairwise_job()?)
+    }
+
+    fn start_pairwise_job(&mut self) -> Result<(), Error> {
+        self.fanova()?;
+        self.pairwise_job = Some(PairwiseJob::new(self.table.features_len()));
+        Ok(())
+    }
+
+    /// Proceeds the computation started by `start_pairwise_importance` for about
+    /// `budget_millis` milliseconds and returns `true` if it has finished (see `run_marginals`).
+    pub fn run_pairwise_importance(&mut self, budget_millis: f64) -> Result<bool, JsValue> {
+        Ok(self.run_pairwise_job(budget_millis)?)
+    }
+
+    fn run_pairwise_job(&mut self, budget_millis: f64) -> Result<bool, Error> {
+        let Some(job) = &mut self.pairwise_job else {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "no computation has been started",
+            ));
+        };
+        Phase::Importance.enter();
+        let fanova = &mut self.fanova;
+        let finished = run_slice(budget_millis, || job.step(fanova))?;
+        let (done, total) = job.progress();
+        Progress::new(Phase::Importance, done, total).report();
+        Ok(finished)
+    }
+
+    /// Returns the pairwise importances computed by `run_pairwise_importance` as an object like
+    /// `get_pairwise_importance`.
+    ///
+    /// Fails if the computation has not finished.
+    pub fn take_pairwise_importance(&mut self) -> Result<JsValue, JsValue> {
+        let pairwise = self.take_pairwise_job()?;
+        Ok(to_value(&pairwise).map_err(Error::from)?)
+    }
+
+    fn take_pairwise_job(&mut self) -> Result<PairwiseImportance, Error> {
+        match self.pairwise_job.take() {
+            Some(job) if job.is_finished() => Ok(PairwiseImportance {
+                names: self.param_names.clone(),
+                matrix: job.matrix(),
+            }),
+            job => {
+                self.pairwise_job = job;
+                Err(Error::new(
+                    ErrorCode::InvalidInput,
+                    "the computation has not finished",
+                ))
+            }
+        }
+    }
+
+    /// Returns the interactions of the pairs of the `top_k` most important parameters as an
+    /// object like `{pairs: [{params, importance, std}], skipped: [{params, upper_bound}]}`.
+    ///
+    /// Unlike `get_pairwise_importance`, this doesn't compute all the pairs, which takes long in
+    /// studies having many parameters. The pairs whose `upper_bound`s of the importances are less
+    /// than `min_importance` are also skipped (see `Fanova::top_interactions`). `params` are the
+    /// names of the pair and `pairs` are sorted by `importance` in descending order.
+    pub fn get_top_interactions(
+        &mut self,
+        top_k: usize,
+        min_importance: f64,
+    ) -> Result<JsValue, JsValue> {
+        let interactions = self.top_interactions(top_k, min_importance)?;
+        Ok(to_value(&interactions).map_err(Error::from)?)
+    }
+
+    fn top_interactions(
+        &mut self,
+        top_k: usize,
+        min_importance: f64,
+    ) -> Result<TopInteractions, Error> {
+        if min_importance.is_nan() {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "the minimum importance must be a number",
+            ));
+        }
+        self.fanova()?;
+        Phase::Importance.enter();
+        let search = self.fanova.top_interactions(top_k, min_importance);
+        let names =
+            |(a, b): (usize, usize)| [self.param_names[a].clone(), self.param_names[b].clone()];
+        Ok(TopInteractions {
+            pairs: search
+                .pairs
+                .into_iter()
+                .map(|(pair, importance)| InteractionEntry {
+                    params: names(pair),
+                    importance: importance.mean,
+                    std: importance.stddev,
+                })
+                .collect(),
+            skipped: search
+                .skipped
+                .into_iter()
+                .map(|(pair, upper_bound)| SkippedPair {
+                    params: names(pair),
+                    upper_bound,
+                })
+                .collect(),
+        })
+    }
+
+    /// Returns the interactions of the parameters as a graph like `{nodes, edges}` (e.g., for a
+    /// force-directed network visualization).
+    ///
+    /// `nodes[i]` is `{id: i, name, importance}` of the `i`-th parameter, and `edges` are
+    /// `{source, target, weight}` of the pairs whose interaction importances (see
+    /// `get_pairwise_importance`) are greater than or equal to `threshold`, where `source` and
+    /// `target` are the ids of the nodes. The edges are sorted by `weight` in descending order.
+    pub fn get_interaction_graph(&mut self, threshold: f64) -> Result<JsValue, JsValue> {
+        let graph = self.interaction_graph(threshold)?;
+        Ok(to_value(&graph).map_err(Error::from)?)
+    }
+
+    fn interaction_graph(&mut self, threshold: f64) -> Result<InteractionGraph, Error> {
+        if threshold.is_nan() {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "the threshold must be a number",
+            ));
+        }
+        let PairwiseImportance { names, matrix } = self.pairwise_importance()?;
+        let mut edges = Vec::new();
+        for (i, row) in matrix.iter().enumerate() {
+            for (j, &weight) in row.iter().enumerate().skip(i + 1) {
+                if weight >= threshold {
+                    edges.push(GraphEdge {
+                        source: i,
+                        target: j,
+                        weight,
+                    });
+                }
+            }
+        }
+        edges.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+        let nodes = names
+            .into_iter()
+            .enumerate()
+            .map(|(id, name)| GraphNode {
+                id,
+                name,
+                importance: matrix[id][id],
+            })
+            .collect();
+        Ok(InteractionGraph { nodes, edges })
+    }
+
+    /// Returns the SHAP values of the trials as an object like
+    /// `{names, expected_value, values, ranking}`.
+    ///
+    /// `values[t][i]` is the SHAP value of the `i`-th parameter of the `t`-th trial, and the
+    /// values of a trial sum up to its prediction minus `expected_value` (the mean prediction
+    /// over the search space). `ranking` ranks the parameters by the mean absolute SHAP values
+    /// like `wasm_fanova_importances` (`std` is the standard deviation across the trials).
+    pub fn get_shap_values(&mut self) -> Result<JsValue, JsValue> {
+        let shap = self.shap_values()?;
+        Ok(to_js(&shap)?)
+    }
+
+    fn shap_values(&mut self) -> Result<ShapValues, Error> {
+        let features_len = self.table.features_len();
+        self.fanova()?;
+        Phase::Importance.enter();
+        let fanova = &self.fanova;
+        let mut rows = vec![Vec::with_capacity(features_len); self.table.rows_len()];
+        for i in 0..features_len {
+            for (row, x) in rows.iter_mut().zip(self.table.column(i)) {
+                row.push(x);
+            }
+        }
+        let values = rows
+            .iter()
+            .map(|row| fanova.shap_values(row))
+            .collect::<Vec<_>>();
+
+        let ranking =
+            ImportanceEntry::ranked(self.param_names.iter().enumerate().map(|(i, name)| {
+                let (mean, stddev) = functions::mean_and_stddev(values.iter().map(|v| v[i].abs()));
+                (name.clone(), Importance { mean, stddev })
+            }));
+        Ok(ShapValues {
+            names: self.param_names.clone(),
+            expected_value: fanova.expected_value(),
+            values,
+            ranking,
+        })
+    }
+
+    /// Returns the Sobol indices of the parameters as an object like
+    /// `{<name>: {first_order, total}}`.
+    ///
+    /// The indices are estimated from `n_samples` quasi-Monte Carlo samples of the forest over
+    /// the search space (see `Fanova::sobol_indices`), so they can be compared with the
+    /// importances.
+    pub fn get_sobol_indices(&mut self, n_samples: usize) -> Result<JsValue, JsValue> {
+        let indices = self.sobol_indices(n_samples)?;
+        Ok(to_js(&indices)?)
+    }
+
+    fn sobol_indices(&mut self, n_samples: usize) -> Result<BTreeMap<String, SobolEntry>, Error> {
+        let samples = NonZeroUsize::new(n_samples).ok_or_else(|| {
+            Error::new(
+                ErrorCode::InvalidInput,
+                "the number of samples must be positive",
+            )
+        })?;
+        let fanova = self.fanova()?;
+        Phase::Importance.enter();
+        let indices = fanova.sobol_indices(samples);
+        Ok(self
+            .param_names
+            .iter()
+            .cloned()
+            .zip(indices)
+            .map(|(name, s)| {
+                let entry = SobolEntry {
+                    first_order: s.first_order,
+                    total: s.total,
+                };
+                (name, entry)
+            })
+            .collect())
+    }
+
+    /// Returns Friedman's H-statistics of the parameters as an object like
+    /// `{names, matrix, total}`.
+    ///
+    /// `matrix[i][j]` is the H-statistic of the pair of the `i`-th and `j`-th parameters (zero
+    /// on the diagonal), and `total[i]` is the total H-statistic of the `i`-th parameter
+    /// estimated from `n_samples` quasi-Monte Carlo samples (see `Fanova::total_h_statistics`).
+    /// Both range from `0.0` (no interaction) to `1.0`.
+    pub fn get_h_statistics(&mut self, n_samples: usize) -> Result<JsValue, JsValue> {
+        let statistics = self.h_statistics(n_samples)?;
+        Ok(to_value(&statistics).map_err(Error::from)?)
+    }
+
+    fn h_statistics(&mut self, n_samples: usize) -> Result<HStatistics, Error> {
+        let samples = NonZeroUsize::new(n_samples).ok_or_else(|| {
+            Error::new(
+                ErrorCode::InvalidInput,
+                "the number of samples must be positive",
+            )
+        })?;
+        let features_len = self.table.features_len();
+        let fanova = self.fanova()?;
+        Phase::Importance.enter();
+        let matrix = (0..features_len)
+            .map(|i| {
+                let row = (0..features_len)
+                    .map(|j| fanova.h_statistic(i, j))
+                    .collect();
+                Progress::new(Phase::Importance, i + 1, features_len).report();
+                row
+            })
+            .collect();
+        let total = fanova.total_h_statistics(samples);
+        Ok(HStatistics {
+            names: self.param_names.clone(),
+            matrix,
+            total,
+        })
+    }
+
+    /// Returns the Morris elementary effects of the parameters as an object like
+    /// `{<name>: {mu, mu_star, sigma, rank}}`.
+    ///
+    /// The effects are computed from `n_trajectories` trajectories over the search space (see
+    /// `Fanova::elementary_effects`), and the parameters are ranked by `mu_star` in descending
+    /// order. This is a cheap screening for studies with many parameters.
+    pub fn get_elementary_effects(&mut self, n_trajectories: usize) -> Result<JsValue, JsValue> {
+        let effects = self.elementary_effects(n_trajectories)?;
+        Ok(to_js(&effects)?)
+    }
+
+    fn elementary_effects(
+        &mut self,
+        n_trajectories: usize,
+    ) -> Result<BTreeMap<String, EffectsEntry>, Error> {
+        let trajectories = NonZeroUsize::new(n_trajectories).ok_or_else(|| {
+            Error::new(
+                ErrorCode::InvalidInput,
+                "the number of trajectories must be positive",
+            )
+        })?;
+        let options = MorrisOptions::new()
+            .trajectories(trajectories)
+            .seed(self.options.seed);
+        let fanova = self.fanova()?;
+        Phase::Importance.enter();
+        let effects = fanova.elementary_effects(&options);
+        let mut effects = self
+            .param_names
+            .iter()
+            .cloned()
+            .zip(effects)
+            .collect::<Vec<_>>();
+        effects.sort_by(|a, b| b.1.mu_star.total_cmp(&a.1.mu_star));
+        Ok(effects
+            .into_iter()
+            .enumerate()
+            .map(|(i, (name, e))| {
+                let entry = EffectsEntry {
+                    mu: e.mu,
+                    mu_star: e.mu_star,
+                    sigma: e.sigma,
+                    rank: i + 1,
+                };
+                (name, entry)
+            })
+            .collect())
+    }
+
+    /// Returns the suggested value of each parameter as an object like
+    /// `{<name>: {value, choice, prediction}}`.
+    ///
+    /// `value` (in the original space) minimizes the marginal prediction of the parameter (or
+    /// maximizes it if `maximize` is `true`), and `prediction` is the marginal prediction at
+    /// `value`. The values of integer and categorical parameters are rounded, and `choice` is
+    /// the suggested choice of a categorical parameter (if its distribution is known).
+    pub fn get_suggestions(&mut self, maximize: bool) -> Result<JsValue, JsValue> {
+        let suggestions = self.suggestions(maximize)?;
+        Ok(to_js(&suggestions)?)
+    }
+
+    fn suggestions(&mut self, maximize: bool) -> Result<BTreeMap<String, Suggestion>, Error> {
+        self.fanova()?;
+        Phase::Importance.enter();
+        let fanova = &self.fanova;
+        Ok((0..self.table.features_len())
+            .map(|i| {
+                let (range, prediction) = fanova.marginal_optimum(i, maximize);
+                let mut x = (range.start + range.end) / 2.0;
+                if self.table.column_type(i).is_discrete() {
+                    x = x.round();
+                }
+                let mut value = self.table.inverse_transform(i, x);
+                let mut choice = None;
+                match &self.distributions[i] {
+                    Some(Distribution::Int { low, step, .. })
+                    | Some(Distribution::Float {
+                        low,
+                        step: Some(step),
+                        ..
+                    }) => value = low + ((value - low) / step).round() * step,
+                    Some(Distribution::Categorical { choices }) => {
+                        choice = choices.get(value as usize).cloned();
+                    }
+                    _ => {}
+                }
+                let suggestion = Suggestion {
+                    value,
+                    choice,
+                    prediction,
+                };
+                (self.param_names[i].clone(), suggestion)
+            })
+            .collect())
+    }
+
+    /// Returns the local parameter importances (LPI) at `config` as an object like
+    /// `{<name>: {importance, global, rank}}`.
+    ///
+    /// `importance` is the normalized variance of the predictions when varying the parameter
+    /// over `n_points` evenly spaced values with the others fixed at `config` (in the original
+    /// space), and `global` is the fANOVA importance of the parameter. The parameters are
+    /// ranked by `importance` in descending order. If `config` is omitted, the best trial is
+    /// used.
+    pub fn get_local_importances(
+        &mut self,
+        config: Option<Vec<f64>>,
+        n_points: usize,
+        maximize: bool,
+    ) -> Result<JsValue, JsValue> {
+        let importances = self.local_importances(config.as_deref(), n_points, maximize)?;
+        Ok(to_js(&importances)?)
+    }
+
+    fn local_importances(
+        &mut self,
+        config: Option<&[f64]>,
+        n_points: usize,
+        maximize: bool,
+    ) -> Result<BTreeMap<String, LocalImportanceEntry>, Error> {
+        if n_points < 2 {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "the number of points must be two or more",
+            ));
+        }
+        let point = match config {
+            Some(config) => self.encode_config(config)?,
+            None => self.best_trial(maximize)?,
+        };
+        let features_len = self.table.features_len();
+        let fanova = self.fanova()?;
+        Phase::Importance.enter();
+        let local = fanova.local_importances(&point, n_points);
+        let global = (0..features_len)
+            .map(|i| fanova.quantify_importance(&[i]).mean)
+            .collect::<Vec<_>>();
+
+        let mut order = (0..features_len).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| local[b].total_cmp(&local[a]));
+        Ok(order
+            .into_iter()
+            .enumerate()
+            .map(|(rank, i)| {
+                let entry = LocalImportanceEntry {
+                    importance: local[i],
+                    global: global[i],
+                    rank: rank + 1,
+                };
+                (self.param_names[i].clone(), entry)
+            })
+            .collect())
+    }
+
+    /// Returns the sensitivities of the marginal predictions at `config` (in the original space)
+    /// as an object like `{<name>: {slope, scaled, direction}}`.
+    ///
+    /// `slope` is the finite-difference slope of the marginal prediction of each parameter
+    /// between its value `± step` of its range (see `Fanova::marginal_sensitivities`), per unit
+    /// of the encoded parameter (e.g., per `ln` unit for log-scale parameters). `scaled` is
+    /// `slope` multiplied by the width of the range, which is comparable across the parameters.
+    /// `direction` tells how to nudge the parameter to improve the prediction (`"increase"`,
+    /// `"decrease"` or `"none"`) where larger predictions are better if `maximize` is `true`.
+    /// Categorical parameters are omitted. If `config` is omitted, the best trial is used.
+    pub fn get_sensitivities(
+        &mut self,
+        config: Option<Vec<f64>>,
+        step: f64,
+        maximize: bool,
+    ) -> Result<JsValue, JsValue> {
+        let sensitivities = self.sensitivities(config.as_deref(), step, maximize)?;
+        Ok(to_js(&sensitivities)?)
+    }
+
+    fn sensitivities(
+        &mut self,
+        config: Option<&[f64]>,
+        step: f64,
+        maximize: bool,
+    ) -> Result<BTreeMap<String, SensitivityEntry>, Error> {
+        if !(step > 0.0 && step <= 1.0) {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "the step must be in the range (0, 1]",
+            ));
+        }
+        let point = match config {
+            Some(config) => self.encode_config(config)?,
+            None => self.best_trial(maximize)?,
+        };
+        self.fanova()?;
+        let fanova = &self.fanova;
+        let slopes = fanova.marginal_sensitivities(&point, step);
+        let ranges = fanova.feature_space().ranges();
+        Ok(slopes
+            .into_iter()
+            .zip(ranges)
+            .enumerate()
+            .filter(|&(i, _)| {
+                !matches!(
+                    self.distributions[i],
+                    Some(Distribution::Categorical { .. })
+                )
+            })
+            .map(|(i, (slope, r))| {
+                let improvement = if maximize { slope } else { -slope };
+                let direction = if improvement > 0.0 {
+                    "increase"
+                } else if improvement < 0.0 {
+                    "decrease"
+                } else {
+                    "none"
+                };
+                let entry = SensitivityEntry {
+                    slope,
+                    scaled: slope * (r.end - r.start),
+                    direction,
+                };
+                (self.param_names[i].clone(), entry)
+            })
+            .collect())
+    }
+
+    /// Returns the prediction at `config` (in the original space) as an object like
+    /// `{mean, std}`.
+    ///
+    /// `config` is encoded in the same way as the trials (e.g., the log scale and the indices of
+    /// categorical choices), and the values outside the search space are clamped into it.
+    /// `std` is the standard deviation of the predictions across the trees, which can be shown
+    /// as the uncertainty of the prediction.
+    pub fn predict(&mut self, config: Vec<f64>) -> Result<JsValue, JsValue> {
+        let prediction = self.predictions(&[config])?.remove(0);
+        Ok(to_value(&prediction).map_err(Error::from)?)
+    }
+
+    /// Batch version of `predict`.
+    ///
+    /// `configs` is an array of configurations and the result is an array of `{mean, std}`.
+    pub fn predict_batch(&mut self, configs: JsValue) -> Result<JsValue, JsValue> {
+        let configs: Vec<Vec<f64>> = from_value(configs).map_err(Error::from)?;
+        let predictions = self.predictions(&configs)?;
+        Ok(to_value(&predictions).map_err(Error::from)?)
+    }
+
+    /// Returns the predictions of every tree at `configs` (an array of configurations like
+    /// `predict_batch`) as a `Float64Array` of `configs.length * trees` values.
+    ///
+    /// The predictions of the `i`-th configuration are at `[i * trees, (i + 1) * trees)`, where
+    /// `trees` is the number of the fitted trees (see `get_fit_quality`), e.g., to draw the
+    /// distribution of the predictions or how their running mean converges.
+    pub fn predict_per_tree(&mut self, configs: JsValue) -> Result<Float64Array, JsValue> {
+        let configs: Vec<Vec<f64>> = from_value(configs).map_err(Error::from)?;
+        let predictions = self.tree_prediction_matrix(&configs)?;
+        Ok(Float64Array::from(&predictions[..]))
+    }
+
+    fn tree_prediction_matrix(&mut self, configs: &[Vec<f64>]) -> Result<Vec<f64>, Error> {
+        let points = configs
+            .iter()
+            .map(|config| self.encode_config(config))
+            .collect::<Result<Vec<_>, _>>()?;
+        let fanova = self.fanova()?;
+        Ok(points
+            .iter()
+            .flat_map(|point| fanova.predict_per_tree(point))
+            .collect())
+    }
+
+    fn predictions(&mut self, configs: &[Vec<f64>]) -> Result<Vec<Prediction>, Error> {
+        let points = configs
+            .iter()
+            .map(|config| self.encode_config(config))
+            .collect::<Result<Vec<_>, _>>()?;
+        let fanova = self.fanova()?;
+        Ok(points
+            .iter()
+            .map(|point| {
+                let (mean, std) = fanova.predict_with_stddev(point);
+                Prediction { mean, std }
+            })
+            .collect())
+    }
+
+    // Converts a configuration in the original space into the encoded space.
+    fn encode_config(&self, config: &[f64]) -> Result<Vec<f64>, Error> {
+        if config.len() != self.table.features_len() || config.iter().any(|x| !x.is_finite()) {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "configurations must have a finite value for each parameter",
+            ));
+        }
+        Ok(config
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| self.table.apply_transforms(i, x))
+            .collect())
+    }
+
+    /// Returns the ablation path from `default` to `incumbent` as an object like
+    /// `{start, steps: [{param, value, prediction, improvement}]}`.
+    ///
+    /// The parameters are switched from the values of `default` to the ones of `incumbent`
+    /// (both in the original space) one by one, choosing the switch which improves the
+    /// prediction the most at each step (see `Fanova::ablation_path`). `start` is the
+    /// prediction at `default`. If `incumbent` is omitted, the best trial is used.
+    pub fn get_ablation_path(
+        &mut self,
+        default: Vec<f64>,
+        incumbent: Option<Vec<f64>>,
+        maximize: bool,
+    ) -> Result<JsValue, JsValue> {
+        let path = self.ablation_path(&default, incumbent.as_deref(), maximize)?;
+        Ok(to_value(&path).map_err(Error::from)?)
+    }
+
+    fn ablation_path(
+        &mut self,
+        default: &[f64],
+        incumbent: Option<&[f64]>,
+        maximize: bool,
+    ) -> Result<AblationPath, Error> {
+        let source = self.encode_config(default)?;
+        let target = match incumbent {
+            Some(incumbent) => self.encode_config(incumbent)?,
+            None => self.best_trial(maximize)?,
+        };
+        let fanova = self.fanova()?;
+        Phase::Importance.enter();
+        let steps = fanova.ablation_path(&source, &target, maximize);
+        let start = fanova.predict(&source);
+        let steps = steps
+            .into_iter()
+            .map(|s| AblationEntry {
+                param: self.param_names[s.feature].clone(),
+                value: self.table.inverse_transform(s.feature, target[s.feature]),
+                prediction: s.prediction,
+                improvement: s.improvement,
+            })
+            .collect();
+        Ok(AblationPath { start, steps })
+    }
+
+    // Returns the features of the trial having the best target (in the encoded space).
+    fn best_trial(&self, maximize: bool) -> Result<Vec<f64>, Error> {
+        let sign = if maximize { -1.0 } else { 1.0 };
+        let (best, _) = self
+            .table
+            .target()
+            .enumerate()
+            .min_by(|a, b| (sign * a.1).total_cmp(&(sign * b.1)))
+            .ok_or_else(|| Error::new(ErrorCode::InvalidInput, "no trials"))?;
+        Ok((0..self.table.features_len())
+            .map(|i| self.table.column(i).nth(best).expect("in range"))
+            .collect())
+    }
+
+    /// Returns the marginal curve of the given parameter as an object like
+    /// `{grid, mean, std, noise}`.
+    ///
+    /// `grid` consists of `n_points` evenly spaced values of the parameter, and `mean[i]` and
+    /// `std[i]` are the mean and the standard deviation (across trees) of the marginal
+    /// predictions at `grid[i]`. `noise[i]` is the standard deviation of the objective values
+    /// within the leaves, which separates noisy regions from the unexplored ones (where `std`
+    /// is large instead); fit with a larger `minSamplesLeaf` to estimate it.
+    pub fn get_marginal(&mut self, param: usize, n_points: usize) -> Result<JsValue, JsValue> {
+        let marginal = self.marginal(param, n_points)?;
+        Ok(to_value(&marginal).map_err(Error::from)?)
+    }
+
+    fn marginal(&mut self, param: usize, n_points: usize) -> Result<Marginal, Error> {
+        self.fanova()?;
+        let grid = self.grid(param, n_points)?;
+        let mut marginal = Marginal::with_capacity(self.original_values(param, &grid));
+        for &x in &grid {
+            marginal.push(self.fanova.marginal_uncertainty(param, x));
+        }
+        Ok(marginal)
+    }
+
+    /// Starts computing the marginal curves of the given parameters in time slices (see
+    /// `run_marginals`), which keeps the main thread responsive without threads.
+    ///
+    /// The curves are the same as `get_marginal`. A computation previously started is discarded.
+    pub fn start_marginals(&mut self, params: Vec<usize>, n_points: usize) -> Result<(), JsValue> {
+        Ok(self.start_marginal_job(params, n_points)?)
+    }
+
+    fn start_marginal_job(&mut self, params: Vec<usize>, n_points: usize) -> Result<(), Error> {
+        self.fanova()?;
+        let grids = params
+            .iter()
+            .map(|&param| self.grid(param, n_points))
+            .collect::<Result<Vec<_>, _>>()?;
+        let marginals = params
+            .iter()
+            .zip(&grids)
+            .map(|(&param, grid)| Marginal::with_capacity(self.original_values(param, grid)))
+            .collect();
+        self.marginal_job = Some(MarginalJob {
+            params,
+            grids,
+            marginals,
+        });
+        Ok(())
+    }
+
+    /// Proceeds the computation started by `start_marginals` for about `budget_millis`
+    /// milliseconds (e.g., `16` to keep up with the frame rate) and returns `true` if it has
+    /// finished.
+    ///
+    /// The intermediate results are kept in this evaluator between the calls, so the
+    /// computation is resumed exactly where it yielded (trials added in the meantime are not
+    /// used until the next computation).
+    pub fn run_marginals(&mut self, budget_millis: f64) -> Result<bool, JsValue> {
+        Ok(self.run_marginal_job(budget_millis)?)
+    }
+
+    fn run_marginal_job(&mut self, budget_millis: f64) -> Result<bool, Error> {
+        let Some(job) = &mut self.marginal_job else {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "no computation has been started",
+            ));
+        };
+        Phase::Importance.enter();
+        let fanova = &self.fanova;
+        let finished = run_slice(budget_millis, || job.step(fanova))?;
+        let (done, total) = job.progress();
+        Progress::new(Phase::Importance, done, total).report();
+        Ok(finished)
+    }
+
+    /// Returns the marginal curves computed by `run_marginals` as an array of objects like
+    /// `get_marginal` (in the order of the parameters given to `start_marginals`).
+    ///
+    /// Fails if the computation has not finished.
+    pub fn take_marginals(&mut self) -> Result<JsValue, JsValue> {
+        let marginals = self.take_marginal_job()?;
+        Ok(to_value(&marginals).map_err(Error::from)?)
+    }
+
+    fn take_marginal_job(&mut self) -> Result<Vec<Marginal>, Error> {
+        match self.marginal_job.take() {
+            Some(job) if job.is_finished() => Ok(job.marginals),
+            job => {
+                self.marginal_job = job;
+                Err(Error::new(
+                    ErrorCode::InvalidInput,
+                    "the computation has not finished",
+                ))
+            }
+        }
+    }
+
+    /// Returns the marginal curves of the given parameters as a CSV string (e.g., to import them
+    /// into spreadsheets).
+    ///
+    /// The columns are `param,value,mean,std,noise` and each parameter has `n_points` rows like
+    /// `get_marginal` (the values are in the original space).
+    pub fn get_marginal_csv(
+        &mut self,
+        params: Vec<usize>,
+        n_points: usize,
+    ) -> Result<String, JsValue> {
+        Ok(self.marginal_csv(&params, n_points)?)
+    }
+
+    fn marginal_csv(&mut self, params: &[usize], n_points: usize) -> Result<String, Error> {
+        let mut csv = Csv::new(&["param", "value", "mean", "std", "noise"]);
+        for &param in params {
+            let marginal = self.marginal(param, n_points)?;
+            for i in 0..marginal.grid.len() {
+                csv.row([
+                    Field::Text(&self.param_names[param]),
+                    Field::Number(marginal.grid[i]),
+                    Field::Number(marginal.mean[i]),
+                    Field::Number(marginal.std[i]),
+                    Field::Number(marginal.noise[i]),
+                ]);
+            }
+        }
+        Ok(csv.finish())
+    }
+
+    /// 2-D version of `get_marginal`.
+    ///
+    /// The result is an object like `{grid: [grid0, grid1], mean, std}` where `mean[i][j]` and
+    /// `std[i][j]` correspond to the point `(grid0[i], grid1[j])`.
+    pub fn get_marginal_2d(
+        &mut self,
+        param0: usize,
+        param1: usize,
+        n_points: usize,
+    ) -> Result<JsValue, JsValue> {
+        let marginal = self.marginal_2d(param0, param1, n_points)?;
+        Ok(to_value(&marginal).map_err(Error::from)?)
+    }
+
+    fn marginal_2d(
+        &mut self,
+        param0: usize,
+        param1: usize,
+        n_points: usize,
+    ) -> Result<Marginal2d, Error> {
+        if param0 == param1 {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "parameters must be different",
+            ));
+        }
+        self.fanova()?;
+        let grid = [self.grid(param0, n_points)?, self.grid(param1, n_points)?];
+        let fanova = &self.fanova;
+        let (mean, std) = grid[0]
+            .iter()
+            .map(|&x| {
+                grid[1]
+                    .iter()
+                    .map(|&y| {
+                        let predictions = fanova.marginal_predictions(&[param0, param1], &[x, y]);
+                        functions::mean_and_stddev(predictions.into_iter())
+                    })
+                    .unzip::<_, _, Vec<_>, Vec<_>>()
+            })
+            .unzip();
+        let grid = [
+            self.original_values(param0, &grid[0]),
+            self.original_values(param1, &grid[1]),
+        ];
+        Ok(Marginal2d { grid, mean, std })
+    }
+
+    /// Returns the partial dependence of the predictions on the given pair of parameters as a
+    /// matrix (an array of arrays).
+    ///
+    /// `matrix[i][j]` is the marginal prediction at `(grid_a[i], grid_b[j])` (in the original
+    /// space), which pairs with the interaction in `get_pairwise_importance` (see
+    /// `Fanova::partial_dependence_2d`).
+    pub fn get_partial_dependence_2d(
+        &mut self,
+        param_a: usize,
+        param_b: usize,
+        grid_a: Vec<f64>,
+        grid_b: Vec<f64>,
+    ) -> Result<JsValue, JsValue> {
+        let matrix = self.partial_dependence_2d(param_a, param_b, &grid_a, &grid_b)?;
+        Ok(to_value(&matrix).map_err(Error::from)?)
+    }
+
+    fn partial_dependence_2d(
+        &mut self,
+        param_a: usize,
+        param_b: usize,
+        grid_a: &[f64],
+        grid_b: &[f64],
+    ) -> Result<Vec<Vec<f64>>, Error> {
+        let features_len = self.table.features_len();
+        if param_a >= features_len || param_b >= features_len {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "parameter out of range",
+            ));
+        }
+        if param_a == param_b {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "parameters must be different",
+            ));
+        }
+        if grid_a.iter().chain(grid_b).any(|x| !x.is_finite()) {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "grid values must be finite",
+            ));
+        }
+        let encode = |param: usize, grid: &[f64]| {
+            grid.iter()
+                .map(|&x| self.table.apply_transforms(param, x))
+                .collect::<Vec<_>>()
+        };
+        let (grid_a, grid_b) = (encode(param_a, grid_a), encode(param_b, grid_b));
+        let fanova = self.fanova()?;
+        Phase::Importance.enter();
+        Ok(fanova.partial_dependence_2d(param_a, param_b, &grid_a, &grid_b))
+    }
+
+    /// Same as `get_marginal` but returns the result as `{descriptor, buffer}`.
+    ///
+    /// `buffer` is an `ArrayBuffer` of `f64` values which can be transferred by `postMessage`
+    /// without copying, and `descriptor` is an object like
+    /// `{names, fields: [{name, offset, shape}]}` describing where each field is stored
+    /// (`offset` is in elements and multi-dimensional fields are stored in row-major order).
+    pub fn get_marginal_buffer(
+        &mut self,
+        param: usize,
+        n_points: usize,
+    ) -> Result<JsValue, JsValue> {
+        Ok(Packed::from(self.marginal(param, n_points)?).into_js()?)
+    }
+
+    /// Same as `get_marginal_2d` but returns the result as `{descriptor, buffer}`
+    /// (see `get_marginal_buffer`).
+    pub fn get_marginal_2d_buffer(
+        &mut self,
+        param0: usize,
+        param1: usize,
+        n_points: usize,
+    ) -> Result<JsValue, JsValue> {
+        let marginal = self.marginal_2d(param0, param1, n_points)?;
+        Ok(Packed::from(marginal).into_js()?)
+    }
+
+    /// Evaluates the forest over a grid of one or two parameters and returns the predictions
+    /// as `{descriptor, buffer}` (see `get_marginal_buffer`).
+    ///
+    /// `options` is an object configuring the grid (see `SurfaceOptions`). The buffer has the
+    /// fields `grid0` (and `grid1`) holding the grid values in the original space, and `mean` and
+    /// `std` (across trees) of the predictions at each grid point (`[n0]` or `[n0, n1]`).
+    pub fn get_response_surface(&mut self, options: JsValue) -> Result<JsValue, JsValue> {
+        let options: SurfaceOptions = from_value(options).map_err(Error::from)?;
+        Ok(self.response_surface(&options)?.into_js()?)
+    }
+
+    fn response_surface(&mut self, options: &SurfaceOptions) -> Result<Packed, Error> {
+        let params = &options.params;
+        let invalid = |message| Err(Error::new(ErrorCode::InvalidInput, message));
+        if params.is_empty() || params.len() > 2 || params.first() == params.get(1) {
+            return invalid("one or two different parameters must be given");
+        }
+        if params.iter().any(|&p| p >= self.table.features_len()) {
+            return invalid("parameter out of range");
+        }
+        let base = match &options.others {
+            OtherParams::Marginalize => None,
+            OtherParams::Incumbent => Some(self.best_trial(options.maximize)?),
+            OtherParams::Fixed(config) => Some(self.encode_config(config)?),
+        };
+        self.fanova()?;
+        let grids = match &options.grids {
+            Some(grids) => {
+                if grids.len() != params.len() || grids.iter().flatten().any(|x| !x.is_finite()) {
+                    return invalid("grids must have finite values for each parameter");
+                }
+                params
+                    .iter()
+                    .zip(grids)
+                    .map(|(&p, grid)| {
+                        let grid = grid.iter().map(|&x| self.table.apply_transforms(p, x));
+                        grid.collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>()
+            }
+            None => params
+                .iter()
+                .map(|&p| self.grid(p, options.n_points))
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+
+        let fanova = &self.fanova;
+        let ranges = fanova.feature_space().ranges();
+        let (features, mut point) = match base {
+            Some(base) => ((0..ranges.len()).collect::<Vec<_>>(), base),
+            None => (params.clone(), vec![0.0; params.len()]),
+        };
+        let positions = params
+            .iter()
+            .map(|p| features.iter().position(|f| f == p).expect("never fails"))
+            .collect::<Vec<_>>();
+        let mut evaluate = |xs: &[f64]| {
+            for (&i, &x) in positions.iter().zip(xs) {
+                point[i] = x;
+            }
+            let point = features
+                .iter()
+                .zip(&point)
+                .map(|(&f, &x)| x.clamp(ranges[f].start, ranges[f].end))
+                .collect::<Vec<_>>();
+            functions::mean_and_stddev(fanova.marginal_predictions(&features, &point).into_iter())
+        };
+        let (mean, std): (Vec<_>, Vec<_>) = match &grids[..] {
+            [grid] => grid.iter().map(|&x| evaluate(&[x])).unzip(),
+            [grid0, grid1] => grid0
+                .iter()
+                .flat_map(|&x| grid1.iter().map(move |&y| [x, y]))
+                .map(|xs| evaluate(&xs))
+                .unzip(),
+            _ => unreachable!(),
+        };
+
+        let mut packed = Packed {
+            names: params
+                .iter()
+                .map(|&p| self.param_names[p].clone())
+                .collect(),
+            ..Packed::default()
+        };
+        for (name, (&p, grid)) in ["grid0", "grid1"]
+            .into_iter()
+            .zip(params.iter().zip(&grids))
+        {
+            packed.push(name, vec![grid.len()], self.original_values(p, grid));
+        }
+        let shape = grids.iter().map(Vec::len).collect::<Vec<_>>();
+        packed.push("mean", shape.clone(), mean);
+        packed.push("std", shape, std);
+        Ok(packed)
+    }
+
+    /// Same as `get_pairwise_importance` but returns the result as `{descriptor, buffer}`
+    /// (see `get_marginal_buffer`).
+    pub fn get_pairwise_importance_buffer(&mut self) -> Result<JsValue, JsValue> {
+        Ok(Packed::from(self.pairwise_importance()?).into_js()?)
+    }
+
+    /// Returns the marginal predictions of each tree as `{descriptor, buffer}`
+    /// (see `get_marginal_buffer`).
+    ///
+    /// The buffer has the fields `grid` (`[n_points]`) and `predictions` (`[n_points, trees]`).
+    pub fn get_tree_predictions_buffer(
+        &mut self,
+        param: usize,
+        n_points: usize,
+    ) -> Result<JsValue, JsValue> {
+        Ok(self.tree_predictions(param, n_points)?.into_js()?)
+    }
+
+    fn tree_predictions(&mut self, param: usize, n_points: usize) -> Result<Packed, Error> {
+        self.fanova()?;
+        let grid = self.grid(param, n_points)?;
+        let fanova = &self.fanova;
+        let trees_len = fanova.trees_len();
+        let mut packed = Packed::default();
+        packed.push("grid", vec![grid.len()], self.original_values(param, &grid));
+        let predictions = grid
+            .iter()
+            .flat_map(|&x| fanova.marginal_predictions(&[param], &[x]));
+        packed.push("predictions", vec![grid.len(), trees_len], predictions);
+        Ok(packed)
+    }
+
+    // Note that `self.fanova()` needs to be called beforehand to reflect the pending trials.
+    fn grid(&self, param: usize, n_points: usize) -> Result<Vec<f64>, Error> {
+        if param >= self.table.features_len() {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "parameter out of range",
+            ));
+        }
+        if n_points < 2 {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "the number of points must be two or more",
+            ));
+        }
+        let range = &self.fanova.feature_space().ranges()[param];
+        Ok(functions::equal_width_edges(
+            range.start,
+            range.end,
+            n_points - 1,
+        ))
+    }
+
+    // Converts the values of the given parameter in the encoded space (e.g., log scale) into
+    // the original space.
+    fn original_values(&self, param: usize, values: &[f64]) -> Vec<f64> {
+        values
+            .iter()
+            .map(|&x| self.table.inverse_transform(param, x))
+            .collect()
+    }
+
+    /// Returns the quality of the fitted forest as an object like
+    /// `{r2, rmse, oob_rows, trees, partial, level, depth: {min, mean, max}, leaves: {min, mean, max},
+    /// target_bounds: {lower, upper}, target_standardization: {mean, std}, duplicated_rows}`.
+    ///
+    /// `r2` and `rmse` are computed from the out-of-bag predictions of the `oob_rows` trials (they
+    /// are `undefined` if no trials have out-of-bag predictions). `level` summarizes `r2` as
+    /// `"good"` (`0.7` or more), `"fair"` (`0.3` or more), `"poor"` or `"unknown"`, so that the
+    /// importances can be flagged as unreliable when the forest fails to explain the targets.
+    /// `trees` is the number of the fitted trees (see the `early_stopping` fit option), `partial`
+    /// tells whether the `time_budget_millis` fit option has cut the fitting short, and
+    /// `target_bounds` are the bounds the targets are clipped to by the `winsorize` fit option (it
+    /// is `undefined` if the option is not set). Likewise, `target_standardization` is given by
+    /// the `standardize_target` fit option, and `rmse` is then in its standard deviations.
+    /// `duplicated_rows` is the number of the trials having the same parameter values as earlier
+    /// trials, which are merged into them by the `aggregate_duplicates` fit option.
+    pub fn get_fit_quality(&mut self) -> Result<JsValue, JsValue> {
+        let quality = self.fit_quality()?;
+        Ok(to_value(&quality).map_err(Error::from)?)
+    }
+
+    fn fit_quality(&mut self) -> Result<FitQualitySummary, Error> {
+        let fanova = self.fanova()?;
+        let quality = fanova.fit_quality();
+        let level = match quality.map(|q| q.r2) {
+            None => "unknown",
+            Some(r2) if r2 >= 0.7 => "good",
+            Some(r2) if r2 >= 0.3 => "fair",
+            Some(_) => "poor",
+        };
+        Ok(FitQualitySummary {
+            r2: quality.map(|q| q.r2),
+            rmse: quality.map(|q| q.rmse),
+            oob_rows: quality.map_or(0, |q| q.rows),
+            trees: fanova.trees_len(),
+            partial: fanova.is_partial(),
+            level,
+            depth: Stats::new(fanova.tree_depths()),
+            leaves: Stats::new(fanova.tree_leaves()),
+            target_bounds: fanova
+                .target_bounds()
+                .map(|(lower, upper)| TargetBounds { lower, upper }),
+            target_standardization: fanova
+                .target_standardization()
+                .map(|(mean, std)| TargetStandardization { mean, std }),
+            duplicated_rows: fanova.duplicated_rows(),
+        })
+    }
+
+    /// Returns the elapsed times (in milliseconds) of the phases of fitting and querying the
+    /// model as `{encoding_millis, sorting_millis, tree_millis, marginals_millis}` (see `Profile`).
+    ///
+    /// `encoding_millis` is the time of preprocessing the trials (e.g., the `winsorize` fit
+    /// option) and encoding the categorical parameters, `sorting_millis` that of presorting them,
+    /// `tree_millis` the time of fitting each tree, and `marginals_millis` the accumulated time of
+    /// computing the marginal variances for the importances. The times are not exported by
+    /// `export_state()`, so an imported evaluator reports the times spent after the import.
+    pub fn get_profile(&mut self) -> Result<JsValue, JsValue> {
+        let profile = self.profile()?;
+        Ok(to_value(&profile).map_err(Error::from)?)
+    }
+
+    fn profile(&mut self) -> Result<ProfileSummary, Error> {
+        let profile = self.fanova()?.profile();
+        Ok(ProfileSummary {
+            encoding_millis: profile.encoding,
+            sorting_millis: profile.sorting,
+            tree_millis: profile.trees.clone(),
+            marginals_millis: profile.marginals,
+        })
+    }
+
+    /// Returns the parameters that the trees never split as an array of
+    /// `{param, reason, original}`.
+    ///
+    /// `reason` is `"constant"` if the parameter takes a single value across the trials or
+    /// `"duplicate"` if it has the same values as the earlier parameter named `original`. Their
+    /// importances are zero.
+    pub fn get_excluded_params(&mut self) -> Result<JsValue, JsValue> {
+        let excluded = self.excluded_params()?;
+        Ok(to_value(&excluded).map_err(Error::from)?)
+    }
+
+    fn excluded_params(&mut self) -> Result<Vec<ExcludedParam>, Error> {
+        self.fanova()?;
+        let fanova = &self.fanova;
+        Ok(fanova
+            .excluded_features()
+            .iter()
+            .map(|excluded| {
+                let (reason, original) = match *excluded {
+                    ExcludedFeature::Constant { .. } => ("constant", None),
+                    ExcludedFeature::Duplicate { original, .. } => ("duplicate", Some(original)),
+                };
+                ExcludedParam {
+                    param: self.param_names[excluded.feature()].clone(),
+                    reason,
+                    original: original.map(|i| self.param_names[i].clone()),
+                }
+            })
+            .collect())
+    }
+
+    /// Returns a JSON document summarizing the importance results (e.g., to archive them).
+    ///
+    /// The document is like `{schema_version, crate_version, study, trials, confidence, params,
+    /// pairwise, fit_quality, settings}`:
+    /// - `params[i]` is `{name, importance, std, lower, upper, total}` of the `i`-th parameter
+    ///   where `lower` and `upper` are the `confidence` percentile interval across the trees
+    ///   (see `Fanova::quantify_importance_interval`) and `total` is the total Sobol index
+    ///   estimated from `n_samples` samples (see `get_sobol_indices`).
+    /// - `pairwise` is the `matrix` of `get_pairwise_importance`.
+    /// - `fit_quality` is the result of `get_fit_quality`.
+    /// - `settings` is the fit options (which can be given to fit the evaluator again).
+    ///
+    /// `schema_version` is incremented whenever the meaning of a field changes, so that the
+    /// archived documents can be compared across versions. Non-finite values are `null`.
+    pub fn get_report_json(
+        &mut self,
+        confidence: f64,
+        n_samples: usize,
+    ) -> Result<String, JsValue> {
+        Ok(self.report(confidence, n_samples)?.to_json())
+    }
+
+    fn report(&mut self, confidence: f64, n_samples: usize) -> Result<ImportanceReport, Error> {
+        if !(0.0..=1.0).contains(&confidence) {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "the confidence must be in the range [0, 1]",
+            ));
+        }
+        let samples = NonZeroUsize::new(n_samples).ok_or_else(|| {
+            Error::new(
+                ErrorCode::InvalidInput,
+                "the number of samples must be positive",
+            )
+        })?;
+        let pairwise = self.pairwise_importance()?.matrix;
+        let fit_quality = self.fit_quality()?;
+        let names = self.param_names.clone();
+        let fanova = self.fanova()?;
+        let totals = fanova.sobol_indices(samples);
+        let params = names
+            .into_iter()
+            .zip(totals)
+            .enumerate()
+            .map(|(i, (name, sobol))| {
+                let importance = fanova.quantify_importance(&[i]);
+                let (lower, upper) = fanova.quantify_importance_interval(&[i], confidence);
+                ReportParam {
+                    name,
+                    importance: importance.mean,
+                    std: importance.stddev,
+                    lower,
+                    upper,
+                    total: sobol.total,
+                }
+            })
+            .collect();
+        Ok(ImportanceReport {
+            schema_version: REPORT_SCHEMA_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION"),
+            study: self.name.clone(),
+            trials: self.table.rows_len(),
+            confidence,
+            params,
+            pairwise,
+            fit_quality,
+            settings: self.options,
+        })
+    }
+
+    /// Distills the forest into a single shallow tree as an object like `{fidelity, root, dot}`.
+    ///
+    /// The tree of depth `max_depth` at most is fitted to the predictions of the forest at
+    /// `n_samples` points sampled uniformly from the search space (see `Fanova::distill`).
+    /// Each node of `root` is like `{param, threshold, value, samples, left, right}` where the
+    /// trials whose `param` is less than or equal to `threshold` (in the original space) go to
+    /// `left` (leaves have only `value` and `samples`). `dot` renders the tree in the Graphviz
+    /// DOT language, and `fidelity` is the R² of the tree with respect to the forest.
+    pub fn get_distilled_tree(
+        &mut self,
+        max_depth: usize,
+        n_samples: usize,
+    ) -> Result<JsValue, JsValue> {
+        let tree = self.distilled_tree(max_depth, n_samples)?;
+        Ok(to_value(&tree).map_err(Error::from)?)
+    }
+
+    fn distilled_tree(
+        &mut self,
+        max_depth: usize,
+        n_samples: usize,
+    ) -> Result<DistilledTree, Error> {
+        let (Some(max_depth), Some(samples)) =
+            (NonZeroUsize::new(max_depth), NonZeroUsize::new(n_samples))
+        else {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "the depth and the number of samples must be positive",
+            ));
+        };
+        let options = DistillOptions::new()
+            .max_depth(max_depth)
+            .samples(samples)
+            .seed(self.options.seed);
+        let mut tree = self.fanova()?.distill(&options);
+        tree.root
+            .map_thresholds(&mut |i, x| self.table.inverse_transform(i, x));
+        let names = self.param_names.iter().map(|s| &s[..]).collect::<Vec<_>>();
+        Ok(DistilledTree {
+            fidelity: tree.fidelity,
+            root: DistilledNode::new(&tree.root, &self.param_names),
+            dot: tree.to_dot(&names),
+        })
+    }
+
+    /// Returns the `tree_index`-th tree of the forest as a JSON string like `{tree, root}` (e.g.,
+    /// for interactive tree views).
+    ///
+    /// Each node of `root` is like `{param, threshold, value, fraction, gain, leaves, left,
+    /// right}` where the trials whose `param` is less than or equal to `threshold` (in the
+    /// original space) go to `left` (leaves have only `value`, `fraction` and `leaves`). `value`
+    /// is the mean prediction over the node, `fraction` is the fraction of the search space
+    /// covered by the node and `gain` is the fraction of the variance of the tree explained by
+    /// the split (see `Fanova::tree_structure`). The nodes deeper than `max_depth` (if given)
+    /// are merged into leaves, whose `leaves` tells the number of the merged leaves.
+    pub fn get_tree_json(
+        &mut self,
+        tree_index: usize,
+        max_depth: Option<usize>,
+    ) -> Result<String, JsValue> {
+        let tree = self.exported_tree(tree_index, max_depth)?;
+        Ok(serde_json::to_string(&tree).expect("never fails"))
+    }
+
+    fn exported_tree(
+        &mut self,
+        tree_index: usize,
+        max_depth: Option<usize>,
+    ) -> Result<ExportedTree, Error> {
+        let fanova = self.fanova()?;
+        if tree_index >= fanova.trees_len() {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                format!(
+                    "the tree index {tree_index} is out of range (the forest has {} trees)",
+                    fanova.trees_len()
+                ),
+            ));
+        }
+        let mut root = fanova.tree_structure(tree_index, max_depth);
+        root.map(&mut |i, x| self.table.inverse_transform(i, x), &mut |y| y);
+        Ok(ExportedTree {
+            tree: tree_index,
+            root: ExportedNode::new(&root, &self.param_names),
+        })
+    }
+
+    /// Returns the thresholds the forest split each parameter at as an object like
+    /// `{<name>: [{threshold, count}]}` (e.g., as the bin edges of histograms).
+    ///
+    /// The thresholds are in the original space and in ascending order, and `count` is the
+    /// number of the splits at each threshold across the trees (see `Fanova::split_thresholds`).
+    /// The parameters never split have empty arrays.
+    pub fn get_split_thresholds(&mut self) -> Result<JsValue, JsValue> {
+        let thresholds = self.split_thresholds()?;
+        Ok(to_js(&thresholds)?)
+    }
+
+    fn split_thresholds(&mut self) -> Result<BTreeMap<String, Vec<ThresholdEntry>>, Error> {
+        let thresholds = self.fanova()?.split_thresholds();
+        Ok(self
+            .param_names
+            .iter()
+            .cloned()
+            .zip(thresholds)
+            .enumerate()
+            .map(|(i, (name, thresholds))| {
+                let entries = thresholds
+                    .into_iter()
+                    .map(|(threshold, count)| ThresholdEntry {
+                        threshold: self.table.inverse_transform(i, threshold),
+                        count,
+                    })
+                    .collect();
+                (name, entries)
+            })
+            .collect())
+    }
+
+    /// Returns how often the forest splits each parameter as an object like
+    /// `{<name>: {importance, std, rank}}` (see `Fanova::split_frequencies`).
+    ///
+    /// This is a cheap alternative to the importances, which weights the splits by their depths
+    /// instead of the variances they explain, so the parameters ranked differently by both are
+    /// split often without changing the predictions much (or vice versa).
+    pub fn get_split_frequencies(&mut self) -> Result<JsValue, JsValue> {
+        let frequencies = self.split_frequencies()?;
+        Ok(to_js(&frequencies)?)
+    }
+
+    fn split_frequencies(&mut self) -> Result<BTreeMap<String, ImportanceEntry>, Error> {
+        let frequencies = self.fanova()?.split_frequencies();
+        Ok(ImportanceEntry::ranked(
+            self.param_names.iter().cloned().zip(frequencies),
+        ))
+    }
+
+    /// Returns the memory footprint of this evaluator (see also `memory_usage()`).
+    pub fn memory_usage(&self) -> Result<JsValue, JsValue> {
+        let usage = MemoryUsage::new(self.table.heap_size(), &self.fanova);
+        Ok(to_value(&usage).map_err(Error::from)?)
+    }
+
+    /// Clears the importance cache.
+    pub fn clear(&mut self) {
+        self.fanova.clear();
+    }
+
+    /// Exports the trials, the fitted model and its metadata (e.g., to cache it in IndexedDB).
+    ///
+    /// The importance cache is not included.
+    pub fn export_state(&self) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        encoder.bytes(STATE_MAGIC);
+        encoder.bytes(self.name.as_bytes());
+        self.options.encode(&mut encoder);
+        encoder.usize(self.param_names.len());
+        self.param_names
+            .iter()
+            .for_each(|name| encoder.bytes(name.as_bytes()));
+        let distributions = serde_json::to_vec(&self.distributions).expect("never fails");
+        encoder.bytes(&distributions);
+        encoder.usize(self.table.features_len());
+        encoder.usize(self.table.rows_len());
+        // The values are stored in the original space and encoded again on import.
+        for i in 0..self.table.features_len() {
+            self.table
+                .column(i)
+                .for_each(|x| encoder.f64(self.table.inverse_transform(i, x)));
+        }
+        self.table.target().for_each(|x| encoder.f64(x));
+        for i in 0..self.table.features_len() {
+            encoder.u8(column_type_code(self.table.column_type(i)));
+        }
+        encoder.usize(self.pending_trials);
+        encoder.u64(self.refits);
+        encoder.usize(self.non_finite_trials);
+        self.fanova.encode(&mut encoder);
+        encoder.finish()
+    }
+
+    /// Same as `export_state()` but compresses the bytes with zlib (deflate).
+    ///
+    /// This is slower but the bytes of large studies get typically several times smaller.
+    pub fn export_state_compressed(&self) -> Vec<u8> {
+        codec::compress(&self.export_state())
+    }
+
+    /// Restores an evaluator from the bytes returned by `export_state()` or
+    /// `export_state_compressed()`.
+    pub fn import_state(bytes: &[u8]) -> Result<Evaluator, JsValue> {
+        Ok(Self::decode(bytes)
+            .ok_or_else(|| Error::new(ErrorCode::InvalidInput, "invalid evaluator state"))?)
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let bytes = codec::decompress(bytes)?;
+        let mut decoder = Decoder::new(&bytes);
+        if decoder.bytes()? != STATE_MAGIC {
+            return None;
+        }
+        let name = String::from_utf8(decoder.bytes()?.to_owned()).ok()?;
+        let options = FitOptions::decode(&mut decoder)?;
+        let param_names = (0..decoder.usize()?)
+            .map(|_| String::from_utf8(decoder.bytes()?.to_owned()).ok())
+            .collect::<Option<Vec<_>>>()?;
+        let distributions: Vec<Option<Distribution>> =
+            serde_json::from_slice(decoder.bytes()?).ok()?;
+        let features_len = decoder.usize()?;
+        if param_names.len() != features_len || distributions.len() != features_len {
+            return None;
+        }
+        let rows_len = decoder.usize()?;
+        let columns = (0..=features_len)
+            .map(|_| (0..rows_len).map(|_| decoder.f64()).collect())
+            .collect::<Option<Vec<Vec<f64>>>>()?;
+        let mut table = Table::new(columns.iter().map(|c| &c[..]).collect())
+            .ok()?
+            .into_owned();
+        encode_table(&mut table, &distributions).ok()?;
+        for i in 0..features_len {
+            let column_type = match decoder.u8()? {
+                0 => ColumnType::Continuous,
+                1 => ColumnType::Integer,
+                2 => ColumnType::Boolean,
+                3 => ColumnType::Categorical,
+                _ => return None,
+            };
+            table.set_column_type(i, column_type).ok()?;
+        }
+        let pending_trials = decoder.usize()?;
+        let refits = decoder.u64()?;
+        let non_finite_trials = decoder.usize()?;
+        let fanova = Fanova::decode(&mut decoder)?;
+        if !decoder.is_empty() {
+            return None;
+        }
+        Some(Self {
+            name,
+            options,
+            param_names,
+            distributions,
+            table,
+            fanova,
+            pending_trials,
+            refits,
+            non_finite_trials,
+            marginal_job: None,
+            pairwise_job: None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct Marginal {
+    grid: Vec<f64>,
+    mean: Vec<f64>,
+    std: Vec<f64>,
+    noise: Vec<f64>,
+}
+
+impl Marginal {
+    // Returns an empty curve to be filled at `grid` by `push`.
+    fn with_capacity(grid: Vec<f64>) -> Self {
+        let n = grid.len();
+        Self {
+            grid,
+            mean: Vec::with_capacity(n),
+            std: Vec::with_capacity(n),
+            noise: Vec::with_capacity(n),
+        }
+    }
+
+    fn push(&mut self, point: MarginalUncertainty) {
+        self.mean.push(point.mean);
+        self.std.push(point.epistemic);
+        self.noise.push(point.aleatoric);
+    }
+}
+
+/// Marginal curves computed point by point (see `Evaluator::start_marginals`).
+#[derive(Debug)]
+struct MarginalJob {
+    params: Vec<usize>,
+
+    // The grid of each parameter in the encoded space.
+    grids: Vec<Vec<f64>>,
+
+    // The curves whose `mean`, `std` and `noise` are filled so far.
+    marginals: Vec<Marginal>,
+}
+
+impl MarginalJob {
+    // Computes the next point and returns `false` if no point is left.
+    fn step(&mut self, fanova: &Fanova) -> bool {
+        let next = self
+            .marginals
+            .iter()
+            .zip(&self.grids)
+            .position(|(m, grid)| m.mean.len() < grid.len());
+        let Some(i) = next else {
+            return false;
+        };
+        let marginal = &mut self.marginals[i];
+        let x = self.grids[i][marginal.mean.len()];
+        marginal.push(fanova.marginal_uncertainty(self.params[i], x));
+        !self.is_finished()
+    }
+
+    fn progress(&self) -> (usize, usize) {
+        let done = self.marginals.iter().map(|m| m.mean.len()).sum();
+        (done, self.grids.iter().map(Vec::len).sum())
+    }
+
+    fn is_finished(&self) -> bool {
+        let (done, total) = self.progress();
+        done == total
+    }
+}
+
+// Returns the feature set of the `(i, j)` entry of the pairwise importance matrix.
+fn pair(i: usize, j: usize) -> Vec<usize> {
+    if i == j {
+        vec![i]
+    } else {
+        vec![i.min(j), i.max(j)]
+    }
+}
+
+/// Computation of the pairwise importances that yields between the chunks of feature sets.
+#[derive(Debug)]
+struct PairwiseJob {
+    // The individual features followed by the pairs (in the row-major order of the upper
+    // triangle of the matrix).
+    feature_sets: Vec<Vec<usize>>,
+    importances: Vec<f64>,
+    features_len: usize,
+}
+
+impl PairwiseJob {
+    fn new(features_len: usize) -> Self {
+        let pairs = (0..features_len).flat_map(|i| (i + 1..features_len).map(move |j| pair(i, j)));
+        let feature_sets = (0..features_len).map(|i| vec![i]).chain(pairs).collect();
+        Self {
+            feature_sets,
+            importances: Vec::new(),
+            features_len,
+        }
+    }
+
+    // Computes a chunk of the feature sets (one per thread) and returns `false` if no sets are
+    // left.
+    fn step(&mut self, fanova: &mut Fanova) -> bool {
+        let start = self.importances.len();
+        let end = (start + rayon::current_num_threads()).min(self.feature_sets.len());
+        let importances = fanova.quantify_importances(&self.feature_sets[start..end]);
+        self.importances
+            .extend(importances.into_iter().map(|importance| importance.mean));
+        !self.is_finished()
+    }
+
+    fn progress(&self) -> (usize, usize) {
+        (self.importances.len(), self.feature_sets.len())
+    }
+
+    fn is_finished(&self) -> bool {
+        self.importances.len() == self.feature_sets.len()
+    }
+
+    fn matrix(&self) -> Vec<Vec<f64>> {
+        let index = self
+            .feature_sets
+            .iter()
+            .zip(&self.importances)
+            .collect::<BTreeMap<_, _>>();
+        (0..self.features_len)
+            .map(|i| {
+                (0..self.features_len)
+                    .map(|j| *index[&pair(i, j)])
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Fields packed into a single buffer (see `Evaluator::get_marginal_buffer`).
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Packed {
+    names: Vec<String>,
+    fields: Vec<PackedField>,
+    data: Vec<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct PackedField {
+    name: &'static str,
+    offset: usize,
+    shape: Vec<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct PackedDescriptor<'a> {
+    names: &'a [String],
+    fields: &'a [PackedField],
+}
+
+impl Packed {
+    fn push(
+        &mut self,
+        name: &'static str,
+        shape: Vec<usize>,
+        values: impl IntoIterator<Item = f64>,
+    ) {
+        let offset = self.data.len();
+        self.data.extend(values);
+        debug_assert_eq!(self.data.len() - offset, shape.iter().product::<usize>());
+        self.fields.push(PackedField {
+            name,
+            offset,
+            shape,
+        });
+    }
+
+    fn into_js(self) -> Result<JsValue, Error> {
+        let descriptor = PackedDescriptor {
+            names: &self.names,
+            fields: &self.fields,
+        };
+        let result = js_sys::Object::new();
+        let _ = Reflect::set(&result, &"descriptor".into(), &to_value(&descriptor)?);
+        let buffer = Float64Array::from(&self.data[..]).buffer();
+        let _ = Reflect::set(&result, &"buffer".into(), &buffer);
+        Ok(result.into())
+    }
+}
+
+impl From<Marginal> for Packed {
+    fn from(marginal: Marginal) -> Self {
+        let n = marginal.grid.len();
+        let mut packed = Self::default();
+        packed.push("grid", vec![n], marginal.grid);
+        packed.push("mean", vec![n], marginal.mean);
+        packed.push("std", vec![n], marginal.std);
+        packed.push("noise", vec![n], marginal.noise);
+        packed
+    }
+}
+
+impl From<Marginal2d> for Packed {
+    fn from(marginal: Marginal2d) -> Self {
+        let [grid0, grid1] = marginal.grid;
+        let shape = vec![grid0.len(), grid1.len()];
+        let mut packed = Self::default();
+        packed.push("grid0", vec![grid0.len()], grid0);
+        packed.push("grid1", vec![grid1.len()], grid1);
+        packed.push("mean", shape.clone(), marginal.mean.into_iter().flatten());
+        packed.push("std", shape, marginal.std.into_iter().flatten());
+        packed
+    }
+}
+
+impl From<PairwiseImportance> for Packed {
+    fn from(pairwise: PairwiseImportance) -> Self {
+        let n = pairwise.matrix.len();
+        let mut packed = Self {
+            names: pairwise.names,
+            ..Self::default()
+        };
+        packed.push("matrix", vec![n, n], pairwise.matrix.into_iter().flatten());
+        packed
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct Marginal2d {
+    grid: [Vec<f64>; 2],
+    mean: Vec<Vec<f64>>,
+    std: Vec<Vec<f64>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct PairwiseImportance {
+    names: Vec<String>,
+    matrix: Vec<Vec<f64>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct TopInteractions {
+    pairs: Vec<InteractionEntry>,
+    skipped: Vec<SkippedPair>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct InteractionEntry {
+    params: [String; 2],
+    importance: f64,
+    std: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct SkippedPair {
+    params: [String; 2],
+    upper_bound: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct InteractionGraph {
+    nodes: Vec<GraphNode>,
+    edges: Vec<GraphEdge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct GraphNode {
+    id: usize,
+    name: String,
+    importance: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct GraphEdge {
+    source: usize,
+    target: usize,
+    weight: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct HStatistics {
+    names: Vec<String>,
+    matrix: Vec<Vec<f64>>,
+    total: Vec<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct ShapValues {
+    names: Vec<String>,
+    expected_value: f64,
+    values: Vec<Vec<f64>>,
+    ranking: BTreeMap<String, ImportanceEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct FoldEntry {
+    r2: f64,
+    rmse: f64,
+    rows: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct DropColumnEntry {
+    importance: f64,
+    r2: f64,
+    rank: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct CrossValidationSummary {
+    r2: f64,
+    rmse: f64,
+    rows: usize,
+    folds: Vec<FoldEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct ExcludedParam {
+    param: String,
+    reason: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    original: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct ConvergenceEntry {
+    importance: Vec<f64>,
+    std: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct SignificanceEntry {
+    importance: f64,
+    null_mean: f64,
+    null_std: f64,
+    p_value: f64,
+    z_score: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct SobolEntry {
+    first_order: f64,
+    total: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct EffectsEntry {
+    mu: f64,
+    mu_star: f64,
+    sigma: f64,
+    rank: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct Suggestion {
+    value: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    choice: Option<serde_json::Value>,
+    prediction: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct LocalImportanceEntry {
+    importance: f64,
+    global: f64,
+    rank: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct ProfileSummary {
+    encoding_millis: f64,
+    sorting_millis: f64,
+    tree_millis: Vec<f64>,
+    marginals_millis: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct FitQualitySummary {
+    r2: Option<f64>,
+    rmse: Option<f64>,
+    oob_rows: usize,
+    trees: usize,
+    partial: bool,
+    level: &'static str,
+    depth: Stats,
+    leaves: Stats,
+    target_bounds: Option<TargetBounds>,
+    target_standardization: Option<TargetStandardization>,
+    duplicated_rows: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct TargetBounds {
+    lower: f64,
+    upper: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct TargetStandardization {
+    mean: f64,
+    std: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct Stats {
+    min: usize,
+    mean: f64,
+    max: usize,
+}
+
+impl Stats {
+    fn new(xs: impl Iterator<Item = usize> + Clone) -> Self {
+        Self {
+            min: xs.clone().min().unwrap_or(0),
+            mean: functions::mean(xs.clone().map(|x| x as f64)),
+            max: xs.max().unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct AblationPath {
+    start: f64,
+    steps: Vec<AblationEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct AblationEntry {
+    param: String,
+    value: f64,
+    prediction: f64,
+    improvement: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct ImportanceReport {
+    schema_version: u32,
+    crate_version: &'static str,
+    study: String,
+    trials: usize,
+    confidence: f64,
+    params: Vec<ReportParam>,
+    pairwise: Vec<Vec<f64>>,
+    fit_quality: FitQualitySummary,
+    settings: FitOptions,
+}
+
+impl ImportanceReport {
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("never fails")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct ReportParam {
+    name: String,
+    importance: f64,
+    std: f64,
+    lower: f64,
+    upper: f64,
+    total: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct DistilledTree {
+    fidelity: f64,
+    root: DistilledNode,
+    dot: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct DistilledNode {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    param: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    threshold: Option<f64>,
+    value: f64,
+    samples: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    left: Option<Box<DistilledNode>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    right: Option<Box<DistilledNode>>,
+}
+
+impl DistilledNode {
+    fn new(node: &ExplanatoryNode, names: &[String]) -> Self {
+        match node {
+            ExplanatoryNode::Leaf { value, samples } => Self {
+                param: None,
+                threshold: None,
+                value: *value,
+                samples: *samples,
+                left: None,
+                right: None,
+            },
+            ExplanatoryNode::Split {
+                feature,
+                threshold,
+                value,
+                samples,
+                left,
+                right,
+            } => Self {
+                param: Some(names[*feature].clone()),
+                threshold: Some(*threshold),
+                value: *value,
+                samples: *samples,
+                left: Some(Box::new(Self::new(left, names))),
+                right: Some(Box::new(Self::new(right, names))),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct ThresholdEntry {
+    threshold: f64,
+    count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct ExportedTree {
+    tree: usize,
+    root: ExportedNode,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct ExportedNode {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    param: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    threshold: Option<f64>,
+    value: f64,
+    fraction: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gain: Option<f64>,
+    leaves: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    left: Option<Box<ExportedNode>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    right: Option<Box<ExportedNode>>,
+}
+
+impl ExportedNode {
+    fn new(node: &TreeNode, names: &[String]) -> Self {
+        match node {
+            TreeNode::Leaf {
+                value,
+                fraction,
+                leaves,
+            } => Self {
+                param: None,
+                threshold: None,
+                value: *value,
+                fraction: *fraction,
+                gain: None,
+                leaves: *leaves,
+                left: None,
+                right: None,
+            },
+            TreeNode::Split {
+                feature,
+                threshold,
+                value,
+                fraction,
+                gain,
+                left,
+                right,
+            } => {
+                let left = Self::new(left, names);
+                let right = Self::new(right, names);
+                Self {
+                    param: Some(names[*feature].clone()),
+                    threshold: Some(*threshold),
+                    value: *value,
+                    fraction: *fraction,
+                    gain: Some(*gain),
+                    leaves: left.leaves + right.leaves,
+                    left: Some(Box::new(left)),
+                    right: Some(Box::new(right)),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct SensitivityEntry {
+    slope: f64,
+    scaled: f64,
+    direction: &'static str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct Prediction {
+    mean: f64,
+    std: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct ImportanceValue {
+    importance: f64,
+    std: f64,
+}
+
+/// A token to cancel asynchronous computations.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Rc<Cell<bool>>,
+}
+
+#[wasm_bindgen]
+impl CancellationToken {
+    /// Makes a new token.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests the cancellation of the computations associated with this token.
+    pub fn cancel(&self) {
+        self.cancelled.set(true);
+    }
+
+    /// Returns `true` if `cancel()` has been called.
+    #[wasm_bindgen(getter, js_name = isCancelled)]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.get()
+    }
+}
+
+/// Trials fed in chunks to build an `Evaluator` (for studies too large to be passed at once).
+///
+/// At most `capacity` trials are kept in the wasm heap: once more trials are fed, a uniform
+/// random sample of all the trials is kept (see `TableStream`).
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct TrialStream {
+    stream: TableStream,
+}
+
+#[wasm_bindgen]
+impl TrialStream {
+    /// Makes an empty stream of trials having `n_params` parameters.
+    ///
+    /// `seed` is used to sample the kept trials (default: `0`).
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        n_params: usize,
+        capacity: usize,
+        seed: Option<u64>,
+    ) -> Result<TrialStream, JsValue> {
+        let capacity = NonZeroUsize::new(capacity)
+            .ok_or_else(|| Error::new(ErrorCode::InvalidInput, "the capacity must be positive"))?;
+        let stream = TableStream::new(n_params, capacity, seed.unwrap_or(0));
+        Ok(Self { stream })
+    }
+
+    /// Feeds a chunk of trials given like the arguments of the `Evaluator` constructor.
+    pub fn push_chunk(&mut self, features: Array, targets: Array) -> Result<(), JsValue> {
+        let features = features
+            .iter()
+            .map(|c| Values::parse(c).map(|c| c.widen()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let targets = parse_targets(targets)?.widen();
+        self.push_columns(&features, &targets)?;
+        Ok(())
+    }
+
+    fn push_columns(&mut self, features: &[Vec<f64>], targets: &[f64]) -> Result<(), Error> {
+        let features = features.iter().map(|c| c.as_slice()).collect::<Vec<_>>();
+        Ok(self.stream.push_chunk(&features, targets)?)
+    }
+
+    /// Returns the number of the fed trials.
+    #[wasm_bindgen(getter)]
+    pub fn trials_seen(&self) -> usize {
+        self.stream.rows_seen()
+    }
+
+    /// Returns the number of the kept trials.
+    #[wasm_bindgen(getter)]
+    pub fn trials_len(&self) -> usize {
+        self.stream.rows_len()
+    }
+
+    /// Fits an `Evaluator` for the kept trials (see the `Evaluator` constructor for `options`).
+    ///
+    /// The kept trials are released, so this stream can't be used afterwards.
+    pub fn into_evaluator(self, name: String, options: JsValue) -> Result<Evaluator, JsValue> {
+        let options = FitOptions::parse(options)?;
+        Ok(self.evaluator(name, options)?)
+    }
+
+    fn evaluator(self, name: String, options: FitOptions) -> Result<Evaluator, Error> {
+        let (table, non_finite_trials) = options.table(self.stream.columns())?;
+        let mut evaluator = Evaluator::from_table(name, table.into_owned(), options)?;
+        evaluator.non_finite_trials = non_finite_trials;
+        Ok(evaluator)
+    }
+}
+
+/// Fitting of an `Evaluator` run in time slices (see `run`).
+///
+/// Unlike `fit_async`, the caller decides when and how long each slice runs (e.g., in
+/// `requestAnimationFrame` callbacks), which keeps the main thread responsive without threads.
+/// Note that the trees are fitted sequentially even if the `threads` feature is enabled.
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct FitJob {
+    name: String,
+    options: FitOptions,
+    table: Table<'static>,
+    non_finite_trials: usize,
+    fitter: Option<FanovaFitter<'static>>,
+    fanova: Option<Fanova>,
+}
+
+#[wasm_bindgen]
+impl FitJob {
+    /// Prepares fitting a model for the given features and targets (see the `Evaluator`
+    /// constructor). No tree is fitted until `run` is called.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        name: String,
+        features: Array,
+        targets: Array,
+        options: JsValue,
+    ) -> Result<FitJob, JsValue> {
+        let options = FitOptions::parse(options)?;
+        let (table, non_finite_trials) = parse_table(features, targets, &options)?;
+        let mut job = Self::from_table(name, table, options)?;
+        job.non_finite_trials = non_finite_trials;
+        Ok(job)
+    }
+
+    fn from_table(name: String, table: Table<'static>, options: FitOptions) -> Result<Self, Error> {
+        let table = check_table(table)?;
+        MemoryUsage::record_table(&table);
+        // A model fitted to the same dataset is finished at once (see `clear_cache`).
+        let (fitter, fanova) = match cache::model(cache::fingerprint(&table, &options)) {
+            Some(fanova) => (None, Some(fanova)),
+            None => (
+                Some(FanovaFitter::new(options.fanova(), table.clone())),
+                None,
+            ),
+        };
+        Ok(Self {
+            name,
+            options,
+            table,
+            non_finite_trials: 0,
+            fitter,
+            fanova,
+        })
+    }
+
+    /// Fits trees for about `budget_millis` milliseconds (e.g., `16` to keep up with the frame
+    /// rate) and returns `true` if the fitting has finished.
+    ///
+    /// At least one tree is fitted per call. The fitted trees are kept in this job between the
+    /// calls, so the fitting is resumed exactly where it yielded.
+    pub fn run(&mut self, budget_millis: f64) -> Result<bool, JsValue> {
+        Ok(self.run_slice(budget_millis)?)
+    }
+
+    fn run_slice(&mut self, budget_millis: f64) -> Result<bool, Error> {
+        Phase::Fit.enter();
+        run_slice(budget_millis, || {
+            let Some(fitter) = &mut self.fitter else {
+                return false;
+            };
+            fitter.step();
+            report_fit_progress(fitter);
+            let (done, total) = fitter.progress();
+            if done < total {
+                return true;
+            }
+            self.fanova = self.fitter.take().map(FanovaFitter::finish);
+            if let Some(fanova) = &self.fanova {
+                cache::store_model(cache::fingerprint(&self.table, &self.options), fanova);
+            }
+            false
+        })
+    }
+
+    /// Returns the progress of the fitting in `[0, 1]`.
+    #[wasm_bindgen(getter)]
+    pub fn progress(&self) -> f64 {
+        match &self.fitter {
+            Some(fitter) => {
+                let (done, total) = fitter.progress();
+                done as f64 / total.max(1) as f64
+            }
+            None => 1.0,
+        }
+    }
+
+    /// Returns the fitted `Evaluator`.
+    ///
+    /// Fails if the fitting has not finished (i.e., `run` has not returned `true`).
+    pub fn into_evaluator(self) -> Result<Evaluator, JsValue> {
+        Ok(self.evaluator()?)
+    }
+
+    fn evaluator(self) -> Result<Evaluator, Error> {
+        let Some(fanova) = self.fanova else {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "the fitting has not finished",
+            ));
+        };
+        let mut evaluator = Evaluator::from_fitted(self.name, self.table, self.options, fanova);
+        evaluator.non_finite_trials = self.non_finite_trials;
+        Ok(evaluator)
+    }
+}
+
+/// Drives an `Evaluator` by messages, so that the main thread can use it in a Web Worker only
+/// through `postMessage`.
+///
+/// The messages are objects tagged by `type` (see `Request` and `Response`). A worker script
+/// forwards its messages to `handle` and calls `run` until the queued requests are processed:
+///
+/// ```js
+/// const host = new MessageHost((response) => self.postMessage(response));
+/// const run = () => {
+///   if (!host.run(16)) setTimeout(run, 0);
+/// };
+/// self.onmessage = (e) => {
+///   host.handle(e.data);
+///   run();
+/// };
+/// ```
+///
+/// The requests are processed in order, and fitting is run in time slices (like `FitJob`), so
+/// the messages received meanwhile (e.g., `cancel`) are handled without waiting for it.
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct MessageHost {
+    post: Function,
+    state: HostState,
+}
+
+#[wasm_bindgen]
+impl MessageHost {
+    /// Creates a host which sends the responses to `post` (e.g., `self.postMessage`).
+    #[wasm_bindgen(constructor)]
+    pub fn new(post: Function) -> MessageHost {
+        Self {
+            post,
+            state: HostState::default(),
+        }
+    }
+
+    /// Queues the given request (a `cancel` request is handled immediately).
+    ///
+    /// An invalid request is answered by an `error` response.
+    pub fn handle(&mut self, message: JsValue) {
+        let responses = match from_value::<Request>(message) {
+            Ok(request) => self.state.handle(request),
+            Err(e) => vec![Response::error(None, e.into())],
+        };
+        self.post_all(responses);
+    }
+
+    /// Processes the queued requests for about `budget_millis` milliseconds and returns `true`
+    /// if no request is left.
+    pub fn run(&mut self, budget_millis: f64) -> Result<bool, JsValue> {
+        let (finished, responses) = self.state.run(budget_millis)?;
+        self.post_all(responses);
+        Ok(finished)
+    }
+
+    fn post_all(&self, responses: Vec<Response>) {
+        for response in responses {
+            if let Ok(response) = to_js(&response) {
+                let _ = self.post.call1(&JsValue::NULL, &response);
+            }
+        }
+    }
+}
+
+/// A message given to `MessageHost::handle`, like `{type: "fit", id: 1, features, targets}`.
+///
+/// `id` is echoed in the responses to the request.
+/// - `fit`: Fits a model (replacing the previous one) to `features` (an array of the columns of
+///   the parameters) and `targets` with the optional `options` (see `FitOptions`). It is
+///   answered by `progress` responses and a `fitted` response.
+/// - `quantify`: Computes the importance of the parameters `params` (jointly), which is answered
+///   by an `importance` response.
+/// - `marginal`: Computes the marginal curve of the parameter `param` at `n_points` points
+///   (default: `50`) like `Evaluator::get_marginal`, which is answered by a `marginal` response.
+/// - `cancel`: Discards the request `id` (e.g., a long fitting), which is answered by a
+///   `cancelled` response unless the request has already been processed.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request {
+    Fit {
+        id: u32,
+        features: Vec<Vec<f64>>,
+        targets: Vec<f64>,
+        #[serde(default)]
+        options: Box<FitOptions>,
+    },
+    Quantify {
+        id: u32,
+        params: Vec<usize>,
+    },
+    Marginal {
+        id: u32,
+        param: usize,
+        #[serde(default = "default_marginal_points")]
+        n_points: usize,
+    },
+    Cancel {
+        id: u32,
+    },
+}
+
+impl Request {
+    fn id(&self) -> u32 {
+        match *self {
+            Self::Fit { id, .. }
+            | Self::Quantify { id, .. }
+            | Self::Marginal { id, .. }
+            | Self::Cancel { id } => id,
+        }
+    }
+}
+
+fn default_marginal_points() -> usize {
+    50
+}
+
+/// A message sent by `MessageHost`, like `{type: "importance", id: 2, importance: 0.8, std: 0.1}`.
+///
+/// - `progress`: `{id, phase, done, total, percent}` of a fitting (see `on_progress`).
+/// - `fitted`: `{id, quality}` where `quality` is the same as `Evaluator::get_fit_quality`.
+/// - `importance`: `{id, importance, std}` like `Evaluator::quantify_importance`.
+/// - `marginal`: `{id, grid, mean, std, noise}` like `Evaluator::get_marginal`.
+/// - `cancelled`: `{id}`.
+/// - `error`: `{id, code, message}` where `code` is the same as the `code` of the errors thrown
+///   by the other functions (`id` is `null` if the request couldn't be parsed).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Response {
+    Progress {
+        id: u32,
+        #[serde(flatten)]
+        progress: Progress,
+    },
+    Fitted {
+        id: u32,
+        quality: FitQualitySummary,
+    },
+    Importance {
+        id: u32,
+        #[serde(flatten)]
+        value: ImportanceValue,
+    },
+    Marginal {
+        id: u32,
+        #[serde(flatten)]
+        marginal: Marginal,
+    },
+    Cancelled {
+        id: u32,
+    },
+    Error {
+        id: Option<u32>,
+        code: &'static str,
+        message: String,
+    },
+}
+
+impl Response {
+    fn error(id: Option<u32>, e: Error) -> Self {
+        Self::Error {
+            id,
+            code: e.code.as_str(),
+            message: e.message,
+        }
+    }
+}
+
+/// The requests and the model of a `MessageHost`.
+#[derive(Debug, Default)]
+struct HostState {
+    queue: VecDeque<Request>,
+    fitting: Option<(u32, FitJob)>,
+    evaluator: Option<Evaluator>,
+}
+
+impl HostState {
+    fn handle(&mut self, request: Request) -> Vec<Response> {
+        let Request::Cancel { id } = request else {
+            self.queue.push_back(request);
+            return Vec::new();
+        };
+        let queued = self.queue.len();
+        self.queue.retain(|r| r.id() != id);
+        let running = self.fitting.as_ref().is_some_and(|(i, _)| *i == id);
+        if running {
+            self.fitting = None;
+        }
+        if running || self.queue.len() < queued {
+            vec![Response::Cancelled { id }]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn run(&mut self, budget_millis: f64) -> Result<(bool, Vec<Response>), Error> {
+        let mut responses = Vec::new();
+        let finished = run_slice(budget_millis, || {
+            if let Some((id, job)) = &mut self.fitting {
+                let id = *id;
+                match job.run_slice(0.0) {
+                    Ok(false) => return true,
+                    Ok(true) => {}
+                    Err(e) => {
+                        responses.push(Response::error(Some(id), e));
+                        self.fitting = None;
+                        return true;
+                    }
+                }
+                let (_, job) = self.fitting.take().expect("running");
+                let fitted = job.evaluator().and_then(|mut evaluator| {
+                    let quality = evaluator.fit_quality()?;
+                    self.evaluator = Some(evaluator);
+                    Ok(quality)
+                });
+                responses.push(match fitted {
+                    Ok(quality) => Response::Fitted { id, quality },
+                    Err(e) => Response::error(Some(id), e),
+                });
+                return true;
+            }
+            let Some(request) = self.queue.pop_front() else {
+                return false;
+            };
+            let id = request.id();
+            if let Err(e) = self.process(request, &mut responses) {
+                responses.push(Response::error(Some(id), e));
+            }
+            true
+        })?;
+        if let Some((id, job)) = &self.fitting {
+            let (done, total) = job.fitter.as_ref().map_or((0, 0), FanovaFitter::progress);
+            responses.push(Response::Progress {
+                id: *id,
+                progress: Progress::new(Phase::Fit, done, total),
+            });
+        }
+        Ok((finished, responses))
+    }
+
+    fn evaluator(&mut self) -> Result<&mut Evaluator, Error> {
+        self.evaluator
+            .as_mut()
+            .ok_or_else(|| Error::new(ErrorCode::InvalidInput, "no model has been fitted"))
+    }
+
+    // Processes a request except for `cancel` (a fitting is only started).
+    fn process(&mut self, request: Request, responses: &mut Vec<Response>) -> Result<(), Error> {
+        match request {
+            Request::Fit {
+                id,
+                features,
+                targets,
+                options,
+            } => {
+                let options = options.check()?;
+                let features = features.into_iter().map(Values::F64).collect();
+                let (table, non_finite_trials) =
+                    table_from_values(features, Values::F64(targets), &options)?;
+                let mut job = FitJob::from_table(format!("request-{id}"), table, options)?;
+                job.non_finite_trials = non_finite_trials;
+                self.fitting = Some((id, job));
+            }
+            Request::Quantify { id, params } => {
+                let value = self.evaluator()?.quantify(&params)?;
+                responses.push(Response::Importance { id, value });
+            }
+            Request::Marginal {
+                id,
+                param,
+                n_points,
+            } => {
+                let marginal = self.evaluator()?.marginal(param, n_points)?;
+                responses.push(Response::Marginal { id, marginal });
+            }
+            Request::Cancel { .. } => unreachable!("handled on receipt"),
+        }
+        Ok(())
+    }
+}
+
+/// Asynchronous version of `wasm_fanova_calculate`.
+///
+/// The returned promise is resolved with the importances of the features.
+/// Computation yields to the event loop periodically so that the page is kept responsive.
+#[wasm_bindgen]
+pub fn fit_async(features: Array, targets: Array, options: JsValue) -> Promise {
+    start_async_job(features, targets, options, None)
+}
+
+/// Cancellable version of `fit_async`.
+///
+/// If `token` is cancelled, the promise is rejected with a `CANCELLED` error.
+#[wasm_bindgen]
+pub fn fit_async_with_token(
+    features: Array,
+    targets: Array,
+    token: &CancellationToken,
+    options: JsValue,
+) -> Promise {
+    start_async_job(features, targets, options, Some(token.clone()))
+}
+
+fn start_async_job(
+    features: Array,
+    targets: Array,
+    options: JsValue,
+    token: Option<CancellationToken>,
+) -> Promise {
+    let mut input = Some(FitOptions::parse(options).and_then(|options| {
+        let (table, _) = parse_table(features, targets, &options)?;
+        Ok((check_table(table)?, options))
+    }));
+    Promise::new(&mut move |resolve, reject| match input
+        .take()
+        .expect("the executor is called only once")
+    {
+        Ok((table, options)) => {
+            MemoryUsage::record_table(&table);
+            let options = options.fanova();
+            let job = AsyncJob {
+                features_len: table.features_len(),
+                fitter: Some(FanovaFitter::new(options, table)),
+                fanova: None,
+                importances: Vec::new(),
+                token: token.clone(),
+                resolve,
+                reject,
+            };
+            job.run();
+        }
+        Err(e) => {
+            let _ = reject.call1(&JsValue::NULL, &e.into());
+        }
+    })
+}
+
+/// State of a computation started by `fit_async`.
+struct AsyncJob {
+    features_len: usize,
+    fitter: Option<FanovaFitter<'static>>,
+    fanova: Option<Fanova>,
+    importances: Vec<f64>,
+    token: Option<CancellationToken>,
+    resolve: Function,
+    reject: Function,
+}
+
+impl AsyncJob {
+    fn run(mut self) {
+        let deadline = js_sys::Date::now() + TIME_SLICE_MILLIS;
+        loop {
+            if self.token.as_ref().is_some_and(|t| t.is_cancelled()) {
+                let e = Error::new(ErrorCode::Cancelled, "computation has been cancelled");
+                let _ = self.reject.call1(&JsValue::NULL, &e.into());
+                return;
+            }
+            if !self.step() {
+                if let Some(fanova) = &self.fanova {
+                    MemoryUsage::record_model(fanova);
+                }
+                let importances = self
+                    .importances
+                    .iter()
+                    .copied()
+                    .map(JsValue::from)
+                    .collect::<Array>();
+                let _ = self.resolve.call1(&JsValue::NULL, &importances);
+                return;
+            }
+            if js_sys::Date::now() >= deadline {
+                let next = Closure::once_into_js(move || self.run());
+                set_timeout(&next, 0);
+                return;
+            }
+        }
+    }
+
+    // Proceeds to fit a tree or to calculate the importance of a feature.
+    // Returns `false` if there is nothing to do.
+    fn step(&mut self) -> bool {
+        if let Some(fitter) = &mut self.fitter {
+            Phase::Fit.enter();
+            if fitter.step() {
+                report_fit_progress(fitter);
+            } else {
+                self.fanova = self.fitter.take().map(FanovaFitter::finish);
+            }
+            return true;
+        }
+
+        let feature = self.importances.len();
+        if feature == self.features_len {
+            return false;
+        }
+        let fanova = self.fanova.as_mut().expect("fitting has finished");
+        let importance = quantify_importance(fanova, feature, self.features_len);
+        self.importances.push(importance.mean);
+        true
+    }
+}
+
+fn fit(table: Table, options: &FitOptions) -> Result<Fanova, Error> {
+    fit_with_options(table, options.fanova())
+}
+
+// Same as `fit` but reuses the model fitted to the same dataset with the same options (see
+// `clear_cache`).
+fn fit_cached(table: Table, options: &FitOptions) -> Result<Fanova, Error> {
+    let fingerprint = cache::fingerprint(&table, options);
+    if let Some(fanova) = cache::model(fingerprint) {
+        return Ok(fanova);
+    }
+    let fanova = fit(table, options)?;
+    cache::store_model(fingerprint, &fanova);
+    Ok(fanova)
+}
+
+fn fit_with_options(table: Table, options: FanovaOptions) -> Result<Fanova, Error> {
+    let table = check_table(table)?;
+    MemoryUsage::record_table(&table);
+    Phase::Fit.enter();
+    if cfg!(feature = "threads") {
+        // Trees are fitted in parallel, so the fitting progress is not reported.
+        return Ok(options.fit_table(table)?);
+    }
+
+    let mut fitter = FanovaFitter::new(options, table);
+    while fitter.step() {
+        report_fit_progress(&fitter);
+    }
+    Ok(fitter.finish())
+}
+
+// Returns the code of a column type in the exported states and the fingerprints.
+fn column_type_code(column_type: ColumnType) -> u8 {
+    match column_type {
+        ColumnType::Continuous => 0,
+        ColumnType::Integer => 1,
+        ColumnType::Boolean => 2,
+        ColumnType::Categorical => 3,
+    }
+}
+
+// Encodes the feature columns according to their distributions (see `Distribution::encode`).
+fn encode_table(table: &mut Table, distributions: &[Option<Distribution>]) -> Result<(), Error> {
+    for (i, distribution) in distributions.iter().enumerate() {
+        if let Some(distribution) = distribution {
+            distribution.encode(table, i)?;
+        }
+    }
+    Ok(())
+}
+
+fn check_table(table: Table) -> Result<Table, Error> {
+    if (0..table.features_len()).all(|i| table.is_constant_column(i)) {
+        return Err(Error::new(
+            ErrorCode::ConstantFeatures,
+            "all features are constant",
+        ));
+    }
+    Ok(table)
+}
+
+fn report_fit_progress(fitter: &FanovaFitter) {
+    let (done, total) = fitter.progress();
+    Progress::new(Phase::Fit, done, total).report();
+}
+
+/// Options given from JavaScript like `{n_trees: 64, max_depth: 10, seed: 1}`.
+///
+/// All fields are optional:
+/// - `n_trees`: The number of trees (default: `100`).
+/// - `max_depth`: The maximum depth of each tree (default: `64`).
+/// - `max_features`: The number of candidate features at each split (default: `sqrt(features)`).
+/// - `min_samples_leaf`: The minimum number of rows at each leaf (default: `1`).
+/// - `max_bins`: Searches the splits only over this many quantile bins of each feature
+///   (default: all the distinct values are searched).
+/// - `max_thresholds_per_feature`: Searches at most this many quantile-spaced thresholds of each
+///   feature at each split, which bounds the cost for features having many distinct values
+///   (default: all the distinct values are searched). This is ignored if `max_bins` is given.
+/// - `split_sample`: Searches the splits of larger nodes with this many rows sampled uniformly
+///   at random (default: all the rows are used).
+/// - `seed`: The random seed (default: `0`).
+/// - `surrogate`: The surrogate model (`"random_forest"` (default) or `"extra_trees"`, see
+///   `Surrogate`). `max_bins`, `max_thresholds_per_feature` and `split_sample` are not supported
+///   by `"extra_trees"`.
+/// - `criterion`: The split criterion (`"squared_error"` (or `"mse"`, default), `"gain_ratio"`,
+///   `"poisson"` or `{tweedie: power}` with `power` of `1` or more, see `SplitCriterion`).
+///   `"gain_ratio"` is meant for categorical outcomes (see `wasm_fanova_calculate_categorical`),
+///   `"poisson"` for counts (e.g., the number of epochs until early stopping) and `"tweedie"`
+///   for skewed positive targets with zeros (e.g., GPU hours, with `power` in `(1, 2)`). The
+///   deviance criteria decompose the log mean of the targets.
+/// - `inactive_params`: How parameters missing in some trials of a study are handled
+///   (`"conditional"` (default) or `"intersection"`, see `InactiveParams`).
+/// - `non_finite_targets`: How trials whose targets are NaN, infinite or missing are handled
+///   (`"error"` (default), `"drop"`, `"mean"` or `"median"`, see `NonFiniteTargetPolicy`).
+///   This is not supported by the batch and multi-objective functions.
+/// - `infinities`: How infinite parameter and target values are handled before
+///   `non_finite_targets` (`"error"`, `"drop"` or `{clamp: q}` which replaces them with the `q`
+///   and `1 - q` quantiles of the finite values, see `InfinityPolicy`).
+///   By default, infinite targets are handled as specified by `non_finite_targets`.
+/// - `aggregate_duplicates`: Merges the trials having the identical parameter values (e.g., of
+///   grid searches) into one weighted trial before fitting, whose target is the `"mean"` or the
+///   `"median"` of theirs (see `FanovaOptions::aggregate_duplicates`). `get_fit_quality` returns
+///   the number of the duplicated trials either way (default: the trials are not merged).
+/// - `winsorize`: Clips the targets to the given lower and upper quantiles like `[0.01, 0.99]`
+///   before fitting, so that a few catastrophic trials don't dominate the importances (see
+///   `FanovaOptions::winsorize_target`). `get_fit_quality` returns the bounds (default: the
+///   targets are not clipped).
+/// - `standardize_target`: Standardizes the targets after `winsorize`, so that the predictions
+///   (e.g., the marginals and SHAP values) are in the standard deviations of the targets and
+///   comparable across studies (see `FanovaOptions::standardize_target`). The importances don't
+///   change. `get_fit_quality` returns the mean and the standard deviation (default: `false`).
+/// - `best_trials`: Fits only the best trials like `{count: 20}` or `{fraction: 0.1, maximize:
+///   true}` (the lowest targets are the best unless `maximize` is `true`), so the importances
+///   tell what differentiates the good trials from each other rather than from the bad ones (see
+///   `FanovaOptions::best_rows`). Unlike `winsorize`, the other trials are dropped (default: all
+///   the trials are fitted).
+/// - `early_stopping`: Stops adding trees once no importance changes by more than this tolerance
+///   in 10 trees (see `FanovaOptions::early_stopping`). `get_fit_quality` returns the number of
+///   the fitted trees (default: all `n_trees` trees are fitted).
+/// - `time_budget_millis`: Stops adding trees once this many milliseconds have elapsed since the
+///   fitting started (at least one tree is fitted), so that interactive callers get the
+///   importances within a bounded latency (see `FanovaOptions::time_budget`). `get_fit_quality`
+///   returns the number of the fitted trees and whether the budget has cut the fitting short
+///   (default: no budget).
+/// - `categorical_encoding`: How the categorical parameters of studies are encoded for the
+///   surrogate (`"ordinal"` (default), which splits the indices of the choices like integers,
+///   `"one_hot"`, which splits each choice from the others, `"target"`, which splits the
+///   choices by their out-of-fold target means and suits the parameters of many choices, or
+///   `"native"`, which splits the choices into arbitrary subsets, see `CategoricalEncoding`). Either way, the importances, marginals and so on are reported per categorical parameter.
+/// - `strict`: Fails with a `VALIDATION_FAILED` error if `wasm_fanova_validate` reports any
+///   issue in the given features and targets, even if it could be handled by the other options
+///   (default: `false`).
+/// - `preset`: Replaces the defaults of the random forest options by a named configuration
+///   (`"reference_fanova"`, which matches the defaults of the `fanova` Python package, i.e., 16
+///   trees searching all the features at each split, see `Preset::ReferenceFanova`). The options
+///   given explicitly (e.g., `n_trees`) still take precedence. This is not supported by
+///   `"extra_trees"` (default: no preset).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FitOptions {
+    n_trees: Option<NonZeroUsize>,
+    max_depth: Option<NonZeroUsize>,
+    max_features: Option<NonZeroUsize>,
+    min_samples_leaf: Option<NonZeroUsize>,
+    max_bins: Option<NonZeroUsize>,
+    max_thresholds_per_feature: Option<NonZeroUsize>,
+    split_sample: Option<NonZeroUsize>,
+    seed: u64,
+    surrogate: Surrogate,
+    criterion: Criterion,
+    inactive_params: InactiveParams,
+    non_finite_targets: NonFiniteTargetPolicy,
+    infinities: Option<InfinityPolicy>,
+    aggregate_duplicates: Option<DuplicateAggregation>,
+    winsorize: Option<[f64; 2]>,
+    standardize_target: bool,
+    best_trials: Option<BestTrials>,
+    early_stopping: Option<f64>,
+    time_budget_millis: Option<f64>,
+    categorical_encoding: Encoding,
+    strict: bool,
+    preset: Option<FitPreset>,
+}
+
+/// Selection of the best trials in `FitOptions` like `{count: 20}` or `{fraction: 0.1}`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BestTrials {
+    #[serde(default)]
+    count: Option<NonZeroUsize>,
+    #[serde(default)]
+    fraction: Option<f64>,
+    #[serde(default)]
+    maximize: bool,
+}
+
+/// Options of `Evaluator::get_response_surface` like `{params: [0, 1], n_points: 30}`.
+///
+/// - `params`: The indices of one or two parameters to be varied (required).
+/// - `grids`: The values of each parameter in the original space (default: `n_points` evenly
+///   spaced values over the range of each parameter).
+/// - `n_points`: The number of the grid values of each parameter (default: `50`).
+/// - `others`: How the other parameters are handled: `"marginalize"` (default) averages the
+///   predictions over them like `get_marginal`, `"incumbent"` fixes them at the best trial, and
+///   `{fixed: [...]}` fixes them at the given configuration (in the original space).
+/// - `maximize`: Whether the best trial has the largest target (default: `false`).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SurfaceOptions {
+    params: Vec<usize>,
+    #[serde(default)]
+    grids: Option<Vec<Vec<f64>>>,
+    #[serde(default = "SurfaceOptions::default_n_points")]
+    n_points: usize,
+    #[serde(default)]
+    others: OtherParams,
+    #[serde(default)]
+    maximize: bool,
+}
+
+impl SurfaceOptions {
+    fn default_n_points() -> usize {
+        50
+    }
+}
+
+/// Options of `Evaluator::get_restricted_importances` like `{domain: {lr: [0.001, 0.01]}}`.
+///
+/// - `domain`: The region of each restricted parameter (required): `[low, high]` in the
+///   original space (both ends are included), or `{choices: [...]}` with a subset of the choices
+///   of a categorical parameter. The other parameters are not restricted.
+/// - `refit`: Whether to fit a separate forest to the trials within the region (default:
+///   `false`, i.e., the forest fitted to all the trials is restricted).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RestrictionOptions {
+    domain: BTreeMap<String, ParamDomain>,
+    #[serde(default)]
+    refit: bool,
+}
+
+/// Region of a parameter in `RestrictionOptions`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+enum ParamDomain {
+    Range([f64; 2]),
+    Choices { choices: Vec<serde_json::Value> },
+}
+
+// Fails if the restricted ranges of a feature don't overlap the space of `fanova` (see
+// `Fanova::restrict`).
+fn check_domain(fanova: &Fanova, domain: &[Option<Vec<Range<f64>>>]) -> Result<(), Error> {
+    let ranges = fanova.feature_space().ranges();
+    let overlaps = ranges.iter().zip(domain).all(|(whole, ranges)| {
+        let Some(ranges) = ranges.as_ref().filter(|_| whole.end > whole.start) else {
+            return true;
+        };
+        ranges
+            .iter()
+            .any(|r| r.end.min(whole.end) > r.start.max(whole.start))
+    });
+    if overlaps {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorCode::InvalidInput,
+            "the domain must overlap the range of the trials of each parameter",
+        ))
+    }
+}
+
+/// Handling of the parameters not in the grid of a response surface.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum OtherParams {
+    #[default]
+    Marginalize,
+    Incumbent,
+    Fixed(Vec<f64>),
+}
+
+/// Handling of infinite values (e.g., diverged trials reporting `inf` losses).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum InfinityPolicy {
+    /// Fails with an `InvalidInput` error.
+    Error,
+
+    /// Ignores the trials.
+    Drop,
+
+    /// Clamps the values to the given quantiles (in `[0, 0.5]`) of the finite values.
+    Clamp(f64),
+}
+
+/// Handling of the trials whose targets are not finite (e.g., failed or diverged trials).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum NonFiniteTargetPolicy {
+    /// Fails with an `InvalidInput` error.
+    #[default]
+    Error,
+
+    /// Ignores the trials.
+    Drop,
+
+    /// Replaces the targets with the mean of the finite targets.
+    Mean,
+
+    /// Replaces the targets with the median of the finite targets.
+    Median,
+}
+
+/// Aggregation of the targets of the trials having the identical parameter values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum DuplicateAggregation {
+    /// The (weighted) mean of the targets.
+    Mean,
+
+    /// The median of the targets.
+    Median,
+}
+
+/// Handling of the parameters which are missing in some trials (i.e., dynamic search spaces).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum InactiveParams {
+    /// Uses the union of the parameters where the missing values are regarded as inactive.
+    ///
+    /// A parameter is used for splits only in the subsets of trials where it is active.
+    #[default]
+    Conditional,
+
+    /// Uses only the parameters which appear in all trials.
+    Intersection,
+}
+
+/// Encoding of the categorical parameters (see `CategoricalEncoding`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Encoding {
+    /// The indices of the choices are split like integers.
+    #[default]
+    Ordinal,
+
+    /// Each choice is split from the others.
+    OneHot,
+
+    /// The choices are split by their target means.
+    Target,
+
+    /// The choices are split into arbitrary subsets.
+    Native,
+}
+
+/// Named configuration of the random forest (see `Preset`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum FitPreset {
+    ReferenceFanova,
+}
+
+/// Surrogate model fitted to the trials.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Surrogate {
+    /// Random forest (see `RandomForestOptions`).
+    #[default]
+    RandomForest,
+
+    /// Extremely randomized trees (see `ExtraTreesOptions`).
+    ExtraTrees,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Criterion {
+    #[default]
+    #[serde(alias = "mse")]
+    SquaredError,
+    GainRatio,
+    Poisson,
+    Tweedie(f64),
+}
+
+impl Criterion {
+    fn split_criterion(self) -> SplitCriterion {
+        match self {
+            Self::SquaredError => SplitCriterion::SquaredError,
+            Self::GainRatio => SplitCriterion::GainRatio,
+            Self::Poisson => SplitCriterion::Poisson,
+            Self::Tweedie(power) => SplitCriterion::Tweedie { power },
+        }
+    }
+}
+
+impl FitOptions {
+    /// Parses an options object (`undefined` and `null` mean the default options).
+    fn parse(value: JsValue) -> Result<Self, Error> {
+        if value.is_undefined() || value.is_null() {
+            return Ok(Self::default());
+        }
+        from_value::<Self>(value)?.check()
+    }
+
+    // Checks the values which can't be expressed by the types.
+    fn check(self) -> Result<Self, Error> {
+        let options = self;
+        if options.surrogate == Surrogate::ExtraTrees
+            && (options.max_bins.is_some()
+                || options.max_thresholds_per_feature.is_some()
+                || options.split_sample.is_some())
+        {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "max_bins, max_thresholds_per_feature and split_sample are not supported by extra \
+                 trees",
+            ));
+        }
+        if options.surrogate == Surrogate::ExtraTrees && options.preset.is_some() {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "the presets are not supported by extra trees",
+            ));
+        }
+        if options
+            .winsorize
+            .is_some_and(|[lower, upper]| !(0.0 <= lower && lower <= upper && upper <= 1.0))
+        {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "the winsorizing quantiles must be in [0, 1] and in ascending order",
+            ));
+        }
+        if let Some(best) = options.best_trials {
+            let valid = match (best.count, best.fraction) {
+                (Some(_), None) => true,
+                (None, Some(fraction)) => fraction > 0.0 && fraction <= 1.0,
+                _ => false,
+            };
+            if !valid {
+                return Err(Error::new(
+                    ErrorCode::InvalidInput,
+                    "the best trials must be given by either a count or a fraction in (0, 1]",
+                ));
+            }
+        }
+        if options
+            .early_stopping
+            .is_some_and(|t| !(t.is_finite() && t >= 0.0))
+        {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "the early stopping tolerance must be non-negative",
+            ));
+        }
+        if options
+            .time_budget_millis
+            .is_some_and(|t| !(t.is_finite() && t >= 0.0))
+        {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "the time budget must be non-negative",
+            ));
+        }
+        if let Criterion::Tweedie(power) = options.criterion {
+            if !(power.is_finite() && power >= 1.0) {
+                return Err(Error::new(
+                    ErrorCode::InvalidInput,
+                    "the tweedie power must be 1 or more",
+                ));
+            }
+        }
+        if let Some(InfinityPolicy::Clamp(q)) = options.infinities {
+            if !(0.0..=0.5).contains(&q) {
+                return Err(Error::new(
+                    ErrorCode::InvalidInput,
+                    "the clamping quantile must be in [0, 0.5]",
+                ));
+            }
+        }
+        Ok(options)
+    }
+
+    fn random_forest(&self, seed: u64, trees: Option<NonZeroUsize>) -> RandomForestOptions {
+        let options = match self.preset {
+            Some(FitPreset::ReferenceFanova) => Preset::ReferenceFanova.random_forest(),
+            None => RandomForestOptions::new(),
+        };
+        let mut options = options.seed(seed);
+        if let Some(n) = trees {
+            options = options.trees(n);
+        }
+        if let Some(n) = self.max_depth {
+            options = options.max_depth(n);
+        }
+        if let Some(n) = self.max_features {
+            options = options.max_features(n);
+        }
+        if let Some(n) = self.min_samples_leaf {
+            options = options.min_samples_leaf(n);
+        }
+        if let Some(n) = self.max_bins {
+            options = options.max_bins(n);
+        }
+        if let Some(n) = self.max_thresholds_per_feature {
+            options = options.max_thresholds_per_feature(n);
+        }
+        if let Some(n) = self.split_sample {
+            options = options.split_sampling(SplitSampling::Uniform(n));
+        }
+        options.criterion(self.criterion.split_criterion())
+    }
+
+    fn non_finite_targets(&self) -> NonFiniteTargets {
+        match self.non_finite_targets {
+            NonFiniteTargetPolicy::Error => NonFiniteTargets::Error,
+            NonFiniteTargetPolicy::Drop => NonFiniteTargets::Drop,
+            NonFiniteTargetPolicy::Mean => NonFiniteTargets::Impute(Aggregation::Mean),
+            NonFiniteTargetPolicy::Median => NonFiniteTargets::Impute(Aggregation::Median),
+        }
+    }
+
+    fn infinities(&self) -> Option<Infinities> {
+        self.infinities.map(|policy| match policy {
+            InfinityPolicy::Error => Infinities::Error,
+            InfinityPolicy::Drop => Infinities::Drop,
+            InfinityPolicy::Clamp(q) => Infinities::Clamp(q),
+        })
+    }
+
+    // Fails if these options are strict and issues are found in the given columns.
+    fn validate(&self, features: &[&[f64]], target: &[f64]) -> Result<(), Error> {
+        if !self.strict {
+            return Ok(());
+        }
+        let mut report = validation::validate(features, target);
+        if self.aggregate_duplicates.is_some() {
+            report.retain(|issue| !matches!(issue, Issue::DuplicatedRows { .. }));
+        }
+        if report.is_clean() {
+            Ok(())
+        } else {
+            Err(FitError::Validation(report).into())
+        }
+    }
+
+    // Makes a table handling the invalid values as specified by these options.
+    fn table<'a>(&self, columns: Vec<&'a [f64]>) -> Result<(Table<'a>, usize), Error> {
+        if let Some((target, features)) = columns.split_last() {
+            self.validate(features, target)?;
+        }
+        let policy = self.non_finite_targets();
+        Ok(Table::with_invalid_values(
+            columns,
+            policy,
+            self.infinities(),
+        )?)
+    }
+
+    fn extra_trees(&self, seed: u64, trees: Option<NonZeroUsize>) -> ExtraTreesOptions {
+        let mut options = ExtraTreesOptions::new().seed(seed);
+        if let Some(n) = trees {
+            options = options.trees(n);
+        }
+        if let Some(n) = self.max_depth {
+            options = options.max_depth(n);
+        }
+        if let Some(n) = self.max_features {
+            options = options.max_features(n);
+        }
+        if let Some(n) = self.min_samples_leaf {
+            options = options.min_samples_leaf(n);
+        }
+        options.criterion(self.criterion.split_criterion())
+    }
+
+    fn fanova(&self) -> FanovaOptions {
+        self.fanova_with(self.seed, self.n_trees)
+    }
+
+    // Same as `fanova` but the seed and the number of the trees are replaced (for warm starts).
+    fn fanova_with(&self, seed: u64, trees: Option<NonZeroUsize>) -> FanovaOptions {
+        let options = match self.surrogate {
+            Surrogate::RandomForest => {
+                FanovaOptions::new().random_forest(self.random_forest(seed, trees))
+            }
+            Surrogate::ExtraTrees => {
+                FanovaOptions::new().extra_trees(self.extra_trees(seed, trees))
+            }
+        };
+        let options = match self.aggregate_duplicates {
+            Some(DuplicateAggregation::Mean) => options.aggregate_duplicates(Aggregation::Mean),
+            Some(DuplicateAggregation::Median) => options.aggregate_duplicates(Aggregation::Median),
+            None => options,
+        };
+        let options = match self.winsorize {
+            Some([lower, upper]) => options.winsorize_target(lower, upper),
+            None => options,
+        };
+        let options = if self.standardize_target {
+            options.standardize_target()
+        } else {
+            options
+        };
+        let options = match self.best_trials {
+            Some(BestTrials {
+                count: Some(count),
+                maximize,
+                ..
+            }) => options.best_rows(BestRows::Count(count), maximize),
+            Some(BestTrials {
+                fraction: Some(fraction),
+                maximize,
+                ..
+            }) => options.best_rows(BestRows::Fraction(fraction), maximize),
+            _ => options,
+        };
+        let options = match self.early_stopping {
+            Some(tolerance) => options.early_stopping(EARLY_STOPPING_INTERVAL, tolerance),
+            None => options,
+        };
+        let options = match self.time_budget_millis {
+            Some(millis) => options.time_budget(Duration::from_secs_f64(millis / 1000.0)),
+            None => options,
+        };
+        let options = options.categorical_encoding(match self.categorical_encoding {
+            Encoding::Ordinal => CategoricalEncoding::Ordinal,
+            Encoding::OneHot => CategoricalEncoding::OneHot,
+            Encoding::Target => CategoricalEncoding::Target,
+            Encoding::Native => CategoricalEncoding::Native,
+        });
+        if cfg!(feature = "threads") {
+            options.parallel()
+        } else {
+            options
+        }
+    }
+
+    fn encode(&self, encoder: &mut Encoder) {
+        for n in [
+            self.n_trees,
+            self.max_depth,
+            self.max_features,
+            self.min_samples_leaf,
+            self.max_bins,
+            self.max_thresholds_per_feature,
+            self.split_sample,
+        ] {
+            encoder.usize(n.map_or(0, |n| n.get()));
+        }
+        encoder.u64(self.seed);
+        encoder.u8(match self.surrogate {
+            Surrogate::RandomForest => 0,
+            Surrogate::ExtraTrees => 1,
+        });
+        encoder.u8(match self.criterion {
+            Criterion::SquaredError => 0,
+            Criterion::GainRatio => 1,
+            Criterion::Poisson => 2,
+            Criterion::Tweedie(_) => 3,
+        });
+        if let Criterion::Tweedie(power) = self.criterion {
+            encoder.f64(power);
+        }
+        encoder.u8(match self.inactive_params {
+            InactiveParams::Conditional => 0,
+            InactiveParams::Intersection => 1,
+        });
+        encoder.u8(match self.non_finite_targets {
+            NonFiniteTargetPolicy::Error => 0,
+            NonFiniteTargetPolicy::Drop => 1,
+            NonFiniteTargetPolicy::Mean => 2,
+            NonFiniteTargetPolicy::Median => 3,
+        });
+        match self.infinities {
+            None => encoder.u8(0),
+            Some(InfinityPolicy::Error) => encoder.u8(1),
+            Some(InfinityPolicy::Drop) => encoder.u8(2),
+            Some(InfinityPolicy::Clamp(q)) => {
+                encoder.u8(3);
+                encoder.f64(q);
+            }
+        }
+        encoder.u8(match self.aggregate_duplicates {
+            None => 0,
+            Some(DuplicateAggregation::Mean) => 1,
+            Some(DuplicateAggregation::Median) => 2,
+        });
+        let [lower, upper] = self.winsorize.unwrap_or([f64::NAN; 2]);
+        encoder.f64(lower);
+        encoder.f64(upper);
+        encoder.u8(u8::from(self.standardize_target));
+        match self.best_trials {
+            None => encoder.u8(0),
+            Some(best) => {
+                encoder.u8(1 + u8::from(best.maximize));
+                encoder.usize(best.count.map_or(0, |n| n.get()));
+                encoder.f64(best.fraction.unwrap_or(f64::NAN));
+            }
+        }
+        encoder.f64(self.early_stopping.unwrap_or(f64::NAN));
+        encoder.f64(self.time_budget_millis.unwrap_or(f64::NAN));
+        encoder.u8(match self.categorical_encoding {
+            Encoding::Ordinal => 0,
+            Encoding::OneHot => 1,
+            Encoding::Target => 2,
+            Encoding::Native => 3,
+        });
+        encoder.u8(u8::from(self.strict));
+        encoder.u8(match self.preset {
+            None => 0,
+            Some(FitPreset::ReferenceFanova) => 1,
+        });
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Self {
+            n_trees: NonZeroUsize::new(decoder.usize()?),
+            max_depth: NonZeroUsize::new(decoder.usize()?),
+            max_features: NonZeroUsize::new(decoder.usize()?),
+            min_samples_leaf: NonZeroUsize::new(decoder.usize()?),
+            max_bins: NonZeroUsize::new(decoder.usize()?),
+            max_thresholds_per_feature: NonZeroUsize::new(decoder.usize()?),
+            split_sample: NonZeroUsize::new(decoder.usize()?),
+            seed: decoder.u64()?,
+            surrogate: match decoder.u8()? {
+                0 => Surrogate::RandomForest,
+                1 => Surrogate::ExtraTrees,
+                _ => return None,
+            },
+            criterion: match decoder.u8()? {
+                0 => Criterion::SquaredError,
+                1 => Criterion::GainRatio,
+                2 => Criterion::Poisson,
+                3 => Criterion::Tweedie(decoder.f64()?),
+                _ => return None,
+            },
+            inactive_params: match decoder.u8()? {
+                0 => InactiveParams::Conditional,
+                1 => InactiveParams::Intersection,
+                _ => return None,
+            },
+            non_finite_targets: match decoder.u8()? {
+                0 => NonFiniteTargetPolicy::Error,
+                1 => NonFiniteTargetPolicy::Drop,
+                2 => NonFiniteTargetPolicy::Mean,
+                3 => NonFiniteTargetPolicy::Median,
+                _ => return None,
+            },
+            infinities: match decoder.u8()? {
+                0 => None,
+                1 => Some(InfinityPolicy::Error),
+                2 => Some(InfinityPolicy::Drop),
+                3 => Some(InfinityPolicy::Clamp(decoder.f64()?)),
+                _ => return None,
+            },
+            aggregate_duplicates: match decoder.u8()? {
+                0 => None,
+                1 => Some(DuplicateAggregation::Mean),
+                2 => Some(DuplicateAggregation::Median),
+                _ => return None,
+            },
+            winsorize: Some([decoder.f64()?, decoder.f64()?]).filter(|[q, _]| !q.is_nan()),
+            standardize_target: decoder.u8()? != 0,
+            best_trials: match decoder.u8()? {
+                0 => None,
+                code @ (1 | 2) => Some(BestTrials {
+                    count: NonZeroUsize::new(decoder.usize()?),
+                    fraction: Some(decoder.f64()?).filter(|f| !f.is_nan()),
+                    maximize: code == 2,
+                }),
+                _ => return None,
+            },
+            early_stopping: Some(decoder.f64()?).filter(|t| !t.is_nan()),
+            time_budget_millis: Some(decoder.f64()?).filter(|t| !t.is_nan()),
+            categorical_encoding: match decoder.u8()? {
+                0 => Encoding::Ordinal,
+                1 => Encoding::OneHot,
+                2 => Encoding::Target,
+                3 => Encoding::Native,
+                _ => return None,
+            },
+            strict: match decoder.u8()? {
+                0 => false,
+                1 => true,
+                _ => return None,
+            },
+            preset: match decoder.u8()? {
+                0 => None,
+                1 => Some(FitPreset::ReferenceFanova),
+                _ => return None,
+            },
+        })
+    }
+}
+
+fn parse_target(value: JsValue) -> Result<Target, Error> {
+    match value.as_f64() {
+        Some(objective) if objective >= 0.0 && objective.fract() == 0.0 => {
+            Ok(Target::Objective(objective as usize))
+        }
+        Some(_) => Err(Error::new(
+            ErrorCode::InvalidInput,
+            "objective must be a non-negative integer",
+        )),
+        None => Ok(from_value(value)?),
+    }
+}
+
+fn has_infinity(xs: &[f64]) -> bool {
+    xs.iter().any(|x| x.is_infinite())
+}
+
+// Returns the `q` and `1 - q` quantiles of the finite values (NaN if there are none).
+fn clamping_bounds(values: Vec<f64>, q: f64) -> (f64, f64) {
+    let mut finite = values
+        .into_iter()
+        .filter(|x| x.is_finite())
+        .collect::<Vec<_>>();
+    if finite.is_empty() {
+        return (f64::NAN, f64::NAN);
+    }
+    finite.sort_by(|a, b| a.total_cmp(b));
+    (
+        functions::quantile_sorted(&finite, q),
+        functions::quantile_sorted(&finite, 1.0 - q),
+    )
+}
+
+// Same as `to_value` but serializes maps (e.g., the results keyed by the parameter names) as
+// plain objects.
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, Error> {
+    Ok(value.serialize(&Serializer::new().serialize_maps_as_objects(true))?)
+}
+
+// Each column is either an array of numbers, a `Float64Array`, a `Float32Array`, an `Int32Array`,
+// a `BigInt64Array` or (for the features) an object like `{categorical: Int32Array}`.
+// Missing targets (`null` or `undefined`) are regarded as NaN.
+fn parse_table(
+    features: Array,
+    targets: Array,
+    options: &FitOptions,
+) -> Result<(Table<'static>, usize), Error> {
+    let features = features
+        .iter()
+        .map(Values::parse)
+        .collect::<Result<Vec<_>, _>>()?;
+    let targets = parse_targets(targets)?;
+    table_from_values(features, targets, options)
+}
+
+// Missing targets (`null` and `undefined`) are regarded as NaN.
+fn parse_targets(targets: Array) -> Result<Values, Error> {
+    if let Some(c) = targets.dyn_ref::<Float32Array>() {
+        Ok(Values::F32(c.to_vec()))
+    } else if let Some(c) = targets.dyn_ref::<Float64Array>() {
+        Ok(Values::F64(c.to_vec()))
+    } else if let Some(values) = Values::parse_integers(targets.as_ref())? {
+        Ok(Values::Integer(values))
+    } else {
+        let targets = targets
+            .iter()
+            .map(|x| {
+                if x.is_null() || x.is_undefined() {
+                    return Ok(f64::NAN);
+                }
+                x.as_f64()
+                    .ok_or_else(|| Error::new(ErrorCode::InvalidInput, "targets must be numbers"))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Values::F64(targets))
+    }
+}
+
+/// Values of a column given from JavaScript.
+#[derive(Debug, Clone, PartialEq)]
+enum Values {
+    F64(Vec<f64>),
+    F32(Vec<f32>),
+
+    // The values of an integer typed array (which make a `ColumnType::Integer` feature).
+    Integer(Vec<f64>),
+
+    // The codes given as `{categorical: Int32Array}` (which make a `ColumnType::Categorical`
+    // feature).
+    Categorical(Vec<f64>),
+}
+
+impl Values {
+    fn parse(value: JsValue) -> Result<Self, Error> {
+        if let Some(c) = value.dyn_ref::<Float32Array>() {
+            Ok(Self::F32(c.to_vec()))
+        } else if let Some(c) = value.dyn_ref::<Float64Array>() {
+            Ok(Self::F64(c.to_vec()))
+        } else if let Some(values) = Self::parse_integers(&value)? {
+            Ok(Self::Integer(values))
+        } else if let Some(codes) = Self::parse_categorical(&value)? {
+            Ok(Self::Categorical(codes))
+        } else {
+            Ok(Self::F64(from_value(value)?))
+        }
+    }
+
+    // Returns `None` if `value` is neither an `Int32Array` nor a `BigInt64Array`.
+    // Fails if a 64-bit integer can't be converted into an `f64` exactly.
+    fn parse_integers(value: &JsValue) -> Result<Option<Vec<f64>>, Error> {
+        if let Some(c) = value.dyn_ref::<Int32Array>() {
+            Ok(Some(c.to_vec().into_iter().map(f64::from).collect()))
+        } else if let Some(c) = value.dyn_ref::<BigInt64Array>() {
+            let values = c.to_vec().into_iter().map(exact_f64);
+            Ok(Some(values.collect::<Result<_, _>>()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // Returns `None` if `value` is not an object like `{categorical: codes}`, where `codes` must
+    // be an `Int32Array` or a `BigInt64Array` of the indices of the choices.
+    fn parse_categorical(value: &JsValue) -> Result<Option<Vec<f64>>, Error> {
+        let key = JsValue::from_str("categorical");
+        if !value.is_object()
+            || Array::is_array(value)
+            || !Reflect::has(value, &key).unwrap_or(false)
+        {
+            return Ok(None);
+        }
+        Self::parse_integers(&Reflect::get(value, &key).unwrap_or_default())?
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorCode::InvalidInput,
+                    "categorical codes must be an Int32Array or a BigInt64Array",
+                )
+            })
+            .map(Some)
+    }
+
+    fn widen(&self) -> Vec<f64> {
+        match self {
+            Self::F64(c) | Self::Integer(c) | Self::Categorical(c) => c.clone(),
+            Self::F32(c) => c.iter().copied().map(f64::from).collect(),
+        }
+    }
+}
+
+// The table keeps the values as `f32` if all the columns are given as `Float32Array`s.
+// The features given as integers are `ColumnType::Integer`.
+fn table_from_values(
+    features: Vec<Values>,
+    targets: Values,
+    options: &FitOptions,
+) -> Result<(Table<'static>, usize), Error> {
+    let column_types = features
+        .iter()
+        .enumerate()
+        .filter_map(|(i, c)| match c {
+            Values::Integer(_) => Some((i, ColumnType::Integer)),
+            Values::Categorical(_) => Some((i, ColumnType::Categorical)),
+            Values::F64(_) | Values::F32(_) => None,
+        })
+        .collect::<Vec<_>>();
+    let mut columns = features;
+    columns.push(targets);
+    let widened;
+    let (mut table, non_finite) = if columns.iter().all(|c| matches!(c, Values::F32(_))) {
+        if options.strict {
+            let widened = columns.iter().map(Values::widen).collect::<Vec<_>>();
+            let widened = widened.iter().map(|c| c.as_slice()).collect::<Vec<_>>();
+            if let Some((target, features)) = widened.split_last() {
+                options.validate(features, target)?;
+            }
+        }
+        let columns = columns
+            .iter()
+            .map(|c| match c {
+                Values::F32(c) => c.as_slice(),
+                Values::F64(_) | Values::Integer(_) | Values::Categorical(_) => unreachable!(),
+            })
+            .collect();
+        let policy = options.non_finite_targets();
+        Table::with_invalid_values_f32(columns, policy, options.infinities())?
+    } else {
+        widened = columns.iter().map(Values::widen).collect::<Vec<_>>();
+        options.table(widened.iter().map(|c| c.as_slice()).collect())?
+    };
+    for (i, column_type) in column_types {
+        table.set_column_type(i, column_type)?;
+    }
+    options.fanova().check_target(&table)?;
+    Ok((table.into_owned(), non_finite))
+}
+
+// Largest magnitude up to which every integer is exactly representable as an `f64`.
+const MAX_EXACT_INTEGER: u64 = 1 << f64::MANTISSA_DIGITS;
+
+fn exact_f64(x: i64) -> Result<f64, Error> {
+    if x.unsigned_abs() > MAX_EXACT_INTEGER {
+        return Err(Error::new(
+            ErrorCode::InvalidInput,
+            format!("{x} can't be represented exactly as a number (beyond 2^53)"),
+        ));
+    }
+    Ok(x as f64)
+}
+
+fn importances(mut fanova: Fanova, features_len: usize) -> Vec<Importance> {
+    let importances = (0..features_len)
+        .map(|i| quantify_importance(&mut fanova, i, features_len))
+        .collect();
+    MemoryUsage::record_model(&fanova);
+    importances
+}
+
+fn quantify_importance(fanova: &mut Fanova, feature: usize, features_len: usize) -> Importance {
+    Phase::Importance.enter();
+    // The importances of the next chunk of features (one per thread) are computed in parallel
+    // and cached, so the progress is still reported per feature.
+    let threads = rayon::current_num_threads();
+    if feature.is_multiple_of(threads) {
+        let chunk = (feature..(feature + threads).min(features_len))
+            .map(|i| vec![i])
+            .collect::<Vec<_>>();
+        fanova.quantify_importances(&chunk);
+    }
+    let importance = fanova.quantify_importance(&[feature]);
+    Progress::new(Phase::Importance, feature + 1, features_len).report();
+    importance
+}
+
+/// Error code which is set to the `code` field of the errors thrown to JavaScript.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorCode {
+    InvalidInput,
+    EmptyTable,
+    ConstantFeatures,
+    NonFiniteTarget,
+    ValidationFailed,
+    Cancelled,
+}
+
+impl ErrorCode {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::InvalidInput => "INVALID_INPUT",
+            Self::EmptyTable => "EMPTY_TABLE",
+            Self::ConstantFeatures => "CONSTANT_FEATURES",
+            Self::NonFiniteTarget => "NON_FINITE_TARGET",
+            Self::ValidationFailed => "VALIDATION_FAILED",
+            Self::Cancelled => "CANCELLED",
+        }
+    }
+}
+
+/// An error converted into a JavaScript `Error` having the `code` field.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{message}")]
+struct Error {
+    code: ErrorCode,
+    message: String,
+}
+
+impl Error {
+    fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<TableError> for Error {
+    fn from(e: TableError) -> Self {
+        FitError::from(e).into()
+    }
+}
+
+impl From<FitError> for Error {
+    fn from(e: FitError) -> Self {
+        FanovaError::from(e).into()
+    }
+}
+
+impl From<FanovaError> for Error {
+    fn from(e: FanovaError) -> Self {
+        let code = match e {
+            FanovaError::Table(TableError::EmptyTable) | FanovaError::Fit(FitError::EmptyRows) => {
+                ErrorCode::EmptyTable
+            }
+            FanovaError::Table(TableError::NonFiniteTarget)
+            | FanovaError::Fit(FitError::NonFiniteTarget) => ErrorCode::NonFiniteTarget,
+            FanovaError::Fit(FitError::Validation(_)) => ErrorCode::ValidationFailed,
+            FanovaError::Table(TableError::ConstantColumn { .. })
+            | FanovaError::Fit(FitError::InvalidTable(TableError::ConstantColumn { .. })) => {
+                ErrorCode::ConstantFeatures
+            }
+            _ => ErrorCode::InvalidInput,
+        };
+        Self::new(code, e.to_string())
+    }
+}
+
+impl From<serde_wasm_bindgen::Error> for Error {
+    fn from(e: serde_wasm_bindgen::Error) -> Self {
+        Self::new(ErrorCode::InvalidInput, e.to_string())
+    }
+}
+
+impl From<Error> for JsValue {
+    fn from(e: Error) -> Self {
+        let error = js_sys::Error::new(&e.message);
+        error.set_name("FanovaError");
+        let _ = Reflect::set(&error, &"code".into(), &e.code.as_str().into());
+        error.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "threads")]
+    #[test]
+    fn claim_thread_pool_works() {
+        assert!(claim_thread_pool().is_ok());
+        let e = claim_thread_pool().expect_err("already initialized");
+        assert_eq!(e.code, ErrorCode::InvalidInput);
+        assert_eq!(
+            e.message,
+            "the global thread pool has already been initialized"
+        );
+    }
+
+    #[test]
+    fn progress_percent_works() {
+        assert_eq!(Progress::new(Phase::Fit, 25, 100).percent, 25.0);
+        assert_eq!(Progress::new(Phase::Importance, 3, 3).percent, 100.0);
+        assert_eq!(Progress::new(Phase::Importance, 0, 0).percent, 100.0);
+    }
+
+    #[test]
+    fn panic_report_works() -> anyhow::Result<()> {
+        let features = [0.0, 1.0, 2.0, 3.0];
+        let target = [0.0, 1.0, 4.0, 9.0];
+        let fanova = fit(
+            Table::new(vec![&features, &target])?,
+            &FitOptions::default(),
+        )?;
+        importances(fanova, 1);
+
+        let report = PanicReport::with_context("oops".to_owned(), Some("src/a.rs:1:2".to_owned()));
+        assert_eq!(report.phase, Some(Phase::Importance));
+        assert_eq!((report.rows, report.features), (4, 1));
+        assert_eq!(
+            report.to_string(),
+            "fanova panicked: oops at src/a.rs:1:2 (phase: importance, rows: 4, features: 1)"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn strict_validation_works() -> anyhow::Result<()> {
+        let features = vec![Values::F32(vec![0.0, 1.0, 2.0]), Values::F32(vec![1.0; 3])];
+        let targets = Values::F32(vec![1.0, f32::NAN, 3.0]);
+        let options = FitOptions {
+            non_finite_targets: NonFiniteTargetPolicy::Drop,
+            ..Default::default()
+        };
+        assert!(table_from_values(features.clone(), targets.clone(), &options).is_ok());
+
+        let strict = FitOptions {
+            strict: true,
+            ..options
+        };
+        let e = table_from_values(features.clone(), targets, &strict).expect_err("invalid input");
+        assert_eq!(e.code, ErrorCode::ValidationFailed);
+
+        let poisson: FitOptions = serde_json::from_str(r#"{"criterion": "poisson"}"#)?;
+        let counts = Values::F32(vec![0.0, 3.0, 1.0]);
+        assert!(table_from_values(features.clone(), counts, &poisson).is_ok());
+        let negative = Values::F32(vec![0.0, -3.0, 1.0]);
+        let e =
+            table_from_values(features.clone(), negative, &poisson).expect_err("negative counts");
+        assert_eq!(e.code, ErrorCode::InvalidInput);
+        let gamma: FitOptions = serde_json::from_str(r#"{"criterion": {"tweedie": 2.0}}"#)?;
+        assert_eq!(gamma.criterion, Criterion::Tweedie(2.0));
+        let zeros = Values::F32(vec![0.0, 3.0, 1.0]);
+        assert!(table_from_values(features, zeros, &gamma).is_err());
+        assert!(strict.table(vec![&[0.0, 1.0], &[1.0, 2.0]]).is_ok());
+        let repeated = vec![&[0.0, 1.0, 0.0][..], &[1.0, 2.0, 3.0]];
+        assert!(strict.table(repeated.clone()).is_err());
+        let merged = FitOptions {
+            aggregate_duplicates: Some(DuplicateAggregation::Mean),
+            ..strict
+        };
+        assert!(merged.table(repeated).is_ok());
+
+        let x = [0.0, 1.0, 2.0];
+        let report = validation::validate(&[&x, &[1.0; 3], &x], &[1.0, f64::NAN, 3.0]);
+        let summary = ValidationSummary::new(&report);
+        assert!(!summary.valid);
+        assert_eq!(
+            summary.issues,
+            [
+                IssueEntry {
+                    kind: "constant_feature",
+                    error: false,
+                    message: "feature 1 is constant".to_owned(),
+                    feature: Some(1),
+                    original: None,
+                    rows: None,
+                },
+                IssueEntry {
+                    kind: "duplicated_feature",
+                    error: false,
+                    message: "feature 2 duplicates feature 0".to_owned(),
+                    feature: Some(2),
+                    original: Some(0),
+                    rows: None,
+                },
+                IssueEntry {
+                    kind: "non_finite_targets",
+                    error: true,
+                    message: "1 rows have non-finite targets".to_owned(),
+                    feature: None,
+                    original: None,
+                    rows: Some(vec![1]),
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn table_from_values_works() -> anyhow::Result<()> {
+        let options = &FitOptions::default();
+        let (f32_table, _) = table_from_values(
+            vec![Values::F32(vec![0.5, 1.5, 2.5])],
+            Values::F32(vec![1.0, 2.0, 3.0]),
+            options,
+        )?;
+        let (mixed_table, _) = table_from_values(
+            vec![Values::F32(vec![0.5, 1.5, 2.5])],
+            Values::F64(vec![1.0, 2.0, 3.0]),
+            options,
+        )?;
+        for table in [&f32_table, &mixed_table] {
+            assert_eq!(table.column(0).collect::<Vec<_>>(), [0.5, 1.5, 2.5]);
+            assert_eq!(table.target().collect::<Vec<_>>(), [1.0, 2.0, 3.0]);
+        }
+        assert!(f32_table.heap_size() < mixed_table.heap_size());
+
+        assert!(
+            table_from_values(vec![Values::F64(vec![0.0])], Values::F32(vec![]), options).is_err()
+        );
+
+        let (table, non_finite) = table_from_values(
+            vec![Values::F32(vec![0.5, 1.5, 2.5])],
+            Values::F32(vec![1.0, f32::NAN, 3.0]),
+            &FitOptions {
+                non_finite_targets: NonFiniteTargetPolicy::Drop,
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(table.target().collect::<Vec<_>>(), [1.0, 3.0]);
+        assert_eq!(non_finite, 1);
+
+        let (table, _) = table_from_values(
+            vec![
+                Values::Integer(vec![0.0, 1.0, 2.0, 3.0]),
+                Values::F32(vec![0.5, 1.5, 0.5, 1.5]),
+            ],
+            Values::Integer(vec![1.0, 2.0, 4.0, 8.0]),
+            options,
+        )?;
+        assert_eq!(table.column_type(0), ColumnType::Integer);
+        assert_eq!(table.column_type(1), ColumnType::Continuous);
+        assert_eq!(table.target().collect::<Vec<_>>(), [1.0, 2.0, 4.0, 8.0]);
+
+        let (table, _) = table_from_values(
+            vec![
+                Values::Categorical(vec![2.0, 0.0, 1.0, 0.0]),
+                Values::Integer(vec![0.0, 1.0, 2.0, 3.0]),
+            ],
+            Values::F64(vec![1.0, 2.0, 4.0, 8.0]),
+            options,
+        )?;
+        assert_eq!(table.column_type(0), ColumnType::Categorical);
+        assert_eq!(table.column_type(1), ColumnType::Integer);
+
+        // The types are kept in the exported states.
+        let evaluator = Evaluator::from_table("integers".to_owned(), table, *options)?;
+        let restored = Evaluator::decode(&evaluator.export_state()).expect("valid state");
+        assert_eq!(restored.table.column_type(0), ColumnType::Categorical);
+        assert_eq!(restored.table.column_type(1), ColumnType::Integer);
+        Ok(())
+    }
+
+    #[test]
+    fn exact_f64_works() {
+        let max = 1 << 53;
+        assert_eq!(exact_f64(max).ok(), Some(9007199254740992.0));
+        assert_eq!(exact_f64(-max).ok(), Some(-9007199254740992.0));
+        for x in [max + 1, -max - 1, i64::MAX, i64::MIN] {
+            let e = exact_f64(x).expect_err("not exact");
+            assert_eq!(e.code, ErrorCode::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn packed_works() {
+        let marginal = Marginal2d {
+            grid: [vec![0.0, 1.0], vec![2.0, 3.0, 4.0]],
+            mean: vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]],
+            std: vec![vec![0.0; 3], vec![0.5; 3]],
+        };
+        let packed = Packed::from(marginal);
+        let fields = packed
+            .fields
+            .iter()
+            .map(|f| (f.name, f.offset, f.shape.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [
+                ("grid0", 0, vec![2]),
+                ("grid1", 2, vec![3]),
+                ("mean", 5, vec![2, 3]),
+                ("std", 11, vec![2, 3]),
+            ]
+        );
+        assert_eq!(packed.data.len(), 17);
+        assert_eq!(packed.data[5..11], [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn error_code_works() {
+        assert_eq!(
+            Error::from(TableError::EmptyTable).code,
+            ErrorCode::EmptyTable
+        );
+        assert_eq!(
+            Error::from(FitError::NonFiniteTarget).code,
+            ErrorCode::NonFiniteTarget
+        );
+        assert_eq!(
+            Error::from(TableError::RowSizeMismatch).code,
+            ErrorCode::InvalidInput
+        );
+
+        let columns = [&[1.0, 1.0][..], &[f64::NAN, 2.0][..], &[0.0, 1.0][..]];
+        let table = Table::new(columns.to_vec()).expect("valid table");
+        assert_eq!(
+            check_table(table).map(|_| ()),
+            Err(Error::new(
+                ErrorCode::ConstantFeatures,
+                "all features are constant"
+            ))
+        );
+    }
+
+    #[test]
+    fn from_journal_works() -> anyhow::Result<()> {
+        let mut journal = String::new();
+        journal.push_str(r#"{"op_code": 0, "study_name": "foo", "directions": [1, 1]}"#);
+        for i in 0..20 {
+            let (x, y) = (i as f64, (i % 3) as f64);
+            journal.push_str(&format!(
+                "\n{{\"op_code\": 4, \"study_id\": 0, \"state\": 1, \"values\": [{}, {}], \"params\": {{\"x\": {x}, \"y\": {y}}}}}",
+                x * x + y,
+                y
+            ));
+        }
+
+        let mut evaluator = Evaluator::from_journal_with_options(
+            &journal,
+            None,
+            Target::Objective(0),
+            FitOptions::default(),
+        )?;
+        assert_eq!(evaluator.name, "foo");
+        assert_eq!(evaluator.param_names, ["x", "y"]);
+        assert_eq!(evaluator.trials_len(), 20);
+        let importances = evaluator.compute_importances()?;
+        assert!(importances[0] > importances[1]);
+
+        let options = FitOptions::default;
+        let name = Some("bar".to_owned());
+        assert!(Evaluator::from_journal_with_options(
+            &journal,
+            name,
+            Target::Objective(0),
+            options()
+        )
+        .is_err());
+        assert!(Evaluator::from_journal_with_options(
+            &journal,
+            None,
+            Target::Objective(2),
+            options()
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn from_trials_works() -> anyhow::Result<()> {
+        let trials = (0..20)
+            .map(|i| {
+                let (x, c) = (i as f64 / 20.0, ["a", "b"][i % 2]);
+                format!(
+                    r#"{{"state": "COMPLETE", "value": {}, "params": {{"x": {x}, "c": "{c}"}},
+ "distributions": {{"x": {{"name": "FloatDistribution", "attributes": {{"low": 0, "high": 1}}}},
+ "c": {{"name": "CategoricalDistribution", "attributes": {{"choices": ["a", "b"]}}}}}}}}"#,
+                    x * (1.0 + (i % 2) as f64)
+                )
+            })
+            .collect::<Vec<_>>();
+        let study = study::parse_trials(&format!("[{}]", trials.join(",")))?;
+        let evaluator = Evaluator::from_study(study, &Target::Objective(0), FitOptions::default())?;
+        assert_eq!(evaluator.param_names, ["c", "x"]);
+        assert!(matches!(
+            evaluator.distributions[0],
+            Some(Distribution::Categorical { .. })
+        ));
+
+        let restored = Evaluator::decode(&evaluator.export_state()).expect("valid state");
+        assert_eq!(restored.distributions, evaluator.distributions);
+        Ok(())
+    }
+
+    #[test]
+    fn response_surface_works() -> anyhow::Result<()> {
+        let xs = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+        let zs = [1.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+        let ys = xs.map(|x| x * x);
+        let table = Table::new(vec![&xs, &zs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        let options = |params: Vec<usize>| SurfaceOptions {
+            params,
+            grids: None,
+            n_points: 4,
+            others: OtherParams::Marginalize,
+            maximize: false,
+        };
+
+        for params in [vec![], vec![0, 0], vec![0, 2], vec![0, 1, 2]] {
+            assert!(evaluator.response_surface(&options(params)).is_err());
+        }
+
+        let marginal = evaluator.marginal_2d(0, 1, 4)?;
+        let surface = evaluator.response_surface(&options(vec![0, 1]))?;
+        assert_eq!(surface.names, ["0", "1"]);
+        let mean = marginal.mean.iter().flatten().copied().collect::<Vec<_>>();
+        assert_eq!(surface.data[8..24], mean);
+
+        let surface = evaluator.response_surface(&SurfaceOptions {
+            grids: Some(vec![vec![0.0, 5.0, 10.0]]),
+            others: OtherParams::Fixed(vec![0.0, 1.0]),
+            ..options(vec![0])
+        })?;
+        let fields = surface.fields.iter().map(|f| f.name).collect::<Vec<_>>();
+        assert_eq!(fields, ["grid0", "mean", "std"]);
+        let mean = &surface.data[3..6];
+        assert!(mean[0] < mean[1]);
+        assert_eq!(mean[1], mean[2]);
+
+        let surface = evaluator.response_surface(&SurfaceOptions {
+            others: OtherParams::Incumbent,
+            ..options(vec![1])
+        })?;
+        assert_eq!(surface.data.len(), 12);
+        Ok(())
+    }
+
+    #[test]
+    fn partial_dependence_2d_works() -> anyhow::Result<()> {
+        let xs = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+        let zs = [1.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+        let ys = xs.map(|x| x * x);
+        let table = Table::new(vec![&xs, &zs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+
+        assert!(evaluator
+            .partial_dependence_2d(0, 0, &[0.0], &[0.0])
+            .is_err());
+        assert!(evaluator
+            .partial_dependence_2d(0, 2, &[0.0], &[0.0])
+            .is_err());
+        assert!(evaluator
+            .partial_dependence_2d(0, 1, &[f64::NAN], &[0.0])
+            .is_err());
+
+        let marginal = evaluator.marginal_2d(0, 1, 3)?;
+        let [grid0, grid1] = &marginal.grid;
+        let matrix = evaluator.partial_dependence_2d(0, 1, grid0, grid1)?;
+        for (row, expected) in matrix.iter().zip(&marginal.mean) {
+            for (x, y) in row.iter().zip(expected) {
+                assert!((x - y).abs() < 1e-9);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn suggestions_work() -> anyhow::Result<()> {
+        let trials = (0..40)
+            .map(|i| {
+                let (x, n, c) = (i as f64 / 40.0, i % 4, ["a", "b"][i % 2]);
+                format!(
+                    r#"{{"state": "COMPLETE", "value": {}, "params": {{"x": {x}, "n": {n}, "c": "{c}"}},
+ "distributions": {{"x": {{"name": "FloatDistribution", "attributes": {{"low": 0, "high": 1}}}},
+ "n": {{"name": "IntDistribution", "attributes": {{"low": 0, "high": 3}}}},
+ "c": {{"name": "CategoricalDistribution", "attributes": {{"choices": ["a", "b"]}}}}}}}}"#,
+                    x * (1.0 + (i % 2) as f64) + n as f64
+                )
+            })
+            .collect::<Vec<_>>();
+        let study = study::parse_trials(&format!("[{}]", trials.join(",")))?;
+        let mut evaluator =
+            Evaluator::from_study(study, &Target::Objective(0), FitOptions::default())?;
+
+        let suggestions = evaluator.suggestions(false)?;
+        assert_eq!(suggestions["c"].choice, Some(serde_json::json!("a")));
+        assert_eq!(suggestions["n"].value, 0.0);
+        assert!(suggestions["x"].value < 0.2);
+        let suggestions = evaluator.suggestions(true)?;
+        assert_eq!(suggestions["c"].choice, Some(serde_json::json!("b")));
+        assert_eq!(suggestions["n"].value, 3.0);
+        assert!(suggestions["x"].value > 0.8);
+        assert!(suggestions["x"].prediction > suggestions["x"].value);
+        Ok(())
+    }
+
+    #[test]
+    fn alternative_targets_work() -> anyhow::Result<()> {
+        // `x` affects the accuracy at step 10 and `y` affects the final value.
+        let trials = (0..40)
+            .map(|i| {
+                let (x, y) = ((i % 8) as f64, (i % 5) as f64);
+                let state = if i % 4 == 0 { "PRUNED" } else { "COMPLETE" };
+                format!(
+                    r#"{{"state": "{state}", "value": {y}, "params": {{"x": {x}, "y": {y}}},
+ "user_attrs": {{"accuracy": {x}}}, "intermediate_values": {{"10": {}}}}}"#,
+                    x * x
+                )
+            })
+            .collect::<Vec<_>>();
+        let study = study::parse_trials(&format!("[{}]", trials.join(",")))?;
+        let options = FitOptions::default;
+
+        let mut evaluator = Evaluator::from_study(study.clone(), &Target::Objective(0), options())?;
+        assert_eq!(evaluator.trials_len(), 30);
+        let importances = evaluator.compute_importances()?;
+        assert!(importances[1] > importances[0]);
+
+        for target in [
+            Target::IntermediateValue(10),
+            Target::UserAttr("accuracy".to_owned()),
+        ] {
+            let mut evaluator = Evaluator::from_study(study.clone(), &target, options())?;
+            assert_eq!(evaluator.trials_len(), 40);
+            let importances = evaluator.compute_importances()?;
+            assert!(importances[0] > importances[1]);
+        }
+
+        let target = Target::IntermediateValue(20);
+        assert!(Evaluator::from_study(study, &target, options()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn inactive_params_work() -> anyhow::Result<()> {
+        // `y` is suggested only if `x` is greater than `0.5`.
+        let trials = (0..40)
+            .map(|i| {
+                let (x, y) = ((i % 10) as f64 / 10.0, (i % 7) as f64);
+                if x > 0.5 {
+                    format!(
+                        r#"{{"state": 1, "value": {}, "params": {{"x": {x}, "y": {y}}}}}"#,
+                        y
+                    )
+                } else {
+                    format!(r#"{{"state": 1, "value": {x}, "params": {{"x": {x}}}}}"#)
+                }
+            })
+            .collect::<Vec<_>>();
+        let study = study::parse_trials(&format!("[{}]", trials.join(",")))?;
+
+        let mut evaluator =
+            Evaluator::from_study(study.clone(), &Target::Objective(0), FitOptions::default())?;
+        assert_eq!(evaluator.param_names, ["x", "y"]);
+        let importances = evaluator.compute_importances()?;
+        assert!(importances.iter().all(|x| x.is_finite()));
+        assert!(importances[1] > 0.0);
+
+        let options = FitOptions {
+            inactive_params: InactiveParams::Intersection,
+            ..Default::default()
+        };
+        let evaluator = Evaluator::from_study(study, &Target::Objective(0), options)?;
+        assert_eq!(evaluator.param_names, ["x"]);
+        Ok(())
+    }
+
+    #[test]
+    fn evaluators_work() -> anyhow::Result<()> {
+        let xs = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+        let zs = [1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+        let ys = xs.map(|x| x * x);
+
+        let table = Table::new(vec![&xs, &zs, &ys])?.into_owned();
+        let mut study0 = Evaluator::from_table("study0".to_owned(), table, FitOptions::default())?;
+
+        let table = Table::new(vec![&zs, &xs, &ys])?.into_owned();
+        let mut study1 = Evaluator::from_table("study1".to_owned(), table, FitOptions::default())?;
+
+        assert_eq!(study0.name(), "study0");
+        assert_eq!(study1.name(), "study1");
+
+        let importances0 = study0.compute_importances()?;
+        let importances1 = study1.compute_importances()?;
+        assert!(importances0[0] > importances0[1]);
+        assert!(importances1[1] > importances1[0]);
+
+        study1.clear();
+        assert_eq!(study0.compute_importances()?, importances0);
+
+        let pairwise = study0.pairwise_importance()?;
+        assert_eq!(pairwise.names, ["0", "1"]);
+        assert_eq!(pairwise.matrix[0][0], importances0[0]);
+        assert_eq!(pairwise.matrix[1][1], importances0[1]);
+        assert_eq!(pairwise.matrix[0][1], pairwise.matrix[1][0]);
+
+        let marginal = study0.marginal(0, 5)?;
+        assert_eq!(marginal.grid.len(), 5);
+        assert!(marginal.mean[0] < marginal.mean[4]);
+        assert_eq!(marginal.noise.len(), 5);
+        assert!(marginal.noise.iter().all(|&noise| noise >= 0.0));
+        let mean = functions::mean(
+            study0
+                .fanova
+                .marginal_predictions(&[0], &[study0.grid(0, 5)?[2]])
+                .into_iter(),
+        );
+        assert_eq!(marginal.mean[2], mean);
+        let marginal = study0.marginal_2d(0, 1, 3)?;
+        assert_eq!(marginal.mean.len(), 3);
+        assert_eq!(marginal.std[2].len(), 3);
+        assert!(study0.marginal(2, 5).is_err());
+        assert!(study0.marginal(0, 1).is_err());
+
+        study0.param_names = vec!["x".to_owned(), "z".to_owned()];
+        let state = study0.export_state();
+        let mut restored = Evaluator::decode(&state).expect("valid state");
+        assert_eq!(restored.name(), "study0");
+        assert_eq!(restored.param_names(), ["x", "z"]);
+        assert_eq!(restored.compute_importances()?, importances0);
+        assert!(Evaluator::decode(&state[1..]).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn add_trials_works() -> anyhow::Result<()> {
+        let xs = [0.0, 1.0, 2.0, 3.0];
+        let zs = [1.0, 0.0, 1.0, 0.0];
+        let ys = xs.map(|x| x * x);
+        let table = Table::new(vec![&xs, &zs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        let before = evaluator.compute_importances()?;
+
+        assert!(evaluator
+            .push_trials(&[vec![4.0, 1.0], vec![5.0]], &[16.0, 25.0])
+            .is_err());
+        assert!(evaluator
+            .push_trials(&[vec![4.0, 1.0]], &[f64::NAN])
+            .is_err());
+        assert_eq!(evaluator.trials_len(), 4);
+
+        // No trial is added if any of them is invalid.
+        let mut integers = Table::new(vec![&xs, &zs, &ys])?.into_owned();
+        integers.set_column_type(0, ColumnType::Integer)?;
+        let mut evaluator_of_integers =
+            Evaluator::from_table("integers".to_owned(), integers, FitOptions::default())?;
+        let e = evaluator_of_integers
+            .push_trials(&[vec![4.0, 1.0], vec![4.5, 0.0]], &[16.0, 20.25])
+            .expect_err("non-integer value");
+        assert_eq!(e.code, ErrorCode::InvalidInput);
+        assert_eq!(evaluator_of_integers.trials_len(), 4);
+        assert_eq!(evaluator_of_integers.pending_trials, 0);
+
+        let params = (4..8)
+            .map(|x| vec![x as f64, (x % 2) as f64])
+            .collect::<Vec<_>>();
+        let values = (4..8).map(|x| (x * x) as f64).collect::<Vec<_>>();
+        evaluator.push_trials(&params, &values)?;
+        assert_eq!(evaluator.trials_len(), 8);
+
+        let after = evaluator.compute_importances()?;
+        assert_ne!(before, after);
+
+        let mut restored = Evaluator::decode(&evaluator.export_state()).expect("valid state");
+        assert_eq!(restored.trials_len(), 8);
+        assert_eq!(restored.compute_importances()?, after);
+
+        let compressed = evaluator.export_state_compressed();
+        assert!(compressed.len() < evaluator.export_state().len());
+        let mut restored = Evaluator::decode(&compressed).expect("valid state");
+        assert_eq!(restored.compute_importances()?, after);
+        assert!(Evaluator::decode(&compressed[..compressed.len() - 1]).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn attributions_work() -> anyhow::Result<()> {
+        let xs = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+        let zs = [1.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+        let ys = xs.map(|x| x * x);
+        let table = Table::new(vec![&xs, &zs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+
+        let shap = evaluator.shap_values()?;
+        assert_eq!(shap.values.len(), 6);
+        for (row, values) in shap.values.iter().enumerate() {
+            let point = [xs[row], zs[row]];
+            let prediction = functions::mean(
+                evaluator
+                    .fanova
+                    .marginal_predictions(&[0, 1], &point)
+                    .into_iter(),
+            );
+            let sum = shap.expected_value + values.iter().sum::<f64>();
+            assert!((sum - prediction).abs() < 1e-9, "row={row}");
+        }
+        assert_eq!(shap.ranking["0"].rank, 1);
+        assert_eq!(shap.ranking["1"].rank, 2);
+
+        assert!(evaluator.sobol_indices(0).is_err());
+        let indices = evaluator.sobol_indices(256)?;
+        assert!(indices["0"].first_order > indices["1"].total);
+
+        assert!(evaluator.elementary_effects(0).is_err());
+        let effects = evaluator.elementary_effects(10)?;
+        assert_eq!(effects["0"].rank, 1);
+        assert!(effects["0"].mu > 0.0);
+
+        assert!(evaluator.h_statistics(0).is_err());
+        let statistics = evaluator.h_statistics(256)?;
+        assert_eq!(statistics.matrix[0][0], 0.0);
+        assert_eq!(statistics.matrix[0][1], statistics.matrix[1][0]);
+        assert!(statistics
+            .matrix
+            .iter()
+            .flatten()
+            .chain(&statistics.total)
+            .all(|h| (0.0..=1.0).contains(h)));
+        Ok(())
+    }
+
+    #[test]
+    fn top_quantile_importances_work() -> anyhow::Result<()> {
+        let xs = (0..40).map(f64::from).collect::<Vec<_>>();
+        let zs = xs.iter().map(|x| x % 2.0).collect::<Vec<_>>();
+        let ys = xs
+            .iter()
+            .zip(&zs)
+            .map(|(x, z)| if *x < 4.0 { -1.0 } else { 0.0 } + z * 100.0)
+            .collect::<Vec<_>>();
+        let table = Table::new(vec![&xs, &zs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        assert!(evaluator.top_quantile_importances(0.0, false).is_err());
+        assert!(evaluator.top_quantile_importances(f64::NAN, false).is_err());
+
+        let importances = evaluator.compute_importances()?;
+        assert!(importances[1] > importances[0]);
+        let top = evaluator.top_quantile_importances(0.05, false)?;
+        assert_eq!(top["0"].rank, 1);
+        let top = evaluator.top_quantile_importances(0.5, true)?;
+        assert_eq!(top["1"].rank, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn restricted_importances_work() -> anyhow::Result<()> {
+        // The choice matters only if `x` is large.
+        let xs = (0..60).map(|x| f64::from(x) / 60.0).collect::<Vec<_>>();
+        let cs = (0..60).map(|x| f64::from(x % 3)).collect::<Vec<_>>();
+        let ys = xs
+            .iter()
+            .zip(&cs)
+            .map(|(x, c)| if *x < 0.5 { 10.0 } else { *c })
+            .collect::<Vec<_>>();
+        let table = Table::new(vec![&xs, &cs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        evaluator.distributions[1] = Some(Distribution::Categorical {
+            choices: vec!["a".into(), "b".into(), "c".into()],
+        });
+        let importances = evaluator.compute_importances()?;
+        assert!(importances[0] > importances[1]);
+
+        let options = |json: &str| serde_json::from_str::<RestrictionOptions>(json);
+        let restricted =
+            evaluator.restricted_importances(&options(r#"{"domain": {"0": [0.5, 1.0]}}"#)?)?;
+        assert_eq!(restricted["1"].rank, 1, "{restricted:?}");
+        let refitted = evaluator
+            .restricted_importances(&options(r#"{"domain": {"0": [0.5, 1.0]}, "refit": true}"#)?)?;
+        assert!(refitted["1"].importance > 0.9, "{refitted:?}");
+        let options_ac = options(r#"{"domain": {"0": [0.5, 1.0], "1": {"choices": ["c", "a"]}}}"#)?;
+        assert_eq!(
+            evaluator.domain(&options_ac)?[1],
+            Some(vec![-0.5..0.5, 1.5..2.5])
+        );
+        assert_eq!(evaluator.restricted_importances(&options_ac)?["1"].rank, 1);
+
+        for json in [
+            r#"{"domain": {"2": [0.0, 1.0]}}"#,
+            r#"{"domain": {"0": [1.0, 0.0]}}"#,
+            r#"{"domain": {"0": {"choices": ["a"]}}}"#,
+            r#"{"domain": {"1": {"choices": ["d"]}}}"#,
+            r#"{"domain": {"0": [2.0, 3.0]}}"#,
+            r#"{"domain": {"0": [2.0, 3.0]}, "refit": true}"#,
+        ] {
+            let e = evaluator
+                .restricted_importances(&options(json)?)
+                .expect_err(json);
+            assert_eq!(e.code, ErrorCode::InvalidInput);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn top_interactions_works() -> anyhow::Result<()> {
+        let xs = (0..50).map(|x| x as f64).collect::<Vec<_>>();
+        let zs = (0..50).map(|x| ((x * 7) % 11) as f64).collect::<Vec<_>>();
+        let noise = (0..50).map(|x| ((x * 13) % 17) as f64).collect::<Vec<_>>();
+        let ys = xs.iter().zip(&zs).map(|(x, z)| x * z).collect::<Vec<_>>();
+        let table = Table::new(vec![&xs, &zs, &noise, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        assert!(evaluator.top_interactions(2, f64::NAN).is_err());
+
+        let interactions = evaluator.top_interactions(2, f64::NEG_INFINITY)?;
+        assert_eq!(interactions.pairs.len(), 1);
+        assert_eq!(
+            interactions.pairs[0].params,
+            ["0".to_owned(), "1".to_owned()]
+        );
+        let pairwise = evaluator.pairwise_importance()?;
+        assert_eq!(interactions.pairs[0].importance, pairwise.matrix[0][1]);
+        let skipped = interactions
+            .skipped
+            .iter()
+            .map(|s| s.params.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            skipped,
+            [
+                ["0".to_owned(), "2".to_owned()],
+                ["1".to_owned(), "2".to_owned()]
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn interaction_graph_works() -> anyhow::Result<()> {
+        let xs = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+        let zs = [1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+        let ws = [0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 1.0];
+        let ys = [0.0, 1.0, 4.0, 9.0, 16.0, 25.0, 36.0, 49.0];
+        let table = Table::new(vec![&xs, &zs, &ws, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        assert!(evaluator.interaction_graph(f64::NAN).is_err());
+
+        let pairwise = evaluator.pairwise_importance()?;
+        let graph = evaluator.interaction_graph(f64::NEG_INFINITY)?;
+        assert_eq!(graph.nodes.len(), 3);
+        assert_eq!(graph.nodes[1].name, "1");
+        assert_eq!(graph.nodes[1].importance, pairwise.matrix[1][1]);
+        assert_eq!(graph.edges.len(), 3);
+        assert!(graph.edges.windows(2).all(|w| w[0].weight >= w[1].weight));
+        for edge in &graph.edges {
+            assert!(edge.source < edge.target);
+            assert_eq!(edge.weight, pairwise.matrix[edge.source][edge.target]);
+        }
+
+        let threshold = graph.edges[1].weight;
+        let graph = evaluator.interaction_graph(threshold)?;
+        assert!(graph.edges.len() >= 2);
+        assert!(graph.edges.iter().all(|e| e.weight >= threshold));
+        Ok(())
+    }
+
+    #[test]
+    fn distilled_tree_works() -> anyhow::Result<()> {
+        let xs = (0..40).map(f64::from).collect::<Vec<_>>();
+        let zs = xs.iter().map(|x| x % 2.0).collect::<Vec<_>>();
+        let ys = xs
+            .iter()
+            .map(|x| if *x < 20.0 { 0.0 } else { 1.0 })
+            .collect::<Vec<_>>();
+        let table = Table::new(vec![&xs, &zs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        evaluator.param_names = vec!["x".to_owned(), "z".to_owned()];
+        assert!(evaluator.distilled_tree(0, 100).is_err());
+        assert!(evaluator.distilled_tree(2, 0).is_err());
+
+        let tree = evaluator.distilled_tree(2, 512)?;
+        assert!(tree.fidelity > 0.9, "{}", tree.fidelity);
+        assert_eq!(tree.root.param.as_deref(), Some("x"));
+        let threshold = tree.root.threshold.expect("split");
+        assert!((18.0..=21.0).contains(&threshold), "{threshold}");
+        assert_eq!(tree.root.samples, 512);
+        assert!(tree.dot.contains("x <= "));
+        Ok(())
+    }
+
+    #[test]
+    fn exported_tree_works() -> anyhow::Result<()> {
+        let xs = (0..40).map(f64::from).collect::<Vec<_>>();
+        let zs = xs.iter().map(|x| x % 2.0).collect::<Vec<_>>();
+        let ys = xs
+            .iter()
+            .map(|x| if *x < 20.0 { 0.0 } else { 1.0 })
+            .collect::<Vec<_>>();
+        let table = Table::new(vec![&xs, &zs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        evaluator.param_names = vec!["x".to_owned(), "z".to_owned()];
+        let trees_len = evaluator.fanova.trees_len();
+        assert!(evaluator.exported_tree(trees_len, None).is_err());
+
+        let tree = evaluator.exported_tree(0, None)?;
+        assert_eq!(tree.tree, 0);
+        assert_eq!(tree.root.param.as_deref(), Some("x"));
+        let threshold = tree.root.threshold.expect("split");
+        assert!((18.0..=21.0).contains(&threshold), "{threshold}");
+        assert!(tree.root.gain.expect("split") > 0.9);
+        assert!((tree.root.fraction - 1.0).abs() < 1e-9);
+
+        let pruned = evaluator.exported_tree(0, Some(1))?;
+        assert_eq!(pruned.root.leaves, tree.root.leaves);
+        let left = pruned.root.left.as_deref().expect("split");
+        assert!(left.param.is_none() && left.gain.is_none());
+        let json = serde_json::to_string(&pruned)?;
+        assert!(json.starts_with("{\"tree\":0,\"root\":{\"param\":\"x\""));
+        assert!(!json.contains("\"left\":{\"param\""));
+        Ok(())
+    }
+
+    #[test]
+    fn split_thresholds_work() -> anyhow::Result<()> {
+        let xs = (0..40).map(f64::from).collect::<Vec<_>>();
+        let zs = vec![1.0; 40];
+        let ys = xs
+            .iter()
+            .map(|x| if *x < 20.0 { 0.0 } else { 1.0 })
+            .collect::<Vec<_>>();
+        let table = Table::new(vec![&xs, &zs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        evaluator.param_names = vec!["x".to_owned(), "z".to_owned()];
+
+        let thresholds = evaluator.split_thresholds()?;
+        assert!(thresholds["z"].is_empty());
+        let x = &thresholds["x"];
+        assert!(x.windows(2).all(|w| w[0].threshold < w[1].threshold));
+        assert!(x.iter().all(|e| (0.0..39.0).contains(&e.threshold)));
+        let step = x
+            .iter()
+            .filter(|e| (18.0..=21.0).contains(&e.threshold))
+            .map(|e| e.count)
+            .sum::<usize>();
+        assert!(step >= evaluator.fanova.trees_len(), "{step}");
+        Ok(())
+    }
+
+    #[test]
+    fn split_frequencies_work() -> anyhow::Result<()> {
+        let xs = (0..40).map(f64::from).collect::<Vec<_>>();
+        let zs = vec![1.0; 40];
+        let ys = xs.iter().map(|x| x * x).collect::<Vec<_>>();
+        let table = Table::new(vec![&xs, &zs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        evaluator.param_names = vec!["x".to_owned(), "z".to_owned()];
+
+        let frequencies = evaluator.split_frequencies()?;
+        assert_eq!(frequencies["x"].importance, 1.0);
+        assert_eq!(frequencies["x"].rank, 1);
+        assert_eq!(frequencies["z"].importance, 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn importance_convergence_works() -> anyhow::Result<()> {
+        let xs = (0..20).map(f64::from).collect::<Vec<_>>();
+        let zs = xs.iter().map(|x| (x * 7.0) % 3.0).collect::<Vec<_>>();
+        let ys = xs.iter().map(|x| x * x).collect::<Vec<_>>();
+        let table = Table::new(vec![&xs, &zs, &ys])?.into_owned();
+        let options = FitOptions {
+            n_trees: NonZeroUsize::new(10),
+            ..FitOptions::default()
+        };
+        let mut evaluator = Evaluator::from_table("study".to_owned(), table, options)?;
+        let convergence = evaluator.importance_convergence()?;
+        assert_eq!(convergence.keys().collect::<Vec<_>>(), ["0", "1"]);
+        assert_eq!(convergence["0"].importance.len(), 10);
+        assert_eq!(convergence["0"].std[0], 0.0);
+        let importances = evaluator.compute_importances()?;
+        for (i, entry) in convergence.values().enumerate() {
+            assert!((entry.importance[9] - importances[i]).abs() < 1e-12);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn null_importances_work() -> anyhow::Result<()> {
+        let xs = (0..20).map(f64::from).collect::<Vec<_>>();
+        let zs = xs.iter().map(|x| (x * 7.0) % 3.0).collect::<Vec<_>>();
+        let ys = xs.iter().map(|x| x * x).collect::<Vec<_>>();
+        let table = Table::new(vec![&xs, &zs, &ys])?.into_owned();
+        let options = FitOptions {
+            n_trees: NonZeroUsize::new(10),
+            ..FitOptions::default()
+        };
+        let mut evaluator = Evaluator::from_table("study".to_owned(), table, options)?;
+        assert!(evaluator.null_importances(0).is_err());
+
+        let significances = evaluator.null_importances(9)?;
+        assert_eq!(significances["0"].p_value, 0.1);
+        assert!(significances["0"].z_score > 0.0);
+        assert!(significances["1"].p_value > 0.1);
+        Ok(())
+    }
+
+    #[test]
+    fn sensitivities_work() -> anyhow::Result<()> {
+        let xs = (0..40).map(f64::from).collect::<Vec<_>>();
+        let zs = xs.iter().map(|x| x % 2.0).collect::<Vec<_>>();
+        let ys = xs.iter().map(|x| x * x).collect::<Vec<_>>();
+        let table = Table::new(vec![&xs, &zs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        assert!(evaluator.sensitivities(None, 0.0, false).is_err());
+        assert!(evaluator.sensitivities(Some(&[1.0]), 0.1, false).is_err());
+
+        // The best trial is at `x = 0`.
+        let sensitivities = evaluator.sensitivities(None, 0.1, false)?;
+        assert!(sensitivities["0"].slope > 0.0);
+        assert_eq!(sensitivities["0"].direction, "decrease");
+        let width = evaluator.fanova.feature_space().ranges()[0].end
+            - evaluator.fanova.feature_space().ranges()[0].start;
+        assert_eq!(sensitivities["0"].scaled, sensitivities["0"].slope * width);
+
+        let sensitivities = evaluator.sensitivities(Some(&[20.0, 0.0]), 0.1, true)?;
+        assert_eq!(sensitivities["0"].direction, "increase");
+        Ok(())
+    }
+
+    #[test]
+    fn predictions_work() -> anyhow::Result<()> {
+        let xs = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+        let zs = [1.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+        let ys = xs.map(|x| x * x);
+        let table = Table::new(vec![&xs, &zs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        assert!(evaluator.predictions(&[vec![0.0]]).is_err());
+        assert!(evaluator.predictions(&[vec![0.0, f64::NAN]]).is_err());
+
+        let configs = [vec![0.0, 1.0], vec![5.0, 0.0], vec![100.0, 0.0]];
+        let predictions = evaluator.predictions(&configs)?;
+        assert!(predictions[0].mean < predictions[1].mean);
+        assert!(predictions.iter().all(|p| p.std >= 0.0));
+        // Out of the search space.
+        assert_eq!(predictions[2], predictions[1]);
+
+        let matrix = evaluator.tree_prediction_matrix(&configs)?;
+        let trees = evaluator.fanova.trees_len();
+        assert_eq!(matrix.len(), 3 * trees);
+        let (mean, std) = functions::mean_and_stddev(matrix[trees..2 * trees].iter().copied());
+        assert!((mean - predictions[1].mean).abs() < 1e-9);
+        assert!((std - predictions[1].std).abs() < 1e-9);
+        assert!(evaluator.tree_prediction_matrix(&[vec![0.0]]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn csv_export_works() -> anyhow::Result<()> {
+        let xs = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+        let zs = [1.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+        let ys = xs.map(|x| x * x);
+        let table = Table::new(vec![&xs, &zs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        evaluator.param_names = vec!["x".to_owned(), "z, flag".to_owned()];
+
+        let csv = evaluator.importances_csv()?;
+        assert!(csv.ends_with(",2\r\n"));
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "param,importance,std,rank");
+        let importances = evaluator.compute_importances()?;
+        assert!(lines[1].starts_with(&format!("x,{},", importances[0])));
+        assert!(lines[1].ends_with(",1"));
+        assert!(lines[2].starts_with("\"z, flag\","));
+
+        let csv = evaluator.marginal_csv(&[0, 1], 5)?;
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 11);
+        assert_eq!(lines[0], "param,value,mean,std,noise");
+        let marginal = evaluator.marginal(0, 5)?;
+        let row = format!(
+            "x,{},{},{},{}",
+            marginal.grid[0], marginal.mean[0], marginal.std[0], marginal.noise[0]
+        );
+        assert_eq!(lines[1], row);
+        assert!(evaluator.marginal_csv(&[2], 5).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn report_works() -> anyhow::Result<()> {
+        let xs = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+        let zs = [1.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+        let ys = xs.map(|x| x * x);
+        let table = Table::new(vec![&xs, &zs, &ys])?.into_owned();
+        let options = FitOptions {
+            n_trees: NonZeroUsize::new(10),
+            ..FitOptions::default()
+        };
+        let mut evaluator = Evaluator::from_table("study".to_owned(), table, options)?;
+        assert!(evaluator.report(1.5, 64).is_err());
+        assert!(evaluator.report(0.9, 0).is_err());
+
+        let report = evaluator.report(0.9, 64)?;
+        assert_eq!(report.schema_version, REPORT_SCHEMA_VERSION);
+        assert_eq!(report.trials, 6);
+        assert_eq!(report.params.len(), 2);
+        let importances = evaluator.compute_importances()?;
+        for (param, importance) in report.params.iter().zip(importances) {
+            assert_eq!(param.importance, importance);
+            assert!(param.lower <= param.upper);
+        }
+        assert_eq!(report.pairwise, evaluator.pairwise_importance()?.matrix);
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json())?;
+        assert_eq!(json["schema_version"], 1);
+        assert_eq!(json["study"], "study");
+        assert_eq!(json["params"][0]["name"], "0");
+        assert_eq!(json["fit_quality"]["level"], report.fit_quality.level);
+        // The settings can be given to fit the evaluator again.
+        let settings: FitOptions = serde_json::from_value(json["settings"].clone())?;
+        assert_eq!(settings, options);
+        Ok(())
+    }
+
+    #[test]
+    fn local_importances_work() -> anyhow::Result<()> {
+        let xs = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+        let zs = [1.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+        let ys = xs.map(|x| x * x);
+        let table = Table::new(vec![&xs, &zs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+
+        assert!(evaluator.local_importances(None, 1, false).is_err());
+        assert!(evaluator
+            .local_importances(Some(&[1.0]), 10, false)
+            .is_err());
+
+        let importances = evaluator.local_importances(None, 10, false)?;
+        assert_eq!(importances["0"].rank, 1);
+        let sum = importances.values().map(|e| e.importance).sum::<f64>();
+        assert!((sum - 1.0).abs() < 1e-9);
+        assert_eq!(importances["0"].global, evaluator.compute_importances()?[0]);
+        Ok(())
+    }
+
+    #[test]
+    fn message_host_works() -> anyhow::Result<()> {
+        let xs = (0..50).map(|x| x as f64).collect::<Vec<_>>();
+        let noise = (0..50).map(|x| ((x * 7) % 11) as f64).collect::<Vec<_>>();
+        let ys = xs.iter().map(|x| x * 2.0).collect::<Vec<_>>();
+        let fit = serde_json::json!({
+            "type": "fit",
+            "id": 1,
+            "features": [xs, noise],
+            "targets": ys,
+            "options": {"n_trees": 10},
+        });
+        let request = |value: serde_json::Value| -> anyhow::Result<Request> {
+            Ok(serde_json::from_value(value)?)
+        };
+        let mut host = HostState::default();
+
+        // The requests wait for the fitting and are answered in order.
+        let quantify = request(serde_json::json!({"type": "quantify", "id": 2, "params": [0]}))?;
+        let marginal = request(serde_json::json!({"type": "marginal", "id": 3, "param": 0}))?;
+        assert!(host.handle(request(fit.clone())?).is_empty());
+        assert!(host.handle(quantify.clone()).is_empty());
+        assert!(host.handle(marginal).is_empty());
+        // Each slice does a step at least (starting the fitting or fitting a tree).
+        for done in 0..2 {
+            let (finished, responses) = host.run(0.0)?;
+            assert!(!finished);
+            assert_eq!(
+                responses,
+                [Response::Progress {
+                    id: 1,
+                    progress: Progress::new(Phase::Fit, done, 10)
+                }]
+            );
+        }
+        let (finished, responses) = host.run(f64::INFINITY)?;
+        assert!(finished);
+        let table = Table::new(vec![&xs, &noise, &ys])?.into_owned();
+        let options = FitOptions {
+            n_trees: NonZeroUsize::new(10),
+            ..FitOptions::default()
+        };
+        let mut evaluator = Evaluator::from_table("study".to_owned(), table, options)?;
+        assert_eq!(
+            responses,
+            [
+                Response::Fitted {
+                    id: 1,
+                    quality: evaluator.fit_quality()?
+                },
+                Response::Importance {
+                    id: 2,
+                    value: evaluator.quantify(&[0])?
+                },
+                Response::Marginal {
+                    id: 3,
+                    marginal: evaluator.marginal(0, 50)?
+                },
+            ]
+        );
+        let response = serde_json::to_value(&responses[1])?;
+        assert_eq!(response["type"], "importance");
+        assert_eq!(response["id"], 2);
+        assert!(response["importance"].as_f64().is_some_and(|x| x > 0.9));
+
+        // A running fitting and queued requests can be cancelled (the model is kept).
+        host.handle(request(fit.clone())?);
+        host.handle(quantify);
+        host.run(0.0)?;
+        let cancel = |id| request(serde_json::json!({"type": "cancel", "id": id}));
+        assert_eq!(host.handle(cancel(1)?), [Response::Cancelled { id: 1 }]);
+        assert_eq!(host.handle(cancel(2)?), [Response::Cancelled { id: 2 }]);
+        assert!(host.handle(cancel(3)?).is_empty());
+        assert_eq!(host.run(f64::INFINITY)?, (true, Vec::new()));
+        assert!(host.evaluator.is_some());
+
+        // Failed requests are answered by errors with their codes.
+        let mut host = HostState::default();
+        host.handle(request(
+            serde_json::json!({"type": "quantify", "id": 4, "params": [0]}),
+        )?);
+        let mut constant = fit;
+        constant["id"] = 5.into();
+        constant["features"] = serde_json::json!([vec![1.0; 50]]);
+        host.handle(request(constant)?);
+        let (_, responses) = host.run(f64::INFINITY)?;
+        let codes = responses
+            .iter()
+            .map(|r| match r {
+                Response::Error { id, code, .. } => (*id, *code),
+                r => panic!("unexpected response: {r:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            codes,
+            [(Some(4), "INVALID_INPUT"), (Some(5), "CONSTANT_FEATURES")]
+        );
+        assert!(serde_json::from_str::<Request>(r#"{"type": "predict", "id": 6}"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn time_sliced_computations_work() -> anyhow::Result<()> {
+        let xs = (0..50).map(|x| x as f64).collect::<Vec<_>>();
+        let noise = (0..50).map(|x| ((x * 7) % 11) as f64).collect::<Vec<_>>();
+        let ys = xs.iter().map(|x| x * 2.0).collect::<Vec<_>>();
+        let table = Table::new(vec![&xs, &noise, &ys])?.into_owned();
+        let options = FitOptions {
+            n_trees: NonZeroUsize::new(10),
+            ..FitOptions::default()
+        };
+
+        // A zero budget fits one tree per slice.
+        let mut job = FitJob::from_table("study".to_owned(), table.clone(), options)?;
+        assert!(job.run_slice(-1.0).is_err());
+        for i in 1..10 {
+            assert!(!job.run_slice(0.0)?);
+            assert_eq!(job.progress(), i as f64 / 10.0);
+        }
+        assert!(job.run_slice(0.0)?);
+        assert_eq!(job.progress(), 1.0);
+        assert!(job.run_slice(0.0)?);
+        let mut sliced = job.evaluator()?;
+        let mut evaluator = Evaluator::from_table("study".to_owned(), table.clone(), options)?;
+        assert_eq!(sliced.fanova.importances(), evaluator.fanova.importances());
+
+        // (The cached model would finish the job at once.)
+        clear_cache();
+        let job = FitJob::from_table("study".to_owned(), table, options)?;
+        assert!(job.evaluator().is_err());
+
+        assert!(sliced.run_marginal_job(0.0).is_err());
+        assert!(sliced.start_marginal_job(vec![0, 2], 5).is_err());
+        sliced.start_marginal_job(vec![1, 0], 5)?;
+        let mut slices = 0;
+        while !sliced.run_marginal_job(0.0)? {
+            slices += 1;
+            if slices == 3 {
+                assert!(sliced.take_marginal_job().is_err());
+            }
+        }
+        assert_eq!(slices, 9);
+        let marginals = sliced.take_marginal_job()?;
+        assert_eq!(marginals, [sliced.marginal(1, 5)?, sliced.marginal(0, 5)?]);
+        assert!(sliced.take_marginal_job().is_err());
+
+        // An infinite budget runs to the end at once.
+        sliced.start_marginal_job(vec![0], 5)?;
+        assert!(sliced.run_marginal_job(f64::INFINITY)?);
+
+        assert!(sliced.run_pairwise_job(0.0).is_err());
+        sliced.start_pairwise_job()?;
+        assert!(sliced.take_pairwise_job().is_err());
+        while !sliced.run_pairwise_job(0.0)? {}
+        let pairwise = sliced.take_pairwise_job()?;
+        assert_eq!(pairwise, evaluator.pairwise_importance()?);
+        Ok(())
+    }
+
+    #[test]
+    fn cross_validation_works() -> anyhow::Result<()> {
+        let xs = (0..50).map(|x| x as f64).collect::<Vec<_>>();
+        let ys = xs.iter().map(|x| x * 2.0).collect::<Vec<_>>();
+        let table = Table::new(vec![&xs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        let summary = evaluator.cross_validation(5)?;
+        assert_eq!(summary.rows, 50);
+        assert!(summary.r2 > 0.9, "{summary:?}");
+        assert_eq!(summary.folds.len(), 5);
+        assert!(summary.folds.iter().all(|f| f.rows == 10));
+        assert_eq!(evaluator.cross_validation(5)?, summary);
+
+        let e = evaluator.cross_validation(1).expect_err("too few folds");
+        assert_eq!(e.code, ErrorCode::InvalidInput);
+        assert!(evaluator.cross_validation(51).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn drop_column_importances_work() -> anyhow::Result<()> {
+        let xs = (0..50).map(|x| x as f64).collect::<Vec<_>>();
+        let noise = (0..50).map(|x| ((x * 7) % 11) as f64).collect::<Vec<_>>();
+        let ys = xs.iter().map(|x| x * 2.0).collect::<Vec<_>>();
+        let table = Table::new(vec![&noise, &xs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        let importances = evaluator.drop_column_importances()?;
+        assert_eq!(importances["1"].rank, 1);
+        assert!(importances["1"].importance > 0.5, "{importances:?}");
+        assert!(importances["0"].r2 > 0.9, "{importances:?}");
+        let r2 = evaluator.fit_quality()?.r2.expect("out-of-bag rows");
+        assert_eq!(importances["0"].importance, r2 - importances["0"].r2);
+
+        let table = Table::new(vec![&xs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        let e = evaluator
+            .drop_column_importances()
+            .expect_err("single parameter");
+        assert_eq!(e.code, ErrorCode::InvalidInput);
+        Ok(())
+    }
+
+    #[test]
+    fn excluded_params_works() -> anyhow::Result<()> {
+        let xs = (0..50).map(|x| x as f64).collect::<Vec<_>>();
+        let constant = vec![3.0; 50];
+        let ys = xs.iter().map(|x| x * 2.0).collect::<Vec<_>>();
+        let table = Table::new(vec![&xs, &constant, &xs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        assert_eq!(
+            evaluator.excluded_params()?,
+            [
+                ExcludedParam {
+                    param: "1".to_owned(),
+                    reason: "constant",
+                    original: None,
+                },
+                ExcludedParam {
+                    param: "2".to_owned(),
+                    reason: "duplicate",
+                    original: Some("0".to_owned()),
+                },
+            ]
+        );
+        let mut restored = Evaluator::decode(&evaluator.export_state()).expect("valid state");
+        assert_eq!(restored.excluded_params()?, evaluator.excluded_params()?);
+        Ok(())
+    }
+
+    #[test]
+    fn profile_works() -> anyhow::Result<()> {
+        let xs = (0..50).map(|x| x as f64).collect::<Vec<_>>();
+        let ys = xs.iter().map(|x| x * 2.0).collect::<Vec<_>>();
+        let table = Table::new(vec![&xs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        let profile = evaluator.profile()?;
+        assert_eq!(profile.tree_millis.len(), 100);
+        assert_eq!(profile.marginals_millis, 0.0);
+
+        evaluator.compute_importances()?;
+        assert!(evaluator.profile()?.marginals_millis > 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn evaluator_cache_works() -> anyhow::Result<()> {
+        let xs = (0..50).map(|x| x as f64).collect::<Vec<_>>();
+        let ys = xs.iter().map(|x| x * 2.0).collect::<Vec<_>>();
+        let table = Table::new(vec![&xs, &ys])?.into_owned();
+        clear_cache();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table.clone(), FitOptions::default())?;
+        let importances = evaluator.compute_importances()?;
+        let key = evaluator.fingerprint().expect("complete model");
+        assert_eq!(cache::importances(key), Some(importances.clone()));
+
+        // An evaluator of the same dataset reuses the cached model and importances.
+        let mut cached = Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        assert_eq!(cached.fingerprint(), Some(key));
+        assert_eq!(cached.compute_importances()?, importances);
+        clear_cache();
+        assert!(cache::model(key).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn fit_quality_works() -> anyhow::Result<()> {
+        let xs = (0..50).map(|x| x as f64).collect::<Vec<_>>();
+        let noise = (0..50).map(|x| ((x * 7) % 11) as f64).collect::<Vec<_>>();
+        let ys = xs.iter().map(|x| x * 2.0).collect::<Vec<_>>();
+        let table = Table::new(vec![&xs, &noise, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        let quality = evaluator.fit_quality()?;
+        assert_eq!(quality.level, "good");
+        assert!(quality.r2.expect("out-of-bag rows") > 0.9);
+        assert_eq!((quality.oob_rows, quality.trees), (50, 100));
+        assert!(quality.depth.min >= 1 && quality.depth.min <= quality.depth.max);
+        assert!(quality.leaves.mean > 1.0);
+        assert_eq!(quality.target_bounds, None);
+        assert_eq!(quality.duplicated_rows, 0);
+
+        let restored = Evaluator::decode(&evaluator.export_state()).expect("valid state");
+        assert_eq!(
+            restored.fanova.fit_quality(),
+            evaluator.fanova.fit_quality()
+        );
+
+        let options = FitOptions {
+            early_stopping: Some(0.05),
+            ..FitOptions::default()
+        };
+        let table = Table::new(vec![&xs, &noise, &ys])?.into_owned();
+        let mut stopped = Evaluator::from_table("study".to_owned(), table, options)?;
+        let trees = stopped.fit_quality()?.trees;
+        assert!(trees < 100 && trees.is_multiple_of(10), "{trees}");
+        assert!(!stopped.fit_quality()?.partial);
+        let restored = Evaluator::decode(&stopped.export_state()).expect("valid state");
+        assert_eq!(restored.options, options);
+
+        let options: FitOptions = serde_json::from_str(r#"{"time_budget_millis": 0}"#)?;
+        let table = Table::new(vec![&xs, &noise, &ys])?.into_owned();
+        let mut budgeted = Evaluator::from_table("study".to_owned(), table, options)?;
+        let quality = budgeted.fit_quality()?;
+        assert!(quality.partial);
+        assert_eq!(quality.trees, 1);
+        let mut restored = Evaluator::decode(&budgeted.export_state()).expect("valid state");
+        assert_eq!(restored.options, options);
+        assert!(restored.fit_quality()?.partial);
+        // Each resume adds a tree until the model is the same as the one fitted at once.
+        for trees in 2..100 {
+            assert!(!restored.resume()?);
+            assert_eq!(restored.fit_quality()?.trees, trees);
+        }
+        assert!(restored.resume()?);
+        let table = Table::new(vec![&xs, &noise, &ys])?.into_owned();
+        let mut full = Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        assert_eq!(restored.fanova.fit_quality(), full.fanova.fit_quality());
+        assert_eq!(restored.compute_importances()?, full.compute_importances()?);
+        assert!(restored.resume()?);
+
+        let options: FitOptions = serde_json::from_str(r#"{"winsorize": [0.0, 0.5]}"#)?;
+        let table = Table::new(vec![&xs, &noise, &ys])?.into_owned();
+        let mut winsorized = Evaluator::from_table("study".to_owned(), table, options)?;
+        let bounds = winsorized.fit_quality()?.target_bounds;
+        assert_eq!(
+            bounds,
+            Some(TargetBounds {
+                lower: 0.0,
+                upper: 49.0
+            })
+        );
+        let mut restored = Evaluator::decode(&winsorized.export_state()).expect("valid state");
+        assert_eq!(restored.options, options);
+        assert_eq!(restored.fit_quality()?.target_bounds, bounds);
+
+        let options: FitOptions = serde_json::from_str(r#"{"standardize_target": true}"#)?;
+        let table = Table::new(vec![&xs, &noise, &ys])?.into_owned();
+        let mut standardized = Evaluator::from_table("study".to_owned(), table, options)?;
+        let quality = standardized.fit_quality()?;
+        let (mean, std) = functions::mean_and_stddev(ys.iter().copied());
+        assert_eq!(
+            quality.target_standardization,
+            Some(TargetStandardization { mean, std })
+        );
+        assert!(quality.rmse.expect("out-of-bag rows") < 0.5);
+        let restored = Evaluator::decode(&standardized.export_state()).expect("valid state");
+        assert_eq!(restored.options, options);
+
+        let options: FitOptions = serde_json::from_str(r#"{"preset": "reference_fanova"}"#)?;
+        let table = Table::new(vec![&xs, &noise, &ys])?.into_owned();
+        let mut reference = Evaluator::from_table("study".to_owned(), table, options)?;
+        assert_eq!(reference.fit_quality()?.trees, 16);
+        let restored = Evaluator::decode(&reference.export_state()).expect("valid state");
+        assert_eq!(restored.options, options);
+        let options: FitOptions =
+            serde_json::from_str(r#"{"preset": "reference_fanova", "n_trees": 8}"#)?;
+        let table = Table::new(vec![&xs, &noise, &ys])?.into_owned();
+        let mut reference = Evaluator::from_table("study".to_owned(), table, options)?;
+        assert_eq!(reference.fit_quality()?.trees, 8);
+        let options = r#"{"preset": "reference_fanova", "surrogate": "extra_trees"}"#;
+        assert!(serde_json::from_str::<FitOptions>(options)?
+            .check()
+            .is_err());
+
+        let options: FitOptions =
+            serde_json::from_str(r#"{"best_trials": {"fraction": 0.2, "maximize": true}}"#)?;
+        let table = Table::new(vec![&xs, &noise, &ys])?.into_owned();
+        let best = Evaluator::from_table("study".to_owned(), table, options)?;
+        assert_eq!(best.fanova.feature_space().ranges()[0], 40.0..49.0);
+        let restored = Evaluator::decode(&best.export_state()).expect("valid state");
+        assert_eq!(restored.options, options);
+        for invalid in [
+            r#"{"best_trials": {}}"#,
+            r#"{"best_trials": {"count": 5, "fraction": 0.5}}"#,
+            r#"{"best_trials": {"fraction": 0.0}}"#,
+        ] {
+            assert!(serde_json::from_str::<FitOptions>(invalid)?
+                .check()
+                .is_err());
+        }
+
+        // A grid search repeating each of 10 configurations 5 times.
+        let grid = xs.iter().map(|x| x % 10.0).collect::<Vec<_>>();
+        let table = Table::new(vec![&grid, &ys])?.into_owned();
+        let mut repeated =
+            Evaluator::from_table("study".to_owned(), table.clone(), FitOptions::default())?;
+        assert_eq!(repeated.fit_quality()?.duplicated_rows, 40);
+        let options: FitOptions = serde_json::from_str(r#"{"aggregate_duplicates": "median"}"#)?;
+        let mut merged = Evaluator::from_table("study".to_owned(), table, options)?;
+        assert_eq!(merged.fit_quality()?.duplicated_rows, 40);
+        let mut restored = Evaluator::decode(&merged.export_state()).expect("valid state");
+        assert_eq!(restored.options, options);
+        assert_eq!(restored.fit_quality()?.duplicated_rows, 40);
+
+        let table = Table::new(vec![&noise, &xs])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+        assert_eq!(evaluator.fit_quality()?.level, "poor");
+        Ok(())
+    }
+
+    #[test]
+    fn ablation_path_works() -> anyhow::Result<()> {
+        let xs = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+        let zs = [1.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+        let ys = xs.map(|x| x * x);
+        let table = Table::new(vec![&xs, &zs, &ys])?.into_owned();
+        let mut evaluator =
+            Evaluator::from_table("study".to_owned(), table, FitOptions::default())?;
+
+        assert!(evaluator.ablation_path(&[5.0], None, false).is_err());
+        assert!(evaluator
+            .ablation_path(&[5.0, f64::NAN], None, false)
+            .is_err());
+
+        let path = evaluator.ablation_path(&[5.0, 0.0], None, false)?;
+        assert_eq!(path.steps[0].param, "0");
+        assert_eq!(path.steps[0].value, 0.0);
+        assert!(path.steps[0].improvement > 0.0);
+        let end = path.steps.last().expect("not empty").prediction;
+        let total = path.steps.iter().map(|s| s.improvement).sum::<f64>();
+        assert!((path.start - end - total).abs() < 1e-9);
+
+        let path = evaluator.ablation_path(&[0.0, 1.0], Some(&[5.0, 1.0]), true)?;
+        assert_eq!(path.steps.len(), 1);
+        assert!(path.steps[0].improvement > 0.0);
+        assert!(path.steps[0].prediction > path.start);
+        Ok(())
+    }
+
+    #[test]
+    fn non_finite_targets_work() -> anyhow::Result<()> {
+        let xs = [0.0, 1.0, 2.0, 3.0];
+        let ys = [0.0, 1.0, 5.0, 1.0];
+        let table = Table::new(vec![&xs, &ys])?.into_owned();
+        let options = |policy| FitOptions {
+            non_finite_targets: policy,
+            ..Default::default()
+        };
+
+        let mut dropping = Evaluator::from_table(
+            "study".to_owned(),
+            table.clone(),
+            options(NonFiniteTargetPolicy::Drop),
+        )?;
+        dropping.push_trials(&[vec![4.0], vec![5.0]], &[f64::NAN, 4.0])?;
+        assert_eq!(dropping.trials_len(), 5);
+        assert_eq!(dropping.non_finite_trials(), 1);
+
+        let mut imputing = Evaluator::from_table(
+            "study".to_owned(),
+            table,
+            options(NonFiniteTargetPolicy::Median),
+        )?;
+        imputing.push_trials(&[vec![4.0], vec![5.0]], &[f64::INFINITY, 4.0])?;
+        assert_eq!(imputing.trials_len(), 6);
+        assert_eq!(imputing.table.target().last(), Some(4.0));
+        assert_eq!(imputing.table.target().nth(4), Some(1.0));
+        assert_eq!(imputing.non_finite_trials(), 1);
+
+        let restored = Evaluator::decode(&imputing.export_state()).expect("valid state");
+        assert_eq!(restored.non_finite_trials(), 1);
+
+        let mut clamping = Evaluator::from_table(
+            "study".to_owned(),
+            restored.table,
+            FitOptions {
+                infinities: Some(InfinityPolicy::Clamp(0.0)),
+                ..options(NonFiniteTargetPolicy::Error)
+            },
+        )?;
+        clamping.push_trials(&[vec![f64::NEG_INFINITY]], &[f64::INFINITY])?;
+        assert_eq!(clamping.table.target().last(), Some(5.0));
+        assert_eq!(clamping.non_finite_trials(), 1);
+        assert!(clamping.push_trials(&[vec![0.0]], &[f64::NAN]).is_err());
+        assert_eq!(clamping.trials_len(), 7);
+        Ok(())
+    }
+
+    #[test]
+    fn fit_options_works() -> anyhow::Result<()> {
+        let options = FitOptions {
+            n_trees: NonZeroUsize::new(3),
+            min_samples_leaf: NonZeroUsize::new(2),
+            max_bins: NonZeroUsize::new(32),
+            max_thresholds_per_feature: NonZeroUsize::new(16),
+            split_sample: NonZeroUsize::new(500),
+            criterion: Criterion::Tweedie(1.5),
+            infinities: Some(InfinityPolicy::Clamp(0.01)),
+            early_stopping: Some(0.01),
+            strict: true,
+            seed: u64::MAX,
+            ..Default::default()
+        };
+        let mut encoder = Encoder::new();
+        options.encode(&mut encoder);
+        let bytes = encoder.finish();
+        assert_eq!(FitOptions::decode(&mut Decoder::new(&bytes)), Some(options));
+
+        let xs = [0.0, 1.0, 2.0, 3.0];
+        let ys = [0.0, 1.0, 4.0, 9.0];
+        let table = Table::new(vec![&xs, &ys])?.into_owned();
+        let evaluator = Evaluator::from_table("study".to_owned(), table.clone(), options)?;
+        assert_eq!(evaluator.fanova.trees_len(), 3);
+        assert_eq!(evaluator.seed(), u64::MAX);
+
+        // The same seed yields the same model.
+        let same = Evaluator::from_table("study".to_owned(), table.clone(), options)?;
+        assert_eq!(same.export_state(), evaluator.export_state());
+
+        let options = FitOptions { seed: 1, ..options };
+        let other = Evaluator::from_table("study".to_owned(), table.clone(), options)?;
+        assert_eq!(other.seed(), 1);
+
+        let options = FitOptions {
+            n_trees: NonZeroUsize::new(3),
+            surrogate: Surrogate::ExtraTrees,
+            ..Default::default()
+        };
+        let mut encoder = Encoder::new();
+        options.encode(&mut encoder);
+        let bytes = encoder.finish();
+        assert_eq!(FitOptions::decode(&mut Decoder::new(&bytes)), Some(options));
+        let mut extra = Evaluator::from_table("study".to_owned(), table, options)?;
+        assert_eq!(extra.fanova.trees_len(), 3);
+        assert_eq!(extra.fanova()?.fit_quality(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn trial_stream_works() -> anyhow::Result<()> {
+        let xs = (0..200).map(|i| f64::from(i % 10)).collect::<Vec<_>>();
+        let zs = (0..200).map(|i| f64::from(i % 2)).collect::<Vec<_>>();
+        let ys = xs.iter().map(|x| x * x).collect::<Vec<_>>();
+        let mut stream = TrialStream {
+            stream: TableStream::new(2, NonZeroUsize::new(50).expect("non zero"), 0),
+        };
+        assert!(stream.push_columns(std::slice::from_ref(&xs), &ys).is_err());
+        for start in (0..200).step_by(40) {
+            let range = start..start + 40;
+            let features = [xs[range.clone()].to_vec(), zs[range.clone()].to_vec()];
+            stream.push_columns(&features, &ys[range])?;
+        }
+        assert_eq!(stream.trials_seen(), 200);
+        assert_eq!(stream.trials_len(), 50);
+
+        let options = FitOptions {
+            n_trees: NonZeroUsize::new(10),
+            ..Default::default()
+        };
+        let mut evaluator = stream.evaluator("study".to_owned(), options)?;
+        assert_eq!(evaluator.trials_len(), 50);
+        let importances = evaluator.compute_importances()?;
+        assert!(importances[0] > importances[1]);
+        Ok(())
+    }
+
+    #[test]
+    fn batch_importances_works() -> anyhow::Result<()> {
+        let xs = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+        let zs = [1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+        let targets = [
+            xs.map(|x| x * x).to_vec(),
+            xs.iter()
+                .zip(zs.iter())
+                .map(|(x, z)| z * 10.0 + x * 0.1)
+                .collect(),
+        ];
+        let options = FitOptions::default();
+
+        let batch = batch_importances(vec![&xs, &zs], &targets, &options)?;
+        assert_eq!(batch.len(), 2);
+        for (target, importances) in targets.iter().zip(batch.iter()) {
+            let table = Table::new(vec![&xs, &zs, target])?;
+            let expected = importances_of(fit(table, &options)?, 2);
+            assert_eq!(importances, &expected);
+        }
+        assert!(batch[0][0] > batch[0][1]);
+        assert!(batch[1][1] > batch[1][0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn categorical_importances_works() -> anyhow::Result<()> {
+        let xs = (0..40).map(f64::from).collect::<Vec<_>>();
+        let zs = xs.iter().map(|x| x % 2.0).collect::<Vec<_>>();
+        let outcomes = xs
+            .iter()
+            .map(|&x| if x < 30.0 { "converged" } else { "oom" }.to_owned())
+            .collect::<Vec<_>>();
+        let options = FitOptions::default();
+
+        let result = categorical_importances(vec![&xs, &zs], &outcomes, &options)?;
+        assert_eq!(
+            result.classes.keys().collect::<Vec<_>>(),
+            vec!["converged", "oom"]
+        );
+        assert!(result.importances[0] > 0.9, "{:?}", result.importances);
+        assert!(result.classes["oom"][0] > result.classes["oom"][1]);
+        assert!(categorical_importances(vec![&xs], &outcomes[..1], &options).is_err());
+
+        let options: FitOptions = serde_json::from_str(r#"{"criterion": "gain_ratio"}"#)?;
+        let result = categorical_importances(vec![&xs, &zs], &outcomes, &options)?;
+        assert!(result.importances[0] > 0.9, "{:?}", result.importances);
+        Ok(())
+    }
+
+    #[test]
+    fn multi_objective_importances_works() -> anyhow::Result<()> {
+        let xs = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+        let zs = [1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+        let objectives = vec![
+            xs.to_vec(),
+            xs.iter()
+                .zip(zs.iter())
+                .map(|(x, z)| z * 10.0 + x * 0.1)
+                .collect(),
+        ];
+        let options = FitOptions::default();
+        let scalarization: Scalarization = serde_json::from_str(
+            r#"{"method": "weighted_sum", "weights": [1.0, 0.0], "directions": ["maximize", "minimize"]}"#,
+        )?;
+
+        let result = multi_objective_importances(
+            vec![&xs, &zs],
+            objectives.clone(),
+            &scalarization,
+            &options,
+        )?;
+        assert_eq!(
+            result.objectives,
+            batch_importances(vec![&xs, &zs], &objectives, &options)?
+        );
+        assert!(result.scalarized[0] > result.scalarized[1]);
+
+        let short = vec![xs.to_vec(), vec![0.0]];
+        assert!(
+            multi_objective_importances(vec![&xs, &zs], short, &scalarization, &options).is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn importance_matrix_works() -> anyhow::Result<()> {
+        let xs = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+        let zs = [1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+        let targets = [
+            xs.map(|x| x * x).to_vec(),
+            xs.iter()
+                .zip(zs.iter())
+                .map(|(x, z)| z * 10.0 + x * 0.1)
+                .collect(),
+            xs.map(|x| -x).to_vec(),
+        ];
+        let options = FitOptions::default();
+
+        let matrix = importance_matrix(vec![&xs, &zs], &targets, &options)?;
+        let batch = batch_importances(vec![&xs, &zs], &targets, &options)?;
+        assert_eq!(matrix.importances.len(), 2);
+        assert_eq!(matrix.std.len(), 2);
+        for (k, importances) in batch.iter().enumerate() {
+            for (i, importance) in importances.iter().enumerate() {
+                assert!((matrix.importances[i][k] - importance).abs() < 1e-12);
+                assert!(matrix.std[i][k] >= 0.0);
+            }
+        }
+        assert!(matrix.importances[1][1] > matrix.importances[0][1]);
+
+        assert_eq!(
+            importance_matrix(vec![&xs, &zs], &[], &options)?.importances,
+            vec![Vec::<f64>::new(); 2]
+        );
+        let short = [xs.to_vec(), vec![0.0]];
+        assert!(importance_matrix(vec![&xs, &zs], &short, &options).is_err());
+        Ok(())
+    }
+
+    fn importances_of(fanova: Fanova, features_len: usize) -> Vec<f64> {
+        importances(fanova, features_len)
+            .into_iter()
+            .map(|i| i.mean)
+            .collect()
+    }
+
+    #[test]
+    fn cancellation_token_works() {
+        let token = CancellationToken::new();
+        let cloned = token.clone();
+        assert!(!cloned.is_cancelled());
+
+        token.cancel();
+        assert!(cloned.is_cancelled());
+    }
+
+    #[test]
+    fn ranked_importances_works() {
+        let importance = |mean| Importance { mean, stddev: 0.1 };
+        let ranked = ImportanceEntry::ranked(
+            [
+                ("a".to_owned(), importance(0.2)),
+                ("b".to_owned(), importance(0.5)),
+                ("c".to_owned(), importance(0.3)),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(
+            ranked.values().map(|e| e.rank).collect::<Vec<_>>(),
+            [3, 1, 2]
+        );
+        assert_eq!(ranked["b"].importance, 0.5);
+        assert_eq!(ranked["b"].std, 0.1);
+    }
+}
+
+// The tests requiring a JavaScript runtime (e.g., `cargo test --target wasm32-unknown-unknown`
+// with `wasm-bindgen-test-runner`).
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_futures::JsFuture;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn dataset() -> (Array, Array) {
+        let feature1 = (0..20).map(|i| f64::from(i) / 20.0).collect::<Vec<_>>();
+        let feature2 = (0..20).map(|i| f64::from(i * 7 % 20)).collect::<Vec<_>>();
+        let target = feature1
+            .iter()
+            .zip(&feature2)
+            .map(|(&x1, &x2)| x1 + x2 * 2.0)
+            .collect::<Vec<_>>();
+        let features = Array::of2(
+            &Float64Array::from(&feature1[..]),
+            &Float64Array::from(&feature2[..]),
+        );
+        let targets = target.into_iter().map(JsValue::from).collect::<Array>();
+        (features, targets)
+    }
+
+    #[wasm_bindgen_test]
+    fn parse_integer_columns_works() {
+        let integers = Int32Array::from(&[2, 0, 1][..]);
+        assert_eq!(
+            Values::parse(integers.clone().into()).ok(),
+            Some(Values::Integer(vec![2.0, 0.0, 1.0]))
+        );
+        let big = BigInt64Array::from(&[-(1 << 53), 1 << 53][..]);
+        assert_eq!(
+            Values::parse(big.into()).ok(),
+            Some(Values::Integer(vec![
+                -9007199254740992.0,
+                9007199254740992.0
+            ]))
+        );
+
+        let categorical = js_sys::Object::new();
+        Reflect::set(&categorical, &"categorical".into(), &integers).expect("object");
+        assert_eq!(
+            Values::parse(categorical.into()).ok(),
+            Some(Values::Categorical(vec![2.0, 0.0, 1.0]))
+        );
+
+        let too_large = BigInt64Array::from(&[0, (1 << 53) + 1][..]);
+        let not_integers = Float64Array::from(&[0.0, 1.0][..]);
+        let categorical = js_sys::Object::new();
+        Reflect::set(&categorical, &"categorical".into(), &not_integers).expect("object");
+        for value in [too_large.clone().into(), categorical.into()] {
+            let e = Values::parse(value).expect_err("invalid column");
+            assert_eq!(e.code, ErrorCode::InvalidInput);
+        }
+        let e = parse_targets(too_large.unchecked_into()).expect_err("too large target");
+        assert_eq!(e.code, ErrorCode::InvalidInput);
+    }
+
+    #[wasm_bindgen_test]
+    async fn fit_async_works() {
+        let (features, targets) = dataset();
+        let expected = wasm_fanova_calculate(features.clone(), targets.clone(), JsValue::UNDEFINED)
+            .expect("valid dataset");
+
+        let promise = fit_async(features, targets, JsValue::UNDEFINED);
+        let importances = JsFuture::from(promise).await.expect("resolved");
+        let importances = Array::from(&importances)
+            .iter()
+            .map(|x| x.as_f64().expect("number"))
+            .collect::<Vec<_>>();
+        assert_eq!(importances, expected);
+    }
+}
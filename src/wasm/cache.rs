@@ -0,0 +1,150 @@
+//! Cache of the fitted models and their importances keyed by the fingerprints of the datasets,
+//! so that re-requesting an unchanged study view doesn't fit the forest again.
+use super::{column_type_code, FitOptions};
+use crate::codec::Encoder;
+use crate::table::Table;
+use crate::Fanova;
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+
+/// The maximum number of the cached models (the least recently used one is evicted first).
+const CAPACITY: usize = 4;
+
+thread_local! {
+    static ENTRIES: RefCell<VecDeque<Entry>> = const { RefCell::new(VecDeque::new()) };
+}
+
+#[derive(Debug)]
+struct Entry {
+    fingerprint: u64,
+
+    // The serialized model (see `Fanova::to_bytes`).
+    model: Vec<u8>,
+    importances: Option<Vec<f64>>,
+}
+
+/// Returns the fingerprint of the columns (including their types and the row weights) of
+/// `table` and the fit options.
+pub(crate) fn fingerprint(table: &Table, options: &FitOptions) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    table.features_len().hash(&mut hasher);
+    table.rows_len().hash(&mut hasher);
+    for i in 0..table.features_len() {
+        column_type_code(table.column_type(i)).hash(&mut hasher);
+        table.column(i).for_each(|x| x.to_bits().hash(&mut hasher));
+    }
+    for (y, w) in table.weighted_target() {
+        (y.to_bits(), w.to_bits()).hash(&mut hasher);
+    }
+    let mut encoder = Encoder::new();
+    options.encode(&mut encoder);
+    encoder.finish().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Returns a copy of the model fitted to the dataset of `fingerprint` (if cached).
+pub(crate) fn model(fingerprint: u64) -> Option<Fanova> {
+    with_entry(fingerprint, |entry| Fanova::from_bytes(&entry.model).ok()).flatten()
+}
+
+/// Caches a model (unless it has been cut short by the time budget).
+pub(crate) fn store_model(fingerprint: u64, fanova: &Fanova) {
+    if fanova.is_partial() {
+        return;
+    }
+    ENTRIES.with(|entries| {
+        let mut entries = entries.borrow_mut();
+        entries.retain(|e| e.fingerprint != fingerprint);
+        if entries.len() == CAPACITY {
+            entries.pop_back();
+        }
+        entries.push_front(Entry {
+            fingerprint,
+            model: fanova.to_bytes(false),
+            importances: None,
+        });
+    });
+}
+
+/// Returns the importances of the model of `fingerprint` (if cached).
+pub(crate) fn importances(fingerprint: u64) -> Option<Vec<f64>> {
+    with_entry(fingerprint, |entry| entry.importances.clone()).flatten()
+}
+
+/// Caches the importances of the model of `fingerprint` (if the model is cached).
+pub(crate) fn store_importances(fingerprint: u64, importances: &[f64]) {
+    with_entry(fingerprint, |entry| {
+        entry.importances = Some(importances.to_vec());
+    });
+}
+
+/// Removes all the cached models.
+pub(crate) fn clear() {
+    ENTRIES.with(|entries| entries.borrow_mut().clear());
+}
+
+// Applies `f` to the entry of `fingerprint` and marks it as the most recently used one.
+fn with_entry<T>(fingerprint: u64, f: impl FnOnce(&mut Entry) -> T) -> Option<T> {
+    ENTRIES.with(|entries| {
+        let mut entries = entries.borrow_mut();
+        let i = entries.iter().position(|e| e.fingerprint == fingerprint)?;
+        let mut entry = entries.remove(i).expect("in range");
+        let value = f(&mut entry);
+        entries.push_front(entry);
+        Some(value)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::ColumnType;
+    use crate::FanovaOptions;
+
+    #[test]
+    fn cache_works() -> anyhow::Result<()> {
+        let xs = (0..20).map(f64::from).collect::<Vec<_>>();
+        let ys = xs.iter().map(|x| x * x).collect::<Vec<_>>();
+        let table = Table::new(vec![&xs, &ys])?;
+        let options = FitOptions::default();
+        let key = fingerprint(&table, &options);
+        assert_eq!(fingerprint(&table, &options), key);
+
+        // Any change of the values, the column types or the options changes the fingerprint.
+        let mut changed = ys.clone();
+        changed[3] += 1.0;
+        assert_ne!(
+            fingerprint(&Table::new(vec![&xs, &changed])?, &options),
+            key
+        );
+        let mut integer = Table::new(vec![&xs, &ys])?;
+        integer.set_column_type(0, ColumnType::Integer)?;
+        assert_ne!(fingerprint(&integer, &options), key);
+        let seeded = FitOptions { seed: 1, ..options };
+        assert_ne!(fingerprint(&table, &seeded), key);
+
+        clear();
+        assert!(model(key).is_none());
+        let mut fanova = FanovaOptions::default().fit_table(table)?;
+        store_model(key, &fanova);
+        store_importances(key, &[0.5]);
+        assert_eq!(model(key).map(|f| f.trees_len()), Some(fanova.trees_len()));
+        assert_eq!(importances(key), Some(vec![0.5]));
+        assert_eq!(
+            model(key).map(|mut f| f.importances()),
+            Some(fanova.importances())
+        );
+
+        // The least recently used models are evicted.
+        for i in 0..CAPACITY as u64 {
+            store_model(key + 1 + i, &fanova);
+        }
+        assert!(model(key).is_none());
+        assert!(model(key + 1).is_some());
+        clear();
+        assert!(model(key + 1).is_none());
+        Ok(())
+    }
+}
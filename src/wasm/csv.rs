@@ -0,0 +1,79 @@
+//! CSV rendering of the results (e.g., to paste them into spreadsheets).
+
+/// Builder of a CSV document (RFC 4180, i.e., the lines end with CRLF).
+#[derive(Debug, Clone)]
+pub(crate) struct Csv {
+    text: String,
+}
+
+impl Csv {
+    /// Makes a document starting with the given header.
+    pub(crate) fn new(header: &[&str]) -> Self {
+        let mut csv = Self {
+            text: String::new(),
+        };
+        csv.row(header.iter().map(|&name| Field::Text(name)));
+        csv
+    }
+
+    /// Appends a row.
+    pub(crate) fn row<'a>(&mut self, fields: impl IntoIterator<Item = Field<'a>>) {
+        for (i, field) in fields.into_iter().enumerate() {
+            if i > 0 {
+                self.text.push(',');
+            }
+            match field {
+                Field::Text(text) => self.push_text(text),
+                // Non-finite values are left empty (which spreadsheets read as blank cells).
+                Field::Number(x) if x.is_finite() => self.text.push_str(&x.to_string()),
+                Field::Number(_) => {}
+                Field::Integer(n) => self.text.push_str(&n.to_string()),
+            }
+        }
+        self.text.push_str("\r\n");
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if text.contains([',', '"', '\r', '\n']) {
+            self.text.push('"');
+            self.text.push_str(&text.replace('"', "\"\""));
+            self.text.push('"');
+        } else {
+            self.text.push_str(text);
+        }
+    }
+
+    /// Returns the document.
+    pub(crate) fn finish(self) -> String {
+        self.text
+    }
+}
+
+/// A field of a CSV row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Field<'a> {
+    Text(&'a str),
+    Number(f64),
+    Integer(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_works() {
+        let mut csv = Csv::new(&["param", "value", "rank"]);
+        csv.row([Field::Text("x"), Field::Number(0.5), Field::Integer(1)]);
+        csv.row([
+            Field::Text("a \"b\", c"),
+            Field::Number(f64::NAN),
+            Field::Integer(2),
+        ]);
+        csv.row([Field::Text("d\ne"), Field::Number(-1e-3), Field::Integer(3)]);
+        assert_eq!(
+            csv.finish(),
+            "param,value,rank\r\nx,0.5,1\r\n\"a \"\"b\"\", c\",,2\r\n\"d\ne\",-0.001,3\r\n"
+        );
+    }
+}
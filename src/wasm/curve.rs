@@ -0,0 +1,139 @@
+//! Importances of the parameters as functions of the training step.
+use super::journal;
+use super::study::{self, Study, Target};
+use super::{Error, Evaluator, FitOptions};
+use serde::Serialize;
+use serde_wasm_bindgen::{from_value, to_value};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+
+// The steps reported by fewer trials are skipped when the steps are not given.
+const MIN_TRIALS: usize = 2;
+
+/// Computes the importance curves for the trials in an Optuna journal storage file.
+///
+/// See `wasm_fanova_importance_curves_from_trials` for `steps` and the result.
+#[wasm_bindgen]
+pub fn wasm_fanova_importance_curves_from_journal(
+    journal: &str,
+    study_name: Option<String>,
+    steps: JsValue,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    let steps: Option<Vec<u64>> = from_value(steps).map_err(Error::from)?;
+    let options = FitOptions::parse(options)?;
+    let study = journal::find_study(journal, study_name)?;
+    let curves = importance_curves(&study, steps, &options)?;
+    Ok(to_value(&curves).map_err(Error::from)?)
+}
+
+/// Computes the importance curves for the trials exported from an Optuna study
+/// (see `Evaluator.from_trials` for the format).
+///
+/// The importances are computed for the intermediate values at each of `steps`
+/// (default: all the steps reported by at least two trials), using the trials which reported
+/// a value at the step (including pruned trials).
+/// The result is an object like `{steps: [1, 2, 3], params: ["x", "y"], importances: [...]}`
+/// where `importances[i][j]` is the importance of `params[i]` at `steps[j]`
+/// (NaN if `params[i]` does not appear in the trials reporting at the step).
+#[wasm_bindgen]
+pub fn wasm_fanova_importance_curves_from_trials(
+    trials: &str,
+    steps: JsValue,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    let steps: Option<Vec<u64>> = from_value(steps).map_err(Error::from)?;
+    let options = FitOptions::parse(options)?;
+    let study = study::parse_trials(trials)?;
+    let curves = importance_curves(&study, steps, &options)?;
+    Ok(to_value(&curves).map_err(Error::from)?)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct ImportanceCurves {
+    steps: Vec<u64>,
+    params: Vec<String>,
+    importances: Vec<Vec<f64>>,
+}
+
+fn importance_curves(
+    study: &Study,
+    steps: Option<Vec<u64>>,
+    options: &FitOptions,
+) -> Result<ImportanceCurves, Error> {
+    let steps = steps.unwrap_or_else(|| reported_steps(study));
+    let params = study.param_names();
+    let mut importances = vec![Vec::with_capacity(steps.len()); params.len()];
+    for &step in &steps {
+        let target = Target::IntermediateValue(step);
+        let mut evaluator = Evaluator::from_study(study.clone(), &target, *options)?;
+        let values = evaluator.compute_importances()?;
+        let values = evaluator
+            .param_names
+            .into_iter()
+            .zip(values)
+            .collect::<BTreeMap<_, _>>();
+        for (name, curve) in params.iter().zip(importances.iter_mut()) {
+            curve.push(values.get(name).copied().unwrap_or(f64::NAN));
+        }
+    }
+    Ok(ImportanceCurves {
+        steps,
+        params,
+        importances,
+    })
+}
+
+fn reported_steps(study: &Study) -> Vec<u64> {
+    let mut counts = BTreeMap::<u64, usize>::new();
+    for trial in &study.trials {
+        for &step in trial.intermediate_values.keys() {
+            *counts.entry(step).or_default() += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .filter(|&(_, count)| count >= MIN_TRIALS)
+        .map(|(step, _)| step)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn importance_curves_work() -> anyhow::Result<()> {
+        // `x` matters at early steps and `y` matters at late steps.
+        let trials = (0..40)
+            .map(|i| {
+                let (x, y) = ((i % 8) as f64, (i % 5) as f64);
+                let late = if i % 4 == 0 {
+                    String::new()
+                } else {
+                    format!(r#", "10": {}"#, y * 10.0 + x * 0.1)
+                };
+                format!(
+                    r#"{{"state": "PRUNED", "params": {{"x": {x}, "y": {y}}},
+ "intermediate_values": {{"1": {}, "5": {}{late}}}}}"#,
+                    x * 10.0,
+                    x + y
+                )
+            })
+            .collect::<Vec<_>>();
+        let study = study::parse_trials(&format!("[{}]", trials.join(",")))?;
+        assert_eq!(reported_steps(&study), [1, 5, 10]);
+
+        let curves = importance_curves(&study, None, &FitOptions::default())?;
+        assert_eq!(curves.steps, [1, 5, 10]);
+        assert_eq!(curves.params, ["x", "y"]);
+        let (x, y) = (&curves.importances[0], &curves.importances[1]);
+        assert!(x[0] > y[0]);
+        assert!(x[2] < y[2]);
+
+        let curves = importance_curves(&study, Some(vec![5]), &FitOptions::default())?;
+        assert_eq!(curves.importances[0].len(), 1);
+        assert!(importance_curves(&study, Some(vec![2]), &FitOptions::default()).is_err());
+        Ok(())
+    }
+}
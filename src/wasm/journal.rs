@@ -0,0 +1,247 @@
+//! Parser of Optuna's journal storage files (JSON lines of the storage operations).
+use super::study::{attr_value, Distribution, Study, Trial};
+use super::{Error, ErrorCode};
+use crate::FanovaError;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+const CREATE_STUDY: u64 = 0;
+const CREATE_TRIAL: u64 = 4;
+const SET_TRIAL_PARAM: u64 = 5;
+const SET_TRIAL_STATE_VALUES: u64 = 6;
+const SET_TRIAL_INTERMEDIATE_VALUE: u64 = 7;
+const SET_TRIAL_USER_ATTR: u64 = 8;
+
+const TRIAL_STATE_COMPLETE: u64 = 1;
+const TRIAL_STATE_PRUNED: u64 = 2;
+
+#[derive(Debug, Default)]
+struct TrialState {
+    study: usize,
+    trial: Trial,
+    values: Option<Vec<f64>>,
+    state: Option<u64>,
+}
+
+/// Parses a journal and returns the studies (in creation order) with their completed and pruned
+/// trials.
+pub(crate) fn parse(journal: &str) -> Result<Vec<Study>, Error> {
+    let mut studies = Vec::new();
+    let mut trials: Vec<TrialState> = Vec::new();
+    for (i, line) in journal.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let invalid = |reason: &str| {
+            Error::from(FanovaError::Parse(format!(
+                "invalid journal at line {}: {reason}",
+                i + 1
+            )))
+        };
+        let op: Value = serde_json::from_str(line).map_err(|e| invalid(&e.to_string()))?;
+        let op_code = op["op_code"]
+            .as_u64()
+            .ok_or_else(|| invalid("missing op_code"))?;
+        match op_code {
+            CREATE_STUDY => {
+                let name = op["study_name"]
+                    .as_str()
+                    .ok_or_else(|| invalid("missing study_name"))?;
+                studies.push(Study {
+                    name: name.to_owned(),
+                    trials: Vec::new(),
+                    distributions: BTreeMap::new(),
+                });
+            }
+            CREATE_TRIAL => {
+                let study = op["study_id"]
+                    .as_u64()
+                    .map(|id| id as usize)
+                    .filter(|&id| id < studies.len())
+                    .ok_or_else(|| invalid("unknown study_id"))?;
+                let mut trial = TrialState {
+                    study,
+                    ..Default::default()
+                };
+                if let Some(params) = op["params"].as_object() {
+                    for (name, value) in params {
+                        let value = value.as_f64().ok_or_else(|| invalid("invalid param"))?;
+                        trial.trial.params.insert(name.clone(), value);
+                    }
+                }
+                for (key, value) in op["user_attrs"].as_object().into_iter().flatten() {
+                    trial.set_user_attr(key, value);
+                }
+                let intermediate_values = op["intermediate_values"].as_object();
+                for (step, value) in intermediate_values.into_iter().flatten() {
+                    let step = step.parse().map_err(|_| invalid("invalid step"))?;
+                    let value = value
+                        .as_f64()
+                        .ok_or_else(|| invalid("invalid intermediate_value"))?;
+                    trial.trial.intermediate_values.insert(step, value);
+                }
+                for (name, distribution) in op["distributions"].as_object().into_iter().flatten() {
+                    let distribution = Distribution::parse(distribution)
+                        .ok_or_else(|| invalid("invalid distribution"))?;
+                    let distributions = &mut studies[study].distributions;
+                    distributions.entry(name.clone()).or_insert(distribution);
+                }
+                trial.update(&op).map_err(&invalid)?;
+                trials.push(trial);
+            }
+            SET_TRIAL_PARAM => {
+                let trial =
+                    trial_mut(&mut trials, &op).ok_or_else(|| invalid("unknown trial_id"))?;
+                let name = op["param_name"]
+                    .as_str()
+                    .ok_or_else(|| invalid("missing param_name"))?;
+                let value = op["param_value_internal"]
+                    .as_f64()
+                    .ok_or_else(|| invalid("missing param_value_internal"))?;
+                trial.trial.params.insert(name.to_owned(), value);
+                if !op["distribution"].is_null() {
+                    let distribution = Distribution::parse(&op["distribution"])
+                        .ok_or_else(|| invalid("invalid distribution"))?;
+                    let distributions = &mut studies[trial.study].distributions;
+                    distributions.entry(name.to_owned()).or_insert(distribution);
+                }
+            }
+            SET_TRIAL_STATE_VALUES => {
+                let trial =
+                    trial_mut(&mut trials, &op).ok_or_else(|| invalid("unknown trial_id"))?;
+                trial.update(&op).map_err(&invalid)?;
+            }
+            SET_TRIAL_INTERMEDIATE_VALUE => {
+                let trial =
+                    trial_mut(&mut trials, &op).ok_or_else(|| invalid("unknown trial_id"))?;
+                let step = op["step"].as_u64().ok_or_else(|| invalid("missing step"))?;
+                let value = op["intermediate_value"]
+                    .as_f64()
+                    .ok_or_else(|| invalid("missing intermediate_value"))?;
+                trial.trial.intermediate_values.insert(step, value);
+            }
+            SET_TRIAL_USER_ATTR => {
+                let trial =
+                    trial_mut(&mut trials, &op).ok_or_else(|| invalid("unknown trial_id"))?;
+                for (key, value) in op["user_attr"].as_object().into_iter().flatten() {
+                    trial.set_user_attr(key, value);
+                }
+            }
+            // The other operations (e.g., system attributes) are irrelevant.
+            _ => {}
+        }
+    }
+
+    for mut trial in trials {
+        match (trial.state, trial.values) {
+            (Some(TRIAL_STATE_COMPLETE), Some(values)) => trial.trial.values = values,
+            (Some(TRIAL_STATE_PRUNED), _) => {}
+            _ => continue,
+        }
+        studies[trial.study].trials.push(trial.trial);
+    }
+    Ok(studies)
+}
+
+/// Parses a journal and returns the study named `study_name`.
+///
+/// `study_name` can be omitted if the journal has only one study.
+pub(crate) fn find_study(journal: &str, study_name: Option<String>) -> Result<Study, Error> {
+    let mut studies = parse(journal)?;
+    let study = match study_name {
+        Some(name) => studies.into_iter().find(|s| s.name == name),
+        None if studies.len() == 1 => studies.pop(),
+        None => {
+            return Err(Error::new(
+                ErrorCode::InvalidInput,
+                "study_name must be given for a journal having multiple studies",
+            ));
+        }
+    };
+    study.ok_or_else(|| Error::new(ErrorCode::InvalidInput, "no such study"))
+}
+
+fn trial_mut<'a>(trials: &'a mut [TrialState], op: &Value) -> Option<&'a mut TrialState> {
+    let id = op["trial_id"].as_u64()?;
+    trials.get_mut(id as usize)
+}
+
+impl TrialState {
+    // Applies the `state`, `value` and `values` fields of an operation.
+    fn update(&mut self, op: &Value) -> Result<(), &'static str> {
+        if let Some(state) = op.get("state").filter(|s| !s.is_null()) {
+            let state = state.as_u64().ok_or("invalid state")?;
+            self.state = Some(state);
+        }
+        if let Some(values) = op.get("values").and_then(Value::as_array) {
+            let values = values.iter().map(Value::as_f64).collect::<Option<_>>();
+            self.values = Some(values.ok_or("invalid values")?);
+        } else if let Some(value) = op.get("value").and_then(Value::as_f64) {
+            self.values = Some(vec![value]);
+        }
+        Ok(())
+    }
+
+    // Non-numeric attributes are ignored (and remove the previous value of the same key).
+    fn set_user_attr(&mut self, key: &str, value: &Value) {
+        let user_attrs = &mut self.trial.user_attrs;
+        match attr_value(value) {
+            Some(value) => user_attrs.insert(key.to_owned(), value),
+            None => user_attrs.remove(key),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm::study::Target;
+
+    #[test]
+    fn parse_works() -> Result<(), Error> {
+        let journal = r#"
+{"op_code": 0, "worker_id": "w", "study_name": "foo", "directions": [1]}
+{"op_code": 0, "worker_id": "w", "study_name": "bar", "directions": [1]}
+{"op_code": 4, "worker_id": "w", "study_id": 0, "datetime_start": "2024-01-01T00:00:00"}
+{"op_code": 5, "worker_id": "w", "trial_id": 0, "param_name": "x", "param_value_internal": 0.5, "distribution": "{\"name\": \"FloatDistribution\", \"attributes\": {\"low\": 0.0, \"high\": 1.0, \"log\": false, \"step\": null}}"}
+{"op_code": 5, "worker_id": "w", "trial_id": 0, "param_name": "c", "param_value_internal": 1.0, "distribution": "{\"name\": \"CategoricalDistribution\", \"attributes\": {\"choices\": [\"a\", \"b\"]}}"}
+{"op_code": 4, "worker_id": "w", "study_id": 1, "datetime_start": "2024-01-01T00:00:00"}
+{"op_code": 7, "worker_id": "w", "trial_id": 1, "step": 0, "intermediate_value": 1.0}
+{"op_code": 6, "worker_id": "w", "trial_id": 0, "state": 1, "values": [3.0]}
+{"op_code": 4, "worker_id": "w", "study_id": 0, "state": 1, "values": [2.0], "params": {"x": 0.25}, "distributions": {}}
+{"op_code": 4, "worker_id": "w", "study_id": 0, "datetime_start": "2024-01-01T00:00:00"}
+{"op_code": 6, "worker_id": "w", "trial_id": 3, "state": 3, "values": null}
+{"op_code": 4, "worker_id": "w", "study_id": 0, "datetime_start": "2024-01-01T00:00:00"}
+{"op_code": 8, "worker_id": "w", "trial_id": 4, "user_attr": {"acc": 0.5}}
+{"op_code": 7, "worker_id": "w", "trial_id": 4, "step": 10, "intermediate_value": 0.75}
+{"op_code": 6, "worker_id": "w", "trial_id": 4, "state": 2, "values": null}
+"#;
+        let studies = parse(journal)?;
+        assert_eq!(studies.len(), 2);
+        assert_eq!(studies[0].name, "foo");
+        assert_eq!(studies[0].trials.len(), 3);
+        assert_eq!(studies[1].trials.len(), 0);
+
+        let study = &studies[0];
+        let names = study.param_names();
+        assert_eq!(names, ["c", "x"]);
+        let (features, target) = study.columns(&names, &Target::Objective(0));
+        assert_eq!(features[0][0], 1.0);
+        assert!(features[0][1].is_nan());
+        assert_eq!(features[1][..2], [0.5, 0.25]);
+        assert_eq!(target[..2], [3.0, 2.0]);
+        assert!(target[2].is_nan());
+
+        let pruned = &study.trials[2];
+        assert_eq!(pruned.target(&Target::UserAttr("acc".into())), Some(0.5));
+        assert_eq!(pruned.target(&Target::IntermediateValue(10)), Some(0.75));
+        assert!(matches!(
+            study.distributions["c"],
+            Distribution::Categorical { ref choices } if choices.len() == 2
+        ));
+
+        let e = parse("{\"op_code\": 4, \"study_id\": 0}").unwrap_err();
+        assert_eq!(e.code, ErrorCode::InvalidInput);
+        Ok(())
+    }
+}
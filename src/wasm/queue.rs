@@ -0,0 +1,262 @@
+//! Queue of importance requests processed cooperatively on the JavaScript event loop.
+use super::{set_timeout, Error, ErrorCode, Evaluator, ImportanceValue, Phase, TIME_SLICE_MILLIS};
+use js_sys::{Function, Promise};
+use serde_wasm_bindgen::to_value;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+
+/// Pending jobs ordered by priority (higher first) and then by arrival.
+///
+/// Jobs having the same key are merged into one job which notifies all the waiters.
+#[derive(Debug)]
+pub(crate) struct JobQueue<K, W> {
+    jobs: Vec<Job<K, W>>,
+    next_seqno: u64,
+}
+
+#[derive(Debug)]
+struct Job<K, W> {
+    key: K,
+    priority: i32,
+    seqno: u64,
+    waiters: Vec<W>,
+}
+
+impl<K: PartialEq, W> JobQueue<K, W> {
+    pub fn new() -> Self {
+        Self {
+            jobs: Vec::new(),
+            next_seqno: 0,
+        }
+    }
+
+    /// Adds a job and returns `false` if the identical job is already pending
+    /// (the priority of the pending job is raised to `priority` if it is lower).
+    pub fn push(&mut self, key: K, priority: i32, waiter: W) -> bool {
+        if let Some(job) = self.jobs.iter_mut().find(|j| j.key == key) {
+            job.priority = job.priority.max(priority);
+            job.waiters.push(waiter);
+            return false;
+        }
+        self.jobs.push(Job {
+            key,
+            priority,
+            seqno: self.next_seqno,
+            waiters: vec![waiter],
+        });
+        self.next_seqno += 1;
+        true
+    }
+
+    /// Changes the priority of a pending job and returns `false` if there is no such job.
+    pub fn set_priority(&mut self, key: &K, priority: i32) -> bool {
+        if let Some(job) = self.jobs.iter_mut().find(|j| j.key == *key) {
+            job.priority = priority;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Removes the job to be processed next.
+    pub fn pop(&mut self) -> Option<(K, Vec<W>)> {
+        let (i, _) = self
+            .jobs
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, j)| (j.priority, std::cmp::Reverse(j.seqno)))?;
+        let job = self.jobs.remove(i);
+        Some((job.key, job.waiters))
+    }
+
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    /// Removes all the jobs and returns their waiters.
+    pub fn clear(&mut self) -> Vec<W> {
+        self.next_seqno = 0;
+        self.jobs.drain(..).flat_map(|j| j.waiters).collect()
+    }
+}
+
+// An importance request: the objective index and the (sorted) feature set.
+type Request = (usize, Vec<usize>);
+
+// The callbacks of the promise returned by `ImportanceQueue::enqueue`.
+type Waiter = (Function, Function);
+
+#[derive(Debug)]
+struct QueueState {
+    objectives: Vec<Evaluator>,
+    jobs: JobQueue<Request, Waiter>,
+    running: bool,
+}
+
+/// Queue of importance requests over one or more objectives.
+///
+/// Requests are processed one by one in the order of their priorities, yielding to the event
+/// loop periodically. Identical pending requests are computed only once.
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct ImportanceQueue {
+    state: Rc<RefCell<QueueState>>,
+}
+
+#[wasm_bindgen]
+impl ImportanceQueue {
+    /// Makes an empty queue.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        let state = QueueState {
+            objectives: Vec::new(),
+            jobs: JobQueue::new(),
+            running: false,
+        };
+        Self {
+            state: Rc::new(RefCell::new(state)),
+        }
+    }
+
+    /// Adds an objective and returns its index.
+    ///
+    /// Note that `evaluator` is moved into the queue (i.e., it can no longer be used from
+    /// JavaScript).
+    pub fn add_objective(&self, evaluator: Evaluator) -> usize {
+        let mut state = self.state.borrow_mut();
+        state.objectives.push(evaluator);
+        state.objectives.len() - 1
+    }
+
+    /// Requests the importance of `features` for the given objective.
+    ///
+    /// Returns a promise resolving to an object like `{importance, std}`.
+    /// Requests with higher `priority` are processed first.
+    pub fn enqueue(&self, objective: usize, features: Vec<usize>, priority: i32) -> Promise {
+        let request = (objective, normalize(features));
+        let state = self.state.clone();
+        let mut request = Some(request);
+        Promise::new(&mut move |resolve, reject| {
+            let request = request.take().expect("the executor is called only once");
+            let mut s = state.borrow_mut();
+            if request.0 >= s.objectives.len() {
+                let e = Error::new(ErrorCode::InvalidInput, "objective out of range");
+                let _ = reject.call1(&JsValue::NULL, &e.into());
+                return;
+            }
+            s.jobs.push(request, priority, (resolve, reject));
+            if !s.running {
+                s.running = true;
+                schedule(state.clone());
+            }
+        })
+    }
+
+    /// Changes the priority of a pending request and returns `false` if there is no such request.
+    pub fn bump(&self, objective: usize, features: Vec<usize>, priority: i32) -> bool {
+        let request = (objective, normalize(features));
+        self.state
+            .borrow_mut()
+            .jobs
+            .set_priority(&request, priority)
+    }
+
+    /// The number of the pending requests (identical requests are counted once).
+    #[wasm_bindgen(getter)]
+    pub fn pending(&self) -> usize {
+        self.state.borrow().jobs.len()
+    }
+
+    /// Rejects all the pending requests.
+    pub fn clear(&self) {
+        let waiters = self.state.borrow_mut().jobs.clear();
+        for (_, reject) in waiters {
+            let e = Error::new(ErrorCode::Cancelled, "computation has been cancelled");
+            let _ = reject.call1(&JsValue::NULL, &e.into());
+        }
+    }
+}
+
+impl Default for ImportanceQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn normalize(mut features: Vec<usize>) -> Vec<usize> {
+    features.sort_unstable();
+    features.dedup();
+    features
+}
+
+fn schedule(state: Rc<RefCell<QueueState>>) {
+    let next = Closure::once_into_js(move || run(state));
+    set_timeout(&next, 0);
+}
+
+fn run(state: Rc<RefCell<QueueState>>) {
+    let deadline = js_sys::Date::now() + TIME_SLICE_MILLIS;
+    loop {
+        let job = state.borrow_mut().jobs.pop();
+        let Some(((objective, features), waiters)) = job else {
+            state.borrow_mut().running = false;
+            return;
+        };
+
+        let result = {
+            let mut s = state.borrow_mut();
+            compute(&mut s.objectives[objective], &features)
+        };
+        // The callbacks are called after releasing the state since they may enqueue requests.
+        for (resolve, reject) in waiters {
+            let _ = match &result {
+                Ok(value) => resolve.call1(&JsValue::NULL, value),
+                Err(e) => reject.call1(&JsValue::NULL, &e.clone().into()),
+            };
+        }
+
+        if js_sys::Date::now() >= deadline {
+            schedule(state);
+            return;
+        }
+    }
+}
+
+fn compute(evaluator: &mut Evaluator, features: &[usize]) -> Result<JsValue, Error> {
+    let fanova = evaluator.fanova()?;
+    Phase::Importance.enter();
+    let importance = fanova.quantify_importance(features);
+    let value = ImportanceValue {
+        importance: importance.mean,
+        std: importance.stddev,
+    };
+    Ok(to_value(&value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn job_queue_works() {
+        let mut queue = JobQueue::new();
+        assert!(queue.push("a", 0, 1));
+        assert!(queue.push("b", 0, 2));
+        assert!(queue.push("c", 1, 3));
+        assert!(!queue.push("a", -1, 4));
+        assert_eq!(queue.len(), 3);
+
+        assert_eq!(queue.pop(), Some(("c", vec![3])));
+        assert!(queue.set_priority(&"b", 5));
+        assert!(!queue.set_priority(&"c", 5));
+        assert_eq!(queue.pop(), Some(("b", vec![2])));
+        assert_eq!(queue.pop(), Some(("a", vec![1, 4])));
+        assert_eq!(queue.pop(), None);
+
+        queue.push("d", 0, 5);
+        queue.push("d", 0, 6);
+        assert_eq!(queue.clear(), [5, 6]);
+        assert_eq!(queue.len(), 0);
+    }
+}
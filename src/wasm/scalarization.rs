@@ -0,0 +1,250 @@
+//! Scalarizations of the objective values of multi-objective studies.
+use super::{Error, ErrorCode};
+use serde::Deserialize;
+
+/// Scalarization given from JavaScript like `{method: "weighted_sum", weights: [1, 2]}`.
+///
+/// - `method`: `"weighted_sum"`, `"chebyshev"`, `"hypervolume_contribution"` or
+///   `"non_domination_rank"`.
+/// - `directions`: `"minimize"` (default) or `"maximize"` for each objective.
+/// - `weights`: The weights of the objectives for `"weighted_sum"` and `"chebyshev"`
+///   (default: equal weights).
+/// - `reference`: The reference point, which is the ideal point for `"chebyshev"`
+///   (default: the best values) and the worst point for `"hypervolume_contribution"`
+///   (default: the worst values plus 10% of the ranges). It is given in the original
+///   directions of the objectives.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Scalarization {
+    method: Method,
+    #[serde(default)]
+    directions: Option<Vec<Direction>>,
+    #[serde(default)]
+    weights: Option<Vec<f64>>,
+    #[serde(default)]
+    reference: Option<Vec<f64>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Method {
+    WeightedSum,
+    Chebyshev,
+    HypervolumeContribution,
+    NonDominationRank,
+}
+
+/// Optimization direction of an objective.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Direction {
+    #[default]
+    Minimize,
+    Maximize,
+}
+
+impl Scalarization {
+    /// Returns the scalarized value of each trial.
+    ///
+    /// `values[i]` are the objective values of the `i`-th trial.
+    pub fn apply(&self, values: &[Vec<f64>]) -> Result<Vec<f64>, Error> {
+        let objectives = values.first().map_or(0, |v| v.len());
+        let invalid = |message: &str| Error::new(ErrorCode::InvalidInput, message);
+        if objectives == 0 || values.iter().any(|v| v.len() != objectives) {
+            return Err(invalid(
+                "all trials must have the same number of objective values",
+            ));
+        }
+        let lengths = [
+            ("directions", self.directions.as_ref().map(Vec::len)),
+            ("weights", self.weights.as_ref().map(Vec::len)),
+            ("reference", self.reference.as_ref().map(Vec::len)),
+        ];
+        for (name, len) in lengths {
+            if len.is_some_and(|len| len != objectives) {
+                return Err(invalid(&format!(
+                    "the length of {name} must be equal to the number of objectives"
+                )));
+            }
+        }
+
+        // All objectives are converted to be minimized.
+        let sign = |i: usize| match self.directions.as_ref().map(|d| d[i]) {
+            Some(Direction::Maximize) => -1.0,
+            _ => 1.0,
+        };
+        let points = values
+            .iter()
+            .map(|v| v.iter().enumerate().map(|(i, x)| sign(i) * x).collect())
+            .collect::<Vec<Vec<f64>>>();
+        let reference = self
+            .reference
+            .as_ref()
+            .map(|r| r.iter().enumerate().map(|(i, x)| sign(i) * x).collect());
+        let weights = self
+            .weights
+            .clone()
+            .unwrap_or_else(|| vec![1.0 / objectives as f64; objectives]);
+
+        let scalarized = match self.method {
+            Method::WeightedSum => points
+                .iter()
+                .map(|p| p.iter().zip(weights.iter()).map(|(x, w)| x * w).sum())
+                .collect(),
+            Method::Chebyshev => {
+                let ideal = reference.unwrap_or_else(|| bounds(&points, f64::min));
+                points
+                    .iter()
+                    .map(|p| {
+                        p.iter()
+                            .zip(weights.iter())
+                            .zip(ideal.iter())
+                            .map(|((x, w), z)| w * (x - z).abs())
+                            .fold(0.0, f64::max)
+                    })
+                    .collect()
+            }
+            Method::HypervolumeContribution => {
+                let reference = reference.unwrap_or_else(|| {
+                    let (min, max) = (bounds(&points, f64::min), bounds(&points, f64::max));
+                    max.iter()
+                        .zip(min.iter())
+                        .map(|(max, min)| max + (max - min) * 0.1)
+                        .collect()
+                });
+                hypervolume_contributions(&points, &reference)
+            }
+            Method::NonDominationRank => non_domination_ranks(&points)
+                .into_iter()
+                .map(|r| r as f64)
+                .collect(),
+        };
+        Ok(scalarized)
+    }
+}
+
+fn bounds(points: &[Vec<f64>], f: fn(f64, f64) -> f64) -> Vec<f64> {
+    let init = points[0].clone();
+    points.iter().fold(init, |acc, p| {
+        acc.iter().zip(p.iter()).map(|(&a, &x)| f(a, x)).collect()
+    })
+}
+
+fn dominates(a: &[f64], b: &[f64]) -> bool {
+    a.iter().zip(b.iter()).all(|(x, y)| x <= y) && a.iter().zip(b.iter()).any(|(x, y)| x < y)
+}
+
+// Returns the rank of the non-dominated front of each point (starting from `0`).
+fn non_domination_ranks(points: &[Vec<f64>]) -> Vec<usize> {
+    let mut ranks = vec![usize::MAX; points.len()];
+    let mut rank = 0;
+    let mut remaining = (0..points.len()).collect::<Vec<_>>();
+    while !remaining.is_empty() {
+        let (front, rest): (Vec<_>, Vec<_>) = remaining
+            .iter()
+            .partition(|&&i| !remaining.iter().any(|&j| dominates(&points[j], &points[i])));
+        for i in front {
+            ranks[i] = rank;
+        }
+        remaining = rest;
+        rank += 1;
+    }
+    ranks
+}
+
+// Returns the volume exclusively dominated by each point (`0` for dominated points).
+fn hypervolume_contributions(points: &[Vec<f64>], reference: &[f64]) -> Vec<f64> {
+    let ranks = non_domination_ranks(points);
+    let front = (0..points.len())
+        .filter(|&i| ranks[i] == 0)
+        .collect::<Vec<_>>();
+    let front_points = front.iter().map(|&i| points[i].clone()).collect::<Vec<_>>();
+    let total = hypervolume(&front_points, reference);
+
+    let mut contributions = vec![0.0; points.len()];
+    for (k, &i) in front.iter().enumerate() {
+        let mut others = front_points.clone();
+        others.remove(k);
+        contributions[i] = total - hypervolume(&others, reference);
+    }
+    contributions
+}
+
+// Calculates the volume dominated by `points` and bounded by `reference` (by slicing).
+fn hypervolume(points: &[Vec<f64>], reference: &[f64]) -> f64 {
+    let points = points
+        .iter()
+        .filter(|p| p.iter().zip(reference.iter()).all(|(x, r)| x < r))
+        .collect::<Vec<_>>();
+    if points.is_empty() {
+        return 0.0;
+    }
+    let last = reference.len() - 1;
+    if last == 0 {
+        let best = points.iter().map(|p| p[0]).fold(f64::INFINITY, f64::min);
+        return reference[0] - best;
+    }
+
+    let mut points = points;
+    points.sort_by(|a, b| a[last].total_cmp(&b[last]));
+    let mut volume = 0.0;
+    let mut slice = Vec::new();
+    for (k, p) in points.iter().enumerate() {
+        slice.push(p[..last].to_vec());
+        let upper = points.get(k + 1).map_or(reference[last], |q| q[last]);
+        if upper > p[last] {
+            volume += hypervolume(&slice, &reference[..last]) * (upper - p[last]);
+        }
+    }
+    volume
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scalarization(method: Method) -> Scalarization {
+        Scalarization {
+            method,
+            directions: Some(vec![Direction::Minimize, Direction::Maximize]),
+            weights: None,
+            reference: None,
+        }
+    }
+
+    #[test]
+    fn scalarizations_work() -> Result<(), Error> {
+        // In the minimization space: (1, -1), (2, -2), (3, -3) and (2, -1).
+        let values = vec![
+            vec![1.0, 1.0],
+            vec![2.0, 2.0],
+            vec![3.0, 3.0],
+            vec![2.0, 1.0],
+        ];
+
+        let weighted_sum = scalarization(Method::WeightedSum).apply(&values)?;
+        assert_eq!(weighted_sum, [0.0, 0.0, 0.0, 0.5]);
+
+        let chebyshev = scalarization(Method::Chebyshev).apply(&values)?;
+        assert_eq!(chebyshev, [1.0, 0.5, 1.0, 1.0]);
+
+        let ranks = scalarization(Method::NonDominationRank).apply(&values)?;
+        assert_eq!(ranks, [0.0, 0.0, 0.0, 1.0]);
+
+        let mut hv = scalarization(Method::HypervolumeContribution);
+        hv.reference = Some(vec![4.0, 0.0]);
+        assert_eq!(hv.apply(&values)?, [1.0, 1.0, 1.0, 0.0]);
+
+        assert!(scalarization(Method::WeightedSum)
+            .apply(&[vec![1.0, 2.0], vec![1.0]])
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn hypervolume_works() {
+        let points = vec![vec![1.0, 2.0, 3.0], vec![2.0, 1.0, 3.0]];
+        assert_eq!(hypervolume(&points, &[3.0, 3.0, 4.0]), 3.0);
+        assert_eq!(hypervolume(&[], &[1.0]), 0.0);
+    }
+}
@@ -0,0 +1,417 @@
+//! Optuna studies (trials and their search space definitions).
+use super::Error;
+use crate::table::{ColumnType, Table, TableError};
+use crate::FanovaError;
+use crate::Transform;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Search space definition of a parameter (i.e., an Optuna distribution).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(crate) enum Distribution {
+    Float {
+        low: f64,
+        high: f64,
+        log: bool,
+        step: Option<f64>,
+    },
+    Int {
+        low: f64,
+        high: f64,
+        log: bool,
+        step: f64,
+    },
+    Categorical {
+        choices: Vec<Value>,
+    },
+}
+
+impl Distribution {
+    /// Parses an Optuna distribution like `{"name": "FloatDistribution", "attributes": {...}}`.
+    ///
+    /// The JSON string of a distribution (as stored in journals) is also accepted.
+    pub fn parse(value: &Value) -> Option<Self> {
+        if let Some(s) = value.as_str() {
+            return Self::parse(&serde_json::from_str(s).ok()?);
+        }
+        let attrs = &value["attributes"];
+        let number = |key: &str| attrs[key].as_f64();
+        let flag = |key: &str| attrs[key].as_bool().unwrap_or(false);
+        let (low, high) = (number("low"), number("high"));
+        let distribution = match value["name"].as_str()? {
+            "FloatDistribution" => Self::Float {
+                low: low?,
+                high: high?,
+                log: flag("log"),
+                step: number("step"),
+            },
+            "UniformDistribution" => Self::Float {
+                low: low?,
+                high: high?,
+                log: false,
+                step: None,
+            },
+            "LogUniformDistribution" => Self::Float {
+                low: low?,
+                high: high?,
+                log: true,
+                step: None,
+            },
+            "DiscreteUniformDistribution" => Self::Float {
+                low: low?,
+                high: high?,
+                log: false,
+                step: number("q"),
+            },
+            "IntDistribution" | "IntUniformDistribution" | "IntLogUniformDistribution" => {
+                Self::Int {
+                    low: low?,
+                    high: high?,
+                    log: flag("log") || value["name"] == "IntLogUniformDistribution",
+                    step: number("step").unwrap_or(1.0),
+                }
+            }
+            "CategoricalDistribution" => Self::Categorical {
+                choices: attrs["choices"].as_array()?.clone(),
+            },
+            _ => return None,
+        };
+        Some(distribution)
+    }
+
+    /// Encodes the given feature column of `table` which follows this distribution.
+    ///
+    /// - Log-scale parameters are log-transformed.
+    /// - Integer parameters are split only at half-integers (`ColumnType::Integer`).
+    /// - The indices of categorical parameters are `ColumnType::Categorical`.
+    /// - The indices of categorical parameters whose choices are `false` and `true` are boolean
+    ///   (`ColumnType::Boolean`).
+    pub fn encode(&self, table: &mut Table, feature: usize) -> Result<(), TableError> {
+        match self {
+            Self::Float { log: true, .. } | Self::Int { log: true, .. } => {
+                table.transform(feature, Transform::Log)
+            }
+            Self::Categorical { choices }
+                if choices.len() == 2 && choices.iter().all(Value::is_boolean) =>
+            {
+                table.set_column_type(feature, ColumnType::Boolean)
+            }
+            Self::Int { .. } => table.set_column_type(feature, ColumnType::Integer),
+            Self::Categorical { .. } => table.set_column_type(feature, ColumnType::Categorical),
+            Self::Float { .. } => Ok(()),
+        }
+    }
+
+    /// Converts a parameter value into the internal representation of Optuna
+    /// (the index of the choice for categorical distributions).
+    pub fn to_internal(&self, value: &Value) -> Option<f64> {
+        match self {
+            Self::Categorical { choices } => {
+                let i = choices.iter().position(|c| same_choice(c, value))?;
+                Some(i as f64)
+            }
+            _ => value.as_f64(),
+        }
+    }
+}
+
+// Numbers are compared by value (e.g., `1` and `1.0` are the same choice).
+fn same_choice(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// A study having completed trials.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Study {
+    pub name: String,
+    pub trials: Vec<Trial>,
+    pub distributions: BTreeMap<String, Distribution>,
+}
+
+/// A completed or pruned trial.
+///
+/// The parameters are in the internal representation of Optuna
+/// (the indices of the choices for categorical parameters).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Trial {
+    pub params: BTreeMap<String, f64>,
+
+    // Empty for pruned trials.
+    pub values: Vec<f64>,
+
+    // Only the numeric (and boolean) attributes are kept.
+    pub user_attrs: BTreeMap<String, f64>,
+
+    pub intermediate_values: BTreeMap<u64, f64>,
+}
+
+impl Trial {
+    /// Returns the value of `target` (`None` if this trial does not have the value).
+    pub fn target(&self, target: &Target) -> Option<f64> {
+        match target {
+            Target::Objective(i) => self.values.get(*i).copied(),
+            Target::UserAttr(key) => self.user_attrs.get(key).copied(),
+            Target::IntermediateValue(step) => self.intermediate_values.get(step).copied(),
+        }
+    }
+}
+
+/// The value of the trials to be analyzed.
+///
+/// Given from JavaScript as the index of an objective (e.g., `0`) or an object like
+/// `{user_attr: "accuracy"}` or `{intermediate_value: 10}` (the value reported at step `10`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Target {
+    Objective(usize),
+    UserAttr(String),
+    IntermediateValue(u64),
+}
+
+/// Converts an attribute value into a number (`None` for non-numeric values).
+pub(crate) fn attr_value(value: &Value) -> Option<f64> {
+    match value {
+        Value::Bool(b) => Some(f64::from(u8::from(*b))),
+        _ => value.as_f64(),
+    }
+}
+
+impl Study {
+    /// Returns the names of the parameters which appear in the trials (in ascending order).
+    pub fn param_names(&self) -> Vec<String> {
+        let names = self.trials.iter().flat_map(|t| t.params.keys().cloned());
+        names.collect::<BTreeSet<_>>().into_iter().collect()
+    }
+
+    /// Returns the names of the parameters which appear in all the trials (in ascending order).
+    pub fn common_param_names(&self) -> Vec<String> {
+        self.param_names()
+            .into_iter()
+            .filter(|name| self.trials.iter().all(|t| t.params.contains_key(name)))
+            .collect()
+    }
+
+    /// Returns the columns of the given parameters and the column of the `target` values.
+    ///
+    /// Missing parameters (and targets) are filled with NaN.
+    pub fn columns(&self, names: &[String], target: &Target) -> (Vec<Vec<f64>>, Vec<f64>) {
+        let features = names
+            .iter()
+            .map(|name| {
+                self.trials
+                    .iter()
+                    .map(|t| t.params.get(name).copied().unwrap_or(f64::NAN))
+                    .collect()
+            })
+            .collect();
+        let target = self
+            .trials
+            .iter()
+            .map(|t| t.target(target).unwrap_or(f64::NAN))
+            .collect();
+        (features, target)
+    }
+}
+
+/// Parses exported trials (e.g., `[trial.__dict__ ...]` or `{"study_name", "trials"}`).
+///
+/// Each trial is an object having `params` (in the external representation), `distributions`,
+/// `state` and either `values` or `value` (and optionally `user_attrs` and
+/// `intermediate_values`). Only the completed and pruned trials are kept.
+pub(crate) fn parse_trials(json: &str) -> Result<Study, Error> {
+    let invalid =
+        |reason: String| Error::from(FanovaError::Parse(format!("invalid trials: {reason}")));
+    let root: Value = serde_json::from_str(json).map_err(|e| invalid(e.to_string()))?;
+    let (name, trials) = match &root {
+        Value::Array(trials) => ("", trials),
+        Value::Object(study) => {
+            let trials = study.get("trials").and_then(Value::as_array);
+            let name = study.get("study_name").and_then(Value::as_str);
+            (
+                name.unwrap_or(""),
+                trials.ok_or_else(|| invalid("missing trials".into()))?,
+            )
+        }
+        _ => return Err(invalid("neither an array nor an object".into())),
+    };
+
+    let mut study = Study {
+        name: name.to_owned(),
+        trials: Vec::new(),
+        distributions: BTreeMap::new(),
+    };
+    for (i, trial) in trials.iter().enumerate() {
+        let invalid = |reason: &str| invalid(format!("{reason} (trial {i})"));
+        let values = match state(&trial["state"]) {
+            Some(TrialState::Complete) => {
+                let values = match (&trial["values"], &trial["value"]) {
+                    (Value::Array(values), _) => values.iter().map(Value::as_f64).collect(),
+                    (_, value) => value.as_f64().map(|v| vec![v]),
+                };
+                values.ok_or_else(|| invalid("invalid values"))?
+            }
+            Some(TrialState::Pruned) => Vec::new(),
+            None => continue,
+        };
+        let user_attrs = trial["user_attrs"].as_object().into_iter().flatten();
+        let user_attrs = user_attrs
+            .filter_map(|(key, value)| Some((key.clone(), attr_value(value)?)))
+            .collect();
+        let mut intermediate_values = BTreeMap::new();
+        for (step, value) in trial["intermediate_values"]
+            .as_object()
+            .into_iter()
+            .flatten()
+        {
+            let step = step.parse().map_err(|_| invalid("invalid step"))?;
+            let value = value
+                .as_f64()
+                .ok_or_else(|| invalid("invalid intermediate value"))?;
+            intermediate_values.insert(step, value);
+        }
+
+        let mut params = BTreeMap::new();
+        for (name, value) in trial["params"].as_object().into_iter().flatten() {
+            let distribution = match trial["distributions"].get(name) {
+                Some(d) => {
+                    Some(Distribution::parse(d).ok_or_else(|| invalid("invalid distribution"))?)
+                }
+                None => None,
+            };
+            let value = match &distribution {
+                Some(d) => d.to_internal(value),
+                None => value.as_f64(),
+            };
+            params.insert(name.clone(), value.ok_or_else(|| invalid("invalid param"))?);
+            if let Some(d) = distribution {
+                study.distributions.entry(name.clone()).or_insert(d);
+            }
+        }
+        study.trials.push(Trial {
+            params,
+            values,
+            user_attrs,
+            intermediate_values,
+        });
+    }
+    Ok(study)
+}
+
+enum TrialState {
+    Complete,
+    Pruned,
+}
+
+fn state(state: &Value) -> Option<TrialState> {
+    let name = match state {
+        Value::Number(n) => match n.as_u64()? {
+            1 => "COMPLETE",
+            2 => "PRUNED",
+            _ => return None,
+        },
+        Value::String(s) => s.strip_prefix("TrialState.").unwrap_or(s),
+        _ => return None,
+    };
+    match name {
+        "COMPLETE" => Some(TrialState::Complete),
+        "PRUNED" => Some(TrialState::Pruned),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_trials_works() -> Result<(), Error> {
+        let json = r#"{"study_name": "foo", "trials": [
+{"state": "COMPLETE", "values": [1.0], "params": {"lr": 0.01, "opt": "adam"},
+ "distributions": {
+   "lr": {"name": "FloatDistribution", "attributes": {"low": 0.001, "high": 1.0, "log": true, "step": null}},
+   "opt": {"name": "CategoricalDistribution", "attributes": {"choices": ["sgd", "adam"]}}}},
+{"state": "PRUNED", "values": null, "params": {"lr": 0.1}, "distributions": {},
+ "user_attrs": {"acc": 0.5, "memo": "x", "ok": true}, "intermediate_values": {"10": 0.25}},
+{"state": "FAIL", "values": null, "params": {"lr": 0.1}, "distributions": {}},
+{"state": 1, "value": 2.0, "params": {"lr": 0.1, "n": 3},
+ "distributions": {"n": "{\"name\": \"IntDistribution\", \"attributes\": {\"low\": 1, \"high\": 8, \"log\": false, \"step\": 1}}"}}
+]}"#;
+        let study = parse_trials(json)?;
+        assert_eq!(study.name, "foo");
+        assert_eq!(study.trials.len(), 3);
+        assert_eq!(study.trials[0].params["opt"], 1.0);
+        assert_eq!(study.trials[2].values, [2.0]);
+
+        let pruned = &study.trials[1];
+        assert_eq!(pruned.target(&Target::Objective(0)), None);
+        assert_eq!(pruned.target(&Target::UserAttr("acc".into())), Some(0.5));
+        assert_eq!(pruned.target(&Target::UserAttr("memo".into())), None);
+        assert_eq!(pruned.target(&Target::UserAttr("ok".into())), Some(1.0));
+        assert_eq!(pruned.target(&Target::IntermediateValue(10)), Some(0.25));
+        assert_eq!(
+            study.distributions["lr"],
+            Distribution::Float {
+                low: 0.001,
+                high: 1.0,
+                log: true,
+                step: None
+            }
+        );
+        assert!(matches!(
+            study.distributions["n"],
+            Distribution::Int { step, .. } if step == 1.0
+        ));
+
+        let json = r#"[{"state": 1, "value": 1.0, "params": {"opt": "rmsprop"},
+ "distributions": {"opt": {"name": "CategoricalDistribution", "attributes": {"choices": ["sgd"]}}}}]"#;
+        assert!(parse_trials(json).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn encode_works() -> anyhow::Result<()> {
+        let lr = [0.01, 0.1, 1.0];
+        let n = [1.0, 2.0, 3.0];
+        let target = [0.0, 1.0, 2.0];
+        let mut table = Table::new(vec![&lr, &n, &target])?;
+
+        let log_float = Distribution::Float {
+            low: 0.01,
+            high: 1.0,
+            log: true,
+            step: None,
+        };
+        log_float.encode(&mut table, 0)?;
+        assert_eq!(table.column_type(0), ColumnType::Continuous);
+        assert_eq!(table.column(0).collect::<Vec<_>>(), lr.map(f64::ln));
+        assert_eq!(table.inverse_transform(0, 0.0), 1.0);
+
+        let int = Distribution::Int {
+            low: 1.0,
+            high: 3.0,
+            log: false,
+            step: 1.0,
+        };
+        int.encode(&mut table, 1)?;
+        assert_eq!(table.column_type(1), ColumnType::Integer);
+
+        let flags = [0.0, 1.0, 1.0];
+        let mut table = Table::new(vec![&flags, &flags, &target])?;
+        let boolean = Distribution::Categorical {
+            choices: vec![Value::Bool(false), Value::Bool(true)],
+        };
+        boolean.encode(&mut table, 0)?;
+        assert_eq!(table.column_type(0), ColumnType::Boolean);
+        let categorical = Distribution::Categorical {
+            choices: vec![Value::Bool(false), "auto".into()],
+        };
+        categorical.encode(&mut table, 1)?;
+        assert_eq!(table.column_type(1), ColumnType::Categorical);
+        Ok(())
+    }
+}